@@ -1,3 +1,4 @@
+use crate::capabilities::Feature;
 use crate::client::{Client, RequestOptions, CR_HEADER_PREFIX};
 use crate::error::ApiResult;
 use crate::models::common::ListAllRes;
@@ -345,13 +346,11 @@ impl ExplorerApi for Client {
             .await?;
 
         if response.obfuscated {
-            // Decode the obfuscated URL
-            let time_now_sec = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .map_err(|e| crate::error::ApiError::Other(format!("System time error: {}", e)))?
-                .as_secs() as i64;
+            // Decode the obfuscated URL. The key is the server's time in
+            // seconds, so use the server's clock rather than the local one.
+            let time_now_ms = self.server_now().await.timestamp_millis();
 
-            response.url = decode_time_flow_string(&response.url, time_now_sec)?;
+            response.url = decode_time_flow_string(&response.url, time_now_ms)?;
         }
 
         Ok(response)
@@ -370,6 +369,9 @@ impl ExplorerApi for Client {
             query_params.push(format!("extended={}", extended));
         }
         if let Some(folder_summary) = params.folder_summary {
+            if folder_summary {
+                self.require(Feature::FolderSummary).await?;
+            }
             query_params.push(format!("folder_summary={}", folder_summary));
         }
 
@@ -510,12 +512,13 @@ impl ExplorerApi for Client {
         let token = self.get_access_token().await?;
 
         let response = self
-            .http_client
-            .put(&url)
-            .header("Authorization", format!("Bearer {}", token))
-            .header("Content-Type", "application/octet-stream")
-            .body(data)
-            .send()
+            .execute(
+                self.http_client
+                    .put(&url)
+                    .header("Authorization", format!("Bearer {}", token))
+                    .header("Content-Type", "application/octet-stream")
+                    .body(data),
+            )
             .await?;
 
         let api_response: crate::error::ApiResponse<FileResponse> = response.json().await?;
@@ -576,17 +579,21 @@ impl ExplorerApi for Client {
         chunk_index: usize,
         data: Bytes,
     ) -> ApiResult<()> {
-        let url = self.build_url(&format!("/file/upload/{}/{}", session_id, chunk_index));
+        let (url, via_lan) = self
+            .build_transfer_url(&format!("/file/upload/{}/{}", session_id, chunk_index))
+            .await;
         let token = self.get_access_token().await?;
 
         let response = self
-            .http_client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", token))
-            .header("Content-Type", "application/octet-stream")
-            .body(data)
-            .send()
-            .await?;
+            .execute(
+                self.http_client
+                    .post(&url)
+                    .header("Authorization", format!("Bearer {}", token))
+                    .header("Content-Type", "application/octet-stream")
+                    .body(data),
+            )
+            .await;
+        let response = self.fall_back_from_lan(response, via_lan).await?;
 
         let api_response: crate::error::ApiResponse<UploadCredential> = response.json().await?;
 
@@ -604,18 +611,22 @@ impl ExplorerApi for Client {
         content_length: u64,
         body: Body,
     ) -> ApiResult<()> {
-        let url = self.build_url(&format!("/file/upload/{}/{}", session_id, chunk_index));
+        let (url, via_lan) = self
+            .build_transfer_url(&format!("/file/upload/{}/{}", session_id, chunk_index))
+            .await;
         let token = self.get_access_token().await?;
 
         let response = self
-            .http_client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", token))
-            .header("Content-Type", "application/octet-stream")
-            .header("Content-Length", content_length)
-            .body(body)
-            .send()
-            .await?;
+            .execute(
+                self.http_client
+                    .post(&url)
+                    .header("Authorization", format!("Bearer {}", token))
+                    .header("Content-Type", "application/octet-stream")
+                    .header("Content-Length", content_length)
+                    .body(body),
+            )
+            .await;
+        let response = self.fall_back_from_lan(response, via_lan).await?;
 
         let api_response: crate::error::ApiResponse<()> = response.json().await?;
 
@@ -745,6 +756,12 @@ impl FileEventSubscription {
             Some("subscribed") => Ok(Some(FileEvent::Subscribed)),
             Some("keep-alive") | Some("keepalive") => Ok(Some(FileEvent::KeepAlive)),
             Some("reconnect-required") => Ok(Some(FileEvent::ReconnectRequired)),
+            Some("wipe") => {
+                let request = data
+                    .and_then(|data_str| serde_json::from_str(data_str).ok())
+                    .unwrap_or_default();
+                Ok(Some(FileEvent::WipeRequested(request)))
+            }
             Some("event") => {
                 if let Some(data_str) = data {
                     // Skip nil data
@@ -802,8 +819,10 @@ pub trait FileEventsApi {
     ///
     ///     while let Some(event) = subscription.next_event().await? {
     ///         match event {
-    ///             cloudreve_api::models::explorer::FileEvent::Event(data) => {
-    ///                 println!("File event: {:?} on {}", data.event_type, data.from);
+    ///             cloudreve_api::models::explorer::FileEvent::Event(events) => {
+    ///                 for data in events {
+    ///                     println!("File event: {:?} on {}", data.event_type, data.from);
+    ///                 }
     ///             }
     ///             _ => {}
     ///         }
@@ -818,20 +837,22 @@ pub trait FileEventsApi {
 #[async_trait]
 impl FileEventsApi for Client {
     async fn subscribe_file_events(&self, uri: &str) -> ApiResult<FileEventSubscription> {
+        self.require(Feature::FileEvents).await?;
         let query = format!("?uri={}", urlencoding::encode(uri));
         let url = self.build_url(&format!("/file/events{}", query));
         let token = self.get_access_token().await?;
 
         let response = self
-            .http_client
-            .get(&url)
-            .header(
-                format!("{}Client-Id", CR_HEADER_PREFIX),
-                self.config.client_id.clone(),
+            .execute(
+                self.http_client
+                    .get(&url)
+                    .header(
+                        format!("{}Client-Id", CR_HEADER_PREFIX),
+                        self.config.client_id.clone(),
+                    )
+                    .header("Authorization", format!("Bearer {}", token))
+                    .header("Accept", "text/event-stream"),
             )
-            .header("Authorization", format!("Bearer {}", token))
-            .header("Accept", "text/event-stream")
-            .send()
             .await?;
 
         // Check if we got an SSE response by looking at content-type
@@ -847,7 +868,20 @@ impl FileEventsApi for Client {
         } else {
             // Server returned a regular response (likely an error)
             // Try to parse it as an API error response
+            let status = response.status();
+            let headers = response.headers().clone();
             let response_text = response.text().await?;
+            if let Some(error) =
+                crate::error::ApiError::maintenance_from(status, &headers, &response_text)
+            {
+                return Err(self.maintenance_error(error).await);
+            }
+
+            if let Ok(raw_value) = serde_json::from_str::<serde_json::Value>(&response_text) {
+                if let Some(error) = self.wipe_error(&raw_value).await {
+                    return Err(error);
+                }
+            }
 
             // Try to parse as API response
             if let Ok(api_response) =
@@ -2,10 +2,14 @@ pub mod user;
 pub mod explorer;
 pub mod workflow;
 pub mod site;
+pub mod share;
+pub mod trash;
 
 // Re-export for convenience
 pub use user::UserApi;
 pub use explorer::ExplorerApi;
 pub use workflow::WorkflowApi;
 pub use site::SiteApi;
+pub use share::ShareApi;
+pub use trash::TrashApi;
 
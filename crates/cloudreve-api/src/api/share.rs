@@ -0,0 +1,82 @@
+use crate::client::{Client, RequestOptions};
+use crate::error::ApiResult;
+use crate::models::explorer::Share;
+use crate::models::share::*;
+use async_trait::async_trait;
+
+const SHARE_PAGE_SIZE: i32 = 100;
+
+/// Share API methods
+#[async_trait]
+pub trait ShareApi {
+    /// Create a share link for a file or folder, returns the link
+    async fn create_share(&self, request: &CreateShareService) -> ApiResult<String>;
+
+    /// Get a share as seen by anyone with the link, without signing in
+    async fn get_share_info(&self, share_id: &str, password: Option<&str>) -> ApiResult<Share>;
+
+    /// List one page of the shares created by the signed in user
+    async fn list_shares(&self, params: &ListShareService) -> ApiResult<ListShareResponse>;
+
+    /// List the shares created by the signed in user, at most `limit`
+    async fn list_all_shares(&self, limit: usize) -> ApiResult<Vec<Share>>;
+}
+
+#[async_trait]
+impl ShareApi for Client {
+    async fn create_share(&self, request: &CreateShareService) -> ApiResult<String> {
+        self.put("/share", request, RequestOptions::new()).await
+    }
+
+    async fn get_share_info(&self, share_id: &str, password: Option<&str>) -> ApiResult<Share> {
+        let mut path = format!("/share/info/{}", urlencoding::encode(share_id));
+        if let Some(password) = password {
+            path.push_str(&format!("?password={}", urlencoding::encode(password)));
+        }
+        self.get(&path, RequestOptions::new().no_credential()).await
+    }
+
+    async fn list_shares(&self, params: &ListShareService) -> ApiResult<ListShareResponse> {
+        let mut query_params = vec![format!("page_size={}", params.page_size)];
+        if let Some(order_by) = &params.order_by {
+            query_params.push(format!("order_by={}", order_by));
+        }
+        if let Some(order_direction) = &params.order_direction {
+            query_params.push(format!("order_direction={}", order_direction));
+        }
+        if let Some(next_page_token) = &params.next_page_token {
+            query_params.push(format!(
+                "next_page_token={}",
+                urlencoding::encode(next_page_token)
+            ));
+        }
+
+        self.get(
+            &format!("/share?{}", query_params.join("&")),
+            RequestOptions::new(),
+        )
+        .await
+    }
+
+    async fn list_all_shares(&self, limit: usize) -> ApiResult<Vec<Share>> {
+        let mut shares = Vec::new();
+        let mut next_page_token = None;
+        loop {
+            let page = self
+                .list_shares(&ListShareService {
+                    page_size: SHARE_PAGE_SIZE,
+                    order_by: None,
+                    order_direction: None,
+                    next_page_token,
+                })
+                .await?;
+            shares.extend(page.shares);
+            next_page_token = page.pagination.next_token.filter(|t| !t.is_empty());
+            if next_page_token.is_none() || shares.len() >= limit {
+                break;
+            }
+        }
+        shares.truncate(limit);
+        Ok(shares)
+    }
+}
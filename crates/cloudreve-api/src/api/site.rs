@@ -1,5 +1,6 @@
+use crate::capabilities::Capabilities;
 use crate::client::{Client, RequestOptions};
-use crate::error::ApiResult;
+use crate::error::{ApiError, ApiResult};
 use crate::models::site::*;
 use async_trait::async_trait;
 
@@ -14,6 +15,12 @@ pub trait SiteApi {
     
     /// Create abuse report
     async fn create_abuse_report(&self, request: &CreateAbuseReportService) -> ApiResult<()>;
+
+    /// Get the server version string
+    async fn ping(&self) -> ApiResult<String>;
+
+    /// Query the server version and remember its capabilities on the client
+    async fn probe_capabilities(&self) -> ApiResult<Capabilities>;
 }
 
 #[async_trait]
@@ -36,5 +43,17 @@ impl SiteApi for Client {
             RequestOptions::new().no_credential(),
         ).await
     }
+
+    async fn ping(&self) -> ApiResult<String> {
+        self.get("/site/ping", RequestOptions::new().no_credential()).await
+    }
+
+    async fn probe_capabilities(&self) -> ApiResult<Capabilities> {
+        let version = self.ping().await?;
+        let capabilities = Capabilities::from_version_string(&version)
+            .ok_or_else(|| ApiError::Other(format!("unrecognized server version: {}", version)))?;
+        self.set_capabilities(Some(capabilities.clone())).await;
+        Ok(capabilities)
+    }
 }
 
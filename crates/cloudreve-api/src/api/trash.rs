@@ -0,0 +1,75 @@
+use crate::api::explorer::{ExplorerApi, ExplorerApiExt};
+use crate::client::Client;
+use crate::error::{ApiError, ApiResult};
+use crate::models::explorer::{DeleteFileService, TrashEntry};
+use crate::models::uri::{filesystem, CrUri, SearchParam, CR_URI_PREFIX};
+use async_trait::async_trait;
+
+const TRASH_PAGE_SIZE: i32 = 500;
+
+/// Trash API methods
+#[async_trait]
+pub trait TrashApi {
+    /// List entries of the trash that `keep` accepts, only those whose name
+    /// contains `keyword` if given. Stops after `limit` accepted entries.
+    async fn search_trash(
+        &self,
+        keyword: Option<&str>,
+        limit: usize,
+        keep: &(dyn for<'a> Fn(&'a TrashEntry) -> bool + Send + Sync),
+    ) -> ApiResult<Vec<TrashEntry>>;
+
+    /// Restore entries of the trash to where they were deleted from
+    async fn restore_from_trash(&self, uris: &[String]) -> ApiResult<()>;
+}
+
+#[async_trait]
+impl TrashApi for Client {
+    async fn search_trash(
+        &self,
+        keyword: Option<&str>,
+        limit: usize,
+        keep: &(dyn for<'a> Fn(&'a TrashEntry) -> bool + Send + Sync),
+    ) -> ApiResult<Vec<TrashEntry>> {
+        let mut uri = CrUri::new(&format!("{}{}", CR_URI_PREFIX, filesystem::TRASH))
+            .map_err(|e| ApiError::Other(e.to_string()))?;
+        if let Some(keyword) = keyword.filter(|k| !k.is_empty()) {
+            uri.set_search_param(SearchParam {
+                name: Some(vec![keyword.to_string()]),
+                ..Default::default()
+            });
+        }
+        let uri = uri.to_string();
+
+        let mut entries = Vec::new();
+        let mut previous = None;
+        loop {
+            let page = self
+                .list_files_all(previous.as_ref(), &uri, TRASH_PAGE_SIZE)
+                .await?;
+            entries.extend(
+                page.res
+                    .files
+                    .iter()
+                    .cloned()
+                    .filter_map(TrashEntry::from_file)
+                    .filter(|entry| keep(entry)),
+            );
+            if !page.more || entries.len() >= limit {
+                break;
+            }
+            previous = Some(page);
+        }
+        entries.truncate(limit);
+        Ok(entries)
+    }
+
+    async fn restore_from_trash(&self, uris: &[String]) -> ApiResult<()> {
+        self.restore_files(&DeleteFileService {
+            uris: uris.to_vec(),
+            unlink: None,
+            skip_soft_delete: None,
+        })
+        .await
+    }
+}
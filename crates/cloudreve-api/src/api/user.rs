@@ -39,6 +39,10 @@ pub trait UserApi {
 
     /// Get user storage policies
     async fn get_user_storage_policies(&self) -> ApiResult<Vec<StoragePolicy>>;
+
+    /// Report a wipe the server asked for as done. Sent without credentials,
+    /// as the session is revoked by then.
+    async fn report_wipe_completed(&self, request: &WipeCompletedService) -> ApiResult<()>;
 }
 
 #[async_trait]
@@ -138,5 +142,13 @@ impl UserApi for Client {
             RequestOptions::new().no_credential(),
         ).await
     }
+
+    async fn report_wipe_completed(&self, request: &WipeCompletedService) -> ApiResult<()> {
+        self.post(
+            "/session/wipe",
+            request,
+            RequestOptions::new().no_credential(),
+        ).await
+    }
 }
 
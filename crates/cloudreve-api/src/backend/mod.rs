@@ -0,0 +1,80 @@
+//! Versioned backend abstraction over the Cloudreve HTTP APIs.
+//!
+//! Cloudreve V4 is served by [`Client`] and supports the full feature set.
+//! Legacy Cloudreve V3 instances are served by `v3::V3Client`, which covers
+//! login, listing, upload and download only, when the `v3` feature is
+//! enabled. [`connect`] probes the server with
+//! [`detect_api_version`] and picks the matching implementation.
+
+#[cfg(feature = "v3")]
+pub mod v3;
+mod v4;
+
+use crate::capabilities::Feature;
+use crate::client::{Client, ClientConfig};
+use crate::error::ApiResult;
+use async_trait::async_trait;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+pub use crate::capabilities::{detect_api_version, ApiVersion};
+#[cfg(feature = "v3")]
+pub use v3::V3Client;
+
+/// A file or folder in the user's own storage, independent of API version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteEntry {
+    /// Server-side ID of the file
+    pub id: String,
+    pub name: String,
+    /// Absolute path from the user's root, e.g. `/docs/a.txt`
+    pub path: String,
+    pub is_folder: bool,
+    pub size: i64,
+    pub updated_at: String,
+}
+
+/// Operations every supported server version provides
+#[async_trait]
+pub trait Backend: Send + Sync {
+    /// API version this backend speaks
+    fn version(&self) -> ApiVersion;
+
+    /// Whether the backend supports an optional client feature
+    async fn supports(&self, feature: Feature) -> bool;
+
+    /// Log in with email and password, keeping the session for later calls
+    async fn login(&self, email: &str, password: &str) -> ApiResult<()>;
+
+    /// List the direct children of the folder at `path`
+    async fn list(&self, path: &str) -> ApiResult<Vec<RemoteEntry>>;
+
+    /// Get a URL the file content can be downloaded from
+    async fn download_url(&self, entry: &RemoteEntry) -> ApiResult<String>;
+
+    /// Upload `data` as a new file named `name` into the folder at `parent`
+    async fn upload(&self, parent: &str, name: &str, data: Bytes) -> ApiResult<()>;
+}
+
+/// Join a folder path and a child name into an absolute path
+pub(crate) fn join_path(parent: &str, name: &str) -> String {
+    format!("{}/{}", parent.trim_end_matches('/'), name)
+}
+
+/// Connect to a Cloudreve server, picking the backend that matches its version.
+pub async fn connect(config: ClientConfig) -> ApiResult<Box<dyn Backend>> {
+    let (version, capabilities) = detect_api_version(&config).await?;
+    match version {
+        ApiVersion::V4 => {
+            let client = Client::new(config);
+            client.set_capabilities(Some(capabilities)).await;
+            Ok(Box::new(client))
+        }
+        #[cfg(feature = "v3")]
+        ApiVersion::V3 => Ok(Box::new(V3Client::new(config))),
+        #[cfg(not(feature = "v3"))]
+        ApiVersion::V3 => Err(crate::error::ApiError::Other(
+            "Cloudreve V3 servers need the v3 feature".to_string(),
+        )),
+    }
+}
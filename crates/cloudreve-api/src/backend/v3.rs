@@ -0,0 +1,287 @@
+//! Cloudreve V3 (legacy) API client.
+//!
+//! V3 authenticates with a session cookie instead of tokens and only exposes a
+//! subset of what the sync client needs: no file events, no folder summaries,
+//! and uploads only through storage policies that relay via the server.
+
+use super::{join_path, ApiVersion, Backend, RemoteEntry};
+use crate::capabilities::Feature;
+use crate::client::ClientConfig;
+use crate::error::{ApiError, ApiResponse, ApiResult};
+use crate::trace;
+use async_trait::async_trait;
+use bytes::Bytes;
+use reqwest::{Client as HttpClient, Method};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize)]
+struct LoginRequest<'a> {
+    #[serde(rename = "userName")]
+    user_name: &'a str,
+    #[serde(rename = "Password")]
+    password: &'a str,
+    #[serde(rename = "captchaCode")]
+    captcha_code: &'a str,
+}
+
+/// Object in a V3 directory listing
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct V3Object {
+    pub id: String,
+    pub name: String,
+    /// Path of the containing folder
+    pub path: String,
+    pub size: i64,
+    /// "file" or "dir"
+    #[serde(rename = "type")]
+    pub object_type: String,
+    pub date: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct V3Policy {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub policy_type: String,
+    #[serde(default)]
+    pub max_size: i64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct V3ListResponse {
+    pub parent: String,
+    pub objects: Vec<V3Object>,
+    pub policy: Option<V3Policy>,
+}
+
+#[derive(Debug, Serialize)]
+struct UploadSessionRequest<'a> {
+    path: &'a str,
+    size: u64,
+    name: &'a str,
+    policy_id: &'a str,
+    last_modified: i64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct UploadSession {
+    #[serde(rename = "sessionID")]
+    session_id: String,
+    #[serde(rename = "chunkSize")]
+    chunk_size: u64,
+    #[serde(rename = "uploadURLs", default)]
+    upload_urls: Option<Vec<String>>,
+}
+
+/// Client for Cloudreve V3 servers
+pub struct V3Client {
+    config: ClientConfig,
+    http_client: HttpClient,
+}
+
+impl V3Client {
+    pub fn new(config: ClientConfig) -> Self {
+        let http_client = config
+            .http_client_builder()
+            .cookie_store(true)
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            http_client,
+            config,
+        }
+    }
+
+    fn build_url(&self, path: &str) -> String {
+        format!(
+            "{}{}{}",
+            self.config.base_url,
+            ApiVersion::V3.prefix(),
+            path
+        )
+    }
+
+    /// Encode an absolute path segment by segment for use in a URL
+    fn encode_path(path: &str) -> String {
+        path.split('/')
+            .filter(|s| !s.is_empty())
+            .map(|s| format!("/{}", urlencoding::encode(s)))
+            .collect()
+    }
+
+    async fn send<T, R>(&self, method: Method, path: &str, body: Option<&T>) -> ApiResult<R>
+    where
+        T: Serialize + ?Sized,
+        R: DeserializeOwned + Default,
+    {
+        let mut request = self.http_client.request(method, self.build_url(path));
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+
+        let response: ApiResponse<R> = trace::execute(&self.http_client, request)
+            .await?
+            .json()
+            .await?;
+        if response.code != 0 {
+            return Err(ApiError::from_response(response));
+        }
+        Ok(response.data.unwrap_or_default())
+    }
+
+    /// List a directory, including the storage policy that applies to it
+    pub async fn list_directory(&self, path: &str) -> ApiResult<V3ListResponse> {
+        self.send::<(), _>(
+            Method::GET,
+            &format!("/directory{}", Self::encode_path(path)),
+            None,
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl Backend for V3Client {
+    fn version(&self) -> ApiVersion {
+        ApiVersion::V3
+    }
+
+    async fn supports(&self, _feature: Feature) -> bool {
+        false
+    }
+
+    async fn login(&self, email: &str, password: &str) -> ApiResult<()> {
+        // The session cookie is kept by the HTTP client
+        let _: serde_json::Value = self
+            .send(
+                Method::POST,
+                "/user/session",
+                Some(&LoginRequest {
+                    user_name: email,
+                    password,
+                    captcha_code: "",
+                }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn list(&self, path: &str) -> ApiResult<Vec<RemoteEntry>> {
+        let listing = self.list_directory(path).await?;
+        Ok(listing
+            .objects
+            .into_iter()
+            .map(|object| RemoteEntry {
+                path: join_path(&object.path, &object.name),
+                is_folder: object.object_type == "dir",
+                id: object.id,
+                name: object.name,
+                size: object.size,
+                updated_at: object.date,
+            })
+            .collect())
+    }
+
+    async fn download_url(&self, entry: &RemoteEntry) -> ApiResult<String> {
+        let url: String = self
+            .send::<(), _>(Method::PUT, &format!("/file/download/{}", entry.id), None)
+            .await?;
+        // Local policies return a path relative to the site
+        if url.starts_with('/') {
+            Ok(format!("{}{}", self.config.base_url, url))
+        } else {
+            Ok(url)
+        }
+    }
+
+    async fn upload(&self, parent: &str, name: &str, data: Bytes) -> ApiResult<()> {
+        let policy = self
+            .list_directory(parent)
+            .await?
+            .policy
+            .ok_or_else(|| ApiError::Other("no storage policy for folder".to_string()))?;
+        if policy.policy_type != "local" {
+            return Err(ApiError::Other(format!(
+                "uploads to {} storage policies are not supported on Cloudreve V3",
+                policy.policy_type
+            )));
+        }
+
+        let session: UploadSession = self
+            .send(
+                Method::PUT,
+                "/file/upload",
+                Some(&UploadSessionRequest {
+                    path: if parent.is_empty() { "/" } else { parent },
+                    size: data.len() as u64,
+                    name,
+                    policy_id: &policy.id,
+                    last_modified: chrono::Utc::now().timestamp_millis(),
+                }),
+            )
+            .await?;
+        if session.upload_urls.is_some_and(|urls| !urls.is_empty()) {
+            return Err(ApiError::Other(
+                "server requested a direct-to-storage upload".to_string(),
+            ));
+        }
+
+        let chunk_size = if session.chunk_size > 0 {
+            session.chunk_size as usize
+        } else {
+            data.len().max(1)
+        };
+        for (index, start) in (0..data.len().max(1)).step_by(chunk_size).enumerate() {
+            let end = (start + chunk_size).min(data.len());
+            let request = self
+                .http_client
+                .post(self.build_url(&format!("/file/upload/{}/{}", session.session_id, index)))
+                .header("Content-Type", "application/octet-stream")
+                .body(data.slice(start..end));
+            let response: ApiResponse<serde_json::Value> =
+                trace::execute(&self.http_client, request)
+                    .await?
+                    .json()
+                    .await?;
+            if response.code != 0 {
+                return Err(ApiError::from_response(response));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_are_encoded_per_segment() {
+        assert_eq!(V3Client::encode_path("/"), "");
+        assert_eq!(V3Client::encode_path("/My Docs/a&b"), "/My%20Docs/a%26b");
+    }
+
+    #[test]
+    fn listings_carry_their_policy() {
+        let listing: V3ListResponse = serde_json::from_str(
+            r#"{
+                "parent": "3x9",
+                "objects": [
+                    {"id": "a1", "name": "docs", "path": "/", "size": 0, "type": "dir", "date": "2024-01-01T00:00:00Z"},
+                    {"id": "b2", "name": "a.txt", "path": "/docs", "size": 12, "type": "file", "date": "2024-01-02T00:00:00Z"}
+                ],
+                "policy": {"id": "p1", "name": "Default", "type": "local", "max_size": 0, "file_type": []}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(listing.objects.len(), 2);
+        assert_eq!(listing.objects[0].object_type, "dir");
+        assert_eq!(
+            join_path(&listing.objects[1].path, &listing.objects[1].name),
+            "/docs/a.txt"
+        );
+        assert_eq!(listing.policy.unwrap().policy_type, "local");
+    }
+}
@@ -0,0 +1,123 @@
+//! [`Backend`] implementation for Cloudreve V4, delegating to [`Client`].
+
+use super::{join_path, ApiVersion, Backend, RemoteEntry};
+use crate::api::explorer::{ExplorerApi, ExplorerApiExt};
+use crate::api::user::UserApi;
+use crate::capabilities::Feature;
+use crate::client::Client;
+use crate::error::{ApiError, ApiResult};
+use crate::models::common::ListAllRes;
+use crate::models::explorer::{
+    file_type, FileURLService, ListResponse, PolicyType, UploadSessionRequest,
+};
+use crate::models::uri::{new_my_uri, CrUri};
+use async_trait::async_trait;
+use bytes::Bytes;
+
+const LIST_PAGE_SIZE: i32 = 1000;
+
+fn my_uri(path: &str) -> ApiResult<CrUri> {
+    let mut uri = new_my_uri(None).map_err(|e| ApiError::Other(e.to_string()))?;
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    uri.join(&segments);
+    Ok(uri)
+}
+
+#[async_trait]
+impl Backend for Client {
+    fn version(&self) -> ApiVersion {
+        ApiVersion::V4
+    }
+
+    async fn supports(&self, feature: Feature) -> bool {
+        self.require(feature).await.is_ok()
+    }
+
+    async fn login(&self, email: &str, password: &str) -> ApiResult<()> {
+        let response = UserApi::login(self, email, password).await?;
+        self.set_tokens_with_expiry(&response.token).await
+    }
+
+    async fn list(&self, path: &str) -> ApiResult<Vec<RemoteEntry>> {
+        let uri = my_uri(path)?.to_string();
+        let mut entries = Vec::new();
+        let mut previous: Option<ListAllRes<ListResponse>> = None;
+        loop {
+            let response = self
+                .list_files_all(previous.as_ref(), &uri, LIST_PAGE_SIZE)
+                .await?;
+            entries.extend(response.res.files.iter().map(|file| RemoteEntry {
+                id: file.id.clone(),
+                name: file.name.clone(),
+                path: join_path(path, &file.name),
+                is_folder: file.file_type == file_type::FOLDER,
+                size: file.size,
+                updated_at: file.updated_at.clone(),
+            }));
+            if !response.more {
+                return Ok(entries);
+            }
+            previous = Some(response);
+        }
+    }
+
+    async fn download_url(&self, entry: &RemoteEntry) -> ApiResult<String> {
+        let response = self
+            .get_file_url(&FileURLService {
+                uris: vec![my_uri(&entry.path)?.to_string()],
+                download: Some(true),
+                ..Default::default()
+            })
+            .await?;
+        response
+            .urls
+            .into_iter()
+            .next()
+            .map(|url| url.url)
+            .ok_or_else(|| ApiError::Other("server returned no download url".to_string()))
+    }
+
+    async fn upload(&self, parent: &str, name: &str, data: Bytes) -> ApiResult<()> {
+        let parent_uri = my_uri(parent)?.to_string();
+        let listing = self.list_files_all(None, &parent_uri, 1).await?;
+        let policy = listing
+            .res
+            .storage_policy
+            .ok_or_else(|| ApiError::Other("no storage policy for folder".to_string()))?;
+
+        // Direct-to-storage uploads need provider specific handling
+        let relayed =
+            matches!(policy.policy_type, PolicyType::Local) || policy.relay.unwrap_or(false);
+        if !relayed {
+            return Err(ApiError::Other(
+                "direct-to-storage uploads are not supported by this backend".to_string(),
+            ));
+        }
+
+        let session = self
+            .create_upload_session(&UploadSessionRequest {
+                uri: my_uri(&join_path(parent, name))?.to_string(),
+                size: data.len() as i64,
+                policy_id: policy.id.clone(),
+                last_modified: None,
+                entity_type: None,
+                metadata: None,
+                mime_type: None,
+                encryption_supported: None,
+                previous: None,
+            })
+            .await?;
+
+        let chunk_size = if session.chunk_size > 0 {
+            session.chunk_size as usize
+        } else {
+            data.len().max(1)
+        };
+        for (index, start) in (0..data.len().max(1)).step_by(chunk_size).enumerate() {
+            let end = (start + chunk_size).min(data.len());
+            self.upload_chunk(&session.session_id, index, data.slice(start..end))
+                .await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+//! Blocking wrapper around [`Client`], for scripts and tools without an
+//! async runtime of their own.
+//!
+//! Enabled by the `blocking` feature. Calls run to completion on a runtime
+//! owned by the wrapper, so they must not be made from within another tokio
+//! runtime.
+//!
+//! ```no_run
+//! use cloudreve_api::api::UserApi;
+//! use cloudreve_api::blocking::BlockingClient;
+//! use cloudreve_api::ClientConfig;
+//!
+//! let client = BlockingClient::new(ClientConfig::new("https://your-cloudreve-instance.com"))?;
+//! let login = client.call(|client| client.login("user@example.com", "password"))?;
+//! client.block_on(client.inner().set_tokens(login.token.access_token, login.token.refresh_token));
+//! let user = client.call(|client| client.get_user_me())?;
+//! println!("Hello, {}!", user.nickname);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::client::{Client, ClientConfig};
+use crate::error::{ApiError, ApiResult};
+use std::future::Future;
+use tokio::runtime::{Builder, Runtime};
+
+/// A [`Client`] with its own single-threaded runtime
+pub struct BlockingClient {
+    runtime: Runtime,
+    client: Client,
+}
+
+impl BlockingClient {
+    /// Create a client for the server in `config`
+    pub fn new(config: ClientConfig) -> ApiResult<Self> {
+        Self::from_client(Client::new(config))
+    }
+
+    /// Wrap a client configured beforehand, e.g. with callbacks set
+    pub fn from_client(client: Client) -> ApiResult<Self> {
+        Ok(Self {
+            runtime: Self::runtime()?,
+            client,
+        })
+    }
+
+    fn runtime() -> ApiResult<Runtime> {
+        Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| ApiError::Other(format!("failed to create runtime: {}", e)))
+    }
+
+    /// The wrapped client
+    pub fn inner(&self) -> &Client {
+        &self.client
+    }
+
+    /// Run an API call on the client and wait for its result
+    pub fn call<'a, F, T>(&'a self, call: impl FnOnce(&'a Client) -> F) -> T
+    where
+        F: Future<Output = T> + 'a,
+    {
+        self.runtime.block_on(call(&self.client))
+    }
+
+    /// Wait for any future, e.g. one of the client's own methods
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+}
@@ -1,4 +1,7 @@
 use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::marker::PhantomData;
 
 /// A compact boolean set stored as a bit array
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -118,3 +121,106 @@ impl Default for Boolset {
         Self::new()
     }
 }
+
+/// A named bit of a permission [`Boolset`]
+pub trait Permission: Copy {
+    /// Index of the bit in the set
+    fn index(self) -> usize;
+}
+
+/// A [`Boolset`] whose bits are named by the permission type `P`.
+///
+/// Serialized as the base64 string the server uses.
+pub struct Permissions<P> {
+    set: Boolset,
+    _permission: PhantomData<fn() -> P>,
+}
+
+impl<P: Permission> Permissions<P> {
+    /// Create a set with no permission granted
+    pub fn new() -> Self {
+        Self::from(Boolset::new())
+    }
+
+    /// Parse a base64-encoded set
+    pub fn from_base64(encoded: &str) -> Result<Self, base64::DecodeError> {
+        Boolset::from_base64(encoded).map(Self::from)
+    }
+
+    /// Check whether a permission is granted
+    pub fn has(&self, permission: P) -> bool {
+        self.set.enabled(permission.index())
+    }
+
+    /// Grant a permission
+    pub fn grant(&mut self, permission: P) -> &mut Self {
+        self.set.set(permission.index(), true);
+        self
+    }
+
+    /// Revoke a permission
+    pub fn revoke(&mut self, permission: P) -> &mut Self {
+        self.set.set(permission.index(), false);
+        self
+    }
+
+    /// Convert to base64-encoded string
+    pub fn to_base64(&self) -> String {
+        self.set.to_base64()
+    }
+
+    /// Get the underlying bit set
+    pub fn as_boolset(&self) -> &Boolset {
+        &self.set
+    }
+}
+
+impl<P> From<Boolset> for Permissions<P> {
+    fn from(set: Boolset) -> Self {
+        Self {
+            set,
+            _permission: PhantomData,
+        }
+    }
+}
+
+impl<P: Permission> Default for Permissions<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P> Clone for Permissions<P> {
+    fn clone(&self) -> Self {
+        Self::from(self.set.clone())
+    }
+}
+
+impl<P> PartialEq for Permissions<P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.set == other.set
+    }
+}
+
+impl<P> Eq for Permissions<P> {}
+
+impl<P> fmt::Debug for Permissions<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Permissions")
+            .field(&self.set.to_base64())
+            .finish()
+    }
+}
+
+impl<P> Serialize for Permissions<P> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.set.to_base64())
+    }
+}
+
+impl<'de, P> Deserialize<'de> for Permissions<P> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        Ok(Self::from(Boolset::from_data(Some(&encoded), None)))
+    }
+}
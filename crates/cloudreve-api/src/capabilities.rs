@@ -0,0 +1,233 @@
+//! Server version detection and the features each version supports.
+
+use crate::client::ClientConfig;
+use crate::error::{ApiError, ApiResponse, ApiResult};
+use crate::trace;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Oldest server version the client has been tested against
+pub const MIN_TESTED_VERSION: ServerVersion = ServerVersion::new(4, 10, 0);
+
+/// Semantic version reported by the server, e.g. `4.10.1` or `4.10.1-pro`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ServerVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl ServerVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Parse a version string, ignoring a leading `v` and any pre-release or
+    /// edition suffix. Missing minor/patch components default to zero.
+    pub fn parse(version: &str) -> Option<Self> {
+        let version = version.trim().trim_start_matches('v');
+        let core = version.split(['-', '+']).next()?;
+        let mut parts = core.split('.').map(|p| p.parse::<u32>());
+        let major = parts.next()?.ok()?;
+        let minor = parts.next().transpose().ok()?.unwrap_or(0);
+        let patch = parts.next().transpose().ok()?.unwrap_or(0);
+        Some(Self::new(major, minor, patch))
+    }
+}
+
+impl fmt::Display for ServerVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Client features that depend on server support
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+    /// Real-time file change events over SSE
+    FileEvents,
+    /// Server-side folder size summaries
+    FolderSummary,
+    /// Info of several files in one request
+    BatchInfo,
+    /// Uploads skipped when the server already stores the same content
+    InstantUpload,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 4] = [
+        Feature::FileEvents,
+        Feature::FolderSummary,
+        Feature::BatchInfo,
+        Feature::InstantUpload,
+    ];
+
+    /// First server version that supports this feature
+    pub const fn min_version(&self) -> ServerVersion {
+        match self {
+            Feature::FileEvents => ServerVersion::new(4, 10, 0),
+            Feature::FolderSummary => ServerVersion::new(4, 0, 0),
+            Feature::BatchInfo => ServerVersion::new(4, 10, 0),
+            Feature::InstantUpload => ServerVersion::new(4, 10, 0),
+        }
+    }
+
+    /// Whether only the Pro edition has this feature
+    pub const fn requires_pro(&self) -> bool {
+        matches!(self, Feature::InstantUpload)
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Feature::FileEvents => "file events",
+            Feature::FolderSummary => "folder summary",
+            Feature::BatchInfo => "batch file info",
+            Feature::InstantUpload => "instant upload",
+        };
+        f.write_str(name)
+    }
+}
+
+/// What the connected server supports, derived from its version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capabilities {
+    pub version: ServerVersion,
+    /// Whether the server is the Pro edition
+    pub pro: bool,
+}
+
+impl Capabilities {
+    /// Build the capability map from the raw version string returned by the server.
+    pub fn from_version_string(version: &str) -> Option<Self> {
+        Some(Self {
+            version: ServerVersion::parse(version)?,
+            pro: version.trim().ends_with("-pro"),
+        })
+    }
+
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.version >= feature.min_version() && (self.pro || !feature.requires_pro())
+    }
+
+    /// Features the client uses that this server lacks
+    pub fn unavailable_features(&self) -> Vec<Feature> {
+        Feature::ALL
+            .into_iter()
+            .filter(|feature| !self.supports(*feature))
+            .collect()
+    }
+
+    /// Whether the server is older than any version the client was tested with
+    pub fn is_below_tested(&self) -> bool {
+        self.version < MIN_TESTED_VERSION
+    }
+}
+
+/// Major API version spoken by a server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiVersion {
+    V3,
+    V4,
+}
+
+impl ApiVersion {
+    /// URL prefix of the API endpoints
+    pub const fn prefix(&self) -> &'static str {
+        match self {
+            ApiVersion::V3 => "/api/v3",
+            ApiVersion::V4 => "/api/v4",
+        }
+    }
+}
+
+/// Probe `base_url` for the API version and capabilities it supports.
+///
+/// Only V4 servers can be synced. V3 servers are served with reduced
+/// features by [`crate::backend::connect`], which picks the backend from the
+/// detected version.
+pub async fn detect_api_version(config: &ClientConfig) -> ApiResult<(ApiVersion, Capabilities)> {
+    let http_client = config.http_client_builder().build()?;
+
+    let mut last_error = None;
+    for version in [ApiVersion::V4, ApiVersion::V3] {
+        let url = format!("{}{}/site/ping", config.base_url, version.prefix());
+        let response = match trace::execute(&http_client, http_client.get(&url)).await {
+            Ok(response) => response,
+            Err(e) => {
+                last_error = Some(e.into());
+                continue;
+            }
+        };
+        let Ok(ping) = response.json::<ApiResponse<String>>().await else {
+            continue;
+        };
+        if ping.code != 0 {
+            last_error = Some(ApiError::from_response(ping));
+            continue;
+        }
+
+        let raw = ping.data.unwrap_or_default();
+        match Capabilities::from_version_string(&raw) {
+            Some(capabilities) => return Ok((version, capabilities)),
+            None => {
+                last_error = Some(ApiError::Other(format!(
+                    "unrecognized server version: {}",
+                    raw
+                )))
+            }
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| ApiError::Other("not a Cloudreve server".to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_are_parsed_without_suffixes() {
+        assert_eq!(
+            ServerVersion::parse("v4.10.1-pro"),
+            Some(ServerVersion::new(4, 10, 1))
+        );
+        assert_eq!(
+            ServerVersion::parse("4.2"),
+            Some(ServerVersion::new(4, 2, 0))
+        );
+        assert_eq!(ServerVersion::parse("4.x"), None);
+        assert_eq!(ServerVersion::parse(""), None);
+    }
+
+    #[test]
+    fn features_follow_version_and_edition() {
+        let old = Capabilities::from_version_string("4.2.0").unwrap();
+        assert!(old.supports(Feature::FolderSummary));
+        assert!(!old.supports(Feature::FileEvents));
+        assert!(old.is_below_tested());
+        assert_eq!(
+            old.unavailable_features(),
+            vec![
+                Feature::FileEvents,
+                Feature::BatchInfo,
+                Feature::InstantUpload
+            ]
+        );
+
+        let community = Capabilities::from_version_string("4.10.1").unwrap();
+        assert!(community.supports(Feature::BatchInfo));
+        assert!(!community.supports(Feature::InstantUpload));
+
+        let pro = Capabilities::from_version_string("4.10.1-pro").unwrap();
+        assert!(pro.pro);
+        assert!(pro.unavailable_features().is_empty());
+    }
+}
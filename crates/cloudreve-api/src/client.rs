@@ -1,15 +1,26 @@
+use crate::api::SiteApi;
+use crate::capabilities::{Capabilities, Feature};
+use crate::correlation;
 use crate::error::{ApiError, ApiResponse, ApiResult, ErrorCode, LockConflictDetail};
-use crate::models::user::{RefreshTokenRequest, Token};
+use crate::models::site::SiteConfig;
+use crate::models::user::{RefreshTokenRequest, Token, WipeRequest};
+use crate::network::{AddressFamily, IpStrategy, StrategyResolver};
+use crate::pinning::{pinned_tls_config, PinMismatch, TlsPin};
+use crate::trace::{self, RequestTrace};
 use chrono::{DateTime, Duration, Utc};
 use reqwest::{Client as HttpClient, Method};
 use serde::de::DeserializeOwned;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::future::Future;
+use std::net::IpAddr;
 use std::pin::Pin;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
 const API_PREFIX: &str = "/api/v4";
+/// How long a LAN address may take to answer a health probe
+const LAN_PROBE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
 pub const CR_HEADER_PREFIX: &str = "X-Cr-";
 
 /// Client configuration
@@ -17,8 +28,27 @@ pub const CR_HEADER_PREFIX: &str = "X-Cr-";
 pub struct ClientConfig {
     /// Base URL of the Cloudreve instance (e.g., "https://example.com")
     pub base_url: String,
-    /// Timeout for requests in seconds
+    /// Timeout for establishing connections in seconds
     pub timeout_seconds: u64,
+    /// Timeout for each read from a response in seconds. `None` waits
+    /// indefinitely, which event streams rely on between events.
+    pub read_timeout_seconds: Option<u64>,
+    /// How long idle pooled connections are kept open in seconds
+    pub pool_idle_timeout_seconds: Option<u64>,
+    /// Maximum number of idle connections kept per host
+    pub pool_max_idle_per_host: Option<usize>,
+    /// Whether HTTP/2 may be negotiated. Disable for proxies that mishandle it.
+    pub http2: bool,
+    /// Which address family to try first
+    pub ip_strategy: IpStrategy,
+    /// Static addresses for host names, bypassing DNS
+    pub dns_overrides: BTreeMap<String, Vec<IpAddr>>,
+    /// Source address connections are made from, which picks the network
+    /// interface they go through
+    pub local_address: Option<IpAddr>,
+    /// Hashes the server certificate must match, any certificate valid for
+    /// the host if empty
+    pub tls_pins: Vec<TlsPin>,
     /// Client ID
     pub client_id: String,
     /// User agent string for HTTP requests
@@ -31,17 +61,73 @@ impl ClientConfig {
         Self {
             base_url: base_url.into(),
             timeout_seconds: 60,
+            read_timeout_seconds: None,
+            pool_idle_timeout_seconds: Some(90),
+            pool_max_idle_per_host: None,
+            http2: true,
+            ip_strategy: IpStrategy::Auto,
+            dns_overrides: BTreeMap::new(),
+            local_address: None,
+            tls_pins: Vec::new(),
             client_id: "".to_string(),
             user_agent: None,
         }
     }
 
-    /// Set the request timeout
+    /// Set the connect timeout
     pub fn with_timeout(mut self, timeout_seconds: u64) -> Self {
         self.timeout_seconds = timeout_seconds;
         self
     }
 
+    /// Set the timeout for each read from a response
+    pub fn with_read_timeout(mut self, timeout_seconds: u64) -> Self {
+        self.read_timeout_seconds = Some(timeout_seconds);
+        self
+    }
+
+    /// Set how long idle pooled connections are kept open
+    pub fn with_pool_idle_timeout(mut self, timeout_seconds: u64) -> Self {
+        self.pool_idle_timeout_seconds = Some(timeout_seconds);
+        self
+    }
+
+    /// Set the maximum number of idle connections kept per host
+    pub fn with_pool_max_idle_per_host(mut self, max_idle: usize) -> Self {
+        self.pool_max_idle_per_host = Some(max_idle);
+        self
+    }
+
+    /// Enable or disable HTTP/2
+    pub fn with_http2(mut self, enabled: bool) -> Self {
+        self.http2 = enabled;
+        self
+    }
+
+    /// Set which address family to try first
+    pub fn with_ip_strategy(mut self, strategy: IpStrategy) -> Self {
+        self.ip_strategy = strategy;
+        self
+    }
+
+    /// Resolve `host` to the given addresses instead of querying DNS
+    pub fn with_dns_override(mut self, host: &str, addrs: Vec<IpAddr>) -> Self {
+        self.dns_overrides.insert(host.to_ascii_lowercase(), addrs);
+        self
+    }
+
+    /// Make connections from the given source address
+    pub fn with_local_address(mut self, addr: IpAddr) -> Self {
+        self.local_address = Some(addr);
+        self
+    }
+
+    /// Only connect to a server whose certificate matches one of `pins`
+    pub fn with_tls_pins(mut self, pins: Vec<TlsPin>) -> Self {
+        self.tls_pins = pins;
+        self
+    }
+
     /// Set the client ID
     pub fn with_client_id(mut self, client_id: impl Into<String>) -> Self {
         self.client_id = client_id.into();
@@ -53,6 +139,58 @@ impl ClientConfig {
         self.user_agent = Some(user_agent.into());
         self
     }
+
+    /// Set a user agent identifying both the app and the device, e.g.
+    /// `cloudreve-desktop/1.0.0 (DESKTOP-1234)`
+    pub fn with_device_user_agent(self, product: &str, device_name: &str) -> Self {
+        self.with_user_agent(format!("{} ({})", product, device_name))
+    }
+
+    /// HTTP client builder with the connection settings of this config
+    /// applied, also for clients reaching hosts other than the server, like
+    /// storage providers
+    pub fn http_client_builder(&self) -> reqwest::ClientBuilder {
+        let mut builder = HttpClient::builder()
+            .connect_timeout(std::time::Duration::from_secs(self.timeout_seconds));
+
+        if let Some(timeout) = self.read_timeout_seconds {
+            builder = builder.read_timeout(std::time::Duration::from_secs(timeout));
+        }
+        if let Some(timeout) = self.pool_idle_timeout_seconds {
+            builder = builder.pool_idle_timeout(std::time::Duration::from_secs(timeout));
+        }
+        if let Some(max_idle) = self.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max_idle);
+        }
+        if !self.http2 {
+            builder = builder.http1_only();
+        }
+        if self.ip_strategy != IpStrategy::Auto || !self.dns_overrides.is_empty() {
+            builder = builder.dns_resolver(Arc::new(StrategyResolver {
+                strategy: self.ip_strategy,
+                overrides: self.dns_overrides.clone(),
+            }));
+        }
+        if let Some(addr) = self.local_address {
+            builder = builder.local_address(addr);
+        }
+        if let Some(ref user_agent) = self.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+        if !self.tls_pins.is_empty() {
+            let host = url::Url::parse(&self.base_url)
+                .ok()
+                .and_then(|url| {
+                    url.host_str()
+                        .map(|host| host.trim_matches(['[', ']']).to_string())
+                })
+                .unwrap_or_default();
+            builder =
+                builder.use_preconfigured_tls(pinned_tls_config(&self.tls_pins, &host, self.http2));
+        }
+
+        builder
+    }
 }
 
 /// Token storage with expiration tracking
@@ -74,15 +212,16 @@ impl TokenStore {
         }
     }
 
-    fn is_access_token_expired(&self) -> bool {
+    /// Expiry times come from the server, so `now` is the server's time
+    fn is_access_token_expired(&self, now: DateTime<Utc>) -> bool {
         self.access_token_expires
-            .map(|exp| Utc::now() >= exp)
+            .map(|exp| now >= exp)
             .unwrap_or(true)
     }
 
-    fn is_refresh_token_expired(&self) -> bool {
+    fn is_refresh_token_expired(&self, now: DateTime<Utc>) -> bool {
         self.refresh_token_expires
-            .map(|exp| Utc::now() >= exp)
+            .map(|exp| now >= exp)
             .unwrap_or(true)
     }
 
@@ -130,6 +269,25 @@ impl RequestOptions {
     }
 }
 
+/// Public share link the client browses as a guest, without an account
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShareSession {
+    pub share_id: String,
+    /// Password of a password protected share
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+impl ShareSession {
+    /// Root URI of the shared files, such as `cloudreve://<id>:<password>@share`
+    pub fn uri(&self) -> String {
+        match &self.password {
+            Some(password) => format!("cloudreve://{}:{}@share", self.share_id, password),
+            None => format!("cloudreve://{}@share", self.share_id),
+        }
+    }
+}
+
 /// Callback type for credential refresh events
 pub type OnCredentialRefreshed =
     Arc<dyn Fn(Token) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
@@ -138,38 +296,118 @@ pub type OnCredentialRefreshed =
 pub type OnCredentialInvalid =
     Arc<dyn Fn() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
 
+/// Callback type for responses saying the server is under maintenance
+pub type OnMaintenance = Arc<dyn Fn() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+/// Callback type for failed token refreshes, with the error as text
+pub type OnRefreshFailed =
+    Arc<dyn Fn(String) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+/// Callback type for the server presenting a certificate not matching its
+/// pin
+pub type OnPinMismatch =
+    Arc<dyn Fn(PinMismatch) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+/// Callback type for the server asking to unlink this device
+pub type OnWipeRequested =
+    Arc<dyn Fn(WipeRequest) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
 /// Main Cloudreve API client
 pub struct Client {
     pub(crate) config: ClientConfig,
     pub(crate) http_client: HttpClient,
     pub(crate) tokens: Arc<RwLock<TokenStore>>,
     pub(crate) purchase_ticket: Arc<RwLock<Option<String>>>,
+    /// Server capabilities, known once probed
+    pub(crate) capabilities: Arc<RwLock<Option<Capabilities>>>,
+    /// Address family of the last API connection
+    pub(crate) address_family: Arc<RwLock<Option<AddressFamily>>>,
+    /// Base URL of the same server on the local network, used for bulk
+    /// transfers while it is healthy
+    pub(crate) lan_base_url: Arc<RwLock<Option<String>>>,
+    /// Server clock minus local clock, estimated from the `Date` header of
+    /// API responses
+    pub(crate) clock_skew: Arc<RwLock<Option<Duration>>>,
+    /// Set when browsing a public share without an account
+    share_session: Option<ShareSession>,
     on_credential_refreshed: Option<OnCredentialRefreshed>,
     on_credential_invalid: Option<OnCredentialInvalid>,
+    on_maintenance: Option<OnMaintenance>,
+    on_refresh_failed: Option<OnRefreshFailed>,
+    on_wipe_requested: Option<OnWipeRequested>,
+    on_pin_mismatch: Option<OnPinMismatch>,
 }
 
 impl Client {
     /// Create a new API client
     pub fn new(config: ClientConfig) -> Self {
-        let mut builder = HttpClient::builder()
-            .connect_timeout(std::time::Duration::from_secs(config.timeout_seconds));
-
-        if let Some(ref user_agent) = config.user_agent {
-            builder = builder.user_agent(user_agent);
-        }
-
-        let http_client = builder.build().expect("Failed to create HTTP client");
+        let http_client = config
+            .http_client_builder()
+            .build()
+            .expect("Failed to create HTTP client");
 
         Self {
             config,
             http_client,
             tokens: Arc::new(RwLock::new(TokenStore::new())),
             purchase_ticket: Arc::new(RwLock::new(None)),
+            capabilities: Arc::new(RwLock::new(None)),
+            address_family: Arc::new(RwLock::new(None)),
+            lan_base_url: Arc::new(RwLock::new(None)),
+            clock_skew: Arc::new(RwLock::new(None)),
+            share_session: None,
             on_credential_refreshed: None,
             on_credential_invalid: None,
+            on_maintenance: None,
+            on_refresh_failed: None,
+            on_wipe_requested: None,
+            on_pin_mismatch: None,
         }
     }
 
+    /// Configuration the client was created with
+    pub fn config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    /// Address family used by the last API request, if any was made
+    pub async fn address_family(&self) -> Option<AddressFamily> {
+        *self.address_family.read().await
+    }
+
+    /// How far the server's clock is ahead of the local one (negative when
+    /// behind), if a response with a `Date` header was received
+    pub async fn clock_skew(&self) -> Option<Duration> {
+        *self.clock_skew.read().await
+    }
+
+    /// Current time on the server's clock, for comparing against times the
+    /// server issued
+    pub async fn server_now(&self) -> DateTime<Utc> {
+        Utc::now() + self.clock_skew().await.unwrap_or_else(Duration::zero)
+    }
+
+    /// Convert a Unix timestamp in seconds issued by the server to the local
+    /// clock
+    pub async fn server_to_local_timestamp(&self, timestamp: i64) -> i64 {
+        timestamp - self.clock_skew().await.map_or(0, |skew| skew.num_seconds())
+    }
+
+    /// Update the clock skew estimate from a response's `Date` header
+    async fn record_server_date(&self, headers: &reqwest::header::HeaderMap) {
+        let Some(date) = headers
+            .get(reqwest::header::DATE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| DateTime::parse_from_rfc2822(v).ok())
+        else {
+            return;
+        };
+        // The header has whole-second precision, so the server's time is
+        // somewhere in the second after it
+        let server_time = date.with_timezone(&Utc) + Duration::milliseconds(500);
+        *self.clock_skew.write().await = Some(server_time - Utc::now());
+    }
+
     /// Set a callback to be invoked when credentials are refreshed
     ///
     /// The callback receives the new token information and can perform async operations
@@ -178,9 +416,9 @@ impl Client {
     /// # Example
     /// ```no_run
     /// use std::sync::Arc;
-    /// use std::pin::Pin;
-    /// use std::future::Future;
+    /// use cloudreve_api::{Client, ClientConfig};
     ///
+    /// let mut client = Client::new(ClientConfig::new("https://your-cloudreve-instance.com"));
     /// client.set_on_credential_refreshed(Arc::new(|token| {
     ///     Box::pin(async move {
     ///         // Save token to storage
@@ -211,6 +449,57 @@ impl Client {
         self.on_credential_invalid = None;
     }
 
+    /// Set a callback to be invoked when a response says the server is under
+    /// maintenance. The request still fails with [`ApiError::Maintenance`].
+    pub fn set_on_maintenance(&mut self, callback: OnMaintenance) {
+        self.on_maintenance = Some(callback);
+    }
+
+    /// Set a callback to be invoked when refreshing the access token fails,
+    /// whether the server rejected the refresh token or could not be reached
+    pub fn set_on_refresh_failed(&mut self, callback: OnRefreshFailed) {
+        self.on_refresh_failed = Some(callback);
+    }
+
+    /// Set a callback to be invoked when the server revoked the session and
+    /// asks to unlink this device (40090). The request still fails with
+    /// [`ApiError::WipeRequested`].
+    pub fn set_on_wipe_requested(&mut self, callback: OnWipeRequested) {
+        self.on_wipe_requested = Some(callback);
+    }
+
+    /// Set a callback to be invoked when an API request fails because the
+    /// server certificate does not match its pin. The request still fails
+    /// with [`ApiError::PinMismatch`].
+    pub fn set_on_pin_mismatch(&mut self, callback: OnPinMismatch) {
+        self.on_pin_mismatch = Some(callback);
+    }
+
+    /// Browse a public share as a guest. Requests are then sent without
+    /// tokens, and the share becoming unreachable (404, 40069) is reported
+    /// through the credential invalid callback, as the link and password are
+    /// the only credentials there are.
+    pub fn set_share_session(&mut self, session: ShareSession) {
+        self.share_session = Some(session);
+    }
+
+    /// Public share the client browses as a guest, if any
+    pub fn share_session(&self) -> Option<&ShareSession> {
+        self.share_session.as_ref()
+    }
+
+    /// Whether an error code means the credentials in use are no longer valid
+    fn is_credential_error(&self, error_code: ErrorCode) -> bool {
+        if self.share_session.is_some() {
+            matches!(
+                error_code,
+                ErrorCode::NotFound | ErrorCode::IncorrectPassword
+            )
+        } else {
+            error_code.is_credential_error()
+        }
+    }
+
     /// Invoke the credential invalid callback if set
     async fn notify_credential_invalid(&self) {
         if let Some(ref callback) = self.on_credential_invalid {
@@ -218,6 +507,37 @@ impl Client {
         }
     }
 
+    /// Report a maintenance response: invoke the callback if set and pass
+    /// the error on
+    pub(crate) async fn maintenance_error(&self, error: ApiError) -> ApiError {
+        if let Some(ref callback) = self.on_maintenance {
+            callback().await;
+        }
+        error
+    }
+
+    /// Recognize a response asking to unlink this device: invoke the
+    /// callback if set and return the error to fail the request with
+    pub(crate) async fn wipe_error(&self, raw: &serde_json::Value) -> Option<ApiError> {
+        let code = raw.get("code").and_then(|c| c.as_i64())?;
+        if code != ErrorCode::WipeRequested as i64 {
+            return None;
+        }
+        let message = raw
+            .get("msg")
+            .and_then(|m| m.as_str())
+            .unwrap_or("")
+            .to_string();
+        let request: WipeRequest = raw
+            .get("data")
+            .and_then(|d| serde_json::from_value(d.clone()).ok())
+            .unwrap_or_default();
+        if let Some(ref callback) = self.on_wipe_requested {
+            callback(request.clone()).await;
+        }
+        Some(ApiError::WipeRequested { message, request })
+    }
+
     /// Set authentication tokens
     pub async fn set_tokens(&self, access_token: String, refresh_token: String) {
         let mut store = self.tokens.write().await;
@@ -316,8 +636,126 @@ impl Client {
         *pt = ticket;
     }
 
+    /// Set the server capabilities, usually from [`crate::api::site::SiteApi::probe_capabilities`]
+    pub async fn set_capabilities(&self, capabilities: Option<Capabilities>) {
+        *self.capabilities.write().await = capabilities;
+    }
+
+    /// Get the server capabilities, if they have been probed
+    pub async fn capabilities(&self) -> Option<Capabilities> {
+        self.capabilities.read().await.clone()
+    }
+
+    /// Fail with [`ApiError::FeatureUnavailable`] if the server is known not to
+    /// support `feature`. Unprobed servers are assumed to support everything.
+    pub async fn require(&self, feature: Feature) -> ApiResult<()> {
+        match self.capabilities.read().await.as_ref() {
+            Some(capabilities) if !capabilities.supports(feature) => {
+                Err(ApiError::FeatureUnavailable {
+                    feature,
+                    version: capabilities.version,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that `base_url` reaches this same server, by the instance id in
+    /// its site config, and route bulk transfers through it if so. Any failure switches transfers back to the public URL.
+    /// Returns whether the LAN route is active.
+    ///
+    /// Servers with pinned certificates are never routed over a LAN address:
+    /// the pins only cover the public host, and the tokens and files sent to
+    /// the LAN address would go without them.
+    pub async fn probe_lan_base_url(&self, base_url: &str) -> ApiResult<bool> {
+        let base_url = base_url.trim_end_matches('/');
+        if !self.config.tls_pins.is_empty() {
+            self.clear_lan_base_url().await;
+            return Err(ApiError::Other(
+                "LAN addresses are not used for servers with a pinned certificate".to_string(),
+            ));
+        }
+        let result: ApiResult<bool> = async {
+            let lan: ApiResponse<SiteConfig> = self
+                .execute(
+                    self.http_client
+                        .get(format!("{}{}/site/config/basic", base_url, API_PREFIX))
+                        .timeout(LAN_PROBE_TIMEOUT),
+                )
+                .await?
+                .json()
+                .await?;
+            if lan.code != 0 {
+                return Err(ApiError::from_response(lan));
+            }
+            let public = SiteApi::get_site_config(self, "basic").await?;
+
+            // Other servers, even of the same version, have another instance
+            // id. It is not secret, so this catches a wrong address rather
+            // than a host impersonating the server on purpose.
+            let lan = lan.data.and_then(|lan| lan.instance_id);
+            Ok(match (public.instance_id, lan) {
+                (Some(public), Some(lan)) => !public.is_empty() && public == lan,
+                _ => false,
+            })
+        }
+        .await;
+
+        let healthy = matches!(result, Ok(true));
+        *self.lan_base_url.write().await = healthy.then(|| base_url.to_string());
+        result
+    }
+
+    /// Stop routing bulk transfers through the LAN address
+    pub async fn clear_lan_base_url(&self) {
+        *self.lan_base_url.write().await = None;
+    }
+
+    /// LAN base URL currently used for bulk transfers
+    pub async fn lan_base_url(&self) -> Option<String> {
+        self.lan_base_url.read().await.clone()
+    }
+
+    /// Rewrite a URL served by this server to go through the LAN address, if
+    /// one is active. URLs pointing at external storage are returned as is.
+    pub async fn prefer_lan(&self, url: &str) -> String {
+        let Some(lan) = self.lan_base_url().await else {
+            return url.to_string();
+        };
+        match url.strip_prefix(self.config.base_url.trim_end_matches('/')) {
+            Some(rest) if rest.is_empty() || rest.starts_with('/') => format!("{}{}", lan, rest),
+            _ => url.to_string(),
+        }
+    }
+
+    /// URL of a bulk transfer endpoint and whether it goes through the LAN address
+    pub(crate) async fn build_transfer_url(&self, path: &str) -> (String, bool) {
+        match self.lan_base_url().await {
+            Some(lan) => (format!("{}{}{}", lan, API_PREFIX, path), true),
+            None => (self.build_url(path), false),
+        }
+    }
+
+    /// Switch back to the public URL if a request over the LAN address failed
+    /// to connect, so that the caller's retry goes over the public route.
+    pub(crate) async fn fall_back_from_lan(
+        &self,
+        response: reqwest::Result<reqwest::Response>,
+        via_lan: bool,
+    ) -> ApiResult<reqwest::Response> {
+        if via_lan && response.is_err() {
+            self.clear_lan_base_url().await;
+        }
+        Ok(response?)
+    }
+
     /// Get a valid access token, refreshing if necessary
     pub(crate) async fn get_access_token(&self) -> ApiResult<String> {
+        // Guests of a share never had tokens, which is not worth reporting
+        if self.share_session.is_some() {
+            return Err(ApiError::NoTokensAvailable);
+        }
+
         let store = self.tokens.read().await;
 
         // Check if we have tokens
@@ -327,13 +765,14 @@ impl Client {
         }
 
         // Check if refresh token is expired
-        if store.is_refresh_token_expired() {
+        let now = self.server_now().await;
+        if store.is_refresh_token_expired(now) {
             self.notify_credential_invalid().await;
             return Err(ApiError::RefreshTokenExpired);
         }
 
         // If access token is not expired, return it
-        if !store.is_access_token_expired() {
+        if !store.is_access_token_expired(now) {
             return Ok(store.access_token.clone().unwrap());
         }
 
@@ -343,8 +782,22 @@ impl Client {
         self.refresh_access_token().await
     }
 
-    /// Refresh the access token using the refresh token
+    /// Refresh the access token using the refresh token, reporting failures
+    /// to the refresh failed callback
     async fn refresh_access_token(&self) -> ApiResult<String> {
+        let result = self.try_refresh_access_token().await;
+        if let Err(ref error) = result {
+            // An unlinked device is handled on its own
+            if !error.is_wipe_requested() {
+                if let Some(ref callback) = self.on_refresh_failed {
+                    callback(error.to_string()).await;
+                }
+            }
+        }
+        result
+    }
+
+    async fn try_refresh_access_token(&self) -> ApiResult<String> {
         let refresh_token = {
             let store = self.tokens.read().await;
             store
@@ -357,9 +810,17 @@ impl Client {
         let url = self.build_url("/session/token/refresh");
         let request = RefreshTokenRequest { refresh_token };
 
-        let response = self.http_client.post(&url).json(&request).send().await?;
+        let response = self
+            .execute(self.http_client.post(&url).json(&request))
+            .await?;
+        self.record_server_date(response.headers()).await;
 
-        let api_response: ApiResponse<Token> = response.json().await?;
+        let raw_value: serde_json::Value = response.json().await?;
+        // A revoked session fails to refresh
+        if let Some(error) = self.wipe_error(&raw_value).await {
+            return Err(error);
+        }
+        let api_response: ApiResponse<Token> = serde_json::from_value(raw_value)?;
 
         if api_response.code != ErrorCode::Success as i32 {
             if let Some(error_code) = ErrorCode::from_code(api_response.code) {
@@ -390,13 +851,23 @@ impl Client {
         format!("{}{}{}", self.config.base_url, API_PREFIX, path)
     }
 
-    /// Internal send method that handles the actual HTTP request
+    /// Send a request, traced when HTTP tracing is on
+    pub(crate) async fn execute(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> reqwest::Result<reqwest::Response> {
+        trace::execute(&self.http_client, request).await
+    }
+
+    /// Internal send method that handles the actual HTTP request. `attempt`
+    /// counts the retries of the request, for tracing.
     async fn send_internal<T, R>(
         &self,
         path: &str,
         method: Method,
         body: Option<&T>,
         options: RequestOptions,
+        attempt: u32,
     ) -> ApiResult<R>
     where
         T: Serialize + ?Sized,
@@ -405,8 +876,8 @@ impl Client {
         let url = self.build_url(path);
         let mut request = self.http_client.request(method, &url);
 
-        // Add authentication header if needed
-        if !options.no_credential {
+        // Add authentication header if needed. Guests of a share have none.
+        if !options.no_credential && self.share_session.is_none() {
             let token = self.get_access_token().await?;
             request = request.header("Authorization", format!("Bearer {}", token));
         }
@@ -419,6 +890,9 @@ impl Client {
             );
         }
 
+        // Tie the request to the user action it is made for
+        request = correlation::tag(request);
+
         // Add purchase ticket if requested
         if options.with_purchase_ticket {
             let ticket = self.purchase_ticket.read().await;
@@ -433,14 +907,40 @@ impl Client {
         }
 
         // Execute request
-        let response = request.send().await?;
+        let request = request.build()?;
+        let trace = RequestTrace::start(&request, attempt);
+        let response = match self.http_client.execute(request).await {
+            Ok(response) => response,
+            Err(e) => {
+                if let Some(trace) = trace {
+                    trace.failed(&e);
+                }
+                return Err(e.into());
+            }
+        };
+        if let Some(addr) = response.remote_addr() {
+            *self.address_family.write().await = Some(AddressFamily::of(&addr.ip()));
+        }
+        self.record_server_date(response.headers()).await;
+        let status = response.status();
+        let headers = response.headers().clone();
         let response_text = response.text().await?;
+        if let Some(trace) = trace {
+            trace.finish(status, trace::correlation_id(&response_text).as_deref());
+        }
+        if let Some(error) = ApiError::maintenance_from(status, &headers, &response_text) {
+            return Err(self.maintenance_error(error).await);
+        }
 
         // First parse as a generic Value to check the error code
         let raw_value: serde_json::Value = serde_json::from_str(&response_text)?;
 
         let code = raw_value.get("code").and_then(|c| c.as_i64()).unwrap_or(0) as i32;
 
+        if let Some(error) = self.wipe_error(&raw_value).await {
+            return Err(error);
+        }
+
         // Handle lock conflict specially - data contains LockConflictDetail
         if code == ErrorCode::LockConflict as i32 {
             let msg = raw_value
@@ -464,7 +964,7 @@ impl Client {
         if api_response.code != ErrorCode::Success as i32 {
             // Check if this is a credential error and invoke callback
             if let Some(error_code) = ErrorCode::from_code(api_response.code) {
-                if error_code.is_credential_error() {
+                if self.is_credential_error(error_code) {
                     self.notify_credential_invalid().await;
                 }
             }
@@ -487,18 +987,26 @@ impl Client {
         T: Serialize + ?Sized,
         R: DeserializeOwned + Default,
     {
-        match self
-            .send_internal(path, method.clone(), body, options.clone())
+        let result = match self
+            .send_internal(path, method.clone(), body, options.clone(), 0)
             .await
         {
             Ok(result) => Ok(result),
             Err(ApiError::AccessTokenExpired) => {
                 // Token expired, refresh and retry
-                self.refresh_access_token().await?;
-                self.send_internal(path, method, body, options).await
+                match self.refresh_access_token().await {
+                    Ok(_) => self.send_internal(path, method, body, options, 1).await,
+                    Err(e) => Err(e),
+                }
             }
             Err(e) => Err(e),
+        };
+        if let (Err(ApiError::PinMismatch(mismatch)), Some(callback)) =
+            (&result, &self.on_pin_mismatch)
+        {
+            callback(mismatch.clone()).await;
         }
+        result
     }
 
     /// Send a GET request
@@ -559,3 +1067,32 @@ impl Client {
         self.send(path, Method::PATCH, Some(body), options).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pinning::PinKind;
+
+    #[tokio::test]
+    async fn pinned_servers_are_not_routed_over_lan() {
+        let config = ClientConfig::new("https://cloud.example.com").with_tls_pins(vec![TlsPin {
+            kind: PinKind::PublicKey,
+            sha256: "Ug9uyCQT4RDqP+/DxueHNpVMVa3RnJq1n+0o1U6sqyk=".to_string(),
+        }]);
+        let client = Client::new(config);
+        *client.lan_base_url.write().await = Some("http://192.168.1.2:5212".to_string());
+
+        assert!(client
+            .probe_lan_base_url("http://192.168.1.2:5212")
+            .await
+            .is_err());
+        assert_eq!(client.lan_base_url().await, None);
+        assert_eq!(
+            client.build_transfer_url("/file/upload").await,
+            (
+                "https://cloud.example.com/api/v4/file/upload".to_string(),
+                false
+            )
+        );
+    }
+}
@@ -0,0 +1,47 @@
+//! Correlation id of the user action requests are made for.
+//!
+//! A task running in a [`scope`] sends its current correlation id with every
+//! request in the [`CORRELATION_HEADER`] header, so the requests of a single
+//! action can be found in the server logs, and includes it in HTTP traces.
+//! The id can be changed within the scope with [`set_current`], e.g. by a
+//! loop handling the commands of several actions one after the other.
+
+use reqwest::RequestBuilder;
+use std::cell::RefCell;
+use std::future::Future;
+
+/// Request header carrying the correlation id
+pub const CORRELATION_HEADER: &str = "X-Cr-Correlation-Id";
+
+tokio::task_local! {
+    static CORRELATION_ID: RefCell<Option<String>>;
+}
+
+/// Run `future` with `id` as its correlation id
+pub async fn scope<F: Future>(id: Option<String>, future: F) -> F::Output {
+    CORRELATION_ID.scope(RefCell::new(id), future).await
+}
+
+/// Correlation id of the current task, if it runs in a [`scope`] and has one
+pub fn current() -> Option<String> {
+    CORRELATION_ID
+        .try_with(|id| id.borrow().clone())
+        .ok()
+        .flatten()
+}
+
+/// Add the current correlation id, if any, to `request`
+pub(crate) fn tag(request: RequestBuilder) -> RequestBuilder {
+    match current() {
+        Some(id) => request.header(CORRELATION_HEADER, id),
+        None => request,
+    }
+}
+
+/// Change the correlation id of the current task. Returns `false`, changing
+/// nothing, if the task does not run in a [`scope`].
+pub fn set_current(id: Option<String>) -> bool {
+    CORRELATION_ID
+        .try_with(|current| *current.borrow_mut() = id)
+        .is_ok()
+}
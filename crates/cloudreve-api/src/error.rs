@@ -1,3 +1,6 @@
+use crate::capabilities::{Feature, ServerVersion};
+use crate::models::user::WipeRequest;
+use crate::pinning::PinMismatch;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use thiserror::Error;
@@ -50,6 +53,8 @@ pub enum ErrorCode {
     DomainNotLicensed = 40087,
     AnonymousAccessDenied = 40088,
     SessionExpired = 40089,
+    /// The session was revoked and the device should be unlinked
+    WipeRequested = 40090,
     PurchaseRequired = 40083,
     LoginRequired = 401,
     PermissionDenied = 403,
@@ -69,6 +74,7 @@ impl ErrorCode {
             40087 => Some(Self::DomainNotLicensed),
             40088 => Some(Self::AnonymousAccessDenied),
             40089 => Some(Self::SessionExpired),
+            40090 => Some(Self::WipeRequested),
             40083 => Some(Self::PurchaseRequired),
             401 => Some(Self::LoginRequired),
             403 => Some(Self::PermissionDenied),
@@ -127,7 +133,11 @@ pub enum ApiError {
 
     /// HTTP request error
     #[error("HTTP request error: {0}")]
-    RequestError(#[from] reqwest::Error),
+    RequestError(reqwest::Error),
+
+    /// The server presented a certificate matching none of its pins
+    #[error("{0}")]
+    PinMismatch(PinMismatch),
 
     /// JSON serialization/deserialization error
     #[error("JSON error: {0}")]
@@ -153,6 +163,30 @@ pub enum ApiError {
     #[error("SSE stream error: {0}")]
     SseStreamError(String),
 
+    /// The server is down for planned maintenance (503 with a maintenance
+    /// marker)
+    #[error("Server is under maintenance: {message}")]
+    Maintenance {
+        message: String,
+        /// How long the server asked clients to wait, from `Retry-After`
+        retry_after: Option<std::time::Duration>,
+    },
+
+    /// The session was revoked and the server asks to unlink this device
+    /// (40090)
+    #[error("Device unlink requested: {message}")]
+    WipeRequested {
+        message: String,
+        request: WipeRequest,
+    },
+
+    /// Feature not supported by the connected server version
+    #[error("{feature} is not supported by server version {version}")]
+    FeatureUnavailable {
+        feature: Feature,
+        version: ServerVersion,
+    },
+
     /// Generic error
     #[error("{0}")]
     Other(String),
@@ -181,6 +215,10 @@ impl ApiError {
             Some(ErrorCode::LoginRequired)
             | Some(ErrorCode::CredentialInvalid)
             | Some(ErrorCode::SessionExpired) => ApiError::LoginRequired(response.msg),
+            Some(ErrorCode::WipeRequested) => ApiError::WipeRequested {
+                message: response.msg,
+                request: WipeRequest::default(),
+            },
             _ => ApiError::ApiError {
                 code,
                 message: response.msg,
@@ -198,6 +236,60 @@ impl ApiError {
         matches!(self, ApiError::AccessTokenExpired)
     }
 
+    /// Check if this error means the server does not support the feature
+    pub fn is_feature_unavailable(&self) -> bool {
+        matches!(self, ApiError::FeatureUnavailable { .. })
+    }
+
+    /// Check if this error means the server asks to unlink this device
+    pub fn is_wipe_requested(&self) -> bool {
+        matches!(self, ApiError::WipeRequested { .. })
+    }
+
+    /// Check if this error means the server is under maintenance
+    pub fn is_maintenance(&self) -> bool {
+        matches!(self, ApiError::Maintenance { .. })
+    }
+
+    /// Check if this error means the server certificate changed from its pin
+    pub fn is_pin_mismatch(&self) -> bool {
+        matches!(self, ApiError::PinMismatch(_))
+    }
+
+    /// Recognize a maintenance response: a 503 that either sets `Retry-After`
+    /// or mentions maintenance in its body. Other 503s, such as an overloaded
+    /// proxy, are left to the usual error handling.
+    pub(crate) fn maintenance_from(
+        status: reqwest::StatusCode,
+        headers: &reqwest::header::HeaderMap,
+        body: &str,
+    ) -> Option<Self> {
+        if status != reqwest::StatusCode::SERVICE_UNAVAILABLE {
+            return None;
+        }
+        // Only the delay in seconds form is used, not an HTTP date
+        let retry_after = headers
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .map(std::time::Duration::from_secs);
+        let mentions_maintenance = body.to_ascii_lowercase().contains("maintenance");
+        if retry_after.is_none() && !mentions_maintenance {
+            return None;
+        }
+
+        // Prefer the message of a JSON error response over an HTML page
+        let message = serde_json::from_str::<ApiResponse<()>>(body)
+            .ok()
+            .map(|response| response.msg)
+            .filter(|msg| !msg.is_empty())
+            .unwrap_or_else(|| "service unavailable".to_string());
+        Some(ApiError::Maintenance {
+            message,
+            retry_after,
+        })
+    }
+
     /// Check if this error requires login
     pub fn requires_login(&self) -> bool {
         matches!(
@@ -207,5 +299,16 @@ impl ApiError {
     }
 }
 
+/// Certificates not matching their pin are told apart from other connection
+/// failures, which are retried as the server being unreachable
+impl From<reqwest::Error> for ApiError {
+    fn from(error: reqwest::Error) -> Self {
+        match PinMismatch::find(&error) {
+            Some(mismatch) => ApiError::PinMismatch(mismatch.clone()),
+            None => ApiError::RequestError(error),
+        }
+    }
+}
+
 /// Result type alias for API operations
 pub type ApiResult<T> = Result<T, ApiError>;
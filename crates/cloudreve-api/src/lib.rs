@@ -8,10 +8,22 @@
 //! - Comprehensive error handling
 //! - Type-safe API methods
 //! - Support for all Cloudreve API endpoints
+//! - Legacy Cloudreve V3 servers through the [`backend`] abstraction
+//!
+//! ## Cargo features
+//!
+//! - `v3` (default): the `backend::V3Client` for legacy Cloudreve V3
+//!   servers, which keeps their session cookie
+//! - `blocking`: a synchronous wrapper around the client for scripts, see
+//!   `blocking::BlockingClient`
+//!
+//! The crate does not depend on the sync engine or on Windows and builds on
+//! its own for CLI and server tooling.
 //!
 //! ## Example
 //!
 //! ```no_run
+//! use cloudreve_api::api::UserApi;
 //! use cloudreve_api::{Client, ClientConfig};
 //!
 //! #[tokio::main]
@@ -37,11 +49,23 @@
 //! ```
 
 pub mod api;
+pub mod backend;
+#[cfg(feature = "blocking")]
+pub mod blocking;
 pub mod boolset;
+pub mod capabilities;
 pub mod client;
+pub mod correlation;
 pub mod error;
 pub mod models;
+pub mod network;
+pub mod pinning;
+pub mod trace;
 
-pub use boolset::Boolset;
-pub use client::{Client, ClientConfig};
+pub use backend::{Backend, RemoteEntry};
+pub use boolset::{Boolset, Permission, Permissions};
+pub use capabilities::{ApiVersion, Capabilities, Feature, ServerVersion};
+pub use client::{Client, ClientConfig, ShareSession};
 pub use error::{ApiError, ApiResult};
+pub use network::{AddressFamily, IpStrategy};
+pub use pinning::{PinKind, PinMismatch, TlsPin};
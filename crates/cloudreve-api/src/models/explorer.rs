@@ -1,6 +1,8 @@
+use crate::boolset::{Permission, Permissions};
 use crate::models::common::PaginationResults;
-use crate::models::user::User;
+use crate::models::user::{User, WipeRequest};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::collections::HashMap;
 
 /// List file service parameters
@@ -33,7 +35,7 @@ pub struct FileResponse {
     pub id: String,
     pub name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub permission: Option<String>,
+    pub permission: Option<FilePermissions>,
     pub created_at: String,
     pub updated_at: String,
     pub size: i64,
@@ -52,6 +54,29 @@ pub struct FileResponse {
     pub extended_info: Option<ExtendedInfo>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub primary_entity: Option<String>,
+    /// Owner of a file the user does not own, such as in a group folder
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<UserBrief>,
+    /// Fields not covered above, kept as sent by the server
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Another user as shown next to files, with only the fields needed for that
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct UserBrief {
+    pub id: String,
+    #[serde(default)]
+    pub nickname: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<GroupBrief>,
+}
+
+/// Group of a [`UserBrief`]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct GroupBrief {
+    pub id: String,
+    pub name: String,
 }
 
 /// Folder summary
@@ -69,7 +94,9 @@ pub struct FolderSummary {
 pub struct ExtendedInfo {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub storage_policy: Option<StoragePolicy>,
+    #[serde(default)]
     pub storage_policy_inherited: bool,
+    #[serde(default)]
     pub storage_used: i64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub shares: Option<Vec<Share>>,
@@ -81,6 +108,9 @@ pub struct ExtendedInfo {
     pub view: Option<ExplorerView>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub direct_links: Option<Vec<DirectLink>>,
+    /// Fields not covered above, kept as sent by the server
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 /// Direct link
@@ -107,6 +137,9 @@ pub struct Entity {
     pub created_by: Option<User>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub encrypted_with: Option<EncryptionCipher>,
+    /// Fields not covered above, kept as sent by the server
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 /// Share information
@@ -129,12 +162,15 @@ pub struct Share {
     pub price: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub permission_setting: Option<PermissionSettingReq>,
+    #[serde(default)]
     pub url: String,
+    #[serde(default)]
     pub visited: i32,
     #[serde(default)]
     pub downloaded: i32,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub expired: Option<bool>,
+    #[serde(default)]
     pub unlocked: bool,
     #[serde(default)]
     pub password_protected: bool,
@@ -149,6 +185,9 @@ pub struct Share {
     pub password: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub show_readme: Option<bool>,
+    /// Fields not covered above, kept as sent by the server
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 /// Storage policy type
@@ -168,6 +207,9 @@ pub enum PolicyType {
     Obs,
     #[serde(rename = "load_balance")]
     LoadBalance,
+    /// A policy type this client does not know
+    #[serde(other)]
+    Unknown,
 }
 
 /// Storage policy
@@ -198,6 +240,9 @@ pub struct StoragePolicy {
     pub encryption: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub streaming_encryption: Option<bool>,
+    /// Fields not covered above, kept as sent by the server
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 /// List response
@@ -220,6 +265,9 @@ pub struct ListResponse {
     pub storage_policy: Option<StoragePolicy>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub view: Option<ExplorerView>,
+    /// Fields not covered above, kept as sent by the server
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 /// Navigator properties
@@ -227,9 +275,15 @@ pub struct ListResponse {
 pub struct NavigatorProps {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub capability: Option<String>,
+    #[serde(default)]
     pub max_page_size: i32,
+    #[serde(default)]
     pub order_by_options: Vec<String>,
+    #[serde(default)]
     pub order_direction_options: Vec<String>,
+    /// Fields not covered above, kept as sent by the server
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 /// Explorer view settings
@@ -443,6 +497,9 @@ pub struct EncryptMetadata {
 pub enum EncryptionCipher {
     #[serde(rename = "aes-256-ctr")]
     Aes256Ctr,
+    /// A cipher this client can't encrypt with
+    #[serde(other)]
+    Unknown,
 }
 
 /// Delete upload session service
@@ -496,15 +553,64 @@ pub mod metadata {
     pub const LIVE_PHOTO: &str = "customize:live_photo";
     pub const TAG_PREFIX: &str = "tag:";
     pub const THUMBNAIL_DISABLED: &str = "thumb:disabled";
+    pub const RESTORE_URI: &str = "sys:restore_uri";
+    pub const EXPECTED_COLLECT_TIME: &str = "sys:expected_collect_time";
 }
 
-/// File permission constants
-pub mod file_permission {
-    pub const READ: i32 = 0;
-    pub const UPDATE: i32 = 1;
-    pub const CREATE: i32 = 2;
-    pub const DELETE: i32 = 3;
-}
+/// A file or folder in the trash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashEntry {
+    /// URI of the entry in the trash, as passed to restore it
+    pub uri: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub file_type: i32,
+    pub size: i64,
+    /// When the entry was moved to the trash
+    pub deleted_at: String,
+    /// Where the entry is restored to
+    pub restore_uri: String,
+    /// When the server empties the entry from the trash
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_collect_time: Option<String>,
+}
+
+impl TrashEntry {
+    /// Entry of a file listed in the trash, `None` if the server did not
+    /// record where it came from
+    pub fn from_file(file: FileResponse) -> Option<Self> {
+        let mut metadata = file.metadata.unwrap_or_default();
+        let restore_uri = metadata.remove(metadata::RESTORE_URI)?;
+        Some(Self {
+            uri: file.path,
+            name: file.name,
+            file_type: file.file_type,
+            size: file.size,
+            // Moving a file to the trash updates it
+            deleted_at: file.updated_at,
+            restore_uri,
+            expected_collect_time: metadata.remove(metadata::EXPECTED_COLLECT_TIME),
+        })
+    }
+}
+
+/// Permissions on a file or folder
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilePermission {
+    Read = 0,
+    Update = 1,
+    Create = 2,
+    Delete = 3,
+}
+
+impl Permission for FilePermission {
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Permissions the user has on a file or folder
+pub type FilePermissions = Permissions<FilePermission>;
 
 /// Navigator capability constants
 pub mod navigator_capability {
@@ -542,6 +648,9 @@ pub enum FileEventType {
     Modify,
     Rename,
     Delete,
+    /// An event type this client does not know
+    #[serde(other)]
+    Unknown,
 }
 
 /// File event data received from SSE
@@ -553,6 +662,9 @@ pub struct FileEventData {
     pub from: String,
     #[serde(default)]
     pub to: String,
+    /// User who made the change, if the server says
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor: Option<UserBrief>,
 }
 
 /// SSE event types from the file events endpoint
@@ -568,4 +680,6 @@ pub enum FileEvent {
     ReconnectRequired,
     /// Batch of file events with data
     Event(Vec<FileEventData>),
+    /// The session was revoked and the device should be unlinked
+    WipeRequested(WipeRequest),
 }
@@ -15,9 +15,26 @@ pub struct ListShareService {
 }
 
 /// List share response
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ListShareResponse {
     pub shares: Vec<Share>,
     pub pagination: PaginationResults,
 }
 
+
+/// Create share service
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct CreateShareService {
+    pub uri: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_private: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub share_view: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub downloads: Option<i32>,
+    /// Seconds until the share expires
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expire: Option<i64>,
+}
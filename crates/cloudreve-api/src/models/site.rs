@@ -119,6 +119,12 @@ pub struct SiteConfig {
     pub thumb_exts: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub show_encryption_status: Option<bool>,
+    /// Longest file name the server accepts, in bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_name_length: Option<i32>,
+    /// Characters the server does not accept in file names
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forbidden_name_chars: Option<String>,
 }
 
 /// Captcha response
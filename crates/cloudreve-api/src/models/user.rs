@@ -1,5 +1,7 @@
+use crate::boolset::{Permission, Permissions};
 use crate::models::common::PaginationResults;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::collections::HashMap;
 
 /// User model
@@ -30,6 +32,9 @@ pub struct User {
     pub disable_view_sync: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub share_links_in_profile: Option<ShareLinksInProfileLevel>,
+    /// Fields not covered above, kept as sent by the server
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 /// User group
@@ -38,11 +43,14 @@ pub struct Group {
     pub id: String,
     pub name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub permission: Option<String>,
+    pub permission: Option<GroupPermissions>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub direct_link_batch_size: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub trash_retention: Option<i32>,
+    /// Fields not covered above, kept as sent by the server
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 /// Pinned file
@@ -62,6 +70,9 @@ pub enum ShareLinksInProfileLevel {
     PublicShareOnly,
     AllShare,
     HideShare,
+    /// A level this client does not know
+    #[serde(other)]
+    Unknown,
 }
 
 /// Authentication token
@@ -113,6 +124,24 @@ pub struct RefreshTokenRequest {
     pub refresh_token: String,
 }
 
+/// Request from the server to unlink this device, sent when the session was
+/// revoked with a wipe
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct WipeRequest {
+    /// Passed back when reporting the wipe as done
+    #[serde(default)]
+    pub id: String,
+}
+
+/// Report that a requested wipe was carried out
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WipeCompletedService {
+    pub id: String,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
 /// User capacity information
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Capacity {
@@ -187,6 +216,9 @@ pub enum OpenIDProvider {
     QQ = 1,
     #[serde(rename = "2")]
     OIDC = 2,
+    /// A provider this client does not know
+    #[serde(other)]
+    Unknown,
 }
 
 /// OpenID information
@@ -283,24 +315,34 @@ pub struct ResetPasswordService {
     pub secret: String,
 }
 
-/// Group permissions constants
-pub mod group_permission {
-    pub const IS_ADMIN: i32 = 0;
-    pub const IS_ANONYMOUS: i32 = 1;
-    pub const SHARE: i32 = 2;
-    pub const WEBDAV: i32 = 3;
-    pub const ARCHIVE_DOWNLOAD: i32 = 4;
-    pub const ARCHIVE_TASK: i32 = 5;
-    pub const WEBDAV_PROXY: i32 = 6;
-    pub const SHARE_DOWNLOAD: i32 = 7;
-    pub const SHARE_FREE: i32 = 8;
-    pub const REMOTE_DOWNLOAD: i32 = 9;
-    pub const RELOCATE: i32 = 10;
-    pub const REDIRECTED_SOURCE: i32 = 11;
-    pub const ADVANCE_DELETE: i32 = 12;
-    pub const SELECT_NODE: i32 = 13;
-    pub const SET_ANONYMOUS_PERMISSION: i32 = 14;
-    pub const SET_EXPLICIT_USER_PERMISSION: i32 = 15;
-    pub const IGNORE_FILE_PERMISSION: i32 = 16;
-    pub const UNIQUE_DIRECT_LINK: i32 = 17;
+/// Permissions granted to a user group
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupPermission {
+    IsAdmin = 0,
+    IsAnonymous = 1,
+    Share = 2,
+    Webdav = 3,
+    ArchiveDownload = 4,
+    ArchiveTask = 5,
+    WebdavProxy = 6,
+    ShareDownload = 7,
+    ShareFree = 8,
+    RemoteDownload = 9,
+    Relocate = 10,
+    RedirectedSource = 11,
+    AdvanceDelete = 12,
+    SelectNode = 13,
+    SetAnonymousPermission = 14,
+    SetExplicitUserPermission = 15,
+    IgnoreFilePermission = 16,
+    UniqueDirectLink = 17,
 }
+
+impl Permission for GroupPermission {
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Permissions of a user group
+pub type GroupPermissions = Permissions<GroupPermission>;
@@ -0,0 +1,82 @@
+//! Address family selection and DNS overrides for reaching the server.
+//!
+//! Some self-hosted servers publish both A and AAAA records while only one of
+//! them actually works. The connector already races the two families (happy
+//! eyeballs) and tries the family of the first resolved address first, so the
+//! strategy only needs to reorder the resolved addresses.
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::{IpAddr, SocketAddr};
+
+/// Which address family to try first when a host has both A and AAAA records
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IpStrategy {
+    /// Keep the system resolver order, falling back to the other family if
+    /// connecting stalls
+    #[default]
+    Auto,
+    /// Try IPv4 first, then IPv6
+    PreferIpv4,
+    /// Try IPv6 first, then IPv4
+    PreferIpv6,
+}
+
+/// Address family of a connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressFamily {
+    Ipv4,
+    Ipv6,
+}
+
+impl AddressFamily {
+    pub fn of(addr: &IpAddr) -> Self {
+        match addr {
+            IpAddr::V4(_) => AddressFamily::Ipv4,
+            IpAddr::V6(v6) if v6.to_ipv4_mapped().is_some() => AddressFamily::Ipv4,
+            IpAddr::V6(_) => AddressFamily::Ipv6,
+        }
+    }
+}
+
+/// Resolver applying an [`IpStrategy`] and static host overrides
+pub(crate) struct StrategyResolver {
+    pub(crate) strategy: IpStrategy,
+    /// Host name (lowercase) to the addresses it should resolve to
+    pub(crate) overrides: BTreeMap<String, Vec<IpAddr>>,
+}
+
+/// Stable-sort addresses so the preferred family comes first
+fn order_by_strategy(strategy: IpStrategy, mut addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let preferred = match strategy {
+        IpStrategy::Auto => return addrs,
+        IpStrategy::PreferIpv4 => AddressFamily::Ipv4,
+        IpStrategy::PreferIpv6 => AddressFamily::Ipv6,
+    };
+    addrs.sort_by_key(|addr| AddressFamily::of(&addr.ip()) != preferred);
+    addrs
+}
+
+impl Resolve for StrategyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_ascii_lowercase();
+
+        // Port 0 is replaced with the port of the request URL by the connector
+        if let Some(ips) = self.overrides.get(&host).filter(|ips| !ips.is_empty()) {
+            let addrs = ips.iter().map(|ip| SocketAddr::new(*ip, 0)).collect();
+            let addrs = order_by_strategy(self.strategy, addrs);
+            return Box::pin(async move { Ok(Box::new(addrs.into_iter()) as Addrs) });
+        }
+
+        let strategy = self.strategy;
+        Box::pin(async move {
+            let resolved: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .collect();
+            Ok(Box::new(order_by_strategy(strategy, resolved).into_iter()) as Addrs)
+        })
+    }
+}
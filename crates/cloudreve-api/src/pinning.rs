@@ -0,0 +1,364 @@
+//! Certificate pinning for servers that must not be impersonated.
+//!
+//! A client configured with pins only connects to the pinned host if its
+//! certificate, or the public key in it, hashes to one of them, even when a
+//! proxy inspecting TLS presents one from a locally trusted root. A matching
+//! pin stands in for chain validation, so servers with a self-signed or
+//! private CA certificate can be pinned too. Other hosts the client reaches,
+//! like storage providers, are validated against the Mozilla and system
+//! roots as usual. Pinning the public key survives certificate renewals that
+//! keep the key; pinning the certificate does not.
+
+use crate::client::ClientConfig;
+use crate::error::{ApiError, ApiResult};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::tls::TlsInfo;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::crypto::ring;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::server::ParsedCertificate;
+use rustls::{CertificateError, DigitallySignedStruct, OtherError, RootCertStore, SignatureScheme};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+use thiserror::Error;
+use url::Url;
+
+/// What part of the server certificate a pin hashes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PinKind {
+    /// The whole certificate, changes with every renewal
+    Certificate,
+    /// The subject public key info, kept by renewals reusing the key
+    PublicKey,
+}
+
+/// SHA-256 hash of a server certificate or of its public key
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TlsPin {
+    pub kind: PinKind,
+    /// Base64 encoded hash
+    pub sha256: String,
+}
+
+impl TlsPin {
+    /// Pin of the given kind for a DER encoded certificate. `None` if the
+    /// public key can't be read from it.
+    pub fn of_certificate(kind: PinKind, der: &[u8]) -> Option<Self> {
+        let hash = match kind {
+            PinKind::Certificate => Sha256::digest(der),
+            PinKind::PublicKey => {
+                let der = CertificateDer::from(der);
+                let certificate = ParsedCertificate::try_from(&der).ok()?;
+                Sha256::digest(certificate.subject_public_key_info().as_ref())
+            }
+        };
+        Some(Self {
+            kind,
+            sha256: STANDARD.encode(hash),
+        })
+    }
+
+    /// Whether a DER encoded certificate matches this pin
+    pub fn matches(&self, der: &[u8]) -> bool {
+        Self::of_certificate(self.kind, der).is_some_and(|pin| pin.sha256 == self.sha256)
+    }
+}
+
+impl fmt::Display for TlsPin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            PinKind::Certificate => write!(f, "cert-sha256/{}", self.sha256),
+            PinKind::PublicKey => write!(f, "sha256/{}", self.sha256),
+        }
+    }
+}
+
+/// The server presented a certificate matching none of its pins
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("Certificate of {host} does not match its pin, presented {}", presented.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+pub struct PinMismatch {
+    pub host: String,
+    /// Pins of the presented certificate, of the kinds pinned
+    pub presented: Vec<TlsPin>,
+}
+
+impl PinMismatch {
+    /// Find a pin mismatch in the causes of `error`. TLS errors reach
+    /// reqwest wrapped in I/O errors, which hide them from `source()`.
+    pub(crate) fn find<'a>(error: &'a (dyn std::error::Error + 'static)) -> Option<&'a Self> {
+        let mut current = Some(error);
+        while let Some(error) = current {
+            if let Some(mismatch) = error.downcast_ref::<Self>() {
+                return Some(mismatch);
+            }
+            if let Some(rustls::Error::InvalidCertificate(CertificateError::Other(other))) =
+                error.downcast_ref::<rustls::Error>()
+            {
+                if let Some(mismatch) = other.0.downcast_ref::<Self>() {
+                    return Some(mismatch);
+                }
+            }
+            if let Some(inner) = error
+                .downcast_ref::<std::io::Error>()
+                .and_then(|io| io.get_ref())
+            {
+                if let Some(mismatch) = Self::find(inner) {
+                    return Some(mismatch);
+                }
+            }
+            current = error.source();
+        }
+        None
+    }
+}
+
+/// Requires a pinned end-entity certificate from the pinned host, and
+/// validates the certificate chain as usual for other hosts
+#[derive(Debug)]
+struct PinnedVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    /// Host name or IP address the pins apply to
+    host: String,
+    pins: Vec<TlsPin>,
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let validated = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        );
+        if !server_name.to_str().eq_ignore_ascii_case(&self.host) {
+            return validated;
+        }
+        if self.pins.iter().any(|pin| pin.matches(end_entity)) {
+            if let Err(e) = validated {
+                tracing::debug!(target: "api::tls", host = %self.host, error = %e, "Accepting pinned certificate not trusted otherwise");
+            }
+            return Ok(ServerCertVerified::assertion());
+        }
+
+        let mut kinds = Vec::new();
+        for pin in &self.pins {
+            if !kinds.contains(&pin.kind) {
+                kinds.push(pin.kind);
+            }
+        }
+        let mismatch = PinMismatch {
+            host: server_name.to_str().into_owned(),
+            presented: kinds
+                .into_iter()
+                .filter_map(|kind| TlsPin::of_certificate(kind, end_entity))
+                .collect(),
+        };
+        tracing::error!(target: "api::tls", error = %mismatch, "Server certificate does not match its pin");
+        Err(rustls::Error::InvalidCertificate(CertificateError::Other(
+            OtherError(Arc::new(mismatch)),
+        )))
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+/// Mozilla roots and the roots trusted by the system, loaded once
+fn root_store() -> Arc<RootCertStore> {
+    static ROOTS: OnceLock<Arc<RootCertStore>> = OnceLock::new();
+    ROOTS
+        .get_or_init(|| {
+            let mut roots = RootCertStore {
+                roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+            };
+            let native = rustls_native_certs::load_native_certs();
+            for error in &native.errors {
+                tracing::warn!(target: "api::tls", error = %error, "Failed to load system root certificates");
+            }
+            let (added, ignored) = roots.add_parsable_certificates(native.certs);
+            tracing::debug!(target: "api::tls", added, ignored, "Loaded system root certificates");
+            Arc::new(roots)
+        })
+        .clone()
+}
+
+/// TLS configuration accepting `host` only if it matches one of `pins`
+pub(crate) fn pinned_tls_config(pins: &[TlsPin], host: &str, http2: bool) -> rustls::ClientConfig {
+    let provider = Arc::new(ring::default_provider());
+    let inner = WebPkiServerVerifier::builder_with_provider(root_store(), provider.clone())
+        .build()
+        .expect("Mozilla root store is not empty");
+    let mut config = rustls::ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .expect("ring supports the default protocol versions")
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(PinnedVerifier {
+            inner,
+            host: host.to_string(),
+            pins: pins.to_vec(),
+        }))
+        .with_no_client_auth();
+    // reqwest leaves protocol negotiation to a preconfigured TLS config
+    config.alpn_protocols = if http2 {
+        vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+    } else {
+        vec![b"http/1.1".to_vec()]
+    };
+    config
+}
+
+/// Pin of the certificate the server at `config.base_url` presents now, for
+/// trusting it on first use. The certificate is taken as presented, also if
+/// it is self-signed, so the pin should be confirmed by the user.
+pub async fn fetch_server_pin(config: &ClientConfig, kind: PinKind) -> ApiResult<TlsPin> {
+    let url = Url::parse(&config.base_url)?;
+    if url.scheme() != "https" {
+        return Err(ApiError::Other(format!(
+            "Only HTTPS servers can be pinned: {}",
+            config.base_url
+        )));
+    }
+    let http_client = config
+        .http_client_builder()
+        .danger_accept_invalid_certs(true)
+        .tls_info(true)
+        .build()?;
+    let response = http_client.get(url).send().await?;
+    let certificate = response
+        .extensions()
+        .get::<TlsInfo>()
+        .and_then(|info| info.peer_certificate())
+        .ok_or_else(|| ApiError::Other("Server presented no certificate".to_string()))?;
+    let pin = TlsPin::of_certificate(kind, certificate)
+        .ok_or_else(|| ApiError::Other("Failed to read server certificate".to_string()))?;
+    tracing::info!(target: "api::tls", url = %config.base_url, pin = %pin, "Fetched server pin");
+    Ok(pin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Self-signed certificate for `cloud.example.com`
+    const CERTIFICATE: &str = "MIIBkDCCATWgAwIBAgIUXQbGe+j5vuMPXWHUzRgRBP5HGbEwCgYIKoZIzj0EAwIwHDEaMBgGA1UEAwwRY2xvdWQuZXhhbXBsZS5jb20wIBcNMjYxMDE3MDIwODU0WhgPMjEyNjA5MjMwMjA4NTRaMBwxGjAYBgNVBAMMEWNsb3VkLmV4YW1wbGUuY29tMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEl4FfNLaczaCVaKbDNmG1WwqJjQhPJKLHI0FXI+gXyrhYQm5Liubmyn8NDQ8IL7LOprAoMNTfwmtI6kAtbLH9naNTMFEwHQYDVR0OBBYEFKIiwodZEj/J1sPCjY5U5vENsMZ6MB8GA1UdIwQYMBaAFKIiwodZEj/J1sPCjY5U5vENsMZ6MA8GA1UdEwEB/wQFMAMBAf8wCgYIKoZIzj0EAwIDSQAwRgIhAIm366G0eHTcc652PFAQaZQjNC63qPXmxIaovgoxKHOQAiEAyNM+lThTI2T6o1sQNzWiBLu0m7kd9cYt5JM98/BC9ss=";
+    /// Its hashes, as printed by `openssl dgst -sha256 -binary | base64`
+    const CERTIFICATE_SHA256: &str = "sosuA0oRPcUndM1vXTXxRJfaNUgpF6QHQ3EhBpJlwJY=";
+    const PUBLIC_KEY_SHA256: &str = "Ug9uyCQT4RDqP+/DxueHNpVMVa3RnJq1n+0o1U6sqyk=";
+
+    fn certificate() -> Vec<u8> {
+        STANDARD.decode(CERTIFICATE).unwrap()
+    }
+
+    fn pin(kind: PinKind, sha256: &str) -> TlsPin {
+        TlsPin {
+            kind,
+            sha256: sha256.to_string(),
+        }
+    }
+
+    fn verifier(pins: Vec<TlsPin>) -> PinnedVerifier {
+        let provider = Arc::new(ring::default_provider());
+        PinnedVerifier {
+            inner: WebPkiServerVerifier::builder_with_provider(root_store(), provider)
+                .build()
+                .unwrap(),
+            host: "cloud.example.com".to_string(),
+            pins,
+        }
+    }
+
+    fn verify(verifier: &PinnedVerifier, host: &'static str) -> Result<(), rustls::Error> {
+        let der = CertificateDer::from(certificate());
+        verifier
+            .verify_server_cert(
+                &der,
+                &[],
+                &ServerName::try_from(host).unwrap(),
+                &[],
+                UnixTime::now(),
+            )
+            .map(|_| ())
+    }
+
+    #[test]
+    fn pins_hash_the_certificate_or_its_public_key() {
+        let der = certificate();
+        assert_eq!(
+            TlsPin::of_certificate(PinKind::Certificate, &der),
+            Some(pin(PinKind::Certificate, CERTIFICATE_SHA256))
+        );
+        assert_eq!(
+            TlsPin::of_certificate(PinKind::PublicKey, &der),
+            Some(pin(PinKind::PublicKey, PUBLIC_KEY_SHA256))
+        );
+        assert_eq!(TlsPin::of_certificate(PinKind::PublicKey, b"garbage"), None);
+    }
+
+    #[test]
+    fn pins_match_only_their_certificate() {
+        let der = certificate();
+        assert!(pin(PinKind::Certificate, CERTIFICATE_SHA256).matches(&der));
+        assert!(pin(PinKind::PublicKey, PUBLIC_KEY_SHA256).matches(&der));
+        // Same hash, but of the other part of the certificate
+        assert!(!pin(PinKind::PublicKey, CERTIFICATE_SHA256).matches(&der));
+        assert!(!pin(PinKind::PublicKey, PUBLIC_KEY_SHA256).matches(b"garbage"));
+    }
+
+    #[test]
+    fn a_matching_pin_trusts_a_self_signed_certificate() {
+        let pinned = verifier(vec![pin(PinKind::PublicKey, PUBLIC_KEY_SHA256)]);
+        assert!(verify(&pinned, "cloud.example.com").is_ok());
+        assert!(verify(&pinned, "CLOUD.example.com").is_ok());
+        // Other hosts are validated as usual
+        assert!(verify(&pinned, "storage.example.com").is_err());
+    }
+
+    #[test]
+    fn mismatches_are_found_through_io_errors() {
+        let pinned = verifier(vec![pin(PinKind::Certificate, PUBLIC_KEY_SHA256)]);
+        let error = verify(&pinned, "cloud.example.com").unwrap_err();
+        // The way reqwest receives TLS errors from the connection
+        let error = std::io::Error::new(std::io::ErrorKind::InvalidData, error);
+
+        let mismatch = PinMismatch::find(&error).unwrap();
+        assert_eq!(mismatch.host, "cloud.example.com");
+        assert_eq!(
+            mismatch.presented,
+            vec![pin(PinKind::Certificate, CERTIFICATE_SHA256)]
+        );
+
+        let unrelated = std::io::Error::new(std::io::ErrorKind::InvalidData, "bad record");
+        assert!(PinMismatch::find(&unrelated).is_none());
+    }
+}
@@ -0,0 +1,72 @@
+//! Callbacks delivering events to the embedding application.
+
+use std::ffi::{c_char, c_void, CString};
+
+use cloudreve_sync::Event;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+/// Called with an event serialized as JSON, and the `user_data` given when
+/// subscribing. The JSON string is only valid during the call.
+pub type CloudreveEventCallback = extern "C" fn(event_json: *const c_char, user_data: *mut c_void);
+
+/// A callback with its user data, called from the engine's threads
+pub(crate) struct Callback {
+    function: CloudreveEventCallback,
+    user_data: *mut c_void,
+}
+
+// The caller guarantees that `user_data` can be used from any thread, as
+// documented on the subscribe functions
+unsafe impl Send for Callback {}
+
+impl Callback {
+    pub(crate) fn new(function: CloudreveEventCallback, user_data: *mut c_void) -> Self {
+        Self {
+            function,
+            user_data,
+        }
+    }
+
+    fn call(&self, event: &Event) {
+        let json = match serde_json::to_string(event) {
+            Ok(json) => json,
+            Err(e) => {
+                tracing::warn!(target: "ffi", event = event.name(), error = %e, "Failed to serialize event");
+                return;
+            }
+        };
+        match CString::new(json) {
+            Ok(json) => (self.function)(json.as_ptr(), self.user_data),
+            Err(e) => {
+                tracing::warn!(target: "ffi", event = event.name(), error = %e, "Event JSON contains a nul byte")
+            }
+        }
+    }
+}
+
+/// Call `callback` with the `pending` events, then with each event of
+/// `receiver` that `filter` keeps, until the task is aborted
+pub(crate) fn spawn_delivery(
+    runtime: &tokio::runtime::Runtime,
+    callback: Callback,
+    pending: Vec<Event>,
+    mut receiver: broadcast::Receiver<Event>,
+    filter: fn(&Event) -> bool,
+) -> JoinHandle<()> {
+    runtime.spawn(async move {
+        for event in &pending {
+            callback.call(event);
+        }
+        loop {
+            match receiver.recv().await {
+                Ok(event) if filter(&event) => callback.call(&event),
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    tracing::warn!(target: "ffi", skipped = n, "Event callback lagged, some events were skipped");
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    })
+}
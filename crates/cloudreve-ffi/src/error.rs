@@ -0,0 +1,114 @@
+//! Errors and strings crossing the C boundary.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+/// Returned by calls that succeeded
+pub const CLOUDREVE_OK: i32 = 0;
+/// Returned by calls that failed, see [`cloudreve_last_error`]
+pub const CLOUDREVE_ERROR: i32 = -1;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(error: &anyhow::Error) {
+    tracing::debug!(target: "ffi", error = %format!("{:#}", error), "FFI call failed");
+    let message = CString::new(format!("{:#}", error).replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Message of the last call that failed on this thread, or null. The string
+/// belongs to the library and stays valid until the next failing call on this
+/// thread.
+#[no_mangle]
+pub extern "C" fn cloudreve_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Free a string returned by the library
+///
+/// # Safety
+/// `value` must be null or a string returned by this library, not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn cloudreve_string_free(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
+
+/// Run `call`, turning an error or a panic into `fallback` and the last error
+pub(crate) fn guard<T>(fallback: T, call: impl FnOnce() -> Result<T>) -> T {
+    let result = catch_unwind(AssertUnwindSafe(call))
+        .unwrap_or_else(|_| Err(anyhow!("Panic in the sync engine")));
+    match result {
+        Ok(value) => value,
+        Err(e) => {
+            set_last_error(&e);
+            fallback
+        }
+    }
+}
+
+/// Read a UTF-8 string passed by the caller
+///
+/// # Safety
+/// `value` must be null or a nul-terminated string.
+pub(crate) unsafe fn read_str<'a>(value: *const c_char, name: &str) -> Result<&'a str> {
+    if value.is_null() {
+        bail!("{} is null", name);
+    }
+    CStr::from_ptr(value)
+        .to_str()
+        .with_context(|| format!("{} is not valid UTF-8", name))
+}
+
+/// Read a string the caller may leave out by passing null
+///
+/// # Safety
+/// `value` must be null or a nul-terminated string.
+pub(crate) unsafe fn read_optional_str<'a>(
+    value: *const c_char,
+    name: &str,
+) -> Result<Option<&'a str>> {
+    if value.is_null() {
+        return Ok(None);
+    }
+    read_str(value, name).map(Some)
+}
+
+/// Hand a string over to the caller, who frees it with [`cloudreve_string_free`]
+pub(crate) fn into_c_string(value: String) -> Result<*mut c_char> {
+    Ok(CString::new(value)
+        .context("String contains a nul byte")?
+        .into_raw())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failures_set_the_last_error() {
+        let value = guard(7, || bail!("Drive not found"));
+        assert_eq!(value, 7);
+        let message = unsafe { CStr::from_ptr(cloudreve_last_error()) };
+        assert_eq!(message.to_str().unwrap(), "Drive not found");
+
+        let value = guard(0, || -> Result<i32> { panic!("boom") });
+        assert_eq!(value, 0);
+        let message = unsafe { CStr::from_ptr(cloudreve_last_error()) };
+        assert_eq!(message.to_str().unwrap(), "Panic in the sync engine");
+
+        let missing = unsafe { read_str(ptr::null(), "drive_id") };
+        assert_eq!(missing.unwrap_err().to_string(), "drive_id is null");
+    }
+}
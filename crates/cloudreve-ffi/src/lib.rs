@@ -0,0 +1,423 @@
+//! C ABI over the [`SyncEngine`] facade, so shells written in Swift, Kotlin,
+//! C# or C can reuse the sync core.
+//!
+//! Values are passed as nul-terminated UTF-8 strings, structured ones as
+//! JSON in the shape of their serde representation in `cloudreve-sync`.
+//! Strings returned by the library are freed with [`cloudreve_string_free`].
+//! Calls returning an `int32_t` return [`CLOUDREVE_OK`] or
+//! [`CLOUDREVE_ERROR`], calls returning a pointer return null on failure, and
+//! [`cloudreve_last_error`] then describes what went wrong.
+//!
+//! Each engine owns the runtime it runs on. Calls block until done and may be
+//! made from any thread, except from a callback: callbacks run on the
+//! runtime's threads, and hand events over to another thread before calling
+//! the engine again.
+//!
+//! The C header is generated with cbindgen, see `cbindgen.toml`.
+
+mod callbacks;
+mod error;
+
+use std::collections::HashMap;
+use std::ffi::{c_char, c_void};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use cloudreve_sync::sdk::{EngineOptions, SyncEngine};
+use cloudreve_sync::{DriveConfig, Event};
+use tokio::runtime::Runtime;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+pub use callbacks::CloudreveEventCallback;
+pub use error::{cloudreve_last_error, cloudreve_string_free, CLOUDREVE_ERROR, CLOUDREVE_OK};
+
+use callbacks::{spawn_delivery, Callback};
+use error::{guard, into_c_string, read_optional_str, read_str};
+
+/// Time given to running tasks when an engine is freed
+const RUNTIME_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// An engine and the runtime it runs on, opaque to callers
+pub struct CloudreveEngine {
+    runtime: Runtime,
+    engine: SyncEngine,
+    subscriptions: Mutex<HashMap<u64, JoinHandle<()>>>,
+    next_subscription: AtomicU64,
+}
+
+impl CloudreveEngine {
+    fn subscribe(
+        &self,
+        callback: Callback,
+        pending: Vec<Event>,
+        receiver: broadcast::Receiver<Event>,
+        filter: fn(&Event) -> bool,
+    ) -> u64 {
+        let handle = spawn_delivery(&self.runtime, callback, pending, receiver, filter);
+        let id = self.next_subscription.fetch_add(1, Ordering::Relaxed);
+        self.subscriptions.lock().unwrap().insert(id, handle);
+        id
+    }
+}
+
+/// Borrow the engine behind a pointer given by the caller
+///
+/// # Safety
+/// `engine` must be null or returned by [`cloudreve_engine_new`] and not freed.
+unsafe fn engine_ref<'a>(engine: *const CloudreveEngine) -> Result<&'a CloudreveEngine> {
+    if engine.is_null() {
+        bail!("engine is null");
+    }
+    Ok(&*engine)
+}
+
+/// Create an engine. `options_json` is null or an
+/// [`EngineOptions`] object, fields left out keep their default. Returns
+/// null on failure.
+///
+/// # Safety
+/// `options_json` must be null or a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn cloudreve_engine_new(options_json: *const c_char) -> *mut CloudreveEngine {
+    guard(std::ptr::null_mut(), || {
+        let options: EngineOptions = match read_optional_str(options_json, "options_json")? {
+            Some(json) => serde_json::from_str(json).context("Invalid engine options")?,
+            None => EngineOptions::default(),
+        };
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .thread_name("cloudreve-sync")
+            .build()
+            .context("Failed to create runtime")?;
+        let engine = {
+            let _runtime = runtime.enter();
+            SyncEngine::new(options)?
+        };
+        tracing::info!(target: "ffi", "Sync engine created");
+        Ok(Box::into_raw(Box::new(CloudreveEngine {
+            runtime,
+            engine,
+            subscriptions: Mutex::new(HashMap::new()),
+            next_subscription: AtomicU64::new(1),
+        })))
+    })
+}
+
+/// Start the engine's background tasks and mount the saved drives
+///
+/// # Safety
+/// `engine` must be returned by [`cloudreve_engine_new`] and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn cloudreve_engine_start(engine: *const CloudreveEngine) -> i32 {
+    guard(CLOUDREVE_ERROR, || {
+        let engine = engine_ref(engine)?;
+        engine.runtime.block_on(engine.engine.start())?;
+        Ok(CLOUDREVE_OK)
+    })
+}
+
+/// Stop syncing every drive and save their configurations. The engine still
+/// has to be freed with [`cloudreve_engine_free`].
+///
+/// # Safety
+/// `engine` must be returned by [`cloudreve_engine_new`] and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn cloudreve_engine_shutdown(engine: *const CloudreveEngine) -> i32 {
+    guard(CLOUDREVE_ERROR, || {
+        let engine = engine_ref(engine)?;
+        engine.runtime.block_on(engine.engine.shutdown())?;
+        Ok(CLOUDREVE_OK)
+    })
+}
+
+/// Free an engine, ending its subscriptions like
+/// [`cloudreve_engine_unsubscribe`]. Does nothing if `engine` is null.
+///
+/// # Safety
+/// `engine` must be null or returned by [`cloudreve_engine_new`] and not
+/// freed, and no other call may use it meanwhile.
+#[no_mangle]
+pub unsafe extern "C" fn cloudreve_engine_free(engine: *mut CloudreveEngine) {
+    if engine.is_null() {
+        return;
+    }
+    guard((), || {
+        let CloudreveEngine {
+            runtime,
+            engine,
+            subscriptions,
+            ..
+        } = *Box::from_raw(engine);
+        let handles: Vec<_> = subscriptions
+            .into_inner()
+            .unwrap_or_default()
+            .into_values()
+            .collect();
+        for handle in &handles {
+            handle.abort();
+        }
+        runtime.block_on(async {
+            for handle in handles {
+                let _ = handle.await;
+            }
+        });
+        {
+            let _runtime = runtime.enter();
+            drop(engine);
+        }
+        runtime.shutdown_timeout(RUNTIME_SHUTDOWN_TIMEOUT);
+        tracing::info!(target: "ffi", "Sync engine freed");
+        Ok(())
+    })
+}
+
+/// Add a drive from a `DriveConfig` object, mount it and save it. Returns
+/// the id of the drive, or null on failure.
+///
+/// # Safety
+/// `engine` must be returned by [`cloudreve_engine_new`] and not freed,
+/// `config_json` must be a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn cloudreve_engine_add_drive(
+    engine: *const CloudreveEngine,
+    config_json: *const c_char,
+) -> *mut c_char {
+    guard(std::ptr::null_mut(), || {
+        let engine = engine_ref(engine)?;
+        let config: DriveConfig = serde_json::from_str(read_str(config_json, "config_json")?)
+            .context("Invalid drive configuration")?;
+        let id = engine.runtime.block_on(engine.engine.add_drive(config))?;
+        into_c_string(id)
+    })
+}
+
+/// Remove a drive and save the change. Returns 1 if it existed, 0 if not.
+///
+/// # Safety
+/// `engine` must be returned by [`cloudreve_engine_new`] and not freed,
+/// `drive_id` must be a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn cloudreve_engine_remove_drive(
+    engine: *const CloudreveEngine,
+    drive_id: *const c_char,
+) -> i32 {
+    guard(CLOUDREVE_ERROR, || {
+        let engine = engine_ref(engine)?;
+        let drive_id = read_str(drive_id, "drive_id")?;
+        let removed = engine
+            .runtime
+            .block_on(engine.engine.remove_drive(drive_id))?;
+        Ok(i32::from(removed))
+    })
+}
+
+/// The drives, as a JSON array of `DriveConfig` objects
+///
+/// # Safety
+/// `engine` must be returned by [`cloudreve_engine_new`] and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn cloudreve_engine_list_drives(
+    engine: *const CloudreveEngine,
+) -> *mut c_char {
+    guard(std::ptr::null_mut(), || {
+        let engine = engine_ref(engine)?;
+        let drives = engine.runtime.block_on(engine.engine.list_drives());
+        into_c_string(serde_json::to_string(&drives)?)
+    })
+}
+
+/// Stop syncing a drive, keeping it registered
+///
+/// # Safety
+/// `engine` must be returned by [`cloudreve_engine_new`] and not freed,
+/// `drive_id` must be a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn cloudreve_engine_pause_drive(
+    engine: *const CloudreveEngine,
+    drive_id: *const c_char,
+) -> i32 {
+    guard(CLOUDREVE_ERROR, || {
+        let engine = engine_ref(engine)?;
+        let drive_id = read_str(drive_id, "drive_id")?;
+        engine
+            .runtime
+            .block_on(engine.engine.pause_drive(drive_id))?;
+        Ok(CLOUDREVE_OK)
+    })
+}
+
+/// Sync a drive paused with [`cloudreve_engine_pause_drive`] again
+///
+/// # Safety
+/// `engine` must be returned by [`cloudreve_engine_new`] and not freed,
+/// `drive_id` must be a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn cloudreve_engine_resume_drive(
+    engine: *const CloudreveEngine,
+    drive_id: *const c_char,
+) -> i32 {
+    guard(CLOUDREVE_ERROR, || {
+        let engine = engine_ref(engine)?;
+        let drive_id = read_str(drive_id, "drive_id")?;
+        engine
+            .runtime
+            .block_on(engine.engine.resume_drive(drive_id))?;
+        Ok(CLOUDREVE_OK)
+    })
+}
+
+/// Drives, active and recent tasks as a JSON `StatusSummary` object, of the
+/// drive `drive_id` or of all of them if it is null
+///
+/// # Safety
+/// `engine` must be returned by [`cloudreve_engine_new`] and not freed,
+/// `drive_id` must be null or a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn cloudreve_engine_query_status(
+    engine: *const CloudreveEngine,
+    drive_id: *const c_char,
+) -> *mut c_char {
+    guard(std::ptr::null_mut(), || {
+        let engine = engine_ref(engine)?;
+        let drive_id = read_optional_str(drive_id, "drive_id")?;
+        let status = engine
+            .runtime
+            .block_on(engine.engine.query_status(drive_id))?;
+        into_c_string(serde_json::to_string(&status)?)
+    })
+}
+
+/// Cancel a pending or running task of any drive
+///
+/// # Safety
+/// `engine` must be returned by [`cloudreve_engine_new`] and not freed,
+/// `task_id` must be a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn cloudreve_engine_cancel_task(
+    engine: *const CloudreveEngine,
+    task_id: *const c_char,
+) -> i32 {
+    guard(CLOUDREVE_ERROR, || {
+        let engine = engine_ref(engine)?;
+        let task_id = read_str(task_id, "task_id")?;
+        engine
+            .runtime
+            .block_on(engine.engine.cancel_task(task_id))?;
+        Ok(CLOUDREVE_OK)
+    })
+}
+
+/// Call `callback` with every event, starting with the critical events not
+/// acknowledged yet. Returns the id of the subscription, or 0 on failure.
+///
+/// # Safety
+/// `engine` must be returned by [`cloudreve_engine_new`] and not freed.
+/// `user_data` must stay valid, and usable from any thread, until the
+/// subscription ends.
+#[no_mangle]
+pub unsafe extern "C" fn cloudreve_engine_subscribe_events(
+    engine: *const CloudreveEngine,
+    callback: CloudreveEventCallback,
+    user_data: *mut c_void,
+) -> u64 {
+    guard(0, || {
+        let engine = engine_ref(engine)?;
+        let (pending, receiver) = engine.engine.subscribe_events()?;
+        let callback = Callback::new(callback, user_data);
+        Ok(engine.subscribe(callback, pending, receiver, |_| true))
+    })
+}
+
+/// Call `callback` with the `TaskProgress` events only. Returns the id of the
+/// subscription, or 0 on failure.
+///
+/// # Safety
+/// Same as [`cloudreve_engine_subscribe_events`].
+#[no_mangle]
+pub unsafe extern "C" fn cloudreve_engine_subscribe_progress(
+    engine: *const CloudreveEngine,
+    callback: CloudreveEventCallback,
+    user_data: *mut c_void,
+) -> u64 {
+    guard(0, || {
+        let engine = engine_ref(engine)?;
+        let receiver = engine.engine.event_broadcaster().subscribe();
+        let callback = Callback::new(callback, user_data);
+        Ok(engine.subscribe(callback, Vec::new(), receiver, |event| {
+            matches!(event, Event::TaskProgress { .. })
+        }))
+    })
+}
+
+/// End a subscription. Waits for a callback call in progress, so once this
+/// returns the callback is not running and is not called again, and its
+/// `user_data` may be freed. Fails if called from the callback itself.
+///
+/// # Safety
+/// `engine` must be returned by [`cloudreve_engine_new`] and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn cloudreve_engine_unsubscribe(
+    engine: *const CloudreveEngine,
+    subscription: u64,
+) -> i32 {
+    guard(CLOUDREVE_ERROR, || {
+        let engine = engine_ref(engine)?;
+        // A callback waiting for its own delivery to end would never return
+        if tokio::runtime::Handle::try_current().is_ok() {
+            bail!("Can't unsubscribe from within a callback");
+        }
+        let Some(handle) = engine.subscriptions.lock().unwrap().remove(&subscription) else {
+            bail!("No subscription {}", subscription);
+        };
+        handle.abort();
+        // Aborting takes effect once a call in progress returns
+        let _ = engine.runtime.block_on(handle);
+        Ok(CLOUDREVE_OK)
+    })
+}
+
+/// Acknowledge a critical event so it is not delivered again. Returns 1 if
+/// it was pending, 0 if not.
+///
+/// # Safety
+/// `engine` must be returned by [`cloudreve_engine_new`] and not freed,
+/// `event_id` must be a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn cloudreve_engine_acknowledge_event(
+    engine: *const CloudreveEngine,
+    event_id: *const c_char,
+) -> i32 {
+    guard(CLOUDREVE_ERROR, || {
+        let engine = engine_ref(engine)?;
+        let event_id = read_str(event_id, "event_id")?;
+        let pending = engine.engine.acknowledge_event(event_id)?;
+        Ok(i32::from(pending))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::{CStr, CString};
+
+    #[test]
+    fn invalid_options_are_reported() {
+        let options = CString::new(r#"{"event_capacity": "many"}"#).unwrap();
+        let engine = unsafe { cloudreve_engine_new(options.as_ptr()) };
+        assert!(engine.is_null());
+        let message = unsafe { CStr::from_ptr(cloudreve_last_error()) };
+        assert!(message
+            .to_str()
+            .unwrap()
+            .starts_with("Invalid engine options"));
+
+        assert_eq!(
+            unsafe { cloudreve_engine_start(std::ptr::null()) },
+            CLOUDREVE_ERROR
+        );
+        unsafe { cloudreve_engine_free(std::ptr::null_mut()) };
+    }
+}
@@ -0,0 +1,142 @@
+//! Random local changes on a small placeholder tree, each followed by what
+//! the engine does about it, checking that the decisions settle.
+
+#![no_main]
+
+use std::path::{Path, PathBuf};
+
+use arbitrary::Arbitrary;
+use cloudreve_sync_core::vfs::{
+    FakeVirtualFs, ModifyAction, PinState, VirtualFs, unpopulated_parents,
+};
+use futures::executor::block_on;
+use libfuzzer_sys::fuzz_target;
+
+const FOLDERS: [&str; 3] = ["/drive", "/drive/a", "/drive/a/b"];
+const FILES: [&str; 4] = ["/drive/x", "/drive/a/y", "/drive/a/b/z", "/drive/c/new"];
+
+#[derive(Debug, Arbitrary)]
+enum Pin {
+    Unspecified,
+    Pinned,
+    Unpinned,
+    Excluded,
+    Inherit,
+}
+
+impl From<Pin> for PinState {
+    fn from(pin: Pin) -> Self {
+        match pin {
+            Pin::Unspecified => PinState::Unspecified,
+            Pin::Pinned => PinState::Pinned,
+            Pin::Unpinned => PinState::Unpinned,
+            Pin::Excluded => PinState::Excluded,
+            Pin::Inherit => PinState::Inherit,
+        }
+    }
+}
+
+#[derive(Debug, Arbitrary)]
+enum Op {
+    /// The user pins or unpins a file from Explorer
+    PinFile(u8, Pin),
+    /// The user pins or unpins a folder and everything in it
+    PinFolder(u8, Pin),
+    Write(u8, u16),
+    Remove(u8),
+    /// Files dropped into folders, which are listed first
+    Drop(Vec<u8>),
+    /// A change notification for a file, handled like the engine does
+    Modified(u8),
+}
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    unpopulated: [bool; 3],
+    sizes: [u16; 3],
+    ops: Vec<Op>,
+}
+
+fn folder(index: u8) -> &'static Path {
+    Path::new(FOLDERS[index as usize % FOLDERS.len()])
+}
+
+fn file(index: u8) -> &'static Path {
+    Path::new(FILES[index as usize % FILES.len()])
+}
+
+/// Apply the action the engine picks for `path` and check that it took
+fn handle_modified(vfs: &FakeVirtualFs, path: &Path) {
+    let action = ModifyAction::for_status(&vfs.status(path).unwrap());
+    match action {
+        ModifyAction::Hydrate => {
+            vfs.hydrate(path).unwrap();
+            assert!(!vfs.status(path).unwrap().partial_on_disk);
+        }
+        ModifyAction::Dehydrate => {
+            // Refused while the file has unsynced changes
+            if block_on(vfs.dehydrate(path)).is_ok() {
+                assert!(vfs.status(path).unwrap().in_sync);
+            }
+        }
+        ModifyAction::Upload => {
+            vfs.mark_in_sync(path, true).unwrap();
+            let status = vfs.status(path).unwrap();
+            assert!(status.in_sync);
+            assert_ne!(ModifyAction::for_status(&status), ModifyAction::Upload);
+        }
+        ModifyAction::Ignore => {}
+    }
+}
+
+fuzz_target!(|input: Input| {
+    let vfs = FakeVirtualFs::new();
+    for (i, path) in FOLDERS.iter().enumerate() {
+        if input.unpopulated[i] {
+            vfs.add_unpopulated_folder(Path::new(path));
+        } else {
+            vfs.add_folder(Path::new(path));
+        }
+    }
+    for (path, size) in FILES.iter().zip(input.sizes) {
+        vfs.add_placeholder(Path::new(path), size.into());
+    }
+
+    for op in input.ops {
+        match op {
+            Op::PinFile(index, pin) => {
+                let _ = vfs.set_pin_state(file(index), pin.into());
+            }
+            Op::PinFolder(index, pin) => {
+                let root = folder(index);
+                let pin = PinState::from(pin);
+                vfs.pin_tree(root, pin).unwrap();
+                for path in FOLDERS.iter().chain(&FILES).map(Path::new) {
+                    let status = vfs.status(path).unwrap();
+                    if status.exists && path.starts_with(root) {
+                        assert_eq!(status.pin_state, pin);
+                    }
+                }
+            }
+            Op::Write(index, size) => vfs.write(file(index), size.into()),
+            Op::Remove(index) => vfs.remove(file(index)),
+            Op::Drop(indexes) => {
+                let paths: Vec<PathBuf> = indexes
+                    .into_iter()
+                    .map(|index| file(index).to_path_buf())
+                    .collect();
+                let parents = unpopulated_parents(&vfs, &paths);
+                assert!(
+                    parents
+                        .windows(2)
+                        .all(|pair| pair[0].components().count() <= pair[1].components().count())
+                );
+                for parent in &parents {
+                    vfs.populate(parent).unwrap();
+                }
+                assert!(unpopulated_parents(&vfs, &paths).is_empty());
+            }
+            Op::Modified(index) => handle_modified(&vfs, file(index)),
+        }
+    }
+});
@@ -0,0 +1,14 @@
+//! Parts of the sync engine that don't depend on Windows.
+//!
+//! `cloudreve-sync` drives the Cloud Files API and only builds on Windows.
+//! The decisions it makes on placeholders and the fakes used to test them
+//! live here instead, so that they are tested, and fuzzed, on any OS.
+//!
+//! ## Cargo features
+//!
+//! - `testing`: [`testing::MockServer`], an in-process mock of the Cloudreve
+//!   API for integration tests
+
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod vfs;
@@ -0,0 +1,456 @@
+//! An in-process mock of the Cloudreve V4 API for integration tests.
+//!
+//! The mock keeps its state in memory and implements just enough of the API
+//! for the client and uploader: login and token refresh, listing, upload
+//! sessions with local-policy chunks, and the file events stream.
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use axum::body::Bytes;
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{HeaderValue, StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event as SseEvent, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post, put};
+use axum::{Json, Router};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{Duration, Utc};
+use cloudreve_api::models::common::PaginationResults;
+use cloudreve_api::models::explorer::{
+    FileEventData, FileResponse, ListResponse, NavigatorProps, PolicyType, StoragePolicy,
+    UploadCredential, UploadSessionRequest, file_type,
+};
+use cloudreve_api::models::user::{LoginResponse, Token, User};
+use cloudreve_api::{Client, ClientConfig};
+use futures::{StreamExt, stream};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+
+/// Version reported by `/site/ping`, new enough for every optional feature
+pub const SERVER_VERSION: &str = "4.10.0";
+/// Chunk size handed out with upload sessions
+pub const CHUNK_SIZE: i64 = 4;
+
+pub const EMAIL: &str = "user@example.com";
+pub const PASSWORD: &str = "password";
+
+/// An upload session opened on the mock server
+#[derive(Debug, Clone)]
+pub struct MockUpload {
+    pub uri: String,
+    pub size: i64,
+    /// Received chunk bodies by index
+    pub chunks: BTreeMap<usize, Vec<u8>>,
+}
+
+impl MockUpload {
+    /// Concatenated chunk bodies
+    pub fn content(&self) -> Vec<u8> {
+        self.chunks.values().flatten().copied().collect()
+    }
+}
+
+#[derive(Default)]
+struct MockData {
+    /// Files by URI of their parent folder
+    files: BTreeMap<String, Vec<FileResponse>>,
+    uploads: HashMap<String, MockUpload>,
+    /// Content of uploads that received all their chunks, by URI
+    completed: HashMap<String, Vec<u8>>,
+    next_id: u64,
+}
+
+impl MockData {
+    fn next_id(&mut self) -> String {
+        self.next_id += 1;
+        format!("f{}", self.next_id)
+    }
+}
+
+struct MockState {
+    data: Mutex<MockData>,
+    events: broadcast::Sender<Vec<FileEventData>>,
+    /// How far the clock in the `Date` header is ahead of the real one
+    clock_offset: Mutex<Duration>,
+    /// Answer every request with a maintenance page
+    maintenance: AtomicBool,
+    /// ID of the wipe every request is answered with, once the session is
+    /// revoked
+    wipe: Mutex<Option<String>>,
+    /// Wipe completion reports received
+    wipe_reports: Mutex<Vec<Value>>,
+}
+
+/// A mock Cloudreve server listening on a local port
+pub struct MockServer {
+    base_url: String,
+    state: Arc<MockState>,
+    handle: tokio::task::JoinHandle<()>,
+}
+
+impl MockServer {
+    /// Bind to an ephemeral port and start serving
+    pub async fn start() -> Self {
+        let (events, _) = broadcast::channel(64);
+        let state = Arc::new(MockState {
+            data: Mutex::new(MockData::default()),
+            events,
+            clock_offset: Mutex::new(Duration::zero()),
+            maintenance: AtomicBool::new(false),
+            wipe: Mutex::new(None),
+            wipe_reports: Mutex::new(Vec::new()),
+        });
+
+        let api = Router::new()
+            .route("/site/ping", get(ping))
+            .route("/session/login", post(login))
+            .route("/session/token/refresh", post(refresh))
+            .route("/file", get(list))
+            .route("/file/upload", put(create_upload).delete(delete_upload))
+            .route("/file/upload/:session/:index", post(upload_chunk))
+            .route("/file/events", get(file_events))
+            .route("/session/wipe", post(wipe_completed))
+            .layer(middleware::from_fn_with_state(state.clone(), wipe))
+            .layer(middleware::from_fn_with_state(state.clone(), maintenance))
+            .layer(middleware::map_response_with_state(state.clone(), set_date))
+            .with_state(state.clone());
+        let app = Router::new().nest("/api/v4", api);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        Self {
+            base_url,
+            state,
+            handle,
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Add a file or folder under the folder at `parent` and return it
+    pub fn add_entry(&self, parent: &str, name: &str, size: i64, is_folder: bool) -> FileResponse {
+        let mut data = self.state.data.lock().unwrap();
+        let now = Utc::now().to_rfc3339();
+        let file = FileResponse {
+            file_type: if is_folder {
+                file_type::FOLDER
+            } else {
+                file_type::FILE
+            },
+            id: data.next_id(),
+            name: name.to_string(),
+            created_at: now.clone(),
+            updated_at: now,
+            size,
+            path: join_uri(parent, name),
+            ..Default::default()
+        };
+        data.files
+            .entry(parent.trim_end_matches('/').to_string())
+            .or_default()
+            .push(file.clone());
+        file
+    }
+
+    /// Make the server's clock run `offset` ahead of the real one
+    pub fn set_clock_offset(&self, offset: Duration) {
+        *self.state.clock_offset.lock().unwrap() = offset;
+    }
+
+    /// Take the server down for maintenance, or bring it back
+    pub fn set_maintenance(&self, maintenance: bool) {
+        self.state.maintenance.store(maintenance, Ordering::SeqCst);
+    }
+
+    /// Revoke the session with a wipe, answering every request with 40090
+    pub fn request_wipe(&self, id: &str) {
+        *self.state.wipe.lock().unwrap() = Some(id.to_string());
+    }
+
+    /// Wipe completion reports received so far
+    pub fn wipe_reports(&self) -> Vec<Value> {
+        self.state.wipe_reports.lock().unwrap().clone()
+    }
+
+    /// Push a batch of file events to every open events stream
+    pub fn push_events(&self, events: Vec<FileEventData>) {
+        let _ = self.state.events.send(events);
+    }
+
+    /// Upload sessions that are still open
+    pub fn open_uploads(&self) -> Vec<MockUpload> {
+        let data = self.state.data.lock().unwrap();
+        data.uploads.values().cloned().collect()
+    }
+
+    /// Content of a finished upload
+    pub fn uploaded(&self, uri: &str) -> Option<Vec<u8>> {
+        let data = self.state.data.lock().unwrap();
+        data.completed.get(uri).cloned()
+    }
+
+    /// A client logged in to this server, with capabilities probed
+    pub async fn client(&self) -> Arc<Client> {
+        use cloudreve_api::api::{SiteApi, UserApi};
+
+        let client = Client::new(ClientConfig::new(self.base_url.clone()));
+        let login = client.login(EMAIL, PASSWORD).await.unwrap();
+        client.set_tokens_with_expiry(&login.token).await.unwrap();
+        client.probe_capabilities().await.unwrap();
+        Arc::new(client)
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+fn join_uri(parent: &str, name: &str) -> String {
+    format!("{}/{}", parent.trim_end_matches('/'), name)
+}
+
+/// Split a file URI into its parent folder URI and name
+fn split_uri(uri: &str) -> (&str, &str) {
+    uri.rsplit_once('/').unwrap_or(("", uri))
+}
+
+fn success<T: serde::Serialize>(data: T) -> Json<Value> {
+    Json(json!({ "code": 0, "msg": "", "data": data }))
+}
+
+fn failure(code: i32, msg: &str) -> Json<Value> {
+    Json(json!({ "code": code, "msg": msg, "data": null }))
+}
+
+/// An unsigned JWT carrying the scopes the client checks for
+fn access_token() -> String {
+    let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"none","typ":"JWT"}"#);
+    let payload = URL_SAFE_NO_PAD.encode(r#"{"sub":"1","scopes":["files"]}"#);
+    format!("{}.{}.", header, payload)
+}
+
+fn token() -> Token {
+    let now = Utc::now();
+    Token {
+        access_token: access_token(),
+        refresh_token: "refresh".to_string(),
+        access_expires: (now + Duration::hours(1)).to_rfc3339(),
+        refresh_expires: (now + Duration::days(7)).to_rfc3339(),
+    }
+}
+
+/// Send the server's clock in the `Date` header, which hyper leaves alone when
+/// it is already set
+async fn set_date(State(state): State<Arc<MockState>>, mut response: Response) -> Response {
+    let now = Utc::now() + *state.clock_offset.lock().unwrap();
+    let date = now.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+    response
+        .headers_mut()
+        .insert(header::DATE, HeaderValue::from_str(&date).unwrap());
+    response
+}
+
+/// Answer like a reverse proxy in front of a server under maintenance
+async fn maintenance(
+    State(state): State<Arc<MockState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if state.maintenance.load(Ordering::SeqCst) {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, "120")],
+            "<html><body>Down for maintenance</body></html>",
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
+
+/// Answer like a server that revoked the session with a wipe, except for the
+/// report that the wipe is done
+async fn wipe(State(state): State<Arc<MockState>>, request: Request, next: Next) -> Response {
+    let id = state.wipe.lock().unwrap().clone();
+    match id {
+        Some(id) if !request.uri().path().ends_with("/session/wipe") => Json(json!({
+            "code": 40090,
+            "msg": "Device unlinked",
+            "data": { "id": id },
+        }))
+        .into_response(),
+        _ => next.run(request).await,
+    }
+}
+
+async fn wipe_completed(
+    State(state): State<Arc<MockState>>,
+    Json(report): Json<Value>,
+) -> Json<Value> {
+    state.wipe_reports.lock().unwrap().push(report);
+    success(())
+}
+
+async fn ping() -> Json<Value> {
+    success(SERVER_VERSION)
+}
+
+#[derive(Deserialize)]
+struct LoginRequest {
+    email: String,
+    password: String,
+}
+
+async fn login(Json(request): Json<LoginRequest>) -> Json<Value> {
+    if request.email != EMAIL || request.password != PASSWORD {
+        return failure(40020, "Wrong password or email address");
+    }
+    success(LoginResponse {
+        user: User {
+            id: "1".to_string(),
+            email: Some(EMAIL.to_string()),
+            nickname: "Test".to_string(),
+            created_at: Utc::now().to_rfc3339(),
+            ..Default::default()
+        },
+        token: token(),
+    })
+}
+
+async fn refresh() -> Json<Value> {
+    success(token())
+}
+
+#[derive(Deserialize)]
+struct ListQuery {
+    uri: String,
+}
+
+async fn list(State(state): State<Arc<MockState>>, Query(query): Query<ListQuery>) -> Json<Value> {
+    let data = state.data.lock().unwrap();
+    let files = data
+        .files
+        .get(query.uri.trim_end_matches('/'))
+        .cloned()
+        .unwrap_or_default();
+    success(ListResponse {
+        pagination: PaginationResults {
+            page: 0,
+            page_size: files.len().max(1) as i32,
+            ..Default::default()
+        },
+        files,
+        props: NavigatorProps {
+            max_page_size: 2000,
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+}
+
+async fn create_upload(
+    State(state): State<Arc<MockState>>,
+    Json(request): Json<UploadSessionRequest>,
+) -> Json<Value> {
+    let mut data = state.data.lock().unwrap();
+    let session_id = data.next_id();
+    data.uploads.insert(
+        session_id.clone(),
+        MockUpload {
+            uri: request.uri.clone(),
+            size: request.size,
+            chunks: BTreeMap::new(),
+        },
+    );
+    success(UploadCredential {
+        session_id,
+        expires: (Utc::now() + Duration::hours(1)).timestamp(),
+        chunk_size: CHUNK_SIZE,
+        callback_secret: "secret".to_string(),
+        storage_policy: Some(StoragePolicy {
+            id: "1".to_string(),
+            name: "Local".to_string(),
+            max_size: 0,
+            policy_type: PolicyType::Local,
+            ..Default::default()
+        }),
+        uri: request.uri,
+        ..Default::default()
+    })
+}
+
+#[derive(Deserialize)]
+struct DeleteUploadRequest {
+    id: String,
+}
+
+async fn delete_upload(
+    State(state): State<Arc<MockState>>,
+    Json(request): Json<DeleteUploadRequest>,
+) -> Json<Value> {
+    let mut data = state.data.lock().unwrap();
+    match data.uploads.remove(&request.id) {
+        Some(_) => success(()),
+        None => failure(40011, "Upload session not exist or expired"),
+    }
+}
+
+async fn upload_chunk(
+    State(state): State<Arc<MockState>>,
+    Path((session_id, index)): Path<(String, usize)>,
+    body: Bytes,
+) -> Json<Value> {
+    let mut data = state.data.lock().unwrap();
+    let Some(upload) = data.uploads.get_mut(&session_id) else {
+        return failure(40011, "Upload session not exist or expired");
+    };
+    upload.chunks.insert(index, body.to_vec());
+
+    let received: usize = upload.chunks.values().map(Vec::len).sum();
+    if received as i64 == upload.size {
+        let upload = data.uploads.remove(&session_id).unwrap();
+        let (parent, name) = split_uri(&upload.uri);
+        let (parent, name) = (parent.to_string(), name.to_string());
+        let content = upload.content();
+        let file = FileResponse {
+            file_type: file_type::FILE,
+            id: data.next_id(),
+            name,
+            created_at: Utc::now().to_rfc3339(),
+            updated_at: Utc::now().to_rfc3339(),
+            size: upload.size,
+            path: upload.uri.clone(),
+            ..Default::default()
+        };
+        let siblings = data.files.entry(parent).or_default();
+        siblings.retain(|f| f.path != upload.uri);
+        siblings.push(file);
+        data.completed.insert(upload.uri, content);
+    }
+    success(())
+}
+
+async fn file_events(State(state): State<Arc<MockState>>) -> Response {
+    let subscribed = stream::once(async { SseEvent::default().event("subscribed").data("") });
+    let events = BroadcastStream::new(state.events.subscribe()).filter_map(|batch| async move {
+        let batch = batch.ok()?;
+        let data = serde_json::to_string(&batch).ok()?;
+        Some(SseEvent::default().event("event").data(data))
+    });
+    Sse::new(subscribed.chain(events).map(Ok::<_, Infallible>)).into_response()
+}
@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{Result, anyhow};
+use futures::future::BoxFuture;
+
+use super::{PinState, PlaceholderStatus, VirtualFs};
+
+/// An operation performed on a [`FakeVirtualFs`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VfsRequest {
+    Hydrate(PathBuf),
+    Dehydrate(PathBuf),
+    Pin(PathBuf, PinState),
+    MarkInSync(PathBuf, bool),
+    Populate(PathBuf),
+}
+
+#[derive(Debug, Clone)]
+struct FakeEntry {
+    is_directory: bool,
+    size: u64,
+    /// Bytes of the content present on disk
+    on_disk: u64,
+    in_sync: bool,
+    pin_state: PinState,
+    /// Children of a folder were listed
+    populated: bool,
+}
+
+#[derive(Debug, Default)]
+struct FakeState {
+    entries: BTreeMap<PathBuf, FakeEntry>,
+    requests: Vec<VfsRequest>,
+}
+
+/// In-memory [`VirtualFs`] that behaves like the Cloud Files API for the
+/// operations the engine uses and records every request in order
+#[derive(Debug, Default)]
+pub struct FakeVirtualFs {
+    state: Mutex<FakeState>,
+}
+
+impl FakeVirtualFs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an in-sync, dehydrated placeholder file
+    pub fn add_placeholder(&self, path: &Path, size: u64) {
+        self.insert(path, false, size);
+    }
+
+    /// Add an in-sync placeholder folder
+    pub fn add_folder(&self, path: &Path) {
+        self.insert(path, true, 0);
+    }
+
+    /// Add a placeholder folder whose children were never listed
+    pub fn add_unpopulated_folder(&self, path: &Path) {
+        self.insert(path, true, 0);
+        if let Some(entry) = self.state.lock().unwrap().entries.get_mut(path) {
+            entry.populated = false;
+        }
+    }
+
+    /// Simulate a local write replacing the content of a file
+    pub fn write(&self, path: &Path, size: u64) {
+        let mut state = self.state.lock().unwrap();
+        let entry = state
+            .entries
+            .entry(path.to_path_buf())
+            .or_insert_with(|| FakeEntry {
+                is_directory: false,
+                size: 0,
+                on_disk: 0,
+                in_sync: false,
+                pin_state: PinState::Unspecified,
+                populated: true,
+            });
+        entry.size = size;
+        entry.on_disk = size;
+        entry.in_sync = false;
+    }
+
+    pub fn remove(&self, path: &Path) {
+        self.state.lock().unwrap().entries.remove(path);
+    }
+
+    /// Requests made so far
+    pub fn requests(&self) -> Vec<VfsRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    fn insert(&self, path: &Path, is_directory: bool, size: u64) {
+        self.state.lock().unwrap().entries.insert(
+            path.to_path_buf(),
+            FakeEntry {
+                is_directory,
+                size,
+                on_disk: 0,
+                in_sync: true,
+                pin_state: PinState::Unspecified,
+                populated: true,
+            },
+        );
+    }
+
+    /// Record a request and apply it to the entry at `path`
+    fn apply(
+        &self,
+        request: VfsRequest,
+        path: &Path,
+        op: impl FnOnce(&mut FakeEntry) -> Result<()>,
+    ) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.requests.push(request);
+        let entry = state
+            .entries
+            .get_mut(path)
+            .ok_or_else(|| anyhow!("{} does not exist", path.display()))?;
+        op(entry)
+    }
+}
+
+impl VirtualFs for FakeVirtualFs {
+    fn status(&self, path: &Path) -> Result<PlaceholderStatus> {
+        let state = self.state.lock().unwrap();
+        Ok(match state.entries.get(path) {
+            Some(entry) => PlaceholderStatus {
+                exists: true,
+                is_directory: entry.is_directory,
+                is_placeholder: true,
+                in_sync: entry.in_sync,
+                partial_on_disk: if entry.is_directory {
+                    !entry.populated
+                } else {
+                    entry.on_disk < entry.size
+                },
+                pin_state: entry.pin_state,
+            },
+            None => PlaceholderStatus::missing(),
+        })
+    }
+
+    fn hydrate(&self, path: &Path) -> Result<()> {
+        self.apply(VfsRequest::Hydrate(path.to_path_buf()), path, |entry| {
+            if entry.is_directory {
+                return Err(anyhow!("cannot hydrate a folder"));
+            }
+            entry.on_disk = entry.size;
+            Ok(())
+        })
+    }
+
+    fn dehydrate<'a>(&'a self, path: &'a Path) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            self.apply(VfsRequest::Dehydrate(path.to_path_buf()), path, |entry| {
+                // Pinned files refuse dehydration, as do unsynced changes
+                if entry.pin_state == PinState::Pinned {
+                    return Err(anyhow!("cannot dehydrate a pinned file"));
+                }
+                if !entry.in_sync {
+                    return Err(anyhow!("cannot dehydrate a file that is not in sync"));
+                }
+                entry.on_disk = 0;
+                Ok(())
+            })
+        })
+    }
+
+    fn set_pin_state(&self, path: &Path, pin_state: PinState) -> Result<()> {
+        self.apply(
+            VfsRequest::Pin(path.to_path_buf(), pin_state),
+            path,
+            |entry| {
+                entry.pin_state = pin_state;
+                Ok(())
+            },
+        )
+    }
+
+    fn pin_tree(&self, path: &Path, pin_state: PinState) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state
+            .requests
+            .push(VfsRequest::Pin(path.to_path_buf(), pin_state));
+        if !state.entries.contains_key(path) {
+            return Err(anyhow!("{} does not exist", path.display()));
+        }
+        for (entry_path, entry) in state.entries.iter_mut() {
+            if entry_path.starts_with(path) {
+                entry.pin_state = pin_state;
+            }
+        }
+        Ok(())
+    }
+
+    fn mark_in_sync(&self, path: &Path, in_sync: bool) -> Result<()> {
+        self.apply(
+            VfsRequest::MarkInSync(path.to_path_buf(), in_sync),
+            path,
+            |entry| {
+                entry.in_sync = in_sync;
+                Ok(())
+            },
+        )
+    }
+
+    fn populate(&self, path: &Path) -> Result<()> {
+        self.apply(VfsRequest::Populate(path.to_path_buf()), path, |entry| {
+            if !entry.is_directory {
+                return Err(anyhow!("cannot populate a file"));
+            }
+            entry.populated = true;
+            Ok(())
+        })
+    }
+}
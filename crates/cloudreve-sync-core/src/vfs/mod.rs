@@ -0,0 +1,253 @@
+//! Placeholder operations the sync engine performs on the local file system.
+//!
+//! `cloudreve-sync` backs them with the Windows Cloud Files API. [`FakeVirtualFs`]
+//! keeps placeholders in memory, so the decisions the engine makes on local
+//! changes can be tested without a registered sync root.
+
+mod fake;
+
+pub use fake::{FakeVirtualFs, VfsRequest};
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use futures::future::BoxFuture;
+
+/// The pin state of a placeholder.
+///
+/// [Read more
+/// here](https://docs.microsoft.com/en-us/windows/win32/api/cfapi/ne-cfapi-cf_pin_state#remarks)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinState {
+    /// The platform could decide freely.
+    Unspecified,
+    /// The rest of the placeholder's data will be fetched. Any dehydration
+    /// requests will fail automatically.
+    Pinned,
+    /// The rest of the placeholder's data will be dropped.
+    Unpinned,
+    /// The placeholder will never sync to the cloud.
+    Excluded,
+    /// The placeholder will inherit the parent placeholder's pin state.
+    Inherit,
+}
+
+/// Placeholder state of a local path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlaceholderStatus {
+    pub exists: bool,
+    pub is_directory: bool,
+    pub is_placeholder: bool,
+    pub in_sync: bool,
+    /// Some of the content is not on disk
+    pub partial_on_disk: bool,
+    pub pin_state: PinState,
+}
+
+impl PlaceholderStatus {
+    pub fn missing() -> Self {
+        Self {
+            exists: false,
+            is_directory: false,
+            is_placeholder: false,
+            in_sync: false,
+            partial_on_disk: false,
+            pin_state: PinState::Unspecified,
+        }
+    }
+}
+
+/// Placeholder operations on local paths
+pub trait VirtualFs: Send + Sync {
+    fn status(&self, path: &Path) -> Result<PlaceholderStatus>;
+
+    /// Fetch the full content of a placeholder file
+    fn hydrate(&self, path: &Path) -> Result<()>;
+
+    /// Drop the local content of a placeholder file. Retries while the file is
+    /// held open by another process.
+    fn dehydrate<'a>(&'a self, path: &'a Path) -> BoxFuture<'a, Result<()>>;
+
+    fn set_pin_state(&self, path: &Path, state: PinState) -> Result<()>;
+
+    /// Set the pin state of a placeholder and, for a folder, of everything in
+    /// it
+    fn pin_tree(&self, path: &Path, state: PinState) -> Result<()>;
+
+    fn mark_in_sync(&self, path: &Path, in_sync: bool) -> Result<()>;
+
+    /// List a placeholder folder, so the placeholders of its children are
+    /// created before anything else is added to it
+    fn populate(&self, path: &Path) -> Result<()>;
+}
+
+/// Placeholder folders holding any of `paths` whose children were never
+/// listed, parents first
+pub fn unpopulated_parents(vfs: &dyn VirtualFs, paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut parents: Vec<PathBuf> = Vec::new();
+    for parent in paths.iter().filter_map(|path| path.parent()) {
+        if parents.iter().any(|p| p == parent) {
+            continue;
+        }
+        match vfs.status(parent) {
+            Ok(status)
+                if status.is_directory && status.is_placeholder && status.partial_on_disk =>
+            {
+                parents.push(parent.to_path_buf());
+            }
+            Ok(_) => {}
+            Err(e) => {
+                tracing::warn!(target: "vfs", path = %parent.display(), error = %e, "Failed to read placeholder state of folder");
+            }
+        }
+    }
+    parents.sort_by_key(|path| path.components().count());
+    parents
+}
+
+/// What to do about a file reported as modified
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModifyAction {
+    /// Pinned, but not all content is on disk
+    Hydrate,
+    /// Unpinned by the user
+    Dehydrate,
+    /// Content changed locally
+    Upload,
+    Ignore,
+}
+
+impl ModifyAction {
+    pub fn for_status(status: &PlaceholderStatus) -> Self {
+        if !status.exists || status.is_directory {
+            ModifyAction::Ignore
+        } else if status.pin_state == PinState::Pinned && status.partial_on_disk {
+            ModifyAction::Hydrate
+        } else if status.pin_state == PinState::Unpinned {
+            ModifyAction::Dehydrate
+        } else if !status.in_sync {
+            ModifyAction::Upload
+        } else {
+            ModifyAction::Ignore
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn pin_changes_drive_hydration() {
+        let vfs = FakeVirtualFs::new();
+        let path = Path::new("/drive/a.txt");
+        vfs.add_placeholder(path, 10);
+        assert_eq!(
+            ModifyAction::for_status(&vfs.status(path).unwrap()),
+            ModifyAction::Ignore
+        );
+
+        // "Always keep on this device"
+        vfs.set_pin_state(path, PinState::Pinned).unwrap();
+        let status = vfs.status(path).unwrap();
+        assert_eq!(ModifyAction::for_status(&status), ModifyAction::Hydrate);
+        vfs.hydrate(path).unwrap();
+        assert!(!vfs.status(path).unwrap().partial_on_disk);
+        assert!(vfs.dehydrate(path).await.is_err());
+
+        // "Free up space"
+        vfs.set_pin_state(path, PinState::Unpinned).unwrap();
+        let status = vfs.status(path).unwrap();
+        assert_eq!(ModifyAction::for_status(&status), ModifyAction::Dehydrate);
+        vfs.dehydrate(path).await.unwrap();
+        assert!(vfs.status(path).unwrap().partial_on_disk);
+
+        assert_eq!(
+            vfs.requests(),
+            [
+                VfsRequest::Pin(path.to_path_buf(), PinState::Pinned),
+                VfsRequest::Hydrate(path.to_path_buf()),
+                VfsRequest::Dehydrate(path.to_path_buf()),
+                VfsRequest::Pin(path.to_path_buf(), PinState::Unpinned),
+                VfsRequest::Dehydrate(path.to_path_buf()),
+            ]
+        );
+    }
+
+    #[test]
+    fn pin_tree_pins_folder_contents() {
+        let vfs = FakeVirtualFs::new();
+        let folder = Path::new("/drive/docs");
+        let inside = Path::new("/drive/docs/a.txt");
+        let outside = Path::new("/drive/b.txt");
+        vfs.add_folder(folder);
+        vfs.add_placeholder(inside, 10);
+        vfs.add_placeholder(outside, 10);
+
+        vfs.pin_tree(folder, PinState::Pinned).unwrap();
+        assert_eq!(vfs.status(folder).unwrap().pin_state, PinState::Pinned);
+        assert_eq!(
+            ModifyAction::for_status(&vfs.status(inside).unwrap()),
+            ModifyAction::Hydrate
+        );
+        assert_eq!(
+            vfs.status(outside).unwrap().pin_state,
+            PinState::Unspecified
+        );
+        assert!(
+            vfs.pin_tree(Path::new("/drive/missing"), PinState::Pinned)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn local_edits_are_uploaded() {
+        let vfs = FakeVirtualFs::new();
+        let path = Path::new("/drive/b.txt");
+        vfs.add_placeholder(path, 4);
+        vfs.hydrate(path).unwrap();
+
+        vfs.write(path, 8);
+        assert_eq!(
+            ModifyAction::for_status(&vfs.status(path).unwrap()),
+            ModifyAction::Upload
+        );
+        vfs.mark_in_sync(path, true).unwrap();
+        assert_eq!(
+            ModifyAction::for_status(&vfs.status(path).unwrap()),
+            ModifyAction::Ignore
+        );
+
+        let missing = Path::new("/drive/missing.txt");
+        assert_eq!(vfs.status(missing).unwrap(), PlaceholderStatus::missing());
+        assert!(vfs.hydrate(missing).is_err());
+    }
+
+    #[test]
+    fn drop_into_unpopulated_folder_populates_it_first() {
+        let vfs = FakeVirtualFs::new();
+        let folder = Path::new("/drive/docs");
+        let populated = Path::new("/drive/photos");
+        vfs.add_unpopulated_folder(folder);
+        vfs.add_folder(populated);
+        let dropped = [
+            folder.join("a.txt"),
+            folder.join("b.txt"),
+            populated.join("c.jpg"),
+        ];
+        for path in &dropped {
+            vfs.write(path, 4);
+        }
+
+        assert_eq!(unpopulated_parents(&vfs, &dropped), [folder.to_path_buf()]);
+        vfs.populate(folder).unwrap();
+        assert!(!vfs.status(folder).unwrap().partial_on_disk);
+        assert!(unpopulated_parents(&vfs, &dropped).is_empty());
+        // The dropped files are new content to upload
+        assert_eq!(
+            ModifyAction::for_status(&vfs.status(&dropped[0]).unwrap()),
+            ModifyAction::Upload
+        );
+        assert_eq!(vfs.requests(), [VfsRequest::Populate(folder.to_path_buf())]);
+    }
+}
@@ -0,0 +1,9 @@
+-- Downloaded files flagged by the scanner. They are dehydrated and refused
+-- hydration until released.
+CREATE TABLE quarantined_files (
+    drive_id TEXT NOT NULL,
+    local_path TEXT NOT NULL PRIMARY KEY,
+    -- Scanner output describing the threat
+    detail TEXT NOT NULL DEFAULT '',
+    detected_at INTEGER NOT NULL
+);
@@ -0,0 +1,17 @@
+-- Files whose sync keeps failing. After enough failures they are quarantined
+-- and no longer queued until retried, or ignored for good.
+CREATE TABLE sync_failures (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    drive_id TEXT NOT NULL,
+    local_path TEXT NOT NULL,
+    -- upload, download or rename
+    operation TEXT NOT NULL,
+    failures INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT NOT NULL DEFAULT '',
+    -- failing, quarantined or ignored
+    state TEXT NOT NULL DEFAULT 'failing',
+    updated_at BIGINT NOT NULL,
+    UNIQUE (local_path, operation)
+);
+
+CREATE INDEX idx_sync_failures_drive ON sync_failures(drive_id);
@@ -0,0 +1,14 @@
+-- Uses of a drive's credentials: sign-ins, token refreshes and the server
+-- rejecting them. Kept to diagnose unexpected sign-outs, without the tokens.
+CREATE TABLE credential_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    drive_id TEXT NOT NULL,
+    -- login, credentials_updated, refreshed, refresh_failed or expired
+    kind TEXT NOT NULL,
+    -- user, auto_refresh or server
+    source TEXT NOT NULL,
+    detail TEXT NOT NULL DEFAULT '',
+    occurred_at BIGINT NOT NULL
+);
+
+CREATE INDEX idx_credential_events_drive ON credential_events(drive_id);
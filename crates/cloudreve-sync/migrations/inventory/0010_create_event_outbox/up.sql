@@ -0,0 +1,14 @@
+-- Critical events kept until the UI acknowledges them, so they reach the
+-- user even when no window was open when they were raised.
+CREATE TABLE event_outbox (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    event_id TEXT NOT NULL UNIQUE,
+    -- Event name, e.g. ReauthRequired
+    kind TEXT NOT NULL,
+    -- The event as JSON
+    payload TEXT NOT NULL,
+    created_at BIGINT NOT NULL,
+    acknowledged_at BIGINT
+);
+
+CREATE INDEX idx_event_outbox_acknowledged ON event_outbox(acknowledged_at);
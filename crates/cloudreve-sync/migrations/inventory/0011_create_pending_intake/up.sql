@@ -0,0 +1,11 @@
+-- Local files found to need an upload but not queued as a task yet. Kept
+-- until the upload task exists, so a restart in between does not lose them.
+CREATE TABLE pending_intake (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    drive_id TEXT NOT NULL,
+    local_path TEXT NOT NULL,
+    -- created, modified, remote_missing, remote_mismatch or bulk
+    reason TEXT NOT NULL,
+    discovered_at BIGINT NOT NULL,
+    UNIQUE (drive_id, local_path)
+);
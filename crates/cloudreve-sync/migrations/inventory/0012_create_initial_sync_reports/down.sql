@@ -0,0 +1 @@
+DROP TABLE IF EXISTS initial_sync_reports;
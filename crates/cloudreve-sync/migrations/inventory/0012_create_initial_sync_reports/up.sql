@@ -0,0 +1,8 @@
+-- Verification report written after a drive's first full sync
+CREATE TABLE initial_sync_reports (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    drive_id TEXT NOT NULL UNIQUE,
+    -- The report as JSON
+    report TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
@@ -430,48 +430,26 @@ impl Default for OpenOptions {
     }
 }
 
-/// The pin state of a placeholder.
-///
-/// [Read more
-/// here](https://docs.microsoft.com/en-us/windows/win32/api/cfapi/ne-cfapi-cf_pin_state#remarks)
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum PinState {
-    /// The platform could decide freely.
-    Unspecified,
-    /// [SyncFilter::fetch_data][crate::filter::SyncFilter::fetch_data] will be called to hydrate the rest
-    /// of the placeholder's data. Any dehydration requests will fail automatically.
-    Pinned,
-    /// [SyncFilter::dehydrate][crate::filter::SyncFilter::dehydrate] will be called to dehydrate the rest
-    /// of the placeholder's data.
-    Unpinned,
-    /// The placeholder will never sync to the cloud.
-    Excluded,
-    /// The placeholder will inherit the parent placeholder's pin state.
-    Inherit,
-}
+pub use cloudreve_sync_core::vfs::PinState;
 
-impl From<PinState> for CF_PIN_STATE {
-    fn from(state: PinState) -> Self {
-        match state {
-            PinState::Unspecified => CloudFilters::CF_PIN_STATE_UNSPECIFIED,
-            PinState::Pinned => CloudFilters::CF_PIN_STATE_PINNED,
-            PinState::Unpinned => CloudFilters::CF_PIN_STATE_UNPINNED,
-            PinState::Excluded => CloudFilters::CF_PIN_STATE_EXCLUDED,
-            PinState::Inherit => CloudFilters::CF_PIN_STATE_INHERIT,
-        }
+fn pin_state_to_cf(state: PinState) -> CF_PIN_STATE {
+    match state {
+        PinState::Unspecified => CloudFilters::CF_PIN_STATE_UNSPECIFIED,
+        PinState::Pinned => CloudFilters::CF_PIN_STATE_PINNED,
+        PinState::Unpinned => CloudFilters::CF_PIN_STATE_UNPINNED,
+        PinState::Excluded => CloudFilters::CF_PIN_STATE_EXCLUDED,
+        PinState::Inherit => CloudFilters::CF_PIN_STATE_INHERIT,
     }
 }
 
-impl From<CF_PIN_STATE> for PinState {
-    fn from(state: CF_PIN_STATE) -> Self {
-        match state {
-            CloudFilters::CF_PIN_STATE_UNSPECIFIED => PinState::Unspecified,
-            CloudFilters::CF_PIN_STATE_PINNED => PinState::Pinned,
-            CloudFilters::CF_PIN_STATE_UNPINNED => PinState::Unpinned,
-            CloudFilters::CF_PIN_STATE_EXCLUDED => PinState::Excluded,
-            CloudFilters::CF_PIN_STATE_INHERIT => PinState::Inherit,
-            _ => unreachable!(),
-        }
+fn pin_state_from_cf(state: CF_PIN_STATE) -> PinState {
+    match state {
+        CloudFilters::CF_PIN_STATE_UNSPECIFIED => PinState::Unspecified,
+        CloudFilters::CF_PIN_STATE_PINNED => PinState::Pinned,
+        CloudFilters::CF_PIN_STATE_UNPINNED => PinState::Unpinned,
+        CloudFilters::CF_PIN_STATE_EXCLUDED => PinState::Excluded,
+        CloudFilters::CF_PIN_STATE_INHERIT => PinState::Inherit,
+        _ => unreachable!(),
     }
 }
 
@@ -605,6 +583,11 @@ impl PlaceholderState {
         }
     }
 
+    #[cfg(test)]
+    pub(crate) fn from_state(state: CF_PLACEHOLDER_STATE) -> Self {
+        Self { state }
+    }
+
     // The file or directory whose FileAttributes and ReparseTag examined by the API is a placeholder.
     pub fn is_placeholder(&self) -> bool {
         (self.state & CloudFilters::CF_PLACEHOLDER_STATE_PLACEHOLDER).0 != 0
@@ -782,7 +765,7 @@ impl PlaceholderInfo {
     }
 
     pub fn pin_state(&self) -> PinState {
-        unsafe { &*self.info }.PinState.into()
+        pin_state_from_cf(unsafe { &*self.info }.PinState)
     }
 
     pub fn is_in_sync(&self) -> bool {
@@ -817,6 +800,140 @@ impl std::fmt::Debug for PlaceholderInfo {
     }
 }
 
+/// Versioned file identity stored as the opaque placeholder blob.
+///
+/// Layout: `MAGIC (3 bytes) | version (u8) | fields...` where each field is
+/// `tag (u8) | length (u16 LE) | value`. Unknown tags are skipped, so newer
+/// writers can add fields without breaking older readers. Blobs without the magic
+/// prefix are legacy (version 0) blobs holding only the primary entity string.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlaceholderBlob {
+    /// Format version the blob was decoded from (0 for legacy blobs)
+    pub version: u8,
+    /// Remote file id
+    pub file_id: Option<String>,
+    /// Remote primary entity id
+    pub entity_id: Option<String>,
+    /// Version tag of the content (currently the primary entity id)
+    pub etag: String,
+    /// Reserved bit flags
+    pub flags: u32,
+}
+
+impl PlaceholderBlob {
+    /// Version written by [PlaceholderBlob::encode]
+    pub const CURRENT_VERSION: u8 = 1;
+
+    // Legacy blobs are plain strings and never contain NUL
+    const MAGIC: [u8; 3] = [0x00, b'C', b'R'];
+
+    const TAG_FILE_ID: u8 = 1;
+    const TAG_ENTITY_ID: u8 = 2;
+    const TAG_ETAG: u8 = 3;
+    const TAG_FLAGS: u8 = 4;
+
+    /// Create a blob in the current format for the given etag.
+    pub fn new(etag: impl Into<String>) -> Self {
+        Self {
+            version: Self::CURRENT_VERSION,
+            etag: etag.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_file_id(mut self, file_id: impl Into<String>) -> Self {
+        self.file_id = Some(file_id.into());
+        self
+    }
+
+    pub fn with_entity_id(mut self, entity_id: impl Into<String>) -> Self {
+        self.entity_id = Some(entity_id.into());
+        self
+    }
+
+    pub fn with_flags(mut self, flags: u32) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Whether the blob was written in the legacy (unversioned) format
+    pub fn is_legacy(&self) -> bool {
+        self.version == 0
+    }
+
+    /// Decode a blob, falling back to the legacy format for unrecognized input.
+    pub fn decode(bytes: &[u8]) -> Self {
+        if !bytes.starts_with(&Self::MAGIC) || bytes.len() <= Self::MAGIC.len() {
+            let etag = String::from_utf8_lossy(bytes).to_string();
+            return Self {
+                version: 0,
+                entity_id: (!etag.is_empty()).then(|| etag.clone()),
+                etag,
+                ..Default::default()
+            };
+        }
+
+        let mut blob = Self {
+            version: bytes[Self::MAGIC.len()],
+            ..Default::default()
+        };
+        let mut rest = &bytes[Self::MAGIC.len() + 1..];
+        while rest.len() >= 3 {
+            let tag = rest[0];
+            let len = u16::from_le_bytes([rest[1], rest[2]]) as usize;
+            let Some(value) = rest.get(3..3 + len) else {
+                // Truncated field, keep what was decoded so far
+                break;
+            };
+            match tag {
+                Self::TAG_FILE_ID => blob.file_id = Some(String::from_utf8_lossy(value).to_string()),
+                Self::TAG_ENTITY_ID => {
+                    blob.entity_id = Some(String::from_utf8_lossy(value).to_string())
+                }
+                Self::TAG_ETAG => blob.etag = String::from_utf8_lossy(value).to_string(),
+                Self::TAG_FLAGS if len == 4 => {
+                    blob.flags = u32::from_le_bytes([value[0], value[1], value[2], value[3]])
+                }
+                // Field from a newer version
+                _ => {}
+            }
+            rest = &rest[3 + len..];
+        }
+        blob
+    }
+
+    /// Encode the blob in the current format.
+    pub fn encode(&self) -> Vec<u8> {
+        fn push_field(buf: &mut Vec<u8>, tag: u8, value: &[u8]) {
+            buf.push(tag);
+            buf.extend_from_slice(&(value.len() as u16).to_le_bytes());
+            buf.extend_from_slice(value);
+        }
+
+        let mut buf = Vec::with_capacity(64);
+        buf.extend_from_slice(&Self::MAGIC);
+        buf.push(Self::CURRENT_VERSION);
+        if let Some(file_id) = &self.file_id {
+            push_field(&mut buf, Self::TAG_FILE_ID, file_id.as_bytes());
+        }
+        if let Some(entity_id) = &self.entity_id {
+            push_field(&mut buf, Self::TAG_ENTITY_ID, entity_id.as_bytes());
+        }
+        push_field(&mut buf, Self::TAG_ETAG, self.etag.as_bytes());
+        if self.flags != 0 {
+            push_field(&mut buf, Self::TAG_FLAGS, &self.flags.to_le_bytes());
+        }
+        buf
+    }
+}
+
+impl PlaceholderInfo {
+    /// The decoded file identity of the placeholder.
+    pub fn identity(&self) -> PlaceholderBlob {
+        PlaceholderBlob::decode(self.blob())
+    }
+}
+
 /// Placeholder update parameters.
 #[derive(Debug, Clone)]
 pub struct UpdateOptions<'a> {
@@ -1073,7 +1190,7 @@ impl Placeholder {
     /// [CfSetPinState](https://learn.microsoft.com/en-us/windows/win32/api/cfapi/nf-cfapi-cfsetpinstate),
     /// [What does "Pinned" Mean?](https://www.userfilesystem.com/programming/faq/#nav_howdoesthealwayskeeponthisdevicemenuworks)
     pub fn mark_pin(&mut self, state: PinState, options: PinOptions) -> core::Result<&mut Self> {
-        unsafe { CfSetPinState(self.handle.handle, state.into(), options.0, None) }?;
+        unsafe { CfSetPinState(self.handle.handle, pin_state_to_cf(state), options.0, None) }?;
         Ok(self)
     }
 
@@ -1357,3 +1474,35 @@ impl TryFrom<Placeholder> for File {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::PlaceholderBlob;
+
+    #[test]
+    fn test_blob_roundtrip() {
+        let blob = PlaceholderBlob::new("etag1")
+            .with_file_id("file1")
+            .with_entity_id("entity1")
+            .with_flags(3);
+        assert_eq!(PlaceholderBlob::decode(&blob.encode()), blob);
+    }
+
+    #[test]
+    fn test_legacy_blob() {
+        let blob = PlaceholderBlob::decode(b"entity1");
+        assert!(blob.is_legacy());
+        assert_eq!(blob.etag, "entity1");
+        assert_eq!(blob.entity_id.as_deref(), Some("entity1"));
+        assert!(PlaceholderBlob::decode(b"").etag.is_empty());
+    }
+
+    #[test]
+    fn test_unknown_fields_skipped() {
+        let mut bytes = PlaceholderBlob::new("etag1").encode();
+        bytes.extend_from_slice(&[99, 2, 0, 0xAB, 0xCD]);
+        let blob = PlaceholderBlob::decode(&bytes);
+        assert_eq!(blob.etag, "etag1");
+        assert_eq!(blob.version, PlaceholderBlob::CURRENT_VERSION);
+    }
+}
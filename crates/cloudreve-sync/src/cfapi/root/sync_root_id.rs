@@ -118,7 +118,7 @@ impl SyncRootIdBuilder {
 /// [here](https://docs.microsoft.com/en-us/uwp/api/windows.storage.provider.storageprovidersyncrootinfo.id?view=winrt-22000#property-value).
 ///
 /// A [SyncRootId] stores an inner, reference counted [HSTRING][windows::core::HSTRING], making this struct cheap to clone.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SyncRootId(pub(crate) HSTRING);
 
 impl Serialize for SyncRootId {
@@ -156,8 +156,7 @@ impl SyncRootId {
         StorageProviderSyncRootManager::GetSyncRootInformationForFolder(
             &StorageFolder::GetFolderFromPathAsync(
                 &U16String::from_os_str(path.as_ref()).to_hstring(),
-            )
-            .unwrap()
+            )?
             .get()?,
         )
         .map(|info| SyncRootId(info.Id().unwrap()))
@@ -239,24 +238,62 @@ impl SyncRootId {
     ///
     /// Panics if the sync root id does not have exactly three components.
     pub fn to_components(&self) -> (&U16Str, &U16Str, &U16Str) {
-        let mut components = Vec::with_capacity(3);
-        components.extend(
-            self.0
-                .as_wide()
-                .split(|&byte| byte == Self::SEPARATOR)
-                .map(U16Str::from_slice),
-        );
+        self.components().unwrap_or_else(|| {
+            panic!(
+                "malformed sync root id, got {:?}",
+                self.to_os_string().to_string_lossy()
+            )
+        })
+    }
 
-        if components.len() != 3 {
-            panic!("malformed sync root id, got {:?}", components)
+    /// Like [SyncRootId::to_components], but [None] if the sync root id does not have exactly
+    /// three components. Sync roots registered by other providers may not follow the
+    /// specification.
+    pub fn components(&self) -> Option<(&U16Str, &U16Str, &U16Str)> {
+        let mut components = self
+            .0
+            .as_wide()
+            .split(|&byte| byte == Self::SEPARATOR)
+            .map(U16Str::from_slice);
+
+        match (
+            components.next(),
+            components.next(),
+            components.next(),
+            components.next(),
+        ) {
+            (Some(provider_id), Some(security_id), Some(account_name), None) => {
+                Some((provider_id, security_id, account_name))
+            }
+            _ => None,
         }
+    }
+
+    /// The [SecurityId] embedded in the [SyncRootId], or [None] if the sync root id does not
+    /// have exactly three components.
+    pub fn security_id(&self) -> Option<SecurityId> {
+        let (_, security_id, _) = self.components()?;
+        Some(SecurityId(security_id.to_ustring()))
+    }
 
-        (components[0], components[1], components[2])
+    /// A copy of the [SyncRootId] with the security id replaced, keeping the provider id and
+    /// account name. [None] if the sync root id does not have exactly three components.
+    pub fn with_security_id(&self, security_id: &SecurityId) -> Option<Self> {
+        let (provider_name, _, account_name) = self.components()?;
+        Some(SyncRootId(
+            [
+                provider_name.as_slice(),
+                security_id.0.as_slice(),
+                account_name.as_slice(),
+            ]
+            .join(&Self::SEPARATOR)
+            .to_hstring(),
+        ))
     }
 }
 
 /// A user security id (SID).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SecurityId(U16String);
 
 impl SecurityId {
@@ -278,6 +315,11 @@ impl SecurityId {
         Self(id)
     }
 
+    /// Encodes the [SecurityId] to an [OsString].
+    pub fn to_os_string(&self) -> OsString {
+        self.0.to_os_string()
+    }
+
     /// The [SecurityId] for the logged in user.
     pub fn current_user() -> core::Result<Self> {
         unsafe {
@@ -348,3 +390,24 @@ fn index_path(path: &Path) -> core::Result<()> {
         crawler.SaveAll()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn malformed_ids_have_no_security_id() {
+        let id = SyncRootId(HSTRING::from("provider!S-1-5-21!account"));
+        assert_eq!(id.security_id(), Some(SecurityId::new("S-1-5-21")));
+        assert_eq!(
+            id.with_security_id(&SecurityId::new("S-1-5-32")),
+            Some(SyncRootId(HSTRING::from("provider!S-1-5-32!account")))
+        );
+
+        for malformed in ["", "provider", "provider!S-1-5-21", "a!b!c!d"] {
+            let id = SyncRootId(HSTRING::from(malformed));
+            assert_eq!(id.security_id(), None, "{malformed}");
+            assert_eq!(id.with_security_id(&SecurityId::new("S-1-5-32")), None);
+        }
+    }
+}
@@ -1,8 +1,12 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::PathBuf;
 use std::sync::{OnceLock, RwLock};
+use tokio::sync::broadcast;
+
+use crate::policy::Policy;
 
 /// Global config manager instance
 static CONFIG_MANAGER: OnceLock<ConfigManager> = OnceLock::new();
@@ -42,8 +46,118 @@ impl LogLevel {
     }
 }
 
+/// Scanner run on files once they have been downloaded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum DownloadScan {
+    /// Downloaded files are not scanned
+    #[default]
+    Off,
+    /// Scan with the Microsoft Defender command line scanner
+    Defender,
+    /// Run a custom scanner. `{path}` in `args` is replaced by the file path,
+    /// which is appended if no argument contains it. Exit code 0 means clean
+    /// and 1 means a threat was found (the clamscan convention), anything else
+    /// is treated as a failed scan.
+    Command { program: String, args: Vec<String> },
+}
+
+/// Throttling of background work while running on a low battery
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BatterySaver {
+    /// Whether sync is throttled at all on battery power
+    pub enabled: bool,
+    /// Throttle once the remaining charge is at or below this percentage
+    pub threshold_percent: u8,
+}
+
+impl Default for BatterySaver {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold_percent: 30,
+        }
+    }
+}
+
+/// Deferral of expensive background work while the user is active
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IdleSchedule {
+    /// Whether full syncs and maintenance wait for the user to be idle
+    pub enabled: bool,
+    /// Seconds without keyboard or mouse input after which the user is idle
+    pub idle_threshold_secs: u64,
+}
+
+impl Default for IdleSchedule {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            idle_threshold_secs: 5 * 60,
+        }
+    }
+}
+
+/// Quieting down while the screen is presented or shared
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PresentationMode {
+    /// Whether presenting is detected at all
+    pub enabled: bool,
+    /// Hold back notifications while presenting
+    pub suppress_notifications: bool,
+    /// Pause uploads and background syncs while presenting
+    pub pause_transfers: bool,
+}
+
+impl Default for PresentationMode {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            suppress_notifications: true,
+            pause_transfers: true,
+        }
+    }
+}
+
+/// Sharing of transfer slots between drives
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FairScheduling {
+    /// Whether busy drives share the budgets below by their priority
+    pub enabled: bool,
+    /// Uploads running at the same time across all drives
+    pub max_uploads: usize,
+    /// Downloads and hydrations running at the same time across all drives
+    pub max_downloads: usize,
+}
+
+impl Default for FairScheduling {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_uploads: 4,
+            max_downloads: 8,
+        }
+    }
+}
+
+/// Position and size of a window, in physical pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Covers the 2 second modification time resolution of FAT file systems
+pub const DEFAULT_MTIME_TOLERANCE_SECS: u64 = 2;
+
 /// Application configuration stored as JSON
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct AppConfig {
     /// Whether to automatically start the application when the system boots
@@ -60,8 +174,28 @@ pub struct AppConfig {
     pub log_level: LogLevel,
     /// Maximum number of log files to keep
     pub log_max_files: usize,
+    /// Log every request made to the servers, with credentials redacted
+    pub http_trace: bool,
     /// Language/locale setting (e.g., "en-US", "zh-CN"). None means use system default.
     pub language: Option<String>,
+    /// Scanner run on newly downloaded files, flagged files are quarantined
+    pub download_scan: DownloadScan,
+    /// Reduce transfers and defer full syncs on a low battery
+    pub battery_saver: BatterySaver,
+    /// Run full syncs and maintenance while the user is idle
+    pub idle_schedule: IdleSchedule,
+    /// Hold back notifications and transfers while presenting
+    pub presentation_mode: PresentationMode,
+    /// Share transfer slots between busy drives
+    pub fair_scheduling: FairScheduling,
+    /// Seconds a local modification time may differ from the one recorded
+    /// for a file and still count as unchanged. Only set in config.json.
+    pub mtime_tolerance_secs: u64,
+    /// Allow simulated drives, which fake a server for UI development and
+    /// demos. Only set in config.json.
+    pub simulated_drives: bool,
+    /// Last geometry of resizable windows, by window label
+    pub window_geometry: BTreeMap<String, WindowGeometry>,
 }
 
 impl Default for AppConfig {
@@ -74,15 +208,43 @@ impl Default for AppConfig {
             log_to_file: true,
             log_level: LogLevel::Debug,
             log_max_files: 5,
+            http_trace: false,
             language: None,
+            download_scan: DownloadScan::Off,
+            battery_saver: BatterySaver::default(),
+            idle_schedule: IdleSchedule::default(),
+            presentation_mode: PresentationMode::default(),
+            fair_scheduling: FairScheduling::default(),
+            mtime_tolerance_secs: DEFAULT_MTIME_TOLERANCE_SECS,
+            simulated_drives: false,
+            window_geometry: BTreeMap::new(),
         }
     }
 }
 
+/// Notification sent to subscribers after the configuration was changed and persisted
+#[derive(Debug, Clone)]
+pub struct ConfigChanged {
+    pub previous: AppConfig,
+    pub current: AppConfig,
+}
+
+impl ConfigChanged {
+    /// Whether the field selected by `field` differs between the previous and current config
+    ///
+    /// ```ignore
+    /// if change.changed(|c| &c.log_level) { ... }
+    /// ```
+    pub fn changed<T: PartialEq>(&self, field: impl Fn(&AppConfig) -> &T) -> bool {
+        field(&self.previous) != field(&self.current)
+    }
+}
+
 /// Thread-safe configuration manager that persists settings to JSON
 pub struct ConfigManager {
     config: RwLock<AppConfig>,
     config_path: PathBuf,
+    changes: broadcast::Sender<ConfigChanged>,
 }
 
 impl ConfigManager {
@@ -90,11 +252,13 @@ impl ConfigManager {
     /// This should be called once at application startup.
     pub fn init() -> Result<&'static ConfigManager> {
         let config_path = Self::get_config_path()?;
-        let config = Self::load_from_path(&config_path)?;
+        let config = Policy::get().apply_settings(Self::load_from_path(&config_path)?);
 
+        let (changes, _) = broadcast::channel(16);
         let manager = ConfigManager {
             config: RwLock::new(config),
             config_path,
+            changes,
         };
 
         Ok(CONFIG_MANAGER.get_or_init(|| manager))
@@ -169,18 +333,44 @@ impl ConfigManager {
             .unwrap_or_else(|_| AppConfig::default())
     }
 
-    /// Update the configuration with a closure and persist to disk
+    /// Subscribe to configuration changes.
+    ///
+    /// Running subsystems use this to apply settings immediately instead of on restart.
+    pub fn subscribe(&self) -> broadcast::Receiver<ConfigChanged> {
+        self.changes.subscribe()
+    }
+
+    /// Update the configuration with a closure and persist to disk.
+    /// Subscribers are notified if the configuration actually changed.
+    ///
+    /// Fails without applying anything if the closure touches a setting locked by [Policy].
     pub fn update<F>(&self, f: F) -> Result<()>
     where
         F: FnOnce(&mut AppConfig),
     {
-        {
+        let change = {
             let mut config = self.config.write().map_err(|e| {
                 anyhow::anyhow!("Failed to acquire write lock on config: {}", e)
             })?;
+            let previous = config.clone();
             f(&mut config);
+            if let Err(e) = Policy::get().check_locked(&previous, &config) {
+                *config = previous;
+                return Err(e);
+            }
+            ConfigChanged {
+                previous,
+                current: config.clone(),
+            }
+        };
+        self.save()?;
+
+        if change.previous != change.current {
+            // No receivers is fine, nothing is running that depends on the config yet
+            let _ = self.changes.send(change);
+            tracing::debug!(target: "config", "Configuration change broadcast");
         }
-        self.save()
+        Ok(())
     }
 
     /// Get whether auto-start is enabled
@@ -288,6 +478,21 @@ impl ConfigManager {
         })
     }
 
+    /// Get whether requests to the servers are traced
+    pub fn http_trace(&self) -> bool {
+        self.config
+            .read()
+            .map(|c| c.http_trace)
+            .unwrap_or(false)
+    }
+
+    /// Set whether requests to the servers are traced
+    pub fn set_http_trace(&self, enabled: bool) -> Result<()> {
+        self.update(|config| {
+            config.http_trace = enabled;
+        })
+    }
+
     /// Get the language setting
     pub fn language(&self) -> Option<String> {
         self.config.read().ok().and_then(|c| c.language.clone())
@@ -300,6 +505,112 @@ impl ConfigManager {
         })
     }
 
+    /// Get the scanner run on downloaded files
+    pub fn download_scan(&self) -> DownloadScan {
+        self.config
+            .read()
+            .map(|c| c.download_scan.clone())
+            .unwrap_or_default()
+    }
+
+    /// Set the scanner run on downloaded files
+    pub fn set_download_scan(&self, scan: DownloadScan) -> Result<()> {
+        self.update(|config| {
+            config.download_scan = scan;
+        })
+    }
+
+    /// Get the low battery throttling settings
+    pub fn battery_saver(&self) -> BatterySaver {
+        self.config
+            .read()
+            .map(|c| c.battery_saver.clone())
+            .unwrap_or_default()
+    }
+
+    /// Set the low battery throttling settings
+    pub fn set_battery_saver(&self, battery_saver: BatterySaver) -> Result<()> {
+        self.update(|config| {
+            config.battery_saver = battery_saver;
+        })
+    }
+
+    /// Get the presentation mode settings
+    pub fn presentation_mode(&self) -> PresentationMode {
+        self.config
+            .read()
+            .map(|c| c.presentation_mode.clone())
+            .unwrap_or_default()
+    }
+
+    /// Set the presentation mode settings
+    pub fn set_presentation_mode(&self, presentation_mode: PresentationMode) -> Result<()> {
+        self.update(|config| {
+            config.presentation_mode = presentation_mode;
+        })
+    }
+
+    /// Get the settings for sharing transfers between drives
+    pub fn fair_scheduling(&self) -> FairScheduling {
+        self.config
+            .read()
+            .map(|c| c.fair_scheduling.clone())
+            .unwrap_or_default()
+    }
+
+    /// Set the settings for sharing transfers between drives
+    pub fn set_fair_scheduling(&self, fair_scheduling: FairScheduling) -> Result<()> {
+        self.update(|config| {
+            config.fair_scheduling = fair_scheduling;
+        })
+    }
+
+    /// Get the idle-time scheduling settings
+    pub fn idle_schedule(&self) -> IdleSchedule {
+        self.config
+            .read()
+            .map(|c| c.idle_schedule.clone())
+            .unwrap_or_default()
+    }
+
+    /// Set the idle-time scheduling settings
+    pub fn set_idle_schedule(&self, idle_schedule: IdleSchedule) -> Result<()> {
+        self.update(|config| {
+            config.idle_schedule = idle_schedule;
+        })
+    }
+
+    /// Get the modification time tolerance in seconds
+    pub fn mtime_tolerance_secs(&self) -> u64 {
+        self.config
+            .read()
+            .map(|c| c.mtime_tolerance_secs)
+            .unwrap_or(DEFAULT_MTIME_TOLERANCE_SECS)
+    }
+
+    /// Get whether simulated drives are allowed
+    pub fn simulated_drives(&self) -> bool {
+        self.config
+            .read()
+            .map(|c| c.simulated_drives)
+            .unwrap_or(false)
+    }
+
+    /// Get the last geometry of a window
+    pub fn window_geometry(&self, label: &str) -> Option<WindowGeometry> {
+        self.config
+            .read()
+            .ok()
+            .and_then(|c| c.window_geometry.get(label).copied())
+    }
+
+    /// Remember the geometry of a window, to restore it when it opens again
+    pub fn set_window_geometry(&self, label: &str, geometry: WindowGeometry) -> Result<()> {
+        self.update(|config| {
+            config.window_geometry.insert(label.to_string(), geometry);
+        })
+    }
+
     /// Get the log directory path
     pub fn get_log_dir() -> PathBuf {
         dirs::home_dir()
@@ -340,6 +651,37 @@ mod tests {
         assert!(!config.auto_start);
     }
 
+    #[test]
+    fn test_config_changed_field() {
+        let previous = AppConfig::default();
+        let mut current = previous.clone();
+        current.log_level = LogLevel::Trace;
+
+        let change = ConfigChanged { previous, current };
+        assert!(change.changed(|c| &c.log_level));
+        assert!(!change.changed(|c| &c.language));
+    }
+
+    #[test]
+    fn test_download_scan_format() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        writeln!(
+            temp_file,
+            r#"{{"download_scan": {{"mode": "command", "program": "clamscan", "args": ["--no-summary"]}}}}"#
+        )
+        .unwrap();
+
+        let config = ConfigManager::load_from_path(&temp_file.path().to_path_buf()).unwrap();
+        assert_eq!(
+            config.download_scan,
+            DownloadScan::Command {
+                program: "clamscan".to_string(),
+                args: vec!["--no-summary".to_string()],
+            }
+        );
+        assert_eq!(AppConfig::default().download_scan, DownloadScan::Off);
+    }
+
     #[test]
     fn test_load_nonexistent_file() {
         let path = PathBuf::from("/nonexistent/path/config.json");
@@ -0,0 +1,64 @@
+//! Who last changed a file in the cloud
+//!
+//! Servers may say which user caused a file event. Changes made by another
+//! user, such as in a group folder, are remembered by local path for a while
+//! so a conflict on that file can name them.
+
+use cloudreve_api::models::explorer::UserBrief;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+/// Forget a remote change after this long
+const ACTOR_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// Nickname of the last other user who changed each path, and when
+static REMOTE_ACTORS: OnceLock<Mutex<HashMap<PathBuf, (String, Instant)>>> = OnceLock::new();
+
+fn remote_actors() -> &'static Mutex<HashMap<PathBuf, (String, Instant)>> {
+    REMOTE_ACTORS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Remember that `actor` changed `path`, unless it is the user of the drive
+pub fn record_remote_actor(path: &Path, actor: &UserBrief, user_id: &str) {
+    if actor.id == user_id || actor.nickname.is_empty() {
+        return;
+    }
+    let mut actors = remote_actors().lock().unwrap();
+    actors.retain(|_, (_, at)| at.elapsed() < ACTOR_TTL);
+    actors.insert(path.to_path_buf(), (actor.nickname.clone(), Instant::now()));
+}
+
+/// Nickname of the other user who recently changed `path` in the cloud
+pub fn recent_remote_actor(path: &Path) -> Option<String> {
+    let actors = remote_actors().lock().unwrap();
+    actors
+        .get(path)
+        .filter(|(_, at)| at.elapsed() < ACTOR_TTL)
+        .map(|(nickname, _)| nickname.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_other_users_are_remembered() {
+        let path = Path::new("C:\\Sync\\group\\plan.docx");
+        let mut actor = UserBrief {
+            id: "me".to_string(),
+            nickname: "Me".to_string(),
+            group: None,
+        };
+        record_remote_actor(path, &actor, "me");
+        assert_eq!(recent_remote_actor(path), None);
+
+        actor.id = "u2".to_string();
+        actor.nickname = "Bob".to_string();
+        record_remote_actor(path, &actor, "me");
+        assert_eq!(recent_remote_actor(path).as_deref(), Some("Bob"));
+    }
+}
@@ -7,31 +7,41 @@ use crate::{
         placeholder_file::PlaceholderFile,
     },
     drive::{
+        command_queue::CommandSender,
         commands::MountCommand,
-        sync::{cloud_file_to_metadata_entry, cloud_file_to_placeholder, is_symbolic_link},
+        origin::{ChangeOrigins, EngineWrite},
+        permissions::{self, LocalOperation},
+        sync::{
+            cloud_file_to_metadata_entry, cloud_file_to_placeholder, is_symbolic_link,
+            metadata_to_placeholder,
+        },
     },
     inventory::{InventoryDb, MetadataEntry},
 };
-use tokio::sync::mpsc;
+use cloudreve_api::models::explorer::metadata;
 use uuid::Uuid;
 
 #[derive(Clone)]
 pub struct CallbackHandler {
-    command_tx: mpsc::UnboundedSender<MountCommand>,
+    command_tx: CommandSender<MountCommand>,
     id: String,
     inventory: Arc<InventoryDb>,
+    /// Local writes made by the engine, which are not pushed to the server
+    change_origins: ChangeOrigins,
 }
 
 impl CallbackHandler {
     pub fn new(
-        command_tx: mpsc::UnboundedSender<MountCommand>,
+        command_tx: CommandSender<MountCommand>,
         id: String,
         inventory: Arc<InventoryDb>,
+        change_origins: ChangeOrigins,
     ) -> Self {
         Self {
             command_tx,
             id: id,
             inventory: inventory,
+            change_origins,
         }
     }
 
@@ -52,6 +62,7 @@ impl SyncFilter for CallbackHandler {
             path: request.path().to_path_buf(),
             ticket,
             range: info.required_file_range(),
+            blob: request.file_blob().to_vec(),
             response: response_tx,
         };
         if let Err(e) = self.command_tx.send(command) {
@@ -71,7 +82,12 @@ impl SyncFilter for CallbackHandler {
 
     fn delete(&self, request: Request, ticket: ticket::Delete, _info: info::Delete) -> CResult<()> {
         tracing::debug!(target: "drive::mounts", id = %self.id, path = %request.path().display(), "Delete");
-       let _ = ticket.pass();
+        let path = request.path();
+        if !permissions::check(&self.inventory, &path, LocalOperation::Delete) {
+            permissions::notify_denied(&path, LocalOperation::Delete);
+            return Err(CloudErrorKind::AccessDenied);
+        }
+        let _ = ticket.pass();
         Ok(())
     }
 
@@ -79,6 +95,12 @@ impl SyncFilter for CallbackHandler {
         let src = request.path();
         let dest = info.target_path();
         tracing::debug!(target: "drive::mounts", id = %self.id, source_path = %src.display(), target_path = %dest.display(), "Rename");
+        // A rename mirroring the remote, nothing to push
+        if let Some(operation) = self.change_origins.caused(&dest, EngineWrite::Rename) {
+            tracing::debug!(target: "drive::mounts", id = %self.id, %operation, target_path = %dest.display(), "Skipping rename made by the engine");
+            let _ = ticket.pass();
+            return Ok(());
+        }
         let (response_tx, response_rx) = tokio::sync::oneshot::channel();
         let command = MountCommand::Rename {
             source: src.to_path_buf(),
@@ -117,6 +139,25 @@ impl SyncFilter for CallbackHandler {
         }
 
         match response_rx.blocking_recv() {
+            Ok(Ok(files)) if files.stale => {
+                // Listed from the inventory, which already has these entries
+                let mut placeholders = files
+                    .cached
+                    .iter()
+                    .filter(|meta| !meta.metadata.contains_key(metadata::SHARE_REDIRECT))
+                    .filter_map(|meta| {
+                        metadata_to_placeholder(meta).map_err(|e| {
+                            tracing::error!(target: "drive::mounts", id = %self.id, error = %e, "Failed to convert cached entry to placeholder");
+                        }).ok()
+                    })
+                    .collect::<Vec<PlaceholderFile>>();
+                if let Err(e) = ticket.pass_with_placeholder(&mut placeholders) {
+                    tracing::error!(target: "drive::mounts", id = %self.id, error = %e, "Failed to pass cached placeholders");
+                    return Err(CloudErrorKind::Unsuccessful);
+                }
+                tracing::debug!(target: "drive::mounts", id = %self.id, placeholders = %placeholders.len(), "Passed cached placeholders");
+                return Ok(());
+            }
             Ok(Ok(files)) => {
                 tracing::debug!(target: "drive::mounts", id = %self.id, files = %files.files.len(), "Received placeholders");
                 let mut placeholders = files.files.iter()
@@ -138,11 +179,14 @@ impl SyncFilter for CallbackHandler {
                 tracing::debug!(target: "drive::mounts", id = %self.id, placeholders = %placeholders.len(), "Passed placeholders");
 
                 // Insert placeholders into inventory
-                let drive_id = Uuid::parse_str(&self.id)
-                    .unwrap_or_else(|e| {
+                // A random ID here would scatter the rows across drives
+                let drive_id = match Uuid::parse_str(&self.id) {
+                    Ok(id) => id,
+                    Err(e) => {
                         tracing::error!(target: "drive::mounts", id = %self.id, error = %e, "Failed to parse drive ID");
-                        return Uuid::new_v4();
-                    });
+                        return Err(CloudErrorKind::Unsuccessful);
+                    }
+                };
                 let entries = files
                     .files
                     .iter()
@@ -0,0 +1,183 @@
+//! Coalescing of sync requests.
+//!
+//! Remote event bursts ask to sync many overlapping paths in quick
+//! succession. Requests arriving within [`SYNC_COALESCE_WINDOW`] are
+//! collected and run as one batch per mode. A path already covered by a
+//! pending one is dropped, a pending path covered by a new one is replaced,
+//! and a file next to a pending folder widens that folder to its first layer,
+//! so each folder is listed once per burst. Deeper walks are never widened:
+//! a full walk of an ancestor costs more than the listings it would save.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::drive::{mounts::Mount, sync::SyncMode};
+
+/// How long to collect sync requests before running them
+pub const SYNC_COALESCE_WINDOW: Duration = Duration::from_millis(500);
+
+fn depth(mode: SyncMode) -> u8 {
+    match mode {
+        SyncMode::PathOnly => 0,
+        SyncMode::PathAndFirstLayer => 1,
+        SyncMode::FullHierarchy => 2,
+    }
+}
+
+/// Whether syncing `ancestor` with `mode` also syncs `path` with `path_mode`
+pub(crate) fn sync_covers(
+    ancestor: &Path,
+    mode: SyncMode,
+    path: &Path,
+    path_mode: SyncMode,
+) -> bool {
+    let Ok(rest) = path.strip_prefix(ancestor) else {
+        return false;
+    };
+    match rest.components().count() {
+        0 => depth(mode) >= depth(path_mode),
+        1 => {
+            mode == SyncMode::FullHierarchy
+                || (mode == SyncMode::PathAndFirstLayer && path_mode == SyncMode::PathOnly)
+        }
+        _ => mode == SyncMode::FullHierarchy,
+    }
+}
+
+/// Sync requests waiting for the coalescing window to end
+#[derive(Debug, Default)]
+pub(crate) struct SyncCoalescer {
+    pending: Vec<(PathBuf, SyncMode)>,
+}
+
+impl SyncCoalescer {
+    /// Add a request. Returns whether it started a new batch, i.e. the caller
+    /// should schedule a flush.
+    pub fn add(&mut self, paths: Vec<PathBuf>, mode: SyncMode) -> bool {
+        let new_batch = self.pending.is_empty();
+        for path in paths {
+            self.add_path(path, mode);
+        }
+        new_batch && !self.pending.is_empty()
+    }
+
+    fn add_path(&mut self, path: PathBuf, mode: SyncMode) {
+        if self
+            .pending
+            .iter()
+            .any(|(pending, pending_mode)| sync_covers(pending, *pending_mode, &path, mode))
+        {
+            return;
+        }
+        let parent = self.pending.iter().position(|(pending, pending_mode)| {
+            *pending_mode == SyncMode::PathOnly
+                && mode == SyncMode::PathOnly
+                && path.parent() == Some(pending.as_path())
+        });
+        let (path, mode) = match parent {
+            Some(index) => (self.pending.remove(index).0, SyncMode::PathAndFirstLayer),
+            None => (path, mode),
+        };
+        self.pending
+            .retain(|(pending, pending_mode)| !sync_covers(&path, mode, pending, *pending_mode));
+        self.pending.push((path, mode));
+    }
+
+    /// Take the batch, grouped by mode with the deepest walks first
+    pub fn take(&mut self) -> Vec<(SyncMode, Vec<PathBuf>)> {
+        let mut batches: Vec<(SyncMode, Vec<PathBuf>)> = Vec::new();
+        for (path, mode) in self.pending.drain(..) {
+            match batches.iter_mut().find(|(m, _)| *m == mode) {
+                Some((_, paths)) => paths.push(path),
+                None => batches.push((mode, vec![path])),
+            }
+        }
+        batches.sort_by_key(|(mode, _)| std::cmp::Reverse(depth(*mode)));
+        batches
+    }
+}
+
+impl Mount {
+    /// Sync `local_paths` once the coalescing window ends, together with any
+    /// other requests made meanwhile
+    pub(crate) async fn queue_sync(self: &Arc<Self>, local_paths: Vec<PathBuf>, mode: SyncMode) {
+        if !self.sync_coalescer.lock().await.add(local_paths, mode) {
+            return;
+        }
+
+        let s = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(SYNC_COALESCE_WINDOW).await;
+            let batches = s.sync_coalescer.lock().await.take();
+            for (mode, local_paths) in batches {
+                tracing::debug!(target: "drive::coalesce", id = %s.id, ?mode, count = local_paths.len(), "Running coalesced sync");
+                if let Err(e) = s.sync_paths(local_paths, mode).await {
+                    tracing::error!(target: "drive::coalesce", id = %s.id, error = %e, "Failed to sync paths");
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlapping_syncs_collapse() {
+        let root = Path::new("C:\\Drive");
+        let docs = root.join("docs");
+        let mut coalescer = SyncCoalescer::default();
+
+        assert!(coalescer.add(vec![docs.join("a.txt")], SyncMode::PathOnly));
+        assert!(!coalescer.add(vec![docs.join("a.txt")], SyncMode::PathOnly));
+        // A folder walk replaces the file it contains
+        coalescer.add(vec![docs.clone()], SyncMode::FullHierarchy);
+        coalescer.add(vec![docs.join("sub").join("b.txt")], SyncMode::PathOnly);
+        // A file next to a pending folder widens the folder
+        coalescer.add(vec![root.join("photos")], SyncMode::PathOnly);
+        coalescer.add(vec![root.join("photos").join("c.jpg")], SyncMode::PathOnly);
+        coalescer.add(vec![root.join("photos").join("d.jpg")], SyncMode::PathOnly);
+        // A deeper file does not widen the folder to a full walk
+        coalescer.add(
+            vec![root.join("photos").join("2024").join("e.jpg")],
+            SyncMode::PathOnly,
+        );
+
+        assert_eq!(
+            coalescer.take(),
+            vec![
+                (SyncMode::FullHierarchy, vec![docs]),
+                (SyncMode::PathAndFirstLayer, vec![root.join("photos")]),
+                (
+                    SyncMode::PathOnly,
+                    vec![root.join("photos").join("2024").join("e.jpg")]
+                ),
+            ]
+        );
+        assert!(coalescer.take().is_empty());
+    }
+
+    #[test]
+    fn sibling_prefixes_do_not_overlap() {
+        assert!(!sync_covers(
+            Path::new("C:\\Drive\\doc"),
+            SyncMode::FullHierarchy,
+            Path::new("C:\\Drive\\docs\\a.txt"),
+            SyncMode::PathOnly,
+        ));
+        assert!(sync_covers(
+            Path::new("C:\\Drive\\docs"),
+            SyncMode::FullHierarchy,
+            Path::new("C:\\Drive\\docs"),
+            SyncMode::PathAndFirstLayer,
+        ));
+        assert!(!sync_covers(
+            Path::new("C:\\Drive\\docs"),
+            SyncMode::PathAndFirstLayer,
+            Path::new("C:\\Drive\\docs\\sub"),
+            SyncMode::FullHierarchy,
+        ));
+    }
+}
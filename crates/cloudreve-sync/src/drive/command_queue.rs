@@ -0,0 +1,474 @@
+//! Bounded command channel with two priority lanes.
+//!
+//! Commands the user is waiting on (placeholder fetches, renames, shell
+//! requests) go through the interactive lane, which is always drained first.
+//! Everything else goes through the background lane, where a command already
+//! covered by a pending one is dropped and a command that can be folded into
+//! the last pending one is merged, so an event storm does not grow the queue
+//! without bound. A full lane rejects new commands, unless the channel has an
+//! overflow command: a full background lane then drops them and queues the
+//! overflow command once, e.g. a full resync that covers everything dropped.
+//!
+//! Senders don't block, so they can be used from the OS threads that run the
+//! Cloud Files callbacks.
+//!
+//! Each command carries the correlation id of the action it was sent for:
+//! the sender's current one, or a fresh one when sent with
+//! [`CommandSender::send_user_action`]. A command merged into or covered by a
+//! pending one takes that one's id.
+//! [`CommandReceiver::recv`] makes the id of the command it returns current
+//! for the receiving task.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tokio::sync::Notify;
+
+use crate::utils::correlation;
+
+/// Interactive commands that may be pending before new ones are rejected
+pub const INTERACTIVE_CAPACITY: usize = 256;
+/// Background commands that may be pending before new ones are rejected
+pub const BACKGROUND_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lane {
+    /// Someone is waiting for the result
+    Interactive,
+    Background,
+}
+
+/// A command that can be sent through a [`CommandSender`]
+pub trait QueuedCommand: Sized {
+    fn lane(&self) -> Lane;
+
+    /// Whether running `self` makes the `newer` command redundant
+    fn covers(&self, _newer: &Self) -> bool {
+        false
+    }
+
+    /// Fold the `newer` command into `self`, or hand it back if they can't
+    /// be combined
+    fn merge(&mut self, newer: Self) -> Result<(), Self> {
+        Err(newer)
+    }
+}
+
+/// Error returned when a command can't be queued
+pub enum SendError<T> {
+    /// The lane is at capacity
+    Full(T),
+    /// The receiver is gone
+    Closed(T),
+}
+
+impl<T> SendError<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            SendError::Full(command) | SendError::Closed(command) => command,
+        }
+    }
+}
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendError::Full(_) => f.write_str("Full(..)"),
+            SendError::Closed(_) => f.write_str("Closed(..)"),
+        }
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendError::Full(_) => f.write_str("command queue is full"),
+            SendError::Closed(_) => f.write_str("command channel closed"),
+        }
+    }
+}
+
+impl<T> std::error::Error for SendError<T> {}
+
+/// Queue depth and the work saved by dropping and merging
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct CommandQueueStats {
+    pub interactive: usize,
+    pub background: usize,
+    /// Most commands pending at once
+    pub high_water: usize,
+    /// Background commands dropped because a pending one covered them
+    pub dropped: u64,
+    /// Background commands folded into a pending one
+    pub merged: u64,
+    /// Commands rejected because their lane was full
+    pub rejected: u64,
+    /// Background commands dropped for a full lane, covered by the overflow
+    /// command
+    pub overflowed: u64,
+}
+
+/// A pending command and the correlation id it was sent with
+struct Pending<T> {
+    command: T,
+    correlation_id: Option<String>,
+}
+
+struct Lanes<T> {
+    interactive: VecDeque<Pending<T>>,
+    background: VecDeque<Pending<T>>,
+    /// Overflow command queued after the background lane
+    overflow: Option<Pending<T>>,
+    receiver_alive: bool,
+}
+
+struct Shared<T> {
+    lanes: Mutex<Lanes<T>>,
+    notify: Notify,
+    senders: AtomicUsize,
+    high_water: AtomicUsize,
+    dropped: AtomicU64,
+    merged: AtomicU64,
+    rejected: AtomicU64,
+    overflowed: AtomicU64,
+    make_overflow: Option<Box<dyn Fn() -> T + Send + Sync>>,
+}
+
+impl<T> Shared<T> {
+    fn stats(&self) -> CommandQueueStats {
+        let lanes = self.lanes.lock().unwrap();
+        CommandQueueStats {
+            interactive: lanes.interactive.len(),
+            background: lanes.background.len(),
+            high_water: self.high_water.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            merged: self.merged.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+            overflowed: self.overflowed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Create a command channel
+pub fn channel<T: QueuedCommand>() -> (CommandSender<T>, CommandReceiver<T>) {
+    new_channel(None)
+}
+
+/// Create a command channel whose full background lane drops new commands
+/// and queues the command made by `overflow` instead, once until it is
+/// received
+pub fn channel_with_overflow<T: QueuedCommand>(
+    overflow: impl Fn() -> T + Send + Sync + 'static,
+) -> (CommandSender<T>, CommandReceiver<T>) {
+    new_channel(Some(Box::new(overflow)))
+}
+
+fn new_channel<T: QueuedCommand>(
+    make_overflow: Option<Box<dyn Fn() -> T + Send + Sync>>,
+) -> (CommandSender<T>, CommandReceiver<T>) {
+    let shared = Arc::new(Shared {
+        lanes: Mutex::new(Lanes {
+            interactive: VecDeque::new(),
+            background: VecDeque::new(),
+            overflow: None,
+            receiver_alive: true,
+        }),
+        notify: Notify::new(),
+        senders: AtomicUsize::new(1),
+        high_water: AtomicUsize::new(0),
+        dropped: AtomicU64::new(0),
+        merged: AtomicU64::new(0),
+        rejected: AtomicU64::new(0),
+        overflowed: AtomicU64::new(0),
+        make_overflow,
+    });
+    (
+        CommandSender {
+            shared: shared.clone(),
+        },
+        CommandReceiver { shared },
+    )
+}
+
+pub struct CommandSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T: QueuedCommand> CommandSender<T> {
+    /// Queue a command without waiting
+    pub fn send(&self, command: T) -> Result<(), SendError<T>> {
+        self.send_with_id(command, correlation::current())
+    }
+
+    /// Queue a command for a new user action, with a fresh correlation id
+    pub fn send_user_action(&self, command: T) -> Result<(), SendError<T>> {
+        self.send_with_id(command, Some(correlation::new_id()))
+    }
+
+    fn send_with_id(&self, command: T, correlation_id: Option<String>) -> Result<(), SendError<T>> {
+        let shared = &self.shared;
+        let mut lanes = shared.lanes.lock().unwrap();
+        if !lanes.receiver_alive {
+            return Err(SendError::Closed(command));
+        }
+
+        match command.lane() {
+            Lane::Interactive => {
+                if lanes.interactive.len() >= INTERACTIVE_CAPACITY {
+                    shared.rejected.fetch_add(1, Ordering::Relaxed);
+                    return Err(SendError::Full(command));
+                }
+                lanes.interactive.push_back(Pending {
+                    command,
+                    correlation_id,
+                });
+            }
+            Lane::Background => {
+                if lanes
+                    .background
+                    .iter()
+                    .any(|pending| pending.command.covers(&command))
+                {
+                    shared.dropped.fetch_add(1, Ordering::Relaxed);
+                    return Ok(());
+                }
+                let command = match lanes.background.back_mut() {
+                    Some(last) => match last.command.merge(command) {
+                        Ok(()) => {
+                            shared.merged.fetch_add(1, Ordering::Relaxed);
+                            drop(lanes);
+                            shared.notify.notify_one();
+                            return Ok(());
+                        }
+                        Err(command) => command,
+                    },
+                    None => command,
+                };
+                if lanes.background.len() >= BACKGROUND_CAPACITY {
+                    let Some(make_overflow) = &shared.make_overflow else {
+                        shared.rejected.fetch_add(1, Ordering::Relaxed);
+                        return Err(SendError::Full(command));
+                    };
+                    shared.overflowed.fetch_add(1, Ordering::Relaxed);
+                    if lanes.overflow.is_none() {
+                        lanes.overflow = Some(Pending {
+                            command: make_overflow(),
+                            correlation_id,
+                        });
+                    }
+                    return Ok(());
+                }
+                lanes.background.push_back(Pending {
+                    command,
+                    correlation_id,
+                });
+            }
+        }
+
+        let depth = lanes.interactive.len() + lanes.background.len();
+        shared.high_water.fetch_max(depth, Ordering::Relaxed);
+        drop(lanes);
+        shared.notify.notify_one();
+        Ok(())
+    }
+
+    pub fn stats(&self) -> CommandQueueStats {
+        self.shared.stats()
+    }
+}
+
+impl<T> Clone for CommandSender<T> {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::SeqCst);
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for CommandSender<T> {
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::SeqCst) == 1 {
+            // Wake the receiver so it sees the channel closed
+            self.shared.notify.notify_one();
+        }
+    }
+}
+
+impl<T> fmt::Debug for CommandSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CommandSender").finish_non_exhaustive()
+    }
+}
+
+pub struct CommandReceiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> CommandReceiver<T> {
+    /// Next command, interactive ones first, with its correlation id made
+    /// current. Returns `None` once every sender is dropped and the queue is
+    /// drained.
+    pub async fn recv(&mut self) -> Option<T> {
+        loop {
+            let notified = self.shared.notify.notified();
+            {
+                let mut lanes = self.shared.lanes.lock().unwrap();
+                let pending = lanes
+                    .interactive
+                    .pop_front()
+                    .or_else(|| lanes.background.pop_front())
+                    .or_else(|| lanes.overflow.take());
+                if let Some(pending) = pending {
+                    correlation::set_current(pending.correlation_id);
+                    return Some(pending.command);
+                }
+            }
+            if self.shared.senders.load(Ordering::SeqCst) == 0 {
+                return None;
+            }
+            notified.await;
+        }
+    }
+
+    pub fn stats(&self) -> CommandQueueStats {
+        self.shared.stats()
+    }
+}
+
+impl<T> Drop for CommandReceiver<T> {
+    fn drop(&mut self) {
+        let mut lanes = self.shared.lanes.lock().unwrap();
+        lanes.receiver_alive = false;
+        lanes.interactive.clear();
+        lanes.background.clear();
+        lanes.overflow = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum TestCommand {
+        Click(u32),
+        Sync(Vec<u32>),
+        Log(u32),
+    }
+
+    impl QueuedCommand for TestCommand {
+        fn lane(&self) -> Lane {
+            match self {
+                TestCommand::Click(_) => Lane::Interactive,
+                _ => Lane::Background,
+            }
+        }
+
+        fn covers(&self, newer: &Self) -> bool {
+            match (self, newer) {
+                (TestCommand::Sync(pending), TestCommand::Sync(paths)) => {
+                    paths.iter().all(|p| pending.contains(p))
+                }
+                _ => false,
+            }
+        }
+
+        fn merge(&mut self, newer: Self) -> Result<(), Self> {
+            match (self, newer) {
+                (TestCommand::Sync(pending), TestCommand::Sync(paths)) => {
+                    pending.extend(paths);
+                    Ok(())
+                }
+                (_, newer) => Err(newer),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn interactive_first_and_redundant_syncs_collapse() {
+        let (tx, mut rx) = channel();
+        tx.send(TestCommand::Sync(vec![1])).unwrap();
+        tx.send(TestCommand::Sync(vec![1])).unwrap();
+        tx.send(TestCommand::Sync(vec![2])).unwrap();
+        tx.send(TestCommand::Log(1)).unwrap();
+        tx.send(TestCommand::Sync(vec![3])).unwrap();
+        tx.send(TestCommand::Click(7)).unwrap();
+
+        let stats = tx.stats();
+        assert_eq!((stats.interactive, stats.background), (1, 3));
+        assert_eq!((stats.dropped, stats.merged), (1, 1));
+
+        assert_eq!(rx.recv().await, Some(TestCommand::Click(7)));
+        assert_eq!(rx.recv().await, Some(TestCommand::Sync(vec![1, 2])));
+        assert_eq!(rx.recv().await, Some(TestCommand::Log(1)));
+        assert_eq!(rx.recv().await, Some(TestCommand::Sync(vec![3])));
+
+        drop(tx);
+        assert_eq!(rx.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn full_lanes_reject() {
+        let (tx, rx) = channel();
+        for i in 0..BACKGROUND_CAPACITY as u32 {
+            tx.send(TestCommand::Log(i)).unwrap();
+        }
+        assert!(matches!(
+            tx.send(TestCommand::Log(0)),
+            Err(SendError::Full(TestCommand::Log(0)))
+        ));
+        // The interactive lane has its own capacity
+        tx.send(TestCommand::Click(1)).unwrap();
+        assert_eq!(tx.stats().rejected, 1);
+        assert_eq!(tx.stats().high_water, BACKGROUND_CAPACITY + 1);
+
+        drop(rx);
+        assert!(matches!(
+            tx.send(TestCommand::Click(2)),
+            Err(SendError::Closed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn full_background_lane_queues_the_overflow_command_once() {
+        let (tx, mut rx) = channel_with_overflow(|| TestCommand::Sync(vec![0]));
+        for i in 0..BACKGROUND_CAPACITY as u32 {
+            tx.send(TestCommand::Log(i)).unwrap();
+        }
+        tx.send(TestCommand::Log(1024)).unwrap();
+        tx.send(TestCommand::Log(1025)).unwrap();
+        let stats = tx.stats();
+        assert_eq!((stats.rejected, stats.overflowed), (0, 2));
+
+        for i in 0..BACKGROUND_CAPACITY as u32 {
+            assert_eq!(rx.recv().await, Some(TestCommand::Log(i)));
+        }
+        assert_eq!(rx.recv().await, Some(TestCommand::Sync(vec![0])));
+        drop(tx);
+        assert_eq!(rx.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn commands_carry_their_correlation_id() {
+        let (tx, mut rx) = channel();
+        correlation::scope(Some("sync".to_string()), async {
+            tx.send(TestCommand::Sync(vec![1])).unwrap();
+            tx.send(TestCommand::Sync(vec![2])).unwrap();
+        })
+        .await;
+        tx.send_user_action(TestCommand::Click(1)).unwrap();
+
+        correlation::scope(None, async {
+            assert_eq!(rx.recv().await, Some(TestCommand::Click(1)));
+            let click = correlation::current().unwrap();
+            assert_ne!(click, "sync");
+            assert_eq!(rx.recv().await, Some(TestCommand::Sync(vec![1, 2])));
+            assert_eq!(correlation::current().as_deref(), Some("sync"));
+        })
+        .await;
+    }
+}
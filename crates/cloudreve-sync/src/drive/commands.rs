@@ -1,31 +1,41 @@
 use crate::{
     cfapi::{
         filter::ticket,
-        placeholder::{LocalFileInfo, OpenOptions, PinState},
+        placeholder::{LocalFileInfo, OpenOptions, PlaceholderBlob},
         utility::WriteAt,
     },
     drive::{
+        coalesce::sync_covers,
+        command_queue::{Lane, QueuedCommand},
+        lifecycle::MountState,
         mounts::Mount,
+        offline::is_unreachable,
+        origin::EngineWrite,
+        permissions::{self, LocalOperation},
         placeholder::CrPlaceholder,
         sync::{GroupedFsEvents, SyncMode},
-        utils::{local_path_to_cr_uri, notify_shell_change},
+        utils::{
+            is_case_only_rename, local_path_to_cr_uri, notify_shell_change, on_disk_file_name,
+        },
     },
-    inventory::ConflictState,
-    tasks::TaskPayload,
+    inventory::{ConflictState, FileMetadata, IntakeReason, SyncOperation},
+    policy::Policy,
+    tasks::{BatchOperation, ResumableDownload, ShareLink, TaskPayload},
     utils::toast,
+    vfs::{ModifyAction, unpopulated_parents},
 };
 use anyhow::{Context, Result};
 use bytes::Bytes;
 use cloudreve_api::{
-    ApiError,
+    ApiError, PinMismatch,
     api::{ExplorerApi, explorer::ExplorerApiExt},
     models::{
         explorer::{
-            DeleteFileService, FileResponse, FileURLService, MoveFileService, RenameFileService,
-            metadata,
+            CreateFileService, DeleteFileService, FileResponse, FileURLService, MoveFileService,
+            RenameFileService, metadata,
         },
         uri::CrUri,
-        user::Token,
+        user::{Token, WipeRequest},
     },
 };
 use notify_debouncer_full::notify::{
@@ -36,11 +46,16 @@ use std::{
     collections::HashMap,
     ops::Range,
     path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
 };
 use tokio::sync::oneshot::Sender;
 use uuid::Uuid;
 use windows::Win32::UI::Shell::SHCNE_ATTRIBUTES;
 const PAGE_SIZE: i32 = 1000;
+/// Files deleted from the cloud by one batch of local deletions from which
+/// the user is alerted
+const MASS_DELETION_THRESHOLD: usize = 100;
 
 /// Generate a unique filename by appending a counter suffix before the extension.
 /// For example: "document.txt" -> "document (1).txt", "document (2).txt", etc.
@@ -85,6 +100,10 @@ pub struct GetPlacehodlerResult {
     pub files: Vec<FileResponse>,
     pub local_path: PathBuf,
     pub remote_path: CrUri,
+    /// Children from the inventory, listed because the server is unreachable
+    pub cached: Vec<FileMetadata>,
+    /// Whether the listing came from the inventory instead of the server
+    pub stale: bool,
 }
 
 /// Messages sent from OS threads (SyncFilter callbacks) to the async processing task
@@ -102,12 +121,18 @@ pub enum MountCommand {
     RefreshCredentials {
         credentials: Token,
     },
+    /// Refreshing the access token failed
+    RefreshFailed {
+        error: String,
+    },
     /// Credential has become invalid (401, 40020, 40089 errors)
     CredentialInvalid,
     FetchData {
         path: PathBuf,
         ticket: ticket::FetchData,
         range: Range<u64>,
+        /// Placeholder blob as stamped on the file
+        blob: Vec<u8>,
         response: Sender<Result<()>>,
     },
     ProcessFsEvents {
@@ -177,6 +202,76 @@ pub enum ManagerCommand {
     OpenSyncStatusWindow,
     /// Request to open the settings window in the UI
     OpenSettingsWindow,
+    /// A downloaded file was flagged by the scanner and quarantined
+    ThreatDetected {
+        drive_id: String,
+        path: PathBuf,
+        detail: String,
+    },
+    /// A mount moved to another lifecycle state
+    MountStateChanged {
+        drive_id: String,
+        state: MountState,
+    },
+    /// A file was quarantined after its sync failed repeatedly
+    SyncItemQuarantined {
+        drive_id: String,
+        path: PathBuf,
+        operation: SyncOperation,
+    },
+    /// A drive's credentials expired and the user has to sign in again
+    ReauthRequired {
+        drive_id: String,
+        drive_name: String,
+    },
+    /// An upload was refused because the file changed in the cloud
+    ConflictDetected {
+        drive_id: String,
+        path: PathBuf,
+    },
+    /// Many files were deleted from the cloud by one batch of local deletions
+    MassDeletion {
+        drive_id: String,
+        count: usize,
+    },
+    /// Apply an operation to files selected together
+    BatchOperation {
+        operation: BatchOperation,
+        paths: Vec<PathBuf>,
+    },
+    /// Prefetch folders selected in Explorer
+    PrefetchFolder {
+        paths: Vec<PathBuf>,
+    },
+    /// Share links were created for files selected together
+    ShareLinksCreated {
+        drive_id: String,
+        links: Vec<ShareLink>,
+    },
+    /// A server responded that it is under maintenance
+    ServerMaintenance {
+        instance_url: String,
+    },
+    /// A folder was imported and should become the sync folder of a new drive
+    FolderImported {
+        drive_id: String,
+        source: PathBuf,
+        uri: String,
+        /// Patterns for the files of the provider the folder came from
+        ignore_patterns: Vec<String>,
+    },
+    /// The server revoked the session of a drive and asks to unlink it
+    RemoteWipe {
+        drive_id: String,
+        request: WipeRequest,
+    },
+    /// The server of a drive presented a certificate not matching its pin
+    PinMismatch {
+        drive_id: String,
+        drive_name: String,
+        instance_url: String,
+        mismatch: PinMismatch,
+    },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -197,18 +292,122 @@ impl ConflictAction {
     }
 }
 
+impl QueuedCommand for MountCommand {
+    fn lane(&self) -> Lane {
+        match self {
+            MountCommand::ProcessFsEvents { .. } | MountCommand::Sync { .. } => Lane::Background,
+            _ => Lane::Interactive,
+        }
+    }
+
+    /// A pending sync covers a new one when it reaches all of its paths
+    fn covers(&self, newer: &Self) -> bool {
+        match (self, newer) {
+            (
+                MountCommand::Sync { local_paths, mode },
+                MountCommand::Sync {
+                    local_paths: newer_paths,
+                    mode: newer_mode,
+                },
+            ) => newer_paths.iter().all(|path| {
+                local_paths
+                    .iter()
+                    .any(|pending| sync_covers(pending, *mode, path, *newer_mode))
+            }),
+            _ => false,
+        }
+    }
+
+    /// Syncs with the same mode sync the union of their paths. File system
+    /// events are batched together only if both batches hold one and the
+    /// same kind, a batch is handled by kind and would otherwise reorder e.g.
+    /// a removal and the creation that followed it.
+    fn merge(&mut self, newer: Self) -> Result<(), Self> {
+        match (self, newer) {
+            (
+                MountCommand::Sync { local_paths, mode },
+                MountCommand::Sync {
+                    local_paths: newer_paths,
+                    mode: newer_mode,
+                },
+            ) if *mode == newer_mode => {
+                for path in newer_paths {
+                    if !local_paths.contains(&path) {
+                        local_paths.push(path);
+                    }
+                }
+                Ok(())
+            }
+            (
+                MountCommand::ProcessFsEvents { events },
+                MountCommand::ProcessFsEvents {
+                    events: newer_events,
+                },
+            ) if events.len() == 1
+                && newer_events.len() == 1
+                && newer_events.keys().all(|kind| events.contains_key(kind)) =>
+            {
+                for (kind, batch) in newer_events {
+                    events.entry(kind).or_default().extend(batch);
+                }
+                Ok(())
+            }
+            (_, newer) => Err(newer),
+        }
+    }
+}
+
+impl QueuedCommand for ManagerCommand {
+    fn lane(&self) -> Lane {
+        match self {
+            ManagerCommand::PersistConfig
+            | ManagerCommand::ThreatDetected { .. }
+            | ManagerCommand::MountStateChanged { .. }
+            | ManagerCommand::SyncItemQuarantined { .. }
+            | ManagerCommand::ReauthRequired { .. }
+            | ManagerCommand::PinMismatch { .. }
+            | ManagerCommand::ConflictDetected { .. }
+            | ManagerCommand::MassDeletion { .. }
+            | ManagerCommand::ShareLinksCreated { .. }
+            | ManagerCommand::FolderImported { .. } => Lane::Background,
+            _ => Lane::Interactive,
+        }
+    }
+
+    /// Persisting once saves every change made so far
+    fn covers(&self, newer: &Self) -> bool {
+        matches!(
+            (self, newer),
+            (ManagerCommand::PersistConfig, ManagerCommand::PersistConfig)
+        )
+    }
+}
+
 impl Mount {
     pub async fn fetch_data(
         &self,
         path: PathBuf,
         ticket: ticket::FetchData,
         range: Range<u64>,
+        identity: &PlaceholderBlob,
     ) -> Result<()> {
         let config = self.config.read().await;
         let remote_base = config.remote_path.clone();
         let sync_path = config.sync_path.clone();
         drop(config);
 
+        if let Some(quarantined) = self
+            .inventory
+            .get_quarantined(path.to_str().unwrap_or(""))
+            .context("failed to query quarantined file")?
+        {
+            return Err(anyhow::anyhow!("file is quarantined: {}", quarantined.detail));
+        }
+
+        // Hydrations count against the drive's download limit
+        let _slot = self.task_queue.acquire_download_slot().await?;
+        let _origin = self.change_origins.begin(&path, EngineWrite::Hydrate);
+
         let uri = local_path_to_cr_uri(path.clone(), sync_path, remote_base)
             .context("failed to convert local path to cloudreve uri")?;
 
@@ -224,21 +423,30 @@ impl Mount {
                 request.entity = Some(meta.etag.clone());
             }
         }
-        let entity_url_res = self
-            .cr_client
-            .get_file_url(&request)
-            .await
-            .context("failed to get file url")?;
-
-        // Get the download URL from the response
-        let download_url = entity_url_res
-            .urls
-            .first()
-            .context("no download URL in response")?
-            .url
-            .clone();
+        // Fall back to the entity stamped on the placeholder
+        if request.entity.is_none() {
+            request.entity = identity.entity_id.clone();
+        }
+        // Refreshes the URL and resumes if it expires during a long hydration
+        let pooled_url = self
+            .pooled_download_url(&path, &uri.to_string(), identity, request.entity.as_deref())
+            .await;
+        let mut download = match pooled_url {
+            Some(url) => {
+                ResumableDownload::with_url(
+                    self.cr_client.clone(),
+                    request,
+                    url,
+                    Some(range.clone()),
+                )
+                .await?
+            }
+            None => {
+                ResumableDownload::new(self.cr_client.clone(), request, Some(range.clone())).await?
+            }
+        };
 
-        tracing::debug!(target: "drive::commands", download_url = %download_url, "Download URL");
+        tracing::debug!(target: "drive::commands", download_url = %download.url(), "Download URL");
 
         // Calculate total bytes to fetch
         let total_bytes = range.end - range.start;
@@ -248,31 +456,12 @@ impl Mount {
         // 64KB buffer for reading from network
         const BUFFER_SIZE: usize = 65536;
 
-        // Create HTTP client and make a single range request
-        let client = reqwest::Client::new();
-        let range_header = format!("bytes={}-{}", range.start, range.end - 1);
-
-        let response = client
-            .get(&download_url)
-            .header("Range", range_header)
-            .send()
-            .await
-            .context("failed to send HTTP range request")?;
-
-        if !response.status().is_success() && response.status().as_u16() != 206 {
-            anyhow::bail!("HTTP request failed with status: {}", response.status());
-        }
-
         // Stream the response and write in 4KB-aligned chunks
-        let mut stream = response.bytes_stream();
         let mut current_offset = range.start;
         let mut bytes_transferred = 0u64;
         let mut accumulator: Vec<u8> = Vec::with_capacity(BUFFER_SIZE);
 
-        use futures::StreamExt;
-
-        while let Some(chunk_result) = stream.next().await {
-            let chunk = chunk_result.context("failed to read chunk from stream")?;
+        while let Some(chunk) = download.next_chunk().await? {
             accumulator.extend_from_slice(&chunk);
 
             // Write out all aligned chunks at once if we have enough data
@@ -326,7 +515,10 @@ impl Mount {
 
         Ok(())
     }
-    pub async fn fetch_placeholders(&self, path: PathBuf) -> Result<GetPlacehodlerResult> {
+    pub async fn fetch_placeholders(
+        self: &Arc<Self>,
+        path: PathBuf,
+    ) -> Result<GetPlacehodlerResult> {
         let config = self.config.read().await;
         let remote_base = config.remote_path.clone();
         let sync_path = config.sync_path.clone();
@@ -334,6 +526,37 @@ impl Mount {
 
         let uri = local_path_to_cr_uri(path.clone(), sync_path, remote_base)
             .context("failed to convert local path to cloudreve uri")?;
+        let mut files = match self.list_remote_folder(&uri).await {
+            Ok(files) => files,
+            Err(e) if is_unreachable(&e) => {
+                let Some(cached) = self.cached_children(&path).await? else {
+                    return Err(e);
+                };
+                tracing::info!(target: "drive::mounts", path = %path.display(), count = cached.len(), "Server unreachable, listing folder from inventory");
+                self.mark_stale(path.clone()).await;
+                return Ok(GetPlacehodlerResult {
+                    files: Vec::new(),
+                    local_path: path,
+                    remote_path: uri,
+                    cached,
+                    stale: true,
+                });
+            }
+            Err(e) => return Err(e),
+        };
+        // Left out files get no placeholder, nor an inventory entry
+        files.retain(|file| !self.excluded_by_type(&path.join(&file.name), file));
+
+        Ok(GetPlacehodlerResult {
+            files,
+            local_path: path.clone(),
+            remote_path: uri.clone(),
+            cached: Vec::new(),
+            stale: false,
+        })
+    }
+
+    async fn list_remote_folder(&self, uri: &CrUri) -> Result<Vec<FileResponse>> {
         let mut placehodlers: Vec<FileResponse> = Vec::new();
 
         let mut previous_response = None;
@@ -358,11 +581,7 @@ impl Mount {
 
         tracing::debug!(target: "drive::mounts", uri = %uri.to_string(), "Fetch file list from cloudreve");
 
-        Ok(GetPlacehodlerResult {
-            files: placehodlers,
-            local_path: path.clone(),
-            remote_path: uri.clone(),
-        })
+        Ok(placehodlers)
     }
 
     pub async fn generate_thumbnail(&self, path: PathBuf) -> Result<Bytes> {
@@ -468,6 +687,13 @@ impl Mount {
     }
 
     pub async fn rename(&self, source: PathBuf, target: PathBuf) -> Result<()> {
+        if Policy::get().force_read_only {
+            anyhow::bail!("Renaming is disabled by administrator policy");
+        }
+        if self.is_guest_share() {
+            anyhow::bail!("Files of a share mounted as a guest can't be renamed");
+        }
+
         let (sync_path, remote_path) = {
             let config = self.config.read().await;
             (config.sync_path.clone(), config.remote_path.to_string())
@@ -494,9 +720,27 @@ impl Mount {
             return Ok(());
         }
 
+        let case_only = is_case_only_rename(&source, &target);
+
         // if target and src under the same dir, trigger rename call
         let target_parent = target.parent().context("root cannot be moved")?;
         let source_parent = source.parent().context("root cannot be moved")?;
+
+        // Refuse what the server would reject before the file moves locally
+        let denied = if target_parent == source_parent {
+            (!permissions::check(&self.inventory, &source, LocalOperation::Rename))
+                .then_some((source.as_path(), LocalOperation::Rename))
+        } else if !permissions::check(&self.inventory, &source, LocalOperation::Move) {
+            Some((source.as_path(), LocalOperation::Move))
+        } else {
+            (!permissions::check(&self.inventory, target_parent, LocalOperation::Create))
+                .then_some((target_parent, LocalOperation::Create))
+        };
+        if let Some((path, operation)) = denied {
+            permissions::notify_denied(path, operation);
+            anyhow::bail!("no permission to {:?} {}", operation, path.display());
+        }
+
         if target_parent == source_parent {
             match self
                 .cr_client
@@ -511,6 +755,9 @@ impl Mount {
                 .await
             {
                 Ok(_) => {
+                    if case_only {
+                        tracing::info!(target: "drive::commands", source = %source.display(), target = %target.display(), "Propagated case-only rename");
+                    }
                     // Block the modify name events for rename (From for source, To for target)
                     self.event_blocker.register_once(
                         &EventKind::Modify(ModifyKind::Name(RenameMode::From)),
@@ -560,11 +807,75 @@ impl Mount {
         }
     }
 
-    pub async fn process_fs_events(&self, events: GroupedFsEvents) -> Result<()> {
+    pub async fn process_fs_events(self: &Arc<Self>, events: GroupedFsEvents) -> Result<()> {
+        // Local changes are never pushed to the cloud in read-only mode
+        if Policy::get().force_read_only {
+            tracing::debug!(target: "drive::commands", "Read-only policy active, skipping filesystem events");
+            return Ok(());
+        }
+        if self.is_guest_share() {
+            tracing::debug!(target: "drive::commands", "Drive is a guest share, skipping filesystem events");
+            return Ok(());
+        }
+        // Removals seen while the sync folder vanished must not reach the cloud
+        if !self.check_sync_root().await {
+            tracing::debug!(target: "drive::commands", "Sync folder is missing, skipping filesystem events");
+            return Ok(());
+        }
+        // Files rewritten like ransomware would must not replace the cloud
+        // copies
+        if self.check_suspicious_changes(&events).await {
+            tracing::debug!(target: "drive::commands", "Suspicious changes pending, skipping filesystem events");
+            return Ok(());
+        }
+
+        // Drop events of subtrees flooded by mass operations, they are
+        // reconciled as a whole once things calm down
+        let sync_root = self.config.read().await.sync_path.clone();
+        let (events, storms) = self
+            .storm
+            .lock()
+            .await
+            .filter(&sync_root, events, Instant::now());
+        for root in storms {
+            self.watch_storm(root);
+        }
+
         for (event_kind, events) in events {
             // Filter out events that were pre-registered by rename operations
             let filtered_events = self.event_blocker.filter_events(events, &event_kind);
 
+            // Filter out events caused by the engine's own writes
+            let filtered_events: Vec<Event> = filtered_events
+                .into_iter()
+                .filter(|event| {
+                    let operations: Vec<_> = event
+                        .paths
+                        .iter()
+                        .map(|path| self.change_origins.caused_event(&event_kind, path))
+                        .collect();
+                    let mut caused =
+                        !operations.is_empty() && operations.iter().all(Option::is_some);
+                    // A placeholder out of sync was changed after the engine wrote it
+                    if caused && !matches!(event_kind, EventKind::Remove(_)) {
+                        caused = event.paths.iter().all(|path| {
+                            LocalFileInfo::from_path(path)
+                                .map(|info| !info.is_placeholder() || info.in_sync())
+                                .unwrap_or(true)
+                        });
+                    }
+                    if caused {
+                        tracing::trace!(
+                            target: "drive::commands",
+                            path = %event.paths[0].display(),
+                            operations = ?operations,
+                            "Ignoring event caused by the engine"
+                        );
+                    }
+                    !caused
+                })
+                .collect();
+
             // Filter out events that are ignored
             let filtered_events: Vec<Event> = filtered_events
                 .into_iter()
@@ -812,85 +1123,61 @@ impl Mount {
         );
 
         for (_, path) in path_uri_mappings {
-            let placeholder_info = match LocalFileInfo::from_path(path.as_path()) {
-                Ok(info) => info,
+            let status = match self.vfs.status(&path) {
+                Ok(status) => status,
                 Err(e) => {
                     tracing::error!(target: "drive::commands", path = %path.display(), error = %e, "Failed to get local file info");
                     continue;
                 }
             };
-            if placeholder_info.is_directory() {
-                continue;
-            }
 
-            // For pinned file but not on disk, hydrate it
-            let pin_state = placeholder_info.pinned();
-            if pin_state == PinState::Pinned && placeholder_info.partial_on_disk() {
-                tracing::debug!(target: "drive::commands", path = %path.display(), "Hydrate pinned not on disk placeholder");
-                let mut placeholder = match OpenOptions::new().open_win32(path.as_path()) {
-                    Ok(p) => p,
-                    Err(e) => {
-                        tracing::error!(target: "drive::commands", path = %path.display(), error = %e, "Failed to open win32 file");
+            match ModifyAction::for_status(&status) {
+                ModifyAction::Hydrate => {
+                    tracing::debug!(target: "drive::commands", path = %path.display(), "Hydrate pinned not on disk placeholder");
+                    let _origin = self.change_origins.begin(&path, EngineWrite::Hydrate);
+                    if let Err(e) = self.vfs.hydrate(&path) {
+                        tracing::error!(target: "drive::commands", path = %path.display(), error = ?e, "Failed to hydrate placeholder");
                         continue;
                     }
-                };
-                if let Err(e) = placeholder.hydrate(0..) {
-                    tracing::error!(target: "drive::commands", path = %path.display(), error = %e, "Failed to hydrate placeholder");
-                    continue;
+                    tracing::trace!(target: "drive::commands", path = %path.display(), "Hydration complete");
+                    _ = notify_shell_change(&path, SHCNE_ATTRIBUTES);
                 }
-                tracing::trace!(target: "drive::commands", path = %path.display(), "Hydration complete");
-                _ = notify_shell_change(&path, SHCNE_ATTRIBUTES);
-                continue;
-            } else if pin_state == PinState::Unpinned {
-                tracing::debug!(target: "drive::commands", path = %path.display(), "Dehydrate unpinned file");
-
-                let mut placeholder = match OpenOptions::new()
-                    .open_win32_with_retry(path.as_path())
-                    .await
-                {
-                    Ok(p) => p,
-                    Err(e) => {
-                        tracing::error!(
-                            target: "drive::commands",
-                            path = %path.display(),
-                            error = %e,
-                            "Failed to open win32 file for dehydration after retries"
-                        );
-                        continue;
+                ModifyAction::Dehydrate => {
+                    tracing::debug!(target: "drive::commands", path = %path.display(), "Dehydrate unpinned file");
+                    let _origin = self.change_origins.begin(&path, EngineWrite::Hydrate);
+                    match self.vfs.dehydrate(&path).await {
+                        Ok(_) => {
+                            tracing::trace!(target: "drive::commands", path = %path.display(), "Dehydration complete");
+                            _ = notify_shell_change(&path, SHCNE_ATTRIBUTES);
+                        }
+                        Err(e) => {
+                            tracing::error!(
+                                target: "drive::commands",
+                                path = %path.display(),
+                                error = ?e,
+                                "Failed to dehydrate placeholder"
+                            );
+                        }
                     }
-                };
-
-                match placeholder.dehydrate(0..) {
-                    Ok(_) => {
-                        tracing::trace!(target: "drive::commands", path = %path.display(), "Dehydration complete");
-                        _ = notify_shell_change(&path, SHCNE_ATTRIBUTES);
+                }
+                // General modification, queue an upload task if not exist
+                ModifyAction::Upload => {
+                    if !permissions::check(&self.inventory, &path, LocalOperation::Modify) {
+                        permissions::notify_denied(&path, LocalOperation::Modify);
+                        continue;
                     }
-                    Err(e) => {
-                        tracing::error!(
-                            target: "drive::commands",
-                            path = %path.display(),
-                            error = %e,
-                            "Failed to dehydrate placeholder"
-                        );
+                    tracing::debug!(target: "drive::commands", path = %path.display(), "Queuing upload task for modified file");
+                    let payload = TaskPayload::upload(path.clone());
+                    let result = self
+                        .task_queue
+                        .enqueue(payload)
+                        .await
+                        .context("Failed to enqueue upload task");
+                    if result.is_err() {
+                        tracing::error!(target: "drive::commands", path = %path.display(), error = ?result, "Failed to enqueue upload task");
                     }
                 }
-                continue;
-            }
-
-            // General modification, quque a upload task if not exist
-            if !placeholder_info.in_sync() {
-                tracing::debug!(target: "drive::commands", path = %path.display(), "Queuing upload task for modified file");
-                let payload = TaskPayload::upload(path.clone());
-                let result = self
-                    .task_queue
-                    .enqueue(payload)
-                    .await
-                    .context("Failed to enqueue upload task");
-                if result.is_err() {
-                    tracing::error!(target: "drive::commands", path = %path.display(), error = ?result, "Failed to enqueue upload task");
-                    continue;
-                }
-                continue;
+                ModifyAction::Ignore => {}
             }
         }
 
@@ -910,7 +1197,56 @@ impl Mount {
             "Processing filesystem create events"
         );
 
-        for (_remote_uri, path) in path_uri_mappings {
+        let paths: Vec<PathBuf> = path_uri_mappings.into_values().collect();
+        // Folders are created first, which can take a while
+        self.record_intake(
+            paths
+                .iter()
+                .filter(|path| path.is_file())
+                .map(|path| (path.as_path(), IntakeReason::Created)),
+        );
+
+        // Items pasted or dropped into a folder that was never opened would sit
+        // next to children that only exist remotely. The folder is listed
+        // first, so it is complete before the new items are uploaded.
+        for folder in unpopulated_parents(self.vfs.as_ref(), &paths) {
+            tracing::debug!(target: "drive::commands", path = %folder.display(), "Populating folder before uploading new items");
+            if let Err(e) = self.vfs.populate(&folder) {
+                tracing::warn!(target: "drive::commands", path = %folder.display(), error = ?e, "Failed to populate folder");
+            }
+        }
+
+        // Folders are created inline, parents first, so uploads of their
+        // contents never race ahead of the remote folder
+        let (mut folders, files): (Vec<PathBuf>, Vec<PathBuf>) =
+            paths.into_iter().partition(|path| path.is_dir());
+        folders.sort_by_key(|path| path.components().count());
+
+        for path in folders {
+            if let Err(e) = self.create_remote_folder(&path).await {
+                tracing::warn!(target: "drive::commands", path = %path.display(), error = %e, "Failed to create remote folder, queueing upload task");
+                self.task_queue
+                    .enqueue(TaskPayload::upload(path.clone()))
+                    .await
+                    .context("Failed to enqueue upload task")?;
+                continue;
+            }
+
+            // A folder moved in from outside the sync root brings its contents without events
+            let has_children = std::fs::read_dir(&path)
+                .map(|mut entries| entries.next().is_some())
+                .unwrap_or(false);
+            if has_children {
+                self.command_tx
+                    .send(MountCommand::Sync {
+                        local_paths: vec![path.clone()],
+                        mode: SyncMode::FullHierarchy,
+                    })
+                    .context("failed to send sync command")?;
+            }
+        }
+
+        for path in files {
             let payload = TaskPayload::upload(path.clone());
 
             self.task_queue
@@ -922,6 +1258,84 @@ impl Mount {
         Ok(())
     }
 
+    /// Apply a remote case-only rename locally. The file keeps its identity, so the
+    /// placeholder is renamed in place and its inventory entry re-keyed, instead
+    /// of being deleted and recreated.
+    pub(crate) fn apply_case_rename(&self, from: &Path, to: &Path) -> Result<()> {
+        let to_str = to.to_str().context("failed to convert path to string")?;
+        let on_disk = on_disk_file_name(from);
+        if on_disk.is_some() && on_disk.as_deref() != to.file_name() {
+            tracing::info!(target: "drive::commands", from = %from.display(), to = %to.display(), "Applying case-only rename");
+            // The rename callback and the watcher must not push this change
+            // back to the server
+            let _from = self.change_origins.begin(from, EngineWrite::Rename);
+            let _to = self.change_origins.begin(to, EngineWrite::Rename);
+            std::fs::rename(from, to).context("failed to rename local file")?;
+        }
+
+        self.inventory
+            .normalize_path_case(to_str)
+            .context("failed to normalize inventory path")?;
+        Ok(())
+    }
+
+    /// Create the remote folder for a local directory and record it as an in-sync
+    /// placeholder. Ancestors missing from inventory are created first.
+    pub(crate) async fn create_remote_folder(&self, path: &Path) -> Result<()> {
+        let (drive_id, sync_root, remote_base) = {
+            let config = self.config.read().await;
+            (
+                Uuid::parse_str(&config.id)?,
+                config.sync_path.clone(),
+                config.remote_path.clone(),
+            )
+        };
+
+        let mut missing = Vec::new();
+        let mut current = Some(path);
+        while let Some(dir) = current {
+            if dir == sync_root || !dir.starts_with(&sync_root) {
+                break;
+            }
+            if dir != path
+                && self
+                    .inventory
+                    .query_by_path(dir.to_str().unwrap_or(""))
+                    .context("failed to query inventory")?
+                    .is_some()
+            {
+                break;
+            }
+            missing.push(dir.to_path_buf());
+            current = dir.parent();
+        }
+
+        for dir in missing.into_iter().rev() {
+            let uri = local_path_to_cr_uri(dir.clone(), sync_root.clone(), remote_base.clone())
+                .context("failed to convert local path to cloudreve uri")?
+                .to_string();
+            let folder = self
+                .cr_client
+                .create_file(&CreateFileService {
+                    uri,
+                    file_type: "folder".to_string(),
+                    err_on_conflict: Some(false),
+                    metadata: None,
+                })
+                .await
+                .context("failed to create remote folder")?;
+
+            tracing::info!(target: "drive::commands", path = %dir.display(), "Created remote folder");
+            CrPlaceholder::new(dir.clone(), sync_root.clone(), drive_id)
+                .with_mark_no_children(true)
+                .with_remote_file(&folder)
+                .commit(self.inventory.clone())
+                .context("failed to commit folder placeholder")?;
+        }
+
+        Ok(())
+    }
+
     /// Process filesystem delete events by synchronizing deletions with the remote server
     /// and updating the local inventory.
     ///
@@ -1002,6 +1416,22 @@ impl Mount {
                 .context("Failed to update local inventory after deletions")?;
         }
 
+        if successful_paths.len() >= MASS_DELETION_THRESHOLD {
+            tracing::warn!(
+                target: "drive::commands",
+                id = %self.id,
+                count = successful_paths.len(),
+                "Many files deleted from the cloud at once"
+            );
+            let command = ManagerCommand::MassDeletion {
+                drive_id: self.id.clone(),
+                count: successful_paths.len(),
+            };
+            if let Err(e) = self.manager_command_tx.send(command) {
+                tracing::error!(target: "drive::commands", error = %e, "Failed to send MassDeletion command");
+            }
+        }
+
         Ok(())
     }
 
@@ -0,0 +1,64 @@
+//! Local log of how a drive's credentials are used.
+//!
+//! Every sign-in, token refresh and credential update is recorded in the
+//! inventory with its time and source, so a drive that keeps asking to sign
+//! in again can be diagnosed from the history. Tokens are never recorded.
+//! When refreshing keeps failing, the user is told once before the
+//! credentials expire for good.
+
+use crate::drive::mounts::Mount;
+use crate::inventory::{CredentialEventKind, CredentialSource};
+use crate::utils::toast::send_general_text_toast;
+
+/// Failed refreshes in a row after which the user is alerted
+pub const REFRESH_FAILURE_ALERT_THRESHOLD: i64 = 5;
+
+impl Mount {
+    /// Record a use of the drive's credentials, logging failures
+    pub(crate) fn record_credential_event(
+        &self,
+        kind: CredentialEventKind,
+        source: CredentialSource,
+        detail: &str,
+    ) {
+        tracing::info!(target: "drive::credentials", id = %self.id, kind = kind.as_str(), source = source.as_str(), detail, "Credential event");
+        if let Err(e) = self
+            .inventory
+            .record_credential_event(&self.id, kind, source, detail)
+        {
+            tracing::warn!(target: "drive::credentials", id = %self.id, error = %e, "Failed to record credential event");
+        }
+    }
+
+    /// Record a failed token refresh and alert the user once refreshing
+    /// failed [`REFRESH_FAILURE_ALERT_THRESHOLD`] times in a row
+    pub(crate) async fn credential_refresh_failed(&self, error: &str) {
+        self.record_credential_event(
+            CredentialEventKind::RefreshFailed,
+            CredentialSource::AutoRefresh,
+            error,
+        );
+        let failures = match self.inventory.consecutive_refresh_failures(&self.id) {
+            Ok(failures) => failures,
+            Err(e) => {
+                tracing::warn!(target: "drive::credentials", id = %self.id, error = %e, "Failed to count refresh failures");
+                return;
+            }
+        };
+        if failures != REFRESH_FAILURE_ALERT_THRESHOLD {
+            return;
+        }
+
+        tracing::warn!(target: "drive::credentials", id = %self.id, failures, "Token refresh keeps failing");
+        let name = self.config.read().await.name.clone();
+        send_general_text_toast(
+            &t!("credentialRefreshFailingTitle"),
+            &t!(
+                "credentialRefreshFailingMessage",
+                "drive" => name,
+                "count" => failures,
+                "error" => error
+            ),
+        );
+    }
+}
@@ -0,0 +1,454 @@
+//! Identical files within a drive
+//!
+//! Files of the same size are compared by what is already known about their
+//! content: the server entity they point at and a hash of the content of
+//! files that are on disk.
+//! Placeholders are never read, so looking for duplicates hydrates nothing.
+//!
+//! Extras can then be deleted, or replaced by server-side copies of the file
+//! that is kept, which point at its content instead of storing it again. Only
+//! a hash of the content read again from disk is trusted for deleting.
+
+use crate::{
+    cfapi::placeholder::LocalFileInfo,
+    drive::{commands::MountCommand, mounts::Mount, sync::SyncMode, utils::local_path_to_cr_uri},
+    inventory::FileMetadata,
+    policy::Policy,
+};
+use anyhow::{Context, Result};
+use cloudreve_api::{
+    api::ExplorerApi,
+    models::explorer::{DeleteFileService, MoveFileService, RenameFileService},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+/// Prefix of the key holding a hash of the content read from disk
+const CONTENT_HASH_PREFIX: &str = "sha256:";
+
+/// Files with identical content
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateGroup {
+    /// Size of each file in bytes
+    pub size: u64,
+    pub files: Vec<DuplicateFile>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateFile {
+    pub path: String,
+    /// Whether the content is on disk rather than only in the cloud
+    pub hydrated: bool,
+}
+
+/// Result of [Mount::find_duplicate_files]
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DuplicateReport {
+    /// Groups of identical files, the most space first
+    pub groups: Vec<DuplicateGroup>,
+    /// Bytes freed by keeping a single file of each group
+    pub reclaimable: u64,
+}
+
+/// What to do with the extra copies of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateAction {
+    /// Delete the extras, which go to the trash like any other deletion. Both
+    /// files must be on disk, so that their content can be hashed again.
+    DeleteExtras,
+    /// Replace each extra by a server-side copy of the kept file
+    ReplaceWithReferences,
+}
+
+/// Result of [Mount::resolve_duplicates]
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DuplicateResolution {
+    /// Number of extras deleted or replaced
+    pub resolved: usize,
+    pub failed: Vec<DuplicateFailure>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateFailure {
+    pub path: String,
+    pub error: String,
+}
+
+impl Mount {
+    /// Look for files with identical content across the drive. Files with
+    /// changes not synced yet are left out.
+    pub async fn find_duplicate_files(&self) -> Result<DuplicateReport> {
+        tracing::info!(target: "drive::duplicate_files", id = %self.id, "Looking for duplicate files");
+
+        // Only files sharing their size with another one can be duplicates
+        let mut by_size: HashMap<u64, Vec<FileMetadata>> = HashMap::new();
+        for meta in self.inventory.list_drive_files(&self.id)? {
+            if meta.size > 0 && meta.conflict_state.is_none() {
+                by_size.entry(meta.size as u64).or_default().push(meta);
+            }
+        }
+
+        let mut report = DuplicateReport::default();
+        for (size, candidates) in by_size {
+            if candidates.len() < 2 {
+                continue;
+            }
+
+            let mut files = Vec::new();
+            let mut fingerprints = Vec::new();
+            for meta in candidates {
+                let path = PathBuf::from(&meta.local_path);
+                match self.fingerprint(&path, &meta).await {
+                    Ok(Some((hydrated, keys))) => {
+                        files.push(DuplicateFile {
+                            path: meta.local_path,
+                            hydrated,
+                        });
+                        fingerprints.push(keys);
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        tracing::warn!(target: "drive::duplicate_files", path = %path.display(), error = %e, "Failed to inspect file");
+                    }
+                }
+            }
+
+            for indexes in group_by_fingerprint(&fingerprints) {
+                report.reclaimable += size * (indexes.len() as u64 - 1);
+                report.groups.push(DuplicateGroup {
+                    size,
+                    files: indexes.into_iter().map(|i| files[i].clone()).collect(),
+                });
+            }
+        }
+
+        report
+            .groups
+            .sort_by_key(|group| std::cmp::Reverse(group.size * (group.files.len() as u64 - 1)));
+        tracing::info!(
+            target: "drive::duplicate_files",
+            id = %self.id,
+            groups = report.groups.len(),
+            reclaimable = report.reclaimable,
+            "Duplicate search finished"
+        );
+        Ok(report)
+    }
+
+    /// Keep `keep` and get rid of each of `extras`, which must be identical
+    /// to it. Extras are checked again first, since the drive may have
+    /// changed since they were found.
+    pub async fn resolve_duplicates(
+        &self,
+        keep: PathBuf,
+        extras: Vec<PathBuf>,
+        action: DuplicateAction,
+    ) -> Result<DuplicateResolution> {
+        if Policy::get().force_read_only || self.is_guest_share() {
+            anyhow::bail!("Drive is read-only");
+        }
+
+        let keep_keys = self
+            .fingerprint_path(&keep)
+            .await?
+            .context("Kept file is not synced")?;
+
+        // Entities find candidates, but a deletion can't be undone by the
+        // server keeping the content
+        let trusted = |key: &&String| match action {
+            DuplicateAction::DeleteExtras => key.starts_with(CONTENT_HASH_PREFIX),
+            DuplicateAction::ReplaceWithReferences => true,
+        };
+        let keep_keys: Vec<&String> = keep_keys.iter().filter(trusted).collect();
+        if keep_keys.is_empty() {
+            anyhow::bail!("Kept file must be on disk to compare it before deleting");
+        }
+
+        let mut resolution = DuplicateResolution::default();
+        let mut refresh = Vec::new();
+        for extra in extras {
+            let result = match self.fingerprint_path(&extra).await {
+                Ok(Some(keys))
+                    if keys
+                        .iter()
+                        .filter(trusted)
+                        .any(|key| keep_keys.contains(&key)) =>
+                {
+                    match action {
+                        DuplicateAction::DeleteExtras => {
+                            std::fs::remove_file(&extra).context("Failed to delete file")
+                        }
+                        DuplicateAction::ReplaceWithReferences => {
+                            self.replace_with_reference(&keep, &extra).await
+                        }
+                    }
+                }
+                Ok(Some(_)) if action == DuplicateAction::DeleteExtras => Err(anyhow::anyhow!(
+                    "File must be on disk and identical to the kept one"
+                )),
+                Ok(_) => Err(anyhow::anyhow!(
+                    "File is no longer identical to the kept one"
+                )),
+                Err(e) => Err(e),
+            };
+
+            match result {
+                Ok(()) => {
+                    resolution.resolved += 1;
+                    if action == DuplicateAction::ReplaceWithReferences {
+                        refresh.extend(extra.parent().map(Path::to_path_buf));
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(target: "drive::duplicate_files", path = %extra.display(), error = %e, "Failed to resolve duplicate");
+                    resolution.failed.push(DuplicateFailure {
+                        path: extra.display().to_string(),
+                        error: format!("{:#}", e),
+                    });
+                }
+            }
+        }
+
+        if !refresh.is_empty() {
+            self.command_tx
+                .send(MountCommand::Sync {
+                    local_paths: refresh,
+                    mode: SyncMode::PathAndFirstLayer,
+                })
+                .context("failed to send sync command")?;
+        }
+
+        tracing::info!(
+            target: "drive::duplicate_files",
+            id = %self.id,
+            action = ?action,
+            resolved = resolution.resolved,
+            failed = resolution.failed.len(),
+            "Duplicates resolved"
+        );
+        Ok(resolution)
+    }
+
+    /// Copy `keep` in place of `extra` on the server, then delete `extra`.
+    /// The extra is set aside under another name meanwhile, and put back if
+    /// the copy fails.
+    async fn replace_with_reference(&self, keep: &Path, extra: &Path) -> Result<()> {
+        let (sync_path, remote_path) = {
+            let config = self.config.read().await;
+            (config.sync_path.clone(), config.remote_path.clone())
+        };
+        let parent = extra.parent().context("root cannot be replaced")?;
+        if keep.parent() == Some(parent) {
+            anyhow::bail!("Kept file is in the same folder, delete the extra instead");
+        }
+        let keep_name = keep.file_name().context("kept file has no name")?;
+        let extra_name = extra.file_name().context("file has no name")?;
+        if keep_name != extra_name && parent.join(keep_name).exists() {
+            anyhow::bail!(
+                "{} already exists in the folder",
+                keep_name.to_string_lossy()
+            );
+        }
+        let aside_name = format!("{}.replacing", extra_name.to_string_lossy());
+        let aside = parent.join(&aside_name);
+        if aside.exists() {
+            anyhow::bail!("{} already exists in the folder", aside_name);
+        }
+
+        let uri = |path: &Path| -> Result<String> {
+            Ok(
+                local_path_to_cr_uri(path.to_path_buf(), sync_path.clone(), remote_path.clone())?
+                    .to_string(),
+            )
+        };
+        self.cr_client
+            .rename_file(&RenameFileService {
+                uri: uri(extra)?,
+                new_name: aside_name,
+            })
+            .await
+            .context("Failed to set file aside")?;
+
+        let copied = async {
+            self.cr_client
+                .move_files(&MoveFileService {
+                    uris: vec![uri(keep)?],
+                    dst: uri(parent)?,
+                    copy: Some(true),
+                })
+                .await
+                .context("Failed to copy kept file")?;
+            if keep_name != extra_name {
+                let copy = uri(&parent.join(keep_name))?;
+                let renamed = self
+                    .cr_client
+                    .rename_file(&RenameFileService {
+                        uri: copy.clone(),
+                        new_name: extra_name.to_string_lossy().to_string(),
+                    })
+                    .await
+                    .context("Failed to rename copy");
+                if let Err(e) = renamed {
+                    // Don't leave the copy behind under the kept name
+                    _ = self
+                        .cr_client
+                        .delete_files(&DeleteFileService {
+                            uris: vec![copy],
+                            unlink: None,
+                            skip_soft_delete: None,
+                        })
+                        .await;
+                    return Err(e);
+                }
+            }
+            anyhow::Ok(())
+        }
+        .await;
+        if let Err(e) = copied {
+            let restored = self
+                .cr_client
+                .rename_file(&RenameFileService {
+                    uri: uri(&aside)?,
+                    new_name: extra_name.to_string_lossy().to_string(),
+                })
+                .await;
+            if let Err(restore) = restored {
+                tracing::error!(target: "drive::duplicate_files", path = %extra.display(), error = %restore, "Failed to put file back after a failed copy");
+            }
+            return Err(e);
+        }
+
+        self.cr_client
+            .delete_files(&DeleteFileService {
+                uris: vec![uri(&aside)?],
+                unlink: None,
+                skip_soft_delete: None,
+            })
+            .await
+            .context("Failed to delete file on the server")?;
+        Ok(())
+    }
+
+    async fn fingerprint_path(&self, path: &Path) -> Result<Option<Vec<String>>> {
+        let meta = self
+            .inventory
+            .query_by_path(&path.to_string_lossy())?
+            .context("File is not in the drive")?;
+        if meta.is_folder {
+            anyhow::bail!("Folders can't be compared");
+        }
+        Ok(self.fingerprint(path, &meta).await?.map(|(_, keys)| keys))
+    }
+
+    /// Keys identifying the content of a file, along with whether it is on
+    /// disk. Files sharing any key are identical. `None` for files with
+    /// changes not synced yet, whose inventory entry may be stale.
+    async fn fingerprint(
+        &self,
+        path: &Path,
+        meta: &FileMetadata,
+    ) -> Result<Option<(bool, Vec<String>)>> {
+        let local = LocalFileInfo::from_path(path)?;
+        if !local.exists || local.is_directory || !local.in_sync() {
+            return Ok(None);
+        }
+
+        let mut keys = Vec::new();
+        if !meta.etag.is_empty() {
+            keys.push(format!("entity:{}", meta.etag));
+        }
+
+        let hydrated = local
+            .placeholder_state
+            .as_ref()
+            .is_some_and(|state| !state.partial());
+        if hydrated {
+            let path = path.to_path_buf();
+            let hash = tokio::task::spawn_blocking(move || hash_file(&path)).await??;
+            keys.push(format!("{}{}", CONTENT_HASH_PREFIX, hash));
+        }
+        Ok(Some((hydrated, keys)))
+    }
+}
+
+pub(crate) fn hash_file(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path).context("Failed to open file")?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1024 * 1024];
+    loop {
+        let read = file.read(&mut buf).context("Failed to read file")?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Group files sharing any key, directly or through other files. Returns the
+/// indexes of each group of at least two files.
+fn group_by_fingerprint(fingerprints: &[Vec<String>]) -> Vec<Vec<usize>> {
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let mut parent: Vec<usize> = (0..fingerprints.len()).collect();
+    let mut first_with_key: HashMap<&str, usize> = HashMap::new();
+    for (i, keys) in fingerprints.iter().enumerate() {
+        for key in keys {
+            match first_with_key.get(key.as_str()) {
+                Some(&j) => {
+                    let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                    parent[a] = b;
+                }
+                None => {
+                    first_with_key.insert(key, i);
+                }
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..fingerprints.len() {
+        groups.entry(root(&mut parent, i)).or_default().push(i);
+    }
+    groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|key| key.to_string()).collect()
+    }
+
+    #[test]
+    fn files_sharing_any_key_are_grouped() {
+        let fingerprints = vec![
+            keys(&["entity:a"]),
+            keys(&["entity:b", "sha256:1"]),
+            keys(&["entity:c"]),
+            keys(&["entity:a", "sha256:1"]),
+            keys(&[]),
+        ];
+        let mut groups = group_by_fingerprint(&fingerprints);
+        for group in &mut groups {
+            group.sort();
+        }
+        assert_eq!(groups, vec![vec![0, 1, 3]]);
+    }
+}
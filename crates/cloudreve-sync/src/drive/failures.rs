@@ -0,0 +1,92 @@
+//! Quarantine of files whose sync keeps failing.
+//!
+//! Every failed upload, download or rename of a file is counted in the
+//! inventory, and a success clears the count. After
+//! [`QUARANTINE_AFTER_FAILURES`] failures in a row the file is quarantined: it
+//! is no longer queued, so it can't hold up the rest of the drive, until the
+//! user retries or ignores it.
+
+use std::path::Path;
+
+use anyhow::Error;
+
+use crate::drive::command_queue::CommandSender;
+use crate::drive::commands::ManagerCommand;
+use crate::inventory::{InventoryDb, SyncFailureState, SyncOperation};
+use crate::tasks::TaskKind;
+
+/// Failures in a row after which a file is quarantined
+pub const QUARANTINE_AFTER_FAILURES: i32 = 5;
+
+/// Operation a task performs, if its failures are counted
+pub(crate) fn task_operation(kind: &TaskKind) -> Option<SyncOperation> {
+    match kind {
+        TaskKind::Upload => Some(SyncOperation::Upload),
+        TaskKind::Download => Some(SyncOperation::Download),
+        TaskKind::Custom(_) => None,
+    }
+}
+
+/// Whether `operation` on `path` is quarantined or ignored and must not be
+/// attempted
+pub(crate) fn is_blocked(inventory: &InventoryDb, path: &Path, operation: SyncOperation) -> bool {
+    match inventory.sync_failure_state(&path.to_string_lossy(), operation) {
+        Ok(state) => state.is_some_and(|state| state.is_blocked()),
+        Err(e) => {
+            tracing::warn!(target: "drive::failures", path = %path.display(), error = %e, "Failed to query sync failure state");
+            false
+        }
+    }
+}
+
+/// Count a failed `operation` on `path`, and report the file once it gets
+/// quarantined
+pub(crate) fn record_failure(
+    inventory: &InventoryDb,
+    manager_command_tx: &CommandSender<ManagerCommand>,
+    drive_id: &str,
+    path: &Path,
+    operation: SyncOperation,
+    error: &Error,
+) {
+    let failure = match inventory.record_sync_failure(
+        drive_id,
+        &path.to_string_lossy(),
+        operation,
+        &format!("{:#}", error),
+        QUARANTINE_AFTER_FAILURES,
+    ) {
+        Ok(failure) => failure,
+        Err(e) => {
+            tracing::warn!(target: "drive::failures", path = %path.display(), error = %e, "Failed to record sync failure");
+            return;
+        }
+    };
+
+    // Only the failure that crossed the threshold is reported
+    if failure.state == SyncFailureState::Quarantined
+        && failure.failures == QUARANTINE_AFTER_FAILURES.max(1)
+    {
+        tracing::warn!(target: "drive::failures", drive_id = %drive_id, path = %path.display(), operation = operation.as_str(), failures = failure.failures, "File quarantined after repeated sync failures");
+        if let Err(e) = manager_command_tx.send(ManagerCommand::SyncItemQuarantined {
+            drive_id: drive_id.to_string(),
+            path: path.to_path_buf(),
+            operation,
+        }) {
+            tracing::warn!(target: "drive::failures", error = %e, "Failed to send SyncItemQuarantined command");
+        }
+    }
+}
+
+/// Forget earlier failures of `operation` on `path` after it succeeded
+pub(crate) fn clear_failure(inventory: &InventoryDb, path: &Path, operation: SyncOperation) {
+    match inventory.clear_sync_failure(&path.to_string_lossy(), operation) {
+        Ok(true) => {
+            tracing::debug!(target: "drive::failures", path = %path.display(), operation = operation.as_str(), "Sync failures cleared");
+        }
+        Ok(false) => {}
+        Err(e) => {
+            tracing::warn!(target: "drive::failures", path = %path.display(), error = %e, "Failed to clear sync failures");
+        }
+    }
+}
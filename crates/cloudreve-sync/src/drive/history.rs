@@ -0,0 +1,161 @@
+//! Timeline of what happened to one file, for the file details panel.
+//!
+//! Transfers come from the task records kept in the inventory, versions from
+//! the entities the server stores for the file.
+
+use crate::{
+    drive::{mounts::Mount, utils::local_path_to_cr_uri},
+    inventory::{ConflictState, TaskStatus},
+    tasks::TaskKind,
+};
+use anyhow::{Context, Result};
+use cloudreve_api::{api::ExplorerApi, models::explorer::GetFileInfoService};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Entity type of a file version, as opposed to thumbnails or live photos
+pub(crate) const VERSION_ENTITY: i32 = 0;
+
+/// What happened to a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileHistoryKind {
+    /// A version was stored on the server
+    Version,
+    /// An older version was made current again
+    Restored,
+    Uploaded,
+    Downloaded,
+    /// A transfer failed
+    Failed,
+    /// An upload was refused because the file changed in the cloud
+    Conflicted,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileHistoryEntry {
+    pub kind: FileHistoryKind,
+    /// Unix timestamp
+    pub at: i64,
+    /// Size of the version or of the transfer in bytes
+    pub size: Option<i64>,
+    /// Who stored a version, or why a transfer failed
+    pub detail: Option<String>,
+}
+
+impl Mount {
+    /// Timeline of the file at `path`, oldest first. Versions are left out
+    /// when the server can't be reached.
+    pub async fn get_file_history(&self, path: PathBuf) -> Result<Vec<FileHistoryEntry>> {
+        let path_str = path.to_string_lossy().to_string();
+        let conflicted = self
+            .inventory
+            .query_by_path(&path_str)?
+            .is_some_and(|meta| meta.conflict_state == Some(ConflictState::Pending));
+
+        let tasks = self.inventory.list_tasks_for_path(&self.id, &path_str)?;
+        // A pending conflict comes from the last upload that failed
+        let conflicting_upload = tasks.iter().rposition(|task| {
+            task.task_type == TaskKind::Upload.as_str() && task.status == TaskStatus::Failed
+        });
+
+        let mut history = Vec::new();
+        for (i, task) in tasks.iter().enumerate() {
+            let kind = match (TaskKind::from_str(&task.task_type), task.status) {
+                (Some(TaskKind::Upload), TaskStatus::Completed) => FileHistoryKind::Uploaded,
+                (Some(TaskKind::Download), TaskStatus::Completed) => FileHistoryKind::Downloaded,
+                (Some(TaskKind::Upload), TaskStatus::Failed)
+                    if conflicted && conflicting_upload == Some(i) =>
+                {
+                    FileHistoryKind::Conflicted
+                }
+                (_, TaskStatus::Failed) => FileHistoryKind::Failed,
+                _ => continue,
+            };
+            history.push(FileHistoryEntry {
+                kind,
+                at: task.updated_at,
+                size: (task.total_bytes > 0).then_some(task.total_bytes),
+                detail: task.error.clone(),
+            });
+        }
+
+        match self.remote_versions(&path).await {
+            Ok(versions) => history.extend(versions),
+            Err(e) => {
+                tracing::debug!(target: "drive::history", path = %path.display(), error = %e, "Versions unavailable");
+            }
+        }
+
+        history.sort_by_key(|entry| entry.at);
+        Ok(history)
+    }
+
+    async fn remote_versions(&self, path: &Path) -> Result<Vec<FileHistoryEntry>> {
+        let (sync_root, remote_base) = {
+            let config = self.config.read().await;
+            (config.sync_path.clone(), config.remote_path.clone())
+        };
+        let uri = local_path_to_cr_uri(path.to_path_buf(), sync_root, remote_base)
+            .context("failed to convert local path to cloudreve uri")?
+            .to_string();
+        let info = self
+            .cr_client
+            .get_file_info(&GetFileInfoService {
+                uri: Some(uri),
+                id: None,
+                extended: Some(true),
+                folder_summary: None,
+            })
+            .await
+            .context("failed to get file info")?;
+
+        let versions: Vec<_> = info
+            .extended_info
+            .and_then(|extended| extended.entities)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|entity| entity.entity_type == VERSION_ENTITY)
+            .collect();
+        let mut history: Vec<_> = versions
+            .iter()
+            .filter_map(|version| {
+                Some(FileHistoryEntry {
+                    kind: FileHistoryKind::Version,
+                    at: parse_time(&version.created_at)?,
+                    size: Some(version.size),
+                    detail: version
+                        .created_by
+                        .as_ref()
+                        .map(|user| user.nickname.clone()),
+                })
+            })
+            .collect();
+
+        // The current version is the newest one, unless an older one was
+        // made current again
+        let newest = versions
+            .iter()
+            .max_by_key(|version| parse_time(&version.created_at));
+        let restored = info
+            .primary_entity
+            .as_deref()
+            .and_then(|id| versions.iter().find(|version| version.id == id))
+            .filter(|current| newest.is_some_and(|newest| newest.id != current.id));
+        if let Some(current) = restored {
+            history.extend(parse_time(&info.updated_at).map(|at| FileHistoryEntry {
+                kind: FileHistoryKind::Restored,
+                at,
+                size: Some(current.size),
+                detail: None,
+            }));
+        }
+        Ok(history)
+    }
+}
+
+pub(crate) fn parse_time(value: &str) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|time| time.timestamp())
+}
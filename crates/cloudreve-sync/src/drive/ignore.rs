@@ -75,6 +75,8 @@ impl IgnoreMatcher {
         builder.add(Glob::new("**/~*")?);
         builder.add(Glob::new("**/.~lock.*")?);
         builder.add(Glob::new("**/~*.tmp")?);
+        // Folder icon customization written by the client at the sync root
+        builder.add(Glob::new(crate::drive::label::DESKTOP_INI)?);
 
         let globset = builder
             .build()
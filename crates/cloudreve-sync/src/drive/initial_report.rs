@@ -0,0 +1,241 @@
+//! Verification report of a drive's first full sync.
+//!
+//! Users moving from another sync tool want to know that everything made it
+//! across. Once the first full sync of a drive finishes, the sync root is
+//! walked once more and its entries are counted by state, together with the
+//! files that were skipped and why and the open conflicts. The report is
+//! stored in the inventory and kept for the life of the drive.
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::cfapi::placeholder::LocalFileInfo;
+use crate::drive::mounts::Mount;
+use crate::inventory::{SyncFailureState, SyncOperation};
+
+/// Skipped files listed in the report at most, the rest are only counted
+const MAX_LISTED_SKIPPED: usize = 500;
+/// Conflicts listed in the report at most
+const MAX_LISTED_CONFLICTS: usize = 500;
+
+/// Why a file is not synced
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SkipReason {
+    /// Matches one of the drive's ignore patterns
+    Ignored,
+    /// Flagged by the download scanner
+    Quarantined { detail: String },
+    /// Failed to sync too often, or the user chose to stop syncing it
+    SyncFailed {
+        operation: SyncOperation,
+        state: SyncFailureState,
+        error: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedFile {
+    pub path: String,
+    pub reason: SkipReason,
+}
+
+/// Counts and details of a drive's sync root after its first full sync
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InitialSyncReport {
+    /// When the report was written, Unix timestamp
+    pub generated_at: i64,
+    pub folders: u64,
+    pub files: u64,
+    /// Total size of the synced files in bytes, whether on disk or not
+    pub total_bytes: u64,
+    /// Files in sync with the server
+    pub in_sync: u64,
+    /// Files in sync whose content is only in the cloud
+    pub cloud_only: u64,
+    /// Files not in sync yet, mostly waiting for their upload
+    pub pending: u64,
+    pub skipped: Vec<SkippedFile>,
+    /// Skipped files in total, including those not listed
+    pub skipped_count: u64,
+    /// Files with an unresolved conflict
+    pub conflicts: Vec<String>,
+    pub conflict_count: u64,
+    /// Remote files left out by the drive's type filter
+    #[serde(default)]
+    pub type_filtered: u64,
+}
+
+impl InitialSyncReport {
+    fn count_entry(&mut self, local: &LocalFileInfo) {
+        if local.is_directory {
+            self.folders += 1;
+            return;
+        }
+        self.files += 1;
+        self.total_bytes += local.file_size.unwrap_or(0);
+        if local.is_placeholder() && local.in_sync() {
+            self.in_sync += 1;
+            if local.partial_on_disk() {
+                self.cloud_only += 1;
+            }
+        } else {
+            self.pending += 1;
+        }
+    }
+
+    fn skip(&mut self, path: String, reason: SkipReason) {
+        self.skipped_count += 1;
+        if self.skipped.len() < MAX_LISTED_SKIPPED {
+            self.skipped.push(SkippedFile { path, reason });
+        }
+    }
+
+    fn conflict(&mut self, path: String) {
+        self.conflict_count += 1;
+        if self.conflicts.len() < MAX_LISTED_CONFLICTS {
+            self.conflicts.push(path);
+        }
+    }
+}
+
+impl Mount {
+    /// Write the report of the first full sync, unless the drive has one
+    pub(crate) async fn write_initial_sync_report(&self) {
+        match self.inventory.has_initial_sync_report(&self.id) {
+            Ok(false) => {}
+            Ok(true) => return,
+            Err(e) => {
+                tracing::warn!(target: "drive::initial_report", id = %self.id, error = ?e, "Failed to check initial sync report");
+                return;
+            }
+        }
+
+        let result = self.build_initial_sync_report().await.and_then(|report| {
+            let json = serde_json::to_string(&report)
+                .context("failed to serialize initial sync report")?;
+            self.inventory.save_initial_sync_report(&self.id, &json)?;
+            Ok(report)
+        });
+        match result {
+            Ok(report) => {
+                tracing::info!(
+                    target: "drive::initial_report",
+                    id = %self.id,
+                    folders = report.folders,
+                    files = report.files,
+                    bytes = report.total_bytes,
+                    pending = report.pending,
+                    skipped = report.skipped_count,
+                    conflicts = report.conflict_count,
+                    "Initial sync report written"
+                );
+            }
+            Err(e) => {
+                tracing::warn!(target: "drive::initial_report", id = %self.id, error = ?e, "Failed to write initial sync report");
+            }
+        }
+    }
+
+    async fn build_initial_sync_report(&self) -> Result<InitialSyncReport> {
+        let sync_root = self.get_sync_path().await;
+        let mut report = InitialSyncReport {
+            generated_at: Utc::now().timestamp(),
+            ..Default::default()
+        };
+
+        let mut pending = vec![sync_root];
+        while let Some(dir) = pending.pop() {
+            let entries = match std::fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    tracing::debug!(target: "drive::initial_report", id = %self.id, path = %dir.display(), error = %e, "Failed to read folder");
+                    continue;
+                }
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if self.ignore_matcher.is_match(&path) {
+                    report.skip(path.display().to_string(), SkipReason::Ignored);
+                    continue;
+                }
+                let local = LocalFileInfo::from_path(&path).unwrap_or(LocalFileInfo::missing());
+                if !local.exists {
+                    continue;
+                }
+                // Listing an unpopulated folder would fetch it from remote
+                if local.is_directory && (!local.is_placeholder() || local.is_folder_populated()) {
+                    pending.push(path);
+                }
+                report.count_entry(&local);
+            }
+        }
+
+        for file in self.inventory.list_quarantined(&self.id)? {
+            report.skip(
+                file.local_path,
+                SkipReason::Quarantined {
+                    detail: file.detail,
+                },
+            );
+        }
+        let failures = self.inventory.list_sync_failures(
+            &self.id,
+            &[SyncFailureState::Quarantined, SyncFailureState::Ignored],
+        )?;
+        for failure in failures {
+            report.skip(
+                failure.local_path,
+                SkipReason::SyncFailed {
+                    operation: failure.operation,
+                    state: failure.state,
+                    error: failure.last_error,
+                },
+            );
+        }
+        for file in self.inventory.list_conflicts(&self.id)? {
+            report.conflict(file.local_path);
+        }
+        report.type_filtered = self.type_filtered_count() as u64;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skipped_files_are_counted_beyond_the_listed_ones() {
+        let mut report = InitialSyncReport::default();
+        for i in 0..MAX_LISTED_SKIPPED + 5 {
+            report.skip(format!("C:\\Drive\\{i}.tmp"), SkipReason::Ignored);
+        }
+        assert_eq!(report.skipped.len(), MAX_LISTED_SKIPPED);
+        assert_eq!(report.skipped_count as usize, MAX_LISTED_SKIPPED + 5);
+    }
+
+    #[test]
+    fn report_round_trips_through_json() {
+        let mut report = InitialSyncReport {
+            files: 2,
+            total_bytes: 10,
+            ..Default::default()
+        };
+        report.skip(
+            "C:\\Drive\\a.exe".into(),
+            SkipReason::SyncFailed {
+                operation: SyncOperation::Upload,
+                state: SyncFailureState::Quarantined,
+                error: "too large".into(),
+            },
+        );
+        report.conflict("C:\\Drive\\b.txt".into());
+
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(json.contains("\"kind\":\"sync_failed\""));
+        let parsed: InitialSyncReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, report);
+    }
+}
@@ -0,0 +1,92 @@
+//! Uploads found but not queued yet.
+//!
+//! A sync can find thousands of files to upload, and queues their tasks one
+//! after the other. Files found to need an upload are recorded in the
+//! inventory first, and each entry is removed once its upload task exists
+//! (see [`TaskQueue::enqueue`](crate::tasks::TaskQueue::enqueue)) or the
+//! upload turned out to be unneeded. Entries left by a quit or crash in
+//! between are queued again when the drive is mounted.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::drive::mounts::Mount;
+use crate::inventory::IntakeReason;
+use crate::tasks::TaskPayload;
+
+impl Mount {
+    /// Record files found to need an upload, before their tasks are queued
+    pub(crate) fn record_intake<'a>(
+        &self,
+        entries: impl IntoIterator<Item = (&'a Path, IntakeReason)>,
+    ) {
+        let entries: Vec<_> = entries
+            .into_iter()
+            .filter_map(|(path, reason)| Some((path.to_str()?.to_string(), reason)))
+            .collect();
+        if let Err(e) = self.inventory.record_pending_intake(&self.id, &entries) {
+            tracing::warn!(target: "drive::intake", id = %self.id, count = entries.len(), error = ?e, "Failed to record pending intake");
+        }
+    }
+
+    /// Forget files whose upload is queued elsewhere or no longer needed
+    pub(crate) fn settle_intake(&self, paths: &[&Path]) {
+        let paths: Vec<&str> = paths.iter().filter_map(|path| path.to_str()).collect();
+        if let Err(e) = self.inventory.remove_pending_intake(&self.id, &paths) {
+            tracing::warn!(target: "drive::intake", id = %self.id, error = ?e, "Failed to remove pending intake");
+        }
+    }
+
+    /// Queue the uploads recorded before the last shutdown in the background
+    pub fn spawn_intake_resume(self: &Arc<Self>) {
+        let s = self.clone();
+        tokio::spawn(async move {
+            s.resume_pending_intake().await;
+        });
+    }
+
+    async fn resume_pending_intake(&self) {
+        let pending = match self.inventory.list_pending_intake(&self.id) {
+            Ok(pending) => pending,
+            Err(e) => {
+                tracing::error!(target: "drive::intake", id = %self.id, error = ?e, "Failed to list pending intake");
+                return;
+            }
+        };
+        if pending.is_empty() {
+            return;
+        }
+        tracing::info!(target: "drive::intake", id = %self.id, count = pending.len(), "Resuming uploads found before shutdown");
+
+        let mut queued = 0usize;
+        for entry in pending {
+            let path = Path::new(&entry.local_path);
+            let status = match self.vfs.status(path) {
+                Ok(status) => status,
+                Err(e) => {
+                    tracing::debug!(target: "drive::intake", id = %self.id, path = %path.display(), error = ?e, "Failed to get local file info");
+                    continue;
+                }
+            };
+            // Deleted since, or uploaded before the entry was removed
+            if !status.exists || (status.is_placeholder && status.in_sync) {
+                self.settle_intake(&[path]);
+                continue;
+            }
+
+            // The queue removes the entry once the task exists
+            match self
+                .task_queue
+                .enqueue(TaskPayload::upload(path.to_path_buf()))
+                .await
+            {
+                Ok(_) => queued += 1,
+                Err(e) => {
+                    tracing::warn!(target: "drive::intake", id = %self.id, path = %path.display(), reason = entry.reason.as_str(), error = %e, "Failed to resume upload");
+                    self.settle_intake(&[path]);
+                }
+            }
+        }
+        tracing::info!(target: "drive::intake", id = %self.id, queued, "Resumed uploads found before shutdown");
+    }
+}
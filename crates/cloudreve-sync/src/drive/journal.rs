@@ -0,0 +1,653 @@
+//! Offline change replay from the NTFS USN change journal.
+//!
+//! The filesystem watcher only sees changes made while the app is running. On
+//! shutdown the current journal position is recorded per drive; on the next
+//! start the records written since then are replayed as filesystem events. If
+//! the journal was recreated or has wrapped past the checkpoint, the replay is
+//! reported as unavailable and startup falls back to a full reconcile.
+
+use crate::drive::{
+    commands::{ManagerCommand, MountCommand},
+    mounts::Mount,
+    resume::LocalReplay,
+    sync::GroupedFsEvents,
+};
+use anyhow::{Context, Result};
+use notify_debouncer_full::notify::{
+    Event, EventKind,
+    event::{CreateKind, ModifyKind, RemoveKind},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashMap},
+    ffi::{OsStr, OsString, c_void},
+    mem::size_of,
+    os::windows::ffi::{OsStrExt, OsStringExt},
+    path::{Path, PathBuf},
+};
+use windows::{
+    Win32::{
+        Foundation::{CloseHandle, HANDLE},
+        Storage::FileSystem::{
+            CreateFileW, FILE_ACCESS_RIGHTS, FILE_FLAG_BACKUP_SEMANTICS,
+            FILE_FLAGS_AND_ATTRIBUTES, FILE_ID_DESCRIPTOR, FILE_ID_DESCRIPTOR_0, FILE_NAME_NORMALIZED,
+            FILE_SHARE_DELETE, FILE_SHARE_READ, FILE_SHARE_WRITE, FileIdType,
+            GetFinalPathNameByHandleW, GetVolumeInformationW, GetVolumePathNameW, OPEN_EXISTING,
+            OpenFileById,
+        },
+        System::{
+            IO::DeviceIoControl,
+            Ioctl::{
+                FSCTL_QUERY_USN_JOURNAL, FSCTL_READ_UNPRIVILEGED_USN_JOURNAL,
+                READ_USN_JOURNAL_DATA_V0, USN_JOURNAL_DATA_V0, USN_REASON_DATA_EXTEND,
+                USN_REASON_DATA_OVERWRITE, USN_REASON_DATA_TRUNCATION, USN_REASON_FILE_CREATE,
+                USN_REASON_FILE_DELETE, USN_REASON_RENAME_NEW_NAME, USN_REASON_RENAME_OLD_NAME,
+                USN_SOURCE_DATA_MANAGEMENT,
+            },
+        },
+    },
+    core::PCWSTR,
+};
+
+/// Size of the buffer used for each journal read
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+/// Give up and reconcile the whole drive beyond this many records
+const MAX_REPLAY_RECORDS: usize = 500_000;
+
+const REASON_MASK: u32 = USN_REASON_FILE_CREATE
+    | USN_REASON_FILE_DELETE
+    | USN_REASON_DATA_OVERWRITE
+    | USN_REASON_DATA_EXTEND
+    | USN_REASON_DATA_TRUNCATION
+    | USN_REASON_RENAME_OLD_NAME
+    | USN_REASON_RENAME_NEW_NAME;
+
+/// Position in a volume's change journal
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UsnCheckpoint {
+    pub journal_id: u64,
+    pub next_usn: i64,
+}
+
+/// A single change read from the journal, with paths resolved at read time
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum JournalChange {
+    Changed(PathBuf),
+    Removed(PathBuf),
+    Renamed { file_ref: u64, from: PathBuf, to: PathBuf },
+}
+
+/// Result of reading the journal since a checkpoint
+enum JournalRead {
+    Changes(Vec<JournalChange>),
+    /// The checkpoint is no longer covered by the journal
+    Reset,
+}
+
+/// Local changes under a sync root, reduced to what needs to be replayed
+#[derive(Debug, Default, PartialEq, Eq)]
+struct ReplayPlan {
+    renames: Vec<(PathBuf, PathBuf)>,
+    removed: BTreeSet<PathBuf>,
+    changed: BTreeSet<PathBuf>,
+}
+
+impl ReplayPlan {
+    fn is_empty(&self) -> bool {
+        self.renames.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+struct VolumeHandle(HANDLE);
+
+impl Drop for VolumeHandle {
+    fn drop(&mut self) {
+        unsafe {
+            _ = CloseHandle(self.0);
+        }
+    }
+}
+
+fn to_wide(s: &OsStr) -> Vec<u16> {
+    s.encode_wide().chain(std::iter::once(0)).collect()
+}
+
+fn from_wide(buf: &[u16]) -> OsString {
+    let len = buf.iter().position(|c| *c == 0).unwrap_or(buf.len());
+    OsString::from_wide(&buf[..len])
+}
+
+/// Open the NTFS volume containing `path` for journal access.
+fn open_volume(path: &Path) -> Result<VolumeHandle> {
+    let mut mount_point = [0u16; 261];
+    unsafe { GetVolumePathNameW(PCWSTR(to_wide(path.as_os_str()).as_ptr()), &mut mount_point) }
+        .context("failed to get volume path")?;
+
+    let mut fs_name = [0u16; 32];
+    unsafe {
+        GetVolumeInformationW(
+            PCWSTR(mount_point.as_ptr()),
+            None,
+            None,
+            None,
+            None,
+            Some(&mut fs_name),
+        )
+    }
+    .context("failed to get volume information")?;
+    if from_wide(&fs_name) != "NTFS" {
+        anyhow::bail!("volume is not NTFS");
+    }
+
+    // "C:\" -> "\\.\C:"
+    let mount_point = from_wide(&mount_point);
+    let device = format!(r"\\.\{}", mount_point.to_string_lossy().trim_end_matches('\\'));
+    let handle = unsafe {
+        CreateFileW(
+            PCWSTR(to_wide(OsStr::new(&device)).as_ptr()),
+            0,
+            FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+            None,
+            OPEN_EXISTING,
+            FILE_FLAGS_AND_ATTRIBUTES(0),
+            None,
+        )
+    }
+    .with_context(|| format!("failed to open volume {}", device))?;
+    Ok(VolumeHandle(handle))
+}
+
+fn query_journal(volume: &VolumeHandle) -> Result<USN_JOURNAL_DATA_V0> {
+    let mut data = USN_JOURNAL_DATA_V0::default();
+    let mut returned = 0u32;
+    unsafe {
+        DeviceIoControl(
+            volume.0,
+            FSCTL_QUERY_USN_JOURNAL,
+            None,
+            0,
+            Some(&mut data as *mut _ as *mut c_void),
+            size_of::<USN_JOURNAL_DATA_V0>() as u32,
+            Some(&mut returned),
+            None,
+        )
+    }
+    .context("failed to query usn journal")?;
+    Ok(data)
+}
+
+/// Resolve the current path of a directory by its file reference number.
+fn resolve_by_id(volume: &VolumeHandle, file_ref: u64) -> Option<PathBuf> {
+    let descriptor = FILE_ID_DESCRIPTOR {
+        dwSize: size_of::<FILE_ID_DESCRIPTOR>() as u32,
+        Type: FileIdType,
+        Anonymous: FILE_ID_DESCRIPTOR_0 {
+            FileId: file_ref as i64,
+        },
+    };
+    let handle = unsafe {
+        OpenFileById(
+            volume.0,
+            &descriptor,
+            FILE_ACCESS_RIGHTS(0),
+            FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+            None,
+            FILE_FLAG_BACKUP_SEMANTICS,
+        )
+    }
+    .ok()?;
+    let handle = VolumeHandle(handle);
+
+    let mut buf = vec![0u16; 1024];
+    let len = unsafe { GetFinalPathNameByHandleW(handle.0, &mut buf, FILE_NAME_NORMALIZED) } as usize;
+    if len == 0 || len > buf.len() {
+        return None;
+    }
+    let path = from_wide(&buf[..len]);
+    let path = path.to_string_lossy();
+    Some(PathBuf::from(path.strip_prefix(r"\\?\").unwrap_or(&path)))
+}
+
+/// Get the current end of the journal on the volume containing `path`.
+pub fn current_checkpoint(path: &Path) -> Result<UsnCheckpoint> {
+    let volume = open_volume(path)?;
+    let data = query_journal(&volume)?;
+    Ok(UsnCheckpoint {
+        journal_id: data.UsnJournalID,
+        next_usn: data.NextUsn,
+    })
+}
+
+/// Read all changes on the volume containing `path` since `checkpoint`,
+/// returning them along with the checkpoint to resume from next time.
+fn read_changes(path: &Path, checkpoint: &UsnCheckpoint) -> Result<(JournalRead, UsnCheckpoint)> {
+    let volume = open_volume(path)?;
+    let data = query_journal(&volume)?;
+    let next = UsnCheckpoint {
+        journal_id: data.UsnJournalID,
+        next_usn: data.NextUsn,
+    };
+
+    if data.UsnJournalID != checkpoint.journal_id || checkpoint.next_usn < data.FirstUsn {
+        return Ok((JournalRead::Reset, next));
+    }
+
+    let mut parents: HashMap<u64, Option<PathBuf>> = HashMap::new();
+    let mut pending_renames: HashMap<u64, PathBuf> = HashMap::new();
+    let mut changes = Vec::new();
+    let mut records = 0usize;
+    let mut buf = vec![0u8; READ_BUFFER_SIZE];
+    let mut start_usn = checkpoint.next_usn;
+
+    while start_usn < data.NextUsn {
+        let request = READ_USN_JOURNAL_DATA_V0 {
+            StartUsn: start_usn,
+            ReasonMask: REASON_MASK,
+            ReturnOnlyOnClose: 0,
+            Timeout: 0,
+            BytesToWaitFor: 0,
+            UsnJournalID: data.UsnJournalID,
+        };
+        let mut returned = 0u32;
+        unsafe {
+            DeviceIoControl(
+                volume.0,
+                FSCTL_READ_UNPRIVILEGED_USN_JOURNAL,
+                Some(&request as *const _ as *const c_void),
+                size_of::<READ_USN_JOURNAL_DATA_V0>() as u32,
+                Some(buf.as_mut_ptr() as *mut c_void),
+                buf.len() as u32,
+                Some(&mut returned),
+                None,
+            )
+        }
+        .context("failed to read usn journal")?;
+
+        let returned = returned as usize;
+        if returned < 8 {
+            break;
+        }
+        let next_usn = i64::from_le_bytes(buf[0..8].try_into().unwrap());
+
+        let mut offset = 8;
+        while offset + 60 <= returned {
+            let record = &buf[offset..returned];
+            let length = u32::from_le_bytes(record[0..4].try_into().unwrap()) as usize;
+            if length == 0 || length > record.len() {
+                break;
+            }
+            offset += length;
+
+            // Only V2 records carry 64-bit file references
+            let major_version = u16::from_le_bytes(record[4..6].try_into().unwrap());
+            if major_version != 2 {
+                continue;
+            }
+            let source_info = u32::from_le_bytes(record[44..48].try_into().unwrap());
+            if source_info & USN_SOURCE_DATA_MANAGEMENT != 0 {
+                continue;
+            }
+
+            records += 1;
+            if records > MAX_REPLAY_RECORDS {
+                return Ok((JournalRead::Reset, next));
+            }
+
+            let file_ref = u64::from_le_bytes(record[8..16].try_into().unwrap());
+            let parent_ref = u64::from_le_bytes(record[16..24].try_into().unwrap());
+            let reason = u32::from_le_bytes(record[40..44].try_into().unwrap());
+            let name_len = u16::from_le_bytes(record[56..58].try_into().unwrap()) as usize;
+            let name_offset = u16::from_le_bytes(record[58..60].try_into().unwrap()) as usize;
+            if name_offset + name_len > length {
+                continue;
+            }
+            let name: Vec<u16> = record[name_offset..name_offset + name_len]
+                .chunks_exact(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                .collect();
+
+            // Parents deleted since are skipped; their own delete record covers them
+            let Some(parent) = parents
+                .entry(parent_ref)
+                .or_insert_with(|| resolve_by_id(&volume, parent_ref))
+                .clone()
+            else {
+                continue;
+            };
+            let path = parent.join(OsString::from_wide(&name));
+
+            if reason & USN_REASON_FILE_DELETE != 0 {
+                changes.push(JournalChange::Removed(path));
+            } else if reason & USN_REASON_RENAME_OLD_NAME != 0 {
+                pending_renames.insert(file_ref, path);
+            } else if reason & USN_REASON_RENAME_NEW_NAME != 0 {
+                match pending_renames.remove(&file_ref) {
+                    Some(from) => changes.push(JournalChange::Renamed {
+                        file_ref,
+                        from,
+                        to: path,
+                    }),
+                    None => changes.push(JournalChange::Changed(path)),
+                }
+            } else {
+                changes.push(JournalChange::Changed(path));
+            }
+        }
+
+        if next_usn <= start_usn {
+            break;
+        }
+        start_usn = next_usn;
+    }
+
+    Ok((JournalRead::Changes(changes), next))
+}
+
+/// Map `path` onto `root` if it lies under it, comparing case-insensitively
+/// like NTFS does.
+fn relocate_under_root(path: &Path, root: &Path) -> Option<PathBuf> {
+    let mut components = path.components();
+    for root_component in root.components() {
+        let component = components.next()?;
+        if !component
+            .as_os_str()
+            .eq_ignore_ascii_case(root_component.as_os_str())
+        {
+            return None;
+        }
+    }
+    Some(root.join(components.as_path()))
+}
+
+/// Reduce raw journal changes to the paths under `root` that need replaying.
+///
+/// Rename chains of the same file are collapsed into a single rename, moves
+/// out of the root become removals and moves into it become changes.
+fn plan_replay(changes: Vec<JournalChange>, root: &Path) -> ReplayPlan {
+    let mut plan = ReplayPlan::default();
+    let mut renames: Vec<(u64, PathBuf, PathBuf)> = Vec::new();
+
+    for change in changes {
+        match change {
+            JournalChange::Changed(path) => {
+                if let Some(path) = relocate_under_root(&path, root) {
+                    plan.removed.remove(&path);
+                    plan.changed.insert(path);
+                }
+            }
+            JournalChange::Removed(path) => {
+                if let Some(path) = relocate_under_root(&path, root) {
+                    plan.changed.remove(&path);
+                    plan.removed.insert(path);
+                }
+            }
+            JournalChange::Renamed { file_ref, from, to } => {
+                let from = relocate_under_root(&from, root);
+                let to = relocate_under_root(&to, root);
+                match (from, to) {
+                    (Some(from), Some(to)) => {
+                        if plan.changed.remove(&from) {
+                            // Not known remotely yet, so nothing to rename
+                            plan.changed.insert(to);
+                        } else if let Some(rename) =
+                            renames.iter_mut().find(|(r, _, dest)| *r == file_ref && *dest == from)
+                        {
+                            rename.2 = to;
+                        } else {
+                            renames.push((file_ref, from, to));
+                        }
+                    }
+                    (Some(from), None) => {
+                        plan.changed.remove(&from);
+                        plan.removed.insert(from);
+                    }
+                    (None, Some(to)) => {
+                        plan.changed.insert(to);
+                    }
+                    (None, None) => {}
+                }
+            }
+        }
+    }
+
+    plan.renames = renames
+        .into_iter()
+        .filter(|(_, from, to)| from != to)
+        .map(|(_, from, to)| (from, to))
+        .collect();
+    plan
+}
+
+impl Mount {
+    /// Replay local changes made while the app was not running.
+    pub async fn replay_journal(&self) -> LocalReplay {
+        let (sync_root, checkpoint) = {
+            let config = self.config.read().await;
+            (config.sync_path.clone(), config.usn_checkpoint)
+        };
+
+        let result = match checkpoint {
+            Some(checkpoint) => {
+                let root = sync_root.clone();
+                tokio::task::spawn_blocking(move || read_changes(&root, &checkpoint))
+                    .await
+                    .context("journal reader panicked")
+                    .and_then(|r| r)
+            }
+            // First start on this drive, nothing to replay from
+            None => {
+                self.update_journal_checkpoint().await;
+                return LocalReplay::Unavailable;
+            }
+        };
+
+        let (read, next) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                tracing::warn!(target: "drive::journal", id = %self.id, error = %e, "Failed to read change journal");
+                self.update_journal_checkpoint().await;
+                return LocalReplay::Unavailable;
+            }
+        };
+
+        let replay = match read {
+            JournalRead::Reset => {
+                tracing::info!(target: "drive::journal", id = %self.id, "Change journal no longer covers last checkpoint");
+                LocalReplay::Unavailable
+            }
+            JournalRead::Changes(changes) => {
+                let plan = plan_replay(changes, &sync_root);
+                if plan.is_empty() {
+                    LocalReplay::Clean
+                } else {
+                    tracing::info!(
+                        target: "drive::journal",
+                        id = %self.id,
+                        renames = plan.renames.len(),
+                        removed = plan.removed.len(),
+                        changed = plan.changed.len(),
+                        "Replaying offline changes"
+                    );
+                    match self.apply_replay_plan(plan).await {
+                        Ok(()) => LocalReplay::Replayed,
+                        Err(e) => {
+                            tracing::warn!(target: "drive::journal", id = %self.id, error = %e, "Failed to replay offline changes");
+                            LocalReplay::Unavailable
+                        }
+                    }
+                }
+            }
+        };
+
+        self.store_journal_checkpoint(next).await;
+        replay
+    }
+
+    /// Record the current end of the change journal as the replay starting point.
+    pub async fn update_journal_checkpoint(&self) {
+        let sync_root = self.config.read().await.sync_path.clone();
+        match tokio::task::spawn_blocking(move || current_checkpoint(&sync_root)).await {
+            Ok(Ok(checkpoint)) => self.store_journal_checkpoint(checkpoint).await,
+            Ok(Err(e)) => {
+                tracing::debug!(target: "drive::journal", id = %self.id, error = %e, "Change journal unavailable");
+            }
+            Err(e) => {
+                tracing::warn!(target: "drive::journal", id = %self.id, error = %e, "Failed to query change journal");
+            }
+        }
+    }
+
+    async fn store_journal_checkpoint(&self, checkpoint: UsnCheckpoint) {
+        self.config.write().await.usn_checkpoint = Some(checkpoint);
+        if let Err(e) = self.manager_command_tx.send(ManagerCommand::PersistConfig) {
+            tracing::error!(target: "drive::journal", id = %self.id, error = %e, "Failed to send PersistConfig command");
+        }
+    }
+
+    async fn apply_replay_plan(&self, plan: ReplayPlan) -> Result<()> {
+        for (from, to) in plan.renames {
+            let known = self
+                .inventory
+                .query_by_path(from.to_str().context("invalid path")?)?
+                .is_some();
+            if !known || !to.exists() {
+                if to.exists() {
+                    self.send_replay_event(EventKind::Create(CreateKind::Any), to);
+                }
+                continue;
+            }
+
+            tracing::debug!(target: "drive::journal", from = %from.display(), to = %to.display(), "Replaying rename");
+            self.rename(from.clone(), to.clone()).await?;
+            self.rename_completed(from, to).await?;
+        }
+
+        let mut events: GroupedFsEvents = HashMap::new();
+        for path in plan.removed {
+            if path.exists() {
+                continue;
+            }
+            // Only removals of synced files are propagated
+            if self
+                .inventory
+                .query_by_path(path.to_str().context("invalid path")?)?
+                .is_some()
+            {
+                events
+                    .entry(EventKind::Remove(RemoveKind::Any))
+                    .or_default()
+                    .push(Event::new(EventKind::Remove(RemoveKind::Any)).add_path(path));
+            }
+        }
+
+        for path in plan.changed {
+            if !path.exists() {
+                continue;
+            }
+            let known = self
+                .inventory
+                .query_by_path(path.to_str().context("invalid path")?)?
+                .is_some();
+            let kind = if known {
+                EventKind::Modify(ModifyKind::Any)
+            } else {
+                EventKind::Create(CreateKind::Any)
+            };
+            events
+                .entry(kind)
+                .or_default()
+                .push(Event::new(kind).add_path(path));
+        }
+
+        if !events.is_empty() {
+            self.command_tx
+                .send(MountCommand::ProcessFsEvents { events })
+                .context("failed to send ProcessFsEvents command")?;
+        }
+        Ok(())
+    }
+
+    fn send_replay_event(&self, kind: EventKind, path: PathBuf) {
+        let mut events: GroupedFsEvents = HashMap::new();
+        events.insert(kind, vec![Event::new(kind).add_path(path)]);
+        if let Err(e) = self.command_tx.send(MountCommand::ProcessFsEvents { events }) {
+            tracing::error!(target: "drive::journal", id = %self.id, error = %e, "Failed to send ProcessFsEvents command");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root() -> PathBuf {
+        PathBuf::from(r"C:\Users\me\Cloudreve")
+    }
+
+    #[test]
+    fn relocates_paths_case_insensitively() {
+        let path = PathBuf::from(r"C:\USERS\me\cloudreve\docs\a.txt");
+        assert_eq!(
+            relocate_under_root(&path, &root()),
+            Some(root().join(r"docs\a.txt"))
+        );
+        assert_eq!(relocate_under_root(Path::new(r"C:\Users\me\Other\a.txt"), &root()), None);
+    }
+
+    #[test]
+    fn collapses_rename_chains() {
+        let plan = plan_replay(
+            vec![
+                JournalChange::Renamed {
+                    file_ref: 7,
+                    from: root().join("a.txt"),
+                    to: root().join("b.txt"),
+                },
+                JournalChange::Renamed {
+                    file_ref: 7,
+                    from: root().join("b.txt"),
+                    to: root().join("c.txt"),
+                },
+            ],
+            &root(),
+        );
+        assert_eq!(plan.renames, vec![(root().join("a.txt"), root().join("c.txt"))]);
+    }
+
+    #[test]
+    fn moves_across_root_become_changes_and_removals() {
+        let outside = PathBuf::from(r"C:\Temp\x.txt");
+        let plan = plan_replay(
+            vec![
+                JournalChange::Renamed {
+                    file_ref: 1,
+                    from: root().join("x.txt"),
+                    to: outside.clone(),
+                },
+                JournalChange::Renamed {
+                    file_ref: 2,
+                    from: outside,
+                    to: root().join("y.txt"),
+                },
+            ],
+            &root(),
+        );
+        assert!(plan.renames.is_empty());
+        assert!(plan.removed.contains(&root().join("x.txt")));
+        assert!(plan.changed.contains(&root().join("y.txt")));
+    }
+
+    #[test]
+    fn created_then_deleted_is_removal_only() {
+        let path = root().join("tmp.txt");
+        let plan = plan_replay(
+            vec![
+                JournalChange::Changed(path.clone()),
+                JournalChange::Removed(path.clone()),
+            ],
+            &root(),
+        );
+        assert!(plan.changed.is_empty());
+        assert!(plan.removed.contains(&path));
+    }
+}
@@ -0,0 +1,188 @@
+//! Sync folders saved relative to the known folders of Windows
+//!
+//! A sync folder under the user profile or another known folder is saved as
+//! `%USERPROFILE%\Cloudreve` next to its absolute path, and resolved again
+//! when drives are loaded. A drive then follows its folder when the profile
+//! moves to another disk or user name. Other folders keep their absolute
+//! path; when its disk comes back under another letter, the same folder is
+//! found there and offered to the user instead.
+
+use crate::drive::utils::{key_is_under, path_key};
+use std::path::{Path, PathBuf};
+
+/// Known folders a sync folder can be saved relative to, by the name used
+/// between `%` signs
+pub struct KnownFolders {
+    folders: Vec<(&'static str, PathBuf)>,
+}
+
+impl KnownFolders {
+    /// Known folders of the signed in user, as currently located
+    pub fn current() -> Self {
+        Self::new(
+            [
+                ("USERPROFILE", dirs::home_dir()),
+                ("DESKTOP", dirs::desktop_dir()),
+                ("DOCUMENTS", dirs::document_dir()),
+                ("DOWNLOADS", dirs::download_dir()),
+                ("MUSIC", dirs::audio_dir()),
+                ("PICTURES", dirs::picture_dir()),
+                ("VIDEOS", dirs::video_dir()),
+            ]
+            .into_iter()
+            .filter_map(|(name, path)| Some((name, path?)))
+            .collect(),
+        )
+    }
+
+    fn new(folders: Vec<(&'static str, PathBuf)>) -> Self {
+        Self { folders }
+    }
+
+    /// `path` relative to the innermost known folder holding it, like
+    /// `%DOCUMENTS%\Cloudreve`. `None` outside of known folders.
+    pub fn portable(&self, path: &Path) -> Option<String> {
+        let key = path_key(path);
+        let (name, base_key) = self
+            .folders
+            .iter()
+            .map(|(name, base)| (*name, path_key(base)))
+            .filter(|(_, base_key)| key_is_under(&key, base_key))
+            .max_by_key(|(_, base_key)| base_key.len())?;
+
+        // Keep the case of the path, only its key is folded
+        let base_parts = base_key.split('\\').filter(|part| !part.is_empty()).count();
+        let path = path.to_string_lossy().replace('/', "\\");
+        let path = path
+            .strip_prefix(r"\\?\UNC\")
+            .or_else(|| path.strip_prefix(r"\\?\"))
+            .unwrap_or(&path);
+        let mut portable = format!("%{}%", name);
+        for part in path
+            .split('\\')
+            .filter(|part| !part.is_empty())
+            .skip(base_parts)
+        {
+            portable.push('\\');
+            portable.push_str(part);
+        }
+        Some(portable)
+    }
+
+    /// Whether `path` is one of the known folders itself, like the whole
+    /// Documents folder
+    pub fn is_known_folder(&self, path: &Path) -> bool {
+        let key = path_key(path);
+        self.folders.iter().any(|(_, base)| path_key(base) == key)
+    }
+
+    /// Absolute path of a path saved by [`Self::portable`]. `None` if it
+    /// names no known folder, or one that can't be located.
+    pub fn resolve(&self, portable: &str) -> Option<PathBuf> {
+        let (name, rest) = portable.strip_prefix('%')?.split_once('%')?;
+        let (_, base) = self
+            .folders
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(name))?;
+        let mut path = base.clone();
+        for part in rest.split(['\\', '/']).filter(|part| !part.is_empty()) {
+            path.push(part);
+        }
+        Some(path)
+    }
+}
+
+/// The folder at `path` on another disk, if only the letter of its disk
+/// changed, e.g. `E:\Cloud` for a missing `D:\Cloud`. Hits the disk.
+pub fn find_on_other_disk(path: &Path) -> Option<PathBuf> {
+    ('A'..='Z')
+        .filter_map(|letter| with_disk_letter(path, letter))
+        .find(|candidate| candidate.is_dir())
+}
+
+/// `path` on the disk `letter` instead of its own. `None` if `path` is not
+/// on a lettered disk, is already on `letter`, or is the root of its disk,
+/// which any other disk would match.
+fn with_disk_letter(path: &Path, letter: char) -> Option<PathBuf> {
+    let path = path.to_str()?;
+    let rest = path.strip_prefix(|c: char| c.is_ascii_alphabetic())?;
+    let folder = rest.strip_prefix(':')?;
+    if path.starts_with(letter.to_ascii_uppercase())
+        || path.starts_with(letter.to_ascii_lowercase())
+        || folder.trim_matches(['\\', '/']).is_empty()
+    {
+        return None;
+    }
+    Some(PathBuf::from(format!("{}{}", letter, rest)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn known_folders() -> KnownFolders {
+        KnownFolders::new(vec![
+            ("USERPROFILE", PathBuf::from(r"C:\Users\Alex")),
+            ("DOCUMENTS", PathBuf::from(r"C:\Users\Alex\Documents")),
+            ("PICTURES", PathBuf::from(r"D:\Pictures")),
+        ])
+    }
+
+    #[test]
+    fn paths_are_saved_relative_to_the_innermost_known_folder() {
+        let folders = known_folders();
+        assert_eq!(
+            folders.portable(Path::new(r"C:\Users\Alex\Cloudreve")),
+            Some(r"%USERPROFILE%\Cloudreve".to_string())
+        );
+        assert_eq!(
+            folders.portable(Path::new(r"c:\users\alex\Documents\Work\Cloud")),
+            Some(r"%DOCUMENTS%\Work\Cloud".to_string())
+        );
+        assert_eq!(
+            folders.portable(Path::new(r"D:\Pictures")),
+            Some("%PICTURES%".to_string())
+        );
+        assert_eq!(
+            folders.portable(Path::new(r"C:\Users\Alexander\Cloud")),
+            None
+        );
+        assert_eq!(folders.portable(Path::new(r"E:\Cloud")), None);
+    }
+
+    #[test]
+    fn only_known_folders_themselves_are_known() {
+        let folders = known_folders();
+        assert!(folders.is_known_folder(Path::new(r"c:\users\alex\documents\")));
+        assert!(folders.is_known_folder(Path::new(r"D:\Pictures")));
+        assert!(!folders.is_known_folder(Path::new(r"C:\Users\Alex\Cloudreve")));
+        assert!(!folders.is_known_folder(Path::new(r"C:\Users")));
+    }
+
+    #[test]
+    fn saved_paths_follow_moved_known_folders() {
+        let moved = KnownFolders::new(vec![("USERPROFILE", PathBuf::from(r"E:\Users\Alex"))]);
+        let path = moved.resolve(r"%USERPROFILE%\Cloudreve\Work").unwrap();
+        assert_eq!(path_key(&path), r"e:\users\alex\cloudreve\work");
+        assert_eq!(
+            path_key(&moved.resolve("%userprofile%").unwrap()),
+            r"e:\users\alex"
+        );
+        assert_eq!(moved.resolve(r"%DOCUMENTS%\Cloudreve"), None);
+        assert_eq!(moved.resolve(r"C:\Cloudreve"), None);
+    }
+
+    #[test]
+    fn only_the_disk_letter_is_replaced() {
+        assert_eq!(
+            with_disk_letter(Path::new(r"D:\Sync\Cloud"), 'E'),
+            Some(PathBuf::from(r"E:\Sync\Cloud"))
+        );
+        assert_eq!(with_disk_letter(Path::new(r"d:\Sync"), 'D'), None);
+        assert_eq!(with_disk_letter(Path::new(r"D:\"), 'E'), None);
+        assert_eq!(
+            with_disk_letter(Path::new(r"\\server\share\Cloud"), 'E'),
+            None
+        );
+    }
+}
@@ -0,0 +1,173 @@
+//! Per-drive color/emoji labels and the custom folder icon derived from them.
+//!
+//! A colored label produces a badged variant of the site favicon, which is used
+//! both for the sync root registration and for the local folder via `desktop.ini`,
+//! so that several drives from the same server can be told apart in Explorer.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use widestring::U16CString;
+use windows::Win32::Storage::FileSystem::{
+    FILE_ATTRIBUTE_HIDDEN, FILE_ATTRIBUTE_READONLY, FILE_ATTRIBUTE_SYSTEM, FILE_FLAGS_AND_ATTRIBUTES,
+    GetFileAttributesW, INVALID_FILE_ATTRIBUTES, SetFileAttributesW,
+};
+use windows::core::PCWSTR;
+
+/// Name of the folder customization file written to the sync root
+pub const DESKTOP_INI: &str = "desktop.ini";
+
+/// Size of the generated ICO
+const ICON_SIZE: u32 = 64;
+
+/// User-assigned label of a drive
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct DriveLabel {
+    /// Badge color in `#RRGGBB` form
+    #[serde(default)]
+    pub color: Option<String>,
+    /// Emoji shown next to the drive name in the UI
+    #[serde(default)]
+    pub emoji: Option<String>,
+}
+
+impl DriveLabel {
+    /// Whether the label carries neither a color nor an emoji
+    pub fn is_empty(&self) -> bool {
+        self.color.is_none() && self.emoji.is_none()
+    }
+
+    /// Parsed RGB value of the badge color, if any
+    pub fn rgb(&self) -> Option<[u8; 3]> {
+        self.color.as_deref().and_then(parse_hex_color)
+    }
+}
+
+/// Parse a `#RRGGBB` (or `RRGGBB`) color string
+pub fn parse_hex_color(color: &str) -> Option<[u8; 3]> {
+    let hex = color.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
+    let g = u8::from_str_radix(&hex[2..4], 16).ok()?;
+    let b = u8::from_str_radix(&hex[4..6], 16).ok()?;
+    Some([r, g, b])
+}
+
+/// Generate an ICO with a colored badge in the bottom-right corner.
+///
+/// The badged icon is saved next to `source_icon` with the color appended to the
+/// file stem, and its path is returned.
+pub fn generate_badged_icon(source_icon: &str, color: [u8; 3]) -> Result<String> {
+    let source = Path::new(source_icon);
+    let img = image::open(source).context("Failed to load source icon")?;
+    let mut canvas = img
+        .resize_exact(ICON_SIZE, ICON_SIZE, image::imageops::FilterType::Lanczos3)
+        .to_rgba8();
+
+    // Badge: filled circle with a white outline, a third of the icon wide
+    let radius = ICON_SIZE as f32 / 6.0;
+    let center = ICON_SIZE as f32 - radius - 1.0;
+    let outline = 2.0;
+    for (x, y, pixel) in canvas.enumerate_pixels_mut() {
+        let dx = x as f32 + 0.5 - center;
+        let dy = y as f32 + 0.5 - center;
+        let distance = (dx * dx + dy * dy).sqrt();
+        if distance <= radius - outline {
+            *pixel = image::Rgba([color[0], color[1], color[2], 255]);
+        } else if distance <= radius {
+            *pixel = image::Rgba([255, 255, 255, 255]);
+        }
+    }
+
+    let stem = source
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .context("Invalid source icon path")?;
+    let dest = source.with_file_name(format!(
+        "{}_{:02x}{:02x}{:02x}.ico",
+        stem, color[0], color[1], color[2]
+    ));
+    canvas
+        .save_with_format(&dest, image::ImageFormat::Ico)
+        .context("Failed to save badged icon")?;
+
+    tracing::debug!(target: "drive::label", path = %dest.display(), "Badged icon generated");
+    Ok(dest.to_string_lossy().to_string())
+}
+
+/// Write `desktop.ini` into the sync root so Explorer shows `icon_path` for the folder.
+pub fn apply_folder_icon(sync_path: &Path, icon_path: &str) -> Result<()> {
+    let ini_path = sync_path.join(DESKTOP_INI);
+
+    // desktop.ini is kept hidden+system, which makes it read-only for std::fs
+    if ini_path.exists() {
+        set_attributes(&ini_path, FILE_FLAGS_AND_ATTRIBUTES(0))?;
+    }
+    let content = format!(
+        "[.ShellClassInfo]\r\nIconResource={},0\r\nIconFile={}\r\nIconIndex=0\r\n",
+        icon_path, icon_path
+    );
+    std::fs::write(&ini_path, content).context("Failed to write desktop.ini")?;
+    set_attributes(&ini_path, FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM)?;
+
+    // Explorer only honors desktop.ini on read-only or system folders
+    add_attributes(sync_path, FILE_ATTRIBUTE_READONLY)?;
+    Ok(())
+}
+
+/// Remove the `desktop.ini` written by [apply_folder_icon], if any, and the
+/// read-only attribute it needed on the folder.
+pub fn remove_folder_icon(sync_path: &Path) -> Result<()> {
+    let ini_path = sync_path.join(DESKTOP_INI);
+    if !ini_path.exists() {
+        return Ok(());
+    }
+    set_attributes(&ini_path, FILE_FLAGS_AND_ATTRIBUTES(0))?;
+    std::fs::remove_file(&ini_path).context("Failed to remove desktop.ini")?;
+    remove_attributes(sync_path, FILE_ATTRIBUTE_READONLY)?;
+    Ok(())
+}
+
+fn set_attributes(path: &Path, attributes: FILE_FLAGS_AND_ATTRIBUTES) -> Result<()> {
+    let wide = U16CString::from_os_str(path.as_os_str())?;
+    unsafe { SetFileAttributesW(PCWSTR(wide.as_ptr()), attributes) }
+        .with_context(|| format!("Failed to set attributes on {}", path.display()))
+}
+
+fn get_attributes(path: &Path) -> Result<FILE_FLAGS_AND_ATTRIBUTES> {
+    let wide = U16CString::from_os_str(path.as_os_str())?;
+    let current = unsafe { GetFileAttributesW(PCWSTR(wide.as_ptr())) };
+    if current == INVALID_FILE_ATTRIBUTES {
+        anyhow::bail!("Failed to get attributes of {}", path.display());
+    }
+    Ok(FILE_FLAGS_AND_ATTRIBUTES(current))
+}
+
+fn add_attributes(path: &Path, attributes: FILE_FLAGS_AND_ATTRIBUTES) -> Result<()> {
+    set_attributes(path, get_attributes(path)? | attributes)
+}
+
+fn remove_attributes(path: &Path, attributes: FILE_FLAGS_AND_ATTRIBUTES) -> Result<()> {
+    set_attributes(
+        path,
+        FILE_FLAGS_AND_ATTRIBUTES(get_attributes(path)?.0 & !attributes.0),
+    )
+}
+
+/// (Re)generate the badged icon for the given favicon and label color.
+///
+/// Returns `None` if there is no favicon, no valid color, or generation failed.
+pub fn resolve_label_icon(icon_path: Option<&str>, label: Option<&DriveLabel>) -> Option<String> {
+    let icon_path = icon_path?;
+    let color = label.and_then(|l| l.rgb())?;
+    match generate_badged_icon(icon_path, color) {
+        Ok(path) => Some(path),
+        Err(e) => {
+            tracing::warn!(target: "drive::label", icon = %icon_path, error = %e, "Failed to generate badged icon");
+            None
+        }
+    }
+}
+
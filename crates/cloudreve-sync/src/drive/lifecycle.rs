@@ -0,0 +1,220 @@
+//! Lifecycle state of a mount.
+//!
+//! A mount moves through a fixed set of states with explicit transitions.
+//! Behaviors tied to a state are derived from it in one place, e.g. uploads
+//! wait until the initial sync has reconciled the sync root, so a local file
+//! is not uploaded before the engine knows whether the remote copy changed.
+
+use std::sync::atomic::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+use crate::drive::{commands::ManagerCommand, mounts::Mount};
+use crate::power;
+use crate::presentation;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MountState {
+    /// Connecting the sync root and starting the watchers
+    #[default]
+    Initializing,
+    /// First full sync of the sync root since the mount started
+    SyncingInitial,
+    Idle,
+    Syncing,
+    /// Sync is throttled to save power or during a presentation, or held
+    /// while the server is under maintenance
+    Paused,
+    /// Running without credentials or real-time remote events, or without
+    /// the local sync folder
+    Degraded,
+    ShuttingDown,
+}
+
+impl MountState {
+    /// Whether the mount may move from `self` to `next`
+    pub fn can_transition_to(self, next: MountState) -> bool {
+        use MountState::*;
+        match (self, next) {
+            (ShuttingDown, _) => false,
+            (_, ShuttingDown) => true,
+            (Initializing, Idle | SyncingInitial | Degraded) => true,
+            (SyncingInitial, Idle | Paused | Degraded) => true,
+            (Idle, SyncingInitial | Syncing | Paused | Degraded) => true,
+            (Syncing, Idle | Paused | Degraded) => true,
+            (Paused, Idle | Degraded) => true,
+            (Degraded, Idle | Paused) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether queued uploads may start
+    pub fn allows_uploads(self) -> bool {
+        !matches!(self, MountState::Initializing | MountState::SyncingInitial)
+    }
+
+    pub fn is_syncing(self) -> bool {
+        matches!(self, MountState::SyncingInitial | MountState::Syncing)
+    }
+
+    /// Transient states that are not worth persisting on their own
+    fn is_transient(self) -> bool {
+        matches!(self, MountState::Syncing)
+    }
+}
+
+impl Mount {
+    /// Current lifecycle state
+    pub async fn state(&self) -> MountState {
+        *self.state.lock().await
+    }
+
+    /// Move to `next` if the transition is allowed. Returns whether the
+    /// state changed.
+    pub(crate) async fn transition(&self, next: MountState) -> bool {
+        let previous = {
+            let mut state = self.state.lock().await;
+            let previous = *state;
+            if previous == next {
+                return false;
+            }
+            if !previous.can_transition_to(next) {
+                tracing::debug!(target: "drive::lifecycle", id = %self.id, from = ?previous, to = ?next, "Ignoring state transition");
+                return false;
+            }
+            *state = next;
+            previous
+        };
+        tracing::info!(target: "drive::lifecycle", id = %self.id, from = ?previous, to = ?next, "Mount state changed");
+
+        self.apply_upload_hold(next).await;
+        self.config.write().await.last_state = Some(next);
+        if !(previous.is_transient() || next.is_transient()) {
+            if let Err(e) = self.manager_command_tx.send(ManagerCommand::PersistConfig) {
+                tracing::error!(target: "drive::lifecycle", id = %self.id, error = %e, "Failed to send PersistConfig command");
+            }
+        }
+        if let Err(e) = self
+            .manager_command_tx
+            .send(ManagerCommand::MountStateChanged {
+                drive_id: self.id.clone(),
+                state: next,
+            })
+        {
+            tracing::error!(target: "drive::lifecycle", id = %self.id, error = %e, "Failed to send MountStateChanged command");
+        }
+        true
+    }
+
+    /// Hold uploads in states that do not allow them, while the server is
+    /// under maintenance, where they would only fail, while suspicious
+    /// changes are pending, and during a presentation
+    pub(crate) async fn apply_upload_hold(&self, state: MountState) {
+        let flags = self.get_status_flags().await;
+        self.task_queue.hold_uploads(
+            !state.allows_uploads()
+                || flags.is_server_maintenance()
+                || flags.is_suspicious_changes()
+                || presentation::pauses_transfers(),
+        );
+    }
+
+    /// Enter a syncing state before syncing paths. The first full sync of the
+    /// sync root is the initial sync.
+    pub(crate) async fn begin_sync(&self, full_root: bool) {
+        let next = if full_root && !self.initial_synced.load(Ordering::SeqCst) {
+            MountState::SyncingInitial
+        } else {
+            MountState::Syncing
+        };
+        self.transition(next).await;
+    }
+
+    /// Leave the syncing state after a sync finished
+    pub(crate) async fn end_sync(&self) {
+        let state = self.state().await;
+        if state == MountState::SyncingInitial {
+            self.initial_synced.store(true, Ordering::SeqCst);
+        }
+        if state.is_syncing() {
+            self.transition(MountState::Idle).await;
+        }
+        if state == MountState::SyncingInitial {
+            self.write_initial_sync_report().await;
+        }
+    }
+
+    /// Skip the initial sync, e.g. when nothing changed since the last run
+    pub(crate) fn mark_initial_synced(&self) {
+        self.initial_synced.store(true, Ordering::SeqCst);
+    }
+
+    /// Re-evaluate the degraded and paused states from the status flags, a
+    /// missing sync folder, server maintenance, power saving and presentations
+    pub(crate) async fn refresh_state(&self) {
+        let state = self.state().await;
+        if matches!(state, MountState::Initializing | MountState::ShuttingDown) {
+            return;
+        }
+        let flags = self.get_status_flags().await;
+        // Guests of a share poll instead of subscribing to events
+        let push_lost = !flags.is_event_push_subscribed() && !self.is_guest_share();
+        let next = if flags.is_sync_root_missing() {
+            // Nothing syncs until the folder is recovered
+            MountState::Degraded
+        } else if flags.is_suspicious_changes() {
+            // Nothing syncs until the user restores or dismisses the changes
+            MountState::Paused
+        } else if flags.is_server_maintenance() {
+            // The event stream is down during maintenance as well, which is
+            // expected rather than degraded
+            MountState::Paused
+        } else if flags.is_credential_expired() || push_lost {
+            MountState::Degraded
+        } else if power::is_power_saving() || presentation::pauses_transfers() {
+            MountState::Paused
+        } else if state.is_syncing() {
+            return;
+        } else {
+            MountState::Idle
+        };
+        self.transition(next).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lifecycle_transitions() {
+        use MountState::*;
+        assert!(Initializing.can_transition_to(SyncingInitial));
+        assert!(SyncingInitial.can_transition_to(Idle));
+        assert!(Idle.can_transition_to(Syncing));
+        assert!(Syncing.can_transition_to(Paused));
+        assert!(Paused.can_transition_to(Idle));
+        assert!(Degraded.can_transition_to(Idle));
+
+        assert!(!Initializing.can_transition_to(Syncing));
+        assert!(!Paused.can_transition_to(Syncing));
+        assert!(!Degraded.can_transition_to(Syncing));
+        for state in [
+            Initializing,
+            SyncingInitial,
+            Idle,
+            Syncing,
+            Paused,
+            Degraded,
+        ] {
+            assert!(state.can_transition_to(ShuttingDown));
+            assert!(!ShuttingDown.can_transition_to(state));
+        }
+
+        assert!(!Initializing.allows_uploads());
+        assert!(!SyncingInitial.allows_uploads());
+        assert!(Idle.allows_uploads());
+        assert!(Degraded.allows_uploads());
+    }
+}
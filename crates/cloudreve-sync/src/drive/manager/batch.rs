@@ -0,0 +1,64 @@
+use super::DriveManager;
+use crate::policy::Policy;
+use crate::tasks::{
+    BATCH_OPERATION_KIND, BatchOperation, BatchOperationState, TaskKind, TaskPayload,
+};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+impl DriveManager {
+    /// Apply `operation` to files selected together, with one task per drive
+    /// the files belong to. Returns the IDs of the tasks.
+    pub async fn start_batch_operation(
+        &self,
+        operation: BatchOperation,
+        paths: Vec<PathBuf>,
+    ) -> Result<Vec<String>> {
+        if operation == BatchOperation::Share && Policy::get().force_read_only {
+            anyhow::bail!("Remote changes are disabled by administrator policy");
+        }
+
+        let mut by_drive: HashMap<String, (_, Vec<PathBuf>)> = HashMap::new();
+        for path in paths {
+            let Some(mount) = self
+                .search_drive_by_child_path(path.to_str().unwrap_or(""))
+                .await
+            else {
+                tracing::warn!(target: "drive::manager", path = %path.display(), "No drive found for path, skipping");
+                continue;
+            };
+            if operation == BatchOperation::Share && mount.is_guest_share() {
+                tracing::warn!(target: "drive::manager", path = %path.display(), "Files of a guest share can't be shared again, skipping");
+                continue;
+            }
+            by_drive
+                .entry(mount.id.clone())
+                .or_insert_with(|| (mount, Vec::new()))
+                .1
+                .push(path);
+        }
+
+        let mut task_ids = Vec::with_capacity(by_drive.len());
+        for (drive_id, (mount, paths)) in by_drive {
+            let state = BatchOperationState {
+                operation,
+                paths,
+                done: 0,
+                failed: Vec::new(),
+                links: Vec::new(),
+            };
+            let total = state.paths.len() as i64;
+            let payload = TaskPayload::new(
+                TaskKind::Custom(BATCH_OPERATION_KIND),
+                state.paths[0].clone(),
+            )
+            .with_totals(0, total)
+            .with_custom_state(serde_json::to_value(&state)?);
+            let task_id = mount.task_queue.enqueue(payload).await?;
+            tracing::info!(target: "drive::manager", drive_id = %drive_id, task_id = %task_id, operation = ?operation, count = total, "Queued batch operation");
+            task_ids.push(task_id);
+        }
+        Ok(task_ids)
+    }
+}
@@ -0,0 +1,284 @@
+//! Cleanup of data left behind by removed drives
+//!
+//! Removing a drive deletes its inventory rows, including task and upload
+//! session rows, and the site icons no drive uses anymore. Icons are shared
+//! by the drives of a server, so one is only deleted once no drive config
+//! points to it. The same cleanup runs daily for data left by crashes or
+//! older versions, once the user is idle. Thumbnails are handed to Explorer
+//! without being cached, so there are none to delete.
+
+use super::DriveManager;
+use super::favicon::get_icons_dir;
+use crate::idle;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// How often orphaned data is looked for
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+/// Delay before the first cleanup after startup
+const INITIAL_CLEANUP_DELAY: Duration = Duration::from_secs(10 * 60);
+/// Icons younger than this are kept, they may belong to a drive being added
+const ICON_GRACE_PERIOD: Duration = Duration::from_secs(60 * 60);
+
+/// What a cleanup deleted
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PurgeReport {
+    /// Removed drives whose inventory rows were deleted
+    pub drives: Vec<String>,
+    pub inventory_rows: usize,
+    pub icons: usize,
+    /// Disk space given back, in bytes
+    pub bytes_reclaimed: u64,
+}
+
+/// Drives in neither the mounted nor the unmounted map for a moment, such as
+/// while unmounting. Their data must not look orphaned.
+#[derive(Default)]
+pub(super) struct Transitions(Mutex<HashSet<String>>);
+
+impl Transitions {
+    /// Mark drive `id` as in transition until the guard is dropped. Call
+    /// before taking the drive out of its map.
+    pub(super) fn begin(&self, id: &str) -> TransitionGuard<'_> {
+        self.0.lock().unwrap().insert(id.to_string());
+        TransitionGuard {
+            transitions: self,
+            id: id.to_string(),
+        }
+    }
+
+    fn ids(&self) -> Vec<String> {
+        self.0.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+pub(super) struct TransitionGuard<'a> {
+    transitions: &'a Transitions,
+    id: String,
+}
+
+impl Drop for TransitionGuard<'_> {
+    fn drop(&mut self) {
+        self.transitions.0.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// Delete the icons in `dir` not in `in_use` and older than the grace
+/// period. Returns the number of icons deleted and their size.
+fn purge_icons(dir: &Path, in_use: &HashSet<PathBuf>) -> Result<(usize, u64)> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((0, 0)),
+        Err(e) => return Err(e).context("Failed to list icons"),
+    };
+    let now = SystemTime::now();
+    let mut deleted = 0;
+    let mut bytes = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if !metadata.is_file() || in_use.contains(&path) {
+            continue;
+        }
+        let age = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .unwrap_or_default();
+        if age < ICON_GRACE_PERIOD {
+            continue;
+        }
+        match std::fs::remove_file(&path) {
+            Ok(()) => {
+                deleted += 1;
+                bytes += metadata.len();
+            }
+            Err(e) => {
+                tracing::debug!(target: "drive::manager", path = %path.display(), error = %e, "Failed to delete orphaned icon");
+            }
+        }
+    }
+    Ok((deleted, bytes))
+}
+
+impl DriveManager {
+    /// Spawn a background task deleting data of removed drives daily
+    pub fn spawn_cleanup_task(self: &Arc<Self>) {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(INITIAL_CLEANUP_DELAY).await;
+            loop {
+                idle::wait_for_idle().await;
+                if let Err(e) = manager.purge_orphaned_data().await {
+                    tracing::error!(target: "drive::manager", error = %e, "Failed to purge orphaned data");
+                }
+                tokio::time::sleep(CLEANUP_INTERVAL).await;
+            }
+        });
+    }
+
+    /// Delete inventory rows and icons no configured drive uses
+    pub async fn purge_orphaned_data(&self) -> Result<PurgeReport> {
+        // Drives not loaded yet would look removed
+        if !self.is_loaded() {
+            anyhow::bail!("Drives are not loaded yet");
+        }
+        let known = self.drive_ids().await;
+        let inventory = self.inventory.clone();
+        let mut report = tokio::task::spawn_blocking(move || -> Result<PurgeReport> {
+            let mut report = PurgeReport::default();
+            for id in inventory.drive_ids()? {
+                if known.contains(&id) {
+                    continue;
+                }
+                report.inventory_rows += inventory.purge_drive(&id)?;
+                report.drives.push(id);
+            }
+            if report.inventory_rows > 0 {
+                report.bytes_reclaimed += inventory.vacuum()?;
+            }
+            Ok(report)
+        })
+        .await
+        .context("Inventory purge panicked")??;
+
+        let (icons, bytes) = self.purge_orphaned_icons().await?;
+        report.icons = icons;
+        report.bytes_reclaimed += bytes;
+        tracing::info!(
+            target: "drive::manager",
+            drives = report.drives.len(),
+            rows = report.inventory_rows,
+            icons = report.icons,
+            bytes = report.bytes_reclaimed,
+            "Purged orphaned data"
+        );
+        Ok(report)
+    }
+
+    /// Delete the data of drive `id` right after its removal
+    pub(super) async fn purge_drive_data(&self, id: &str) {
+        let inventory = self.inventory.clone();
+        let drive_id = id.to_string();
+        match tokio::task::spawn_blocking(move || inventory.purge_drive(&drive_id)).await {
+            Ok(Ok(rows)) => {
+                tracing::debug!(target: "drive::manager", drive_id = %id, rows, "Deleted inventory rows of removed drive");
+            }
+            Ok(Err(e)) => {
+                tracing::warn!(target: "drive::manager", drive_id = %id, error = ?e, "Failed to delete inventory rows of removed drive");
+            }
+            Err(e) => {
+                tracing::error!(target: "drive::manager", drive_id = %id, error = %e, "Inventory purge panicked");
+            }
+        }
+        if let Err(e) = self.purge_orphaned_icons().await {
+            tracing::warn!(target: "drive::manager", drive_id = %id, error = ?e, "Failed to delete orphaned icons");
+        }
+    }
+
+    /// IDs of the mounted and unmounted drives, and of those moving between
+    /// the two. Both maps are locked together, so a drive can't leave one
+    /// and enter the other unseen.
+    async fn drive_ids(&self) -> HashSet<String> {
+        let drives = self.drives.read().await;
+        let unmounted = self.unmounted.read().await;
+        let mut ids: HashSet<String> = drives.keys().cloned().collect();
+        ids.extend(unmounted.keys().cloned());
+        ids.extend(self.transitions.ids());
+        ids
+    }
+
+    async fn purge_orphaned_icons(&self) -> Result<(usize, u64)> {
+        let mut in_use = HashSet::new();
+        let (mounts, mut configs) = {
+            let drives = self.drives.read().await;
+            let unmounted = self.unmounted.read().await;
+            // The config of a drive being unmounted is in neither map
+            if !self.transitions.ids().is_empty() {
+                tracing::debug!(target: "drive::manager", "Drive in transition, icons left for the next cleanup");
+                return Ok((0, 0));
+            }
+            let mounts: Vec<_> = drives.values().cloned().collect();
+            let configs: Vec<_> = unmounted.values().cloned().collect();
+            (mounts, configs)
+        };
+        for mount in mounts {
+            configs.push(mount.get_config().await);
+        }
+        for config in configs {
+            in_use.extend(
+                [
+                    config.icon_path,
+                    config.raw_icon_path,
+                    config.label_icon_path,
+                ]
+                .into_iter()
+                .flatten()
+                .map(PathBuf::from),
+            );
+        }
+
+        let dir = get_icons_dir()?;
+        tokio::task::spawn_blocking(move || purge_icons(&dir, &in_use))
+            .await
+            .context("Icon purge panicked")?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn icons_in_use_or_recent_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = SystemTime::now() - 2 * ICON_GRACE_PERIOD;
+        let write = |name: &str, modified: Option<SystemTime>| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, b"icon").unwrap();
+            if let Some(modified) = modified {
+                std::fs::File::options()
+                    .write(true)
+                    .open(&path)
+                    .unwrap()
+                    .set_modified(modified)
+                    .unwrap();
+            }
+            path
+        };
+        let used = write("used.ico", Some(old));
+        let orphan = write("orphan.ico", Some(old));
+        let recent = write("recent.ico", None);
+
+        let in_use = HashSet::from([used.clone()]);
+        assert_eq!(purge_icons(dir.path(), &in_use).unwrap(), (1, 4));
+        assert!(used.exists());
+        assert!(!orphan.exists());
+        assert!(recent.exists());
+        assert_eq!(
+            purge_icons(&dir.path().join("missing"), &in_use).unwrap(),
+            (0, 0)
+        );
+    }
+
+    #[test]
+    fn transitions_last_until_the_guard_is_dropped() {
+        let transitions = Transitions::default();
+        let first = transitions.begin("a");
+        let second = transitions.begin("b");
+        let mut ids = transitions.ids();
+        ids.sort();
+        assert_eq!(ids, ["a", "b"]);
+
+        drop(first);
+        assert_eq!(transitions.ids(), ["b"]);
+        drop(second);
+        assert!(transitions.ids().is_empty());
+    }
+}
@@ -1,12 +1,14 @@
 use super::DriveManager;
+use crate::drive::command_queue::CommandReceiver;
 use crate::drive::commands::{ManagerCommand, MountCommand};
 use crate::drive::utils::{local_path_to_cr_uri, view_online_url};
-use crate::utils::toast::send_conflict_toast;
+use crate::tasks::DEFAULT_PREFETCH_MAX_FILE_SIZE;
+use crate::utils::correlation::{self, spawn};
+use crate::utils::toast::{send_conflict_toast, send_general_text_toast, send_threat_toast};
 use anyhow::{Context, Result};
+use rust_i18n::t;
 use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::spawn;
-use tokio::sync::mpsc;
 
 impl DriveManager {
     /// Spawn the command processor task
@@ -14,9 +16,10 @@ impl DriveManager {
         let mut command_rx_guard = self.command_rx.lock().await;
         if let Some(command_rx) = command_rx_guard.take() {
             let manager = self.clone();
-            let handle = tokio::spawn(async move {
+            // Each command runs with the correlation id it was sent with
+            let handle = tokio::spawn(correlation::scope(None, async move {
                 Self::process_commands(manager, command_rx).await;
-            });
+            }));
             *self.processor_handle.lock().await = Some(handle);
         }
     }
@@ -24,7 +27,7 @@ impl DriveManager {
     /// Process commands from external sources asynchronously
     pub(super) async fn process_commands(
         manager: Arc<Self>,
-        mut command_rx: mpsc::UnboundedReceiver<ManagerCommand>,
+        mut command_rx: CommandReceiver<ManagerCommand>,
     ) {
         tracing::info!(target: "drive::manager", "Command processor started");
 
@@ -41,12 +44,7 @@ impl DriveManager {
                         tracing::debug!(target: "drive::manager", path = %path.display(), result = ?result, "ViewOnline command result");
                     });
                 }
-                ManagerCommand::PersistConfig => {
-                    let result = manager.persist().await;
-                    if let Err(e) = result {
-                        tracing::error!(target: "drive::manager", error = %e, "Failed to persist config");
-                    }
-                }
+                ManagerCommand::PersistConfig => manager.debounce_persist(),
                 ManagerCommand::SyncNow { paths, mode } => {
                     let paths = paths.clone();
                     if paths.len() < 1 {
@@ -146,6 +144,122 @@ impl DriveManager {
                 ManagerCommand::OpenSettingsWindow => {
                     manager.event_broadcaster.open_settings_window();
                 }
+                ManagerCommand::ThreatDetected {
+                    drive_id,
+                    path,
+                    detail,
+                } => {
+                    send_threat_toast(&path);
+                    manager.event_broadcaster.security_threat_detected(
+                        drive_id,
+                        path.display().to_string(),
+                        detail,
+                    );
+                }
+                ManagerCommand::MountStateChanged { drive_id, state } => {
+                    manager
+                        .event_broadcaster
+                        .mount_state_changed(drive_id, state);
+                }
+                ManagerCommand::SyncItemQuarantined {
+                    drive_id,
+                    path,
+                    operation,
+                } => {
+                    manager.event_broadcaster.sync_item_quarantined(
+                        drive_id,
+                        path.display().to_string(),
+                        operation,
+                    );
+                }
+                ManagerCommand::ReauthRequired {
+                    drive_id,
+                    drive_name,
+                } => {
+                    manager
+                        .event_broadcaster
+                        .reauth_required(drive_id, drive_name);
+                }
+                ManagerCommand::ConflictDetected { drive_id, path } => {
+                    manager
+                        .event_broadcaster
+                        .conflict_detected(drive_id, path.display().to_string());
+                }
+                ManagerCommand::MassDeletion { drive_id, count } => {
+                    manager
+                        .event_broadcaster
+                        .mass_deletion_detected(drive_id, count);
+                }
+                ManagerCommand::BatchOperation { operation, paths } => {
+                    spawn(async move {
+                        let result = manager.start_batch_operation(operation, paths).await;
+                        if let Err(e) = result {
+                            tracing::error!(target: "drive::manager", operation = ?operation, error = %e, "Failed to start batch operation");
+                            send_general_text_toast(&operation.title(), &e.to_string());
+                        }
+                    });
+                }
+                ManagerCommand::PrefetchFolder { paths } => {
+                    for path in paths {
+                        let manager = manager.clone();
+                        spawn(async move {
+                            let result = manager
+                                .prefetch_folder(path.clone(), Some(DEFAULT_PREFETCH_MAX_FILE_SIZE))
+                                .await;
+                            if let Err(e) = result {
+                                tracing::error!(target: "drive::manager", path = %path.display(), error = %e, "Failed to start folder prefetch");
+                                send_general_text_toast(&t!("prefetchFolder"), &e.to_string());
+                            }
+                        });
+                    }
+                }
+                ManagerCommand::ShareLinksCreated { drive_id, links } => {
+                    manager
+                        .event_broadcaster
+                        .share_links_created(drive_id, links);
+                }
+                ManagerCommand::ServerMaintenance { instance_url } => {
+                    manager.enter_maintenance(instance_url).await;
+                }
+                ManagerCommand::FolderImported {
+                    drive_id,
+                    source,
+                    uri,
+                    ignore_patterns,
+                } => {
+                    spawn(async move {
+                        let result = manager
+                            .adopt_imported_folder(&drive_id, source.clone(), uri, ignore_patterns)
+                            .await;
+                        if let Err(e) = result {
+                            tracing::error!(target: "drive::manager", source = %source.display(), error = ?e, "Failed to sync imported folder");
+                            send_general_text_toast(
+                                &t!("folderImportFinished"),
+                                &t!("folderImportSetupFailed", "path" => source.display(), "error" => e),
+                            );
+                        }
+                    });
+                }
+                ManagerCommand::RemoteWipe { drive_id, request } => {
+                    spawn(async move {
+                        manager.remote_wipe(drive_id, request).await;
+                    });
+                }
+                ManagerCommand::PinMismatch {
+                    drive_id,
+                    drive_name,
+                    instance_url,
+                    mismatch,
+                } => {
+                    tracing::error!(target: "drive::manager", drive_id = %drive_id, instance_url = %instance_url, error = %mismatch, "Server certificate changed from its pin");
+                    let presented = mismatch.presented.iter().map(ToString::to_string).collect();
+                    manager.event_broadcaster.pin_mismatch(
+                        drive_id,
+                        drive_name,
+                        instance_url,
+                        presented,
+                    );
+                }
             }
         }
 
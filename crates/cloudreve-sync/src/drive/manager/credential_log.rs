@@ -0,0 +1,31 @@
+use super::DriveManager;
+use crate::inventory::{CredentialEvent, CredentialEventKind, CredentialSource};
+use anyhow::{Context, Result};
+
+/// Credential events listed for a drive
+const LISTED_CREDENTIAL_EVENTS: i64 = 200;
+
+impl DriveManager {
+    /// List the sign-ins, token refreshes and credential updates of a drive,
+    /// most recent first
+    pub async fn list_credential_events(&self, drive_id: &str) -> Result<Vec<CredentialEvent>> {
+        let inventory = self.inventory.clone();
+        let drive_id = drive_id.to_string();
+        tokio::task::spawn_blocking(move || {
+            inventory.list_credential_events(&drive_id, LISTED_CREDENTIAL_EVENTS)
+        })
+        .await
+        .context("Credential event listing panicked")?
+    }
+
+    /// Record the user signing in to a drive, logging failures
+    pub(super) fn record_sign_in(&self, drive_id: &str, kind: CredentialEventKind) {
+        tracing::info!(target: "drive::credentials", drive_id = %drive_id, kind = kind.as_str(), "Credential event");
+        if let Err(e) =
+            self.inventory
+                .record_credential_event(drive_id, kind, CredentialSource::User, "")
+        {
+            tracing::warn!(target: "drive::credentials", drive_id = %drive_id, error = %e, "Failed to record credential event");
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use super::DriveManager;
+use crate::drive::mounts::{DriveConfig, Mount};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Why a drive can't be added
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum AddDriveError {
+    /// The same remote folder already syncs to the same local folder
+    #[error("Drive is already added as {existing_id}")]
+    AlreadyExists { existing_id: String },
+    /// The local folder already syncs with another remote folder
+    #[error("Local folder is already synced by drive {existing_id}")]
+    SyncPathInUse { existing_id: String },
+}
+
+impl AddDriveError {
+    /// Drive the new one conflicts with
+    pub fn existing_id(&self) -> &str {
+        match self {
+            AddDriveError::AlreadyExists { existing_id }
+            | AddDriveError::SyncPathInUse { existing_id } => existing_id,
+        }
+    }
+}
+
+pub(super) fn same_instance(a: &str, b: &str) -> bool {
+    let normalize = |url: &str| url.trim().trim_end_matches('/').to_lowercase();
+    normalize(a) == normalize(b)
+}
+
+/// Windows paths are case-insensitive and may end with a separator
+fn same_sync_path(a: &Path, b: &Path) -> bool {
+    let normalize = |path: &Path| {
+        path.to_string_lossy()
+            .trim_end_matches(['\\', '/'])
+            .to_lowercase()
+    };
+    normalize(a) == normalize(b)
+}
+
+/// Check a new drive against an existing one
+fn conflict(
+    existing: &DriveConfig,
+    instance_url: &str,
+    remote_path: &str,
+    sync_path: &Path,
+) -> Option<AddDriveError> {
+    if !same_sync_path(&existing.sync_path, sync_path) {
+        return None;
+    }
+    let existing_id = existing.id.clone();
+    if same_instance(&existing.instance_url, instance_url) && existing.remote_path == remote_path {
+        Some(AddDriveError::AlreadyExists { existing_id })
+    } else {
+        Some(AddDriveError::SyncPathInUse { existing_id })
+    }
+}
+
+/// Find the first drive in `drives` a new drive would conflict with
+pub(super) async fn find_conflict(
+    drives: &HashMap<String, Arc<Mount>>,
+    instance_url: &str,
+    remote_path: &str,
+    sync_path: &Path,
+) -> Option<AddDriveError> {
+    for mount in drives.values() {
+        let config = mount.config.read().await;
+        if let Some(error) = conflict(&config, instance_url, remote_path, sync_path) {
+            return Some(error);
+        }
+    }
+    None
+}
+
+/// Find the first registered but unmounted drive a new drive would conflict
+/// with
+pub(super) fn find_unmounted_conflict(
+    drives: &HashMap<String, DriveConfig>,
+    instance_url: &str,
+    remote_path: &str,
+    sync_path: &Path,
+) -> Option<AddDriveError> {
+    drives
+        .values()
+        .find_map(|config| conflict(config, instance_url, remote_path, sync_path))
+}
+
+impl DriveManager {
+    /// Check whether a drive for `remote_path` on `instance_url` can be
+    /// synced to `sync_path`, so the add drive wizard can offer to adopt the
+    /// existing drive instead
+    pub async fn check_new_drive(
+        &self,
+        instance_url: &str,
+        remote_path: &str,
+        sync_path: &Path,
+    ) -> Result<(), AddDriveError> {
+        let drives = self.drives.read().await;
+        if let Some(error) = find_conflict(&drives, instance_url, remote_path, sync_path).await {
+            return Err(error);
+        }
+        let unmounted = self.unmounted.read().await;
+        match find_unmounted_conflict(&unmounted, instance_url, remote_path, sync_path) {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drive(instance_url: &str, remote_path: &str, sync_path: &str) -> DriveConfig {
+        serde_json::from_value(serde_json::json!({
+            "id": "existing",
+            "name": "Drive",
+            "instance_url": instance_url,
+            "remote_path": remote_path,
+            "credentials": { "refresh_token": "", "refresh_expires": "" },
+            "sync_path": sync_path,
+            "enabled": true,
+            "user_id": "user",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn duplicates_are_detected() {
+        let existing = drive("https://cloud.example.com/", "cloudreve://my", "C:\\Cloud");
+
+        assert_eq!(
+            conflict(
+                &existing,
+                "https://Cloud.example.com",
+                "cloudreve://my",
+                Path::new("c:\\cloud\\"),
+            ),
+            Some(AddDriveError::AlreadyExists {
+                existing_id: "existing".to_string()
+            })
+        );
+        assert_eq!(
+            conflict(
+                &existing,
+                "https://cloud.example.com",
+                "cloudreve://my/docs",
+                Path::new("C:\\Cloud"),
+            ),
+            Some(AddDriveError::SyncPathInUse {
+                existing_id: "existing".to_string()
+            })
+        );
+        assert_eq!(
+            conflict(
+                &existing,
+                "https://cloud.example.com",
+                "cloudreve://my",
+                Path::new("C:\\Cloud 2"),
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn unmounted_drives_are_checked() {
+        let mut drives = HashMap::new();
+        drives.insert(
+            "existing".to_string(),
+            drive("https://cloud.example.com", "cloudreve://my", "C:\\Cloud"),
+        );
+
+        assert_eq!(
+            find_unmounted_conflict(
+                &drives,
+                "https://cloud.example.com",
+                "cloudreve://my",
+                Path::new("C:\\Cloud"),
+            ),
+            Some(AddDriveError::AlreadyExists {
+                existing_id: "existing".to_string()
+            })
+        );
+        assert_eq!(
+            find_unmounted_conflict(
+                &drives,
+                "https://cloud.example.com",
+                "cloudreve://my",
+                Path::new("D:\\Cloud"),
+            ),
+            None
+        );
+    }
+}
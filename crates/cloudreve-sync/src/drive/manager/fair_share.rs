@@ -0,0 +1,92 @@
+use super::DriveManager;
+use crate::config::ConfigManager;
+use crate::tasks::FairShare;
+use crate::tasks::fair_share::{Demand, FAIR_SHARE_INTERVAL, allocate};
+use anyhow::Result;
+use std::sync::Arc;
+use std::time::Duration;
+
+impl DriveManager {
+    /// Spawn a background task splitting the transfer budget between the
+    /// drives with queued work, so that one busy drive does not starve the
+    /// others
+    pub fn spawn_fair_scheduler(self: &Arc<Self>) {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            loop {
+                manager.rebalance_transfers().await;
+                tokio::time::sleep(FAIR_SHARE_INTERVAL).await;
+            }
+        });
+    }
+
+    /// Give `drive_id` a larger share of the transfers for `duration`, e.g.
+    /// while the user waits for it. A zero duration ends the boost.
+    pub async fn boost_drive(&self, drive_id: &str, duration: Duration) -> Result<()> {
+        if !self.drives.read().await.contains_key(drive_id) {
+            anyhow::bail!("Drive not found: {}", drive_id);
+        }
+        self.boosts.set(drive_id, duration);
+        tracing::info!(
+            target: "drive::manager",
+            drive_id,
+            duration_secs = duration.as_secs(),
+            "Drive boosted"
+        );
+        self.rebalance_transfers().await;
+        Ok(())
+    }
+
+    /// Time left on the boost of `drive_id`, if it is boosted
+    pub fn drive_boost_remaining(&self, drive_id: &str) -> Option<Duration> {
+        self.boosts.remaining(drive_id)
+    }
+
+    /// Recompute each drive's share of the transfer budget from the work
+    /// queued right now
+    async fn rebalance_transfers(&self) {
+        let settings = ConfigManager::try_get()
+            .map(|config| config.fair_scheduling())
+            .unwrap_or_default();
+        let drives: Vec<_> = self
+            .drives
+            .read()
+            .await
+            .iter()
+            .map(|(id, mount)| (id.clone(), mount.clone()))
+            .collect();
+
+        if !settings.enabled || drives.len() < 2 {
+            for (_, mount) in &drives {
+                mount.task_queue().set_fair_share(None);
+            }
+            return;
+        }
+
+        let mut uploads = Vec::with_capacity(drives.len());
+        let mut downloads = Vec::with_capacity(drives.len());
+        for (id, mount) in &drives {
+            let weight = self
+                .boosts
+                .weight(id, mount.get_transfer_limits().await.priority);
+            let (queued_uploads, queued_downloads) = mount.task_queue().demand();
+            uploads.push(Demand {
+                tasks: queued_uploads,
+                weight,
+            });
+            downloads.push(Demand {
+                tasks: queued_downloads,
+                weight,
+            });
+        }
+
+        let upload_shares = allocate(settings.max_uploads.max(1), &uploads);
+        let download_shares = allocate(settings.max_downloads.max(1), &downloads);
+        for (i, (_, mount)) in drives.iter().enumerate() {
+            mount.task_queue().set_fair_share(Some(FairShare {
+                uploads: upload_shares[i],
+                downloads: download_shares[i],
+            }));
+        }
+    }
+}
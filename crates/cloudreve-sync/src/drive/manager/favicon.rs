@@ -37,7 +37,7 @@ enum IconType {
 }
 
 /// Get the icons directory path
-fn get_icons_dir() -> Result<PathBuf> {
+pub(crate) fn get_icons_dir() -> Result<PathBuf> {
     let home_dir = dirs::home_dir().context("Failed to get user home directory")?;
     let icons_dir = home_dir.join(".cloudreve").join("icos");
 
@@ -49,6 +49,13 @@ fn get_icons_dir() -> Result<PathBuf> {
     Ok(icons_dir)
 }
 
+/// Hash of the icon file at `path`, to tell whether it was overwritten with
+/// other content. `None` if there is no icon or it can't be read.
+pub(crate) fn icon_digest(path: Option<&str>) -> Option<Vec<u8>> {
+    let bytes = std::fs::read(path?).ok()?;
+    Some(Sha256::digest(&bytes).to_vec())
+}
+
 /// Parse icon size from sizes string (e.g., "192x192" or "64x64 32x32")
 /// Returns the first (typically largest for multi-size) dimension
 fn parse_icon_size(sizes: &str) -> Option<u32> {
@@ -0,0 +1,57 @@
+use super::DriveManager;
+use super::remote_transfer::resolve;
+use crate::tasks::{FOLDER_EXPORT_KIND, FolderExportState, TaskKind, TaskPayload};
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+impl DriveManager {
+    /// Export a hydrated copy of the remote folder `source` of a drive into
+    /// the local folder `destination`, which must be empty and outside every
+    /// sync root. `source` is a local path in the drive or a `cloudreve://`
+    /// URI. Returns the ID of the task tracking the export.
+    pub async fn export_folder(
+        &self,
+        drive_id: &str,
+        source: String,
+        destination: PathBuf,
+    ) -> Result<String> {
+        if !destination.is_absolute() {
+            anyhow::bail!("Export destination must be an absolute path");
+        }
+        for mount in self.drives.read().await.values() {
+            let config = mount.get_config().await;
+            if destination.starts_with(&config.sync_path)
+                || config.sync_path.starts_with(&destination)
+            {
+                anyhow::bail!(
+                    "Export destination overlaps the sync folder of drive {}",
+                    config.name
+                );
+            }
+        }
+        let occupied = match std::fs::read_dir(&destination) {
+            Ok(mut entries) => entries.next().is_some(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => false,
+            Err(e) => return Err(e).context("Failed to read export destination"),
+        };
+        if occupied {
+            anyhow::bail!("Export destination is not empty");
+        }
+
+        let mount = self
+            .get_drive(drive_id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", drive_id))?;
+        let config = mount.get_config().await;
+        let (uri, local) = resolve(&config, &source)?;
+
+        let state = FolderExportState::new(uri.to_string(), destination.clone());
+        let task_path = local.unwrap_or_else(|| PathBuf::from(&source));
+        let payload = TaskPayload::new(TaskKind::Custom(FOLDER_EXPORT_KIND), task_path)
+            .with_custom_state(serde_json::to_value(&state)?);
+
+        let task_id = mount.task_queue.enqueue(payload).await?;
+        tracing::info!(target: "drive::manager", drive_id = %drive_id, task_id = %task_id, uri = %state.uri, destination = %destination.display(), "Queued folder export");
+        Ok(task_id)
+    }
+}
@@ -0,0 +1,129 @@
+use super::DriveManager;
+use super::remote_transfer::resolve;
+use crate::drive::mounts::DriveConfig;
+use crate::policy::Policy;
+use crate::tasks::{
+    FOLDER_IMPORT_KIND, FolderImportState, ImportMapping, ImportSource, TaskKind, TaskPayload,
+};
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+impl DriveManager {
+    /// Upload the local folder `source`, typically the folder of another
+    /// sync provider, into the remote folder `destination` of a drive.
+    /// `destination` is a local path in the drive or a `cloudreve://` URI.
+    /// With `setup_sync_root`, `source` becomes the sync folder of a new drive
+    /// once imported. Returns the ID of the task tracking the import.
+    pub async fn import_folder(
+        &self,
+        drive_id: &str,
+        source: PathBuf,
+        destination: String,
+        mappings: Vec<ImportMapping>,
+        skip_duplicates: bool,
+        setup_sync_root: bool,
+    ) -> Result<String> {
+        if Policy::get().force_read_only {
+            anyhow::bail!("Remote changes are disabled by administrator policy");
+        }
+        if !source.is_absolute() || !source.is_dir() {
+            anyhow::bail!("{} is not a folder", source.display());
+        }
+        if setup_sync_root && !mappings.is_empty() {
+            anyhow::bail!("A folder imported with mappings can't become a sync folder");
+        }
+        for mount in self.drives.read().await.values() {
+            let config = mount.get_config().await;
+            if source.starts_with(&config.sync_path) || config.sync_path.starts_with(&source) {
+                anyhow::bail!(
+                    "{} overlaps the sync folder of drive {}",
+                    source.display(),
+                    config.name
+                );
+            }
+        }
+
+        let mount = self
+            .get_drive(drive_id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", drive_id))?;
+        if mount.is_guest_share() {
+            anyhow::bail!("Folders can't be imported into a share mounted as a guest");
+        }
+        let config = mount.get_config().await;
+        let (uri, local) = resolve(&config, &destination)?;
+        if setup_sync_root && local.is_some() {
+            anyhow::bail!("{} is already synced by drive {}", destination, config.name);
+        }
+
+        let provider = ImportSource::detect(&source);
+        let state = FolderImportState {
+            source: source.clone(),
+            provider,
+            uri: uri.to_string(),
+            mappings,
+            skip_duplicates,
+            setup_sync_root,
+            scanned: false,
+            folders: Vec::new(),
+            files: Vec::new(),
+        };
+        let payload = TaskPayload::new(TaskKind::Custom(FOLDER_IMPORT_KIND), source.clone())
+            .with_custom_state(serde_json::to_value(&state)?);
+
+        let task_id = mount.task_queue.enqueue(payload).await?;
+        tracing::info!(target: "drive::manager", drive_id = %drive_id, task_id = %task_id, source = %source.display(), provider = ?provider, uri = %state.uri, "Queued folder import");
+        Ok(task_id)
+    }
+
+    /// Add a drive syncing the imported folder `source` with `uri`, signed in
+    /// like the drive that imported it. Merge mode adopts the imported files.
+    pub(super) async fn adopt_imported_folder(
+        &self,
+        drive_id: &str,
+        source: PathBuf,
+        uri: String,
+        ignore_patterns: Vec<String>,
+    ) -> Result<String> {
+        let importer = self
+            .get_drive(drive_id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", drive_id))?
+            .get_config()
+            .await;
+        let name = source
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| importer.name.clone());
+
+        let mut patterns = importer.ignore_patterns.clone();
+        for pattern in ignore_patterns {
+            if !patterns.contains(&pattern) {
+                patterns.push(pattern);
+            }
+        }
+        let config = DriveConfig {
+            id: String::new(),
+            name,
+            remote_path: uri,
+            sync_path: source.clone(),
+            portable_sync_path: None,
+            sync_root_id: None,
+            ignore_patterns: patterns,
+            label: None,
+            label_icon_path: None,
+            import_existing: true,
+            usn_checkpoint: None,
+            resume: Default::default(),
+            last_state: None,
+            ..importer
+        };
+
+        let id = self
+            .add_drive(config)
+            .await
+            .with_context(|| format!("Failed to sync {}", source.display()))?;
+        tracing::info!(target: "drive::manager", drive_id = %id, source = %source.display(), "Imported folder is now synced");
+        Ok(id)
+    }
+}
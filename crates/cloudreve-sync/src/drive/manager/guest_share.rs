@@ -0,0 +1,76 @@
+use super::DriveManager;
+use crate::drive::guest_share::parse_share_link;
+use crate::drive::mounts::{Credentials, DriveConfig, SyncDirection};
+use anyhow::{Context, Result};
+use cloudreve_api::api::ShareApi;
+use cloudreve_api::{Client, ClientConfig};
+use std::path::PathBuf;
+use uuid::Uuid;
+
+impl DriveManager {
+    /// Add a read-only drive from a public share link, browsed as a guest.
+    /// `drive_name` defaults to the name of the share.
+    pub async fn add_share_drive(
+        &self,
+        link: &str,
+        password: Option<String>,
+        local_path: PathBuf,
+        drive_name: Option<String>,
+        import_existing: bool,
+    ) -> Result<String> {
+        let (instance_url, share) = parse_share_link(link, password)?;
+
+        // Make sure the share is there and the password is right before
+        // setting up a sync root for it
+        let client = Client::new(
+            ClientConfig::new(instance_url.clone())
+                .with_device_user_agent(crate::USER_AGENT, &crate::device_name()),
+        );
+        let info = client
+            .get_share_info(&share.share_id, share.password.as_deref())
+            .await
+            .context("Failed to open share")?;
+        if info.expired == Some(true) {
+            anyhow::bail!("Share has expired");
+        }
+
+        let name = drive_name
+            .or(info.name)
+            .unwrap_or_else(|| share.share_id.clone());
+        let config = DriveConfig {
+            id: Uuid::new_v4().to_string(),
+            name,
+            instance_url,
+            remote_path: share.uri(),
+            credentials: Credentials::default(),
+            sync_path: local_path,
+            portable_sync_path: None,
+            icon_path: None,
+            raw_icon_path: None,
+            enabled: true,
+            user_id: String::new(),
+            sync_root_id: None,
+            ignore_patterns: Vec::new(),
+            type_filter: Default::default(),
+            sync_direction: SyncDirection::default(),
+            site_title: None,
+            label: None,
+            label_icon_path: None,
+            import_existing,
+            usn_checkpoint: None,
+            resume: Default::default(),
+            network: Default::default(),
+            compression: Default::default(),
+            transfer_limits: Default::default(),
+            guest_share: Some(share),
+            kind: Default::default(),
+            last_state: None,
+            suspicious_changes: None,
+            extra: Default::default(),
+        };
+
+        let id = self.add_drive(config).await?;
+        tracing::info!(target: "drive::manager", drive_id = %id, "Added drive from share link");
+        Ok(id)
+    }
+}
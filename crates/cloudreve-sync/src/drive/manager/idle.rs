@@ -0,0 +1,29 @@
+use super::DriveManager;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often deferred background work is checked
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+impl DriveManager {
+    /// Spawn a background task running deferred full syncs once the user is
+    /// idle and power saving has ended
+    pub fn spawn_idle_scheduler(self: &Arc<Self>) {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(IDLE_CHECK_INTERVAL).await;
+                manager.run_deferred_work(false).await;
+            }
+        });
+    }
+
+    /// Run background work deferred for power saving or user activity if it
+    /// may run now, or right away with `force`
+    pub async fn run_deferred_work(&self, force: bool) {
+        let drives: Vec<_> = self.drives.read().await.values().cloned().collect();
+        for drive in drives {
+            drive.run_deferred_full_sync(force).await;
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use super::DriveManager;
+use crate::drive::initial_report::InitialSyncReport;
+use anyhow::{Context, Result};
+
+impl DriveManager {
+    /// Report written after the first full sync of a drive, `None` until
+    /// that sync finished
+    pub async fn get_initial_sync_report(
+        &self,
+        drive_id: &str,
+    ) -> Result<Option<InitialSyncReport>> {
+        let inventory = self.inventory.clone();
+        let drive_id = drive_id.to_string();
+        let report = tokio::task::spawn_blocking(move || inventory.initial_sync_report(&drive_id))
+            .await
+            .context("Initial sync report lookup panicked")??;
+        report
+            .map(|json| serde_json::from_str(&json).context("Failed to parse initial sync report"))
+            .transpose()
+    }
+}
@@ -0,0 +1,290 @@
+//! Everything currently wrong across drives, gathered for one issues panel.
+//!
+//! Each issue carries an ID that [DriveManager::resolve_issue] takes back.
+//! Resolving does what needs no input from the user, such as retrying a
+//! failed file, and returns the issue so the caller can open whatever window
+//! the rest of the action needs.
+
+use super::{DriveInfoStatus, DriveManager};
+use crate::inventory::SyncFailureState;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::Path;
+use widestring::U16CString;
+use windows::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+use windows::core::PCWSTR;
+
+/// Below this much free space on the disk of a sync folder, downloads may fail
+const LOW_DISK_SPACE: u64 = 1024 * 1024 * 1024;
+/// Share of the cloud storage used before warning about it
+const STORAGE_WARNING_RATIO: f64 = 0.95;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueSeverity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    /// A file changed both locally and in the cloud
+    Conflict,
+    /// A file kept failing to sync and was set aside
+    SyncFailure,
+    /// A downloaded file was flagged by the scanner
+    Threat,
+    CredentialExpired,
+    /// The share a guest drive mounts is gone
+    ShareUnavailable,
+    /// Cloud storage is full or nearly full
+    StorageFull,
+    /// The disk of the sync folder is nearly full
+    LowDiskSpace,
+    /// Changes made in the cloud are only noticed by periodic syncs
+    EventPushLost,
+    ServerMaintenance,
+    /// The local sync folder was deleted or its disk is gone
+    SyncRootMissing,
+    /// Many files were rewritten with content that looks encrypted
+    SuspiciousChanges,
+}
+
+/// What the user can do about an issue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueAction {
+    /// Choose which version of the file to keep
+    ResolveConflict,
+    /// Sync the file again
+    Retry,
+    /// Look at the file and release it if it is safe
+    ReviewThreat,
+    /// Sign in to the drive again
+    Reauthorize,
+    /// Remove the drive
+    RemoveDrive,
+    /// Delete files in the cloud or get more storage
+    FreeCloudSpace,
+    /// Delete files or free up space on the disk
+    FreeDiskSpace,
+    /// Nothing to do, the drive recovers on its own
+    Wait,
+    /// Point the drive at another folder, or recreate the missing one
+    RecoverSyncRoot,
+    /// Restore the files from the cloud, or resume syncing if the changes
+    /// were expected
+    ReviewSuspiciousChanges,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncIssue {
+    /// Stable while the issue lasts, passed to [DriveManager::resolve_issue]
+    pub id: String,
+    pub kind: IssueKind,
+    pub severity: IssueSeverity,
+    pub drive_id: String,
+    pub drive_name: String,
+    /// File the issue is about, if any
+    pub path: Option<String>,
+    /// Inventory ID of a conflicted file, for the conflict window
+    pub file_id: Option<i64>,
+    /// Error message, scanner output or storage usage
+    pub detail: Option<String>,
+    pub action: IssueAction,
+}
+
+impl DriveManager {
+    /// Current issues of every drive, most severe first
+    pub async fn get_issues(&self) -> Result<Vec<SyncIssue>> {
+        let mut issues = Vec::new();
+        for drive in self.get_drives_info().await? {
+            let issue = |kind, severity, action| SyncIssue {
+                id: format!("{}:{}", issue_key(kind), drive.id),
+                kind,
+                severity,
+                drive_id: drive.id.clone(),
+                drive_name: drive.name.clone(),
+                path: None,
+                file_id: None,
+                detail: None,
+                action,
+            };
+
+            match drive.status {
+                // Unmounting is up to the user, nothing to fix
+                DriveInfoStatus::Active | DriveInfoStatus::Unmounted => {}
+                DriveInfoStatus::CredentialExpired => issues.push(issue(
+                    IssueKind::CredentialExpired,
+                    IssueSeverity::Error,
+                    IssueAction::Reauthorize,
+                )),
+                DriveInfoStatus::ShareUnavailable => issues.push(issue(
+                    IssueKind::ShareUnavailable,
+                    IssueSeverity::Error,
+                    IssueAction::RemoveDrive,
+                )),
+                DriveInfoStatus::EventPushLost => issues.push(issue(
+                    IssueKind::EventPushLost,
+                    IssueSeverity::Warning,
+                    IssueAction::Wait,
+                )),
+                DriveInfoStatus::ServerMaintenance => issues.push(issue(
+                    IssueKind::ServerMaintenance,
+                    IssueSeverity::Info,
+                    IssueAction::Wait,
+                )),
+                DriveInfoStatus::SyncRootMissing => issues.push(SyncIssue {
+                    path: Some(drive.sync_path.clone()),
+                    ..issue(
+                        IssueKind::SyncRootMissing,
+                        IssueSeverity::Error,
+                        IssueAction::RecoverSyncRoot,
+                    )
+                }),
+                DriveInfoStatus::SuspiciousChanges => issues.push(issue(
+                    IssueKind::SuspiciousChanges,
+                    IssueSeverity::Error,
+                    IssueAction::ReviewSuspiciousChanges,
+                )),
+            }
+
+            if let Some(capacity) = drive.capacity.as_ref().filter(|c| c.total > 0) {
+                let ratio = capacity.used as f64 / capacity.total as f64;
+                if ratio >= STORAGE_WARNING_RATIO {
+                    let severity = if ratio >= 1.0 {
+                        IssueSeverity::Error
+                    } else {
+                        IssueSeverity::Warning
+                    };
+                    issues.push(SyncIssue {
+                        detail: Some(capacity.label.clone()),
+                        ..issue(
+                            IssueKind::StorageFull,
+                            severity,
+                            IssueAction::FreeCloudSpace,
+                        )
+                    });
+                }
+            }
+
+            match free_disk_space(Path::new(&drive.sync_path)) {
+                Ok(free) if free < LOW_DISK_SPACE => issues.push(SyncIssue {
+                    path: Some(drive.sync_path.clone()),
+                    detail: Some(crate::utils::format::format_bytes(free as i64)),
+                    ..issue(
+                        IssueKind::LowDiskSpace,
+                        IssueSeverity::Warning,
+                        IssueAction::FreeDiskSpace,
+                    )
+                }),
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::debug!(target: "drive::manager", drive_id = %drive.id, error = %e, "Failed to get free disk space");
+                }
+            }
+
+            let inventory = self.inventory.clone();
+            let drive_id = drive.id.clone();
+            let (conflicts, failures, threats) = tokio::task::spawn_blocking(move || {
+                anyhow::Ok((
+                    inventory.list_conflicts(&drive_id)?,
+                    inventory.list_sync_failures(&drive_id, &[SyncFailureState::Quarantined])?,
+                    inventory.list_quarantined(&drive_id)?,
+                ))
+            })
+            .await
+            .context("Issue listing panicked")??;
+
+            for conflict in conflicts {
+                issues.push(SyncIssue {
+                    id: format!("conflict:{}", conflict.id),
+                    path: Some(conflict.local_path),
+                    file_id: Some(conflict.id),
+                    ..issue(
+                        IssueKind::Conflict,
+                        IssueSeverity::Warning,
+                        IssueAction::ResolveConflict,
+                    )
+                });
+            }
+            for failure in failures {
+                issues.push(SyncIssue {
+                    id: format!("sync_failure:{}", failure.id),
+                    path: Some(failure.local_path),
+                    detail: Some(failure.last_error),
+                    ..issue(
+                        IssueKind::SyncFailure,
+                        IssueSeverity::Error,
+                        IssueAction::Retry,
+                    )
+                });
+            }
+            for threat in threats {
+                issues.push(SyncIssue {
+                    id: format!("threat:{}", threat.local_path),
+                    path: Some(threat.local_path),
+                    detail: Some(threat.detail),
+                    ..issue(
+                        IssueKind::Threat,
+                        IssueSeverity::Error,
+                        IssueAction::ReviewThreat,
+                    )
+                });
+            }
+        }
+
+        // Stable sort keeps drive order within a severity
+        issues.sort_by(|a, b| b.severity.cmp(&a.severity));
+        Ok(issues)
+    }
+
+    /// Resolve the issue with `id` as far as possible without the user, and
+    /// return it so the caller can handle the rest of its action
+    pub async fn resolve_issue(&self, id: &str) -> Result<SyncIssue> {
+        let issue = self
+            .get_issues()
+            .await?
+            .into_iter()
+            .find(|issue| issue.id == id)
+            .context("Issue no longer exists")?;
+
+        if issue.action == IssueAction::Retry {
+            let failure_id = id
+                .strip_prefix("sync_failure:")
+                .and_then(|id| id.parse().ok())
+                .context("Invalid sync failure issue")?;
+            self.retry_sync_failures(vec![failure_id]).await?;
+        }
+        tracing::info!(target: "drive::manager", issue = %issue.id, action = ?issue.action, "Resolving issue");
+        Ok(issue)
+    }
+}
+
+/// Prefix of the IDs of drive-wide issues
+fn issue_key(kind: IssueKind) -> &'static str {
+    match kind {
+        IssueKind::Conflict => "conflict",
+        IssueKind::SyncFailure => "sync_failure",
+        IssueKind::Threat => "threat",
+        IssueKind::CredentialExpired => "credential_expired",
+        IssueKind::ShareUnavailable => "share_unavailable",
+        IssueKind::StorageFull => "storage_full",
+        IssueKind::LowDiskSpace => "low_disk_space",
+        IssueKind::EventPushLost => "event_push_lost",
+        IssueKind::ServerMaintenance => "server_maintenance",
+        IssueKind::SyncRootMissing => "sync_root_missing",
+        IssueKind::SuspiciousChanges => "suspicious_changes",
+    }
+}
+
+/// Free space available to the user on the disk holding `path`
+fn free_disk_space(path: &Path) -> Result<u64> {
+    let path = U16CString::from_os_str(path.as_os_str()).context("Invalid path")?;
+    let mut free = 0u64;
+    unsafe { GetDiskFreeSpaceExW(PCWSTR(path.as_ptr()), Some(&mut free), None, None) }
+        .context("Failed to get free disk space")?;
+    Ok(free)
+}
@@ -0,0 +1,108 @@
+//! Planned server downtime
+//!
+//! A server under maintenance answers with 503 and a maintenance marker. The
+//! first such response pauses every drive on that server instead of letting
+//! each request fail and log on its own. The server is then pinged with
+//! backoff, and once it answers again the drives resume with a full sync.
+
+use super::DriveManager;
+use crate::utils::toast::send_general_text_toast;
+use cloudreve_api::api::SiteApi;
+use cloudreve_api::{Client, ClientConfig};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+/// First delay before checking whether the server is back
+const INITIAL_PROBE_DELAY: Duration = Duration::from_secs(30);
+/// Longest delay between checks, unless the server asks for more
+const MAX_PROBE_DELAY: Duration = Duration::from_secs(10 * 60);
+/// Longest delay honored from `Retry-After`
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60 * 60);
+
+/// Servers under maintenance, by instance URL
+static IN_MAINTENANCE: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+
+fn in_maintenance() -> &'static Mutex<HashSet<String>> {
+    IN_MAINTENANCE.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+/// Host name shown to the user for a server
+fn display_host(instance_url: &str) -> String {
+    url::Url::parse(instance_url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| instance_url.to_string())
+}
+
+impl DriveManager {
+    /// Pause the drives of a server that said it is under maintenance and
+    /// watch for it to come back. Further reports while it is down are
+    /// ignored.
+    pub(super) async fn enter_maintenance(self: &Arc<Self>, instance_url: String) {
+        {
+            let mut servers = in_maintenance().lock().unwrap();
+            if !servers.insert(instance_url.clone()) {
+                return;
+            }
+        }
+        tracing::warn!(target: "drive::manager", instance_url = %instance_url, "Server is under maintenance, pausing its drives");
+        self.set_drives_maintenance(&instance_url, true).await;
+        self.event_broadcaster
+            .server_maintenance_changed(instance_url.clone(), true);
+        send_general_text_toast(
+            &t!("serverMaintenanceTitle"),
+            &t!("serverMaintenanceMessage", "host" => display_host(&instance_url)),
+        );
+
+        let manager = self.clone();
+        tokio::spawn(async move {
+            manager.wait_for_maintenance_end(&instance_url).await;
+            in_maintenance().lock().unwrap().remove(&instance_url);
+            tracing::info!(target: "drive::manager", instance_url = %instance_url, "Server is back from maintenance, resuming its drives");
+            manager.set_drives_maintenance(&instance_url, false).await;
+            send_general_text_toast(
+                &t!("serverMaintenanceEndedTitle"),
+                &t!("serverMaintenanceEndedMessage", "host" => display_host(&instance_url)),
+            );
+            manager
+                .event_broadcaster
+                .server_maintenance_changed(instance_url, false);
+        });
+    }
+
+    async fn set_drives_maintenance(&self, instance_url: &str, maintenance: bool) {
+        let drives: Vec<_> = self.drives.read().await.values().cloned().collect();
+        for drive in drives {
+            if drive.config.read().await.instance_url == instance_url {
+                drive.set_server_maintenance(maintenance).await;
+            }
+        }
+    }
+
+    /// Ping the server with backoff until it answers normally
+    async fn wait_for_maintenance_end(&self, instance_url: &str) {
+        let client = Client::new(
+            ClientConfig::new(instance_url.to_string())
+                .with_device_user_agent(crate::USER_AGENT, &crate::device_name()),
+        );
+        let mut delay = INITIAL_PROBE_DELAY;
+        loop {
+            tokio::time::sleep(delay).await;
+            match client.ping().await {
+                Ok(_) => return,
+                Err(cloudreve_api::ApiError::Maintenance {
+                    retry_after: Some(retry_after),
+                    ..
+                }) => {
+                    delay = retry_after.clamp(INITIAL_PROBE_DELAY, MAX_RETRY_AFTER);
+                }
+                Err(e) => {
+                    // Network errors are expected while the server restarts
+                    tracing::debug!(target: "drive::manager", instance_url = %instance_url, error = %e, "Server still unavailable");
+                    delay = (delay * 2).min(MAX_PROBE_DELAY);
+                }
+            }
+        }
+    }
+}
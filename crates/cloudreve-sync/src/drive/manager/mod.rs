@@ -1,53 +1,145 @@
+mod batch;
+mod cleanup;
 mod command_handlers;
+mod credential_log;
+mod duplicates;
+mod fair_share;
+mod folder_export;
+mod folder_import;
 pub(crate) mod favicon;
+mod guest_share;
+mod idle;
+mod initial_report;
+mod issues;
+mod maintenance;
+mod mounting;
+mod persist;
+mod pinning;
+mod power;
+mod prefetch;
+mod presentation;
+mod progress;
+mod quarantine;
+mod reconfigure;
+mod recovery;
+mod remote_transfer;
+mod remote_wipe;
+mod shares;
+mod simulated;
+mod snapshots;
+mod sync_failures;
+mod templates;
+mod trash;
 mod types;
 
+pub use cleanup::PurgeReport;
+pub use duplicates::AddDriveError;
+pub use issues::{IssueAction, IssueKind, IssueSeverity, SyncIssue};
+pub use shares::{ShareReport, SharedItem};
+pub use templates::{DriveTemplate, USER_ID_PLACEHOLDER};
+pub use trash::TrashItem;
 pub use types::*;
 
+use crate::drive::command_queue::{self, CommandReceiver, CommandSender};
 use crate::drive::commands::ManagerCommand;
-use crate::drive::mounts::{Credentials, DriveConfig, Mount, SyncDirection};
+use crate::drive::duplicate_files::{DuplicateAction, DuplicateReport, DuplicateResolution};
+use crate::drive::history::FileHistoryEntry;
+use crate::drive::known_folders::find_on_other_disk;
+use crate::drive::label::DriveLabel;
+use crate::drive::lifecycle::MountState;
+use crate::drive::mounts::{
+    Credentials, DriveConfig, DriveKind, Mount, NetworkSettings, SyncDirection,
+};
+use crate::drive::repair::RepairReport;
+use crate::drive::summary::FolderSummary;
+use crate::drive::type_filter::TypeFilter;
+use crate::drive::utils::path_key;
 use crate::EventBroadcaster;
-use crate::inventory::InventoryDb;
-use crate::tasks::TaskProgress;
+use crate::inventory::{CredentialEventKind, InventoryDb};
+use crate::tasks::fair_share::Boosts;
+use crate::tasks::{self, TaskHandler, TaskProgress, TransferLimits};
+use crate::uploader::{CompressionSettings, CompressionStats, IntegrityStats};
+use crate::utils::format::{format_bytes, format_percentage};
+use crate::vfs::CloudFilesVfs;
 use anyhow::{Context, Result};
+use cleanup::Transitions;
+use cloudreve_api::ClientConfig;
+use cloudreve_api::capabilities::{
+    ApiVersion, Capabilities, MIN_TESTED_VERSION, detect_api_version,
+};
+use persist::PersistState;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use std::{fs, thread};
-use tokio::sync::{Mutex, RwLock, mpsc};
+use tokio::sync::{Mutex, RwLock};
+use uuid::Uuid;
 
 pub struct DriveManager {
     pub(super) drives: Arc<RwLock<HashMap<String, Arc<Mount>>>>,
+    /// Drives registered but not mounted, see [`Self::register_drive`]
+    pub(super) unmounted: RwLock<HashMap<String, DriveConfig>>,
     config_dir: PathBuf,
     pub(super) inventory: Arc<InventoryDb>,
-    pub(super) command_tx: mpsc::UnboundedSender<ManagerCommand>,
-    pub(super) command_rx: Arc<Mutex<Option<mpsc::UnboundedReceiver<ManagerCommand>>>>,
+    pub(super) command_tx: CommandSender<ManagerCommand>,
+    pub(super) command_rx: Arc<Mutex<Option<CommandReceiver<ManagerCommand>>>>,
     pub(super) processor_handle: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
     pub(super) event_broadcaster: Arc<EventBroadcaster>,
+    persist_state: PersistState,
+    /// Drives temporarily given a larger share of the transfers
+    pub(super) boosts: Boosts,
+    /// Drives on their way from `drives` to `unmounted`
+    pub(super) transitions: Transitions,
 }
 
 impl DriveManager {
     /// Create a new DriveManager instance
     pub fn new(event_broadcaster: Arc<EventBroadcaster>) -> Result<Self> {
-        let config_dir = Self::get_config_dir()?;
+        Self::with_config_dir(event_broadcaster, Self::get_config_dir()?)
+    }
 
+    /// Create a DriveManager keeping `drives.json` and the inventory database
+    /// in `config_dir` instead of `~/.cloudreve`
+    pub fn with_config_dir(
+        event_broadcaster: Arc<EventBroadcaster>,
+        config_dir: PathBuf,
+    ) -> Result<Self> {
         // Ensure config directory exists
         if !config_dir.exists() {
             fs::create_dir_all(&config_dir)
                 .context("Failed to create .cloudreve config directory")?;
         }
 
-        let (command_tx, command_rx) = mpsc::unbounded_channel();
+        let (command_tx, command_rx) = command_queue::channel();
+        tasks::remote_transfer::register()?;
+        tasks::batch::register(Arc::new(CloudFilesVfs))?;
+        tasks::folder_export::register()?;
+        tasks::folder_import::register()?;
+        tasks::bulk_upload::register()?;
+        tasks::prefetch::register(Arc::new(CloudFilesVfs))?;
+        tasks::simulated_transfer::register()?;
+        tasks::trash_restore::register()?;
+
+        let inventory = Arc::new(
+            InventoryDb::with_path(config_dir.join("meta.db"))
+                .context("Failed to create inventory database")?,
+        );
+        // Keep critical events until the UI has seen them
+        event_broadcaster.attach_outbox(inventory.clone());
 
         Ok(Self {
             config_dir,
             drives: Arc::new(RwLock::new(HashMap::new())),
-            inventory: Arc::new(InventoryDb::new().context("Failed to create inventory database")?),
+            unmounted: RwLock::new(HashMap::new()),
+            inventory,
             command_tx,
             command_rx: Arc::new(Mutex::new(Some(command_rx))),
             processor_handle: Arc::new(Mutex::new(None)),
             event_broadcaster: event_broadcaster,
+            persist_state: PersistState::default(),
+            boosts: Boosts::default(),
+            transitions: Transitions::default(),
         })
     }
 
@@ -55,6 +147,13 @@ impl DriveManager {
         self.inventory.clone()
     }
 
+    /// Register a handler for a custom task type. Call before [`Self::load`]
+    /// so that persisted tasks of this type are restored with the drives.
+    /// Task types are shared by all managers in the process.
+    pub fn register_task_handler(&self, handler: Arc<dyn TaskHandler>) -> Result<()> {
+        tasks::register_task_handler(handler)
+    }
+
     /// Get the .cloudreve config directory path
     fn get_config_dir() -> Result<PathBuf> {
         let home_dir = dirs::home_dir().context("Failed to get user home directory")?;
@@ -73,7 +172,7 @@ impl DriveManager {
         if !config_file.exists() {
             tracing::info!(target: "drive", "No existing drive config found, starting fresh");
             self.event_broadcaster.no_drive();
-            return Ok(());
+            return self.finish_loading(false).await;
         }
 
         tracing::debug!(target: "drive", path = %config_file.display(), "Loading drive configurations");
@@ -81,15 +180,23 @@ impl DriveManager {
         let content =
             fs::read_to_string(&config_file).context("Failed to read drive config file")?;
 
-        let state: DriveState =
+        let mut state: DriveState =
             serde_json::from_str(&content).context("Failed to parse drive config")?;
+        let migrated = self.assign_stable_ids(&mut state.drives)?;
+        let relocated = self.resolve_sync_paths(&mut state.drives);
 
         // Add drives to manager
         let mut count = 0;
         for config in state.drives.iter() {
-            self.add_drive(config.clone())
-                .await
-                .context(format!("Failed to add drive: {}", config.id))?;
+            if config.enabled {
+                self.start_drive(config.clone())
+                    .await
+                    .context(format!("Failed to add drive: {}", config.id))?;
+            } else {
+                self.register_drive(config.clone())
+                    .await
+                    .context(format!("Failed to register drive: {}", config.id))?;
+            }
             count += 1;
         }
 
@@ -99,31 +206,37 @@ impl DriveManager {
 
         tracing::info!(target: "drive", count = count, "Loaded drive(s) from config");
 
-        Ok(())
+        self.finish_loading(migrated || relocated).await
     }
 
-    /// Persist drive configurations to disk
-    pub async fn persist(&self) -> Result<()> {
-        let config_file = self.get_config_file();
-        let write_guard = self.drives.write().await;
-
-        tracing::debug!(target: "drive", path = %config_file.display(), count = write_guard.len(), "Persisting drive configurations");
-
-        let mut new_state = DriveState::default();
-
-        // Update drive states from underlying mounts
-        for (_, mount) in write_guard.iter() {
-            let config = mount.get_config().await;
-            new_state.drives.push(config);
+    /// Give drives saved without a UUID one, moving their inventory rows
+    /// along. Returns whether any drive changed.
+    fn assign_stable_ids(&self, drives: &mut [DriveConfig]) -> Result<bool> {
+        let mut uses: HashMap<String, usize> = HashMap::new();
+        for drive in drives.iter() {
+            *uses.entry(drive.id.clone()).or_default() += 1;
         }
 
-        let content =
-            serde_json::to_string_pretty(&new_state).context("Failed to serialize drive state")?;
-        fs::write(&config_file, content).context("Failed to write drive config file")?;
-
-        tracing::info!(target: "drive", count = new_state.drives.len(), "Persisted drive(s) to config");
-
-        Ok(())
+        let mut migrated = false;
+        for drive in drives.iter_mut() {
+            if Uuid::parse_str(&drive.id).is_ok() {
+                continue;
+            }
+            let new_id = Uuid::new_v4().to_string();
+            // Rows shared by several drives can't be told apart, leave them
+            // to be rebuilt by the next sync
+            let moved = if uses[&drive.id] > 1 {
+                0
+            } else {
+                self.inventory
+                    .reassign_drive_id(&drive.id, &new_id)
+                    .with_context(|| format!("Failed to migrate drive: {}", drive.id))?
+            };
+            tracing::info!(target: "drive", old_id = %drive.id, id = %new_id, moved, "Assigned stable ID to drive");
+            drive.id = new_id;
+            migrated = true;
+        }
+        Ok(migrated)
     }
 
     /// Register a callback to be invoked when status UI changes
@@ -142,83 +255,84 @@ impl DriveManager {
         Ok(())
     }
 
-    /// Add a new drive
-    pub async fn add_drive(&self, mut config: DriveConfig) -> Result<String> {
-        // Fetch favicon if icon_path is not set or doesn't exist
-        if config.icon_path.is_none()
-            || !config
-                .icon_path
-                .as_ref()
-                .map(|p| std::path::Path::new(p).exists())
-                .unwrap_or(false)
-        {
-            match favicon::fetch_and_save_favicon(&config.instance_url).await {
-                Ok(result) => {
-                    tracing::info!(target: "drive", ico_path = %result.ico_path, raw_path = %result.raw_path, "Favicon fetched successfully");
-                    config.icon_path = Some(result.ico_path);
-                    config.raw_icon_path = Some(result.raw_path);
-                }
-                Err(e) => {
-                    tracing::warn!(target: "drive", error = %e, "Failed to fetch favicon, continuing without icon");
-                }
-            }
+    /// Add a new drive and mount it right away. Fails with [`AddDriveError`]
+    /// if its local folder is already synced by another drive, or if its
+    /// server runs a version that can't be synced.
+    pub async fn add_drive(&self, config: DriveConfig) -> Result<String> {
+        let capabilities = self.probe_new_drive(&config).await?;
+        let id = self.start_drive(config).await?;
+        if let (Some(capabilities), Some(mount)) = (capabilities, self.get_drive(&id).await) {
+            mount.cr_client.set_capabilities(Some(capabilities)).await;
         }
+        Ok(id)
+    }
 
-        let mut write_guard = self.drives.write().await;
-        let mut mount = Mount::new(
-            config.clone(),
-            self.inventory.clone(),
-            self.command_tx.clone(),
-        )
-        .await;
-        if let Err(e) = mount.start().await {
-            tracing::error!(target: "drive", error = ?e, "Failed to start drive");
-            return Err(e).context("Failed to start drive");
+    /// Register a drive and mount it, forgetting it if it can't start
+    async fn start_drive(&self, config: DriveConfig) -> Result<String> {
+        let signed_in = config.guest_share.is_none() && config.kind == DriveKind::Cloudreve;
+        let id = self.register_drive(config).await?;
+        if let Err(e) = self.mount_drive(&id).await {
+            // A drive that can't start is not kept
+            self.unmounted.write().await.remove(&id);
+            return Err(e);
+        }
+        if signed_in {
+            self.record_sign_in(&id, CredentialEventKind::Login);
         }
-
-        let mount_arc = Arc::new(mount);
-        mount_arc.spawn_command_processor(mount_arc.clone()).await;
-        mount_arc
-            .spawn_remote_event_processor(mount_arc.clone())
-            .await;
-        mount_arc.spawn_props_refresh_task().await;
-        let id = mount_arc.id.clone();
-        write_guard.insert(id.clone(), mount_arc);
         Ok(id)
     }
 
+    /// Probe the server of a drive being added for the features it supports.
+    /// Fails for V3 servers, an unreachable server is left for the mount to
+    /// report.
+    async fn probe_new_drive(&self, config: &DriveConfig) -> Result<Option<Capabilities>> {
+        if config.kind != DriveKind::Cloudreve {
+            return Ok(None);
+        }
+        let client_config = ClientConfig::new(config.instance_url.clone())
+            .with_device_user_agent(crate::USER_AGENT, &crate::device_name())
+            .with_ip_strategy(config.network.ip_strategy)
+            .with_tls_pins(config.network.tls_pins.clone());
+        match detect_api_version(&client_config).await {
+            Ok((ApiVersion::V4, capabilities)) => {
+                if capabilities.is_below_tested() {
+                    tracing::warn!(target: "drive::manager", version = %capabilities.version, tested = %MIN_TESTED_VERSION, unavailable = ?capabilities.unavailable_features(), "Server is older than tested versions");
+                }
+                Ok(Some(capabilities))
+            }
+            Ok((ApiVersion::V3, capabilities)) => anyhow::bail!(
+                "Server runs Cloudreve {}, only V4 servers can be synced",
+                capabilities.version
+            ),
+            Err(e) => {
+                tracing::warn!(target: "drive::manager", instance_url = %config.instance_url, error = %e, "Failed to probe server capabilities");
+                Ok(None)
+            }
+        }
+    }
+
     // Search drive by child file path.
     // Child path can be up to the sync root path.
+    // Compared in memory, without touching the disk.
     pub async fn search_drive_by_child_path(&self, path: &str) -> Option<Arc<Mount>> {
-        let read_guard = self.drives.read().await;
-
-        // Convert the input path to an absolute PathBuf for comparison
-        let target_path = PathBuf::from(path);
-        let target_path = match target_path.canonicalize() {
-            Ok(p) => p,
-            Err(_) => {
-                // If canonicalize fails (e.g., path doesn't exist), try to work with the original path
-                target_path
-            }
-        };
-
-        // Iterate through all drives and check if the target path is under their sync root
-        for (_, mount) in read_guard.iter() {
-            let sync_path = mount.get_sync_path().await;
-
-            // Normalize the sync path
-            let sync_path = match sync_path.canonicalize() {
-                Ok(p) => p,
-                Err(_) => sync_path,
-            };
+        self.search_drive_by_path_key(&path_key(Path::new(path))).await
+    }
 
-            // Check if target_path starts with sync_path (is a child of sync_path)
-            if target_path.starts_with(&sync_path) {
-                return Some(mount.clone());
-            }
-        }
+    /// Like [`Self::search_drive_by_child_path`], but resolves junctions and
+    /// links in `path` first, which hits the disk. For paths that may reach a
+    /// sync root through a link of their own.
+    pub async fn search_drive_by_child_path_strict(&self, path: &str) -> Option<Arc<Mount>> {
+        let path = PathBuf::from(path);
+        let resolved = path.canonicalize().unwrap_or(path);
+        self.search_drive_by_path_key(&path_key(&resolved)).await
+    }
 
-        None
+    async fn search_drive_by_path_key(&self, key: &str) -> Option<Arc<Mount>> {
+        let read_guard = self.drives.read().await;
+        read_guard
+            .values()
+            .find(|mount| mount.contains_path_key(key))
+            .cloned()
     }
 
     /// Remove a drive by ID
@@ -227,14 +341,21 @@ impl DriveManager {
     /// 1. Stop and delete the mount (unregister sync root, cleanup inventory)
     /// 2. Remove the drive from the manager's drive map
     ///
-    /// Note: The caller is responsible for calling `persist()` after this to save the config.
+    /// The config is saved shortly after; call `persist()` to save it right away.
     pub async fn remove_drive(&self, id: &str) -> Result<Option<DriveConfig>> {
         let mut write_guard = self.drives.write().await;
 
         // Remove the mount from the map
         let mount = match write_guard.remove(id) {
             Some(m) => m,
-            None => return Ok(None),
+            None => {
+                drop(write_guard);
+                let config = self.remove_unmounted_drive(id).await?;
+                if config.is_some() {
+                    self.after_drive_removed(id).await;
+                }
+                return Ok(config);
+            }
         };
 
         // Get the config before deleting the mount
@@ -246,14 +367,20 @@ impl DriveManager {
         // Delete the mount (unregister sync root, cleanup, etc.)
         mount.delete().await.context("Failed to delete mount")?;
 
+        self.after_drive_removed(id).await;
+
+        Ok(Some(config))
+    }
+
+    async fn after_drive_removed(&self, id: &str) {
         // Broadcast no_drive event if no drives remain
-        if self.drives.read().await.is_empty() {
+        if self.drives.read().await.is_empty() && self.unmounted.read().await.is_empty() {
             self.event_broadcaster.no_drive();
         }
 
         tracing::info!(target: "drive::manager", drive_id = %id, "Drive removed successfully");
-
-        Ok(Some(config))
+        self.schedule_persist();
+        self.purge_drive_data(id).await;
     }
 
     /// Get a drive by ID
@@ -272,18 +399,6 @@ impl DriveManager {
         Vec::new()
     }
 
-    /// Update drive configuration
-    pub async fn update_drive(&self, _id: &str, _config: DriveConfig) -> Result<()> {
-        // let mut write_guard = self.drives.write().await;
-        // if write_guard.contains_key(id) {
-        //     // write_guard.insert(id.to_string(), Mount::new(config.clone()));
-        //     Ok(())
-        // } else {
-        //     anyhow::bail!("Drive not found: {}", id)
-        // }
-        Err(anyhow::anyhow!("Not implemented"))
-    }
-
     /// Update drive credentials for reauthorization.
     ///
     /// This updates the name, instance_url, and credentials for an existing drive.
@@ -308,6 +423,19 @@ impl DriveManager {
         credentials: Credentials,
         user_id: &str,
     ) -> Result<()> {
+        if self
+            .update_unmounted_credentials(
+                id,
+                name.clone(),
+                instance_url.clone(),
+                credentials.clone(),
+                user_id,
+            )
+            .await?
+        {
+            return Ok(());
+        }
+
         let read_guard = self.drives.read().await;
         let mount = read_guard
             .get(id)
@@ -376,8 +504,10 @@ impl DriveManager {
 
         // Clear the credential expired flag since we got new credentials
         mount.set_credential_expired(false).await;
+        self.record_sign_in(id, CredentialEventKind::CredentialsUpdated);
 
         tracing::info!(target: "drive::manager", drive_id = %id, "Drive credentials updated successfully");
+        self.schedule_persist();
 
         Ok(())
     }
@@ -458,10 +588,19 @@ impl DriveManager {
             })
             .collect();
 
+        let quarantined = drives
+            .iter()
+            .map(|drive| (drive.id.clone(), self.quarantined_count(&drive.id)))
+            .filter(|(_, count)| *count > 0)
+            .collect();
+
         Ok(StatusSummary {
             drives,
             active_tasks,
             finished_tasks: recent_tasks.finished,
+            integrity: IntegrityStats::snapshot(),
+            compression: CompressionStats::snapshot(),
+            quarantined,
         })
     }
 
@@ -530,15 +669,34 @@ impl DriveManager {
 
         // Determine sync status based on active tasks
         let active_task_count = self.get_active_task_count(drive_id);
+        // Transfers run side by side, the last one to finish sets the ETA
+        let eta_seconds = mount
+            .task_queue
+            .ongoing_progress()
+            .await
+            .iter()
+            .filter_map(|progress| progress.eta_seconds)
+            .max();
+
+        let quarantined_count = self.quarantined_count(drive_id);
+        let conflict_count = self.conflict_count(drive_id);
 
         let sync_status = if active_task_count > 0 {
             SyncStatus::Syncing
+        } else if quarantined_count > 0 {
+            SyncStatus::Error
+        } else if crate::power::is_power_saving()
+            || crate::presentation::pauses_transfers()
+            || mount.is_server_maintenance().await
+        {
+            SyncStatus::Paused
         } else {
             SyncStatus::InSync
         };
 
         Ok(Some(DriveStatusUI {
             name: config.name.clone(),
+            label: config.label.clone(),
             raw_icon_path: config.raw_icon_path.clone(),
             capacity,
             profile_url,
@@ -546,6 +704,9 @@ impl DriveManager {
             storage_url,
             sync_status,
             active_task_count,
+            eta_seconds,
+            quarantined_count,
+            conflict_count,
         }))
     }
 
@@ -563,7 +724,20 @@ impl DriveManager {
             let drive_state = mount.get_status_flags().await;
 
             // Determine drive status
-            let status = if drive_state.is_credential_expired() {
+            // Guests of a share never get event push
+            let status = if drive_state.is_sync_root_missing() {
+                DriveInfoStatus::SyncRootMissing
+            } else if drive_state.is_suspicious_changes() {
+                DriveInfoStatus::SuspiciousChanges
+            } else if drive_state.is_server_maintenance() {
+                DriveInfoStatus::ServerMaintenance
+            } else if config.guest_share.is_some() {
+                if drive_state.is_credential_expired() {
+                    DriveInfoStatus::ShareUnavailable
+                } else {
+                    DriveInfoStatus::Active
+                }
+            } else if drive_state.is_credential_expired() {
                 DriveInfoStatus::CredentialExpired
             } else {
                 if !drive_state.is_event_push_subscribed(){
@@ -573,18 +747,72 @@ impl DriveManager {
                 }
             };
 
+            // Only looked for once the folder is gone, it hits every disk
+            let relocation = if drive_state.is_sync_root_missing() {
+                find_on_other_disk(&config.sync_path).map(|path| path.to_string_lossy().to_string())
+            } else {
+                None
+            };
+
             drives_info.push(DriveInfo {
                 id: config.id.clone(),
                 name: config.name.clone(),
                 instance_url: config.instance_url.clone(),
                 sync_path: config.sync_path.to_string_lossy().to_string(),
+                relocation,
                 icon_path: config.icon_path.clone(),
                 remote_path: config.remote_path.clone(),
                 raw_icon_path: config.raw_icon_path.clone(),
                 enabled: config.enabled,
                 user_id: config.user_id.clone(),
+                label: config.label.clone(),
+                guest_share: config.guest_share.is_some(),
                 status,
                 capacity,
+                server: mount.cr_client.capabilities().await.map(|capabilities| ServerInfo {
+                    version: capabilities.version.to_string(),
+                    min_tested_version: MIN_TESTED_VERSION.to_string(),
+                    outdated: capabilities.is_below_tested(),
+                    unavailable_features: capabilities.unavailable_features(),
+                }),
+                state: mount.state().await,
+                address_family: mount.cr_client.address_family().await,
+                clock_skew_secs: mount
+                    .cr_client
+                    .clock_skew()
+                    .await
+                    .map(|skew| skew.num_seconds()),
+                lan_active: mount.cr_client.lan_base_url().await.is_some(),
+                bind_address: mount.cr_client.config().local_address,
+                type_filtered_count: mount.type_filtered_count(),
+            });
+        }
+        drop(read_guard);
+
+        for config in self.unmounted.read().await.values() {
+            drives_info.push(DriveInfo {
+                id: config.id.clone(),
+                name: config.name.clone(),
+                instance_url: config.instance_url.clone(),
+                sync_path: config.sync_path.to_string_lossy().to_string(),
+                relocation: None,
+                icon_path: config.icon_path.clone(),
+                remote_path: config.remote_path.clone(),
+                raw_icon_path: config.raw_icon_path.clone(),
+                enabled: config.enabled,
+                user_id: config.user_id.clone(),
+                label: config.label.clone(),
+                guest_share: config.guest_share.is_some(),
+                status: DriveInfoStatus::Unmounted,
+                capacity: None,
+                server: None,
+                // Never mounted, or shut down when it was unmounted
+                state: config.last_state.unwrap_or(MountState::ShuttingDown),
+                address_family: None,
+                clock_skew_secs: None,
+                lan_active: false,
+                bind_address: None,
+                type_filtered_count: 0,
             });
         }
 
@@ -592,10 +820,22 @@ impl DriveManager {
     }
 
     /// Get a command sender for external code to send commands to the manager
-    pub fn get_command_sender(&self) -> mpsc::UnboundedSender<ManagerCommand> {
+    pub fn get_command_sender(&self) -> CommandSender<ManagerCommand> {
         self.command_tx.clone()
     }
 
+    /// Queue depths of the manager and every mount
+    pub async fn command_queue_stats(&self) -> CommandQueueReport {
+        let drives = self.drives.read().await;
+        CommandQueueReport {
+            manager: self.command_tx.stats(),
+            drives: drives
+                .iter()
+                .map(|(id, mount)| (id.clone(), mount.command_tx.stats()))
+                .collect(),
+        }
+    }
+
     pub async fn shutdown(&self) {
         tracing::info!(target: "drive::manager", "Shutting down DriveManager");
 
@@ -612,6 +852,12 @@ impl DriveManager {
         for (_, mount) in write_guard.iter() {
             mount.shutdown().await;
         }
+        drop(write_guard);
+
+        // Mounts record their journal checkpoints on shutdown
+        if let Err(e) = self.persist().await {
+            tracing::error!(target: "drive::manager", error = %e, "Failed to persist config on shutdown");
+        }
         tracing::info!(target: "drive", "All drives shutdown");
     }
 }
@@ -640,12 +886,13 @@ impl DriveManager {
                 CapacitySummary {
                     total: cap.total,
                     used: cap.used,
-                    label: format!(
-                        "{} / {} ({:.1}%)",
-                        format_bytes(cap.used),
-                        format_bytes(cap.total),
-                        percentage
-                    ),
+                    label: t!(
+                        "capacityLabel",
+                        "used" => format_bytes(cap.used),
+                        "total" => format_bytes(cap.total),
+                        "percentage" => format_percentage(percentage)
+                    )
+                    .to_string(),
                 }
             }),
             Ok(None) => None,
@@ -667,6 +914,95 @@ impl DriveManager {
         }
     }
 
+    /// Get the number of files of a drive quarantined after failing to sync
+    fn quarantined_count(&self, drive_id: &str) -> usize {
+        match self.inventory.count_quarantined_failures(drive_id) {
+            Ok(count) => count as usize,
+            Err(e) => {
+                tracing::warn!(target: "drive::manager", drive_id = %drive_id, error = %e, "Failed to count quarantined files");
+                0
+            }
+        }
+    }
+
+    fn conflict_count(&self, drive_id: &str) -> usize {
+        match self.inventory.list_conflicts(drive_id) {
+            Ok(conflicts) => conflicts.len(),
+            Err(e) => {
+                tracing::warn!(target: "drive::manager", drive_id = %drive_id, error = %e, "Failed to count conflicts");
+                0
+            }
+        }
+    }
+
+    /// Set the color/emoji label for a specific drive
+    pub async fn set_drive_label(&self, drive_id: &str, label: Option<DriveLabel>) -> Result<()> {
+        let read_guard = self.drives.read().await;
+        let mount = read_guard
+            .get(drive_id)
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", drive_id))?;
+        mount.set_label(label).await?;
+        let label = mount.config.read().await.label.clone();
+        self.event_broadcaster
+            .drive_label_changed(drive_id.to_string(), label);
+        self.schedule_persist();
+        Ok(())
+    }
+
+    /// Scan a drive's sync root for broken placeholders and repair them
+    pub async fn repair_placeholders(&self, drive_id: &str) -> Result<RepairReport> {
+        let mount = self
+            .get_drive(drive_id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", drive_id))?;
+        mount.repair_placeholders().await
+    }
+
+    /// Look for files with identical content in a drive
+    pub async fn find_duplicate_files(&self, drive_id: &str) -> Result<DuplicateReport> {
+        let read_guard = self.drives.read().await;
+        let mount = read_guard
+            .get(drive_id)
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", drive_id))?;
+        mount.find_duplicate_files().await
+    }
+
+    /// Keep one of a drive's duplicate files and delete or replace the others
+    pub async fn resolve_duplicates(
+        &self,
+        drive_id: &str,
+        keep: PathBuf,
+        extras: Vec<PathBuf>,
+        action: DuplicateAction,
+    ) -> Result<DuplicateResolution> {
+        let read_guard = self.drives.read().await;
+        let mount = read_guard
+            .get(drive_id)
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", drive_id))?;
+        mount.resolve_duplicates(keep, extras, action).await
+    }
+
+    /// Get the total size and file count of a remote folder in a drive.
+    ///
+    /// Walking a large remote tree takes a while, so it runs on a clone of
+    /// the mount without holding the drives lock.
+    pub async fn get_folder_summary(&self, drive_id: &str, path: PathBuf) -> Result<FolderSummary> {
+        let mount = self
+            .get_drive(drive_id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", drive_id))?;
+        mount.get_folder_summary(path).await
+    }
+
+    /// Timeline of a file in any drive, oldest first
+    pub async fn get_file_history(&self, path: PathBuf) -> Result<Vec<FileHistoryEntry>> {
+        let mount = self
+            .search_drive_by_child_path(&path.to_string_lossy())
+            .await
+            .ok_or_else(|| anyhow::anyhow!("No drive found for path: {}", path.display()))?;
+        mount.get_file_history(path).await
+    }
+
     /// Get the sync direction for a specific drive
     pub async fn get_sync_direction(&self, drive_id: &str) -> Result<SyncDirection> {
         let read_guard = self.drives.read().await;
@@ -683,6 +1019,97 @@ impl DriveManager {
             .get(drive_id)
             .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", drive_id))?;
         mount.set_sync_direction(direction).await;
+        self.schedule_persist();
+        Ok(())
+    }
+
+    /// Get the connection settings for a specific drive
+    pub async fn get_network_settings(&self, drive_id: &str) -> Result<NetworkSettings> {
+        let read_guard = self.drives.read().await;
+        let mount = read_guard
+            .get(drive_id)
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", drive_id))?;
+        Ok(mount.get_network_settings().await)
+    }
+
+    /// Set the connection settings for a specific drive
+    pub async fn set_network_settings(
+        &self,
+        drive_id: &str,
+        settings: NetworkSettings,
+    ) -> Result<()> {
+        let read_guard = self.drives.read().await;
+        let mount = read_guard
+            .get(drive_id)
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", drive_id))?;
+        mount.set_network_settings(settings).await;
+        // The LAN route can switch right away; the rest applies on restart
+        mount.refresh_lan_route().await;
+        self.schedule_persist();
+        Ok(())
+    }
+
+    /// Get the compressed upload settings for a specific drive
+    pub async fn get_compression_settings(&self, drive_id: &str) -> Result<CompressionSettings> {
+        let read_guard = self.drives.read().await;
+        let mount = read_guard
+            .get(drive_id)
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", drive_id))?;
+        Ok(mount.get_compression_settings().await)
+    }
+
+    /// Set the compressed upload settings for a specific drive
+    pub async fn set_compression_settings(
+        &self,
+        drive_id: &str,
+        settings: CompressionSettings,
+    ) -> Result<()> {
+        let read_guard = self.drives.read().await;
+        let mount = read_guard
+            .get(drive_id)
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", drive_id))?;
+        mount.set_compression_settings(settings).await;
+        self.schedule_persist();
+        Ok(())
+    }
+
+    /// Get the transfer concurrency limits for a specific drive
+    pub async fn get_transfer_limits(&self, drive_id: &str) -> Result<TransferLimits> {
+        let read_guard = self.drives.read().await;
+        let mount = read_guard
+            .get(drive_id)
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", drive_id))?;
+        Ok(mount.get_transfer_limits().await)
+    }
+
+    /// Set the transfer concurrency limits for a specific drive
+    pub async fn set_transfer_limits(&self, drive_id: &str, limits: TransferLimits) -> Result<()> {
+        let read_guard = self.drives.read().await;
+        let mount = read_guard
+            .get(drive_id)
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", drive_id))?;
+        mount.set_transfer_limits(limits).await;
+        self.schedule_persist();
+        Ok(())
+    }
+
+    /// Get the type filter of a specific drive
+    pub async fn get_type_filter(&self, drive_id: &str) -> Result<TypeFilter> {
+        let read_guard = self.drives.read().await;
+        let mount = read_guard
+            .get(drive_id)
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", drive_id))?;
+        Ok(mount.get_type_filter().await)
+    }
+
+    /// Set the type filter of a specific drive. Fails on invalid patterns.
+    pub async fn set_type_filter(&self, drive_id: &str, filter: TypeFilter) -> Result<()> {
+        let read_guard = self.drives.read().await;
+        let mount = read_guard
+            .get(drive_id)
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", drive_id))?;
+        mount.set_type_filter(filter).await?;
+        self.schedule_persist();
         Ok(())
     }
 }
@@ -0,0 +1,198 @@
+//! Registering drives separately from mounting them
+//!
+//! A registered drive has its config validated and saved in `drives.json`,
+//! but no sync root connected, no watchers and no tasks. It is kept with
+//! `enabled` unset, so it stays unmounted across restarts until
+//! [`DriveManager::mount_drive`] is called. This lets drives be provisioned
+//! ahead of the first sign in and lets the user pause a drive without
+//! removing it.
+
+use super::DriveManager;
+use super::duplicates::{find_conflict, find_unmounted_conflict};
+use super::favicon;
+use crate::drive::lifecycle::MountState;
+use crate::drive::mounts::{Credentials, DriveConfig, DriveKind, Mount};
+use crate::inventory::CredentialEventKind;
+use crate::policy::Policy;
+use anyhow::{Context, Result};
+use std::sync::Arc;
+use uuid::Uuid;
+
+impl DriveManager {
+    /// Validate and save a drive without mounting it. Fails with
+    /// [`AddDriveError`](super::AddDriveError) if its local folder is already
+    /// synced by another drive.
+    pub async fn register_drive(&self, mut config: DriveConfig) -> Result<String> {
+        Policy::get().check_server_allowed(&config.instance_url)?;
+        if Uuid::parse_str(&config.id).is_err() {
+            config.id = Uuid::new_v4().to_string();
+        }
+
+        // Fetch favicon if icon_path is not set or doesn't exist
+        if config.kind != DriveKind::Simulated
+            && !config
+                .icon_path
+                .as_ref()
+                .is_some_and(|path| std::path::Path::new(path).exists())
+        {
+            match favicon::fetch_and_save_favicon(&config.instance_url).await {
+                Ok(result) => {
+                    tracing::info!(target: "drive", ico_path = %result.ico_path, raw_path = %result.raw_path, "Favicon fetched successfully");
+                    config.icon_path = Some(result.ico_path);
+                    config.raw_icon_path = Some(result.raw_path);
+                }
+                Err(e) => {
+                    tracing::warn!(target: "drive", error = %e, "Failed to fetch favicon, continuing without icon");
+                }
+            }
+        }
+
+        let drives = self.drives.read().await;
+        let mut unmounted = self.unmounted.write().await;
+        let conflict = match find_conflict(
+            &drives,
+            &config.instance_url,
+            &config.remote_path,
+            &config.sync_path,
+        )
+        .await
+        {
+            Some(error) => Some(error),
+            None => find_unmounted_conflict(
+                &unmounted,
+                &config.instance_url,
+                &config.remote_path,
+                &config.sync_path,
+            ),
+        };
+        if let Some(error) = conflict {
+            tracing::warn!(target: "drive::manager", error = %error, "Refusing to add duplicate drive");
+            return Err(error.into());
+        }
+
+        config.enabled = false;
+        let id = config.id.clone();
+        unmounted.insert(id.clone(), config);
+        tracing::info!(target: "drive::manager", drive_id = %id, "Drive registered");
+        self.schedule_persist();
+        Ok(id)
+    }
+
+    /// Connect the sync root of a registered drive and start syncing it. Does
+    /// nothing if the drive is mounted already.
+    pub async fn mount_drive(&self, id: &str) -> Result<()> {
+        let mut write_guard = self.drives.write().await;
+        if write_guard.contains_key(id) {
+            return Ok(());
+        }
+        let mut config = self
+            .unmounted
+            .read()
+            .await
+            .get(id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", id))?;
+        config.enabled = true;
+
+        let mut mount = Mount::new(config, self.inventory.clone(), self.command_tx.clone()).await;
+        if let Err(e) = mount.start().await {
+            tracing::error!(target: "drive", error = ?e, "Failed to start drive");
+            return Err(e).context("Failed to start drive");
+        }
+        let state = if mount.is_sync_root_missing().await {
+            MountState::Degraded
+        } else {
+            MountState::Idle
+        };
+        mount.transition(state).await;
+
+        let mount_arc = Arc::new(mount);
+        mount_arc.spawn_command_processor(mount_arc.clone()).await;
+        if mount_arc.is_simulated().await {
+            mount_arc.spawn_simulator().await;
+        } else {
+            mount_arc
+                .spawn_remote_event_processor(mount_arc.clone())
+                .await;
+            mount_arc.spawn_props_refresh_task().await;
+            mount_arc.spawn_branding_refresh_task().await;
+            mount_arc.spawn_intake_resume();
+        }
+        self.unmounted.write().await.remove(id);
+        write_guard.insert(id.to_string(), mount_arc);
+        tracing::info!(target: "drive::manager", drive_id = %id, "Drive mounted");
+        self.schedule_persist();
+        Ok(())
+    }
+
+    /// Stop syncing a drive and disconnect its sync root. The config, the sync
+    /// root registration and the inventory are kept, so mounting it again
+    /// picks up where it left off. Does nothing if the drive is not mounted.
+    pub async fn unmount_drive(&self, id: &str) -> Result<()> {
+        // The drive is in neither map while it shuts down
+        let _transition = self.transitions.begin(id);
+        let Some(mount) = self.drives.write().await.remove(id) else {
+            if self.unmounted.read().await.contains_key(id) {
+                return Ok(());
+            }
+            anyhow::bail!("Drive not found: {}", id);
+        };
+
+        // Keep the drive registered even if disconnecting fails, the mount is
+        // shut down either way
+        let result = mount.unmount().await;
+        let mut config = mount.get_config().await;
+        config.enabled = false;
+        self.unmounted.write().await.insert(id.to_string(), config);
+        tracing::info!(target: "drive::manager", drive_id = %id, "Drive unmounted");
+        self.schedule_persist();
+        result.context("Failed to unmount drive")
+    }
+
+    /// Remove a drive that is registered but not mounted, unregistering the
+    /// sync root left from an earlier mount
+    pub(super) async fn remove_unmounted_drive(&self, id: &str) -> Result<Option<DriveConfig>> {
+        let Some(config) = self.unmounted.write().await.remove(id) else {
+            return Ok(None);
+        };
+        if let Some(sync_root_id) = config.sync_root_id.as_ref() {
+            if let Err(e) = sync_root_id.unregister() {
+                tracing::warn!(target: "drive::manager", drive_id = %id, error = %e, "Failed to unregister sync root");
+            }
+        }
+        if let Err(e) = self.inventory.nuke_drive(id) {
+            tracing::error!(target: "drive::manager", drive_id = %id, error = %e, "Failed to nuke drive");
+        }
+        Ok(Some(config))
+    }
+
+    /// Update the credentials of a drive that is registered but not mounted.
+    /// A drive registered before its first sign in has no user yet and takes
+    /// the one signing in. Returns `false` if the drive is not registered.
+    pub(super) async fn update_unmounted_credentials(
+        &self,
+        id: &str,
+        name: String,
+        instance_url: String,
+        credentials: Credentials,
+        user_id: &str,
+    ) -> Result<bool> {
+        let mut unmounted = self.unmounted.write().await;
+        let Some(config) = unmounted.get_mut(id) else {
+            return Ok(false);
+        };
+        if !config.user_id.is_empty() && config.user_id != user_id {
+            return Err(anyhow::anyhow!(t!("userIdMismatch")));
+        }
+        config.name = name;
+        config.instance_url = instance_url;
+        config.credentials = credentials;
+        config.user_id = user_id.to_string();
+        drop(unmounted);
+        self.record_sign_in(id, CredentialEventKind::CredentialsUpdated);
+
+        tracing::info!(target: "drive::manager", drive_id = %id, "Credentials of unmounted drive updated");
+        self.schedule_persist();
+        Ok(true)
+    }
+}
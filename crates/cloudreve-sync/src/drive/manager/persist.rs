@@ -0,0 +1,112 @@
+//! Saving drive configurations to `drives.json`
+//!
+//! Changes such as refreshed credentials or a new sync root ID ask for a save
+//! through [ManagerCommand::PersistConfig]. Requests are debounced, so a burst
+//! of changes is written once, and writes never overlap. Nothing is written
+//! before all drives are loaded, which would drop the drives not loaded yet.
+//! Sync folders are saved relative to known folders where possible, see
+//! [`known_folders`](crate::drive::known_folders).
+
+use super::{DriveManager, DriveState};
+use crate::drive::commands::ManagerCommand;
+use crate::drive::known_folders::KnownFolders;
+use crate::utils::file::write_atomic;
+use anyhow::{Context, Result};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// How long to wait for more changes before saving
+const PERSIST_DEBOUNCE: Duration = Duration::from_secs(2);
+
+#[derive(Default)]
+pub(super) struct PersistState {
+    /// Held while writing the file
+    write_lock: Mutex<()>,
+    /// A save was requested and has not happened yet
+    scheduled: AtomicBool,
+    /// Drives are loaded, saving is safe
+    ready: AtomicBool,
+}
+
+impl DriveManager {
+    /// Persist drive configurations to disk
+    pub async fn persist(&self) -> Result<()> {
+        let config_file = self.get_config_file();
+        let _write = self.persist_state.write_lock.lock().await;
+
+        let mut new_state = DriveState::default();
+        {
+            let read_guard = self.drives.read().await;
+            tracing::debug!(target: "drive", path = %config_file.display(), count = read_guard.len(), "Persisting drive configurations");
+
+            // Update drive states from underlying mounts
+            for (_, mount) in read_guard.iter() {
+                let config = mount.get_config().await;
+                new_state.drives.push(config);
+            }
+            new_state
+                .drives
+                .extend(self.unmounted.read().await.values().cloned());
+        }
+        let known_folders = KnownFolders::current();
+        for config in new_state.drives.iter_mut() {
+            config.portable_sync_path = known_folders.portable(&config.sync_path);
+        }
+
+        let content =
+            serde_json::to_string_pretty(&new_state).context("Failed to serialize drive state")?;
+        write_atomic(&config_file, content).context("Failed to write drive config file")?;
+
+        tracing::info!(target: "drive", count = new_state.drives.len(), "Persisted drive(s) to config");
+
+        Ok(())
+    }
+
+    /// Ask for the drive configurations to be saved soon
+    pub(super) fn schedule_persist(&self) {
+        if let Err(e) = self.command_tx.send(ManagerCommand::PersistConfig) {
+            tracing::error!(target: "drive::manager", error = %e, "Failed to send PersistConfig command");
+        }
+    }
+
+    /// Save after [PERSIST_DEBOUNCE], unless a save is already pending
+    pub(super) fn debounce_persist(self: &Arc<Self>) {
+        if self.persist_state.scheduled.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        // Loading saves once it is done
+        if !self.persist_state.ready.load(Ordering::SeqCst) {
+            return;
+        }
+
+        let manager = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(PERSIST_DEBOUNCE).await;
+            manager
+                .persist_state
+                .scheduled
+                .store(false, Ordering::SeqCst);
+            if let Err(e) = manager.persist().await {
+                tracing::error!(target: "drive::manager", error = %e, "Failed to persist config");
+            }
+        });
+    }
+
+    /// Whether all saved drives are loaded
+    pub(super) fn is_loaded(&self) -> bool {
+        self.persist_state.ready.load(Ordering::SeqCst)
+    }
+
+    /// Allow saving now that drives are loaded, and save if anything changed
+    /// while loading
+    pub(super) async fn finish_loading(&self, changed: bool) -> Result<()> {
+        self.persist_state.ready.store(true, Ordering::SeqCst);
+        let requested = self.persist_state.scheduled.swap(false, Ordering::SeqCst);
+        if changed || requested {
+            self.persist().await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,16 @@
+use super::DriveManager;
+use anyhow::{Context, Result};
+use cloudreve_api::pinning::fetch_server_pin;
+use cloudreve_api::{ClientConfig, PinKind, TlsPin};
+
+impl DriveManager {
+    /// Pin of the certificate `instance_url` presents now, for a new drive to
+    /// trust it from its first connection on
+    pub async fn fetch_server_pin(&self, instance_url: &str, kind: PinKind) -> Result<TlsPin> {
+        let config = ClientConfig::new(instance_url.to_string())
+            .with_device_user_agent(crate::USER_AGENT, &crate::device_name());
+        fetch_server_pin(&config, kind)
+            .await
+            .context("Failed to fetch server certificate")
+    }
+}
@@ -0,0 +1,54 @@
+use super::DriveManager;
+use crate::config::ConfigManager;
+use crate::power::{self, PowerStatus};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often the power source and battery charge are checked
+const POWER_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+impl DriveManager {
+    /// Spawn a background task following the power source and battery charge,
+    /// so that sync is throttled on a low battery and resumes on AC power
+    pub fn spawn_power_watcher(self: &Arc<Self>) {
+        let manager = self.clone();
+        let mut config_changes = ConfigManager::try_get().map(|config| config.subscribe());
+        tokio::spawn(async move {
+            loop {
+                manager.update_power_saving().await;
+
+                // Re-evaluate right away when the threshold is changed
+                let config_changed = async {
+                    match config_changes.as_mut() {
+                        Some(receiver) => {
+                            let _ = receiver.recv().await;
+                        }
+                        None => std::future::pending().await,
+                    }
+                };
+                tokio::select! {
+                    _ = tokio::time::sleep(POWER_POLL_INTERVAL) => {}
+                    _ = config_changed => {}
+                }
+            }
+        });
+    }
+
+    async fn update_power_saving(&self) {
+        let settings = ConfigManager::try_get()
+            .map(|config| config.battery_saver())
+            .unwrap_or_default();
+        let status = PowerStatus::current();
+        let active = status.is_some_and(|status| status.should_save_power(&settings));
+        if !power::set_power_saving(active) {
+            return;
+        }
+
+        tracing::info!(target: "drive::manager", active, status = ?status, "Power saving changed");
+        let drives: Vec<_> = self.drives.read().await.values().cloned().collect();
+        for drive in drives {
+            drive.apply_throttling().await;
+        }
+        self.event_broadcaster.power_saving_changed(active);
+    }
+}
@@ -0,0 +1,40 @@
+use super::DriveManager;
+use crate::tasks::{PREFETCH_KIND, PrefetchState, TaskKind, TaskPayload};
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+impl DriveManager {
+    /// Prefetch the folder at `path` in the background, downloading files up
+    /// to `max_file_size`. Returns the ID of the task.
+    pub async fn prefetch_folder(
+        &self,
+        path: PathBuf,
+        max_file_size: Option<u64>,
+    ) -> Result<String> {
+        let mount = self
+            .search_drive_by_child_path(path.to_str().unwrap_or(""))
+            .await
+            .context("No drive found for path")?;
+        if !path.is_dir() {
+            anyhow::bail!("Only folders can be prefetched");
+        }
+
+        let state = PrefetchState::new(max_file_size);
+        let payload = TaskPayload::new(TaskKind::Custom(PREFETCH_KIND), path.clone())
+            .with_custom_state(serde_json::to_value(&state)?);
+        let task_id = mount.task_queue.enqueue(payload).await?;
+        tracing::info!(target: "drive::manager", drive_id = %mount.id, task_id = %task_id, path = %path.display(), max_file_size = ?max_file_size, "Queued folder prefetch");
+        Ok(task_id)
+    }
+
+    /// Cancel a pending or running task of any drive
+    pub async fn cancel_task(&self, task_id: &str) -> Result<()> {
+        let mounts: Vec<_> = self.drives.read().await.values().cloned().collect();
+        for mount in mounts {
+            if mount.task_queue.cancel(task_id).await? {
+                return Ok(());
+            }
+        }
+        anyhow::bail!("Task is not running")
+    }
+}
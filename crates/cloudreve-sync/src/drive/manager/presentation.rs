@@ -0,0 +1,56 @@
+use super::DriveManager;
+use crate::config::ConfigManager;
+use crate::presentation::{self, PresentationStatus};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often the screen is checked for a presentation. Shorter than the power
+/// poll, so notifications are held back soon after a presentation starts.
+const PRESENTATION_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+impl DriveManager {
+    /// Spawn a background task following presentations and screen sharing,
+    /// so that sync keeps quiet while presenting and resumes afterwards
+    pub fn spawn_presentation_watcher(self: &Arc<Self>) {
+        let manager = self.clone();
+        let mut config_changes = ConfigManager::try_get().map(|config| config.subscribe());
+        tokio::spawn(async move {
+            loop {
+                manager.update_presentation().await;
+
+                // Re-evaluate right away when presentation mode is toggled
+                let config_changed = async {
+                    match config_changes.as_mut() {
+                        Some(receiver) => {
+                            let _ = receiver.recv().await;
+                        }
+                        None => std::future::pending().await,
+                    }
+                };
+                tokio::select! {
+                    _ = tokio::time::sleep(PRESENTATION_POLL_INTERVAL) => {}
+                    _ = config_changed => {}
+                }
+            }
+        });
+    }
+
+    async fn update_presentation(&self) {
+        let settings = ConfigManager::try_get()
+            .map(|config| config.presentation_mode())
+            .unwrap_or_default();
+        // Not worth querying the display configuration while disabled
+        let status = settings.enabled.then(PresentationStatus::current);
+        let active = status.is_some_and(|status| status.is_presenting(&settings));
+        if !presentation::set_presenting(active) {
+            return;
+        }
+
+        tracing::info!(target: "drive::manager", active, status = ?status, "Presentation changed");
+        let drives: Vec<_> = self.drives.read().await.values().cloned().collect();
+        for drive in drives {
+            drive.apply_throttling().await;
+        }
+        self.event_broadcaster.presentation_mode_changed(active);
+    }
+}
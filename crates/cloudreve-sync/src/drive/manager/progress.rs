@@ -0,0 +1,46 @@
+use super::DriveManager;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::MissedTickBehavior;
+
+/// How often the progress of running tasks is broadcast
+const TASK_PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+impl DriveManager {
+    /// Spawn a background task broadcasting the progress of every running
+    /// task, at most once per [`TASK_PROGRESS_INTERVAL`] and only for tasks
+    /// that moved since
+    pub fn spawn_progress_reporter(self: &Arc<Self>) {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(TASK_PROGRESS_INTERVAL);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            // Bytes processed by each running task when last broadcast
+            let mut reported: HashMap<String, i64> = HashMap::new();
+            loop {
+                interval.tick().await;
+                let drives: Vec<_> = manager.drives.read().await.values().cloned().collect();
+                let mut running = HashMap::with_capacity(reported.len());
+                for drive in drives {
+                    for progress in drive.task_queue.ongoing_progress().await {
+                        let processed = progress.processed_bytes.unwrap_or(0);
+                        if reported.get(&progress.task_id) != Some(&processed) {
+                            manager.event_broadcaster.task_progress(
+                                progress.task_id.clone(),
+                                drive.id.clone(),
+                                progress.local_path,
+                                processed,
+                                progress.total_bytes,
+                                progress.speed_bytes_per_sec,
+                            );
+                        }
+                        running.insert(progress.task_id, processed);
+                    }
+                }
+                // Finished tasks are dropped
+                reported = running;
+            }
+        });
+    }
+}
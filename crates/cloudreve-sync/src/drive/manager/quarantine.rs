@@ -0,0 +1,28 @@
+use super::DriveManager;
+use crate::inventory::QuarantinedFile;
+use anyhow::{Context, Result};
+
+impl DriveManager {
+    /// List downloaded files of a drive that were quarantined by the scanner
+    pub async fn list_quarantined_files(&self, drive_id: &str) -> Result<Vec<QuarantinedFile>> {
+        let inventory = self.inventory.clone();
+        let drive_id = drive_id.to_string();
+        tokio::task::spawn_blocking(move || inventory.list_quarantined(&drive_id))
+            .await
+            .context("Quarantine listing panicked")?
+    }
+
+    /// Allow a quarantined file to be hydrated again. It is scanned again
+    /// once downloaded, unless scanning was turned off.
+    pub async fn release_quarantined_file(&self, path: &str) -> Result<bool> {
+        let inventory = self.inventory.clone();
+        let path = path.to_string();
+        let released = tokio::task::spawn_blocking(move || inventory.release_quarantine(&path))
+            .await
+            .context("Quarantine release panicked")??;
+        if released {
+            tracing::info!(target: "drive::manager", "Released file from quarantine");
+        }
+        Ok(released)
+    }
+}
@@ -0,0 +1,288 @@
+//! Changing the name and folders of a drive while the app runs
+//!
+//! Renaming a drive only updates its config and the sync root shown in
+//! Explorer. A new local or remote folder needs the drive stopped: a mounted
+//! drive is unmounted, changed and mounted again. The local folder is moved
+//! with its files, on the same disk only, and the inventory follows it. For a
+//! new remote folder the inventory is dropped and the local files are merged
+//! with the ones in the new folder, like a relinked sync folder.
+
+use super::DriveManager;
+use crate::drive::label::DESKTOP_INI;
+use crate::drive::mounts::DriveConfig;
+use crate::drive::utils::path_key;
+use anyhow::{Context, Result};
+use cloudreve_api::models::uri::CrUri;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Whether `path` holds no files, apart from the folder icon of a label
+fn is_folder_empty(path: &Path) -> Result<bool> {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(true),
+        Err(e) => return Err(e).with_context(|| format!("Failed to list {}", path.display())),
+    };
+    for entry in entries {
+        let entry = entry.with_context(|| format!("Failed to list {}", path.display()))?;
+        if !entry.file_name().eq_ignore_ascii_case(DESKTOP_INI) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Check that the sync folder can move from `old` to `new`
+fn check_new_sync_path(old: &Path, new: &Path) -> Result<()> {
+    if !new.is_absolute() {
+        anyhow::bail!("Sync folder must be an absolute path: {}", new.display());
+    }
+    let (old_key, new_key) = (path_key(old), path_key(new));
+    if new_key.starts_with(&format!("{}\\", old_key))
+        || old_key.starts_with(&format!("{}\\", new_key))
+    {
+        anyhow::bail!(
+            "Can't move {} into or out of itself: {}",
+            old.display(),
+            new.display()
+        );
+    }
+    if new.exists() && !(new.is_dir() && is_folder_empty(new)?) {
+        anyhow::bail!("{} must be an empty folder", new.display());
+    }
+    Ok(())
+}
+
+/// Move the sync folder `old` with its files to `new`, which is missing or
+/// empty. A missing `old` is created at `new` instead.
+fn move_sync_folder(old: &Path, new: &Path) -> Result<()> {
+    if new.exists() {
+        fs::remove_dir_all(new).with_context(|| format!("Failed to replace {}", new.display()))?;
+    }
+    if let Some(parent) = new.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    if !old.exists() {
+        return fs::create_dir_all(new)
+            .with_context(|| format!("Failed to create {}", new.display()));
+    }
+    fs::rename(old, new).with_context(|| {
+        format!(
+            "Failed to move {} to {}, sync folders can only be moved on the same disk",
+            old.display(),
+            new.display()
+        )
+    })
+}
+
+impl DriveManager {
+    /// Change the name, remote folder or local folder of a drive, taken from
+    /// `config`. Other fields have setters of their own and are ignored. A
+    /// mounted drive is stopped for a new folder and mounted again, even if
+    /// the change fails. Files in the local folder are merged with the ones
+    /// in a new remote folder, those missing there are uploaded to it.
+    pub async fn update_drive(&self, id: &str, config: DriveConfig) -> Result<()> {
+        let current = self.drive_config(id).await?;
+        let name = config.name.trim().to_string();
+        if name.is_empty() {
+            anyhow::bail!("Drive name can't be empty");
+        }
+        let remote_changed = config.remote_path != current.remote_path;
+        let path_changed = path_key(&config.sync_path) != path_key(&current.sync_path);
+
+        if remote_changed {
+            if current.guest_share.is_some() {
+                anyhow::bail!("The folder of a share mounted as a guest can't be changed");
+            }
+            CrUri::new(&config.remote_path)
+                .with_context(|| format!("Invalid remote path {}", config.remote_path))?;
+        }
+        if path_changed {
+            check_new_sync_path(&current.sync_path, &config.sync_path)?;
+        }
+
+        if !remote_changed && !path_changed {
+            if name != current.name {
+                self.rename_drive(id, name).await?;
+                self.schedule_persist();
+            }
+            return Ok(());
+        }
+
+        if let Err(error) = self
+            .check_new_drive(
+                &current.instance_url,
+                &config.remote_path,
+                &config.sync_path,
+            )
+            .await
+        {
+            if error.existing_id() != id {
+                return Err(error.into());
+            }
+        }
+
+        let was_mounted = self.drives.read().await.contains_key(id);
+        if was_mounted {
+            if let Err(e) = self.unmount_drive(id).await {
+                // The mount is shut down either way, bring it back before
+                // giving up on the change
+                let _ = self.mount_drive(id).await;
+                return Err(e);
+            }
+        }
+        let result = self
+            .change_drive_folders(
+                id,
+                name,
+                config.remote_path,
+                path_changed.then_some(config.sync_path),
+            )
+            .await;
+        if was_mounted {
+            let mounted = self.mount_drive(id).await;
+            result?;
+            mounted?;
+        } else {
+            result?;
+            self.schedule_persist();
+        }
+        tracing::info!(target: "drive::manager", drive_id = %id, "Drive updated");
+        Ok(())
+    }
+
+    /// Config of a mounted or unmounted drive
+    async fn drive_config(&self, id: &str) -> Result<DriveConfig> {
+        if let Some(mount) = self.get_drive(id).await {
+            return Ok(mount.get_config().await);
+        }
+        self.unmounted
+            .read()
+            .await
+            .get(id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", id))
+    }
+
+    async fn rename_drive(&self, id: &str, name: String) -> Result<()> {
+        if let Some(mount) = self.get_drive(id).await {
+            return mount.set_name(name).await;
+        }
+        let mut unmounted = self.unmounted.write().await;
+        let config = unmounted
+            .get_mut(id)
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", id))?;
+        config.name = name;
+        Ok(())
+    }
+
+    /// Point an unmounted drive to new folders, moving its local folder to
+    /// `sync_path` if given
+    async fn change_drive_folders(
+        &self,
+        id: &str,
+        name: String,
+        remote_path: String,
+        sync_path: Option<PathBuf>,
+    ) -> Result<()> {
+        let mut unmounted = self.unmounted.write().await;
+        let config = unmounted
+            .get_mut(id)
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", id))?;
+        config.name = name;
+
+        if let Some(sync_path) = sync_path {
+            let old_path = config.sync_path.clone();
+            let old_root = old_path.to_str().context("Path contains invalid UTF-8")?;
+            let new_root = sync_path.to_str().context("Path contains invalid UTF-8")?;
+            // The ID is derived from the folder and Explorer holds on to a
+            // registered one, it is let go before the folder moves. A new one
+            // is registered at the next mount.
+            let sync_root_id = config.sync_root_id.take();
+            if let Some(sync_root_id) = &sync_root_id {
+                if let Err(e) = sync_root_id.unregister() {
+                    tracing::warn!(target: "drive::manager", drive_id = %id, error = %e, "Failed to unregister old sync root");
+                }
+            }
+            if let Err(e) = move_sync_folder(&old_path, &sync_path) {
+                // Registered again at the old folder by the next mount
+                config.sync_root_id = sync_root_id;
+                return Err(e);
+            }
+            let moved = match self.inventory.move_drive_root(id, old_root, new_root) {
+                Ok(moved) => moved,
+                Err(e) => {
+                    // The inventory still describes the old folder, a mount
+                    // at the new one would find every file deleted
+                    if let Err(back) = fs::rename(&sync_path, &old_path) {
+                        tracing::error!(target: "drive::manager", drive_id = %id, from = %sync_path.display(), to = %old_path.display(), error = %back, "Failed to move sync folder back");
+                    }
+                    config.sync_root_id = sync_root_id;
+                    return Err(e.context("Failed to move inventory"));
+                }
+            };
+            tracing::info!(target: "drive::manager", drive_id = %id, from = %old_path.display(), to = %sync_path.display(), rows = moved, "Moved sync folder");
+            config.sync_path = sync_path;
+            // Journal positions and resume markers describe the old folder
+            config.usn_checkpoint = None;
+            config.resume = Default::default();
+        }
+
+        if remote_path != config.remote_path {
+            // What the inventory knows about the files, and the uploads left
+            // to resume, belong to the old remote folder. The first sync
+            // merges the local files with the new one instead.
+            self.inventory
+                .nuke_drive(id)
+                .context("Failed to reset inventory")?;
+            let sessions = self
+                .inventory
+                .delete_upload_sessions_by_drive(id)
+                .context("Failed to drop upload sessions")?;
+            tracing::info!(target: "drive::manager", drive_id = %id, from = %config.remote_path, to = %remote_path, upload_sessions = sessions, "Changed remote folder");
+            config.remote_path = remote_path;
+            config.import_existing = true;
+            config.resume = Default::default();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sync_folder_moves_with_its_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = dir.path().join("Drive");
+        fs::create_dir_all(old.join("docs")).unwrap();
+        fs::write(old.join("docs").join("a.txt"), b"a").unwrap();
+        let new = dir.path().join("Sync").join("Drive");
+
+        assert!(!is_folder_empty(&old).unwrap());
+        check_new_sync_path(&old, &new).unwrap();
+        move_sync_folder(&old, &new).unwrap();
+        assert!(!old.exists());
+        assert!(new.join("docs").join("a.txt").exists());
+        assert!(is_folder_empty(&old).unwrap());
+    }
+
+    #[test]
+    fn sync_folder_must_move_to_an_empty_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = dir.path().join("Drive");
+        let taken = dir.path().join("Taken");
+        fs::create_dir_all(&old).unwrap();
+        fs::create_dir_all(&taken).unwrap();
+        fs::write(taken.join("b.txt"), b"b").unwrap();
+
+        assert!(check_new_sync_path(&old, &taken).is_err());
+        assert!(check_new_sync_path(&old, &old.join("Inner")).is_err());
+        assert!(check_new_sync_path(&old, Path::new("Relative")).is_err());
+
+        fs::write(old.join(DESKTOP_INI), b"").unwrap();
+        assert!(is_folder_empty(&old).unwrap());
+    }
+}
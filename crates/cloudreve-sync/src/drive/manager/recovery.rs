@@ -0,0 +1,177 @@
+use super::DriveManager;
+use crate::drive::known_folders::{KnownFolders, find_on_other_disk};
+use crate::drive::mounts::{DriveConfig, Mount};
+use crate::drive::utils::path_key;
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+impl DriveManager {
+    /// Sync a drive whose folder went missing with `path` instead, e.g. where
+    /// its disk is mounted now. Files already in `path` are merged with the
+    /// ones in the cloud, nothing is deleted on either side.
+    pub async fn relink_sync_root(&self, drive_id: &str, path: PathBuf) -> Result<()> {
+        if !path.is_dir() {
+            anyhow::bail!("Folder does not exist: {}", path.display());
+        }
+        let mount = self.missing_root_mount(drive_id).await?;
+        let (instance_url, remote_path) = {
+            let config = mount.config.read().await;
+            (config.instance_url.clone(), config.remote_path.clone())
+        };
+        // The drive itself conflicts if the folder is back where it was
+        if let Err(error) = self
+            .check_new_drive(&instance_url, &remote_path, &path)
+            .await
+        {
+            if error.existing_id() != drive_id {
+                return Err(error.into());
+            }
+        }
+
+        tracing::info!(target: "drive::manager", drive_id = %drive_id, path = %path.display(), "Relinking sync folder");
+        self.rebuild_sync_root(drive_id, path, true).await
+    }
+
+    /// Sync a drive whose folder went missing with the same folder on the disk
+    /// letter it has now, as offered in [`DriveInfo::relocation`]. Files are
+    /// merged like with [`Self::relink_sync_root`]. Returns the folder now
+    /// synced.
+    ///
+    /// [`DriveInfo::relocation`]: super::DriveInfo::relocation
+    pub async fn relocate_sync_root(&self, drive_id: &str) -> Result<PathBuf> {
+        let mount = self.missing_root_mount(drive_id).await?;
+        let old_path = mount.get_sync_path().await;
+        let path = find_on_other_disk(&old_path)
+            .with_context(|| format!("{} was not found on another disk", old_path.display()))?;
+        self.relink_sync_root(drive_id, path.clone()).await?;
+        Ok(path)
+    }
+
+    /// Create the missing folder of a drive again and download its content
+    /// from the cloud. Fails if the disk of the folder is still gone.
+    pub async fn recreate_sync_root(&self, drive_id: &str) -> Result<()> {
+        let mount = self.missing_root_mount(drive_id).await?;
+        let path = mount.get_sync_path().await;
+        std::fs::create_dir_all(&path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+
+        tracing::info!(target: "drive::manager", drive_id = %drive_id, path = %path.display(), "Recreating sync folder");
+        self.rebuild_sync_root(drive_id, path, false).await
+    }
+
+    /// Restore the files a drive stopped syncing for from the versions stored
+    /// in the cloud before they were rewritten, then resume syncing. Returns
+    /// the number of files restored.
+    pub async fn restore_suspicious_changes(&self, drive_id: &str) -> Result<usize> {
+        let mount = self
+            .get_drive(drive_id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", drive_id))?;
+        mount.restore_suspicious_changes().await
+    }
+
+    /// Resume syncing a drive stopped for suspicious changes the user made
+    /// on purpose, e.g. by encrypting files themselves
+    pub async fn dismiss_suspicious_changes(&self, drive_id: &str) -> Result<()> {
+        let mount = self
+            .get_drive(drive_id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", drive_id))?;
+        mount.dismiss_suspicious_changes().await
+    }
+
+    async fn missing_root_mount(&self, drive_id: &str) -> Result<Arc<Mount>> {
+        let mount = self
+            .get_drive(drive_id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", drive_id))?;
+        if !mount.is_sync_root_missing().await {
+            anyhow::bail!("Sync folder of drive {} is not missing", drive_id);
+        }
+        Ok(mount)
+    }
+
+    /// Mount the drive again at `sync_path` as if it was just added. The
+    /// inventory is dropped, so no file can look deleted locally and the
+    /// first sync starts over from the cloud.
+    async fn rebuild_sync_root(
+        &self,
+        drive_id: &str,
+        sync_path: PathBuf,
+        import_existing: bool,
+    ) -> Result<()> {
+        // Disconnecting a sync root whose folder is gone may fail, the mount
+        // is shut down either way
+        if let Err(e) = self.unmount_drive(drive_id).await {
+            tracing::warn!(target: "drive::manager", drive_id = %drive_id, error = ?e, "Failed to unmount drive with missing folder");
+        }
+
+        {
+            let mut unmounted = self.unmounted.write().await;
+            let config = unmounted
+                .get_mut(drive_id)
+                .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", drive_id))?;
+            self.reset_sync_root(config, sync_path, import_existing)?;
+        }
+
+        self.mount_drive(drive_id).await
+    }
+
+    /// Point the config of a stopped drive to `sync_path`, forgetting what
+    /// was synced at the old folder
+    fn reset_sync_root(
+        &self,
+        config: &mut DriveConfig,
+        sync_path: PathBuf,
+        import_existing: bool,
+    ) -> Result<()> {
+        self.inventory
+            .nuke_drive(&config.id)
+            .context("Failed to reset inventory")?;
+        if let Some(sync_root_id) = config.sync_root_id.take() {
+            if let Err(e) = sync_root_id.unregister() {
+                tracing::warn!(target: "drive::manager", drive_id = %config.id, error = %e, "Failed to unregister old sync root");
+            }
+        }
+        config.sync_path = sync_path;
+        config.import_existing = import_existing;
+        config.usn_checkpoint = None;
+        config.resume = Default::default();
+        Ok(())
+    }
+
+    /// Point saved drives whose folder moved along with a known folder, like
+    /// a user profile moved to another disk, to where it is now. Only done
+    /// if the old folder is gone and the new one exists, and files are
+    /// merged like with [`Self::relink_sync_root`]. Returns whether any
+    /// drive changed.
+    pub(super) fn resolve_sync_paths(&self, drives: &mut [DriveConfig]) -> bool {
+        let known_folders = KnownFolders::current();
+        let mut changed = false;
+        for drive in drives.iter_mut() {
+            let Some(resolved) = drive
+                .portable_sync_path
+                .as_deref()
+                .and_then(|portable| known_folders.resolve(portable))
+            else {
+                continue;
+            };
+            if path_key(&resolved) == path_key(&drive.sync_path)
+                || drive.sync_path.is_dir()
+                || !resolved.is_dir()
+            {
+                continue;
+            }
+
+            let old_path = drive.sync_path.clone();
+            if let Err(e) = self.reset_sync_root(drive, resolved, true) {
+                tracing::warn!(target: "drive::manager", drive_id = %drive.id, error = ?e, "Failed to follow moved sync folder");
+                continue;
+            }
+            tracing::info!(target: "drive::manager", drive_id = %drive.id, from = %old_path.display(), to = %drive.sync_path.display(), "Sync folder moved with its known folder");
+            changed = true;
+        }
+        changed
+    }
+}
@@ -0,0 +1,162 @@
+use super::DriveManager;
+use super::duplicates::same_instance;
+use crate::drive::mounts::DriveConfig;
+use crate::drive::utils::{local_path_to_cr_uri, remote_path_to_local_relative_path};
+use crate::policy::Policy;
+use crate::tasks::{REMOTE_TRANSFER_KIND, RemoteTransferState, TaskKind, TaskPayload};
+use anyhow::{Context, Result};
+use cloudreve_api::models::uri::{CR_URI_PREFIX, CrUri};
+use std::path::PathBuf;
+
+/// Resolve a local path in the drive or a `cloudreve://` URI to the remote
+/// URI and, if the drive syncs it, the local path
+pub(super) fn resolve(config: &DriveConfig, path: &str) -> Result<(CrUri, Option<PathBuf>)> {
+    let base = CrUri::new(&config.remote_path)?;
+    if path.starts_with(CR_URI_PREFIX) {
+        let uri = CrUri::new(path)?;
+        let local = if uri.to_string() == base.to_string() {
+            Some(config.sync_path.clone())
+        } else {
+            remote_path_to_local_relative_path(&uri, &base)
+                .ok()
+                .map(|relative| config.sync_path.join(relative))
+        };
+        return Ok((uri, local));
+    }
+
+    let local = PathBuf::from(path);
+    let uri = local_path_to_cr_uri(
+        local.clone(),
+        config.sync_path.clone(),
+        config.remote_path.clone(),
+    )
+    .with_context(|| format!("{} is not in drive {}", path, config.name))?;
+    Ok((uri, Some(local)))
+}
+
+impl DriveManager {
+    /// Copy or move `sources` of one drive into the folder `destination` of
+    /// the same or another drive, on the server. Paths are local paths in the
+    /// drive or `cloudreve://` URIs. Both drives must be signed in to the same
+    /// account. Returns the ID of the task tracking the transfer, which runs
+    /// in the source drive's queue.
+    pub async fn transfer_remote_files(
+        &self,
+        source_drive_id: &str,
+        sources: Vec<String>,
+        destination_drive_id: &str,
+        destination: String,
+        copy: bool,
+    ) -> Result<String> {
+        if sources.is_empty() {
+            anyhow::bail!("No files to transfer");
+        }
+        if Policy::get().force_read_only {
+            anyhow::bail!("Remote changes are disabled by administrator policy");
+        }
+
+        let source_mount = self
+            .get_drive(source_drive_id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", source_drive_id))?;
+        let destination_mount = if destination_drive_id == source_drive_id {
+            source_mount.clone()
+        } else {
+            self.get_drive(destination_drive_id)
+                .await
+                .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", destination_drive_id))?
+        };
+        if source_mount.is_guest_share() || destination_mount.is_guest_share() {
+            anyhow::bail!("Files of a share mounted as a guest can't be transferred");
+        }
+        let source_config = source_mount.get_config().await;
+        let destination_config = destination_mount.get_config().await;
+        let same_account = same_instance(
+            &source_config.instance_url,
+            &destination_config.instance_url,
+        ) && source_config.user_id == destination_config.user_id;
+        if !same_account {
+            anyhow::bail!(
+                "Drives {} and {} are not signed in to the same account",
+                source_config.name,
+                destination_config.name
+            );
+        }
+
+        let mut uris = Vec::with_capacity(sources.len());
+        let mut refresh: Vec<PathBuf> = Vec::new();
+        let mut task_path = None;
+        for source in &sources {
+            let (uri, local) = resolve(&source_config, source)?;
+            uris.push(uri.to_string());
+            if let Some(local) = local {
+                // A move removes the file from its parent folder
+                if !copy {
+                    if let Some(parent) = local.parent() {
+                        if !refresh.iter().any(|p| p == parent) {
+                            refresh.push(parent.to_path_buf());
+                        }
+                    }
+                }
+                task_path.get_or_insert(local);
+            }
+        }
+        let (dst, dst_local) = resolve(&destination_config, &destination)?;
+        if let Some(dst_local) = dst_local {
+            if !refresh.contains(&dst_local) {
+                refresh.push(dst_local);
+            }
+        }
+
+        let state = RemoteTransferState {
+            uris,
+            dst: dst.to_string(),
+            copy,
+            refresh,
+            done: 0,
+        };
+        let task_path = task_path.unwrap_or_else(|| PathBuf::from(&sources[0]));
+        let total = state.uris.len() as i64;
+        let payload = TaskPayload::new(TaskKind::Custom(REMOTE_TRANSFER_KIND), task_path)
+            .with_totals(0, total)
+            .with_custom_state(serde_json::to_value(&state)?);
+
+        let task_id = source_mount.task_queue.enqueue(payload).await?;
+        tracing::info!(target: "drive::manager", drive_id = %source_drive_id, task_id = %task_id, count = total, copy, dst = %state.dst, "Queued remote transfer");
+        Ok(task_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_resolve_to_uris() {
+        let config: DriveConfig = serde_json::from_value(serde_json::json!({
+            "id": "team",
+            "name": "Team",
+            "instance_url": "https://cloud.example.com",
+            "remote_path": "cloudreve://my/Team",
+            "credentials": { "refresh_token": "", "refresh_expires": "" },
+            "sync_path": "C:\\Team",
+            "enabled": true,
+            "user_id": "user",
+        }))
+        .unwrap();
+
+        let (uri, local) = resolve(&config, "C:\\Team\\docs\\a.txt").unwrap();
+        assert_eq!(uri.to_string(), "cloudreve://my/Team/docs/a.txt");
+        assert_eq!(local, Some(PathBuf::from("C:\\Team\\docs\\a.txt")));
+
+        let (_, local) = resolve(&config, "cloudreve://my/Team/docs").unwrap();
+        assert_eq!(local, Some(PathBuf::from("C:\\Team\\docs")));
+        let (_, local) = resolve(&config, "cloudreve://my/Team").unwrap();
+        assert_eq!(local, Some(PathBuf::from("C:\\Team")));
+        // Remote folders outside the drive can be used, but are not synced
+        let (_, local) = resolve(&config, "cloudreve://my/Other").unwrap();
+        assert_eq!(local, None);
+
+        assert!(resolve(&config, "D:\\Elsewhere\\a.txt").is_err());
+    }
+}
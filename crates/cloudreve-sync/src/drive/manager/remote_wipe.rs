@@ -0,0 +1,304 @@
+//! Unlinking a device from the server
+//!
+//! An administrator or the user can revoke the session of this device on the
+//! server with a wipe flag. The server then answers 40090, or sends a `wipe`
+//! event on the file events stream. The drive is removed like the user would
+//! from settings, leaving the downloaded content as the administrator policy
+//! says (see [`Policy::remote_wipe_content`]), and the server is told once
+//! it is done. Deleting only removes files the server has a copy of, and
+//! never a whole known folder such as Documents.
+
+use super::DriveManager;
+use crate::cfapi::placeholder::PinState;
+use crate::drive::known_folders::KnownFolders;
+use crate::drive::mounts::Mount;
+use crate::drive::utils::{key_is_under, path_key};
+use crate::policy::{Policy, RemoteWipeContent};
+use crate::utils::toast::send_general_text_toast;
+use crate::vfs::VirtualFs;
+use anyhow::{Context, Result};
+use cloudreve_api::api::UserApi;
+use cloudreve_api::models::user::{WipeCompletedService, WipeRequest};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Drives being unlinked, as every failing request reports the wipe again
+static WIPING: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+
+fn wiping() -> &'static Mutex<HashSet<String>> {
+    WIPING.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+impl DriveManager {
+    /// Remove a drive the server unlinked and report the result back.
+    /// Further reports for the same drive while it is removed are ignored.
+    pub(super) async fn remote_wipe(&self, drive_id: String, request: WipeRequest) {
+        if !wiping().lock().unwrap().insert(drive_id.clone()) {
+            return;
+        }
+        let Some(mount) = self.get_drive(&drive_id).await else {
+            wiping().lock().unwrap().remove(&drive_id);
+            return;
+        };
+        let content = Policy::get().remote_wipe_content;
+        tracing::warn!(target: "drive::manager", drive_id = %drive_id, request_id = %request.id, content = ?content, "Server unlinked this device, removing drive");
+
+        let name = mount.config.read().await.name.clone();
+        let client = mount.cr_client.clone();
+        let result = self.wipe_drive(&mount, content).await;
+        drop(mount);
+        wiping().lock().unwrap().remove(&drive_id);
+
+        let report = WipeCompletedService {
+            id: request.id,
+            success: result.is_ok(),
+            error: result.as_ref().err().map(|e| format!("{:#}", e)),
+        };
+        match result {
+            Ok(()) => {
+                tracing::info!(target: "drive::manager", drive_id = %drive_id, "Drive unlinked");
+                send_general_text_toast(
+                    &t!("driveUnlinkedTitle"),
+                    &t!("driveUnlinkedMessage", "drive" => name),
+                );
+            }
+            Err(ref e) => {
+                tracing::error!(target: "drive::manager", drive_id = %drive_id, error = ?e, "Failed to unlink drive");
+            }
+        }
+        if let Err(e) = client.report_wipe_completed(&report).await {
+            tracing::warn!(target: "drive::manager", drive_id = %drive_id, error = %e, "Failed to report unlink to the server");
+        }
+    }
+
+    async fn wipe_drive(&self, mount: &Arc<Mount>, content: RemoteWipeContent) -> Result<()> {
+        let sync_path = mount.get_sync_path().await;
+        let known_folder = content == RemoteWipeContent::Delete
+            && KnownFolders::current().is_known_folder(&sync_path);
+
+        // Placeholder states can only be read while the sync root is registered
+        let mut to_delete = Vec::new();
+        match content {
+            RemoteWipeContent::Dehydrate => {
+                dehydrate_tree(mount.vfs.clone(), &sync_path).await?;
+            }
+            RemoteWipeContent::Delete if !known_folder => {
+                let tracked = self
+                    .inventory
+                    .list_drive_files(&mount.id)
+                    .context("Failed to list synced files")?
+                    .into_iter()
+                    .map(|file| PathBuf::from(file.local_path))
+                    .collect();
+                let vfs = mount.vfs.clone();
+                let root = sync_path.clone();
+                to_delete =
+                    tokio::task::spawn_blocking(move || synced_files(vfs.as_ref(), &root, tracked))
+                        .await
+                        .context("Listing synced files panicked")?;
+            }
+            _ => {}
+        }
+
+        self.remove_drive(&mount.id).await?;
+
+        if known_folder {
+            anyhow::bail!(
+                "{} is a known folder, its content was kept",
+                sync_path.display()
+            );
+        }
+        if content == RemoteWipeContent::Delete {
+            tokio::task::spawn_blocking(move || delete_files(&sync_path, &to_delete))
+                .await
+                .context("Deleting synced files panicked")?;
+        }
+        Ok(())
+    }
+}
+
+/// Unpin everything below `root` and free the content of the files on disk.
+/// Files with changes not uploaded yet are left alone.
+async fn dehydrate_tree(vfs: Arc<dyn VirtualFs>, root: &Path) -> Result<()> {
+    let files = {
+        let vfs = vfs.clone();
+        let root = root.to_path_buf();
+        tokio::task::spawn_blocking(move || hydrated_files(vfs.as_ref(), &root))
+            .await
+            .context("Listing downloaded files panicked")??
+    };
+    let mut failed = 0;
+    for path in &files {
+        if let Err(e) = vfs.dehydrate(path).await {
+            tracing::warn!(target: "drive::manager", path = %path.display(), error = %e, "Failed to free file content");
+            failed += 1;
+        }
+    }
+    tracing::info!(target: "drive::manager", root = %root.display(), files = files.len(), failed, "Freed downloaded content");
+    Ok(())
+}
+
+/// Files below `root` known to the inventory and in sync, so that the server
+/// has a copy of them. Local changes not uploaded yet are left out.
+fn synced_files(vfs: &dyn VirtualFs, root: &Path, tracked: Vec<PathBuf>) -> Vec<PathBuf> {
+    let root_key = path_key(root);
+    tracked
+        .into_iter()
+        .filter(|path| key_is_under(&path_key(path), &root_key))
+        .filter(|path| match vfs.status(path) {
+            Ok(status) => {
+                status.exists && status.is_placeholder && !status.is_directory && status.in_sync
+            }
+            Err(e) => {
+                tracing::warn!(target: "drive::manager", path = %path.display(), error = %e, "Failed to read placeholder state, keeping file");
+                false
+            }
+        })
+        .collect()
+}
+
+/// Delete `files`, then the folders below and including `root` left empty
+/// by it. Folders holding anything else are kept.
+fn delete_files(root: &Path, files: &[PathBuf]) {
+    let mut failed = 0;
+    let mut folders = Vec::new();
+    for path in files {
+        if let Err(e) = std::fs::remove_file(path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                tracing::warn!(target: "drive::manager", path = %path.display(), error = %e, "Failed to delete file");
+                failed += 1;
+            }
+        }
+        folders.extend(
+            path.ancestors()
+                .skip(1)
+                .take_while(|folder| folder.starts_with(root)),
+        );
+    }
+    // Deepest first, so that emptied parents go too
+    folders.sort_unstable_by(|a, b| {
+        b.components()
+            .count()
+            .cmp(&a.components().count())
+            .then_with(|| a.cmp(b))
+    });
+    folders.dedup();
+    for folder in folders {
+        // Fails on folders that are not empty, which are kept
+        let _ = std::fs::remove_dir(folder);
+    }
+    let _ = std::fs::remove_dir(root);
+    tracing::info!(target: "drive::manager", root = %root.display(), files = files.len(), failed, "Deleted synced files");
+}
+
+/// In-sync placeholder files below `root` with content on disk. Folders that
+/// were never listed are skipped, there is nothing downloaded in them.
+fn hydrated_files(vfs: &dyn VirtualFs, root: &Path) -> Result<Vec<PathBuf>> {
+    vfs.pin_tree(root, PinState::Unspecified)
+        .with_context(|| format!("Failed to unpin {}", root.display()))?;
+
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(folder) = pending.pop() {
+        let entries = match std::fs::read_dir(&folder) {
+            Ok(entries) => entries,
+            Err(e) => {
+                tracing::warn!(target: "drive::manager", path = %folder.display(), error = %e, "Failed to read folder");
+                continue;
+            }
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let status = vfs.status(&path)?;
+            if !status.exists || !status.is_placeholder {
+                continue;
+            }
+            if status.is_directory {
+                if !status.partial_on_disk {
+                    pending.push(path);
+                }
+            } else if status.in_sync && !status.partial_on_disk {
+                files.push(path);
+            }
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::FakeVirtualFs;
+
+    #[test]
+    fn only_downloaded_files_in_sync_are_freed() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let listed = root.join("listed");
+        let unlisted = root.join("unlisted");
+        let downloaded = listed.join("a.txt");
+        let edited = root.join("edited.txt");
+        let hidden = unlisted.join("b.txt");
+        std::fs::create_dir_all(&listed).unwrap();
+        std::fs::create_dir_all(&unlisted).unwrap();
+        for path in [&downloaded, &edited, &hidden] {
+            std::fs::write(path, b"content").unwrap();
+        }
+
+        let vfs = FakeVirtualFs::new();
+        vfs.add_folder(root);
+        vfs.add_folder(&listed);
+        vfs.add_unpopulated_folder(&unlisted);
+        for path in [&downloaded, &edited, &hidden] {
+            vfs.add_placeholder(path, 7);
+            vfs.hydrate(path).unwrap();
+        }
+        vfs.set_pin_state(&downloaded, PinState::Pinned).unwrap();
+        vfs.write(&edited, 8);
+
+        let files = hydrated_files(&vfs, root).unwrap();
+        assert_eq!(files, vec![downloaded.clone()]);
+        assert_eq!(
+            vfs.status(&downloaded).unwrap().pin_state,
+            PinState::Unspecified
+        );
+    }
+
+    #[test]
+    fn only_tracked_files_in_sync_are_deleted() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("Cloudreve");
+        let synced = root.join("docs").join("a.txt");
+        let edited = root.join("docs").join("b.txt");
+        let untracked = root.join("notes").join("c.txt");
+        let emptied = root.join("photos").join("d.jpg");
+        let outside = dir.path().join("e.txt");
+        for path in [&synced, &edited, &untracked, &emptied, &outside] {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"content").unwrap();
+        }
+
+        let vfs = FakeVirtualFs::new();
+        for path in [&synced, &edited, &untracked, &emptied, &outside] {
+            vfs.add_placeholder(path, 7);
+        }
+        vfs.write(&edited, 8);
+
+        let tracked = vec![
+            synced.clone(),
+            edited.clone(),
+            emptied.clone(),
+            outside.clone(),
+        ];
+        let files = synced_files(&vfs, &root, tracked);
+        assert_eq!(files, vec![synced.clone(), emptied.clone()]);
+
+        delete_files(&root, &files);
+        assert!(!synced.exists());
+        assert!(!emptied.parent().unwrap().exists());
+        for path in [&edited, &untracked, &outside] {
+            assert!(path.exists());
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use super::DriveManager;
+use super::remote_transfer::resolve;
+use crate::drive::mounts::DriveConfig;
+use anyhow::{Context, Result};
+use chrono::Utc;
+use cloudreve_api::api::ShareApi;
+use cloudreve_api::models::explorer::{Share, file_type};
+use cloudreve_api::models::uri::CrUri;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Shares of the user listed at most when inspecting a folder
+const MAX_SHARES: usize = 5000;
+
+/// A share link exposing a file or folder of an inspected folder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedItem {
+    /// URI of the shared file or folder
+    pub uri: String,
+    pub name: String,
+    pub is_folder: bool,
+    /// Local path of the shared file or folder, `None` if outside the drive
+    pub local_path: Option<PathBuf>,
+    /// Link of the share
+    pub url: String,
+    /// Anyone with the link can open it, without a password
+    pub public: bool,
+    pub password_protected: bool,
+    pub expires: Option<String>,
+    pub expired: bool,
+    pub views: i32,
+    pub downloads: i32,
+    /// Downloads left before the share expires, if limited
+    pub remaining_downloads: Option<i32>,
+    /// Shares a folder above the inspected one, exposing all of it
+    pub inherited: bool,
+}
+
+/// Share links exposing the contents of a folder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShareReport {
+    pub folder: PathBuf,
+    /// When the report was made, Unix timestamp
+    pub generated_at: i64,
+    /// Shares of the folder, of items in it, and of folders above it
+    pub items: Vec<SharedItem>,
+    /// Shares that can be opened with the link alone
+    pub public_count: usize,
+    /// Shares that need a password
+    pub protected_count: usize,
+    /// Shares that no longer open
+    pub expired_count: usize,
+}
+
+/// Whether a share with source `source` exposes `folder`, and if so whether
+/// it does through a folder above it
+fn share_scope(folder: &CrUri, source: &CrUri) -> Option<bool> {
+    if folder.fs() != source.fs() {
+        return None;
+    }
+    let folder = folder.elements();
+    let source = source.elements();
+    if source.starts_with(&folder) {
+        Some(false)
+    } else if folder.starts_with(&source) {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+fn shared_item(config: &DriveConfig, folder: &CrUri, share: Share) -> Option<SharedItem> {
+    let source_uri = share.source_uri?;
+    let source = CrUri::new(&source_uri).ok()?;
+    let inherited = share_scope(folder, &source)?;
+    let local_path = resolve(config, &source_uri)
+        .ok()
+        .and_then(|(_, local)| local);
+    let expired = share.expired.unwrap_or(false);
+    let password_protected = share.password_protected || share.is_private.unwrap_or(false);
+    Some(SharedItem {
+        name: share
+            .name
+            .unwrap_or_else(|| source.elements().pop().unwrap_or_default()),
+        uri: source_uri,
+        is_folder: share.source_type == Some(file_type::FOLDER),
+        local_path,
+        url: share.url,
+        public: !password_protected && !expired,
+        password_protected,
+        expires: share.expires,
+        expired,
+        views: share.visited,
+        downloads: share.downloaded,
+        remaining_downloads: share.remain_downloads,
+        inherited,
+    })
+}
+
+impl DriveManager {
+    /// List the share links exposing `folder` of a drive or anything in it,
+    /// for checking before the folder is shown or handed to someone else
+    pub async fn inspect_folder_shares(
+        &self,
+        drive_id: &str,
+        folder: PathBuf,
+    ) -> Result<ShareReport> {
+        let mount = self
+            .get_drive(drive_id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", drive_id))?;
+        let config = mount.get_config().await;
+        if config.guest_share.is_some() {
+            anyhow::bail!("Guests of a share can't see its links");
+        }
+        let folder_path = folder.to_str().context("Path contains invalid UTF-8")?;
+        let (folder_uri, _) = resolve(&config, folder_path)?;
+
+        let shares = mount.cr_client.list_all_shares(MAX_SHARES).await?;
+        let mut items: Vec<SharedItem> = shares
+            .into_iter()
+            .filter_map(|share| shared_item(&config, &folder_uri, share))
+            .collect();
+        items.sort_by(|a, b| {
+            b.inherited
+                .cmp(&a.inherited)
+                .then_with(|| a.uri.cmp(&b.uri))
+        });
+
+        let report = ShareReport {
+            folder,
+            generated_at: Utc::now().timestamp(),
+            public_count: items.iter().filter(|item| item.public).count(),
+            protected_count: items
+                .iter()
+                .filter(|item| item.password_protected && !item.expired)
+                .count(),
+            expired_count: items.iter().filter(|item| item.expired).count(),
+            items,
+        };
+        tracing::info!(
+            target: "drive::manager",
+            drive_id = %drive_id,
+            folder = %report.folder.display(),
+            shares = report.items.len(),
+            public = report.public_count,
+            "Inspected folder shares"
+        );
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shares_in_and_above_the_folder_expose_it() {
+        let folder = CrUri::new("cloudreve://my/Team/docs").unwrap();
+        let scope = |source: &str| share_scope(&folder, &CrUri::new(source).unwrap());
+
+        assert_eq!(scope("cloudreve://my/Team/docs"), Some(false));
+        assert_eq!(scope("cloudreve://my/Team/docs/a.txt"), Some(false));
+        assert_eq!(scope("cloudreve://my/Team"), Some(true));
+        assert_eq!(scope("cloudreve://my"), Some(true));
+        assert_eq!(scope("cloudreve://my/Team/docs2/a.txt"), None);
+        assert_eq!(scope("cloudreve://my/Other"), None);
+        assert_eq!(scope("cloudreve://share/Team/docs"), None);
+    }
+}
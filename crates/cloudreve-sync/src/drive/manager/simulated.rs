@@ -0,0 +1,32 @@
+use super::DriveManager;
+use crate::drive::mounts::{DriveConfig, DriveKind};
+use crate::drive::simulated::{SIMULATED_INSTANCE_URL, ensure_enabled};
+use anyhow::Result;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+impl DriveManager {
+    /// Add a simulated drive syncing to `local_path`, see
+    /// [`crate::drive::simulated`]. Fails unless simulated drives are
+    /// allowed.
+    pub async fn add_simulated_drive(&self, name: String, local_path: PathBuf) -> Result<String> {
+        ensure_enabled()?;
+        let id = Uuid::new_v4().to_string();
+        let config = DriveConfig {
+            // Every simulated drive has a remote tree of its own
+            remote_path: format!("cloudreve://my/{}", id),
+            id,
+            name,
+            instance_url: SIMULATED_INSTANCE_URL.to_string(),
+            sync_path: local_path,
+            enabled: true,
+            user_id: "simulated".to_string(),
+            kind: DriveKind::Simulated,
+            ..Default::default()
+        };
+
+        let id = self.add_drive(config).await?;
+        tracing::info!(target: "drive::manager", drive_id = %id, "Added simulated drive");
+        Ok(id)
+    }
+}
@@ -0,0 +1,89 @@
+use super::DriveManager;
+use crate::idle;
+use crate::inventory::InventorySnapshot;
+use anyhow::{Context, Result};
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often the inventory is snapshotted
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+impl DriveManager {
+    /// Spawn a background task snapshotting the inventory periodically, so that
+    /// local metadata can be rolled back without a full resync
+    pub fn spawn_inventory_snapshot_task(self: &Arc<Self>) {
+        let inventory = self.inventory.clone();
+        tokio::spawn(async move {
+            loop {
+                // Continue the schedule of the previous run across restarts
+                let latest = inventory
+                    .list_snapshots()
+                    .ok()
+                    .and_then(|snapshots| snapshots.first().map(|s| s.timestamp));
+                let wait = latest
+                    .map(|taken| {
+                        let age = Utc::now().timestamp().saturating_sub(taken).max(0) as u64;
+                        SNAPSHOT_INTERVAL.saturating_sub(Duration::from_secs(age))
+                    })
+                    .unwrap_or(Duration::ZERO);
+                tokio::time::sleep(wait).await;
+                idle::wait_for_idle().await;
+
+                let inventory = inventory.clone();
+                match tokio::task::spawn_blocking(move || inventory.create_snapshot()).await {
+                    Ok(Ok(_)) => {}
+                    Ok(Err(e)) => {
+                        tracing::error!(target: "drive::manager", error = %e, "Failed to snapshot inventory");
+                        tokio::time::sleep(SNAPSHOT_INTERVAL).await;
+                    }
+                    Err(e) => {
+                        tracing::error!(target: "drive::manager", error = %e, "Inventory snapshot task panicked");
+                        tokio::time::sleep(SNAPSHOT_INTERVAL).await;
+                    }
+                }
+            }
+        });
+    }
+
+    /// List available inventory snapshots, newest first
+    pub async fn list_inventory_snapshots(&self) -> Result<Vec<InventorySnapshot>> {
+        let inventory = self.inventory.clone();
+        tokio::task::spawn_blocking(move || inventory.list_snapshots())
+            .await
+            .context("Inventory snapshot listing panicked")?
+    }
+
+    /// Roll file metadata and drive props back to the snapshot taken at
+    /// `timestamp` (seconds since the epoch). Mounted drives are stopped
+    /// while the inventory is swapped, so none of them writes to it or keeps
+    /// working from the state being replaced, and mounted again afterwards.
+    /// The restored metadata may not match either side anymore, so every
+    /// drive reconciles fully on its next mount.
+    pub async fn restore_inventory_snapshot(&self, timestamp: i64) -> Result<()> {
+        let mounted: Vec<String> = self.drives.read().await.keys().cloned().collect();
+        for id in &mounted {
+            if let Err(e) = self.unmount_drive(id).await {
+                tracing::warn!(target: "drive::manager", drive_id = %id, error = ?e, "Failed to unmount drive before restoring inventory");
+            }
+        }
+
+        let inventory = self.inventory.clone();
+        let result = tokio::task::spawn_blocking(move || inventory.restore_snapshot(timestamp))
+            .await
+            .context("Inventory restore panicked");
+        if matches!(result, Ok(Ok(()))) {
+            for config in self.unmounted.write().await.values_mut() {
+                config.resume = Default::default();
+            }
+            self.schedule_persist();
+        }
+
+        for id in &mounted {
+            if let Err(e) = self.mount_drive(id).await {
+                tracing::error!(target: "drive::manager", drive_id = %id, error = ?e, "Failed to mount drive after restoring inventory");
+            }
+        }
+        result?
+    }
+}
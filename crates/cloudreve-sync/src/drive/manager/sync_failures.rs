@@ -0,0 +1,65 @@
+use super::DriveManager;
+use crate::drive::commands::MountCommand;
+use crate::drive::sync::SyncMode;
+use crate::inventory::{SyncFailure, SyncFailureState, SyncOperation};
+use anyhow::{Context, Result};
+
+impl DriveManager {
+    /// List files of a drive that were quarantined, or ignored, after their
+    /// sync failed repeatedly
+    pub async fn list_sync_failures(&self, drive_id: &str) -> Result<Vec<SyncFailure>> {
+        let inventory = self.inventory.clone();
+        let drive_id = drive_id.to_string();
+        tokio::task::spawn_blocking(move || {
+            inventory.list_sync_failures(
+                &drive_id,
+                &[SyncFailureState::Quarantined, SyncFailureState::Ignored],
+            )
+        })
+        .await
+        .context("Sync failure listing panicked")?
+    }
+
+    /// Take files out of the quarantine and sync them again. Returns the
+    /// number of files retried.
+    pub async fn retry_sync_failures(&self, ids: Vec<i64>) -> Result<usize> {
+        let inventory = self.inventory.clone();
+        let failures = tokio::task::spawn_blocking(move || inventory.take_sync_failures(&ids))
+            .await
+            .context("Sync failure retry panicked")??;
+
+        for failure in &failures {
+            let Some(drive) = self.get_drive(&failure.drive_id).await else {
+                tracing::warn!(target: "drive::manager", drive_id = %failure.drive_id, "Drive of quarantined file not found, not retrying");
+                continue;
+            };
+            let path = std::path::PathBuf::from(&failure.local_path);
+            // The source of a failed rename may be gone, so its folder is
+            // synced instead
+            let command = match (failure.operation, path.parent()) {
+                (SyncOperation::Rename, Some(parent)) => MountCommand::Sync {
+                    local_paths: vec![parent.to_path_buf()],
+                    mode: SyncMode::PathAndFirstLayer,
+                },
+                _ => MountCommand::Sync {
+                    local_paths: vec![path],
+                    mode: SyncMode::PathOnly,
+                },
+            };
+            if let Err(e) = drive.command_tx.send(command) {
+                tracing::warn!(target: "drive::manager", drive_id = %failure.drive_id, error = %e, "Failed to send Sync command");
+            }
+        }
+        tracing::info!(target: "drive::manager", count = failures.len(), "Retrying quarantined files");
+        Ok(failures.len())
+    }
+
+    /// Keep quarantined files out of sync and stop reporting them. Returns the
+    /// number of files ignored.
+    pub async fn ignore_sync_failures(&self, ids: Vec<i64>) -> Result<usize> {
+        let inventory = self.inventory.clone();
+        tokio::task::spawn_blocking(move || inventory.ignore_sync_failures(&ids))
+            .await
+            .context("Sync failure ignore panicked")?
+    }
+}
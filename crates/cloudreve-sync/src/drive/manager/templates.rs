@@ -0,0 +1,286 @@
+//! Named drive templates for adding the same drive setup again.
+//!
+//! A template keeps everything about a drive except the credentials and the
+//! local folder. Templates created in the app live in `templates.json` next
+//! to `drives.json`. Administrators can roll out templates through
+//! [`Policy::drive_templates`]; those are listed first and can't be changed
+//! from the app.
+
+use super::DriveManager;
+use crate::drive::label::DriveLabel;
+use crate::drive::mounts::{Credentials, DriveConfig, NetworkSettings, SyncDirection};
+use crate::drive::remote_path::{PathVariables, expand_remote_path};
+use crate::drive::type_filter::TypeFilter;
+use crate::policy::Policy;
+use crate::tasks::TransferLimits;
+use crate::uploader::CompressionSettings;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// Placeholder in [`DriveTemplate::remote_path`] replaced by the user's ID
+pub const USER_ID_PLACEHOLDER: &str = "{user_id}";
+
+/// Drive settings saved for quick re-adding
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DriveTemplate {
+    /// Template ID, assigned when saved
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub instance_url: String,
+    /// Remote folder to sync, may contain [`USER_ID_PLACEHOLDER`] and the
+    /// other variables of [`expand_remote_path`]
+    pub remote_path: String,
+    #[serde(default)]
+    pub ignore_patterns: Vec<String>,
+    #[serde(default)]
+    pub type_filter: TypeFilter,
+    #[serde(default)]
+    pub sync_direction: SyncDirection,
+    #[serde(default)]
+    pub label: Option<DriveLabel>,
+    #[serde(default)]
+    pub network: NetworkSettings,
+    #[serde(default)]
+    pub compression: CompressionSettings,
+    #[serde(default)]
+    pub transfer_limits: TransferLimits,
+    /// Provided by administrator policy
+    #[serde(default, skip_deserializing)]
+    pub managed: bool,
+}
+
+impl DriveTemplate {
+    /// Capture the settings of an existing drive
+    pub fn from_config(name: String, config: &DriveConfig) -> Self {
+        Self {
+            id: String::new(),
+            name,
+            instance_url: config.instance_url.clone(),
+            remote_path: config.remote_path.clone(),
+            ignore_patterns: config.ignore_patterns.clone(),
+            type_filter: config.type_filter.clone(),
+            sync_direction: config.sync_direction,
+            label: config.label.clone(),
+            network: config.network.clone(),
+            compression: config.compression.clone(),
+            transfer_limits: config.transfer_limits.clone(),
+            managed: false,
+        }
+    }
+
+    /// Build the configuration of a new drive, with the variables of the
+    /// remote path replaced. The label is not applied here, see
+    /// [`DriveManager::create_drive_from_template`].
+    pub fn to_config(
+        &self,
+        drive_name: String,
+        credentials: Credentials,
+        user_id: &str,
+        sync_path: PathBuf,
+    ) -> Result<DriveConfig> {
+        let remote_path =
+            expand_remote_path(&self.remote_path, &PathVariables::current(user_id))?;
+        Ok(DriveConfig {
+            id: Uuid::new_v4().to_string(),
+            name: drive_name,
+            instance_url: self.instance_url.clone(),
+            remote_path,
+            credentials,
+            sync_path,
+            portable_sync_path: None,
+            icon_path: None,
+            raw_icon_path: None,
+            enabled: true,
+            user_id: user_id.to_string(),
+            sync_root_id: None,
+            ignore_patterns: self.ignore_patterns.clone(),
+            type_filter: self.type_filter.clone(),
+            sync_direction: self.sync_direction,
+            site_title: None,
+            label: None,
+            label_icon_path: None,
+            import_existing: false,
+            usn_checkpoint: None,
+            resume: Default::default(),
+            network: self.network.clone(),
+            compression: self.compression.clone(),
+            transfer_limits: self.transfer_limits.clone(),
+            guest_share: None,
+            kind: Default::default(),
+            last_state: None,
+            suspicious_changes: None,
+            extra: Default::default(),
+        })
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TemplateState {
+    templates: Vec<DriveTemplate>,
+}
+
+impl DriveManager {
+    fn get_templates_file(&self) -> PathBuf {
+        self.config_dir.join("templates.json")
+    }
+
+    fn load_user_templates(&self) -> Result<Vec<DriveTemplate>> {
+        let path = self.get_templates_file();
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&path).context("Failed to read drive templates")?;
+        let state: TemplateState =
+            serde_json::from_str(&content).context("Failed to parse drive templates")?;
+        Ok(state.templates)
+    }
+
+    fn persist_user_templates(&self, templates: Vec<DriveTemplate>) -> Result<()> {
+        let content = serde_json::to_string_pretty(&TemplateState { templates })
+            .context("Failed to serialize drive templates")?;
+        fs::write(self.get_templates_file(), content).context("Failed to write drive templates")
+    }
+
+    /// Templates from administrator policy followed by the user's own
+    pub fn list_drive_templates(&self) -> Result<Vec<DriveTemplate>> {
+        let mut templates: Vec<DriveTemplate> = Policy::get()
+            .drive_templates
+            .iter()
+            .cloned()
+            .map(|mut template| {
+                template.managed = true;
+                template
+            })
+            .collect();
+        templates.extend(self.load_user_templates()?);
+        Ok(templates)
+    }
+
+    pub fn get_drive_template(&self, template_id: &str) -> Result<DriveTemplate> {
+        self.list_drive_templates()?
+            .into_iter()
+            .find(|template| template.id == template_id)
+            .ok_or_else(|| anyhow::anyhow!("Drive template not found: {}", template_id))
+    }
+
+    /// Create or update a template. Returns it with its ID.
+    pub fn save_drive_template(&self, mut template: DriveTemplate) -> Result<DriveTemplate> {
+        if Policy::get()
+            .drive_templates
+            .iter()
+            .any(|managed| managed.id == template.id)
+        {
+            anyhow::bail!(
+                "Drive template {} is managed by administrator policy",
+                template.id
+            );
+        }
+        if template.id.is_empty() {
+            template.id = Uuid::new_v4().to_string();
+        }
+        template.managed = false;
+        // Catch unknown variables now rather than when the template is used
+        expand_remote_path(&template.remote_path, &PathVariables::current("user"))
+            .context("Invalid remote path in drive template")?;
+
+        let mut templates = self.load_user_templates()?;
+        match templates.iter_mut().find(|t| t.id == template.id) {
+            Some(existing) => *existing = template.clone(),
+            None => templates.push(template.clone()),
+        }
+        self.persist_user_templates(templates)?;
+        tracing::info!(target: "drive::manager", template_id = %template.id, "Saved drive template");
+        Ok(template)
+    }
+
+    /// Delete a template. Returns whether it existed.
+    pub fn delete_drive_template(&self, template_id: &str) -> Result<bool> {
+        let mut templates = self.load_user_templates()?;
+        let count = templates.len();
+        templates.retain(|t| t.id != template_id);
+        if templates.len() == count {
+            return Ok(false);
+        }
+        self.persist_user_templates(templates)?;
+        Ok(true)
+    }
+
+    /// Save the settings of an existing drive as a template
+    pub async fn save_drive_as_template(
+        &self,
+        drive_id: &str,
+        name: String,
+    ) -> Result<DriveTemplate> {
+        let mount = self
+            .get_drive(drive_id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", drive_id))?;
+        let config = mount.get_config().await;
+        self.save_drive_template(DriveTemplate::from_config(name, &config))
+    }
+
+    /// Add a drive from a template. `drive_name` defaults to the template
+    /// name.
+    pub async fn create_drive_from_template(
+        &self,
+        template_id: &str,
+        credentials: Credentials,
+        user_id: &str,
+        local_path: PathBuf,
+        drive_name: Option<String>,
+        import_existing: bool,
+    ) -> Result<String> {
+        let template = self.get_drive_template(template_id)?;
+        let drive_name = drive_name.unwrap_or_else(|| template.name.clone());
+        let mut config = template.to_config(drive_name, credentials, user_id, local_path)?;
+        config.import_existing = import_existing;
+
+        let id = self.add_drive(config).await?;
+        if template.label.is_some() {
+            if let Err(e) = self.set_drive_label(&id, template.label).await {
+                tracing::warn!(target: "drive::manager", drive_id = %id, error = %e, "Failed to apply template label");
+            }
+        }
+        tracing::info!(target: "drive::manager", drive_id = %id, template_id = %template_id, "Added drive from template");
+        Ok(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn template_fills_in_the_user() {
+        let template: DriveTemplate = serde_json::from_value(serde_json::json!({
+            "id": "team",
+            "name": "Team",
+            "instance_url": "https://cloud.example.com",
+            "remote_path": "cloudreve://my/Team/{user_id}",
+            "sync_direction": "one_way_upload",
+            "managed": true,
+        }))
+        .unwrap();
+        // Only policy loading marks templates as managed
+        assert!(!template.managed);
+
+        let config = template.to_config(
+            "Team".to_string(),
+            Credentials::default(),
+            "u1",
+            PathBuf::from("C:\\Team"),
+        )
+        .unwrap();
+        assert_eq!(config.remote_path, "cloudreve://my/Team/u1");
+        assert_eq!(config.user_id, "u1");
+        assert_eq!(config.sync_direction, SyncDirection::OneWayUpload);
+
+        let again = DriveTemplate::from_config("Team".to_string(), &config);
+        assert_eq!(again.instance_url, template.instance_url);
+        assert_eq!(again.sync_direction, template.sync_direction);
+    }
+}
@@ -0,0 +1,189 @@
+use super::DriveManager;
+use super::remote_transfer::resolve;
+use crate::drive::mounts::DriveConfig;
+use crate::policy::Policy;
+use crate::tasks::{TRASH_RESTORE_KIND, TaskKind, TaskPayload, TrashRestoreState};
+use anyhow::Result;
+use cloudreve_api::api::TrashApi;
+use cloudreve_api::models::explorer::{TrashEntry, file_type};
+use cloudreve_api::models::uri::CrUri;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Trash entries of a drive listed at most
+const MAX_TRASH_ENTRIES: usize = 2000;
+
+/// A file or folder deleted from a drive, still in the server trash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashItem {
+    /// URI of the entry in the trash
+    pub uri: String,
+    pub name: String,
+    pub is_folder: bool,
+    pub size: i64,
+    pub deleted_at: String,
+    /// When the server empties the entry from the trash
+    pub expires_at: Option<String>,
+    /// Local path the entry is restored to
+    pub local_path: PathBuf,
+}
+
+/// Local path a trash entry is restored to, `None` if it was deleted from
+/// outside the drive
+fn restore_target(config: &DriveConfig, entry: &TrashEntry) -> Option<PathBuf> {
+    let base = CrUri::new(&config.remote_path).ok()?;
+    let uri = CrUri::new(&entry.restore_uri).ok()?;
+    if uri.fs() != base.fs() {
+        return None;
+    }
+    resolve(config, &entry.restore_uri).ok()?.1
+}
+
+/// Nearest folder of `path` that exists locally, for placeholders of restored
+/// entries to be created in
+fn existing_ancestor(path: &Path, sync_path: &Path) -> PathBuf {
+    path.ancestors()
+        .skip(1)
+        .take_while(|ancestor| ancestor.starts_with(sync_path))
+        .find(|ancestor| ancestor.is_dir())
+        .unwrap_or(sync_path)
+        .to_path_buf()
+}
+
+impl DriveManager {
+    /// Search the server trash for entries deleted from a drive, only those
+    /// whose name contains `keyword` if given
+    pub async fn search_trash(
+        &self,
+        drive_id: &str,
+        keyword: Option<String>,
+    ) -> Result<Vec<TrashItem>> {
+        let mount = self
+            .get_drive(drive_id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", drive_id))?;
+        let config = mount.get_config().await;
+        if config.guest_share.is_some() {
+            anyhow::bail!("Guests of a share have no trash");
+        }
+
+        // Entries of other drives are dropped while paging, so they don't
+        // count against the limit
+        let entries = mount
+            .cr_client
+            .search_trash(keyword.as_deref(), MAX_TRASH_ENTRIES, &|entry| {
+                restore_target(&config, entry).is_some()
+            })
+            .await?;
+        Ok(entries
+            .into_iter()
+            .filter_map(|entry| {
+                let local_path = restore_target(&config, &entry)?;
+                Some(TrashItem {
+                    uri: entry.uri,
+                    name: entry.name,
+                    is_folder: entry.file_type == file_type::FOLDER,
+                    size: entry.size,
+                    deleted_at: entry.deleted_at,
+                    expires_at: entry.expected_collect_time,
+                    local_path,
+                })
+            })
+            .collect())
+    }
+
+    /// Restore entries of the server trash into a drive. Placeholders are
+    /// created once they are restored. Returns the ID of the task tracking
+    /// the restore.
+    pub async fn restore_from_trash(
+        &self,
+        drive_id: &str,
+        items: Vec<TrashItem>,
+    ) -> Result<String> {
+        if items.is_empty() {
+            anyhow::bail!("No files to restore");
+        }
+        if Policy::get().force_read_only {
+            anyhow::bail!("Remote changes are disabled by administrator policy");
+        }
+        let mount = self
+            .get_drive(drive_id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Drive not found: {}", drive_id))?;
+        if mount.is_guest_share() {
+            anyhow::bail!("Files of a share mounted as a guest can't be restored");
+        }
+        let sync_path = mount.get_sync_path().await;
+
+        let mut refresh: Vec<PathBuf> = Vec::new();
+        for item in &items {
+            if !item.local_path.starts_with(&sync_path) {
+                anyhow::bail!("{} is not in drive {}", item.local_path.display(), drive_id);
+            }
+            let folder = existing_ancestor(&item.local_path, &sync_path);
+            if !refresh.contains(&folder) {
+                refresh.push(folder);
+            }
+        }
+
+        let state = TrashRestoreState {
+            uris: items.iter().map(|item| item.uri.clone()).collect(),
+            refresh,
+            done: 0,
+        };
+        let total = state.uris.len() as i64;
+        let payload = TaskPayload::new(
+            TaskKind::Custom(TRASH_RESTORE_KIND),
+            items[0].local_path.clone(),
+        )
+        .with_totals(0, total)
+        .with_custom_state(serde_json::to_value(&state)?);
+
+        let task_id = mount.task_queue.enqueue(payload).await?;
+        tracing::info!(target: "drive::manager", drive_id = %drive_id, task_id = %task_id, count = total, "Queued trash restore");
+        Ok(task_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_restore_into_the_drive() {
+        let config: DriveConfig = serde_json::from_value(serde_json::json!({
+            "id": "team",
+            "name": "Team",
+            "instance_url": "https://cloud.example.com",
+            "remote_path": "cloudreve://my/Team",
+            "credentials": { "refresh_token": "", "refresh_expires": "" },
+            "sync_path": "C:\\Team",
+            "enabled": true,
+            "user_id": "user",
+        }))
+        .unwrap();
+        let entry = |restore_uri: &str| TrashEntry {
+            uri: "cloudreve://trash/a.txt".into(),
+            name: "a.txt".into(),
+            file_type: file_type::FILE,
+            size: 1,
+            deleted_at: "2026-10-01T00:00:00Z".into(),
+            restore_uri: restore_uri.into(),
+            expected_collect_time: None,
+        };
+
+        assert_eq!(
+            restore_target(&config, &entry("cloudreve://my/Team/docs/a.txt")),
+            Some(PathBuf::from("C:\\Team\\docs\\a.txt"))
+        );
+        assert_eq!(
+            restore_target(&config, &entry("cloudreve://my/Other/a.txt")),
+            None
+        );
+        assert_eq!(
+            restore_target(&config, &entry("cloudreve://share/Team/a.txt")),
+            None
+        );
+        assert_eq!(restore_target(&config, &entry("not a uri")), None);
+    }
+}
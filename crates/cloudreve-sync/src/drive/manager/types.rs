@@ -1,7 +1,14 @@
+use crate::drive::command_queue::CommandQueueStats;
+use crate::drive::label::DriveLabel;
+use crate::drive::lifecycle::MountState;
 use crate::drive::mounts::DriveConfig;
 use crate::inventory::TaskRecord;
 use crate::tasks::TaskProgress;
+use crate::uploader::{CompressionStats, IntegrityStats};
+use cloudreve_api::{AddressFamily, Feature};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct DriveState {
@@ -17,6 +24,13 @@ pub struct StatusSummary {
     pub active_tasks: Vec<TaskWithProgress>,
     /// Recently finished tasks (completed/failed/cancelled)
     pub finished_tasks: Vec<TaskRecord>,
+    /// Transfer integrity check counters
+    pub integrity: IntegrityStats,
+    /// Compressed upload counters
+    pub compression: CompressionStats,
+    /// Number of files quarantined after their sync failed repeatedly, by
+    /// drive ID (unfiltered). Drives without any are left out.
+    pub quarantined: HashMap<String, usize>,
 }
 
 /// A task record with optional live progress information
@@ -58,6 +72,8 @@ pub enum SyncStatus {
 pub struct DriveStatusUI {
     /// Drive display name
     pub name: String,
+    /// User-assigned color/emoji label
+    pub label: Option<DriveLabel>,
     /// Path to the raw (non-ICO) icon image
     pub raw_icon_path: Option<String>,
     /// Capacity summary (None if not available)
@@ -71,6 +87,12 @@ pub struct DriveStatusUI {
     pub sync_status: SyncStatus,
     /// Number of active (pending/running) tasks
     pub active_task_count: usize,
+    /// Estimated seconds until the running transfers are done
+    pub eta_seconds: Option<u64>,
+    /// Number of files quarantined after their sync failed repeatedly
+    pub quarantined_count: usize,
+    /// Number of files with a conflict waiting for the user
+    pub conflict_count: usize,
 }
 
 /// Drive information for the settings UI
@@ -85,18 +107,55 @@ pub struct DriveInfo {
     pub remote_path: String,
     /// Local sync path
     pub sync_path: String,
+    /// The missing sync folder found on another disk letter, offered to move
+    /// the drive to with
+    /// [`DriveManager::relocate_sync_root`](super::DriveManager::relocate_sync_root)
+    pub relocation: Option<String>,
     /// Path to the ICO icon
     pub icon_path: Option<String>,
     /// Path to the raw (non-ICO) icon image
     pub raw_icon_path: Option<String>,
-    /// Whether the drive is enabled
+    /// Whether the drive is mounted
     pub enabled: bool,
     /// User ID
     pub user_id: String,
+    /// User-assigned color/emoji label
+    pub label: Option<DriveLabel>,
+    /// Whether the drive is a public share mounted as a guest, read-only
+    pub guest_share: bool,
     /// Current drive status
     pub status: DriveInfoStatus,
+    /// Lifecycle state of the mount
+    pub state: MountState,
     /// Capacity summary (None if not available)
     pub capacity: Option<CapacitySummary>,
+    /// Server version and feature support (None until probed)
+    pub server: Option<ServerInfo>,
+    /// Address family of the connection to the server (None until connected)
+    pub address_family: Option<AddressFamily>,
+    /// Seconds the server's clock is ahead of the local one (None until
+    /// connected)
+    pub clock_skew_secs: Option<i64>,
+    /// Whether transfers currently go through the drive's LAN address
+    pub lan_active: bool,
+    /// Source address connections are made from, if the drive is bound to a
+    /// network adapter that was connected when it started
+    pub bind_address: Option<IpAddr>,
+    /// Remote files left out by the drive's type filter so far
+    pub type_filtered_count: usize,
+}
+
+/// Server compatibility information for the settings UI
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerInfo {
+    /// Server version, e.g. "4.10.1"
+    pub version: String,
+    /// Oldest server version the client was tested against
+    pub min_tested_version: String,
+    /// Whether the server is older than `min_tested_version`
+    pub outdated: bool,
+    /// Features disabled because the server does not support them
+    pub unavailable_features: Vec<Feature>,
 }
 
 /// Drive status for the settings UI
@@ -109,26 +168,24 @@ pub enum DriveInfoStatus {
     EventPushLost,
     /// Credentials have expired
     CredentialExpired,
+    /// The share a guest drive mounts was revoked or expired
+    ShareUnavailable,
+    /// The server is under maintenance, sync is paused until it is back
+    ServerMaintenance,
+    /// Registered but not mounted, nothing is synced
+    Unmounted,
+    /// The local sync folder was deleted or its disk is gone, sync is stopped
+    /// until it is relinked or recreated
+    SyncRootMissing,
+    /// Files were rewritten like ransomware would, sync is stopped until they
+    /// are restored or the alert is dismissed
+    SuspiciousChanges,
 }
 
-/// Format bytes into a human-readable string (e.g., "1.5 GB")
-pub fn format_bytes(bytes: i64) -> String {
-    const KB: f64 = 1024.0;
-    const MB: f64 = KB * 1024.0;
-    const GB: f64 = MB * 1024.0;
-    const TB: f64 = GB * 1024.0;
-
-    let bytes_f = bytes as f64;
-
-    if bytes_f >= TB {
-        format!("{:.1} TB", bytes_f / TB)
-    } else if bytes_f >= GB {
-        format!("{:.1} GB", bytes_f / GB)
-    } else if bytes_f >= MB {
-        format!("{:.1} MB", bytes_f / MB)
-    } else if bytes_f >= KB {
-        format!("{:.1} KB", bytes_f / KB)
-    } else {
-        format!("{} B", bytes)
-    }
+/// Depth of the manager's and each mount's command queue, for diagnostics
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandQueueReport {
+    pub manager: CommandQueueStats,
+    /// Keyed by drive ID
+    pub drives: HashMap<String, CommandQueueStats>,
 }
@@ -0,0 +1,57 @@
+//! Detection of a vanished sync folder
+//!
+//! When the folder of a drive is deleted or the disk holding it is unplugged,
+//! every file looks deleted locally, and syncing would remove them all from
+//! the cloud. Instead the mount checks for the folder before syncing or
+//! handling filesystem events, and once it is gone does neither and stays
+//! degraded. The user then points the drive at another folder or recreates
+//! it, see [`DriveManager::relink_sync_root`] and
+//! [`DriveManager::recreate_sync_root`]. A folder whose disk came back under
+//! another letter is found there and offered, see
+//! [`DriveManager::relocate_sync_root`].
+//!
+//! [`DriveManager::relink_sync_root`]: crate::drive::manager::DriveManager::relink_sync_root
+//! [`DriveManager::recreate_sync_root`]: crate::drive::manager::DriveManager::recreate_sync_root
+//! [`DriveManager::relocate_sync_root`]: crate::drive::manager::DriveManager::relocate_sync_root
+
+use crate::drive::mounts::Mount;
+use crate::utils::toast::send_general_text_toast;
+
+impl Mount {
+    /// Whether the local sync folder was found missing
+    pub async fn is_sync_root_missing(&self) -> bool {
+        self.status_flags.lock().await.is_sync_root_missing()
+    }
+
+    /// Check that the sync folder still exists. The first time it is found
+    /// missing the mount turns degraded and the user is notified. Returns
+    /// whether the folder is there.
+    pub(crate) async fn check_sync_root(&self) -> bool {
+        if self.is_sync_root_missing().await {
+            return false;
+        }
+        let (sync_path, name) = {
+            let config = self.config.read().await;
+            (config.sync_path.clone(), config.name.clone())
+        };
+        if sync_path.is_dir() {
+            return true;
+        }
+
+        let newly_missing = {
+            let mut flags = self.status_flags.lock().await;
+            let newly_missing = !flags.is_sync_root_missing();
+            flags.set_sync_root_missing(true);
+            newly_missing
+        };
+        if newly_missing {
+            tracing::warn!(target: "drive::missing_root", id = %self.id, path = %sync_path.display(), "Sync folder is missing, sync stopped");
+            self.refresh_state().await;
+            send_general_text_toast(
+                &t!("syncRootMissingTitle"),
+                &t!("syncRootMissingMessage", "drive" => name),
+            );
+        }
+        false
+    }
+}
@@ -1,10 +1,39 @@
+pub mod actors;
 pub mod callback;
+pub mod coalesce;
+pub mod command_queue;
 pub mod commands;
+pub mod credential_log;
+pub mod duplicate_files;
 pub mod event_blocker;
+pub mod failures;
+pub mod guest_share;
+pub mod history;
 pub mod ignore;
+pub mod initial_report;
+pub mod intake;
+pub mod journal;
+pub mod known_folders;
+pub mod label;
+pub mod lifecycle;
 pub mod manager;
+pub mod missing_root;
 pub mod mounts;
+pub mod offline;
+pub mod origin;
+pub mod permissions;
 pub mod placeholder;
+pub mod ransomware;
+pub mod remote_path;
 pub mod remote_events;
+pub mod remote_poll;
+pub mod repair;
+pub mod resume;
+pub mod scan;
+pub mod simulated;
+pub mod storm;
+pub mod summary;
 pub mod sync;
+pub mod type_filter;
+pub mod url_prefetch;
 pub mod utils;
@@ -2,34 +2,64 @@ use crate::cfapi::root::{
     Connection, HydrationType, PopulationType, SecurityId, Session, SyncRootId, SyncRootIdBuilder,
     SyncRootInfo,
 };
+use crate::cfapi::placeholder::{LocalFileInfo, OpenOptions, PlaceholderBlob, UpdateOptions};
 use crate::drive::callback::CallbackHandler;
+use crate::drive::coalesce::SyncCoalescer;
+use crate::drive::command_queue::{self, CommandReceiver, CommandSender};
 use crate::drive::commands::ManagerCommand;
 use crate::drive::commands::MountCommand;
 use crate::drive::event_blocker::EventBlocker;
+use crate::drive::origin::ChangeOrigins;
+use crate::drive::failures;
 use crate::drive::ignore::IgnoreMatcher;
-use crate::drive::sync::group_fs_events;
-use crate::drive::utils::recycle_bin_url;
-use crate::inventory::{DrivePropsUpdate, InventoryDb, TaskRecord};
-use crate::tasks::{TaskProgress, TaskQueue, TaskQueueConfig};
+use crate::drive::journal::UsnCheckpoint;
+use crate::drive::resume::{LocalReplay, ResumeMarkers};
+use crate::drive::scan::scan_downloaded_file;
+use crate::drive::ransomware::{RansomwareDetector, SuspiciousChanges};
+use crate::drive::storm::StormDetector;
+use crate::drive::type_filter::{TypeFilter, TypeFiltered, TypeMatcher};
+use crate::drive::label::{self, DriveLabel};
+use crate::drive::lifecycle::MountState;
+use crate::drive::manager::favicon;
+use crate::drive::offline::StaleFolders;
+use crate::drive::sync::{SyncMode, group_fs_events};
+use crate::drive::utils::{
+    key_is_under, notify_shell_change, path_key, recycle_bin_url, sync_root_keys,
+};
+use crate::idle;
+use crate::inventory::{
+    CredentialEventKind, CredentialSource, DrivePropsUpdate, InventoryDb, SyncOperation, TaskRecord,
+};
+use crate::tasks::{NamePolicy, TaskProgress, TaskQueue, TaskQueueConfig, TransferLimits, UrlPool};
+use crate::uploader::CompressionSettings;
+use crate::utils::correlation::{self, spawn};
+use crate::utils::network::resolve_bind_address;
 use crate::utils::toast;
+use crate::vfs::{CloudFilesVfs, VirtualFs};
 use ::serde::{Deserialize, Serialize};
 use anyhow::{Context, Result};
-use cloudreve_api::api::user::UserApi;
-use cloudreve_api::{Client, ClientConfig, models::user::Token};
+use cloudreve_api::models::explorer::FileResponse;
+use cloudreve_api::api::{SiteApi, user::UserApi};
+use cloudreve_api::capabilities::{ApiVersion, MIN_TESTED_VERSION, detect_api_version};
+use cloudreve_api::{Client, ClientConfig, IpStrategy, ShareSession, TlsPin, models::user::Token};
 use notify_debouncer_full::notify::{RecommendedWatcher, RecursiveMode};
 use notify_debouncer_full::{DebounceEventResult, Debouncer, RecommendedCache, new_debouncer};
 use sha2::{Digest, Sha256};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
+    net::IpAddr,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
 };
-use tokio::spawn;
-use tokio::sync::{Mutex, RwLock, mpsc};
+use tokio::sync::{Mutex, RwLock};
 use tokio::task::JoinHandle;
 use url::Url;
 use windows::Storage::Provider::StorageProviderSyncRootManager;
+use windows::Win32::UI::Shell::{SHCNE_UPDATEDIR, SHCNE_UPDATEITEM};
 /// Determines the direction of synchronization for a drive
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
 #[serde(rename_all = "snake_case")]
@@ -41,17 +71,68 @@ pub enum SyncDirection {
     OneWayUpload,
 }
 
+/// What a drive syncs with
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DriveKind {
+    /// A Cloudreve server, through a Cloud Files sync root
+    #[default]
+    Cloudreve,
+    /// A fabricated remote tree with synthetic transfers, conflicts and
+    /// errors, for UI development and demos. See [`crate::drive::simulated`].
+    Simulated,
+}
+
+/// Per-drive connection settings, applied when the drive starts
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct NetworkSettings {
+    /// Which address family to try first for hosts with both A and AAAA records
+    #[serde(default)]
+    pub ip_strategy: IpStrategy,
+    /// Host name to the addresses it should resolve to, bypassing DNS
+    #[serde(default)]
+    pub dns_overrides: BTreeMap<String, Vec<IpAddr>>,
+    /// Address of the same server on the local network (e.g.
+    /// `http://192.168.1.10:5212`). Chunk uploads and downloads served by the
+    /// server go through it while it passes health probes.
+    #[serde(default)]
+    pub lan_url: Option<String>,
+    /// Network adapter name (e.g. `Ethernet`) or source IP address the
+    /// drive's connections are made from. Any adapter is used when it is
+    /// unset or not connected.
+    #[serde(default)]
+    pub bind_interface: Option<String>,
+    /// Seconds between remote listings while the file events stream is
+    /// unavailable. Defaults to
+    /// [`DEFAULT_POLL_INTERVAL_SECS`](crate::drive::remote_poll::DEFAULT_POLL_INTERVAL_SECS).
+    #[serde(default)]
+    pub poll_interval_secs: Option<u64>,
+    /// Hashes the server certificate must match, taken when the drive was
+    /// added or set in its settings. Any valid certificate if empty.
+    #[serde(default)]
+    pub tls_pins: Vec<TlsPin>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct DriveConfig {
+    /// Stable UUID, assigned when the drive is added
+    #[serde(default)]
     pub id: String,
     pub name: String,
     pub instance_url: String,
     pub remote_path: String,
     pub credentials: Credentials,
     pub sync_path: PathBuf,
+    /// `sync_path` relative to a known folder like the user profile, written
+    /// on save and preferred when loading, see
+    /// [`known_folders`](crate::drive::known_folders)
+    #[serde(default)]
+    pub portable_sync_path: Option<String>,
     pub icon_path: Option<String>,
     /// Path to the raw (non-ICO) favicon image
     pub raw_icon_path: Option<String>,
+    /// Mounted on startup. Unset for drives only registered, see
+    /// [`DriveManager::register_drive`](crate::drive::manager::DriveManager::register_drive).
     pub enabled: bool,
     pub user_id: String,
 
@@ -62,14 +143,82 @@ pub struct DriveConfig {
     #[serde(default)]
     pub ignore_patterns: Vec<String>,
 
+    /// Remote files left out by type or size, see [`TypeFilter`]
+    #[serde(default)]
+    pub type_filter: TypeFilter,
+
     /// Sync direction: two-way (default) or one-way (PC to Cloud only)
     #[serde(default)]
     pub sync_direction: SyncDirection,
 
+    /// Site title last fetched from the server, used to detect branding changes
+    #[serde(default)]
+    pub site_title: Option<String>,
+
+    /// User-assigned color/emoji label
+    #[serde(default)]
+    pub label: Option<DriveLabel>,
+
+    /// Path to the badged ICO generated from the favicon and label color
+    #[serde(default)]
+    pub label_icon_path: Option<String>,
+
+    /// Merge mode: adopt files already present in the sync folder on the first
+    /// full sync instead of treating them as conflicting changes
+    #[serde(default)]
+    pub import_existing: bool,
+
+    /// Change journal position at last shutdown, used to replay offline changes
+    #[serde(default)]
+    pub usn_checkpoint: Option<UsnCheckpoint>,
+
+    /// Markers used to skip the full sync on startup when nothing changed
+    #[serde(default)]
+    pub resume: ResumeMarkers,
+
+    /// Address family preference and DNS overrides
+    #[serde(default)]
+    pub network: NetworkSettings,
+
+    /// Compressed uploads of text-like files
+    #[serde(default)]
+    pub compression: CompressionSettings,
+
+    /// Transfer concurrency limits
+    #[serde(default)]
+    pub transfer_limits: TransferLimits,
+
+    /// Public share mounted as a guest, without an account. Such a drive is
+    /// read-only, and `remote_path` is the root URI of the share.
+    #[serde(default)]
+    pub guest_share: Option<ShareSession>,
+
+    /// Server or simulation the drive syncs with
+    #[serde(default)]
+    pub kind: DriveKind,
+
+    /// Lifecycle state when the config was last saved. Anything but
+    /// `ShuttingDown` on startup means the previous run did not stop cleanly.
+    #[serde(default)]
+    pub last_state: Option<MountState>,
+
+    /// Files rewritten like ransomware would, which stopped the drive from
+    /// syncing until they are restored or the alert is dismissed
+    #[serde(default)]
+    pub suspicious_changes: Option<SuspiciousChanges>,
+
     #[serde(flatten)]
     pub extra: HashMap<String, serde_json::Value>,
 }
 
+impl DriveConfig {
+    /// Icon used for the sync root and local folder: the badged variant if a
+    /// label color is set, otherwise the plain favicon.
+    pub fn effective_icon_path(&self) -> Option<&String> {
+        self.label_icon_path.as_ref().or(self.icon_path.as_ref())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Credentials {
     pub access_token: Option<String>,
@@ -94,6 +243,9 @@ pub struct MountStatusFlags(u8);
 impl MountStatusFlags {
     const CREDENTIAL_EXPIRED: u8 = 1 << 0;
     const EVENT_PUSH_SUBSCRIBED: u8 = 1 << 1;
+    const SERVER_MAINTENANCE: u8 = 1 << 2;
+    const SYNC_ROOT_MISSING: u8 = 1 << 3;
+    const SUSPICIOUS_CHANGES: u8 = 1 << 4;
 
     /// Create a new MountStatusFlags with all flags cleared
     pub fn new() -> Self {
@@ -128,6 +280,48 @@ impl MountStatusFlags {
         }
     }
 
+    /// Check if the server is under maintenance
+    pub fn is_server_maintenance(&self) -> bool {
+        self.0 & Self::SERVER_MAINTENANCE != 0
+    }
+
+    /// Set the server maintenance flag
+    pub fn set_server_maintenance(&mut self, maintenance: bool) {
+        if maintenance {
+            self.0 |= Self::SERVER_MAINTENANCE;
+        } else {
+            self.0 &= !Self::SERVER_MAINTENANCE;
+        }
+    }
+
+    /// Check if the local sync folder is gone
+    pub fn is_sync_root_missing(&self) -> bool {
+        self.0 & Self::SYNC_ROOT_MISSING != 0
+    }
+
+    /// Set the sync root missing flag
+    pub fn set_sync_root_missing(&mut self, missing: bool) {
+        if missing {
+            self.0 |= Self::SYNC_ROOT_MISSING;
+        } else {
+            self.0 &= !Self::SYNC_ROOT_MISSING;
+        }
+    }
+
+    /// Check if sync stopped because files were rewritten like ransomware would
+    pub fn is_suspicious_changes(&self) -> bool {
+        self.0 & Self::SUSPICIOUS_CHANGES != 0
+    }
+
+    /// Set the suspicious changes flag
+    pub fn set_suspicious_changes(&mut self, suspicious: bool) {
+        if suspicious {
+            self.0 |= Self::SUSPICIOUS_CHANGES;
+        } else {
+            self.0 &= !Self::SUSPICIOUS_CHANGES;
+        }
+    }
+
     /// Get the raw bits value
     pub fn bits(&self) -> u8 {
         self.0
@@ -144,55 +338,126 @@ type FsWatcher = Debouncer<RecommendedWatcher, RecommendedCache>;
 pub struct Mount {
     pub config: Arc<RwLock<DriveConfig>>,
     connection: Option<Connection<CallbackHandler>>,
-    pub command_tx: mpsc::UnboundedSender<MountCommand>,
-    command_rx: Arc<Mutex<Option<mpsc::UnboundedReceiver<MountCommand>>>>,
+    pub command_tx: CommandSender<MountCommand>,
+    command_rx: Arc<Mutex<Option<CommandReceiver<MountCommand>>>>,
     processor_handle: Arc<Mutex<Option<JoinHandle<()>>>>,
     props_refresh_handle: Arc<Mutex<Option<JoinHandle<()>>>>,
-    remote_event_handle: Arc<Mutex<Option<JoinHandle<()>>>>,
-    manager_command_tx: mpsc::UnboundedSender<ManagerCommand>,
+    branding_refresh_handle: Arc<Mutex<Option<JoinHandle<()>>>>,
+    pub(crate) remote_event_handle: Arc<Mutex<Option<JoinHandle<()>>>>,
+    pub(crate) manager_command_tx: CommandSender<ManagerCommand>,
     fs_watcher: Mutex<Option<FsWatcher>>,
     pub(crate) sync_lock: Mutex<()>,
+    /// Imported files of the remote size waiting for a content comparison,
+    /// see [`Mount::run_content_checks`]
+    pub(crate) content_checks: Mutex<Vec<(PathBuf, FileResponse)>>,
+    /// Sync requests collected before they run, see [`Mount::queue_sync`]
+    pub(crate) sync_coalescer: Mutex<SyncCoalescer>,
+    /// Event storms and subtrees in bulk mode, see [`Mount::watch_storm`]
+    pub(crate) storm: Mutex<StormDetector>,
+    /// Recent files rewritten with content that looks encrypted, see
+    /// [`Mount::check_suspicious_changes`]
+    pub(crate) ransomware: Mutex<RansomwareDetector>,
+    /// Folders listed from the inventory while offline, see [`Mount::mark_stale`]
+    pub(crate) stale_folders: Mutex<StaleFolders>,
     pub cr_client: Arc<Client>,
     pub inventory: Arc<InventoryDb>,
     pub task_queue: Arc<TaskQueue>,
     pub id: String,
     pub event_blocker: EventBlocker,
+    /// Local writes made by the engine, whose events are not local changes
+    pub(crate) change_origins: ChangeOrigins,
+    /// Download URLs requested ahead of hydration, see
+    /// [`Mount::pooled_download_url`]
+    pub(crate) download_urls: UrlPool,
     /// Compiled glob matcher for ignore patterns
     pub ignore_matcher: IgnoreMatcher,
+    /// Compiled type filter, see [`Mount::excluded_by_type`]
+    pub(crate) type_matcher: std::sync::RwLock<TypeMatcher>,
+    /// Remote files the type filter left out so far
+    pub(crate) type_filtered: std::sync::Mutex<TypeFiltered>,
     /// Status flags for the mount (credential expired, event push subscribed, etc.)
-    status_flags: Mutex<MountStatusFlags>,
+    pub(crate) status_flags: Mutex<MountStatusFlags>,
+    /// Result of the startup journal replay, consumed by the first remote subscription
+    pub(crate) startup_replay: Mutex<Option<LocalReplay>>,
+    /// Since when a background full sync has been waiting for the user to be
+    /// idle or power saving to end
+    deferred_full_sync: Mutex<Option<Instant>>,
+    /// Placeholder operations on the sync root
+    pub(crate) vfs: Arc<dyn VirtualFs>,
+    /// Lifecycle state, changed through [`Mount::transition`]
+    pub(crate) state: Mutex<MountState>,
+    /// Whether the initial full sync has run or was not needed
+    pub(crate) initial_synced: AtomicBool,
+    /// Keys of the sync root, resolved once, see [`Mount::contains_path`]
+    sync_root_keys: std::sync::RwLock<Vec<String>>,
 }
 
 impl Mount {
     pub async fn new(
-        config: DriveConfig,
+        mut config: DriveConfig,
         inventory: Arc<InventoryDb>,
-        manager_command_tx: mpsc::UnboundedSender<ManagerCommand>,
+        manager_command_tx: CommandSender<ManagerCommand>,
     ) -> Self {
+        migrate_legacy_limits(&mut config);
+        if let Some(state) = config.last_state.filter(|s| *s != MountState::ShuttingDown) {
+            tracing::warn!(target: "drive::mounts", id = %config.id, last_state = ?state, "Drive did not shut down cleanly");
+        }
         // let task_config = TaskManagerConfig {
         //     max_workers: 4,
         //     completed_buffer_size: 100,
         // };
         // let task_manager = TaskManager::new(task_config);
-        let (command_tx, command_rx) = mpsc::unbounded_channel();
+        // Events and syncs dropped for a full queue are picked up by a full
+        // sync once it drains
+        let overflow_root = config.sync_path.clone();
+        let (command_tx, command_rx) =
+            command_queue::channel_with_overflow(move || MountCommand::Sync {
+                local_paths: vec![overflow_root.clone()],
+                mode: SyncMode::FullHierarchy,
+            });
         // initialize the client with the credentials
-        let client_config = ClientConfig::new(config.instance_url.clone())
+        let mut client_config = ClientConfig::new(config.instance_url.clone())
             .with_client_id(config.id.clone())
-            .with_user_agent(crate::USER_AGENT);
+            .with_device_user_agent(crate::USER_AGENT, &crate::device_name())
+            .with_ip_strategy(config.network.ip_strategy)
+            .with_tls_pins(config.network.tls_pins.clone());
+        for (host, addrs) in &config.network.dns_overrides {
+            client_config = client_config.with_dns_override(host, addrs.clone());
+        }
+        if let Some(binding) = config
+            .network
+            .bind_interface
+            .as_deref()
+            .filter(|binding| !binding.trim().is_empty())
+        {
+            match resolve_bind_address(binding, config.network.ip_strategy) {
+                Ok(addr) => {
+                    tracing::info!(target: "drive::mounts", id = %config.id, binding = %binding, addr = %addr, "Binding connections to network adapter");
+                    client_config = client_config.with_local_address(addr);
+                }
+                Err(e) => {
+                    tracing::warn!(target: "drive::mounts", id = %config.id, binding = %binding, error = %e, "Failed to bind to network adapter, using any adapter");
+                }
+            }
+        }
         let mut cr_client = Client::new(client_config);
-        let _ = cr_client
-            .set_tokens_with_expiry(&Token {
-                access_token: config.credentials.access_token.clone().unwrap_or_default(),
-                refresh_token: config.credentials.refresh_token.clone(),
-                access_expires: config
-                    .credentials
-                    .access_expires
-                    .clone()
-                    .unwrap_or_default(),
-                refresh_expires: config.credentials.refresh_expires.clone(),
-            })
-            .await;
-        let command_tx_clone: mpsc::UnboundedSender<MountCommand> = command_tx.clone();
+        if let Some(share) = &config.guest_share {
+            cr_client.set_share_session(share.clone());
+        } else {
+            let _ = cr_client
+                .set_tokens_with_expiry(&Token {
+                    access_token: config.credentials.access_token.clone().unwrap_or_default(),
+                    refresh_token: config.credentials.refresh_token.clone(),
+                    access_expires: config
+                        .credentials
+                        .access_expires
+                        .clone()
+                        .unwrap_or_default(),
+                    refresh_expires: config.credentials.refresh_expires.clone(),
+                })
+                .await;
+        }
+        let command_tx_clone: CommandSender<MountCommand> = command_tx.clone();
         // Setup hooks to update the credentials in the config
         cr_client.set_on_credential_refreshed(Arc::new(move |token| {
             let command_tx = command_tx_clone.clone();
@@ -204,6 +469,17 @@ impl Mount {
                 })
         }));
 
+        // Setup hook for failed token refreshes, recorded in the credential log
+        let command_tx_refresh_failed = command_tx.clone();
+        cr_client.set_on_refresh_failed(Arc::new(move |error| {
+            let command_tx = command_tx_refresh_failed.clone();
+            Box::pin(async move {
+                if let Err(e) = command_tx.send(MountCommand::RefreshFailed { error }) {
+                    tracing::error!(target: "drive::mounts", error = %e, "Failed to send RefreshFailed command");
+                }
+            })
+        }));
+
         // Setup hook for credential invalid events (401, 40020, 40089)
         let command_tx_invalid = command_tx.clone();
         cr_client.set_on_credential_invalid(Arc::new(move || {
@@ -215,6 +491,60 @@ impl Mount {
             })
         }));
 
+        // Setup hook for the server going down for maintenance (503)
+        let manager_tx_maintenance = manager_command_tx.clone();
+        let instance_url = config.instance_url.clone();
+        cr_client.set_on_maintenance(Arc::new(move || {
+            let manager_tx = manager_tx_maintenance.clone();
+            let instance_url = instance_url.clone();
+            Box::pin(async move {
+                let command = ManagerCommand::ServerMaintenance { instance_url };
+                if let Err(e) = manager_tx.send(command) {
+                    tracing::error!(target: "drive::mounts", error = %e, "Failed to send ServerMaintenance command");
+                }
+            })
+        }));
+
+        // Setup hook for the server unlinking this device (40090)
+        let manager_tx_wipe = manager_command_tx.clone();
+        let drive_id = config.id.clone();
+        cr_client.set_on_wipe_requested(Arc::new(move |request| {
+            let manager_tx = manager_tx_wipe.clone();
+            let drive_id = drive_id.clone();
+            Box::pin(async move {
+                let command = ManagerCommand::RemoteWipe { drive_id, request };
+                if let Err(e) = manager_tx.send(command) {
+                    tracing::error!(target: "drive::mounts", error = %e, "Failed to send RemoteWipe command");
+                }
+            })
+        }));
+
+        // Setup hook for the server certificate changing from its pin,
+        // reported once per start as every request fails the same way
+        let manager_tx_pin = manager_command_tx.clone();
+        let drive_id = config.id.clone();
+        let drive_name = config.name.clone();
+        let instance_url = config.instance_url.clone();
+        let pin_reported = Arc::new(AtomicBool::new(false));
+        cr_client.set_on_pin_mismatch(Arc::new(move |mismatch| {
+            let manager_tx = manager_tx_pin.clone();
+            let command = (!pin_reported.swap(true, Ordering::Relaxed)).then(|| {
+                ManagerCommand::PinMismatch {
+                    drive_id: drive_id.clone(),
+                    drive_name: drive_name.clone(),
+                    instance_url: instance_url.clone(),
+                    mismatch,
+                }
+            });
+            Box::pin(async move {
+                if let Some(command) = command {
+                    if let Err(e) = manager_tx.send(command) {
+                        tracing::error!(target: "drive::mounts", error = %e, "Failed to send PinMismatch command");
+                    }
+                }
+            })
+        }));
+
         let cr_client_arc = Arc::new(cr_client);
         let id = config.id.clone();
         let queue_config = resolve_task_queue_config(&config);
@@ -225,11 +555,15 @@ impl Mount {
             queue_config,
             config.sync_path.clone(),
             config.remote_path.clone(),
+            manager_command_tx.clone(),
         )
         .await;
+        // Shared with the download tasks, which replace file contents
+        let change_origins = task_queue.change_origins().clone();
 
         // Parse ignore patterns from config
         let sync_path = config.sync_path.clone();
+        let sync_root_keys = sync_root_keys(&sync_path);
         let ignore_matcher = match IgnoreMatcher::new(&config.ignore_patterns, sync_path.clone()) {
             Ok(matcher) => {
                 if !matcher.is_empty() {
@@ -252,6 +586,10 @@ impl Mount {
                 IgnoreMatcher::empty(sync_path)
             }
         };
+        let type_matcher = TypeMatcher::new(&config.type_filter).unwrap_or_else(|e| {
+            tracing::warn!(target: "drive::mounts", id = %id, error = %e, "Failed to parse type filter, syncing all files");
+            TypeMatcher::default()
+        });
 
         Self {
             config: Arc::new(RwLock::new(config)),
@@ -260,6 +598,7 @@ impl Mount {
             command_rx: Arc::new(tokio::sync::Mutex::new(Some(command_rx))),
             processor_handle: Arc::new(tokio::sync::Mutex::new(None)),
             props_refresh_handle: Arc::new(tokio::sync::Mutex::new(None)),
+            branding_refresh_handle: Arc::new(tokio::sync::Mutex::new(None)),
             remote_event_handle: Arc::new(tokio::sync::Mutex::new(None)),
             cr_client: cr_client_arc,
             inventory,
@@ -268,9 +607,24 @@ impl Mount {
             manager_command_tx,
             fs_watcher: Mutex::new(None),
             sync_lock: Mutex::new(()),
+            content_checks: Mutex::new(Vec::new()),
+            sync_coalescer: Mutex::new(SyncCoalescer::default()),
+            storm: Mutex::new(StormDetector::default()),
+            ransomware: Mutex::new(RansomwareDetector::default()),
+            stale_folders: Mutex::new(StaleFolders::default()),
             event_blocker: EventBlocker::new(),
+            change_origins,
+            download_urls: UrlPool::new(),
             ignore_matcher,
+            type_matcher: std::sync::RwLock::new(type_matcher),
+            type_filtered: std::sync::Mutex::new(TypeFiltered::default()),
             status_flags: Mutex::new(MountStatusFlags::new()),
+            startup_replay: Mutex::new(None),
+            deferred_full_sync: Mutex::new(None),
+            vfs: Arc::new(CloudFilesVfs),
+            state: Mutex::new(MountState::Initializing),
+            initial_synced: AtomicBool::new(false),
+            sync_root_keys: std::sync::RwLock::new(sync_root_keys),
         }
     }
 
@@ -283,6 +637,27 @@ impl Mount {
         self.config.read().await.sync_path.clone()
     }
 
+    /// Whether `path` is the sync root or inside it. Compares in memory
+    /// against the sync root resolved when the drive was mounted or started.
+    pub fn contains_path(&self, path: &Path) -> bool {
+        self.contains_path_key(&path_key(path))
+    }
+
+    /// Like [`Self::contains_path`], for a key made by [`path_key`]
+    pub fn contains_path_key(&self, key: &str) -> bool {
+        self.sync_root_keys
+            .read()
+            .unwrap()
+            .iter()
+            .any(|root| key_is_under(key, root))
+    }
+
+    /// Resolve the sync root again, e.g. once the folder exists
+    async fn refresh_sync_root_keys(&self) {
+        let keys = sync_root_keys(&self.get_sync_path().await);
+        *self.sync_root_keys.write().unwrap() = keys;
+    }
+
     /// Get a reference to the ignore matcher
     pub fn ignore_matcher(&self) -> &IgnoreMatcher {
         &self.ignore_matcher
@@ -331,6 +706,42 @@ impl Mount {
         self.config.write().await.sync_direction = direction;
     }
 
+    /// Get the connection settings for the drive
+    pub async fn get_network_settings(&self) -> NetworkSettings {
+        self.config.read().await.network.clone()
+    }
+
+    /// Set the connection settings for the drive. The HTTP client is built when
+    /// the drive starts, so changes other than the LAN address apply after the
+    /// drive is restarted.
+    pub async fn set_network_settings(&self, settings: NetworkSettings) {
+        self.config.write().await.network = settings;
+    }
+
+    /// Get the compressed upload settings for the drive
+    pub async fn get_compression_settings(&self) -> CompressionSettings {
+        self.config.read().await.compression.clone()
+    }
+
+    /// Set the compressed upload settings for the drive. The task queue reads
+    /// them when the drive starts, so changes apply after a restart.
+    pub async fn set_compression_settings(&self, settings: CompressionSettings) {
+        self.config.write().await.compression = settings;
+    }
+
+    /// Get the transfer concurrency limits of the drive
+    pub async fn get_transfer_limits(&self) -> TransferLimits {
+        self.config.read().await.transfer_limits.clone()
+    }
+
+    /// Set the transfer concurrency limits of the drive, applied to the
+    /// running task queue and hydrations right away
+    pub async fn set_transfer_limits(&self, limits: TransferLimits) {
+        let limits = limits.sanitized();
+        self.task_queue.set_limits(limits.clone());
+        self.config.write().await.transfer_limits = limits;
+    }
+
     /// Set the credential expired flag.
     /// If the flag changes from false to true, sends a toast notification to remind user to re-authorize.
     pub async fn set_credential_expired(&self, expired: bool) {
@@ -341,6 +752,7 @@ impl Mount {
             flags.set_credential_expired(expired);
             notify
         };
+        self.refresh_state().await;
 
         // Send toast outside of the lock to avoid potential deadlocks
         if should_notify {
@@ -349,11 +761,31 @@ impl Mount {
             let drive_id = config.id.clone();
             drop(config);
 
-            toast::send_token_expiry_toast(
-                &drive_id,
-                &t!("credentialExpiredTitle"),
-                &t!("credentialExpiredMessage", "drive" => drive_name),
-            );
+            if self.is_guest_share() {
+                toast::send_token_expiry_toast(
+                    &drive_id,
+                    &t!("shareUnavailableTitle"),
+                    &t!("shareUnavailableMessage", "drive" => drive_name),
+                );
+            } else {
+                self.record_credential_event(
+                    CredentialEventKind::Expired,
+                    CredentialSource::Server,
+                    "",
+                );
+                let command = ManagerCommand::ReauthRequired {
+                    drive_id: drive_id.clone(),
+                    drive_name: drive_name.clone(),
+                };
+                if let Err(e) = self.manager_command_tx.send(command) {
+                    tracing::error!(target: "drive::mounts", id = %drive_id, error = %e, "Failed to send ReauthRequired command");
+                }
+                toast::send_token_expiry_toast(
+                    &drive_id,
+                    &t!("credentialExpiredTitle"),
+                    &t!("credentialExpiredMessage", "drive" => drive_name),
+                );
+            }
         }
     }
 
@@ -363,6 +795,87 @@ impl Mount {
             .lock()
             .await
             .set_event_push_subscribed(subscribed);
+        self.refresh_state().await;
+    }
+
+    /// Run a full sync of the drive that was not requested by the user. It is
+    /// deferred while power saving is active or the user is not idle, see
+    /// [`idle::may_run_background_work`], and while the server is under
+    /// maintenance.
+    pub(crate) async fn request_background_full_sync(&self, sync_path: PathBuf) {
+        if !idle::may_run_background_work(Duration::ZERO) || self.is_server_maintenance().await {
+            tracing::debug!(target: "drive::mounts", id = %self.id, "Deferring full sync");
+            self.deferred_full_sync
+                .lock()
+                .await
+                .get_or_insert_with(Instant::now);
+            return;
+        }
+        self.send_full_sync(sync_path);
+    }
+
+    /// Run a deferred full sync if it may run now, or right away with `force`
+    pub(crate) async fn run_deferred_full_sync(&self, force: bool) {
+        // Resuming from maintenance runs its own full sync
+        if self.is_server_maintenance().await {
+            return;
+        }
+        {
+            let mut deferred = self.deferred_full_sync.lock().await;
+            let Some(since) = *deferred else {
+                return;
+            };
+            if !force && !idle::may_run_background_work(since.elapsed()) {
+                return;
+            }
+            *deferred = None;
+        }
+
+        let sync_path = self.config.read().await.sync_path.clone();
+        tracing::info!(target: "drive::mounts", id = %self.id, force, "Running deferred full sync");
+        self.send_full_sync(sync_path);
+    }
+
+    fn send_full_sync(&self, sync_path: PathBuf) {
+        if let Err(e) = self.command_tx.send(MountCommand::Sync {
+            local_paths: vec![sync_path],
+            mode: SyncMode::FullHierarchy,
+        }) {
+            tracing::error!(target: "drive::mounts", id = %self.id, error = %e, "Failed to send sync command");
+        }
+    }
+
+    /// Whether the server of the drive is under maintenance
+    pub async fn is_server_maintenance(&self) -> bool {
+        self.status_flags.lock().await.is_server_maintenance()
+    }
+
+    /// Pause sync while the server is under maintenance. Once it is back, a
+    /// full sync reconciles whatever changed in the meantime.
+    pub(crate) async fn set_server_maintenance(&self, maintenance: bool) {
+        let changed = {
+            let mut flags = self.status_flags.lock().await;
+            let changed = flags.is_server_maintenance() != maintenance;
+            flags.set_server_maintenance(maintenance);
+            changed
+        };
+        if !changed {
+            return;
+        }
+        self.refresh_state().await;
+        if !maintenance {
+            self.deferred_full_sync.lock().await.take();
+            let sync_path = self.config.read().await.sync_path.clone();
+            self.send_full_sync(sync_path);
+        }
+    }
+
+    /// Apply a change of the power saving or presentation state to the
+    /// transfer limits, the upload hold and the mount state
+    pub(crate) async fn apply_throttling(&self) {
+        self.task_queue.refresh_limits();
+        self.refresh_state().await;
+        self.apply_upload_hold(self.state().await).await;
     }
 
     pub fn task_queue(&self) -> Arc<TaskQueue> {
@@ -378,16 +891,31 @@ impl Mount {
     }
 
     pub async fn start(&mut self) -> Result<()> {
+        if self.is_simulated().await {
+            return self.start_simulated().await;
+        }
+
         if !StorageProviderSyncRootManager::IsSupported()
             .context("Cloud Filter API is not supported")?
         {
             return Err(anyhow::anyhow!("Cloud Filter API is not supported"));
         }
 
+        self.hold_pending_suspicious_changes().await;
+
+        // Connecting would fail, and the journal replay would see every file
+        // as deleted. The mount stays degraded until the folder is recovered.
+        if !self.check_sync_root().await {
+            return Ok(());
+        }
+        // The folder may not have existed when the drive was mounted
+        self.refresh_sync_root_keys().await;
+
         let mut write_guard = self.config.write().await;
 
         // if sync root id is not set, generate one
-        if write_guard.sync_root_id.is_none() {
+        let generated = write_guard.sync_root_id.is_none();
+        if generated {
             write_guard.sync_root_id = Some(
                 generate_sync_root_id(
                     &write_guard.instance_url,
@@ -399,6 +927,14 @@ impl Mount {
             );
         }
 
+        // The ID embeds the Windows user SID, which changes with roaming profiles or
+        // account migrations; re-key the registration for the current user if needed
+        if revalidate_sync_root_id(&self.id, &mut write_guard)? || generated {
+            if let Err(e) = self.manager_command_tx.send(ManagerCommand::PersistConfig) {
+                tracing::error!(target: "drive::mounts", id=%self.id, error=%e, "Failed to send PersistConfig command");
+            }
+        }
+
         drop(write_guard);
         let config = self.config.read().await;
 
@@ -406,12 +942,13 @@ impl Mount {
 
         // Register sync root if not registered
         if !sync_root_id.is_registered()? {
+            ensure_path_not_claimed(sync_root_id, &config.sync_path)?;
             tracing::info!(target: "drive::mounts", id = %self.id, "Registering sync root");
             let mut sync_root_info = SyncRootInfo::default();
             sync_root_info.set_display_name(config.name.clone());
             sync_root_info.set_hydration_type(HydrationType::Full);
             sync_root_info.set_population_type(PopulationType::Full);
-            if let Some(icon_path) = config.icon_path.as_ref() {
+            if let Some(icon_path) = config.effective_icon_path() {
                 sync_root_info.set_icon(format!("{},0", icon_path));
             }
             sync_root_info.set_version("1.0.0");
@@ -423,6 +960,7 @@ impl Mount {
                 .context("failed to set sync root path")?;
             sync_root_info.add_custom_state(t!("shared").as_ref(), 1)?;
             sync_root_info.add_custom_state(t!("accessible").as_ref(), 2)?;
+            sync_root_info.add_custom_state(t!("owner").as_ref(), 3)?;
             sync_root_id
                 .register(sync_root_info)
                 .context("failed to register sync root")?;
@@ -433,6 +971,13 @@ impl Mount {
             tracing::warn!(target: "drive::mounts", id = %self.id, error = %e, "Failed to add sync root to search indexer");
         }
 
+        // desktop.ini is lost when the folder is moved back or recreated
+        if let Some(icon_path) = config.label_icon_path.as_deref() {
+            if let Err(e) = label::apply_folder_icon(&config.sync_path, icon_path) {
+                tracing::warn!(target: "drive::mounts", id = %self.id, error = %e, "Failed to apply folder icon");
+            }
+        }
+
         tracing::info!(target: "drive::mounts",sync_path = %config.sync_path.display(), id = %self.id, "Connecting to sync root");
         let connection = Session::new()
             .connect(
@@ -441,12 +986,16 @@ impl Mount {
                     self.command_tx.clone(),
                     self.id.clone(),
                     self.inventory.clone(),
+                    self.change_origins.clone(),
                 ),
             )
             .context("failed to connect to sync root")?;
 
         self.connection = Some(connection);
+        drop(config);
         self.start_fs_watcher().await?;
+        let replay = self.replay_journal().await;
+        *self.startup_replay.lock().await = Some(replay);
         Ok(())
     }
 
@@ -485,9 +1034,10 @@ impl Mount {
         let mut command_rx_guard = self.command_rx.lock().await;
         if let Some(command_rx) = command_rx_guard.take() {
             let mount_id = self.id.to_string();
-            let handle = tokio::spawn(async move {
+            // Each command runs with the correlation id it was sent with
+            let handle = tokio::spawn(correlation::scope(None, async move {
                 Self::process_commands(s, mount_id, command_rx).await;
-            });
+            }));
             *self.processor_handle.lock().await = Some(handle);
         }
     }
@@ -503,7 +1053,7 @@ impl Mount {
     async fn process_commands(
         s: Arc<Self>,
         mount_id: String,
-        mut command_rx: mpsc::UnboundedReceiver<MountCommand>,
+        mut command_rx: CommandReceiver<MountCommand>,
     ) {
         tracing::info!(target: "drive::mounts", id = %mount_id, "Command processor started");
 
@@ -519,24 +1069,27 @@ impl Mount {
                     let s_clone = s.clone();
                     let mount_id_clone = mount_id.clone();
                     spawn(async move {
-                        let result = s_clone.rename(source, target).await;
+                        let result = s_clone.rename(source.clone(), target).await;
                         if let Err(e) = result {
                             tracing::error!(target: "drive::mounts", id = %mount_id_clone, error = %e, "Failed to rename");
+                            failures::record_failure(
+                                &s_clone.inventory,
+                                &s_clone.manager_command_tx,
+                                &mount_id_clone,
+                                &source,
+                                SyncOperation::Rename,
+                                &e,
+                            );
                             let _ = response.send(Err(e));
                             return;
                         }
+                        failures::clear_failure(&s_clone.inventory, &source, SyncOperation::Rename);
                         tracing::debug!(target: "drive::mounts", id = %mount_id_clone, result = ?result, "Renamed");
                         let _ = response.send(result);
                     });
                 }
                 MountCommand::Sync { mode, local_paths } => {
-                    let s_clone = s.clone();
-                    let mount_id_clone = mount_id.clone();
-                    spawn(async move {
-                        if let Err(e) = s_clone.sync_paths(local_paths, mode).await {
-                            tracing::error!(target: "drive::mounts", id = %mount_id_clone, error = %e, "Failed to sync paths");
-                        }
-                    });
+                    s.queue_sync(local_paths, mode).await;
                 }
                 MountCommand::FetchPlaceholders { path, response } => {
                     let s_clone = s.clone();
@@ -558,6 +1111,11 @@ impl Mount {
                     config.credentials.refresh_token = credentials.refresh_token;
                     config.credentials.refresh_expires = credentials.refresh_expires;
                     config.credentials.access_expires = Some(credentials.access_expires);
+                    s.record_credential_event(
+                        CredentialEventKind::Refreshed,
+                        CredentialSource::AutoRefresh,
+                        "",
+                    );
 
                     // Clear credential expired flag since we got new credentials
                     s.set_credential_expired(false).await;
@@ -569,6 +1127,20 @@ impl Mount {
                     }
                     drop(config);
                 }
+                MountCommand::RefreshFailed { error } => {
+                    s.credential_refresh_failed(&error).await;
+                }
+                MountCommand::CredentialInvalid if s.is_guest_share() => {
+                    // A single file removed from the share fails the same
+                    // way, so check the share itself before giving up on it
+                    let s_clone = s.clone();
+                    let mount_id_clone = mount_id.clone();
+                    spawn(async move {
+                        if let Err(e) = s_clone.check_guest_share().await {
+                            tracing::warn!(target: "drive::mounts", id = %mount_id_clone, error = %e, "Failed to check share");
+                        }
+                    });
+                }
                 MountCommand::CredentialInvalid => {
                     tracing::warn!(target: "drive::mounts", id = %mount_id, "Credential invalid, marking as expired");
                     s.set_credential_expired(true).await;
@@ -577,12 +1149,15 @@ impl Mount {
                     path,
                     ticket,
                     range,
+                    blob,
                     response,
                 } => {
                     let s_clone = s.clone();
                     let mount_id_clone = mount_id.clone();
                     spawn(async move {
-                        let result = s_clone.fetch_data(path, ticket, range).await;
+                        let identity = PlaceholderBlob::decode(&blob);
+                        let range_end = range.end;
+                        let result = s_clone.fetch_data(path.clone(), ticket, range, &identity).await;
                         if let Err(e) = result {
                             tracing::error!(target: "drive::mounts", id = %mount_id_clone, error = ?e, "Failed to fetch data");
                             let _ = response.send(Err(e));
@@ -590,6 +1165,37 @@ impl Mount {
                         }
                         tracing::debug!(target: "drive::mounts", id = %mount_id_clone, result = ?result, "Fetched data");
                         let _ = response.send(result);
+
+                        // Both hit the disk, keep them off the runtime
+                        let blocking_path = path.clone();
+                        let hydrated = tokio::task::spawn_blocking(move || {
+                            // Migrate legacy blobs to the current format once the file is accessed
+                            if identity.is_legacy() {
+                                migrate_placeholder_blob(&blocking_path, &identity);
+                            }
+                            // Reading the tail of a file fetches its end too,
+                            // only scan once all of it is on disk
+                            LocalFileInfo::from_path(&blocking_path).is_ok_and(|info| {
+                                info.exists
+                                    && info.file_size.is_some_and(|size| range_end >= size)
+                                    && !info.partial_on_disk()
+                            })
+                        })
+                        .await
+                        .unwrap_or(false);
+                        if !hydrated {
+                            return;
+                        }
+                        if let Err(e) = scan_downloaded_file(
+                            &mount_id_clone,
+                            &path,
+                            &s_clone.inventory,
+                            &s_clone.manager_command_tx,
+                        )
+                        .await
+                        {
+                            tracing::warn!(target: "drive::mounts", id = %mount_id_clone, path = %path.display(), error = %e, "Failed to scan downloaded file");
+                        }
                     });
                 }
                 MountCommand::ProcessFsEvents { events } => {
@@ -618,12 +1224,19 @@ impl Mount {
         tracing::info!(target: "drive::mounts", id = %mount_id, "Command processor stopped");
     }
 
-    pub async fn delete(&self) -> Result<()> {
+    /// Shut down and disconnect the sync root, leaving it registered and the
+    /// inventory in place
+    pub async fn unmount(&self) -> Result<()> {
         self.shutdown().await;
         if let Some(ref connection) = self.connection {
             connection.disconnect().context("faield to disconnect sync root")?;
         }
         self.task_queue.shutdown().await;
+        Ok(())
+    }
+
+    pub async fn delete(&self) -> Result<()> {
+        self.unmount().await?;
         if let Some(sync_root_id) = self.config.read().await.sync_root_id.as_ref() {
             if let Err(e) = sync_root_id.unregister() {
                 tracing::warn!(target: "drive::mounts", id=%self.id, error=%e, "Failed to unregister sync root");
@@ -639,6 +1252,10 @@ impl Mount {
 
     pub async fn shutdown(&self) {
         tracing::info!(target: "drive::mounts", id=%self.id, "Shutting down Mount");
+        self.transition(MountState::ShuttingDown).await;
+
+        // Remember where to resume replaying local changes from on next start
+        self.update_journal_checkpoint().await;
 
         // Stop the remote event listener
         if let Some(handle) = self.remote_event_handle.lock().await.take() {
@@ -665,6 +1282,12 @@ impl Mount {
             tracing::debug!(target: "drive::mounts", id=%self.id, "Stopping props refresh task");
             handle.abort();
         }
+
+        // Stop the branding refresh task
+        if let Some(handle) = self.branding_refresh_handle.lock().await.take() {
+            tracing::debug!(target: "drive::mounts", id=%self.id, "Stopping branding refresh task");
+            handle.abort();
+        }
         // self.queue.shutdown().await;
     }
 
@@ -686,6 +1309,8 @@ impl Mount {
             // Refresh interval: 5 minutes
             let refresh_interval = Duration::from_secs(300);
 
+            mount.refresh_lan_route().await;
+
             // If no props exist, refresh immediately
             if should_refresh_immediately {
                 tracing::info!(target: "drive::mounts", id=%mount_id, "No drive props found, triggering immediate refresh");
@@ -701,6 +1326,7 @@ impl Mount {
                 if let Err(e) = mount.refresh_drive_props().await {
                     tracing::error!(target: "drive::mounts", id=%mount_id, error=%e, "Failed to refresh drive props");
                 }
+                mount.refresh_lan_route().await;
             }
         });
 
@@ -711,6 +1337,11 @@ impl Mount {
     pub async fn refresh_drive_props(&self) -> Result<()> {
         tracing::debug!(target: "drive::mounts", id=%self.id, "Refreshing drive props");
 
+        // Guests have no account to fetch props of, but the share may be gone
+        if self.is_guest_share() {
+            return self.check_guest_share().await;
+        }
+
         let mut update = DrivePropsUpdate::default();
 
         // Fetch user capacity
@@ -746,6 +1377,256 @@ impl Mount {
         Ok(())
     }
 
+    /// Spawn the periodic branding refresh task
+    pub async fn spawn_branding_refresh_task(self: &Arc<Self>) {
+        let mount = self.clone();
+        let mount_id = self.id.clone();
+
+        let handle = spawn(async move {
+            // Refresh interval: 6 hours
+            let refresh_interval = Duration::from_secs(6 * 3600);
+
+            loop {
+                if let Err(e) = mount.refresh_branding().await {
+                    tracing::warn!(target: "drive::mounts", id=%mount_id, error=%e, "Failed to refresh branding");
+                }
+                if let Err(e) = mount.refresh_capabilities().await {
+                    tracing::warn!(target: "drive::mounts", id=%mount_id, error=%e, "Failed to probe server capabilities");
+                }
+                if let Err(e) = mount.refresh_name_policy().await {
+                    tracing::warn!(target: "drive::mounts", id=%mount_id, error=%e, "Failed to fetch naming policy");
+                }
+                tokio::time::sleep(refresh_interval).await;
+            }
+        });
+
+        *self.branding_refresh_handle.lock().await = Some(handle);
+    }
+
+    /// Re-fetch the site title and favicon, and update the sync root registration
+    /// in place if the server branding has changed.
+    pub async fn refresh_branding(&self) -> Result<()> {
+        tracing::debug!(target: "drive::mounts", id=%self.id, "Refreshing branding");
+
+        let instance_url = self.config.read().await.instance_url.clone();
+
+        let site_title = match self.cr_client.get_site_config("basic").await {
+            Ok(site_config) => site_config.title,
+            Err(e) => {
+                tracing::warn!(target: "drive::mounts", id=%self.id, error=%e, "Failed to fetch site config");
+                None
+            }
+        };
+
+        // Cached icon files are keyed by host, so this overwrites them in place
+        // and only their content tells whether the icon changed
+        let old_icon = favicon::icon_digest(self.config.read().await.icon_path.as_deref());
+        let favicon = match favicon::fetch_and_save_favicon(&instance_url).await {
+            Ok(result) => Some(result),
+            Err(e) => {
+                tracing::warn!(target: "drive::mounts", id=%self.id, error=%e, "Failed to re-fetch favicon");
+                None
+            }
+        };
+
+        let (config, changed) = {
+            let mut config = self.config.write().await;
+            let mut changed = false;
+            if let Some(title) = site_title {
+                if config.site_title.as_deref() != Some(title.as_str()) {
+                    // Only follow the server title if the user has not renamed the drive
+                    if config.site_title.as_deref() == Some(config.name.as_str()) {
+                        tracing::info!(target: "drive::mounts", id=%self.id, old = %config.name, new = %title, "Site title changed, renaming drive");
+                        config.name = title.clone();
+                    }
+                    config.site_title = Some(title);
+                    changed = true;
+                }
+            }
+            if let Some(favicon) = favicon {
+                let label_icon_path =
+                    label::resolve_label_icon(Some(&favicon.ico_path), config.label.as_ref());
+                if config.icon_path.as_deref() != Some(favicon.ico_path.as_str())
+                    || config.raw_icon_path.as_deref() != Some(favicon.raw_path.as_str())
+                    || config.label_icon_path != label_icon_path
+                {
+                    config.icon_path = Some(favicon.ico_path);
+                    config.raw_icon_path = Some(favicon.raw_path);
+                    config.label_icon_path = label_icon_path;
+                    changed = true;
+                }
+            }
+            (config.clone(), changed)
+        };
+
+        let icon_rewritten = favicon::icon_digest(config.icon_path.as_deref()) != old_icon;
+        self.update_sync_root_branding(&config, icon_rewritten)?;
+
+        // The icon files are overwritten in place, so unchanged paths need no save
+        if !changed {
+            return Ok(());
+        }
+        if let Err(e) = self.manager_command_tx.send(ManagerCommand::PersistConfig) {
+            tracing::error!(target: "drive::mounts", id=%self.id, error=%e, "Failed to send PersistConfig command");
+        }
+
+        Ok(())
+    }
+
+    /// Probe the LAN address configured for the drive and route bulk transfers
+    /// through it while it is healthy, falling back to the public URL otherwise.
+    pub async fn refresh_lan_route(&self) {
+        let (lan_url, pinned) = {
+            let config = self.config.read().await;
+            (
+                config.network.lan_url.clone(),
+                !config.network.tls_pins.is_empty(),
+            )
+        };
+        let Some(lan_url) = lan_url.filter(|url| !url.trim().is_empty()) else {
+            self.cr_client.clear_lan_base_url().await;
+            return;
+        };
+        if pinned {
+            tracing::warn!(target: "drive::mounts", id=%self.id, lan_url=%lan_url, "Server certificate is pinned, not routing transfers through the LAN address");
+            self.cr_client.clear_lan_base_url().await;
+            return;
+        }
+
+        let was_active = self.cr_client.lan_base_url().await.is_some();
+        match self.cr_client.probe_lan_base_url(lan_url.trim()).await {
+            Ok(true) if !was_active => {
+                tracing::info!(target: "drive::mounts", id=%self.id, lan_url=%lan_url, "LAN address reachable, routing transfers through it");
+            }
+            Ok(true) => {}
+            Ok(false) => {
+                tracing::warn!(target: "drive::mounts", id=%self.id, lan_url=%lan_url, "LAN address answers for a different server, ignoring it");
+            }
+            Err(e) => {
+                tracing::debug!(target: "drive::mounts", id=%self.id, lan_url=%lan_url, error=%e, "LAN address unreachable, using the public URL");
+            }
+        }
+    }
+
+    /// Probe the server version and update the features the client may use
+    pub async fn refresh_capabilities(&self) -> Result<()> {
+        let capabilities = match self.cr_client.probe_capabilities().await {
+            Ok(capabilities) => capabilities,
+            Err(e) => {
+                // Tell legacy servers apart from unreachable ones in the log
+                if let Ok((ApiVersion::V3, capabilities)) =
+                    detect_api_version(self.cr_client.config()).await
+                {
+                    tracing::warn!(target: "drive::mounts", id=%self.id, version=%capabilities.version, "Server runs Cloudreve V3, which can't be synced");
+                }
+                return Err(e).context("failed to probe server capabilities");
+            }
+        };
+        let unavailable = capabilities.unavailable_features();
+        if capabilities.is_below_tested() {
+            tracing::warn!(target: "drive::mounts", id=%self.id, version=%capabilities.version, tested=%MIN_TESTED_VERSION, unavailable=?unavailable, "Server is older than tested versions");
+        } else {
+            tracing::debug!(target: "drive::mounts", id=%self.id, version=%capabilities.version, pro=capabilities.pro, "Probed server capabilities");
+        }
+        Ok(())
+    }
+
+    /// Fetch the names the server accepts, so uploads of rejected names fail
+    /// before transferring anything
+    pub async fn refresh_name_policy(&self) -> Result<()> {
+        let site_config = self
+            .cr_client
+            .get_site_config("explorer")
+            .await
+            .context("failed to fetch explorer site config")?;
+        self.task_queue
+            .set_name_policy(NamePolicy::from_site_config(&site_config));
+        Ok(())
+    }
+
+    /// Update display name and icon of the registered sync root, then ask Explorer
+    /// to refresh the navigation pane node. `icon_rewritten` tells that the icon
+    /// file got other content at the same path.
+    fn update_sync_root_branding(&self, config: &DriveConfig, icon_rewritten: bool) -> Result<()> {
+        let Some(sync_root_id) = config.sync_root_id.as_ref() else {
+            return Ok(());
+        };
+        if !sync_root_id.is_registered()? {
+            return Ok(());
+        }
+
+        let mut info = sync_root_id.info().context("failed to get sync root info")?;
+        let icon = config
+            .effective_icon_path()
+            .map(|icon_path| format!("{},0", icon_path));
+        let name_changed = info.display_name().to_string_lossy() != config.name;
+        let icon_changed = icon
+            .as_ref()
+            .map(|icon| icon_rewritten || info.icon().to_string_lossy() != icon.as_str())
+            .unwrap_or(false);
+        if !name_changed && !icon_changed {
+            return Ok(());
+        }
+
+        info.set_display_name(config.name.clone());
+        if let Some(icon) = icon {
+            info.set_icon(icon);
+        }
+        // Registering an existing ID updates the registration in place
+        sync_root_id
+            .register(info)
+            .context("failed to update sync root registration")?;
+
+        _ = notify_shell_change(&config.sync_path, SHCNE_UPDATEDIR);
+        _ = notify_shell_change(&config.sync_path, SHCNE_UPDATEITEM);
+
+        tracing::info!(target: "drive::mounts", id=%self.id, name=%config.name, "Sync root branding updated");
+        Ok(())
+    }
+
+    /// Rename the drive, in Explorer as well
+    pub async fn set_name(&self, name: String) -> Result<()> {
+        let config = {
+            let mut config = self.config.write().await;
+            config.name = name;
+            config.clone()
+        };
+        self.update_sync_root_branding(&config, false)?;
+
+        tracing::info!(target: "drive::mounts", id=%self.id, name=%config.name, "Drive renamed");
+        Ok(())
+    }
+
+    /// Set the color/emoji label of the drive.
+    ///
+    /// Regenerates the badged icon, rewrites `desktop.ini` in the sync root and
+    /// updates the sync root registration so Explorer picks up the new icon.
+    pub async fn set_label(&self, label: Option<DriveLabel>) -> Result<()> {
+        let label = label.filter(|l| !l.is_empty());
+        if let Some(color) = label.as_ref().and_then(|l| l.color.as_deref()) {
+            if label::parse_hex_color(color).is_none() {
+                anyhow::bail!("Invalid label color: {}", color);
+            }
+        }
+
+        let config = {
+            let mut config = self.config.write().await;
+            config.label_icon_path =
+                label::resolve_label_icon(config.icon_path.as_deref(), label.as_ref());
+            config.label = label;
+            config.clone()
+        };
+
+        match config.label_icon_path.as_ref() {
+            Some(icon_path) => label::apply_folder_icon(&config.sync_path, icon_path)?,
+            None => label::remove_folder_icon(&config.sync_path)?,
+        }
+        self.update_sync_root_branding(&config, false)?;
+
+        tracing::info!(target: "drive::mounts", id=%self.id, label=?config.label, "Drive label updated");
+        Ok(())
+    }
+
     /// Get cached drive props from the database
     pub fn get_drive_props(&self) -> Result<Option<crate::inventory::DriveProps>> {
         self.inventory
@@ -754,6 +1635,77 @@ impl Mount {
     }
 }
 
+/// Re-key the sync root ID if it was created for a different Windows user SID.
+///
+/// The stale registration is removed so the folder can be registered again under the
+/// current user. Returns whether the ID changed.
+fn revalidate_sync_root_id(drive_id: &str, config: &mut DriveConfig) -> Result<bool> {
+    let Some(sync_root_id) = config.sync_root_id.as_ref() else {
+        return Ok(false);
+    };
+
+    let current_sid = SecurityId::current_user().context("failed to get current user SID")?;
+    let Some(stored_sid) = sync_root_id.security_id() else {
+        // Not an ID this client generated, start over with a new one
+        tracing::warn!(target: "drive::mounts", id = %drive_id, sync_root_id = %sync_root_id.to_os_string().to_string_lossy(), "Sync root ID is malformed, generating a new one");
+        config.sync_root_id = Some(
+            generate_sync_root_id(
+                &config.instance_url,
+                &config.name,
+                &config.user_id,
+                &config.sync_path,
+            )
+            .context("failed to generate sync root id")?,
+        );
+        return Ok(true);
+    };
+    if stored_sid == current_sid {
+        return Ok(false);
+    }
+
+    tracing::warn!(
+        target: "drive::mounts",
+        id = %drive_id,
+        stored_sid = %stored_sid.to_os_string().to_string_lossy(),
+        current_sid = %current_sid.to_os_string().to_string_lossy(),
+        "Sync root ID was created for another user SID, migrating registration"
+    );
+
+    if sync_root_id.is_registered().unwrap_or(false) {
+        if let Err(e) = sync_root_id.unregister() {
+            tracing::warn!(target: "drive::mounts", id = %drive_id, error = %e, "Failed to unregister stale sync root");
+        }
+    }
+
+    config.sync_root_id = sync_root_id.with_security_id(&current_sid);
+    Ok(true)
+}
+
+/// Fail if `sync_path` is already a sync root registered by another Windows user.
+fn ensure_path_not_claimed(sync_root_id: &SyncRootId, sync_path: &Path) -> Result<()> {
+    // Not a sync root (or not accessible) means nobody claims it
+    let Ok(existing) = SyncRootId::from_path(sync_path) else {
+        return Ok(());
+    };
+    if existing == *sync_root_id {
+        return Ok(());
+    }
+
+    // An ID that can't be parsed was not registered by this client, so it is
+    // treated as foreign too
+    let foreign = match existing.security_id() {
+        Some(sid) => Some(sid) != sync_root_id.security_id(),
+        None => true,
+    };
+    if foreign {
+        anyhow::bail!(
+            "{} is already used as a sync folder by another Windows user",
+            sync_path.display()
+        );
+    }
+    Ok(())
+}
+
 fn generate_sync_root_id(
     instance_url: &str,
     _account_name: &str,
@@ -786,16 +1738,49 @@ fn generate_sync_root_id(
     Ok(sync_root_id)
 }
 
+/// Re-stamp a legacy placeholder blob in the current format, keeping its identity.
+/// Best effort: the file may still be held by the filter, the next access retries.
+fn migrate_placeholder_blob(path: &Path, identity: &PlaceholderBlob) {
+    // Encoding always writes the current version
+    let blob = identity.encode();
+    let result = OpenOptions::new()
+        .open_win32(path)
+        .context("failed to open placeholder")
+        .and_then(|mut handle| {
+            handle
+                .update(UpdateOptions::default().blob(&blob), None)
+                .map(|_| ())
+                .context("failed to update placeholder blob")
+        });
+    match result {
+        Ok(()) => {
+            tracing::debug!(target: "drive::mounts", path = %path.display(), "Migrated legacy placeholder blob")
+        }
+        Err(e) => {
+            tracing::debug!(target: "drive::mounts", path = %path.display(), error = %e, "Failed to migrate legacy placeholder blob")
+        }
+    }
+}
+
 fn resolve_task_queue_config(config: &DriveConfig) -> TaskQueueConfig {
-    let concurrency = config
+    TaskQueueConfig {
+        limits: config.transfer_limits.clone(),
+        compression: config.compression.clone(),
+        // Released once the mount leaves the initializing state
+        hold_uploads: true,
+    }
+}
+
+/// Move the task concurrency formerly kept as an untyped extra key into
+/// [TransferLimits]
+fn migrate_legacy_limits(config: &mut DriveConfig) {
+    if let Some(limit) = config
         .extra
-        .get("task_queue_max_concurrency")
+        .remove("task_queue_max_concurrency")
         .and_then(|value| value.as_u64())
-        .map(|value| value as usize)
         .filter(|value| *value > 0)
-        .unwrap_or(2);
-
-    TaskQueueConfig {
-        max_concurrent: concurrency,
+    {
+        config.transfer_limits.max_uploads = limit as usize;
+        config.transfer_limits.max_downloads = limit as usize;
     }
 }
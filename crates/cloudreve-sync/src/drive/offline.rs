@@ -0,0 +1,95 @@
+//! Listing folders from the inventory while the server is unreachable.
+//!
+//! Explorer shows a folder as empty when listing it fails. If the server
+//! can't be reached and the folder is known to the inventory, its cached
+//! children are listed instead and the folder is remembered as stale. A probe
+//! then checks the server every [`RECONNECT_PROBE_INTERVAL`], and once it
+//! answers the stale folders are synced.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use cloudreve_api::ApiError;
+
+use crate::drive::{lifecycle::MountState, mounts::Mount, sync::SyncMode};
+use crate::inventory::FileMetadata;
+
+/// How often to check whether the server is reachable again
+pub const RECONNECT_PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Whether `error` means the server could not be reached, as opposed to the
+/// server refusing the request
+pub(crate) fn is_unreachable(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        let request = match cause.downcast_ref::<ApiError>() {
+            Some(ApiError::RequestError(e)) => Some(e),
+            _ => cause.downcast_ref::<reqwest::Error>(),
+        };
+        request.is_some_and(|e| e.is_connect() || e.is_timeout())
+    })
+}
+
+/// Folders listed from the inventory since the server was last reachable
+#[derive(Debug, Default)]
+pub(crate) struct StaleFolders {
+    folders: HashSet<PathBuf>,
+    probing: bool,
+}
+
+impl Mount {
+    /// Children of the folder `path` as recorded in the inventory, or `None`
+    /// if the folder itself is unknown
+    pub(crate) async fn cached_children(&self, path: &Path) -> Result<Option<Vec<FileMetadata>>> {
+        let path_str = path.to_str().context("Path contains invalid UTF-8")?;
+        let is_root = path == self.get_sync_path().await;
+        if !is_root && self.inventory.query_by_path(path_str)?.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(self.inventory.list_children(path_str)?))
+    }
+
+    /// Whether `path` was listed from the inventory and is waiting to be
+    /// synced
+    pub async fn is_stale(&self, path: &Path) -> bool {
+        self.stale_folders.lock().await.folders.contains(path)
+    }
+
+    /// Remember that `path` was listed from the inventory, and sync it once
+    /// the server is reachable again
+    pub(crate) async fn mark_stale(self: &Arc<Self>, path: PathBuf) {
+        {
+            let mut stale = self.stale_folders.lock().await;
+            stale.folders.insert(path);
+            if std::mem::replace(&mut stale.probing, true) {
+                return;
+            }
+        }
+
+        let s = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(RECONNECT_PROBE_INTERVAL).await;
+                if s.state().await == MountState::ShuttingDown {
+                    return;
+                }
+                match s.cr_client.probe_capabilities().await {
+                    Ok(_) => break,
+                    Err(e) => {
+                        tracing::debug!(target: "drive::offline", id = %s.id, error = %e, "Server still unreachable");
+                    }
+                }
+            }
+
+            let folders: Vec<PathBuf> = {
+                let mut stale = s.stale_folders.lock().await;
+                stale.probing = false;
+                stale.folders.drain().collect()
+            };
+            tracing::info!(target: "drive::offline", id = %s.id, count = folders.len(), "Server reachable again, syncing folders listed offline");
+            s.queue_sync(folders, SyncMode::PathAndFirstLayer).await;
+        });
+    }
+}
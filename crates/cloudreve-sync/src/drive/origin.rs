@@ -0,0 +1,290 @@
+//! Ledger of local changes made by the engine itself.
+//!
+//! Writing a placeholder, hydrating a file or applying a remote rename shows
+//! up again as filesystem watcher events and CFAPI callbacks, which would
+//! otherwise be pushed back to the server as local changes. Each such write
+//! is recorded under an operation id for as long as it runs, plus
+//! [`ORIGIN_GRACE`] for the debounced watcher events to arrive. Once the
+//! write is done, the size and modification time it left are kept with it,
+//! and later events are only attributed to it while the file still has
+//! them, so a user editing or deleting the file right after is not missed.
+//! Both event pipelines consult the ledger and drop what the engine caused.
+
+use notify_debouncer_full::notify::EventKind;
+use notify_debouncer_full::notify::event::ModifyKind;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How long after an operation ended its events are still attributed to it.
+/// Covers the watcher's debounce delay and the command queue.
+pub const ORIGIN_GRACE: Duration = Duration::from_secs(10);
+
+/// Kind of local write done by the engine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EngineWrite {
+    /// Placeholder created or updated from the remote file
+    Placeholder,
+    /// Placeholder content fetched from the server, or pinned/unpinned
+    Hydrate,
+    /// File content replaced by a download task
+    Download,
+    /// Local file renamed to mirror the remote
+    Rename,
+}
+
+impl EngineWrite {
+    /// Whether the write shows up as watcher events of `kind`
+    fn causes(self, kind: &EventKind) -> bool {
+        match self {
+            EngineWrite::Placeholder => {
+                matches!(kind, EventKind::Create(_) | EventKind::Modify(_))
+            }
+            EngineWrite::Hydrate => {
+                matches!(kind, EventKind::Modify(modify) if !matches!(modify, ModifyKind::Name(_)))
+            }
+            // Replacing the file removes and recreates it on some file systems
+            EngineWrite::Download => matches!(
+                kind,
+                EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+            ),
+            EngineWrite::Rename => matches!(kind, EventKind::Modify(ModifyKind::Name(_))),
+        }
+    }
+}
+
+/// Id of one recorded engine operation, for tracing suppressed events
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OperationId(u64);
+
+impl fmt::Display for OperationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "op-{}", self.0)
+    }
+}
+
+/// What a write left on disk, `None` in [`Finished::left`] if no file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileState {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl FileState {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(Self {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Finished {
+    at: Instant,
+    left: Option<FileState>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    id: OperationId,
+    write: EngineWrite,
+    /// When the operation ended and what it left, `None` while it runs
+    finished: Option<Finished>,
+}
+
+#[derive(Debug, Default)]
+struct Ledger {
+    next_id: u64,
+    entries: HashMap<PathBuf, Vec<Entry>>,
+}
+
+impl Ledger {
+    fn prune(&mut self, now: Instant) {
+        self.entries.retain(|_, entries| {
+            entries.retain(|entry| {
+                entry
+                    .finished
+                    .is_none_or(|finished| now.duration_since(finished.at) < ORIGIN_GRACE)
+            });
+            !entries.is_empty()
+        });
+    }
+}
+
+/// Shared ledger of engine writes, cloned into the watcher and CFAPI callback
+/// pipelines of a mount
+#[derive(Debug, Clone, Default)]
+pub struct ChangeOrigins {
+    ledger: Arc<Mutex<Ledger>>,
+}
+
+impl ChangeOrigins {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a write to `path` starting now. It counts as running until the
+    /// returned guard is dropped.
+    pub fn begin(&self, path: impl Into<PathBuf>, write: EngineWrite) -> OriginGuard {
+        let path = path.into();
+        let mut ledger = self.ledger.lock().unwrap();
+        ledger.prune(Instant::now());
+        ledger.next_id += 1;
+        let id = OperationId(ledger.next_id);
+        ledger.entries.entry(path.clone()).or_default().push(Entry {
+            id,
+            write,
+            finished: None,
+        });
+        OriginGuard {
+            origins: self.clone(),
+            path,
+            id,
+        }
+    }
+
+    fn finish(&self, path: &Path, id: OperationId) {
+        let left = FileState::of(path);
+        let mut ledger = self.ledger.lock().unwrap();
+        if let Some(entry) = ledger
+            .entries
+            .get_mut(path)
+            .and_then(|entries| entries.iter_mut().find(|entry| entry.id == id))
+        {
+            entry.finished = Some(Finished {
+                at: Instant::now(),
+                left,
+            });
+        }
+    }
+
+    /// The engine operation that caused a watcher event of `kind` on `path`,
+    /// if any
+    pub fn caused_event(&self, kind: &EventKind, path: &Path) -> Option<OperationId> {
+        self.find(path, |write| write.causes(kind))
+    }
+
+    /// The engine operation of kind `write` running on or recently done to
+    /// `path`, if any
+    pub fn caused(&self, path: &Path, write: EngineWrite) -> Option<OperationId> {
+        self.find(path, |recorded| recorded == write)
+    }
+
+    /// A running operation on `path` matching the write, or a finished one
+    /// whose file was not touched since
+    fn find(&self, path: &Path, matches: impl Fn(EngineWrite) -> bool) -> Option<OperationId> {
+        let candidates: Vec<(OperationId, Option<Finished>)> = {
+            let mut ledger = self.ledger.lock().unwrap();
+            ledger.prune(Instant::now());
+            ledger
+                .entries
+                .get(path)?
+                .iter()
+                .filter(|entry| matches(entry.write))
+                .map(|entry| (entry.id, entry.finished))
+                .collect()
+        };
+        if let Some((id, _)) = candidates.iter().find(|(_, finished)| finished.is_none()) {
+            return Some(*id);
+        }
+        if candidates.is_empty() {
+            return None;
+        }
+
+        let current = FileState::of(path);
+        candidates
+            .into_iter()
+            .find(|(_, finished)| finished.is_some_and(|finished| finished.left == current))
+            .map(|(id, _)| id)
+    }
+}
+
+/// Marks its operation as finished when dropped
+#[must_use = "the operation ends when the guard is dropped"]
+pub struct OriginGuard {
+    origins: ChangeOrigins,
+    path: PathBuf,
+    id: OperationId,
+}
+
+impl OriginGuard {
+    pub fn id(&self) -> OperationId {
+        self.id
+    }
+}
+
+impl Drop for OriginGuard {
+    fn drop(&mut self) {
+        self.origins.finish(&self.path, self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify_debouncer_full::notify::event::{CreateKind, DataChange, RemoveKind, RenameMode};
+
+    #[test]
+    fn events_match_the_recorded_write() {
+        let origins = ChangeOrigins::new();
+        let path = Path::new("C:\\Drive\\a.txt");
+        let guard = origins.begin(path, EngineWrite::Hydrate);
+
+        let modified = EventKind::Modify(ModifyKind::Data(DataChange::Any));
+        assert_eq!(origins.caused_event(&modified, path), Some(guard.id()));
+        assert_eq!(
+            origins.caused_event(&EventKind::Remove(RemoveKind::Any), path),
+            None
+        );
+        assert_eq!(
+            origins.caused_event(&EventKind::Modify(ModifyKind::Name(RenameMode::To)), path),
+            None
+        );
+        assert_eq!(
+            origins.caused_event(&modified, Path::new("C:\\Drive\\b.txt")),
+            None
+        );
+    }
+
+    #[test]
+    fn finished_operations_expire_after_grace() {
+        let origins = ChangeOrigins::new();
+        let path = Path::new("C:\\Drive\\a.txt");
+        let id = origins.begin(path, EngineWrite::Placeholder).id();
+
+        // Still attributed right after the guard is dropped
+        let created = EventKind::Create(CreateKind::File);
+        assert_eq!(origins.caused_event(&created, path), Some(id));
+        assert_eq!(origins.caused(path, EngineWrite::Placeholder), Some(id));
+
+        let mut ledger = origins.ledger.lock().unwrap();
+        ledger.prune(Instant::now() + ORIGIN_GRACE);
+        assert!(ledger.entries.is_empty());
+    }
+
+    #[test]
+    fn changes_after_the_write_are_not_attributed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        std::fs::write(&path, b"remote").unwrap();
+        let origins = ChangeOrigins::new();
+
+        let id = origins.begin(&path, EngineWrite::Download).id();
+        let modified = EventKind::Modify(ModifyKind::Data(DataChange::Any));
+        assert_eq!(origins.caused_event(&modified, &path), Some(id));
+
+        std::fs::write(&path, b"edited by the user").unwrap();
+        assert_eq!(origins.caused_event(&modified, &path), None);
+
+        drop(origins.begin(&path, EngineWrite::Download));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            origins.caused_event(&EventKind::Remove(RemoveKind::Any), &path),
+            None
+        );
+    }
+}
@@ -0,0 +1,123 @@
+//! Local checks against the file permissions the server reports.
+//!
+//! Files shared with the user carry a permission set; anything the user owns
+//! has none and is unrestricted. Operations the server would reject are
+//! refused locally, so the file stays in place and the user is told why
+//! instead of seeing the change rolled back after a failed request.
+
+use std::path::Path;
+
+use cloudreve_api::models::explorer::{FilePermission, FilePermissions};
+use windows::Win32::Storage::FileSystem::FILE_ATTRIBUTE_READONLY;
+
+use crate::inventory::InventoryDb;
+use crate::utils::toast;
+
+/// A local change that needs a permission on the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LocalOperation {
+    Delete,
+    Rename,
+    /// Move to another folder, which removes the file from its current one
+    Move,
+    Modify,
+    /// Create a file in a folder
+    Create,
+}
+
+impl LocalOperation {
+    fn required(self) -> FilePermission {
+        match self {
+            LocalOperation::Delete | LocalOperation::Move => FilePermission::Delete,
+            LocalOperation::Rename | LocalOperation::Modify => FilePermission::Update,
+            LocalOperation::Create => FilePermission::Create,
+        }
+    }
+
+    fn message_key(self) -> &'static str {
+        match self {
+            LocalOperation::Delete => "permissionDeniedDelete",
+            LocalOperation::Rename => "permissionDeniedRename",
+            LocalOperation::Move => "permissionDeniedMove",
+            LocalOperation::Modify => "permissionDeniedModify",
+            LocalOperation::Create => "permissionDeniedCreate",
+        }
+    }
+}
+
+/// Whether the stored base64 permission set allows `operation`. An empty or
+/// unreadable set means the server did not restrict the file.
+pub(crate) fn allows(permissions: &str, operation: LocalOperation) -> bool {
+    if permissions.is_empty() {
+        return true;
+    }
+    FilePermissions::from_base64(permissions)
+        .map(|set| set.has(operation.required()))
+        .unwrap_or(true)
+}
+
+/// Extra attributes for a placeholder: files the user can't change are
+/// read-only
+pub(crate) fn placeholder_attributes(permissions: &str, is_folder: bool) -> u32 {
+    if !is_folder && !allows(permissions, LocalOperation::Modify) {
+        FILE_ATTRIBUTE_READONLY.0
+    } else {
+        0
+    }
+}
+
+/// Check `operation` on the file at `path` against its inventory record.
+/// Unknown paths are allowed.
+pub(crate) fn check(inventory: &InventoryDb, path: &Path, operation: LocalOperation) -> bool {
+    let Some(path_str) = path.to_str() else {
+        return true;
+    };
+    match inventory.query_by_path(path_str) {
+        Ok(Some(metadata)) => allows(&metadata.permissions, operation),
+        Ok(None) => true,
+        Err(e) => {
+            tracing::warn!(target: "drive::permissions", path = %path.display(), error = %e, "Failed to look up permissions");
+            true
+        }
+    }
+}
+
+/// Tell the user a local change was refused
+pub(crate) fn notify_denied(path: &Path, operation: LocalOperation) {
+    tracing::info!(target: "drive::permissions", path = %path.display(), ?operation, "Refused local operation without permission");
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    toast::send_general_text_toast(
+        &t!("permissionDeniedTitle"),
+        &t!(operation.message_key(), "name" => name),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_restricted_files_are_gated() {
+        assert!(allows("", LocalOperation::Delete));
+        assert!(allows("not base64!", LocalOperation::Delete));
+
+        let mut set = FilePermissions::new();
+        set.grant(FilePermission::Read);
+        let read_only = set.to_base64();
+        assert!(!allows(&read_only, LocalOperation::Delete));
+        assert!(!allows(&read_only, LocalOperation::Rename));
+        assert_eq!(
+            placeholder_attributes(&read_only, false),
+            FILE_ATTRIBUTE_READONLY.0
+        );
+        assert_eq!(placeholder_attributes(&read_only, true), 0);
+
+        set.grant(FilePermission::Update);
+        let editable = set.to_base64();
+        assert!(allows(&editable, LocalOperation::Rename));
+        assert!(allows(&editable, LocalOperation::Modify));
+        assert!(!allows(&editable, LocalOperation::Move));
+        assert_eq!(placeholder_attributes(&editable, false), 0);
+        assert_eq!(placeholder_attributes("", false), 0);
+    }
+}
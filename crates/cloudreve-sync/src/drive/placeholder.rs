@@ -1,18 +1,17 @@
 use crate::{
     cfapi::{
         metadata::Metadata,
-        placeholder::{ConvertOptions, LocalFileInfo, OpenOptions, UpdateOptions},
+        placeholder::{ConvertOptions, LocalFileInfo, OpenOptions, PlaceholderBlob, UpdateOptions},
         placeholder_file::PlaceholderFile,
     },
-    drive::utils::notify_shell_change,
-    inventory::{FileMetadata, InventoryDb, MetadataEntry},
+    drive::{permissions, utils::notify_shell_change},
+    inventory::{FileMetadata, InventoryDb, MetadataEntry, remote_props},
 };
 use anyhow::{Context, Result};
 use chrono::DateTime;
 use cloudreve_api::models::explorer::{FileResponse, file_type};
 use nt_time::FileTime;
 use std::{
-    ffi::OsString,
     path::PathBuf,
     sync::Arc,
 };
@@ -43,6 +42,8 @@ pub struct CrPlaceholder {
     sync_root: PathBuf,
     drive_id: Uuid,
     file_meta: Option<FileMetadata>,
+    /// Remote file id, known when built from a remote file
+    file_id: Option<String>,
     options: u32,
 }
 
@@ -59,6 +60,7 @@ impl CrPlaceholder {
             sync_root,
             drive_id,
             file_meta: None,
+            file_id: None,
             options: 0,
             local_file_info: LocalFileInfo::from_path(&local_path.clone())
                 .unwrap_or(LocalFileInfo::missing()),
@@ -88,6 +90,18 @@ impl CrPlaceholder {
         self
     }
 
+    /// Encoded placeholder identity for the given metadata
+    fn identity_blob(&self, file_meta: &FileMetadata) -> Vec<u8> {
+        let mut identity = PlaceholderBlob::new(file_meta.etag.clone());
+        if !file_meta.etag.is_empty() {
+            identity = identity.with_entity_id(file_meta.etag.clone());
+        }
+        if let Some(file_id) = &self.file_id {
+            identity = identity.with_file_id(file_id.clone());
+        }
+        identity.encode()
+    }
+
     pub fn delete_placeholder(&self, inventory: Arc<InventoryDb>) -> Result<()> {
         // Delete local file/folder if it exists
         if self.local_file_info.exists {
@@ -125,8 +139,7 @@ impl CrPlaceholder {
 
         if self.local_file_info.exists {
             if !self.local_file_info.is_placeholder() {
-                let primary_entity = OsString::from(file_meta.etag.clone());
-                let blob = primary_entity.into_encoded_bytes();
+                let blob = self.identity_blob(file_meta);
                 // Upgrade to placeholder
                 let mut local_handle = match self.local_file_info.is_directory {
                     true => OpenOptions::new()
@@ -149,13 +162,18 @@ impl CrPlaceholder {
                     .context("failed to convert to placeholder")?;
             }
 
-            // Update file metadata
-            let mut upload_options = UpdateOptions::default().mark_in_sync().metadata(
+            // Update file metadata, re-stamping the identity in the current blob format
+            let blob = self.identity_blob(file_meta);
+            let mut upload_options = UpdateOptions::default().mark_in_sync().blob(&blob).metadata(
                 Metadata::default()
                     .size(file_meta.size as u64)
                     .changed(FileTime::from_unix_time(file_meta.updated_at)?)
                     .written(FileTime::from_unix_time(file_meta.updated_at)?)
-                    .created(FileTime::from_unix_time(file_meta.created_at)?),
+                    .created(FileTime::from_unix_time(file_meta.created_at)?)
+                    .attributes(permissions::placeholder_attributes(
+                        &file_meta.permissions,
+                        file_meta.is_folder,
+                    )),
             );
 
             let dehydrate_requested =
@@ -194,7 +212,6 @@ impl CrPlaceholder {
                 .strip_prefix(&self.sync_root)
                 .context("failed to get relative path")?;
             tracing::trace!(target: "drive::placeholder", relative_path = %relative_path.to_string_lossy(), "Relative path");
-            let primary_entity = OsString::from(file_meta.etag.clone());
             let placeholder = PlaceholderFile::new(
                 self.local_path
                     .file_name()
@@ -208,11 +225,15 @@ impl CrPlaceholder {
                 .size(file_meta.size as u64)
                 .changed(FileTime::from_unix_time(file_meta.updated_at)?)
                 .written(FileTime::from_unix_time(file_meta.updated_at)?)
-                .created(FileTime::from_unix_time(file_meta.created_at)?),
+                .created(FileTime::from_unix_time(file_meta.created_at)?)
+                .attributes(permissions::placeholder_attributes(
+                    &file_meta.permissions,
+                    file_meta.is_folder,
+                )),
             )
             .mark_in_sync()
             .overwrite()
-            .blob(primary_entity.into_encoded_bytes());
+            .blob(self.identity_blob(file_meta));
             let parent_path: &std::path::Path = self
                 .local_path
                 .parent()
@@ -253,6 +274,7 @@ impl CrPlaceholder {
             .map(|dt| dt.timestamp())
             .unwrap_or_default();
 
+        self.file_id = Some(file_info.id.clone());
         self.file_meta = Some(FileMetadata {
             drive_id: self.drive_id,
             local_path: self.local_path.to_string_lossy().to_string(),
@@ -263,8 +285,12 @@ impl CrPlaceholder {
             etag: file_info.primary_entity.clone().unwrap_or_default(),
             id: 0,
             metadata: file_info.metadata.clone().unwrap_or_default(),
-            props: None,
-            permissions: file_info.permission.clone().unwrap_or_default(),
+            props: Some(remote_props(file_info)),
+            permissions: file_info
+                .permission
+                .as_ref()
+                .map(|p| p.to_base64())
+                .unwrap_or_default(),
             shared: file_info.shared.unwrap_or(false),
             conflict_state: None,
         });
@@ -0,0 +1,628 @@
+//! Detection of local changes that look like ransomware at work.
+//!
+//! Ransomware rewrites file after file with encrypted content, often renaming
+//! them to a new extension. Syncing those changes would replace every file in
+//! the cloud with its encrypted copy. Once [`SUSPICIOUS_THRESHOLD`] files were
+//! rewritten with content that looks encrypted within [`SUSPICIOUS_WINDOW`],
+//! the drive stops syncing and uploads are held. The affected files are kept
+//! in the drive config, so sync stays stopped across restarts, until
+//! the user restores them from the versions stored in the cloud before the
+//! changes began, or dismisses the alert if the changes were expected.
+//!
+//! Content looks encrypted when a sample of it is close to random and it
+//! starts with none of the signatures of common compressed formats, which are
+//! close to random as well. Files created without an earlier version in the
+//! cloud count towards the threshold, but can't be restored.
+
+use std::collections::VecDeque;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use cloudreve_api::{
+    api::ExplorerApi,
+    models::explorer::{GetFileInfoService, RenameFileService, VersionControlService},
+};
+use notify_debouncer_full::notify::{
+    EventKind,
+    event::{ModifyKind, RemoveKind, RenameMode},
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::drive::{
+    commands::{ManagerCommand, MountCommand},
+    history::{VERSION_ENTITY, parse_time},
+    mounts::Mount,
+    placeholder::CrPlaceholder,
+    sync::{GroupedFsEvents, SyncMode},
+    utils::local_path_to_cr_uri,
+};
+use crate::utils::format::plural;
+use crate::utils::toast::send_general_text_toast;
+use crate::vfs::VirtualFs;
+
+/// Suspicious changes counted towards an incident are those seen within this
+/// window
+pub const SUSPICIOUS_WINDOW: Duration = Duration::from_secs(60);
+/// Number of suspicious changes within [`SUSPICIOUS_WINDOW`] that stops sync
+pub const SUSPICIOUS_THRESHOLD: usize = 20;
+/// Content with more bits of entropy per byte looks encrypted
+const HIGH_ENTROPY: f64 = 7.5;
+/// Bytes read from the start of a file to measure its entropy
+const ENTROPY_SAMPLE: usize = 64 * 1024;
+/// Samples shorter than this tell nothing about the content
+const MIN_ENTROPY_SAMPLE: usize = 4 * 1024;
+/// Versions stored this long before the first suspicious change are assumed
+/// to be affected as well, since events arrive with a delay
+const RESTORE_MARGIN_SECS: i64 = 60;
+
+/// Signatures of compressed formats, whose content is close to random
+const SIGNATURES: &[(usize, &[u8])] = &[
+    (0, b"PK\x03\x04"),         // zip, docx, xlsx, jar
+    (0, b"\x1f\x8b"),           // gzip
+    (0, b"7z\xbc\xaf\x27\x1c"), // 7z
+    (0, b"Rar!\x1a\x07"),       // rar
+    (0, b"\xfd7zXZ\x00"),       // xz
+    (0, b"\x28\xb5\x2f\xfd"),   // zstd
+    (0, b"BZh"),                // bzip2
+    (0, b"MSCF"),               // cab
+    (0, b"\xd0\xcf\x11\xe0"),   // msi, legacy office
+    (0, b"%PDF"),               // pdf
+    (0, b"\x89PNG"),            // png
+    (0, b"\xff\xd8\xff"),       // jpeg
+    (0, b"GIF8"),               // gif
+    (0, b"RIFF"),               // webp, avi, wav
+    (0, b"ID3"),                // mp3
+    (0, b"OggS"),               // ogg
+    (0, b"fLaC"),               // flac
+    (0, b"\x1a\x45\xdf\xa3"),   // mkv, webm
+    (0, b"MZ"),                 // exe, dll
+    (0, b"\x03\xd9\xa2\x9a"),   // kdbx
+    (4, b"ftyp"),               // mp4, mov, heic
+];
+
+/// File rewritten with content that looks encrypted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SuspiciousFile {
+    pub path: PathBuf,
+    /// Path before the file was renamed to another extension
+    #[serde(default)]
+    pub original: Option<PathBuf>,
+}
+
+impl SuspiciousFile {
+    /// Path of the file to restore
+    pub fn target(&self) -> &Path {
+        self.original.as_deref().unwrap_or(&self.path)
+    }
+}
+
+/// Suspicious changes that stopped a drive from syncing
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SuspiciousChanges {
+    /// Unix timestamp of the first suspicious change
+    pub since: i64,
+    pub files: Vec<SuspiciousFile>,
+}
+
+impl SuspiciousChanges {
+    /// Add `file` unless it is listed already
+    fn add(&mut self, file: SuspiciousFile) {
+        if !self.files.iter().any(|known| known.path == file.path) {
+            self.files.push(file);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct SeenChange {
+    seen: Instant,
+    /// Unix timestamp
+    at: i64,
+    file: SuspiciousFile,
+}
+
+/// Suspicious changes of a mount seen within [`SUSPICIOUS_WINDOW`]
+#[derive(Debug, Default)]
+pub(crate) struct RansomwareDetector {
+    recent: VecDeque<SeenChange>,
+}
+
+impl RansomwareDetector {
+    /// Count a suspicious change. Returns the changes of the window once
+    /// they reach [`SUSPICIOUS_THRESHOLD`].
+    pub fn record(
+        &mut self,
+        file: SuspiciousFile,
+        at: i64,
+        now: Instant,
+    ) -> Option<SuspiciousChanges> {
+        while self
+            .recent
+            .front()
+            .is_some_and(|change| now.duration_since(change.seen) > SUSPICIOUS_WINDOW)
+        {
+            self.recent.pop_front();
+        }
+        // A file written twice counts once
+        self.recent.retain(|change| change.file.path != file.path);
+        self.recent.push_back(SeenChange {
+            seen: now,
+            at,
+            file,
+        });
+        if self.recent.len() < SUSPICIOUS_THRESHOLD {
+            return None;
+        }
+
+        let mut changes = SuspiciousChanges {
+            since: at,
+            files: Vec::new(),
+        };
+        for change in self.recent.drain(..) {
+            changes.since = changes.since.min(change.at);
+            changes.files.push(change.file);
+        }
+        Some(changes)
+    }
+}
+
+/// Shannon entropy of `data` in bits per byte
+pub fn shannon_entropy(data: &[u8]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+    let mut counts = [0usize; 256];
+    for byte in data {
+        counts[*byte as usize] += 1;
+    }
+    let len = data.len() as f64;
+    counts
+        .iter()
+        .filter(|count| **count > 0)
+        .map(|count| {
+            let p = *count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Whether `sample`, taken from the start of a file, looks encrypted
+pub fn looks_encrypted(sample: &[u8]) -> bool {
+    if sample.len() < MIN_ENTROPY_SAMPLE {
+        return false;
+    }
+    let known_format = SIGNATURES.iter().any(|(offset, signature)| {
+        sample
+            .get(*offset..*offset + signature.len())
+            .is_some_and(|bytes| bytes == *signature)
+    });
+    !known_format && shannon_entropy(sample) >= HIGH_ENTROPY
+}
+
+/// Whether a rename from `from` to `to` changed the extension
+fn extension_changed(from: &Path, to: &Path) -> bool {
+    let extension = |path: &Path| {
+        path.extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+    };
+    from.parent() == to.parent() && extension(from) != extension(to)
+}
+
+/// Files written or renamed to another extension by `events`
+fn candidates(events: &GroupedFsEvents) -> Vec<SuspiciousFile> {
+    let mut files = Vec::new();
+    for (kind, kind_events) in events {
+        match kind {
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+                files.extend(
+                    kind_events
+                        .iter()
+                        .filter(|event| event.paths.len() == 2)
+                        .filter(|event| extension_changed(&event.paths[0], &event.paths[1]))
+                        .map(|event| SuspiciousFile {
+                            path: event.paths[1].clone(),
+                            original: Some(event.paths[0].clone()),
+                        }),
+                );
+            }
+            EventKind::Create(_) | EventKind::Modify(_) => {
+                files.extend(
+                    kind_events
+                        .iter()
+                        .filter_map(|event| event.paths.first())
+                        .map(|path| SuspiciousFile {
+                            path: path.clone(),
+                            original: None,
+                        }),
+                );
+            }
+            _ => {}
+        }
+    }
+    files
+}
+
+/// Whether the content of `path` looks encrypted. Placeholders still in sync
+/// were downloaded rather than rewritten, and those whose content is not on
+/// disk are never read, as that would download them.
+fn inspect(vfs: &dyn VirtualFs, path: &Path) -> bool {
+    let Ok(status) = vfs.status(path) else {
+        return false;
+    };
+    if !status.exists
+        || status.is_directory
+        || (status.is_placeholder && (status.in_sync || status.partial_on_disk))
+    {
+        return false;
+    }
+    let mut sample = Vec::with_capacity(ENTROPY_SAMPLE);
+    let read = std::fs::File::open(path)
+        .and_then(|file| file.take(ENTROPY_SAMPLE as u64).read_to_end(&mut sample));
+    read.is_ok() && looks_encrypted(&sample)
+}
+
+impl Mount {
+    /// Whether sync is stopped because of suspicious changes
+    pub async fn has_suspicious_changes(&self) -> bool {
+        self.status_flags.lock().await.is_suspicious_changes()
+    }
+
+    /// Look for files rewritten the way ransomware does among `events`. Once
+    /// enough are seen, the drive stops syncing and the user is alerted.
+    /// Returns whether sync is stopped.
+    pub(crate) async fn check_suspicious_changes(&self, events: &GroupedFsEvents) -> bool {
+        let held = self.has_suspicious_changes().await;
+        let candidates: Vec<_> = candidates(events)
+            .into_iter()
+            .filter(|file| !self.is_ignored(&file.path))
+            .collect();
+        if candidates.is_empty() {
+            return held;
+        }
+
+        let vfs = self.vfs.clone();
+        let suspicious = tokio::task::spawn_blocking(move || {
+            candidates
+                .into_iter()
+                .filter(|file| inspect(vfs.as_ref(), &file.path))
+                .collect::<Vec<_>>()
+        })
+        .await
+        .unwrap_or_default();
+        if suspicious.is_empty() {
+            return held;
+        }
+
+        // Files rewritten while sync is stopped are restored as well
+        if held {
+            let mut config = self.config.write().await;
+            let changes = config
+                .suspicious_changes
+                .get_or_insert_with(Default::default);
+            for file in suspicious {
+                changes.add(file);
+            }
+            drop(config);
+            self.persist_config();
+            return true;
+        }
+
+        let now = Instant::now();
+        let at = chrono::Utc::now().timestamp();
+        let tripped = {
+            let mut detector = self.ransomware.lock().await;
+            let mut tripped: Option<SuspiciousChanges> = None;
+            for file in suspicious {
+                match tripped.as_mut() {
+                    Some(changes) => changes.add(file),
+                    None => tripped = detector.record(file, at, now),
+                }
+            }
+            tripped
+        };
+        let Some(changes) = tripped else {
+            return false;
+        };
+
+        let count = changes.files.len();
+        let name = {
+            let mut config = self.config.write().await;
+            config.suspicious_changes = Some(changes);
+            config.name.clone()
+        };
+        self.persist_config();
+        self.status_flags.lock().await.set_suspicious_changes(true);
+        tracing::warn!(target: "drive::ransomware", id = %self.id, count, "Files rewritten like ransomware would, sync stopped");
+        self.refresh_state().await;
+        self.apply_upload_hold(self.state().await).await;
+        send_general_text_toast(
+            &t!("suspiciousChangesTitle"),
+            &t!("suspiciousChangesMessage", "drive" => name),
+        );
+        true
+    }
+
+    /// Stop syncing again if suspicious changes were pending when the drive
+    /// last ran
+    pub(crate) async fn hold_pending_suspicious_changes(&self) {
+        if self.config.read().await.suspicious_changes.is_none() {
+            return;
+        }
+        self.status_flags.lock().await.set_suspicious_changes(true);
+        tracing::warn!(target: "drive::ransomware", id = %self.id, "Suspicious changes still pending, sync stays stopped");
+    }
+
+    /// Make the versions stored in the cloud before the suspicious changes
+    /// current again and replace the local files with them, then resume
+    /// syncing. Returns the number of files restored.
+    pub async fn restore_suspicious_changes(&self) -> Result<usize> {
+        let Some(changes) = self.config.read().await.suspicious_changes.clone() else {
+            anyhow::bail!("Drive {} has no suspicious changes", self.id);
+        };
+        let (sync_root, remote_base, drive_id) = {
+            let config = self.config.read().await;
+            (
+                config.sync_path.clone(),
+                config.remote_path.clone(),
+                Uuid::parse_str(&config.id).context("invalid drive ID")?,
+            )
+        };
+        let before = changes.since - RESTORE_MARGIN_SECS;
+        tracing::info!(target: "drive::ransomware", id = %self.id, count = changes.files.len(), since = changes.since, "Restoring files from before suspicious changes");
+
+        let mut restored = Vec::new();
+        let mut failed = 0;
+        for file in &changes.files {
+            // Uploads of the rewritten content must not run once released
+            for path in [Some(&file.path), file.original.as_ref()]
+                .into_iter()
+                .flatten()
+            {
+                if let Err(e) = self.task_queue.cancel_by_path(path).await {
+                    tracing::warn!(target: "drive::ransomware", id = %self.id, path = %path.display(), error = %e, "Failed to cancel tasks");
+                }
+            }
+            match self
+                .restore_remote_version(file, &sync_root, &remote_base, before)
+                .await
+            {
+                Ok(true) => restored.push(file),
+                Ok(false) => {
+                    tracing::info!(target: "drive::ransomware", id = %self.id, path = %file.target().display(), "No version from before the suspicious changes");
+                }
+                Err(e) => {
+                    tracing::warn!(target: "drive::ransomware", id = %self.id, path = %file.target().display(), error = ?e, "Failed to restore version");
+                    failed += 1;
+                }
+            }
+        }
+
+        self.clear_suspicious_changes().await;
+
+        // Replace the local copies with the restored versions
+        let mut local_paths = Vec::new();
+        for file in &restored {
+            for path in [Some(&file.path), file.original.as_ref()]
+                .into_iter()
+                .flatten()
+            {
+                self.event_blocker
+                    .register_once(&EventKind::Remove(RemoveKind::Any), path.clone());
+                let placeholder = CrPlaceholder::new(path.clone(), sync_root.clone(), drive_id);
+                if let Err(e) = placeholder.delete_placeholder(self.inventory.clone()) {
+                    tracing::warn!(target: "drive::ransomware", id = %self.id, path = %path.display(), error = ?e, "Failed to delete rewritten file");
+                }
+            }
+            local_paths.push(file.target().to_path_buf());
+        }
+        if !local_paths.is_empty() {
+            if let Err(e) = self.command_tx.send(MountCommand::Sync {
+                local_paths,
+                mode: SyncMode::PathOnly,
+            }) {
+                tracing::error!(target: "drive::ransomware", id = %self.id, error = %e, "Failed to send Sync command");
+            }
+        }
+
+        let mut message = plural("batchSucceeded", restored.len() as u64);
+        if failed > 0 {
+            message.push('\n');
+            message.push_str(&plural("batchFailed", failed as u64));
+        }
+        send_general_text_toast(&t!("suspiciousChangesRestored"), &message);
+        Ok(restored.len())
+    }
+
+    /// Resume syncing, keeping the suspicious changes. What changed while
+    /// sync was stopped is picked up by a full sync.
+    pub async fn dismiss_suspicious_changes(&self) -> Result<()> {
+        if self.config.read().await.suspicious_changes.is_none() {
+            anyhow::bail!("Drive {} has no suspicious changes", self.id);
+        }
+        tracing::info!(target: "drive::ransomware", id = %self.id, "Suspicious changes dismissed");
+        self.clear_suspicious_changes().await;
+        let sync_path = self.config.read().await.sync_path.clone();
+        if let Err(e) = self.command_tx.send(MountCommand::Sync {
+            local_paths: vec![sync_path],
+            mode: SyncMode::FullHierarchy,
+        }) {
+            tracing::error!(target: "drive::ransomware", id = %self.id, error = %e, "Failed to send Sync command");
+        }
+        Ok(())
+    }
+
+    async fn clear_suspicious_changes(&self) {
+        self.config.write().await.suspicious_changes = None;
+        self.persist_config();
+        self.status_flags.lock().await.set_suspicious_changes(false);
+        self.refresh_state().await;
+        self.apply_upload_hold(self.state().await).await;
+    }
+
+    /// Make the newest version of `file` stored before `before` current in
+    /// the cloud, renaming it back first if the rename was synced. Returns
+    /// false if there is no such version.
+    async fn restore_remote_version(
+        &self,
+        file: &SuspiciousFile,
+        sync_root: &Path,
+        remote_base: &str,
+        before: i64,
+    ) -> Result<bool> {
+        let uri = |path: &Path| -> Result<String> {
+            Ok(local_path_to_cr_uri(
+                path.to_path_buf(),
+                sync_root.to_path_buf(),
+                remote_base.to_string(),
+            )
+            .context("failed to convert local path to cloudreve uri")?
+            .to_string())
+        };
+        let file_info = |uri: String| GetFileInfoService {
+            uri: Some(uri),
+            id: None,
+            extended: Some(true),
+            folder_summary: None,
+        };
+
+        let target_uri = uri(file.target())?;
+        let info = match self
+            .cr_client
+            .get_file_info(&file_info(target_uri.clone()))
+            .await
+        {
+            Ok(info) => info,
+            Err(_) if file.original.is_some() => {
+                // The rename reached the cloud before sync stopped
+                let new_name = file
+                    .target()
+                    .file_name()
+                    .context("file has no name")?
+                    .to_string_lossy()
+                    .to_string();
+                self.cr_client
+                    .rename_file(&RenameFileService {
+                        uri: uri(&file.path)?,
+                        new_name,
+                    })
+                    .await
+                    .context("failed to rename file back")?;
+                self.cr_client
+                    .get_file_info(&file_info(target_uri.clone()))
+                    .await
+                    .context("failed to get file info")?
+            }
+            Err(e) => return Err(e).context("failed to get file info"),
+        };
+
+        let mut versions = Vec::new();
+        for entity in info
+            .extended_info
+            .and_then(|extended| extended.entities)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|entity| entity.entity_type == VERSION_ENTITY)
+        {
+            let Some(created_at) = parse_time(&entity.created_at) else {
+                continue;
+            };
+            let created_at = self.cr_client.server_to_local_timestamp(created_at).await;
+            versions.push((created_at, entity.id));
+        }
+        let Some((_, version)) = versions
+            .into_iter()
+            .filter(|(created_at, _)| *created_at < before)
+            .max_by_key(|(created_at, _)| *created_at)
+        else {
+            return Ok(false);
+        };
+
+        if info.primary_entity.as_deref() != Some(version.as_str()) {
+            self.cr_client
+                .set_current_version(&VersionControlService {
+                    uri: target_uri,
+                    version,
+                })
+                .await
+                .context("failed to set current version")?;
+        }
+        Ok(true)
+    }
+
+    fn persist_config(&self) {
+        if let Err(e) = self.manager_command_tx.send(ManagerCommand::PersistConfig) {
+            tracing::error!(target: "drive::ransomware", id = %self.id, error = %e, "Failed to send PersistConfig command");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Bytes that look random, from a xorshift generator
+    fn random_bytes(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn encrypted_content_is_told_apart() {
+        let random = random_bytes(ENTROPY_SAMPLE);
+        assert!(shannon_entropy(&random) > HIGH_ENTROPY);
+        assert!(looks_encrypted(&random));
+
+        let text = "Quarterly report, nothing to see here.\n".repeat(2000);
+        assert!(!looks_encrypted(text.as_bytes()));
+
+        let mut zip = b"PK\x03\x04".to_vec();
+        zip.extend(random_bytes(ENTROPY_SAMPLE));
+        assert!(!looks_encrypted(&zip));
+
+        assert!(!looks_encrypted(&random[..MIN_ENTROPY_SAMPLE - 1]));
+    }
+
+    #[test]
+    fn incident_needs_enough_changes_within_the_window() {
+        let root = Path::new("C:\\Drive");
+        let file = |i: usize| SuspiciousFile {
+            path: root.join(format!("{i}.docx.locked")),
+            original: Some(root.join(format!("{i}.docx"))),
+        };
+        let now = Instant::now();
+        let mut detector = RansomwareDetector::default();
+
+        for i in 0..SUSPICIOUS_THRESHOLD - 1 {
+            assert!(detector.record(file(i), 100, now).is_none());
+        }
+        // Writing the same file again counts once
+        assert!(detector.record(file(0), 100, now).is_none());
+        // Changes outside the window are forgotten
+        let later = now + SUSPICIOUS_WINDOW * 2;
+        assert!(detector.record(file(100), 200, later).is_none());
+
+        for i in 1..SUSPICIOUS_THRESHOLD - 1 {
+            assert!(
+                detector
+                    .record(file(100 + i), 200 + i as i64, later)
+                    .is_none()
+            );
+        }
+        let changes = detector
+            .record(file(500), 150, later)
+            .expect("threshold reached");
+        assert_eq!(changes.since, 150);
+        assert_eq!(changes.files.len(), SUSPICIOUS_THRESHOLD);
+        assert_eq!(changes.files[0].target(), root.join("100.docx"));
+    }
+}
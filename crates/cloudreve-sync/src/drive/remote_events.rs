@@ -1,6 +1,14 @@
 use crate::{
     cfapi::placeholder::LocalFileInfo,
-    drive::{commands::MountCommand, mounts::Mount, sync::SyncMode},
+    drive::{
+        actors::record_remote_actor,
+        commands::{ManagerCommand, MountCommand},
+        mounts::Mount,
+        remote_poll::PollCursors,
+        resume::RemoteResume,
+        sync::SyncMode,
+        utils::is_case_only_rename,
+    },
 };
 use anyhow::{Context, Result};
 use cloudreve_api::{
@@ -18,6 +26,8 @@ const MAX_RETRIES: u32 = 5;
 const INITIAL_BACKOFF_SECS: u64 = 1;
 const MAX_BACKOFF_SECS: u64 = 32;
 const LONG_RETRY_DELAY_SECS: u64 = 3600; // 1 hour
+/// How often to check whether server maintenance is over before subscribing again
+const MAINTENANCE_RECHECK_SECS: u64 = 30;
 
 struct BackoffState {
     retry_count: u32,
@@ -51,8 +61,14 @@ impl BackoffState {
 
 enum ListenResult {
     Error(anyhow::Error),
+    /// The server does not support file events
+    Unsupported,
+    /// The server is under maintenance, the manager polls until it is back
+    Maintenance,
     ReconnectRequired,
     StreamEnded,
+    /// The server unlinked this device, the manager removes the drive
+    Unlinked,
 }
 
 impl Mount {
@@ -65,9 +81,51 @@ impl Mount {
             config.sync_path.clone()
         };
 
+        if let Err(e) = s.refresh_capabilities().await {
+            tracing::warn!(target: "drive::remote_events", error = %e, "Failed to probe server capabilities");
+        }
+
+        // Set once the stream keeps failing, until it subscribes again
+        let mut polling = false;
+        let mut poll_cursors = PollCursors::default();
+
         loop {
-            let result = s.listen_remote_events().await;
+            let mut connected = false;
+            let result = s.listen_remote_events(&mut connected).await;
+            if connected {
+                backoff.reset();
+                if polling {
+                    tracing::info!(target: "drive::remote_events", "Event stream recovered, stop polling");
+                    polling = false;
+                    poll_cursors.clear();
+                }
+            }
+            // Some proxies close the stream as soon as it opens
+            let result = match result {
+                ListenResult::StreamEnded if !connected => {
+                    ListenResult::Error(anyhow::anyhow!("event stream closed before subscribing"))
+                }
+                result => result,
+            };
             match result {
+                ListenResult::Unsupported => {
+                    // Without real-time events, fall back to periodic full syncs
+                    tracing::info!(target: "drive::remote_events", "Server does not support file events, polling instead");
+                    s.request_background_full_sync(sync_path.clone()).await;
+                    tokio::time::sleep(Duration::from_secs(LONG_RETRY_DELAY_SECS)).await;
+                    continue;
+                }
+                ListenResult::Maintenance => {
+                    tracing::debug!(target: "drive::remote_events", "Server is under maintenance, waiting to re-subscribe");
+                    backoff.reset();
+                    loop {
+                        tokio::time::sleep(Duration::from_secs(MAINTENANCE_RECHECK_SECS)).await;
+                        if !s.is_server_maintenance().await {
+                            break;
+                        }
+                    }
+                    continue;
+                }
                 ListenResult::ReconnectRequired => {
                     tracing::info!(target: "drive::remote_events", "Reconnect required, re-subscribing immediately");
                     backoff.reset();
@@ -78,8 +136,15 @@ impl Mount {
                     backoff.reset();
                     continue;
                 }
+                ListenResult::Unlinked => {
+                    tracing::info!(target: "drive::remote_events", "Device unlinked by the server, stop listening");
+                    return;
+                }
                 ListenResult::Error(e) => {
-                    if let Some(delay) = backoff.next_delay() {
+                    if polling {
+                        tracing::debug!(target: "drive::remote_events", error = %e, "Event stream still unavailable, polling");
+                        s.poll_remote_changes(&mut poll_cursors).await;
+                    } else if let Some(delay) = backoff.next_delay() {
                         tracing::error!(
                             target: "drive::remote_events",
                             error = %e,
@@ -89,25 +154,29 @@ impl Mount {
                         );
                         tokio::time::sleep(delay).await;
                     } else {
-                        tracing::error!(
+                        tracing::warn!(
                             target: "drive::remote_events",
                             error = %e,
-                            "Max retries reached, waiting 1 hour before retrying. Triggerring full sync..."
+                            "Max retries reached, polling for remote changes until the event stream recovers"
                         );
-                        tokio::time::sleep(Duration::from_secs(10)).await;
-                        let _ = s.command_tx.send(MountCommand::Sync {
-                            local_paths: vec![sync_path.clone()],
-                            mode: SyncMode::FullHierarchy,
-                        });
-                        tokio::time::sleep(Duration::from_secs(LONG_RETRY_DELAY_SECS)).await;
-                        backoff.reset();
+                        polling = true;
+                        // Catch up on what changed while the stream was down,
+                        // polling only sees changes from here on
+                        s.request_background_full_sync(sync_path.clone()).await;
+                        s.poll_remote_changes(&mut poll_cursors).await;
                     }
                 }
             }
         }
     }
 
-    async fn listen_remote_events(&self) -> ListenResult {
+    /// Subscribe to file events and handle them until the stream ends.
+    /// `connected` is set once the server confirmed the subscription.
+    async fn listen_remote_events(&self, connected: &mut bool) -> ListenResult {
+        // File events need an account, guests of a share poll instead
+        if self.is_guest_share() {
+            return ListenResult::Unsupported;
+        }
         let (remote_base, sync_path) = {
             let config = self.config.read().await;
             (config.remote_path.clone(), config.sync_path.clone())
@@ -115,6 +184,10 @@ impl Mount {
 
         let mut subscription = match self.cr_client.subscribe_file_events(&remote_base).await {
             Ok(sub) => sub,
+            Err(e) if e.is_feature_unavailable() => return ListenResult::Unsupported,
+            Err(e) if e.is_maintenance() => return ListenResult::Maintenance,
+            // The client already asked the manager to unlink the drive
+            Err(e) if e.is_wipe_requested() => return ListenResult::Unlinked,
             Err(e) => return ListenResult::Error(e.into()),
         };
 
@@ -128,16 +201,22 @@ impl Mount {
                         }
                     }
                     FileEvent::Resumed => {
+                        *connected = true;
                         self.set_event_push_subscribed(true).await;
                         tracing::debug!(target: "drive::remote_events", "Subscription resumed");
+                        self.resolve_startup_strategy(RemoteResume::Resumed).await;
                     }
                     FileEvent::Subscribed => {
+                        *connected = true;
                         self.set_event_push_subscribed(true).await;
-                        tracing::info!(target: "drive::remote_events", "New subscribtion, triggger full sync...");
-                        let _ = self.command_tx.send(MountCommand::Sync {
-                            local_paths: vec![sync_path.clone()],
-                            mode: SyncMode::FullHierarchy,
-                        });
+                        if self
+                            .resolve_startup_strategy(RemoteResume::Subscribed)
+                            .await
+                            .is_none()
+                        {
+                            tracing::info!(target: "drive::remote_events", "New subscribtion, triggger full sync...");
+                            self.request_background_full_sync(sync_path.clone()).await;
+                        }
                     }
                     FileEvent::KeepAlive => {
                         tracing::trace!(target: "drive::remote_events", "Keep-alive");
@@ -147,6 +226,18 @@ impl Mount {
                         self.set_event_push_subscribed(false).await;
                         return ListenResult::ReconnectRequired;
                     }
+                    FileEvent::WipeRequested(request) => {
+                        tracing::warn!(target: "drive::remote_events", request_id = %request.id, "Server asked to unlink this device");
+                        self.set_event_push_subscribed(false).await;
+                        let command = ManagerCommand::RemoteWipe {
+                            drive_id: self.id.clone(),
+                            request,
+                        };
+                        if let Err(e) = self.manager_command_tx.send(command) {
+                            tracing::error!(target: "drive::remote_events", error = %e, "Failed to send RemoteWipe command");
+                        }
+                        return ListenResult::Unlinked;
+                    }
                 },
                 Ok(None) => {
                     self.set_event_push_subscribed(false).await;
@@ -176,6 +267,9 @@ impl Mount {
                 FileEventType::Modify => create_update_events.push(event),
                 FileEventType::Rename => rename_events.push(event),
                 FileEventType::Delete => delete_events.push(event),
+                FileEventType::Unknown => {
+                    tracing::debug!(target: "drive::remote_events", file_id = %event.file_id, "Ignoring unknown file event type");
+                }
             }
         }
 
@@ -214,6 +308,31 @@ impl Mount {
             // Handle `from` path (like delete) - only if it exists locally
             let from_relative: PathBuf = event.from.trim_start_matches('/').split('/').collect();
             let local_from_path = sync_root.join(&from_relative);
+            let to_relative: PathBuf = event.to.trim_start_matches('/').split('/').collect();
+            let local_to_path = sync_root.join(&to_relative);
+
+            // Both names refer to the same local file; syncing `from` would delete it
+            if is_case_only_rename(&local_from_path, &local_to_path) {
+                match self.apply_case_rename(&local_from_path, &local_to_path) {
+                    Ok(()) => continue,
+                    Err(e) => {
+                        tracing::warn!(
+                            target: "drive::remote_events",
+                            from = %local_from_path.display(),
+                            to = %local_to_path.display(),
+                            error = ?e,
+                            "Failed to apply case-only rename, syncing target"
+                        );
+                        if let Some(parent) = local_to_path.parent() {
+                            to_grouped_by_parent
+                                .entry(parent.to_path_buf())
+                                .or_default()
+                                .push(local_to_path);
+                        }
+                        continue;
+                    }
+                }
+            }
 
             let from_exists = match LocalFileInfo::from_path(&local_from_path) {
                 Ok(info) => info.exists,
@@ -238,9 +357,6 @@ impl Mount {
             }
 
             // Handle `to` path (like create) - always process
-            let to_relative: PathBuf = event.to.trim_start_matches('/').split('/').collect();
-            let local_to_path = sync_root.join(&to_relative);
-
             if let Some(parent) = local_to_path.parent() {
                 to_grouped_by_parent
                     .entry(parent.to_path_buf())
@@ -348,11 +464,15 @@ impl Mount {
     ) -> Result<()> {
         // Group create events by parent of `from` path
         let mut grouped_by_parent: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+        let user_id = self.config.read().await.user_id.clone();
 
         for event in events {
             // Remote paths use Unix-style separators, convert to OS-native path
             let relative_path: PathBuf = event.from.trim_start_matches('/').split('/').collect();
             let local_from_path = sync_root.join(&relative_path);
+            if let Some(actor) = &event.actor {
+                record_remote_actor(&local_from_path, actor, &user_id);
+            }
 
             if let Some(parent) = local_from_path.parent() {
                 grouped_by_parent
@@ -0,0 +1,174 @@
+//! Variables in the remote folder of a new drive.
+//!
+//! Backup-style drives should often land in a folder of their own per
+//! device, like `cloudreve://my/Backups/{hostname}`. Variables are replaced
+//! once, when the drive is added, so the drive keeps syncing the same folder
+//! after the computer is renamed or the day changes. Folder names with
+//! braces of their own can't be used in such a template.
+
+use anyhow::{Context, Result, bail};
+use chrono::Local;
+use cloudreve_api::models::uri::CrUri;
+use serde::Serialize;
+
+/// Variables a remote path can contain, without braces
+pub const REMOTE_PATH_VARIABLES: &[&str] = &["hostname", "username", "user_id", "date"];
+
+/// Characters not allowed in remote file names, replaced in variable values
+const INVALID_NAME_CHARS: &[char] = &['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Values the variables of a remote path are replaced with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PathVariables {
+    /// Name of this computer
+    pub hostname: String,
+    /// Name of the signed in Windows user
+    pub username: String,
+    /// ID of the Cloudreve user the drive signs in as
+    pub user_id: String,
+    /// Today, as `YYYY-MM-DD`
+    pub date: String,
+}
+
+impl PathVariables {
+    /// Values for a drive added now by `user_id`
+    pub fn current(user_id: &str) -> Self {
+        Self {
+            hostname: crate::device_name(),
+            username: std::env::var("USERNAME").unwrap_or_else(|_| "user".to_string()),
+            user_id: user_id.to_string(),
+            date: Local::now().format("%Y-%m-%d").to_string(),
+        }
+    }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        match name {
+            "hostname" => Some(&self.hostname),
+            "username" => Some(&self.username),
+            "user_id" => Some(&self.user_id),
+            "date" => Some(&self.date),
+            _ => None,
+        }
+    }
+}
+
+/// Whether `remote_path` contains variables to replace
+pub fn has_variables(remote_path: &str) -> bool {
+    remote_path.contains('{')
+}
+
+/// Replace the variables of `remote_path`, like `{hostname}`. Fails on
+/// unknown variables, unbalanced braces, variables without a value, and
+/// results that are not a `cloudreve://` URI.
+pub fn expand_remote_path(remote_path: &str, vars: &PathVariables) -> Result<String> {
+    let mut expanded = String::with_capacity(remote_path.len());
+    let mut rest = remote_path;
+    while let Some(start) = rest.find(['{', '}']) {
+        if rest[start..].starts_with('}') {
+            bail!("Unexpected '}}' in remote path {}", remote_path);
+        }
+        expanded.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .map(|end| start + end)
+            .with_context(|| format!("Unclosed '{{' in remote path {}", remote_path))?;
+        let name = &rest[start + 1..end];
+        let value = vars.value(name).with_context(|| {
+            format!(
+                "Unknown variable {{{}}} in remote path, expected one of {}",
+                name,
+                REMOTE_PATH_VARIABLES.join(", ")
+            )
+        })?;
+        let value = sanitize(value);
+        if value.is_empty() {
+            bail!("Variable {{{}}} has no value", name);
+        }
+        expanded.push_str(&value);
+        rest = &rest[end + 1..];
+    }
+    expanded.push_str(rest);
+
+    CrUri::new(&expanded).with_context(|| format!("Invalid remote path {}", expanded))?;
+    Ok(expanded)
+}
+
+/// Make a variable value usable as a remote file name
+fn sanitize(value: &str) -> String {
+    value
+        .trim()
+        .chars()
+        .map(|c| {
+            if INVALID_NAME_CHARS.contains(&c) || c.is_control() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect::<String>()
+        .trim_end_matches('.')
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> PathVariables {
+        PathVariables {
+            hostname: "DESKTOP-1".to_string(),
+            username: "alex".to_string(),
+            user_id: "u1".to_string(),
+            date: "2026-10-17".to_string(),
+        }
+    }
+
+    #[test]
+    fn variables_are_replaced() {
+        assert_eq!(
+            expand_remote_path("cloudreve://my/Backups/{hostname}/{username}", &vars()).unwrap(),
+            "cloudreve://my/Backups/DESKTOP-1/alex"
+        );
+        assert_eq!(
+            expand_remote_path("cloudreve://my/Team/{user_id}-{date}", &vars()).unwrap(),
+            "cloudreve://my/Team/u1-2026-10-17"
+        );
+        assert_eq!(
+            expand_remote_path("cloudreve://my/Plain", &vars()).unwrap(),
+            "cloudreve://my/Plain"
+        );
+    }
+
+    #[test]
+    fn values_are_made_valid_names() {
+        let vars = PathVariables {
+            username: "CORP\\alex.".to_string(),
+            ..vars()
+        };
+        assert_eq!(
+            expand_remote_path("cloudreve://my/{username}", &vars).unwrap(),
+            "cloudreve://my/CORP_alex"
+        );
+
+        let empty = PathVariables {
+            hostname: " ".to_string(),
+            ..vars
+        };
+        assert!(expand_remote_path("cloudreve://my/{hostname}", &empty).is_err());
+    }
+
+    #[test]
+    fn invalid_templates_are_rejected() {
+        for template in [
+            "cloudreve://my/{computer}",
+            "cloudreve://my/{hostname",
+            "cloudreve://my/hostname}",
+            "my/{hostname}",
+        ] {
+            assert!(
+                expand_remote_path(template, &vars()).is_err(),
+                "{template} was accepted"
+            );
+        }
+    }
+}
@@ -0,0 +1,239 @@
+//! Polling fallback for remote changes.
+//!
+//! Some proxies cut the file events stream right after it opens. When the
+//! stream keeps failing, the drive lists its locally visible folders on a
+//! timer instead, paging with the server's cursors, and keeps a fingerprint
+//! of each listing. Only folders whose listing changed since the previous
+//! pass are synced. The stream is retried every [`SSE_RETRY_INTERVAL`] and
+//! polling stops as soon as it subscribes again.
+
+use crate::{
+    cfapi::placeholder::LocalFileInfo,
+    drive::{commands::MountCommand, mounts::Mount, sync::SyncMode, utils::local_path_to_cr_uri},
+};
+use anyhow::{Context, Result};
+use cloudreve_api::{
+    api::explorer::ExplorerApiExt,
+    models::{
+        common::ListAllRes,
+        explorer::{FileResponse, ListResponse, file_type},
+    },
+};
+use std::{
+    collections::{HashMap, VecDeque, hash_map::DefaultHasher},
+    hash::{Hash, Hasher},
+    time::{Duration, Instant},
+};
+
+/// Poll interval used when the drive does not set one
+pub const DEFAULT_POLL_INTERVAL_SECS: u64 = 60;
+/// Shortest poll interval accepted from the settings
+const MIN_POLL_INTERVAL_SECS: u64 = 15;
+/// How long to poll before trying the events stream again
+pub(crate) const SSE_RETRY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// Page size of the listings compared between passes
+const POLL_PAGE_SIZE: i32 = 1000;
+/// Folders listed per pass at most; the rest are left to the periodic full sync
+const MAX_POLLED_FOLDERS: usize = 2000;
+
+/// Poll interval for a configured value, `None` meaning the default
+pub fn poll_interval(configured_secs: Option<u64>) -> Duration {
+    Duration::from_secs(
+        configured_secs
+            .unwrap_or(DEFAULT_POLL_INTERVAL_SECS)
+            .max(MIN_POLL_INTERVAL_SECS),
+    )
+}
+
+/// Fingerprint of a folder listing. Independent of the order and paging the
+/// server returned the entries in.
+pub(crate) fn listing_fingerprint<'a>(files: impl IntoIterator<Item = &'a FileResponse>) -> u64 {
+    let mut entries: Vec<_> = files
+        .into_iter()
+        .map(|file| {
+            (
+                file.id.as_str(),
+                file.name.as_str(),
+                file.updated_at.as_str(),
+                file.size,
+                file.primary_entity.as_deref(),
+            )
+        })
+        .collect();
+    entries.sort_unstable();
+
+    let mut hasher = DefaultHasher::new();
+    entries.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Fingerprints of the folders listed in the previous pass, by remote URI
+#[derive(Debug, Default)]
+pub(crate) struct PollCursors {
+    fingerprints: HashMap<String, u64>,
+}
+
+impl PollCursors {
+    /// Record the listing of `uri`, returning whether it differs from the
+    /// previous pass. Folders seen for the first time are not changes; the
+    /// full sync run when polling starts covers them.
+    pub fn update(&mut self, uri: &str, fingerprint: u64) -> bool {
+        match self.fingerprints.insert(uri.to_string(), fingerprint) {
+            Some(previous) => previous != fingerprint,
+            None => false,
+        }
+    }
+
+    /// Forget every folder, e.g. once the events stream is back
+    pub fn clear(&mut self) {
+        self.fingerprints.clear();
+    }
+}
+
+impl Mount {
+    /// Poll for remote changes until it is time to retry the events stream
+    pub(crate) async fn poll_remote_changes(&self, cursors: &mut PollCursors) {
+        let interval = poll_interval(self.get_network_settings().await.poll_interval_secs);
+        let retry_at = Instant::now() + SSE_RETRY_INTERVAL;
+        loop {
+            if self.is_server_maintenance().await {
+                tracing::debug!(target: "drive::remote_poll", id = %self.id, "Server is under maintenance, skipping poll");
+            } else if let Err(e) = self.poll_remote_pass(cursors).await {
+                tracing::warn!(target: "drive::remote_poll", id = %self.id, error = ?e, "Failed to poll remote changes");
+            }
+            if Instant::now() + interval >= retry_at {
+                return;
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// List every locally visible folder once and sync those that changed
+    async fn poll_remote_pass(&self, cursors: &mut PollCursors) -> Result<()> {
+        let (sync_root, remote_base) = {
+            let config = self.config.read().await;
+            (config.sync_path.clone(), config.remote_path.clone())
+        };
+
+        let mut pending = VecDeque::from([sync_root.clone()]);
+        let mut listed = 0;
+        let mut changed = Vec::new();
+        while let Some(folder) = pending.pop_front() {
+            if listed >= MAX_POLLED_FOLDERS {
+                tracing::debug!(target: "drive::remote_poll", id = %self.id, folders = listed, "Poll pass reached folder limit");
+                break;
+            }
+            listed += 1;
+
+            let uri = local_path_to_cr_uri(folder.clone(), sync_root.clone(), remote_base.clone())
+                .context("failed to convert local path to cloudreve uri")?
+                .to_string();
+            let files = match self.list_remote_folder(&uri).await {
+                Ok(files) => files,
+                Err(e) => {
+                    tracing::debug!(target: "drive::remote_poll", id = %self.id, uri = %uri, error = ?e, "Failed to list remote folder");
+                    continue;
+                }
+            };
+
+            if cursors.update(&uri, listing_fingerprint(&files)) {
+                changed.push(folder.clone());
+            }
+
+            // Only descend into folders the user can see locally; the
+            // others are listed when they are opened
+            for file in files.iter().filter(|f| f.file_type == file_type::FOLDER) {
+                let local = folder.join(&file.name);
+                match LocalFileInfo::from_path(&local) {
+                    Ok(info)
+                        if info.exists
+                            && (!info.is_placeholder() || info.is_folder_populated()) =>
+                    {
+                        pending.push_back(local);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if !changed.is_empty() {
+            tracing::info!(target: "drive::remote_poll", id = %self.id, folders = changed.len(), "Remote changes found by polling");
+            self.command_tx
+                .send(MountCommand::Sync {
+                    local_paths: changed,
+                    mode: SyncMode::PathAndFirstLayer,
+                })
+                .context("failed to send sync command")?;
+        }
+        Ok(())
+    }
+
+    /// All entries of a remote folder, following the pagination cursor
+    async fn list_remote_folder(&self, uri: &str) -> Result<Vec<FileResponse>> {
+        let mut files = Vec::new();
+        let mut previous: Option<ListAllRes<ListResponse>> = None;
+        loop {
+            let response = self
+                .cr_client
+                .list_files_all(previous.as_ref(), uri, POLL_PAGE_SIZE)
+                .await?;
+            files.extend(response.res.files.iter().cloned());
+            if !response.more {
+                return Ok(files);
+            }
+            previous = Some(response);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(id: &str, updated_at: &str) -> FileResponse {
+        FileResponse {
+            id: id.to_string(),
+            name: format!("{}.txt", id),
+            updated_at: updated_at.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn fingerprint_ignores_listing_order() {
+        let a = file("a", "2024-01-01T00:00:00Z");
+        let b = file("b", "2024-01-02T00:00:00Z");
+        assert_eq!(listing_fingerprint([&a, &b]), listing_fingerprint([&b, &a]));
+
+        let b_modified = file("b", "2024-01-03T00:00:00Z");
+        assert_ne!(
+            listing_fingerprint([&a, &b]),
+            listing_fingerprint([&a, &b_modified])
+        );
+        assert_ne!(listing_fingerprint([&a, &b]), listing_fingerprint([&a]));
+    }
+
+    #[test]
+    fn cursors_report_changes_after_first_pass() {
+        let mut cursors = PollCursors::default();
+        assert!(!cursors.update("cloudreve://my/docs", 1));
+        assert!(!cursors.update("cloudreve://my/docs", 1));
+        assert!(cursors.update("cloudreve://my/docs", 2));
+
+        cursors.clear();
+        assert!(!cursors.update("cloudreve://my/docs", 3));
+    }
+
+    #[test]
+    fn poll_interval_has_a_floor() {
+        assert_eq!(
+            poll_interval(None),
+            Duration::from_secs(DEFAULT_POLL_INTERVAL_SECS)
+        );
+        assert_eq!(
+            poll_interval(Some(1)),
+            Duration::from_secs(MIN_POLL_INTERVAL_SECS)
+        );
+        assert_eq!(poll_interval(Some(300)), Duration::from_secs(300));
+    }
+}
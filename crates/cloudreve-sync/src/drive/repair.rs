@@ -0,0 +1,418 @@
+//! Placeholder repair: scan a sync root for broken CFAPI placeholders and
+//! re-stamp them from the inventory, handing anything ambiguous to the sync engine.
+
+use crate::{
+    cfapi::placeholder::{LocalFileInfo, OpenOptions, PlaceholderBlob},
+    config::{ConfigManager, DEFAULT_MTIME_TOLERANCE_SECS},
+    drive::{
+        commands::MountCommand, ignore::IgnoreMatcher, mounts::Mount, placeholder::CrPlaceholder,
+        sync::SyncMode,
+    },
+    inventory::{FileMetadata, InventoryDb, mtime_matches},
+};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use uuid::Uuid;
+
+/// Summary of a [Mount::repair_placeholders] run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RepairReport {
+    /// Number of entries inspected
+    pub scanned: usize,
+    /// Placeholders unknown to the inventory, re-synced against remote
+    pub orphaned: usize,
+    /// Placeholders whose blob identity was re-stamped from the inventory
+    pub restamped: usize,
+    /// Placeholders stuck out of sync without a pending task, re-synced
+    pub stuck: usize,
+    /// Full files/folders converted back into placeholders
+    pub converted: usize,
+    /// Entries that could not be inspected or repaired
+    pub failed: Vec<RepairFailure>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RepairFailure {
+    pub path: String,
+    pub error: String,
+}
+
+impl RepairReport {
+    fn fail(&mut self, path: &Path, error: anyhow::Error) {
+        self.failed.push(RepairFailure {
+            path: path.display().to_string(),
+            error: format!("{:#}", error),
+        });
+    }
+}
+
+enum RepairAction {
+    None,
+    Orphaned,
+    Restamped,
+    Stuck,
+    Converted,
+}
+
+/// What to do with an entry, decided from its state on disk and its
+/// inventory entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Triage {
+    /// Nothing to repair
+    Keep,
+    Orphaned,
+    Stuck,
+    /// A full file still matching its inventory entry
+    Convert,
+    /// An in-sync placeholder, its identity is compared to the inventory
+    CheckIdentity,
+}
+
+fn triage(local: &LocalFileInfo, meta: Option<&FileMetadata>, has_task: bool) -> Triage {
+    let Some(meta) = meta else {
+        // A full file without inventory entry is a new local file, handled by upload
+        return if local.is_placeholder() {
+            Triage::Orphaned
+        } else {
+            Triage::Keep
+        };
+    };
+    if !local.is_placeholder() {
+        return if is_safe_to_convert(local, meta) {
+            Triage::Convert
+        } else {
+            Triage::Keep
+        };
+    }
+    if !local.in_sync() {
+        return if has_task {
+            Triage::Keep
+        } else {
+            Triage::Stuck
+        };
+    }
+    Triage::CheckIdentity
+}
+
+/// Whether a placeholder with `identity` must be re-stamped with `etag`:
+/// `Some(true)` if its content is stale as well, `Some(false)` if only the
+/// blob format is outdated, `None` if it is fine
+fn restamp(identity: Option<&PlaceholderBlob>, etag: &str) -> Option<bool> {
+    let etag_matches = identity.is_some_and(|identity| identity.etag == etag);
+    let legacy = identity.is_none_or(PlaceholderBlob::is_legacy);
+    (!etag_matches || legacy).then_some(!etag_matches)
+}
+
+impl Mount {
+    /// Scan the sync root for broken placeholders and repair them.
+    ///
+    /// - Placeholders without an inventory entry, and placeholders stuck out of sync
+    ///   without a pending task, are re-synced against the remote.
+    /// - Placeholders whose blob does not match the inventory etag are re-stamped;
+    ///   hydrated files are dehydrated so content is fetched again for that identity.
+    ///   Legacy blobs are migrated to the current format.
+    /// - Full files matching their inventory entry (size and modification time) are
+    ///   converted back into placeholders. Anything else is left to the regular sync.
+    pub async fn repair_placeholders(&self) -> Result<RepairReport> {
+        let (drive_id, sync_root) = {
+            let config = self.config.read().await;
+            (Uuid::parse_str(&config.id)?, config.sync_path.clone())
+        };
+
+        tracing::info!(target: "drive::repair", id = %self.id, sync_root = %sync_root.display(), "Repairing placeholders");
+
+        let active_paths: HashSet<String> = self
+            .task_queue
+            .list_active_tasks()?
+            .into_iter()
+            .map(|task| task.local_path)
+            .collect();
+
+        let walk = RepairWalk {
+            id: self.id.clone(),
+            inventory: self.inventory.clone(),
+            ignore_matcher: self.ignore_matcher.clone(),
+            active_paths,
+            drive_id,
+            sync_root,
+        };
+        let (report, resync) = {
+            // Keep sync from changing placeholders while we inspect them
+            let _sync_guard = self.sync_lock.lock().await;
+            // Listing folders and opening placeholders blocks, keep it off the runtime
+            tokio::task::spawn_blocking(move || walk.run())
+                .await
+                .context("placeholder repair panicked")?
+        };
+
+        if !resync.is_empty() {
+            self.command_tx
+                .send(MountCommand::Sync {
+                    local_paths: resync,
+                    mode: SyncMode::PathOnly,
+                })
+                .context("failed to send sync command")?;
+        }
+
+        tracing::info!(
+            target: "drive::repair",
+            id = %self.id,
+            scanned = report.scanned,
+            orphaned = report.orphaned,
+            restamped = report.restamped,
+            stuck = report.stuck,
+            converted = report.converted,
+            failed = report.failed.len(),
+            "Placeholder repair finished"
+        );
+        Ok(report)
+    }
+}
+
+/// A repair run over the sync root of a drive, done on a blocking thread
+struct RepairWalk {
+    id: String,
+    inventory: Arc<InventoryDb>,
+    ignore_matcher: IgnoreMatcher,
+    /// Local paths with a pending task
+    active_paths: HashSet<String>,
+    drive_id: Uuid,
+    sync_root: PathBuf,
+}
+
+impl RepairWalk {
+    /// Repair every entry below the sync root. Returns the report and the
+    /// paths to sync again.
+    fn run(&self) -> (RepairReport, Vec<PathBuf>) {
+        let mut report = RepairReport::default();
+        let mut resync = Vec::new();
+
+        let mut pending = vec![self.sync_root.clone()];
+        while let Some(dir) = pending.pop() {
+            let entries = match std::fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    report.fail(&dir, e.into());
+                    continue;
+                }
+            };
+
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if self.ignore_matcher.is_match(&path) {
+                    continue;
+                }
+                report.scanned += 1;
+
+                let local = LocalFileInfo::from_path(&path).unwrap_or(LocalFileInfo::missing());
+                // Don't descend into unpopulated folders, listing them would fetch from remote
+                if local.is_directory && (!local.is_placeholder() || local.is_folder_populated()) {
+                    pending.push(path.clone());
+                }
+
+                let path_str = path.to_string_lossy().to_string();
+                let has_task = self.active_paths.contains(&path_str);
+                let result = self
+                    .inventory
+                    .query_by_path(&path_str)
+                    .context("failed to query inventory")
+                    .and_then(|meta| self.repair_entry(&path, &local, meta, has_task));
+
+                match result {
+                    Ok(RepairAction::None) => {}
+                    Ok(RepairAction::Orphaned) => {
+                        report.orphaned += 1;
+                        resync.push(path);
+                    }
+                    Ok(RepairAction::Stuck) => {
+                        report.stuck += 1;
+                        resync.push(path);
+                    }
+                    Ok(RepairAction::Restamped) => report.restamped += 1,
+                    Ok(RepairAction::Converted) => report.converted += 1,
+                    Err(e) => {
+                        tracing::warn!(target: "drive::repair", id = %self.id, path = %path.display(), error = %e, "Failed to repair placeholder");
+                        report.fail(&path, e);
+                    }
+                }
+            }
+        }
+        (report, resync)
+    }
+
+    fn repair_entry(
+        &self,
+        path: &PathBuf,
+        local: &LocalFileInfo,
+        meta: Option<FileMetadata>,
+        has_task: bool,
+    ) -> Result<RepairAction> {
+        let triage = triage(local, meta.as_ref(), has_task);
+        let meta = match (triage, meta) {
+            (Triage::Orphaned, _) => return Ok(RepairAction::Orphaned),
+            (Triage::Stuck, _) => return Ok(RepairAction::Stuck),
+            (Triage::Convert | Triage::CheckIdentity, Some(meta)) => meta,
+            _ => return Ok(RepairAction::None),
+        };
+
+        if triage == Triage::Convert {
+            tracing::info!(target: "drive::repair", path = %path.display(), "Converting stray file to placeholder");
+            CrPlaceholder::new(path.clone(), self.sync_root.clone(), self.drive_id)
+                .with_file_meta(meta)
+                .commit(self.inventory.clone())?;
+            return Ok(RepairAction::Converted);
+        }
+
+        let handle = match local.is_directory {
+            true => OpenOptions::new().open(path),
+            false => OpenOptions::new().open_win32(path),
+        }
+        .context("failed to open placeholder")?;
+        let identity = handle
+            .info()
+            .context("failed to get placeholder info")?
+            .map(|info| info.identity());
+        drop(handle);
+
+        let Some(stale) = restamp(identity.as_ref(), &meta.etag) else {
+            return Ok(RepairAction::None);
+        };
+
+        // A legacy blob with the right etag only needs the new format, content stays valid
+        tracing::info!(target: "drive::repair", path = %path.display(), etag_matches = !stale, "Re-stamping placeholder identity");
+        CrPlaceholder::new(path.clone(), self.sync_root.clone(), self.drive_id)
+            .with_file_meta(meta)
+            .with_invalidate_all_range(stale && !local.is_directory)
+            .commit(self.inventory.clone())?;
+        Ok(RepairAction::Restamped)
+    }
+}
+
+/// A full file can become an in-sync placeholder only if it still matches its inventory entry
+fn is_safe_to_convert(local: &LocalFileInfo, meta: &FileMetadata) -> bool {
+    if local.is_directory != meta.is_folder {
+        return false;
+    }
+    if local.is_directory {
+        return true;
+    }
+
+    let tolerance = ConfigManager::try_get()
+        .map(|config| config.mtime_tolerance_secs())
+        .unwrap_or(DEFAULT_MTIME_TOLERANCE_SECS);
+    local.file_size == Some(meta.size as u64)
+        && mtime_matches(local.last_modified, meta.updated_at, tolerance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfapi::placeholder::{PinState, PlaceholderState};
+    use std::collections::HashMap;
+    use std::time::{Duration, SystemTime};
+    use windows::Win32::Storage::CloudFilters::{
+        CF_PLACEHOLDER_STATE, CF_PLACEHOLDER_STATE_IN_SYNC, CF_PLACEHOLDER_STATE_PLACEHOLDER,
+    };
+
+    const UPDATED_AT: i64 = 1_700_000_000;
+
+    fn local(state: Option<CF_PLACEHOLDER_STATE>, size: u64, updated_at: i64) -> LocalFileInfo {
+        LocalFileInfo {
+            exists: true,
+            is_directory: false,
+            file_size: Some(size),
+            last_modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(updated_at as u64)),
+            placeholder_state: state.map(PlaceholderState::from_state),
+            pin_state: PinState::Unspecified,
+        }
+    }
+
+    fn meta(size: i64) -> FileMetadata {
+        FileMetadata {
+            id: 1,
+            drive_id: Uuid::nil(),
+            is_folder: false,
+            local_path: r"C:\Users\me\Cloudreve\a.txt".to_string(),
+            created_at: UPDATED_AT,
+            updated_at: UPDATED_AT,
+            etag: "entity".to_string(),
+            metadata: HashMap::new(),
+            props: None,
+            permissions: String::new(),
+            shared: false,
+            size,
+            conflict_state: None,
+        }
+    }
+
+    #[test]
+    fn placeholders_without_entry_or_stuck_are_resynced() {
+        let in_sync = Some(CF_PLACEHOLDER_STATE_PLACEHOLDER | CF_PLACEHOLDER_STATE_IN_SYNC);
+        let out_of_sync = Some(CF_PLACEHOLDER_STATE_PLACEHOLDER);
+        let entry = meta(10);
+
+        assert_eq!(
+            triage(&local(in_sync, 10, UPDATED_AT), None, false),
+            Triage::Orphaned
+        );
+        assert_eq!(
+            triage(&local(None, 10, UPDATED_AT), None, false),
+            Triage::Keep
+        );
+        assert_eq!(
+            triage(&local(out_of_sync, 10, UPDATED_AT), Some(&entry), false),
+            Triage::Stuck
+        );
+        // An upload or download still on its way brings it in sync
+        assert_eq!(
+            triage(&local(out_of_sync, 10, UPDATED_AT), Some(&entry), true),
+            Triage::Keep
+        );
+        assert_eq!(
+            triage(&local(in_sync, 10, UPDATED_AT), Some(&entry), false),
+            Triage::CheckIdentity
+        );
+    }
+
+    #[test]
+    fn full_files_are_converted_only_if_unchanged() {
+        let entry = meta(10);
+        assert_eq!(
+            triage(&local(None, 10, UPDATED_AT), Some(&entry), false),
+            Triage::Convert
+        );
+        assert_eq!(
+            triage(&local(None, 11, UPDATED_AT), Some(&entry), false),
+            Triage::Keep
+        );
+        assert_eq!(
+            triage(&local(None, 10, UPDATED_AT + 3600), Some(&entry), false),
+            Triage::Keep
+        );
+
+        let mut folder = local(None, 0, UPDATED_AT);
+        folder.is_directory = true;
+        folder.file_size = None;
+        assert_eq!(triage(&folder, Some(&entry), false), Triage::Keep);
+    }
+
+    #[test]
+    fn placeholders_are_restamped_for_a_new_etag_or_a_legacy_blob() {
+        let current = PlaceholderBlob::new("entity");
+        let legacy = PlaceholderBlob {
+            version: 0,
+            etag: "entity".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(restamp(Some(&current), "entity"), None);
+        assert_eq!(restamp(Some(&legacy), "entity"), Some(false));
+        assert_eq!(restamp(Some(&current), "other"), Some(true));
+        assert_eq!(restamp(None, "entity"), Some(true));
+    }
+}
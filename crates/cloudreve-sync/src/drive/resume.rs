@@ -0,0 +1,170 @@
+//! Startup fast-resume: decide whether a restarted drive needs a full
+//! reconcile or only an incremental catch-up.
+//!
+//! A full reconcile lists the whole remote tree, which is expensive for large
+//! drives. On startup the local side is covered by the change journal replay.
+//! The remote side is only covered when the event subscription is resumed and
+//! the server replays the events missed while the app was stopped; sizes and
+//! counts of the remote tree miss renames, moves and same-size edits, so a new
+//! subscription always reconciles.
+
+use crate::drive::{commands::ManagerCommand, mounts::Mount};
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+
+/// Force a full reconcile if the last one is older than this, in case the
+/// server dropped replayed events.
+const FULL_SYNC_MAX_AGE_SECS: i64 = 3 * 24 * 60 * 60;
+
+/// Markers used to decide the startup strategy, persisted with the drive config
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ResumeMarkers {
+    /// Unix time of the last completed full reconcile of the sync root
+    pub last_full_sync_at: Option<i64>,
+}
+
+/// Outcome of replaying the local change journal at startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocalReplay {
+    /// No local changes since the last shutdown
+    Clean,
+    /// Local changes were found and replayed
+    Replayed,
+    /// The journal could not tell what changed
+    Unavailable,
+}
+
+/// How the remote event subscription was established
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteResume {
+    /// The server kept our queue and replays missed events
+    Resumed,
+    /// A new subscription; events missed while offline are lost
+    Subscribed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartupStrategy {
+    Incremental,
+    Full,
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Pick the cheapest strategy that still catches every change, along with a
+/// short reason for the log.
+fn choose_strategy(
+    markers: &ResumeMarkers,
+    local: LocalReplay,
+    remote: RemoteResume,
+    now: i64,
+) -> (StartupStrategy, &'static str) {
+    let Some(last_full_sync_at) = markers.last_full_sync_at else {
+        return (StartupStrategy::Full, "no completed full sync");
+    };
+    if now - last_full_sync_at > FULL_SYNC_MAX_AGE_SECS {
+        return (StartupStrategy::Full, "last full sync too old");
+    }
+    if local == LocalReplay::Unavailable {
+        return (StartupStrategy::Full, "local change journal unavailable");
+    }
+    if remote == RemoteResume::Subscribed {
+        return (StartupStrategy::Full, "remote events missed while stopped");
+    }
+    (StartupStrategy::Incremental, "replaying missed changes")
+}
+
+impl Mount {
+    /// Decide what the first remote subscription after startup needs to sync,
+    /// and trigger a full reconcile if required.
+    ///
+    /// Returns `None` once the startup decision has been made, so reconnects
+    /// fall back to the regular behavior.
+    pub(crate) async fn resolve_startup_strategy(
+        &self,
+        remote: RemoteResume,
+    ) -> Option<StartupStrategy> {
+        let local = self.startup_replay.lock().await.take()?;
+        let markers = self.config.read().await.resume.clone();
+
+        let (strategy, reason) = choose_strategy(&markers, local, remote, now_secs());
+        tracing::info!(
+            target: "drive::resume",
+            id = %self.id,
+            strategy = ?strategy,
+            reason,
+            local = ?local,
+            remote = ?remote,
+            "Chose startup sync strategy"
+        );
+
+        if strategy == StartupStrategy::Full {
+            let sync_root = self.config.read().await.sync_path.clone();
+            self.request_background_full_sync(sync_root).await;
+        } else {
+            self.mark_initial_synced();
+        }
+        Some(strategy)
+    }
+
+    /// Record a completed full reconcile of the sync root.
+    pub(crate) async fn record_full_sync(&self) {
+        self.config.write().await.resume = ResumeMarkers {
+            last_full_sync_at: Some(now_secs()),
+        };
+        if let Err(e) = self.manager_command_tx.send(ManagerCommand::PersistConfig) {
+            tracing::error!(target: "drive::resume", id = %self.id, error = %e, "Failed to send PersistConfig command");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn markers() -> ResumeMarkers {
+        ResumeMarkers {
+            last_full_sync_at: Some(NOW - 3600),
+        }
+    }
+
+    #[test]
+    fn resumed_events_are_incremental() {
+        for local in [LocalReplay::Clean, LocalReplay::Replayed] {
+            let (strategy, _) = choose_strategy(&markers(), local, RemoteResume::Resumed, NOW);
+            assert_eq!(strategy, StartupStrategy::Incremental);
+        }
+    }
+
+    #[test]
+    fn falls_back_to_full() {
+        let cases = [
+            (
+                ResumeMarkers::default(),
+                LocalReplay::Clean,
+                RemoteResume::Resumed,
+            ),
+            (
+                ResumeMarkers {
+                    last_full_sync_at: Some(NOW - FULL_SYNC_MAX_AGE_SECS - 1),
+                },
+                LocalReplay::Clean,
+                RemoteResume::Resumed,
+            ),
+            (markers(), LocalReplay::Unavailable, RemoteResume::Resumed),
+            // Renames and same-size edits leave no trace in a new subscription
+            (markers(), LocalReplay::Clean, RemoteResume::Subscribed),
+        ];
+        for (markers, local, remote) in cases {
+            let (strategy, reason) = choose_strategy(&markers, local, remote, NOW);
+            assert_eq!(strategy, StartupStrategy::Full, "{}", reason);
+        }
+    }
+}
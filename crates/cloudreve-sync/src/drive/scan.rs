@@ -0,0 +1,293 @@
+//! Scanning of downloaded files
+//!
+//! Once a file is fully on disk it is handed to the scanner configured in
+//! [`DownloadScan`]. A flagged file is quarantined: the placeholder is
+//! dehydrated so its content leaves the disk, the inventory records it so it
+//! is not hydrated again until released, and the manager is told so the user
+//! gets notified.
+
+use crate::{
+    cfapi::placeholder::{OpenOptions, PinOptions, PinState},
+    config::{ConfigManager, DownloadScan},
+    drive::{command_queue::CommandSender, commands::ManagerCommand, utils::notify_shell_change},
+    inventory::InventoryDb,
+};
+use anyhow::{Context, Result, anyhow};
+use std::{
+    collections::HashSet,
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::{Arc, Mutex, OnceLock},
+    time::Duration,
+};
+use tokio::process::Command;
+use windows::Win32::UI::Shell::SHCNE_ATTRIBUTES;
+
+/// Give up on a scanner that has not finished after this long
+const SCAN_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// Placeholder in custom scanner arguments replaced by the file path
+const PATH_PLACEHOLDER: &str = "{path}";
+
+/// Exit code of `MpCmdRun.exe` when a threat was found
+const DEFENDER_THREAT_EXIT_CODE: i32 = 2;
+/// Exit code of custom scanners when a threat was found
+const COMMAND_THREAT_EXIT_CODE: i32 = 1;
+
+/// Files being scanned right now. Reading a file that is not fully hydrated
+/// yet fetches the missing ranges, which must not start another scan.
+static SCANNING: OnceLock<Mutex<HashSet<PathBuf>>> = OnceLock::new();
+
+/// Outcome of scanning one file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanVerdict {
+    Clean,
+    /// A threat was found, with the scanner's description of it
+    Threat(String),
+}
+
+/// Scan `path` with the configured scanner and quarantine it if flagged.
+/// Does nothing if scanning is disabled.
+pub async fn scan_downloaded_file(
+    drive_id: &str,
+    path: &Path,
+    inventory: &Arc<InventoryDb>,
+    manager_command_tx: &CommandSender<ManagerCommand>,
+) -> Result<ScanVerdict> {
+    let scan = ConfigManager::try_get()
+        .map(|config| config.download_scan())
+        .unwrap_or_default();
+    if scan == DownloadScan::Off {
+        return Ok(ScanVerdict::Clean);
+    }
+
+    let Some(_guard) = ScanGuard::acquire(path) else {
+        return Ok(ScanVerdict::Clean);
+    };
+
+    let verdict = scan_file(&scan, path).await?;
+    if let ScanVerdict::Threat(detail) = &verdict {
+        tracing::warn!(target: "drive::scan", path = %path.display(), detail = %detail, "Threat found in downloaded file");
+        quarantine(drive_id, path, detail, inventory).await?;
+        let _ = manager_command_tx.send(ManagerCommand::ThreatDetected {
+            drive_id: drive_id.to_string(),
+            path: path.to_path_buf(),
+            detail: detail.clone(),
+        });
+    } else {
+        tracing::debug!(target: "drive::scan", path = %path.display(), "Downloaded file is clean");
+    }
+    Ok(verdict)
+}
+
+/// Run the scanner on a single file
+pub async fn scan_file(scan: &DownloadScan, path: &Path) -> Result<ScanVerdict> {
+    let (program, args, threat_code) = match scan {
+        DownloadScan::Off => return Ok(ScanVerdict::Clean),
+        DownloadScan::Defender => (
+            defender_path(),
+            vec![
+                OsString::from("-Scan"),
+                OsString::from("-ScanType"),
+                OsString::from("3"),
+                OsString::from("-File"),
+                path.as_os_str().to_owned(),
+                // Leave the verdict to us, so the placeholder is quarantined
+                // instead of Defender deleting it behind the sync engine
+                OsString::from("-DisableRemediation"),
+            ],
+            DEFENDER_THREAT_EXIT_CODE,
+        ),
+        DownloadScan::Command { program, args } => (
+            PathBuf::from(program),
+            command_args(args, path),
+            COMMAND_THREAT_EXIT_CODE,
+        ),
+    };
+
+    let mut command = Command::new(&program);
+    command
+        .args(&args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    #[cfg(windows)]
+    command.creation_flags(windows::Win32::System::Threading::CREATE_NO_WINDOW.0);
+
+    let output = tokio::time::timeout(SCAN_TIMEOUT, command.output())
+        .await
+        .map_err(|_| anyhow!("scanner timed out after {:?}", SCAN_TIMEOUT))?
+        .with_context(|| format!("failed to run scanner {}", program.display()))?;
+
+    match output.status.code() {
+        Some(0) => Ok(ScanVerdict::Clean),
+        Some(code) if code == threat_code => Ok(ScanVerdict::Threat(threat_detail(
+            &String::from_utf8_lossy(&output.stdout),
+        ))),
+        code => Err(anyhow!(
+            "scanner {} failed with exit code {:?}: {}",
+            program.display(),
+            code,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+    }
+}
+
+/// Dehydrate a flagged file and keep it from being hydrated again
+async fn quarantine(
+    drive_id: &str,
+    path: &Path,
+    detail: &str,
+    inventory: &Arc<InventoryDb>,
+) -> Result<()> {
+    // Record first, so a concurrent access is refused from now on
+    let record = {
+        let inventory = inventory.clone();
+        let drive_id = drive_id.to_string();
+        let local_path = path.to_str().unwrap_or("").to_string();
+        let detail = detail.to_string();
+        tokio::task::spawn_blocking(move || {
+            inventory.quarantine_file(&drive_id, &local_path, &detail)
+        })
+    };
+    record
+        .await
+        .context("recording quarantined file panicked")?
+        .context("failed to record quarantined file")?;
+
+    let mut placeholder = OpenOptions::new()
+        .open_win32_with_retry(path)
+        .await
+        .context("failed to open quarantined file")?;
+    let shell_path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        // Pinned files refuse dehydration
+        placeholder
+            .mark_pin(PinState::Unspecified, PinOptions::default())
+            .context("failed to unpin quarantined file")?;
+        placeholder
+            .dehydrate(0..)
+            .context("failed to dehydrate quarantined file")?;
+        _ = notify_shell_change(&shell_path, SHCNE_ATTRIBUTES);
+        Ok(())
+    })
+    .await
+    .context("dehydrating quarantined file panicked")??;
+
+    tracing::info!(target: "drive::scan", path = %path.display(), "Quarantined downloaded file");
+    Ok(())
+}
+
+fn defender_path() -> PathBuf {
+    let program_files =
+        std::env::var_os("ProgramFiles").unwrap_or_else(|| OsString::from("C:\\Program Files"));
+    PathBuf::from(program_files)
+        .join("Windows Defender")
+        .join("MpCmdRun.exe")
+}
+
+/// Substitute the file path into custom scanner arguments, or append it
+fn command_args(args: &[String], path: &Path) -> Vec<OsString> {
+    let path_str = path.to_string_lossy();
+    let mut substituted = false;
+    let mut result: Vec<OsString> = args
+        .iter()
+        .map(|arg| {
+            if arg.contains(PATH_PLACEHOLDER) {
+                substituted = true;
+                OsString::from(arg.replace(PATH_PLACEHOLDER, &path_str))
+            } else {
+                OsString::from(arg)
+            }
+        })
+        .collect();
+    if !substituted {
+        result.push(path.as_os_str().to_owned());
+    }
+    result
+}
+
+/// Pick the lines naming the threat out of the scanner output
+fn threat_detail(stdout: &str) -> String {
+    let threats: Vec<&str> = stdout
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with("Threat ") || line.ends_with(" FOUND"))
+        .collect();
+    if !threats.is_empty() {
+        return threats.join("; ");
+    }
+    match stdout.trim() {
+        "" => "Threat found".to_string(),
+        output => output.to_string(),
+    }
+}
+
+/// Marks a file as being scanned for as long as it is held
+struct ScanGuard(PathBuf);
+
+impl ScanGuard {
+    fn acquire(path: &Path) -> Option<Self> {
+        let scanning = SCANNING.get_or_init(|| Mutex::new(HashSet::new()));
+        if !scanning.lock().unwrap().insert(path.to_path_buf()) {
+            return None;
+        }
+        Some(Self(path.to_path_buf()))
+    }
+}
+
+impl Drop for ScanGuard {
+    fn drop(&mut self) {
+        if let Some(scanning) = SCANNING.get() {
+            scanning.lock().unwrap().remove(&self.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitutes_or_appends_path() {
+        let path = Path::new("C:/Drive/setup.exe");
+        assert_eq!(
+            command_args(&["--file={path}".to_string()], path),
+            vec![OsString::from("--file=C:/Drive/setup.exe")]
+        );
+        assert_eq!(
+            command_args(&["--no-summary".to_string()], path),
+            vec![
+                OsString::from("--no-summary"),
+                OsString::from("C:/Drive/setup.exe")
+            ]
+        );
+    }
+
+    #[test]
+    fn extracts_threat_names() {
+        let defender = "Scan starting...\nScan finished.\nScanning C:\\Drive\\setup.exe found 1 threats.\n\
+                        Threat                  : Virus:DOS/EICAR_Test_File\nResources               : 1 total\n";
+        assert_eq!(
+            threat_detail(defender),
+            "Threat                  : Virus:DOS/EICAR_Test_File"
+        );
+        let clamscan = "C:/Drive/setup.exe: Win.Test.EICAR_HDB-1 FOUND\n";
+        assert_eq!(
+            threat_detail(clamscan),
+            "C:/Drive/setup.exe: Win.Test.EICAR_HDB-1 FOUND"
+        );
+        assert_eq!(threat_detail(""), "Threat found");
+    }
+
+    #[test]
+    fn concurrent_scans_of_a_file_are_skipped() {
+        let path = Path::new("C:/Drive/report.pdf");
+        let guard = ScanGuard::acquire(path).unwrap();
+        assert!(ScanGuard::acquire(path).is_none());
+        drop(guard);
+        assert!(ScanGuard::acquire(path).is_some());
+    }
+}
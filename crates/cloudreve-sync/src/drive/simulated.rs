@@ -0,0 +1,243 @@
+//! Simulated drives, for UI development and demos.
+//!
+//! A [`DriveKind::Simulated`] drive has no server and no sync root: it makes
+//! no requests and never touches the Cloud Files API. Its remote tree is
+//! fabricated from the drive id and written to the inventory, so the same
+//! drive always shows the same files. In place of the remote event listener,
+//! a simulator plays transfers of random files through the task queue, with
+//! synthetic progress, and now and then a transfer failing halfway or a
+//! conflict. The events are drawn from the drive id as well, so a demo plays
+//! out the same way on every run.
+//!
+//! Simulated drives can only be added and mounted while
+//! [`AppConfig::simulated_drives`](crate::config::AppConfig::simulated_drives)
+//! is set in config.json.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::ConfigManager;
+use crate::drive::commands::ManagerCommand;
+use crate::drive::mounts::{DriveKind, Mount};
+use crate::inventory::{ConflictState, MetadataEntry};
+use crate::tasks::{SIMULATED_TRANSFER_KIND, SimulatedTransferState, TaskKind, TaskPayload};
+use crate::utils::correlation::spawn;
+
+/// Server URL of simulated drives. The `.invalid` top-level domain never
+/// resolves, should anything try to reach it.
+pub const SIMULATED_INSTANCE_URL: &str = "https://simulated.invalid";
+
+/// Folders at the root of the fabricated tree
+const FOLDERS: &[&str] = &["Documents", "Photos", "Music", "Projects", "Shared"];
+/// Names the fabricated files are picked from
+const FILE_STEMS: &[&str] = &[
+    "Report", "Draft", "Holiday", "Invoice", "Notes", "Budget", "Cover", "Mix", "Backup", "Slides",
+];
+const FILE_EXTENSIONS: &[&str] = &["docx", "jpg", "mp3", "pdf", "txt", "xlsx", "zip", "pptx"];
+/// Files fabricated in each folder
+const FILES_PER_FOLDER: usize = 12;
+
+/// Delay between two simulated events
+const EVENT_INTERVAL: Duration = Duration::from_secs(4);
+
+/// A file or folder of a fabricated tree, relative to the sync root
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulatedFile {
+    pub path: PathBuf,
+    pub is_folder: bool,
+    pub size: i64,
+}
+
+/// Fabricate the remote tree of the simulated drive `drive_id`
+pub fn fabricate_tree(drive_id: &str) -> Vec<SimulatedFile> {
+    let mut rng = SimulatedRng::seeded(drive_id);
+    let mut tree = Vec::with_capacity(FOLDERS.len() * (FILES_PER_FOLDER + 1));
+    for folder in FOLDERS {
+        tree.push(SimulatedFile {
+            path: PathBuf::from(folder),
+            is_folder: true,
+            size: 0,
+        });
+        for index in 1..=FILES_PER_FOLDER {
+            let stem = rng.pick(FILE_STEMS);
+            let extension = rng.pick(FILE_EXTENSIONS);
+            // From 1 KB to 256 MB
+            let size = (1i64 << (10 + rng.below(18))) + rng.below(1024) as i64;
+            tree.push(SimulatedFile {
+                path: Path::new(folder).join(format!("{} {}.{}", stem, index, extension)),
+                is_folder: false,
+                size,
+            });
+        }
+    }
+    tree
+}
+
+/// Fail unless simulated drives are allowed
+pub fn ensure_enabled() -> Result<()> {
+    let enabled = ConfigManager::try_get().is_some_and(|config| config.simulated_drives());
+    if !enabled {
+        bail!("Simulated drives are disabled, set simulated_drives in config.json to use them");
+    }
+    Ok(())
+}
+
+/// Small deterministic generator (SplitMix64), so that a drive fabricates the
+/// same tree and plays the same events on every run
+struct SimulatedRng(u64);
+
+impl SimulatedRng {
+    fn seeded(seed: &str) -> Self {
+        let digest = Sha256::digest(seed.as_bytes());
+        let mut state = [0u8; 8];
+        state.copy_from_slice(&digest[..8]);
+        Self(u64::from_le_bytes(state))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len() as u64) as usize]
+    }
+}
+
+impl Mount {
+    pub async fn is_simulated(&self) -> bool {
+        self.config.read().await.kind == DriveKind::Simulated
+    }
+
+    /// Start a simulated drive: create its folder and, on the first start,
+    /// write its fabricated tree to the inventory
+    pub(crate) async fn start_simulated(&self) -> Result<()> {
+        ensure_enabled()?;
+        let sync_path = self.config.read().await.sync_path.clone();
+        std::fs::create_dir_all(&sync_path).context("Failed to create sync folder")?;
+
+        let listed = self
+            .inventory
+            .list_drive_files(&self.id)
+            .context("Failed to list simulated files")?;
+        if listed.is_empty() {
+            let drive_id = Uuid::parse_str(&self.id).context("Invalid drive id")?;
+            let entries: Vec<MetadataEntry> = fabricate_tree(&self.id)
+                .into_iter()
+                .map(|file| {
+                    let local_path = sync_path.join(&file.path);
+                    MetadataEntry::new(drive_id, local_path.to_string_lossy(), file.is_folder)
+                        .with_size(file.size)
+                        .with_etag(Uuid::new_v4().to_string())
+                })
+                .collect();
+            self.inventory
+                .batch_insert(&entries)
+                .context("Failed to write simulated tree")?;
+            tracing::info!(target: "drive::simulated", id = %self.id, files = entries.len(), "Fabricated simulated tree");
+        }
+
+        self.initial_synced.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Spawn the simulator, which stands in for the remote event listener
+    /// and is stopped with it
+    pub async fn spawn_simulator(self: &Arc<Self>) {
+        let mount = self.clone();
+        let handle = spawn(async move {
+            mount.simulate().await;
+        });
+        *self.remote_event_handle.lock().await = Some(handle);
+    }
+
+    async fn simulate(&self) {
+        let files: Vec<SimulatedFile> = fabricate_tree(&self.id)
+            .into_iter()
+            .filter(|file| !file.is_folder)
+            .collect();
+        let sync_path = self.config.read().await.sync_path.clone();
+        let mut rng = SimulatedRng::seeded(&format!("{}/events", self.id));
+        tracing::info!(target: "drive::simulated", id = %self.id, "Simulator started");
+
+        let mut interval = tokio::time::interval(EVENT_INTERVAL);
+        loop {
+            interval.tick().await;
+            let file = rng.pick(&files);
+            let local_path = sync_path.join(&file.path);
+            match rng.below(10) {
+                0 => self.simulate_conflict(local_path),
+                roll => {
+                    // One transfer in nine fails
+                    let direction = if roll % 2 == 0 { "upload" } else { "download" };
+                    self.simulate_transfer(local_path, direction, file.size, roll == 1)
+                        .await
+                }
+            }
+        }
+    }
+
+    fn simulate_conflict(&self, local_path: PathBuf) {
+        tracing::info!(target: "drive::simulated", id = %self.id, path = %local_path.display(), "Simulating conflict");
+        if let Err(e) = self
+            .inventory
+            .mark_as_conflicted(&local_path.to_string_lossy(), Some(ConflictState::Pending))
+        {
+            tracing::warn!(target: "drive::simulated", id = %self.id, error = %e, "Failed to mark simulated conflict");
+        }
+        let command = ManagerCommand::ConflictDetected {
+            drive_id: self.id.clone(),
+            path: local_path,
+        };
+        if let Err(e) = self.manager_command_tx.send(command) {
+            tracing::error!(target: "drive::simulated", error = %e, "Failed to send ConflictDetected command");
+        }
+    }
+
+    async fn simulate_transfer(&self, local_path: PathBuf, direction: &str, size: i64, fail: bool) {
+        let state = SimulatedTransferState {
+            direction: direction.to_string(),
+            fail,
+        };
+        let payload = TaskPayload::new(TaskKind::Custom(SIMULATED_TRANSFER_KIND), local_path)
+            .with_totals(0, size)
+            .with_custom_state(serde_json::to_value(&state).unwrap_or_default());
+        if let Err(e) = self.task_queue.enqueue(payload).await {
+            tracing::warn!(target: "drive::simulated", id = %self.id, error = %e, "Failed to enqueue simulated transfer");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn fabricated_tree_is_stable_per_drive() {
+        let tree = fabricate_tree("drive");
+        assert_eq!(tree, fabricate_tree("drive"));
+        assert_ne!(tree, fabricate_tree("other drive"));
+
+        let paths: HashSet<_> = tree.iter().map(|file| &file.path).collect();
+        assert_eq!(paths.len(), tree.len());
+        assert_eq!(
+            tree.iter().filter(|file| file.is_folder).count(),
+            FOLDERS.len()
+        );
+        assert!(tree.iter().all(|file| file.is_folder || file.size >= 1024));
+    }
+}
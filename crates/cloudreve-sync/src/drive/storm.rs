@@ -0,0 +1,369 @@
+//! Protection against storms of filesystem events.
+//!
+//! Extracting an archive into the sync root creates tens of thousands of
+//! files within seconds, and handling each of their events floods the task
+//! queue with per-file work. Once [`STORM_THRESHOLD`] create or modify events
+//! hit one top-level folder within [`STORM_WINDOW`], the folder the events
+//! share switches to bulk mode: its events are dropped, and when none arrived
+//! for [`STORM_QUIET`] it is reconciled with a single full sync. Uploads that
+//! sync finds are gathered into one [`BULK_UPLOAD_KIND`] task, so the whole
+//! operation shows up as one entry with aggregated progress.
+//!
+//! Removals and renames always go through: a reconcile would restore files
+//! deleted locally from the cloud, and upload renamed ones as new files.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use notify_debouncer_full::notify::{EventKind, event::ModifyKind};
+
+use crate::drive::{
+    mounts::Mount,
+    sync::{GroupedFsEvents, SyncMode},
+};
+use crate::tasks::{BULK_UPLOAD_KIND, BulkUploadState, TaskKind, TaskPayload};
+
+/// Events counted towards a storm are those seen within this window
+pub const STORM_WINDOW: Duration = Duration::from_secs(5);
+/// Number of events within [`STORM_WINDOW`] that makes a storm
+pub const STORM_THRESHOLD: usize = 1000;
+/// A subtree leaves bulk mode after this long without events
+pub const STORM_QUIET: Duration = Duration::from_secs(10);
+
+/// Events counted for one top-level folder
+#[derive(Debug)]
+struct Burst {
+    started: Instant,
+    count: usize,
+    /// Deepest folder holding every counted event
+    root: PathBuf,
+}
+
+#[derive(Debug)]
+struct BulkSubtree {
+    root: PathBuf,
+    last_event: Instant,
+    suppressed: usize,
+    /// The reconcile is running. Events go through again, and uploads it
+    /// queues are collected instead.
+    reconciling: bool,
+    uploads: Vec<PathBuf>,
+}
+
+/// Storms seen by a mount and the subtrees in bulk mode
+#[derive(Debug, Default)]
+pub(crate) struct StormDetector {
+    bursts: HashMap<PathBuf, Burst>,
+    bulk: Vec<BulkSubtree>,
+}
+
+/// Whether events of `kind` count towards a storm and may be dropped
+fn is_bulk_kind(kind: &EventKind) -> bool {
+    matches!(
+        kind,
+        EventKind::Create(_) | EventKind::Modify(ModifyKind::Any)
+    )
+}
+
+/// Deepest folder containing both `a` and `b`
+fn common_ancestor(a: &Path, b: &Path) -> PathBuf {
+    a.components()
+        .zip(b.components())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a)
+        .collect()
+}
+
+impl StormDetector {
+    /// Count `events` and drop those in subtrees in bulk mode. Returns the
+    /// remaining events and the roots of subtrees that just entered bulk mode.
+    pub fn filter(
+        &mut self,
+        sync_root: &Path,
+        mut events: GroupedFsEvents,
+        now: Instant,
+    ) -> (GroupedFsEvents, Vec<PathBuf>) {
+        let mut started = Vec::new();
+        for (kind, kind_events) in &events {
+            if !is_bulk_kind(kind) {
+                continue;
+            }
+            for path in kind_events.iter().filter_map(|event| event.paths.first()) {
+                if let Some(root) = self.count(sync_root, path, now) {
+                    started.push(root);
+                }
+            }
+        }
+
+        for (kind, kind_events) in events.iter_mut() {
+            if !is_bulk_kind(kind) {
+                continue;
+            }
+            kind_events.retain(|event| {
+                let Some(path) = event.paths.first() else {
+                    return true;
+                };
+                match self
+                    .bulk
+                    .iter_mut()
+                    .find(|bulk| !bulk.reconciling && path.starts_with(&bulk.root))
+                {
+                    Some(bulk) => {
+                        bulk.last_event = now;
+                        bulk.suppressed += 1;
+                        false
+                    }
+                    None => true,
+                }
+            });
+        }
+        events.retain(|_, kind_events| !kind_events.is_empty());
+        (events, started)
+    }
+
+    /// Count one event. Returns the root of the subtree if it starts a storm.
+    fn count(&mut self, sync_root: &Path, path: &Path, now: Instant) -> Option<PathBuf> {
+        let folder = path.parent().unwrap_or(path);
+        let key = match folder
+            .strip_prefix(sync_root)
+            .ok()
+            .and_then(|rest| rest.components().next())
+        {
+            Some(top) => sync_root.join(top),
+            None => sync_root.to_path_buf(),
+        };
+
+        let burst = self.bursts.entry(key.clone()).or_insert_with(|| Burst {
+            started: now,
+            count: 0,
+            root: folder.to_path_buf(),
+        });
+        if now.duration_since(burst.started) > STORM_WINDOW {
+            *burst = Burst {
+                started: now,
+                count: 0,
+                root: folder.to_path_buf(),
+            };
+        }
+        burst.count += 1;
+        burst.root = common_ancestor(&burst.root, folder);
+        if burst.count < STORM_THRESHOLD {
+            return None;
+        }
+
+        let root = self.bursts.remove(&key)?.root;
+        if self.bulk.iter().any(|bulk| root.starts_with(&bulk.root)) {
+            return None;
+        }
+        // A storm in a wider folder takes over those inside it, unless they
+        // are being reconciled already
+        self.bulk
+            .retain(|bulk| bulk.reconciling || !bulk.root.starts_with(&root));
+        self.bulk.push(BulkSubtree {
+            root: root.clone(),
+            last_event: now,
+            suppressed: 0,
+            reconciling: false,
+            uploads: Vec::new(),
+        });
+        Some(root)
+    }
+
+    /// Whether `root` is still in bulk mode
+    pub fn is_bulk(&self, root: &Path) -> bool {
+        self.bulk.iter().any(|bulk| bulk.root == root)
+    }
+
+    /// Start reconciling `root` if no event arrived for [`STORM_QUIET`].
+    /// Returns whether it did.
+    pub fn start_reconcile(&mut self, root: &Path, now: Instant) -> bool {
+        match self.bulk.iter_mut().find(|bulk| bulk.root == root) {
+            Some(bulk)
+                if !bulk.reconciling && now.duration_since(bulk.last_event) >= STORM_QUIET =>
+            {
+                bulk.reconciling = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Collect an upload of `path` for the bulk upload task of a subtree
+    /// being reconciled. Returns false if no such subtree contains it.
+    pub fn absorb_upload(&mut self, path: &Path) -> bool {
+        match self
+            .bulk
+            .iter_mut()
+            .find(|bulk| bulk.reconciling && path.starts_with(&bulk.root))
+        {
+            Some(bulk) => {
+                bulk.uploads.push(path.to_path_buf());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Leave bulk mode for `root`. Returns the uploads collected and the
+    /// number of events dropped.
+    pub fn finish(&mut self, root: &Path) -> (Vec<PathBuf>, usize) {
+        match self.bulk.iter().position(|bulk| bulk.root == root) {
+            Some(index) => {
+                let bulk = self.bulk.remove(index);
+                (bulk.uploads, bulk.suppressed)
+            }
+            None => (Vec::new(), 0),
+        }
+    }
+}
+
+impl Mount {
+    /// Wait for the storm in `root` to calm down, then reconcile the subtree
+    /// and upload what changed in one task
+    pub(crate) fn watch_storm(self: &Arc<Self>, root: PathBuf) {
+        tracing::warn!(target: "drive::storm", id = %self.id, root = %root.display(), "Filesystem event storm, switching subtree to bulk mode");
+
+        let s = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(STORM_QUIET).await;
+                let mut storm = s.storm.lock().await;
+                if !storm.is_bulk(&root) {
+                    return;
+                }
+                if storm.start_reconcile(&root, Instant::now()) {
+                    break;
+                }
+            }
+
+            tracing::info!(target: "drive::storm", id = %s.id, root = %root.display(), "Event storm is over, reconciling subtree");
+            if let Err(e) = s
+                .sync_paths(vec![root.clone()], SyncMode::FullHierarchy)
+                .await
+            {
+                tracing::error!(target: "drive::storm", id = %s.id, root = %root.display(), error = %e, "Failed to reconcile subtree after event storm");
+            }
+
+            let (uploads, suppressed) = s.storm.lock().await.finish(&root);
+            tracing::info!(target: "drive::storm", id = %s.id, root = %root.display(), suppressed, uploads = uploads.len(), "Left bulk mode");
+            if uploads.is_empty() {
+                return;
+            }
+            s.enqueue_bulk_upload(root, uploads).await;
+        });
+    }
+
+    /// Queue `uploads` as one bulk upload task, or one by one if that fails
+    async fn enqueue_bulk_upload(&self, root: PathBuf, uploads: Vec<PathBuf>) {
+        let state = match serde_json::to_value(BulkUploadState::new(uploads.clone())) {
+            Ok(state) => state,
+            Err(e) => {
+                tracing::error!(target: "drive::storm", id = %self.id, error = %e, "Failed to serialize bulk upload state");
+                return;
+            }
+        };
+        let payload = TaskPayload::new(TaskKind::Custom(BULK_UPLOAD_KIND), root.clone())
+            .with_custom_state(state);
+        match self.task_queue.enqueue(payload).await {
+            Ok(task_id) => {
+                tracing::info!(target: "drive::storm", id = %self.id, root = %root.display(), task_id = %task_id, count = uploads.len(), "Queued bulk upload");
+                let paths: Vec<&Path> = uploads.iter().map(PathBuf::as_path).collect();
+                self.settle_intake(&paths);
+                return;
+            }
+            // E.g. the upload of an earlier storm in the same folder is still running
+            Err(e) => {
+                tracing::warn!(target: "drive::storm", id = %self.id, root = %root.display(), error = %e, "Failed to queue bulk upload, queueing files one by one");
+            }
+        }
+
+        for path in uploads {
+            if let Err(e) = self
+                .task_queue
+                .enqueue(TaskPayload::upload(path.clone()))
+                .await
+            {
+                tracing::error!(target: "drive::storm", id = %self.id, path = %path.display(), error = %e, "Failed to enqueue upload task");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify_debouncer_full::notify::{
+        Event,
+        event::{CreateKind, RemoveKind},
+    };
+
+    fn events(kind: EventKind, paths: impl IntoIterator<Item = PathBuf>) -> GroupedFsEvents {
+        let events = paths
+            .into_iter()
+            .map(|path| Event::new(kind).add_path(path))
+            .collect();
+        HashMap::from([(kind, events)])
+    }
+
+    #[test]
+    fn storm_switches_subtree_to_bulk_mode() {
+        let root = Path::new("C:\\Drive");
+        let archive = root.join("docs").join("archive");
+        let create = EventKind::Create(CreateKind::Any);
+        let now = Instant::now();
+        let mut storm = StormDetector::default();
+
+        let files = (0..STORM_THRESHOLD)
+            .map(|i| archive.join(format!("{}", i % 10)).join(format!("{i}.txt")));
+        let (remaining, started) = storm.filter(root, events(create, files), now);
+        assert_eq!(started, vec![archive.clone()]);
+        assert!(remaining.is_empty());
+
+        // Other folders and removals go through
+        let mut batch = events(
+            create,
+            [root.join("docs").join("a.txt"), archive.join("b.txt")],
+        );
+        batch.extend(events(
+            EventKind::Remove(RemoveKind::Any),
+            [archive.join("c.txt")],
+        ));
+        let (remaining, started) = storm.filter(root, batch, now);
+        assert!(started.is_empty());
+        assert_eq!(remaining[&create].len(), 1);
+        assert_eq!(remaining[&EventKind::Remove(RemoveKind::Any)].len(), 1);
+
+        assert!(!storm.start_reconcile(&archive, now));
+        assert!(storm.start_reconcile(&archive, now + STORM_QUIET));
+        assert!(storm.absorb_upload(&archive.join("0").join("0.txt")));
+        assert!(!storm.absorb_upload(&root.join("docs").join("a.txt")));
+
+        let (uploads, suppressed) = storm.finish(&archive);
+        assert_eq!(uploads, vec![archive.join("0").join("0.txt")]);
+        assert_eq!(suppressed, STORM_THRESHOLD + 1);
+        assert!(!storm.is_bulk(&archive));
+    }
+
+    #[test]
+    fn events_outside_the_window_are_no_storm() {
+        let root = Path::new("C:\\Drive");
+        let create = EventKind::Create(CreateKind::Any);
+        let now = Instant::now();
+        let mut storm = StormDetector::default();
+
+        let half = STORM_THRESHOLD / 2;
+        let batch = |from: usize| {
+            events(
+                create,
+                (from..from + half + 1).map(|i| root.join("docs").join(format!("{i}.txt"))),
+            )
+        };
+        let (_, started) = storm.filter(root, batch(0), now);
+        assert!(started.is_empty());
+        let (remaining, started) = storm.filter(root, batch(half + 1), now + STORM_WINDOW * 2);
+        assert!(started.is_empty());
+        assert_eq!(remaining[&create].len(), half + 1);
+    }
+}
@@ -0,0 +1,126 @@
+//! Remote folder size calculation, used to warn before making large folders
+//! available offline.
+
+use crate::drive::{mounts::Mount, utils::local_path_to_cr_uri};
+use anyhow::{Context, Result};
+use cloudreve_api::{
+    Feature,
+    api::{ExplorerApi, explorer::ExplorerApiExt},
+    models::{
+        common::ListAllRes,
+        explorer::{GetFileInfoService, ListResponse, file_type},
+    },
+};
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Page size used when walking a remote folder
+const WALK_PAGE_SIZE: i32 = 1000;
+/// Stop walking after this many entries and report a partial result
+const WALK_MAX_ENTRIES: u64 = 200_000;
+
+/// Total size and entry counts of a remote folder
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FolderSummary {
+    /// Total size of all files in bytes
+    pub size: u64,
+    pub files: u64,
+    pub folders: u64,
+    /// False if the server summary or the walk did not cover the whole tree
+    pub completed: bool,
+    /// When the server calculated the summary; unset if computed by walking
+    pub calculated_at: Option<String>,
+}
+
+impl Mount {
+    /// Get the total size and file count of the remote folder behind `path`.
+    ///
+    /// Uses the server-side folder summary when available and complete, otherwise
+    /// walks the remote tree.
+    pub async fn get_folder_summary(&self, path: PathBuf) -> Result<FolderSummary> {
+        let (sync_root, remote_base) = {
+            let config = self.config.read().await;
+            (config.sync_path.clone(), config.remote_path.clone())
+        };
+        let uri = local_path_to_cr_uri(path.clone(), sync_root, remote_base)
+            .context("failed to convert local path to cloudreve uri")?
+            .to_string();
+
+        let server_summary = self.cr_client.require(Feature::FolderSummary).await.is_ok();
+        let info = self
+            .cr_client
+            .get_file_info(&GetFileInfoService {
+                uri: Some(uri.clone()),
+                id: None,
+                extended: None,
+                folder_summary: server_summary.then_some(true),
+            })
+            .await
+            .context("failed to get file info")?;
+
+        if info.file_type != file_type::FOLDER {
+            return Ok(FolderSummary {
+                size: info.size.max(0) as u64,
+                files: 1,
+                folders: 0,
+                completed: true,
+                calculated_at: None,
+            });
+        }
+
+        if let Some(summary) = info.folder_summary.filter(|summary| summary.completed) {
+            return Ok(FolderSummary {
+                size: summary.size.max(0) as u64,
+                files: summary.files.max(0) as u64,
+                folders: summary.folders.max(0) as u64,
+                completed: true,
+                calculated_at: Some(summary.calculated_at),
+            });
+        }
+
+        tracing::debug!(target: "drive::summary", id = %self.id, uri = %uri, "Server folder summary unavailable, walking remote tree");
+        self.walk_folder_summary(uri).await
+    }
+
+    async fn walk_folder_summary(&self, root_uri: String) -> Result<FolderSummary> {
+        let mut summary = FolderSummary {
+            completed: true,
+            ..Default::default()
+        };
+        let mut pending = vec![root_uri];
+
+        while let Some(uri) = pending.pop() {
+            let mut previous: Option<ListAllRes<ListResponse>> = None;
+            loop {
+                let response = self
+                    .cr_client
+                    .list_files_all(previous.as_ref(), &uri, WALK_PAGE_SIZE)
+                    .await
+                    .with_context(|| format!("failed to list remote folder {}", uri))?;
+
+                for file in &response.res.files {
+                    if file.file_type == file_type::FOLDER {
+                        summary.folders += 1;
+                        pending.push(file.path.clone());
+                    } else {
+                        summary.files += 1;
+                        summary.size += file.size.max(0) as u64;
+                    }
+                }
+
+                if summary.files + summary.folders >= WALK_MAX_ENTRIES {
+                    tracing::warn!(target: "drive::summary", id = %self.id, entries = WALK_MAX_ENTRIES, "Remote folder too large, returning partial summary");
+                    summary.completed = false;
+                    return Ok(summary);
+                }
+
+                if !response.more {
+                    break;
+                }
+                previous = Some(response);
+            }
+        }
+
+        Ok(summary)
+    }
+}
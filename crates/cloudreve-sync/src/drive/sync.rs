@@ -1,16 +1,26 @@
 use crate::{
     cfapi::{
         metadata::Metadata,
-        placeholder::{LocalFileInfo, PinState},
+        placeholder::{LocalFileInfo, PinState, PlaceholderBlob},
         placeholder_file::PlaceholderFile,
     },
+    config::{ConfigManager, DEFAULT_MTIME_TOLERANCE_SECS},
     drive::{
+        commands::ManagerCommand,
+        duplicate_files::hash_file,
+        failures,
         mounts::{Mount, SyncDirection},
+        origin::EngineWrite,
+        permissions,
         placeholder::CrPlaceholder,
-        utils::{local_path_to_cr_uri, remote_path_to_local_relative_path},
+        utils::{is_case_only_rename, local_path_to_cr_uri, remote_path_to_local_relative_path},
     },
-    inventory::{ConflictState, FileMetadata, MetadataEntry},
-    tasks::TaskPayload,
+    inventory::{
+        ConflictState, FileMetadata, IntakeReason, MetadataEntry, SyncOperation, mtime_matches,
+        remote_props,
+    },
+    policy::Policy,
+    tasks::{ResumableDownload, TaskPayload},
 };
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
@@ -19,7 +29,7 @@ use cloudreve_api::{
     api::explorer::ExplorerApiExt,
     error::ErrorCode,
     models::{
-        explorer::{FileResponse, file_type, metadata},
+        explorer::{FileResponse, FileURLService, file_type, metadata},
         uri::CrUri,
     },
 };
@@ -28,9 +38,9 @@ use notify_debouncer_full::notify::event::{
 };
 use notify_debouncer_full::{DebouncedEvent, notify::Event};
 use nt_time::FileTime;
+use sha2::{Digest, Sha256};
 use std::{
     collections::{HashMap, HashSet},
-    ffi::OsString,
     fmt, fs, io,
     path::{Path, PathBuf},
     time::SystemTime,
@@ -46,7 +56,11 @@ pub fn cloud_file_to_placeholder(
     let file_uri = CrUri::new(&file.path)?;
     let relative_path = remote_path_to_local_relative_path(&file_uri, &remote_path)?;
     tracing::trace!(target: "drive::sync", file_uri = %file_uri.to_string(), remote_path = %remote_path.to_string(), relative_path = %relative_path.to_string_lossy(), "Relative path");
-    let primary_entity = OsString::from(file.primary_entity.as_ref().unwrap_or(&String::new()));
+    let primary_entity = file.primary_entity.clone().unwrap_or_default();
+    let mut identity = PlaceholderBlob::new(primary_entity.clone()).with_file_id(file.id.clone());
+    if !primary_entity.is_empty() {
+        identity = identity.with_entity_id(primary_entity);
+    }
     // Remove leading slash if presented
 
     // Parse RFC time string to unix timestamp
@@ -57,20 +71,64 @@ pub fn cloud_file_to_placeholder(
 
     tracing::trace!(target: "drive::sync::cloud_file_to_placeholder", relative_path = %relative_path.to_string_lossy(), "Relative path");
 
+    let is_folder = file.file_type == file_type::FOLDER;
+    let permissions = file
+        .permission
+        .as_ref()
+        .map(|p| p.to_base64())
+        .unwrap_or_default();
+
     Ok(PlaceholderFile::new(relative_path)
         .metadata(
-            match file.file_type == file_type::FOLDER {
+            match is_folder {
                 true => Metadata::directory(),
                 false => Metadata::file(),
             }
             .size(file.size as u64)
             .changed(last_modified)
             .written(last_modified)
-            .created(created_at),
+            .created(created_at)
+            .attributes(permissions::placeholder_attributes(&permissions, is_folder)),
         )
         .mark_in_sync()
         .overwrite()
-        .blob(primary_entity.into_encoded_bytes()))
+        .blob(identity.encode()))
+}
+
+/// Placeholder for a file known from the inventory, used to list a folder
+/// while the server is unreachable
+pub fn metadata_to_placeholder(meta: &FileMetadata) -> Result<PlaceholderFile> {
+    let name = Path::new(&meta.local_path)
+        .file_name()
+        .context("inventory entry has no file name")?;
+    let mut identity = PlaceholderBlob::new(meta.etag.clone());
+    if let Some(file_id) = meta.file_id() {
+        identity = identity.with_file_id(file_id.to_string());
+    }
+    if !meta.etag.is_empty() {
+        identity = identity.with_entity_id(meta.etag.clone());
+    }
+    let created_at = FileTime::from_unix_time(meta.created_at)?;
+    let last_modified = FileTime::from_unix_time(meta.updated_at)?;
+
+    Ok(PlaceholderFile::new(name)
+        .metadata(
+            match meta.is_folder {
+                true => Metadata::directory(),
+                false => Metadata::file(),
+            }
+            .size(meta.size as u64)
+            .changed(last_modified)
+            .written(last_modified)
+            .created(created_at)
+            .attributes(permissions::placeholder_attributes(
+                &meta.permissions,
+                meta.is_folder,
+            )),
+        )
+        .mark_in_sync()
+        .overwrite()
+        .blob(identity.encode()))
 }
 
 pub fn cloud_file_to_metadata_entry(
@@ -101,7 +159,12 @@ pub fn cloud_file_to_metadata_entry(
     )
     .with_created_at(created_at)
     .with_updated_at(last_modified)
-    .with_permissions(file.permission.as_ref().unwrap_or(&String::new()).clone())
+    .with_permissions(
+        file.permission
+            .as_ref()
+            .map(|p| p.to_base64())
+            .unwrap_or_default(),
+    )
     .with_shared(file.shared.unwrap_or(false))
     .with_size(file.size)
     .with_etag(
@@ -110,7 +173,8 @@ pub fn cloud_file_to_metadata_entry(
             .unwrap_or(&String::new())
             .clone(),
     )
-    .with_metadata(file.metadata.as_ref().unwrap_or(&HashMap::new()).clone()))
+    .with_metadata(file.metadata.as_ref().unwrap_or(&HashMap::new()).clone())
+    .with_props(remote_props(file)))
 }
 
 pub fn is_symbolic_link(file: &FileResponse) -> bool {
@@ -224,6 +288,17 @@ enum SyncAction {
         original: PathBuf,
         renamed: PathBuf,
     },
+    /// Local name differs from remote only by case: rename in place, keep identity
+    RenameLocalCase {
+        original: PathBuf,
+        renamed: PathBuf,
+    },
+    /// Merge mode: adopt a local file of the remote size if its content hashes
+    /// the same, keep both versions otherwise
+    AdoptIfSameContent {
+        path: PathBuf,
+        remote: FileResponse,
+    },
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -232,6 +307,15 @@ enum UploadReason {
     RemoteMissing,
 }
 
+impl From<UploadReason> for IntakeReason {
+    fn from(reason: UploadReason) -> Self {
+        match reason {
+            UploadReason::RemoteMismatch => IntakeReason::RemoteMismatch,
+            UploadReason::RemoteMissing => IntakeReason::RemoteMissing,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 enum WalkReason {
     ModePropagation,
@@ -256,6 +340,8 @@ struct WalkRequest {
 struct SyncPlan {
     actions: Vec<SyncAction>,
     walk_requests: Vec<WalkRequest>,
+    /// Remote-only files left out by the type filter
+    type_filtered: usize,
 }
 
 // Debug print for SyncPlan
@@ -378,6 +464,40 @@ fn generate_conflict_path(path: &Path) -> PathBuf {
     conflict_path
 }
 
+/// Whether a local full file holds the same content as the remote file.
+///
+/// The server does not expose content hashes in file listings, so name (implied by
+/// path), size and modification time must all match. The local modification time
+/// may be off by the configured tolerance. Files of the same size with other
+/// times are compared by content, see [SyncAction::AdoptIfSameContent].
+fn local_matches_remote(local: &LocalFileInfo, remote: &FileResponse) -> bool {
+    if local.is_directory || local.file_size != Some(remote.size as u64) {
+        return false;
+    }
+    let Ok(remote_modified) = remote.updated_at.parse::<DateTime<Utc>>() else {
+        return false;
+    };
+    let tolerance = ConfigManager::try_get()
+        .map(|config| config.mtime_tolerance_secs())
+        .unwrap_or(DEFAULT_MTIME_TOLERANCE_SECS);
+    mtime_matches(local.last_modified, remote_modified.timestamp(), tolerance)
+}
+
+/// Keep both versions of an imported file: move the local copy aside and let it
+/// upload as a new file
+fn import_conflict_actions(path: &PathBuf, remote: &FileResponse) -> Vec<SyncAction> {
+    vec![
+        SyncAction::RenameLocalWithConflict {
+            original: path.clone(),
+            renamed: generate_conflict_path(path),
+        },
+        SyncAction::CreatePlaceholderAndInventory {
+            path: path.clone(),
+            remote: remote.clone(),
+        },
+    ]
+}
+
 fn next_child_mode(mode: SyncMode) -> SyncMode {
     match mode {
         SyncMode::FullHierarchy => SyncMode::FullHierarchy,
@@ -399,11 +519,29 @@ impl Mount {
     pub async fn sync_paths(&self, local_paths: Vec<PathBuf>, mode: SyncMode) -> Result<()> {
         let _sync_guard = self.sync_lock.lock().await;
 
+        // Without the folder every file would look deleted locally
+        if !self.check_sync_root().await {
+            tracing::debug!(target: "drive::sync", id = %self.id, "Sync folder is missing, skipping sync");
+            return Ok(());
+        }
+        if self.has_suspicious_changes().await {
+            tracing::debug!(target: "drive::sync", id = %self.id, "Suspicious changes pending, skipping sync");
+            return Ok(());
+        }
+        if self.is_simulated().await {
+            tracing::debug!(target: "drive::sync", id = %self.id, "Simulated drive has no server, skipping sync");
+            return Ok(());
+        }
+
         if local_paths.is_empty() {
             tracing::debug!(target: "drive::sync", id = %self.id, "No paths provided for sync");
             return Ok(());
         }
 
+        let sync_root = self.config.read().await.sync_path.clone();
+        let full_root = mode == SyncMode::FullHierarchy && local_paths.contains(&sync_root);
+        self.begin_sync(full_root).await;
+
         let mut grouped: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
 
         for path in local_paths {
@@ -423,7 +561,9 @@ impl Mount {
             }
         }
 
+        self.end_sync().await;
         drop(_sync_guard);
+        self.run_content_checks(&mut aggregate_error).await;
         aggregate_error.into_result()
     }
 
@@ -451,9 +591,13 @@ impl Mount {
         ));
 
         // For sync root, directly walk to descendants
-        let (sync_root, sync_direction) = {
+        let (sync_root, sync_direction, import_existing) = {
             let config = self.config.read().await;
-            (config.sync_path.clone(), config.sync_direction)
+            (
+                config.sync_path.clone(),
+                config.sync_direction,
+                config.import_existing,
+            )
         };
         if paths.len() == 1 && paths[0] == sync_root {
             tracing::debug!(
@@ -472,6 +616,12 @@ impl Mount {
                 &mut aggregate_error,
             )
             .await;
+            if mode == SyncMode::FullHierarchy && aggregate_error.is_empty() {
+                if import_existing {
+                    self.finish_import_existing().await;
+                }
+                self.record_full_sync().await;
+            }
             return aggregate_error.into_result();
         }
 
@@ -506,6 +656,7 @@ impl Mount {
             parent,
             mode,
             sync_direction,
+            import_existing,
             paths,
             &remote_files,
             &local_files,
@@ -518,6 +669,7 @@ impl Mount {
             parent = %parent.display(),
             actions = plan.actions.len(),
             walks = plan.walk_requests.len(),
+            type_filtered = plan.type_filtered,
             "Planned sync actions"
         );
         tracing::trace!(target: "drive::sync", plan = ?plan, "Planned actions detail");
@@ -525,6 +677,7 @@ impl Mount {
         let SyncPlan {
             actions,
             walk_requests,
+            ..
         } = plan;
         let (immediate_walks, deferred_walks): (Vec<_>, Vec<_>) = walk_requests
             .into_iter()
@@ -555,6 +708,12 @@ impl Mount {
             (Uuid::parse_str(&config.id)?, config.sync_path.clone())
         };
 
+        // Queueing many uploads takes a while, don't lose the rest on a quit
+        self.record_intake(actions.iter().filter_map(|action| match action {
+            SyncAction::QueueUpload { path, reason } => Some((path.as_path(), (*reason).into())),
+            _ => None,
+        }));
+
         for action in actions {
             self.process_action(action, &sync_root, &drive_id, aggregate_error)
                 .await;
@@ -572,6 +731,7 @@ impl Mount {
     ) {
         match action {
             SyncAction::CreatePlaceholderAndInventory { path, remote } => {
+                let _origin = self.change_origins.begin(path, EngineWrite::Placeholder);
                 let cr_placeholder =
                     CrPlaceholder::new(path.clone(), sync_root.clone(), drive_id.clone());
                 if let Err(err) = cr_placeholder
@@ -593,6 +753,7 @@ impl Mount {
                 remote,
                 invalidate_all,
             } => {
+                let _origin = self.change_origins.begin(path, EngineWrite::Placeholder);
                 let cr_placeholder =
                     CrPlaceholder::new(path.clone(), sync_root.clone(), drive_id.clone());
                 if let Err(err) = cr_placeholder
@@ -611,6 +772,20 @@ impl Mount {
                 }
             }
             SyncAction::QueueUpload { path, reason } => {
+                if failures::is_blocked(&self.inventory, path, SyncOperation::Upload) {
+                    tracing::debug!(
+                        target: "drive::sync",
+                        id = %self.id,
+                        path = %path.display(),
+                        "Skipping upload of quarantined file"
+                    );
+                    self.settle_intake(&[path.as_path()]);
+                    return;
+                }
+                // Subtrees reconciled after an event storm upload in one task
+                if self.storm.lock().await.absorb_upload(path) {
+                    return;
+                }
                 tracing::info!(
                     target: "drive::sync",
                     id = %self.id,
@@ -635,6 +810,15 @@ impl Mount {
                 }
             }
             SyncAction::QueueDownload { path, remote:_ } => {
+                if failures::is_blocked(&self.inventory, path, SyncOperation::Download) {
+                    tracing::debug!(
+                        target: "drive::sync",
+                        id = %self.id,
+                        path = %path.display(),
+                        "Skipping download of quarantined file"
+                    );
+                    return;
+                }
                 tracing::info!(
                     target: "drive::sync",
                     id = %self.id,
@@ -711,19 +895,28 @@ impl Mount {
                     path = %path.display(),
                     "Creating remote folder"
                 );
-                if let Err(err) = self
-                    .task_queue
-                    .enqueue(TaskPayload::upload(path.clone()))
-                    .await
-                {
+                if let Err(err) = self.create_remote_folder(path).await {
                     tracing::error!(
                         target: "drive::sync",
                         id = %self.id,
                         path = %path.display(),
                         error = ?err,
-                        "Failed to enqueue upload task"
+                        "Failed to create remote folder"
                     );
-                    aggregate_error.push(path.clone(), anyhow::Error::from(err));
+                    aggregate_error.push(path.clone(), err);
+                }
+            }
+            SyncAction::RenameLocalCase { original, renamed } => {
+                if let Err(err) = self.apply_case_rename(original, renamed) {
+                    tracing::error!(
+                        target: "drive::sync",
+                        id = %self.id,
+                        original = %original.display(),
+                        renamed = %renamed.display(),
+                        error = ?err,
+                        "Failed to apply case-only rename"
+                    );
+                    aggregate_error.push(original.clone(), err);
                 }
             }
             SyncAction::RenameLocalWithConflict { original, renamed } => {
@@ -750,9 +943,111 @@ impl Mount {
                     aggregate_error.push(original.clone(), anyhow::Error::from(err));
                 }
             }
+            SyncAction::AdoptIfSameContent { path, remote } => {
+                // Comparing downloads the remote file, which must not hold up
+                // other syncs, see [Mount::run_content_checks]
+                self.content_checks
+                    .lock()
+                    .await
+                    .push((path.clone(), remote.clone()));
+            }
         }
     }
 
+    /// Compare the files queued by [SyncAction::AdoptIfSameContent] with their
+    /// remote content, without holding the sync lock while downloading. A file
+    /// is adopted if the content is the same and both versions are kept
+    /// otherwise, unless it changed or got synced in the meantime.
+    async fn run_content_checks(&self, aggregate_error: &mut SyncAggregateError) {
+        let checks = std::mem::take(&mut *self.content_checks.lock().await);
+        if checks.is_empty() {
+            return;
+        }
+        let (drive_id, sync_root) = {
+            let config = self.config.read().await;
+            match Uuid::parse_str(&config.id) {
+                Ok(id) => (id, config.sync_path.clone()),
+                Err(err) => {
+                    aggregate_error.push(config.sync_path.clone(), err);
+                    return;
+                }
+            }
+        };
+
+        for (path, remote) in checks {
+            let before = LocalFileInfo::from_path(&path).ok();
+            let same = self
+                .same_content_as_remote(&path, &remote)
+                .await
+                .unwrap_or_else(|err| {
+                    tracing::warn!(
+                        target: "drive::sync",
+                        id = %self.id,
+                        path = %path.display(),
+                        error = ?err,
+                        "Failed to compare local file with remote, keeping both"
+                    );
+                    false
+                });
+
+            let _sync_guard = self.sync_lock.lock().await;
+            let after = LocalFileInfo::from_path(&path).ok();
+            let unchanged = match (&before, &after) {
+                (Some(before), Some(after)) => {
+                    after.exists
+                        && before.file_size == after.file_size
+                        && before.last_modified == after.last_modified
+                        && !after.is_placeholder()
+                }
+                _ => false,
+            };
+            let tracked = path
+                .to_str()
+                .and_then(|path| self.inventory.query_by_path(path).ok().flatten())
+                .is_some();
+            if !unchanged || tracked {
+                tracing::debug!(target: "drive::sync", id = %self.id, path = %path.display(), "Local file changed while compared with remote, leaving it to the next sync");
+                continue;
+            }
+
+            let follow_up = if same {
+                tracing::debug!(target: "drive::sync", id = %self.id, path = %path.display(), "Adopting existing local file with the same content");
+                vec![SyncAction::UpdateInventoryFromRemote {
+                    path: path.clone(),
+                    remote: remote.clone(),
+                    invalidate_all: false,
+                }]
+            } else {
+                import_conflict_actions(&path, &remote)
+            };
+            for action in &follow_up {
+                self.process_action(action, &sync_root, &drive_id, aggregate_error)
+                    .await;
+            }
+        }
+    }
+
+    /// Whether the local file at `path` holds the content of `remote`,
+    /// comparing SHA-256 digests. Streams the remote file through the hash
+    /// without keeping it.
+    async fn same_content_as_remote(&self, path: &Path, remote: &FileResponse) -> Result<bool> {
+        let local_path = path.to_path_buf();
+        let local_hash = task::spawn_blocking(move || hash_file(&local_path)).await??;
+
+        let _slot = self.task_queue.acquire_download_slot().await?;
+        let request = FileURLService {
+            uris: vec![remote.path.clone()],
+            entity: remote.primary_entity.clone(),
+            ..Default::default()
+        };
+        let mut download = ResumableDownload::new(self.cr_client.clone(), request, None).await?;
+        let mut hasher = Sha256::new();
+        while let Some(chunk) = download.next_chunk().await? {
+            hasher.update(&chunk);
+        }
+        Ok(format!("{:x}", hasher.finalize()) == local_hash)
+    }
+
     async fn fetch_local_file_infos(
         &self,
         paths: &[PathBuf],
@@ -917,6 +1212,7 @@ impl Mount {
         _parent: &PathBuf,
         mode: SyncMode,
         sync_direction: SyncDirection,
+        import_existing: bool,
         paths: &[PathBuf],
         remote_files: &HashMap<PathBuf, FileResponse>,
         local_files: &HashMap<PathBuf, LocalFileInfo>,
@@ -924,14 +1220,61 @@ impl Mount {
     ) -> SyncPlan {
         let mut plan = SyncPlan::default();
 
+        // Local entries whose remote counterpart differs only by case are renamed
+        // first and then planned under the remote name
+        let mut case_renamed: HashMap<&PathBuf, &PathBuf> = HashMap::new();
+        for path in paths {
+            let local_exists = local_files.get(path).is_some_and(|local| local.exists);
+            if remote_files.contains_key(path) || !local_exists {
+                continue;
+            }
+            if let Some(remote_path) = remote_files
+                .keys()
+                .find(|remote_path| is_case_only_rename(path, remote_path))
+            {
+                plan.actions.push(SyncAction::RenameLocalCase {
+                    original: path.clone(),
+                    renamed: remote_path.clone(),
+                });
+                case_renamed.insert(remote_path, path);
+            }
+        }
+
         for path in paths {
+            if case_renamed.values().any(|original| *original == path) {
+                continue;
+            }
             let local_info = local_files
                 .get(path)
                 .cloned()
                 .unwrap_or_else(LocalFileInfo::missing);
             let remote = remote_files.get(path);
-            let inventory = inventory_entries.get(path);
-            self.plan_entry_actions(path, mode, sync_direction, remote, &local_info, inventory, &mut plan);
+            let inventory = inventory_entries.get(path).or_else(|| {
+                case_renamed
+                    .get(path)
+                    .and_then(|original| inventory_entries.get(*original))
+            });
+            self.plan_entry_actions(
+                path,
+                mode,
+                sync_direction,
+                import_existing,
+                remote,
+                &local_info,
+                inventory,
+                &mut plan,
+            );
+        }
+
+        // Local changes are never pushed to the cloud in read-only mode, and
+        // guests of a share can't change it
+        if Policy::get().force_read_only || self.is_guest_share() {
+            plan.actions.retain(|action| {
+                !matches!(
+                    action,
+                    SyncAction::QueueUpload { .. } | SyncAction::CreateRemoteFolderIfExist { .. }
+                )
+            });
         }
 
         plan
@@ -942,6 +1285,7 @@ impl Mount {
         path: &PathBuf,
         mode: SyncMode,
         sync_direction: SyncDirection,
+        import_existing: bool,
         remote: Option<&FileResponse>,
         local: &LocalFileInfo,
         inventory: Option<&FileMetadata>,
@@ -951,6 +1295,7 @@ impl Mount {
             (Some(remote_entry), true) => self.plan_entry_with_remote_and_local(
                 path,
                 mode,
+                import_existing,
                 remote_entry,
                 local,
                 inventory,
@@ -962,6 +1307,10 @@ impl Mount {
                 if sync_direction == SyncDirection::OneWayUpload {
                     return;
                 }
+                if self.excluded_by_type(path, remote_entry) {
+                    plan.type_filtered += 1;
+                    return;
+                }
                 plan.actions
                     .push(SyncAction::CreatePlaceholderAndInventory {
                         path: path.clone(),
@@ -979,6 +1328,7 @@ impl Mount {
         &self,
         path: &PathBuf,
         mode: SyncMode,
+        import_existing: bool,
         remote: &FileResponse,
         local: &LocalFileInfo,
         inventory: Option<&FileMetadata>,
@@ -1034,18 +1384,75 @@ impl Mount {
             return;
         }
 
-        if !etag_match || !modify_date_match {
+        // Merge mode: a local file that predates the drive is adopted if it matches remote
+        if import_existing && inventory.is_none() && !local.is_placeholder() {
+            self.plan_import_existing_file(path, remote, local, plan);
+            return;
+        }
+
+        if etag_match && !modify_date_match && local.is_placeholder() && local.in_sync() {
+            // Same content, only the metadata changed on the server
+            plan.actions.push(SyncAction::UpdateInventoryFromRemote {
+                path: path.clone(),
+                remote: remote.clone(),
+                invalidate_all: false,
+            });
+        } else if !etag_match || !modify_date_match {
             self.plan_file_actions(path, remote, local, inventory, plan);
         }
     }
 
+    fn plan_import_existing_file(
+        &self,
+        path: &PathBuf,
+        remote: &FileResponse,
+        local: &LocalFileInfo,
+        plan: &mut SyncPlan,
+    ) {
+        if local_matches_remote(local, remote) {
+            tracing::debug!(target: "drive::sync", id = %self.id, path = %path.display(), "Adopting existing local file");
+            plan.actions.push(SyncAction::UpdateInventoryFromRemote {
+                path: path.clone(),
+                remote: remote.clone(),
+                invalidate_all: false,
+            });
+            return;
+        }
+
+        // Copies and restores get new times, only the content can tell
+        if local.file_size == Some(remote.size as u64) {
+            plan.actions.push(SyncAction::AdoptIfSameContent {
+                path: path.clone(),
+                remote: remote.clone(),
+            });
+            return;
+        }
+
+        plan.actions.extend(import_conflict_actions(path, remote));
+    }
+
+    /// Turn off merge mode once the first full sync of the drive has completed.
+    async fn finish_import_existing(&self) {
+        let mut config = self.config.write().await;
+        if !config.import_existing {
+            return;
+        }
+        config.import_existing = false;
+        drop(config);
+
+        tracing::info!(target: "drive::sync", id = %self.id, "Finished importing existing local files");
+        if let Err(e) = self.manager_command_tx.send(ManagerCommand::PersistConfig) {
+            tracing::error!(target: "drive::sync", id = %self.id, error = %e, "Failed to send PersistConfig command");
+        }
+    }
+
     fn plan_entry_with_local_only(
         &self,
         path: &PathBuf,
         mode: SyncMode,
         sync_direction: SyncDirection,
         local: &LocalFileInfo,
-        _inventory: Option<&FileMetadata>,
+        inventory: Option<&FileMetadata>,
         plan: &mut SyncPlan,
     ) {
         if !local.exists {
@@ -1053,6 +1460,15 @@ impl Mount {
         }
 
         if local.is_directory {
+            // A plain folder never synced before is new locally: mirror it remotely,
+            // then walk its contents once the remote folder exists
+            if !local.is_placeholder() && inventory.is_none() {
+                plan.actions
+                    .push(SyncAction::CreateRemoteFolderIfExist { path: path.clone() });
+                self.maybe_enqueue_walk_for_directory(path, mode, local, true, false, plan);
+                return;
+            }
+
             let hydrated = local.is_folder_populated();
             if !hydrated {
                 // In one-way upload mode, skip deleting local directories that are not on remote
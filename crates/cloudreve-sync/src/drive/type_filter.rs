@@ -0,0 +1,258 @@
+//! Sparse checkout by file type.
+//!
+//! A drive can leave remote files out by type, like "no `*.psd` of 1 GB or
+//! more" or "only documents and images". Left out files get no placeholder,
+//! neither when Explorer lists a folder nor when a sync reconciles it, but
+//! stay on the server untouched. Folders are never left out, and files that
+//! already exist locally keep syncing, so changing the filter never deletes
+//! anything. The paths left out are counted per mount for the settings UI.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use cloudreve_api::models::explorer::{FileResponse, file_type};
+use globset::{GlobBuilder, GlobMatcher};
+use serde::{Deserialize, Serialize};
+
+use crate::drive::commands::MountCommand;
+use crate::drive::mounts::Mount;
+use crate::drive::sync::SyncMode;
+
+/// Kind of file, by extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileCategory {
+    Document,
+    Image,
+    Video,
+    Audio,
+    Archive,
+}
+
+impl FileCategory {
+    const ALL: [FileCategory; 5] = [
+        FileCategory::Document,
+        FileCategory::Image,
+        FileCategory::Video,
+        FileCategory::Audio,
+        FileCategory::Archive,
+    ];
+
+    fn extensions(self) -> &'static [&'static str] {
+        match self {
+            FileCategory::Document => &[
+                "doc", "docx", "odt", "rtf", "txt", "md", "pdf", "xls", "xlsx", "ods", "csv",
+                "ppt", "pptx", "odp", "pages", "numbers", "key", "epub",
+            ],
+            FileCategory::Image => &[
+                "jpg", "jpeg", "png", "gif", "bmp", "webp", "heic", "heif", "tif", "tiff", "svg",
+                "ico", "psd", "raw", "cr2", "nef", "arw", "dng",
+            ],
+            FileCategory::Video => &[
+                "mp4", "mkv", "mov", "avi", "wmv", "webm", "m4v", "flv", "mpg", "mpeg", "3gp",
+            ],
+            FileCategory::Audio => &[
+                "mp3", "flac", "wav", "aac", "ogg", "m4a", "wma", "opus", "aiff",
+            ],
+            FileCategory::Archive => &["zip", "rar", "7z", "tar", "gz", "bz2", "xz", "tgz", "iso"],
+        }
+    }
+
+    /// Category of a file name, `None` for other kinds of files
+    pub fn of(name: &str) -> Option<Self> {
+        let (_, extension) = name.rsplit_once('.')?;
+        let extension = extension.to_ascii_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|category| category.extensions().contains(&extension.as_str()))
+    }
+}
+
+/// Files left out by name, and optionally only from a size on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypeRule {
+    /// Glob matched against the file name, case-insensitive, like `*.psd`
+    pub pattern: String,
+    /// Only files of at least this many bytes are left out
+    #[serde(default)]
+    pub min_size: Option<u64>,
+}
+
+/// Remote files a drive creates no placeholders for
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypeFilter {
+    /// Only files of these categories are synced, all files if empty
+    #[serde(default)]
+    pub only: Vec<FileCategory>,
+    /// Files left out whatever their category
+    #[serde(default)]
+    pub exclude: Vec<TypeRule>,
+}
+
+/// Compiled [`TypeFilter`]
+#[derive(Debug, Clone, Default)]
+pub struct TypeMatcher {
+    only: Vec<FileCategory>,
+    exclude: Vec<(GlobMatcher, Option<u64>)>,
+}
+
+impl TypeMatcher {
+    /// Compile a filter. Fails on invalid patterns.
+    pub fn new(filter: &TypeFilter) -> Result<Self> {
+        let exclude = filter
+            .exclude
+            .iter()
+            .map(|rule| {
+                let glob = GlobBuilder::new(rule.pattern.trim())
+                    .case_insensitive(true)
+                    .build()
+                    .with_context(|| format!("Invalid file type pattern {}", rule.pattern))?;
+                Ok((glob.compile_matcher(), rule.min_size))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            only: filter.only.clone(),
+            exclude,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.only.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Whether a file named `name` of `size` bytes is left out
+    pub fn excludes(&self, name: &str, size: u64) -> bool {
+        if !self.only.is_empty()
+            && !FileCategory::of(name).is_some_and(|category| self.only.contains(&category))
+        {
+            return true;
+        }
+        self.exclude.iter().any(|(glob, min_size)| {
+            glob.is_match(name) && min_size.is_none_or(|min_size| size >= min_size)
+        })
+    }
+}
+
+/// Paths left out by the type filter of a mount, for counting them
+#[derive(Debug, Default)]
+pub struct TypeFiltered {
+    paths: HashSet<PathBuf>,
+}
+
+impl Mount {
+    /// Whether the type filter leaves the remote file at `path` out. Left
+    /// out paths are remembered for [`Mount::type_filtered_count`].
+    pub(crate) fn excluded_by_type(&self, path: &Path, remote: &FileResponse) -> bool {
+        if remote.file_type == file_type::FOLDER {
+            return false;
+        }
+        let excluded = {
+            let matcher = self.type_matcher.read().unwrap();
+            !matcher.is_empty() && matcher.excludes(&remote.name, remote.size.max(0) as u64)
+        };
+        if excluded {
+            tracing::trace!(target: "drive::type_filter", id = %self.id, path = %path.display(), "Left out by type filter");
+            self.type_filtered
+                .lock()
+                .unwrap()
+                .paths
+                .insert(path.to_path_buf());
+        }
+        excluded
+    }
+
+    /// Number of remote files seen so far that the type filter left out
+    pub fn type_filtered_count(&self) -> usize {
+        self.type_filtered.lock().unwrap().paths.len()
+    }
+
+    pub async fn get_type_filter(&self) -> TypeFilter {
+        self.config.read().await.type_filter.clone()
+    }
+
+    /// Change the type filter. Files it no longer leaves out get their
+    /// placeholders with the sync started here; files it now leaves out stay
+    /// until removed by the user.
+    pub async fn set_type_filter(&self, filter: TypeFilter) -> Result<()> {
+        let matcher = TypeMatcher::new(&filter)?;
+        *self.type_matcher.write().unwrap() = matcher;
+        self.type_filtered.lock().unwrap().paths.clear();
+        let sync_path = {
+            let mut config = self.config.write().await;
+            config.type_filter = filter;
+            config.sync_path.clone()
+        };
+        tracing::info!(target: "drive::type_filter", id = %self.id, "Type filter changed");
+
+        self.command_tx
+            .send(MountCommand::Sync {
+                local_paths: vec![sync_path],
+                mode: SyncMode::FullHierarchy,
+            })
+            .context("failed to send sync command")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GB: u64 = 1 << 30;
+
+    #[test]
+    fn large_files_of_a_type_are_left_out() {
+        let matcher = TypeMatcher::new(&TypeFilter {
+            only: Vec::new(),
+            exclude: vec![
+                TypeRule {
+                    pattern: "*.psd".to_string(),
+                    min_size: Some(GB),
+                },
+                TypeRule {
+                    pattern: "*.tmp".to_string(),
+                    min_size: None,
+                },
+            ],
+        })
+        .unwrap();
+
+        assert!(matcher.excludes("poster.PSD", 2 * GB));
+        assert!(!matcher.excludes("icon.psd", 1024));
+        assert!(matcher.excludes("build.tmp", 0));
+        assert!(!matcher.excludes("report.docx", 2 * GB));
+    }
+
+    #[test]
+    fn only_listed_categories_are_kept() {
+        let matcher = TypeMatcher::new(&TypeFilter {
+            only: vec![FileCategory::Document, FileCategory::Image],
+            exclude: Vec::new(),
+        })
+        .unwrap();
+
+        assert!(!matcher.excludes("report.PDF", 10));
+        assert!(!matcher.excludes("photo.jpeg", 10));
+        assert!(matcher.excludes("movie.mkv", 10));
+        assert!(matcher.excludes("Makefile", 10));
+    }
+
+    #[test]
+    fn files_are_categorized_by_extension() {
+        assert_eq!(FileCategory::of("a.tar.gz"), Some(FileCategory::Archive));
+        assert_eq!(FileCategory::of("song.FLAC"), Some(FileCategory::Audio));
+        assert_eq!(FileCategory::of("notes"), None);
+        assert!(TypeMatcher::default().is_empty());
+        assert!(
+            TypeMatcher::new(&TypeFilter {
+                only: Vec::new(),
+                exclude: vec![TypeRule {
+                    pattern: "[".to_string(),
+                    min_size: None,
+                }],
+            })
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,142 @@
+//! Batched download URL requests for bulk hydration, see
+//! [`UrlPool`](crate::tasks::UrlPool).
+
+use crate::{
+    cfapi::placeholder::{LocalFileInfo, OpenOptions, PinState, PlaceholderBlob},
+    drive::{mounts::Mount, utils::local_path_to_cr_uri},
+    tasks::url_pool::{URL_BATCH_SIZE, expiry_instant},
+};
+use cloudreve_api::{api::ExplorerApi, models::explorer::FileURLService};
+use std::{path::Path, time::Instant};
+
+/// File whose URL is requested in a batch
+struct UrlTarget {
+    uri: String,
+    file_id: String,
+    entity: Option<String>,
+}
+
+impl Mount {
+    /// Download URL for hydrating `path`, taken from the pool or requested
+    /// together with the pinned siblings waiting for their content. `None`
+    /// when no other file is being made available offline, in which case the
+    /// caller requests the URL on its own as before.
+    pub(crate) async fn pooled_download_url(
+        &self,
+        path: &Path,
+        uri: &str,
+        identity: &PlaceholderBlob,
+        entity: Option<&str>,
+    ) -> Option<String> {
+        let file_id = identity.file_id.as_deref()?;
+        if let Some(url) = self.download_urls.get(file_id, entity) {
+            tracing::trace!(target: "drive::url_prefetch", path = %path.display(), "Using pooled download URL");
+            return Some(url);
+        }
+
+        let siblings = self.pinned_siblings(path).await;
+        if siblings.is_empty() {
+            return None;
+        }
+
+        let mut batch = vec![UrlTarget {
+            uri: uri.to_string(),
+            file_id: file_id.to_string(),
+            entity: entity.map(str::to_string),
+        }];
+        batch.extend(siblings);
+        let request = FileURLService {
+            uris: batch.iter().map(|target| target.uri.clone()).collect(),
+            ..Default::default()
+        };
+        let response = match self.cr_client.get_file_url(&request).await {
+            Ok(response) => response,
+            Err(e) => {
+                tracing::debug!(target: "drive::url_prefetch", error = ?e, count = batch.len(), "Batched URL request failed, requesting single URL");
+                return None;
+            }
+        };
+        // URLs come back in request order; anything else can't be matched
+        if response.urls.len() != batch.len() {
+            tracing::debug!(target: "drive::url_prefetch", requested = batch.len(), returned = response.urls.len(), "Batched URL response incomplete, requesting single URL");
+            return None;
+        }
+
+        tracing::debug!(target: "drive::url_prefetch", path = %path.display(), count = batch.len(), "Prefetched download URLs");
+        let server_now = self.cr_client.server_now().await;
+        let expires = expiry_instant(&response.expires, server_now, Instant::now());
+        let mut urls = response.urls.into_iter().zip(batch);
+        let (first, _) = urls.next()?;
+        if let Some(expires) = expires {
+            for (url, target) in urls {
+                self.download_urls
+                    .insert(target.file_id, target.entity, url.url, expires);
+            }
+        }
+        Some(first.url)
+    }
+
+    /// Files next to `path` that are pinned but not yet downloaded and have
+    /// no fresh pooled URL, up to one batch
+    async fn pinned_siblings(&self, path: &Path) -> Vec<UrlTarget> {
+        let Some(parent) = path.parent().and_then(Path::to_str) else {
+            return Vec::new();
+        };
+        let children = match self.inventory.list_children(parent) {
+            Ok(children) => children,
+            Err(e) => {
+                tracing::debug!(target: "drive::url_prefetch", parent, error = ?e, "Failed to list siblings");
+                return Vec::new();
+            }
+        };
+        let (sync_root, remote_base) = {
+            let config = self.config.read().await;
+            (config.sync_path.clone(), config.remote_path.clone())
+        };
+
+        let mut siblings = Vec::new();
+        for child in children {
+            if siblings.len() + 1 >= URL_BATCH_SIZE {
+                break;
+            }
+            let child_path = Path::new(&child.local_path);
+            if child.is_folder || child_path == path {
+                continue;
+            }
+            let Ok(info) = LocalFileInfo::from_path(child_path) else {
+                continue;
+            };
+            if !info.exists
+                || !info.is_placeholder()
+                || !info.partial_on_disk()
+                || info.pinned() != PinState::Pinned
+            {
+                continue;
+            }
+            let Some(file_id) = OpenOptions::new()
+                .open_win32(child_path)
+                .ok()
+                .and_then(|handle| handle.info().ok().flatten())
+                .and_then(|info| info.identity().file_id)
+            else {
+                continue;
+            };
+            if self.download_urls.is_fresh(&file_id) {
+                continue;
+            }
+            let Ok(uri) = local_path_to_cr_uri(
+                child_path.to_path_buf(),
+                sync_root.clone(),
+                remote_base.clone(),
+            ) else {
+                continue;
+            };
+            siblings.push(UrlTarget {
+                uri: uri.to_string(),
+                file_id,
+                entity: (!child.etag.is_empty()).then_some(child.etag),
+            });
+        }
+        siblings
+    }
+}
@@ -1,4 +1,5 @@
-use std::path::PathBuf;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
 use cloudreve_api::models::uri::CrUri;
@@ -53,6 +54,81 @@ pub fn remote_path_to_local_relative_path(
     Ok(PathBuf::from(relative_path))
 }
 
+/// Whether renaming `from` to `to` only changes the letter case of the file name,
+/// which Windows treats as the same path.
+pub fn is_case_only_rename(from: &Path, to: &Path) -> bool {
+    if from == to || from.parent() != to.parent() {
+        return false;
+    }
+    match (from.file_name(), to.file_name()) {
+        (Some(a), Some(b)) => {
+            a.to_string_lossy().to_lowercase() == b.to_string_lossy().to_lowercase()
+        }
+        _ => false,
+    }
+}
+
+/// Key comparing local paths in memory the way Windows does: case folded,
+/// with `\` separators, without the `\\?\` prefix of canonical paths and
+/// without trailing separators. Does not touch the disk.
+pub fn path_key(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('/', "\\");
+    let path = if let Some(rest) = path.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{}", rest)
+    } else if let Some(rest) = path
+        .strip_prefix(r"\\?\")
+        .or_else(|| path.strip_prefix(r"\\.\"))
+    {
+        rest.to_string()
+    } else {
+        path
+    };
+
+    // Keep the leading pair of a UNC path, collapse any other repeats
+    let unc = path.starts_with(r"\\");
+    let mut key = String::with_capacity(path.len());
+    if unc {
+        key.push_str(r"\\");
+    }
+    for part in path.split('\\').filter(|part| !part.is_empty()) {
+        if !key.is_empty() && !key.ends_with('\\') {
+            key.push('\\');
+        }
+        key.push_str(part);
+    }
+    key.to_lowercase()
+}
+
+/// Whether the path with key `key` is `root` or inside it
+pub fn key_is_under(key: &str, root: &str) -> bool {
+    key.strip_prefix(root)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('\\'))
+}
+
+/// Keys a sync root is reachable under: as configured, and resolved through
+/// subst and mapped drives, junctions and links if that differs. Resolving
+/// hits the disk, so call this once per mount.
+pub fn sync_root_keys(sync_path: &Path) -> Vec<String> {
+    let mut keys = vec![path_key(sync_path)];
+    if let Ok(resolved) = sync_path.canonicalize() {
+        let resolved = path_key(&resolved);
+        if !keys.contains(&resolved) {
+            keys.push(resolved);
+        }
+    }
+    keys
+}
+
+/// Name of `path` as stored on disk, which may differ in case from `path` itself.
+pub fn on_disk_file_name(path: &Path) -> Option<OsString> {
+    let wanted = path.file_name()?.to_string_lossy().to_lowercase();
+    std::fs::read_dir(path.parent()?)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.file_name())
+        .find(|name| name.to_string_lossy().to_lowercase() == wanted)
+}
+
 /// Generate a URL to view a folder or file online.
 ///
 /// For folders: pass the folder path as `folder_path` and None for `open_file`
@@ -105,3 +181,69 @@ pub fn notify_shell_change(path: &PathBuf, event: SHCNE_ID) -> Result<()> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_case_only_rename() {
+        let dir = Path::new(r"C:\Drive\docs");
+        assert!(is_case_only_rename(&dir.join("readme.md"), &dir.join("README.md")));
+        assert!(!is_case_only_rename(&dir.join("readme.md"), &dir.join("readme.md")));
+        assert!(!is_case_only_rename(&dir.join("readme.md"), &dir.join("notes.md")));
+        assert!(!is_case_only_rename(
+            &dir.join("readme.md"),
+            &Path::new(r"C:\Drive").join("README.md")
+        ));
+    }
+
+    #[test]
+    fn test_path_key_normalizes_case_and_separators() {
+        let root = path_key(Path::new(r"C:\Users\Me\Cloudreve\"));
+        assert_eq!(root, r"c:\users\me\cloudreve");
+        assert_eq!(path_key(Path::new("c:/users//me/CLOUDREVE")), root);
+        assert_eq!(path_key(Path::new(r"\\?\C:\Users\Me\Cloudreve")), root);
+
+        assert!(key_is_under(
+            &path_key(Path::new(r"C:\Users\me\Cloudreve\a.txt")),
+            &root
+        ));
+        assert!(key_is_under(&root, &root));
+        assert!(!key_is_under(
+            &path_key(Path::new(r"C:\Users\Me\Cloudreve2\a.txt")),
+            &root
+        ));
+    }
+
+    #[test]
+    fn test_path_key_unc_and_mapped_drives() {
+        // A mapped network drive resolves to the UNC path of its share
+        let unc = path_key(Path::new(r"\\?\UNC\Server\Share\Cloud"));
+        assert_eq!(unc, r"\\server\share\cloud");
+        assert_eq!(path_key(Path::new(r"\\server\share\cloud\")), unc);
+        assert!(key_is_under(
+            &path_key(Path::new(r"\\SERVER\Share\Cloud\docs\a.txt")),
+            &unc
+        ));
+        assert!(!key_is_under(
+            &path_key(Path::new(r"\\server\share2\cloud")),
+            &path_key(Path::new(r"\\server\share"))
+        ));
+
+        let mapped = path_key(Path::new(r"Z:\Cloud"));
+        let file = path_key(Path::new(r"z:\cloud\docs\a.txt"));
+        assert!(key_is_under(&file, &mapped));
+        assert!(!key_is_under(&file, &unc));
+    }
+
+    #[test]
+    fn test_path_key_subst_drive_root() {
+        // A subst drive used as the sync root itself
+        let subst = path_key(Path::new(r"S:\"));
+        assert_eq!(subst, "s:");
+        assert!(key_is_under(&path_key(Path::new(r"S:\a.txt")), &subst));
+        assert!(!key_is_under(&path_key(Path::new(r"ST:\a.txt")), &subst));
+        assert_eq!(path_key(Path::new(r"\\.\S:\docs")), r"s:\docs");
+    }
+}
@@ -1,7 +1,16 @@
+mod outbox;
+
+use crate::config::AppConfig;
+use crate::drive::label::DriveLabel;
+use crate::drive::lifecycle::MountState;
+use crate::inventory::{InventoryDb, SyncOperation};
+use crate::tasks::ShareLink;
+use crate::utils::correlation;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use tokio::sync::broadcast;
 use tracing;
+use uuid::Uuid;
 
 /// Different types of events that can be broadcast to GUI
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,6 +25,99 @@ pub enum Event {
     OpenSyncStatusWindow,
     /// Request to open the settings window
     OpenSettingsWindow,
+    /// A drive's color/emoji label was changed
+    DriveLabelChanged {
+        drive_id: String,
+        label: Option<DriveLabel>,
+    },
+    /// Application settings were changed and applied
+    ConfigChanged {
+        config: AppConfig,
+    },
+    /// A downloaded file was flagged by the scanner and quarantined
+    SecurityThreatDetected {
+        drive_id: String,
+        path: String,
+        detail: String,
+    },
+    /// Sync started or stopped being throttled to save battery
+    PowerSavingChanged {
+        active: bool,
+    },
+    /// A presentation started or ended while presentation mode is enabled
+    PresentationModeChanged {
+        active: bool,
+    },
+    /// A drive moved to another lifecycle state
+    MountStateChanged {
+        drive_id: String,
+        state: MountState,
+    },
+    /// A file was quarantined after its sync failed repeatedly
+    SyncItemQuarantined {
+        drive_id: String,
+        path: String,
+        operation: SyncOperation,
+    },
+    /// Progress of a running task, sent about once a second while it moves
+    TaskProgress {
+        task_id: String,
+        drive_id: String,
+        path: String,
+        /// Bytes processed so far
+        processed: i64,
+        /// Total bytes, if known
+        total: Option<i64>,
+        /// Bytes per second
+        speed: u64,
+    },
+    /// Share links were created for files selected together
+    ShareLinksCreated {
+        drive_id: String,
+        links: Vec<ShareLink>,
+    },
+    /// A server went down for maintenance or came back, sync of its drives
+    /// is paused meanwhile
+    ServerMaintenanceChanged {
+        instance_url: String,
+        active: bool,
+    },
+    /// A drive's credentials expired and the user has to sign in again.
+    /// Critical, kept until acknowledged.
+    ReauthRequired {
+        event_id: String,
+        drive_id: String,
+        drive_name: String,
+    },
+    /// A file was changed both locally and in the cloud. Critical, kept
+    /// until acknowledged.
+    ConflictDetected {
+        event_id: String,
+        drive_id: String,
+        path: String,
+    },
+    /// Many files were deleted from the cloud at once by local deletions.
+    /// Critical, kept until acknowledged.
+    MassDeletionDetected {
+        event_id: String,
+        drive_id: String,
+        count: usize,
+    },
+    /// The server of a drive presented a certificate not matching its pin,
+    /// so the drive no longer connects. Critical, kept until acknowledged.
+    PinMismatch {
+        event_id: String,
+        drive_id: String,
+        drive_name: String,
+        instance_url: String,
+        /// Pins of the certificate presented, to compare with the one expected
+        presented: Vec<String>,
+    },
+    /// A critical event was acknowledged, so windows still showing it can
+    /// dismiss it
+    EventAcknowledged {
+        event_id: String,
+    },
 }
 
 impl Event {
@@ -25,6 +127,33 @@ impl Event {
             Event::NoDrive {  } => "NoDrive",
             Event::OpenSyncStatusWindow => "OpenSyncStatusWindow",
             Event::OpenSettingsWindow => "OpenSettingsWindow",
+            Event::DriveLabelChanged { .. } => "DriveLabelChanged",
+            Event::ConfigChanged { .. } => "ConfigChanged",
+            Event::SecurityThreatDetected { .. } => "SecurityThreatDetected",
+            Event::PowerSavingChanged { .. } => "PowerSavingChanged",
+            Event::PresentationModeChanged { .. } => "PresentationModeChanged",
+            Event::MountStateChanged { .. } => "MountStateChanged",
+            Event::SyncItemQuarantined { .. } => "SyncItemQuarantined",
+            Event::TaskProgress { .. } => "TaskProgress",
+            Event::ShareLinksCreated { .. } => "ShareLinksCreated",
+            Event::ServerMaintenanceChanged { .. } => "ServerMaintenanceChanged",
+            Event::ReauthRequired { .. } => "ReauthRequired",
+            Event::ConflictDetected { .. } => "ConflictDetected",
+            Event::MassDeletionDetected { .. } => "MassDeletionDetected",
+            Event::PinMismatch { .. } => "PinMismatch",
+            Event::EventAcknowledged { .. } => "EventAcknowledged",
+        }
+    }
+
+    /// Id of a critical event, which is kept until acknowledged. `None` for
+    /// other events.
+    pub fn critical_id(&self) -> Option<&str> {
+        match self {
+            Event::ReauthRequired { event_id, .. }
+            | Event::ConflictDetected { event_id, .. }
+            | Event::MassDeletionDetected { event_id, .. }
+            | Event::PinMismatch { event_id, .. } => Some(event_id),
+            _ => None,
         }
     }
 }
@@ -33,6 +162,8 @@ impl Event {
 #[derive(Clone)]
 pub struct EventBroadcaster {
     sender: Arc<broadcast::Sender<Event>>,
+    /// Where critical events are kept, see [`Self::attach_outbox`]
+    outbox: Arc<OnceLock<Arc<InventoryDb>>>,
 }
 
 impl EventBroadcaster {
@@ -44,6 +175,7 @@ impl EventBroadcaster {
         let (sender, _) = broadcast::channel(capacity);
         Self {
             sender: Arc::new(sender),
+            outbox: Arc::new(OnceLock::new()),
         }
     }
 
@@ -60,9 +192,12 @@ impl EventBroadcaster {
     /// # Returns
     /// The number of receivers that received the event
     pub fn broadcast(&self, event: Event) -> usize {
+        if event.critical_id().is_some() {
+            self.keep_critical(&event);
+        }
         match self.sender.send(event.clone()) {
             Ok(count) => {
-                tracing::debug!(target: "events", subscribers = count, "Broadcast event to subscriber(s)");
+                tracing::debug!(target: "events", event = event.name(), subscribers = count, correlation_id = %correlation::current().unwrap_or_default(), "Broadcast event to subscriber(s)");
                 tracing::trace!(target: "events", event = ?event, "Event details");
                 count
             }
@@ -93,6 +228,147 @@ impl EventBroadcaster {
         self.broadcast(Event::OpenSettingsWindow);
     }
 
+    /// Helper: Broadcast drive label changed event
+    pub fn drive_label_changed(&self, drive_id: String, label: Option<DriveLabel>) {
+        self.broadcast(Event::DriveLabelChanged { drive_id, label });
+    }
+
+    /// Helper: Broadcast config changed event
+    pub fn config_changed(&self, config: AppConfig) {
+        self.broadcast(Event::ConfigChanged { config });
+    }
+
+    /// Helper: Broadcast security threat detected event
+    pub fn security_threat_detected(&self, drive_id: String, path: String, detail: String) {
+        self.broadcast(Event::SecurityThreatDetected {
+            drive_id,
+            path,
+            detail,
+        });
+    }
+
+    /// Helper: Broadcast power saving changed event
+    pub fn power_saving_changed(&self, active: bool) {
+        self.broadcast(Event::PowerSavingChanged { active });
+    }
+
+    /// Helper: Broadcast presentation mode changed event
+    pub fn presentation_mode_changed(&self, active: bool) {
+        self.broadcast(Event::PresentationModeChanged { active });
+    }
+
+    /// Helper: Broadcast mount state changed event
+    pub fn mount_state_changed(&self, drive_id: String, state: MountState) {
+        self.broadcast(Event::MountStateChanged { drive_id, state });
+    }
+
+    /// Helper: Broadcast sync item quarantined event
+    pub fn sync_item_quarantined(&self, drive_id: String, path: String, operation: SyncOperation) {
+        self.broadcast(Event::SyncItemQuarantined {
+            drive_id,
+            path,
+            operation,
+        });
+    }
+
+    /// Helper: Broadcast task progress event
+    pub fn task_progress(
+        &self,
+        task_id: String,
+        drive_id: String,
+        path: String,
+        processed: i64,
+        total: Option<i64>,
+        speed: u64,
+    ) {
+        self.broadcast(Event::TaskProgress {
+            task_id,
+            drive_id,
+            path,
+            processed,
+            total,
+            speed,
+        });
+    }
+
+    /// Helper: Broadcast share links created event
+    pub fn share_links_created(&self, drive_id: String, links: Vec<ShareLink>) {
+        self.broadcast(Event::ShareLinksCreated { drive_id, links });
+    }
+
+    /// Helper: Broadcast server maintenance changed event
+    pub fn server_maintenance_changed(&self, instance_url: String, active: bool) {
+        self.broadcast(Event::ServerMaintenanceChanged {
+            instance_url,
+            active,
+        });
+    }
+
+    /// Helper: Broadcast reauth required event
+    pub fn reauth_required(&self, drive_id: String, drive_name: String) {
+        self.broadcast(Event::ReauthRequired {
+            event_id: Uuid::new_v4().to_string(),
+            drive_id,
+            drive_name,
+        });
+    }
+
+    /// Helper: Broadcast conflict detected event
+    pub fn conflict_detected(&self, drive_id: String, path: String) {
+        self.broadcast(Event::ConflictDetected {
+            event_id: Uuid::new_v4().to_string(),
+            drive_id,
+            path,
+        });
+    }
+
+    /// Helper: Broadcast mass deletion detected event
+    pub fn mass_deletion_detected(&self, drive_id: String, count: usize) {
+        self.broadcast(Event::MassDeletionDetected {
+            event_id: Uuid::new_v4().to_string(),
+            drive_id,
+            count,
+        });
+    }
+
+    /// Helper: Broadcast certificate pin mismatch event
+    pub fn pin_mismatch(
+        &self,
+        drive_id: String,
+        drive_name: String,
+        instance_url: String,
+        presented: Vec<String>,
+    ) {
+        self.broadcast(Event::PinMismatch {
+            event_id: Uuid::new_v4().to_string(),
+            drive_id,
+            drive_name,
+            instance_url,
+            presented,
+        });
+    }
+
+    /// Forward [ConfigManager](crate::ConfigManager) changes to subscribers (e.g. open settings windows)
+    pub fn spawn_config_bridge(&self) {
+        let Some(config_manager) = crate::ConfigManager::try_get() else {
+            return;
+        };
+        let mut receiver = config_manager.subscribe();
+        let broadcaster = self.clone();
+
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(change) => broadcaster.config_changed(change.current),
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        tracing::warn!(target: "events", skipped = n, "Config bridge lagged, some changes were skipped");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
     /// Get the number of active subscribers
     pub fn subscriber_count(&self) -> usize {
         self.sender.receiver_count()
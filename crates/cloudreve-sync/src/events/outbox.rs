@@ -0,0 +1,122 @@
+//! Outbox of critical events.
+//!
+//! A broadcast only reaches the subscribers attached when it is sent, so a
+//! conflict found before any window is open would go unnoticed. Critical
+//! events (see [`Event::critical_id`]) are therefore also kept in the
+//! inventory until the UI acknowledges them, and handed to new subscribers
+//! by [`EventBroadcaster::subscribe_with_pending`].
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use tokio::sync::broadcast;
+
+use super::{Event, EventBroadcaster};
+use crate::inventory::InventoryDb;
+
+impl EventBroadcaster {
+    /// Keep critical events in `inventory` until acknowledged. Critical
+    /// events broadcast before are only delivered to current subscribers.
+    pub fn attach_outbox(&self, inventory: Arc<InventoryDb>) {
+        if self.outbox.set(inventory).is_err() {
+            tracing::warn!(target: "events", "Event outbox already attached");
+        }
+    }
+
+    /// Store a critical event in the outbox
+    pub(super) fn keep_critical(&self, event: &Event) {
+        let (Some(outbox), Some(event_id)) = (self.outbox.get(), event.critical_id()) else {
+            return;
+        };
+        let result = serde_json::to_string(event)
+            .context("Failed to serialize event")
+            .and_then(|payload| outbox.push_outbox_event(event_id, event.name(), &payload));
+        if let Err(e) = result {
+            tracing::warn!(target: "events", event = event.name(), error = %e, "Failed to keep critical event");
+        }
+    }
+
+    /// Critical events not acknowledged yet, oldest first
+    pub fn pending_critical_events(&self) -> Result<Vec<Event>> {
+        let Some(outbox) = self.outbox.get() else {
+            return Ok(Vec::new());
+        };
+        let events = outbox
+            .pending_outbox_events()?
+            .into_iter()
+            .filter_map(|pending| match serde_json::from_str(&pending.payload) {
+                Ok(event) => Some(event),
+                Err(e) => {
+                    tracing::warn!(target: "events", event_id = %pending.event_id, kind = %pending.kind, error = %e, "Dropping unreadable critical event");
+                    None
+                }
+            })
+            .collect();
+        Ok(events)
+    }
+
+    /// Subscribe to events, also getting the critical events not
+    /// acknowledged yet. An event raised meanwhile can be in both, subscribers
+    /// tell them apart by their id.
+    pub fn subscribe_with_pending(&self) -> Result<(Vec<Event>, broadcast::Receiver<Event>)> {
+        let receiver = self.subscribe();
+        Ok((self.pending_critical_events()?, receiver))
+    }
+
+    /// Acknowledge a critical event so it is not delivered again, and tell
+    /// subscribers with [`Event::EventAcknowledged`]. Returns whether it was
+    /// pending.
+    pub fn acknowledge(&self, event_id: &str) -> Result<bool> {
+        let Some(outbox) = self.outbox.get() else {
+            return Ok(false);
+        };
+        let pending = outbox.acknowledge_outbox_event(event_id)?;
+        if pending {
+            self.broadcast(Event::EventAcknowledged {
+                event_id: event_id.to_string(),
+            });
+        }
+        Ok(pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn critical_events_wait_for_acknowledgement() {
+        let dir = tempfile::tempdir().unwrap();
+        let inventory = Arc::new(InventoryDb::with_path(dir.path().join("meta.db")).unwrap());
+        let broadcaster = EventBroadcaster::new(16);
+        broadcaster.attach_outbox(inventory);
+
+        // Nobody listens yet
+        broadcaster.conflict_detected("drive".to_string(), "C:\\a.txt".to_string());
+        broadcaster.power_saving_changed(true);
+
+        let (pending, mut receiver) = broadcaster.subscribe_with_pending().unwrap();
+        assert_eq!(pending.len(), 1);
+        let Event::ConflictDetected { event_id, path, .. } = &pending[0] else {
+            panic!("unexpected event {:?}", pending[0]);
+        };
+        assert_eq!(path, "C:\\a.txt");
+
+        broadcaster.reauth_required("drive".to_string(), "Work".to_string());
+        let live = receiver.recv().await.unwrap();
+        assert_eq!(live.name(), "ReauthRequired");
+
+        assert!(broadcaster.acknowledge(event_id).unwrap());
+        let Event::EventAcknowledged {
+            event_id: acknowledged,
+        } = receiver.recv().await.unwrap()
+        else {
+            panic!("acknowledgement not broadcast");
+        };
+        assert_eq!(&acknowledged, event_id);
+        assert!(!broadcaster.acknowledge(event_id).unwrap());
+        let pending = broadcaster.pending_critical_events().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].critical_id(), live.critical_id());
+    }
+}
@@ -0,0 +1,83 @@
+//! Scheduling of expensive background work for idle periods
+//!
+//! Full reconciles and inventory snapshots read and write a lot. With
+//! [`IdleSchedule`] enabled they wait until the user has not used keyboard or
+//! mouse for the configured time, so they do not compete with the user for IO,
+//! but never longer than [`MAX_IDLE_WAIT`]. Power saving (see
+//! [`crate::power`]) and presentations (see [`crate::presentation`]) defer
+//! them as well, without a limit.
+
+use crate::config::{ConfigManager, IdleSchedule};
+use crate::power;
+use crate::presentation;
+use std::time::{Duration, Instant};
+
+/// Run deferred work even if the user is still active after this long
+pub const MAX_IDLE_WAIT: Duration = Duration::from_secs(60 * 60);
+
+/// How often waiting work checks whether it may run
+const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Time since the last keyboard or mouse input, `None` if unavailable
+pub fn user_idle_time() -> Option<Duration> {
+    use windows::Win32::System::SystemInformation::GetTickCount;
+    use windows::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+    let mut info = LASTINPUTINFO {
+        cbSize: size_of::<LASTINPUTINFO>() as u32,
+        dwTime: 0,
+    };
+    if !unsafe { GetLastInputInfo(&mut info) }.as_bool() {
+        return None;
+    }
+    // Both are milliseconds since boot and wrap around after 49.7 days
+    let now = unsafe { GetTickCount() };
+    Some(Duration::from_millis(now.wrapping_sub(info.dwTime) as u64))
+}
+
+/// Whether background work that has been waiting for `waited` may run now
+pub fn may_run_background_work(waited: Duration) -> bool {
+    if power::is_power_saving() || presentation::pauses_transfers() {
+        return false;
+    }
+    let settings = ConfigManager::try_get()
+        .map(|config| config.idle_schedule())
+        .unwrap_or_default();
+    waited >= MAX_IDLE_WAIT || is_user_idle(&settings, user_idle_time())
+}
+
+/// Wait until background work may run
+pub async fn wait_for_idle() {
+    let start = Instant::now();
+    while !may_run_background_work(start.elapsed()) {
+        tokio::time::sleep(IDLE_POLL_INTERVAL).await;
+    }
+}
+
+/// An unknown idle time does not hold work back
+fn is_user_idle(settings: &IdleSchedule, idle_time: Option<Duration>) -> bool {
+    !settings.enabled
+        || idle_time.is_none_or(|idle| idle >= Duration::from_secs(settings.idle_threshold_secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idle_after_threshold_when_enabled() {
+        let settings = IdleSchedule {
+            enabled: true,
+            idle_threshold_secs: 300,
+        };
+        assert!(!is_user_idle(&settings, Some(Duration::from_secs(10))));
+        assert!(is_user_idle(&settings, Some(Duration::from_secs(300))));
+        assert!(is_user_idle(&settings, None));
+
+        let disabled = IdleSchedule {
+            enabled: false,
+            ..settings
+        };
+        assert!(is_user_idle(&disabled, Some(Duration::ZERO)));
+    }
+}
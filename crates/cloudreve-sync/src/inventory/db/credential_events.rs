@@ -0,0 +1,182 @@
+use super::InventoryDb;
+use anyhow::{Context, Result, anyhow};
+use chrono::Utc;
+use diesel::prelude::*;
+
+use crate::inventory::schema::credential_events::{self, dsl as credential_events_dsl};
+use crate::inventory::{CredentialEvent, CredentialEventKind, CredentialSource};
+
+/// Events kept per drive, older ones are dropped as new ones are recorded
+const MAX_EVENTS_PER_DRIVE: i64 = 500;
+
+impl InventoryDb {
+    /// Record a use of a drive's credentials. `detail` must never contain
+    /// tokens.
+    pub fn record_credential_event(
+        &self,
+        drive_id: &str,
+        kind: CredentialEventKind,
+        source: CredentialSource,
+        detail: &str,
+    ) -> Result<()> {
+        let mut conn = self.connection()?;
+        (&mut *conn)
+            .transaction::<_, diesel::result::Error, _>(|tx_conn| {
+                diesel::insert_into(credential_events::table)
+                    .values(&NewCredentialEventRow {
+                        drive_id: drive_id.to_string(),
+                        kind: kind.as_str().to_string(),
+                        source: source.as_str().to_string(),
+                        detail: detail.to_string(),
+                        occurred_at: Utc::now().timestamp(),
+                    })
+                    .execute(tx_conn)?;
+
+                let oldest_kept = credential_events_dsl::credential_events
+                    .filter(credential_events_dsl::drive_id.eq(drive_id))
+                    .order(credential_events_dsl::id.desc())
+                    .offset(MAX_EVENTS_PER_DRIVE - 1)
+                    .select(credential_events_dsl::id)
+                    .first::<i64>(tx_conn)
+                    .optional()?;
+                if let Some(oldest_kept) = oldest_kept {
+                    diesel::delete(
+                        credential_events_dsl::credential_events
+                            .filter(credential_events_dsl::drive_id.eq(drive_id))
+                            .filter(credential_events_dsl::id.lt(oldest_kept)),
+                    )
+                    .execute(tx_conn)?;
+                }
+                Ok(())
+            })
+            .context("Failed to record credential event")
+    }
+
+    /// List the credential events of a drive, most recent first
+    pub fn list_credential_events(
+        &self,
+        drive_id: &str,
+        limit: i64,
+    ) -> Result<Vec<CredentialEvent>> {
+        let mut conn = self.connection()?;
+        let rows = credential_events_dsl::credential_events
+            .filter(credential_events_dsl::drive_id.eq(drive_id))
+            .order(credential_events_dsl::id.desc())
+            .limit(limit)
+            .load::<CredentialEventRow>(&mut conn)
+            .context("Failed to list credential events")?;
+        rows.into_iter().map(CredentialEvent::try_from).collect()
+    }
+
+    /// Number of token refreshes of a drive that failed since the last
+    /// successful refresh or sign-in
+    pub fn consecutive_refresh_failures(&self, drive_id: &str) -> Result<i64> {
+        let mut conn = self.connection()?;
+        let last_other = credential_events_dsl::credential_events
+            .filter(credential_events_dsl::drive_id.eq(drive_id))
+            .filter(credential_events_dsl::kind.ne(CredentialEventKind::RefreshFailed.as_str()))
+            .select(diesel::dsl::max(credential_events_dsl::id))
+            .first::<Option<i64>>(&mut conn)
+            .context("Failed to query credential events")?
+            .unwrap_or(0);
+        credential_events_dsl::credential_events
+            .filter(credential_events_dsl::drive_id.eq(drive_id))
+            .filter(credential_events_dsl::kind.eq(CredentialEventKind::RefreshFailed.as_str()))
+            .filter(credential_events_dsl::id.gt(last_other))
+            .count()
+            .get_result(&mut conn)
+            .context("Failed to count refresh failures")
+    }
+}
+
+// =========================================================================
+// Row Types
+// =========================================================================
+
+#[derive(Queryable)]
+#[diesel(table_name = credential_events)]
+struct CredentialEventRow {
+    id: i64,
+    drive_id: String,
+    kind: String,
+    source: String,
+    detail: String,
+    occurred_at: i64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = credential_events)]
+struct NewCredentialEventRow {
+    drive_id: String,
+    kind: String,
+    source: String,
+    detail: String,
+    occurred_at: i64,
+}
+
+impl TryFrom<CredentialEventRow> for CredentialEvent {
+    type Error = anyhow::Error;
+
+    fn try_from(row: CredentialEventRow) -> Result<Self> {
+        Ok(Self {
+            id: row.id,
+            kind: CredentialEventKind::from_str(&row.kind)
+                .ok_or_else(|| anyhow!("Unknown credential event {}", row.kind))?,
+            source: CredentialSource::from_str(&row.source)
+                .ok_or_else(|| anyhow!("Unknown credential source {}", row.source))?,
+            drive_id: row.drive_id,
+            detail: row.detail,
+            occurred_at: row.occurred_at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refresh_failures_are_counted_until_success() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = InventoryDb::with_path(dir.path().join("meta.db")).unwrap();
+        let failed = |drive: &str| {
+            db.record_credential_event(
+                drive,
+                CredentialEventKind::RefreshFailed,
+                CredentialSource::AutoRefresh,
+                "network unreachable",
+            )
+            .unwrap()
+        };
+
+        db.record_credential_event(
+            "drive",
+            CredentialEventKind::Login,
+            CredentialSource::User,
+            "",
+        )
+        .unwrap();
+        failed("drive");
+        failed("drive");
+        failed("other");
+        assert_eq!(db.consecutive_refresh_failures("drive").unwrap(), 2);
+
+        db.record_credential_event(
+            "drive",
+            CredentialEventKind::Refreshed,
+            CredentialSource::AutoRefresh,
+            "",
+        )
+        .unwrap();
+        assert_eq!(db.consecutive_refresh_failures("drive").unwrap(), 0);
+        failed("drive");
+        assert_eq!(db.consecutive_refresh_failures("drive").unwrap(), 1);
+
+        let events = db.list_credential_events("drive", 10).unwrap();
+        assert_eq!(events.len(), 5);
+        assert_eq!(events[0].kind, CredentialEventKind::RefreshFailed);
+        assert_eq!(events[0].detail, "network unreachable");
+        assert_eq!(events[4].kind, CredentialEventKind::Login);
+        assert_eq!(events[4].source, CredentialSource::User);
+    }
+}
@@ -0,0 +1,147 @@
+use super::InventoryDb;
+use anyhow::{Context, Result};
+use chrono::Utc;
+use diesel::prelude::*;
+
+use crate::inventory::OutboxEvent;
+use crate::inventory::schema::event_outbox::{self, dsl as outbox_dsl};
+
+/// Pending events kept at most, the oldest are dropped first
+const MAX_PENDING_EVENTS: i64 = 200;
+/// How long acknowledged events are kept, in seconds
+const ACKNOWLEDGED_RETENTION_SECS: i64 = 7 * 24 * 60 * 60;
+
+impl InventoryDb {
+    /// Keep an event until it is acknowledged
+    pub fn push_outbox_event(&self, event_id: &str, kind: &str, payload: &str) -> Result<()> {
+        let now = Utc::now().timestamp();
+        let mut conn = self.connection()?;
+        (&mut *conn)
+            .transaction::<_, diesel::result::Error, _>(|tx_conn| {
+                diesel::insert_into(event_outbox::table)
+                    .values(&NewOutboxEventRow {
+                        event_id: event_id.to_string(),
+                        kind: kind.to_string(),
+                        payload: payload.to_string(),
+                        created_at: now,
+                    })
+                    .execute(tx_conn)?;
+
+                diesel::delete(
+                    outbox_dsl::event_outbox
+                        .filter(outbox_dsl::acknowledged_at.lt(now - ACKNOWLEDGED_RETENTION_SECS)),
+                )
+                .execute(tx_conn)?;
+
+                let oldest_kept = outbox_dsl::event_outbox
+                    .filter(outbox_dsl::acknowledged_at.is_null())
+                    .order(outbox_dsl::id.desc())
+                    .offset(MAX_PENDING_EVENTS - 1)
+                    .select(outbox_dsl::id)
+                    .first::<i64>(tx_conn)
+                    .optional()?;
+                if let Some(oldest_kept) = oldest_kept {
+                    diesel::delete(
+                        outbox_dsl::event_outbox
+                            .filter(outbox_dsl::acknowledged_at.is_null())
+                            .filter(outbox_dsl::id.lt(oldest_kept)),
+                    )
+                    .execute(tx_conn)?;
+                }
+                Ok(())
+            })
+            .context("Failed to store outbox event")
+    }
+
+    /// Events not acknowledged yet, oldest first
+    pub fn pending_outbox_events(&self) -> Result<Vec<OutboxEvent>> {
+        let mut conn = self.connection()?;
+        let rows = outbox_dsl::event_outbox
+            .filter(outbox_dsl::acknowledged_at.is_null())
+            .order(outbox_dsl::id.asc())
+            .select((
+                outbox_dsl::event_id,
+                outbox_dsl::kind,
+                outbox_dsl::payload,
+                outbox_dsl::created_at,
+            ))
+            .load::<OutboxEventRow>(&mut conn)
+            .context("Failed to list outbox events")?;
+        Ok(rows.into_iter().map(OutboxEvent::from).collect())
+    }
+
+    /// Mark an event acknowledged. Returns whether it was pending.
+    pub fn acknowledge_outbox_event(&self, event_id: &str) -> Result<bool> {
+        let mut conn = self.connection()?;
+        let updated = diesel::update(
+            outbox_dsl::event_outbox
+                .filter(outbox_dsl::event_id.eq(event_id))
+                .filter(outbox_dsl::acknowledged_at.is_null()),
+        )
+        .set(outbox_dsl::acknowledged_at.eq(Some(Utc::now().timestamp())))
+        .execute(&mut conn)
+        .context("Failed to acknowledge outbox event")?;
+        Ok(updated > 0)
+    }
+}
+
+// =========================================================================
+// Row Types
+// =========================================================================
+
+#[derive(Queryable)]
+#[diesel(table_name = event_outbox)]
+struct OutboxEventRow {
+    event_id: String,
+    kind: String,
+    payload: String,
+    created_at: i64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = event_outbox)]
+struct NewOutboxEventRow {
+    event_id: String,
+    kind: String,
+    payload: String,
+    created_at: i64,
+}
+
+impl From<OutboxEventRow> for OutboxEvent {
+    fn from(row: OutboxEventRow) -> Self {
+        Self {
+            event_id: row.event_id,
+            kind: row.kind,
+            payload: row.payload,
+            created_at: row.created_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn acknowledged_events_are_no_longer_pending() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = InventoryDb::with_path(dir.path().join("meta.db")).unwrap();
+        db.push_outbox_event("a", "ReauthRequired", "{}").unwrap();
+        db.push_outbox_event("b", "ConflictDetected", "{}").unwrap();
+
+        let pending = db.pending_outbox_events().unwrap();
+        let ids: Vec<_> = pending
+            .iter()
+            .map(|event| event.event_id.as_str())
+            .collect();
+        assert_eq!(ids, ["a", "b"]);
+        assert_eq!(pending[1].kind, "ConflictDetected");
+
+        assert!(db.acknowledge_outbox_event("a").unwrap());
+        assert!(!db.acknowledge_outbox_event("a").unwrap());
+        assert!(!db.acknowledge_outbox_event("unknown").unwrap());
+        let pending = db.pending_outbox_events().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].event_id, "b");
+    }
+}
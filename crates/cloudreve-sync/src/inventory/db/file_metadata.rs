@@ -6,6 +6,7 @@ use anyhow::{Context, Result};
 use diesel::prelude::*;
 use diesel::sql_types::Text;
 use std::collections::HashMap;
+use std::path::Path;
 use uuid::Uuid;
 
 use crate::inventory::schema::file_metadata::{self, dsl as file_metadata_dsl};
@@ -102,6 +103,57 @@ impl InventoryDb {
         row.map(FileMetadata::try_from).transpose()
     }
 
+    /// Query the direct children of a folder, e.g. to list it while the
+    /// server is unreachable
+    pub fn list_children(&self, parent: &str) -> Result<Vec<FileMetadata>> {
+        let parent = parent.trim_end_matches(['/', '\\']);
+        let child_of = |sep: char| {
+            file_metadata_dsl::local_path
+                .like(format!("{parent}{sep}%"))
+                .and(file_metadata_dsl::local_path.not_like(format!("{parent}{sep}%{sep}%")))
+        };
+
+        let mut conn = self.connection()?;
+        let rows = file_metadata_dsl::file_metadata
+            .filter(child_of('/').or(child_of('\\')))
+            .load::<FileMetadataRow>(&mut conn)
+            .context("Failed to list inventory children")?;
+
+        // `_` and `%` in the parent path are wildcards to LIKE
+        let parent = Path::new(parent);
+        rows.into_iter()
+            .map(FileMetadata::try_from)
+            .filter(|row| match row {
+                Ok(meta) => Path::new(&meta.local_path).parent() == Some(parent),
+                Err(_) => true,
+            })
+            .collect()
+    }
+
+    /// Query every file (not folder) of a drive
+    pub fn list_drive_files(&self, drive: &str) -> Result<Vec<FileMetadata>> {
+        let mut conn = self.connection()?;
+        let rows = file_metadata_dsl::file_metadata
+            .filter(file_metadata_dsl::drive_id.eq(drive))
+            .filter(file_metadata_dsl::is_folder.eq(false))
+            .load::<FileMetadataRow>(&mut conn)
+            .context("Failed to list inventory files of drive")?;
+
+        rows.into_iter().map(FileMetadata::try_from).collect()
+    }
+
+    /// Query the files of a drive with a conflict waiting for the user
+    pub fn list_conflicts(&self, drive: &str) -> Result<Vec<FileMetadata>> {
+        let mut conn = self.connection()?;
+        let rows = file_metadata_dsl::file_metadata
+            .filter(file_metadata_dsl::drive_id.eq(drive))
+            .filter(file_metadata_dsl::conflict_state.eq(ConflictState::Pending.as_str()))
+            .load::<FileMetadataRow>(&mut conn)
+            .context("Failed to list conflicted files")?;
+
+        rows.into_iter().map(FileMetadata::try_from).collect()
+    }
+
     /// Batch delete file metadata by local path
     pub fn batch_delete_by_path(&self, paths: Vec<&str>) -> Result<bool> {
         if paths.is_empty() {
@@ -134,6 +186,8 @@ impl InventoryDb {
 
         // Delete upload sessions - now safe to acquire a new connection
         self.batch_delete_upload_session_by_path(&paths)?;
+        self.batch_release_quarantine_by_path(&paths)?;
+        self.batch_delete_sync_failures_by_path(&paths)?;
         Ok(affected > 0)
     }
 
@@ -196,6 +250,33 @@ impl InventoryDb {
         Ok(total)
     }
 
+    /// Re-key an entry stored under a path that differs from `path` only by letter
+    /// case, together with its descendants. Local paths are case-insensitive on
+    /// Windows, so both keys name the same file; SQLite `NOCASE` folds ASCII only.
+    ///
+    /// Returns the number of rows updated.
+    pub fn normalize_path_case(&self, path: &str) -> Result<usize> {
+        let stored = {
+            let mut conn = self.connection()?;
+            file_metadata_dsl::file_metadata
+                .select(file_metadata_dsl::local_path)
+                .filter(
+                    diesel::dsl::sql::<diesel::sql_types::Bool>("local_path = ")
+                        .bind::<Text, _>(path)
+                        .sql(" COLLATE NOCASE"),
+                )
+                .filter(file_metadata_dsl::local_path.ne(path))
+                .first::<String>(&mut conn)
+                .optional()
+                .context("Failed to query inventory metadata by path")?
+        };
+
+        match stored {
+            Some(stored) => self.rename_path(&stored, path),
+            None => Ok(0),
+        }
+    }
+
     /// Mark a file as conflicted by setting its conflict_state.
     /// Pass `None` to clear the conflict state.
     ///
@@ -354,3 +435,38 @@ impl FileMetadataChangeset {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn children_exclude_deeper_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = InventoryDb::with_path(dir.path().join("meta.db")).unwrap();
+        let drive = Uuid::new_v4();
+        let entries: Vec<MetadataEntry> = [
+            ("C:/Drive/docs", true),
+            ("C:/Drive/docs/a.txt", false),
+            ("C:/Drive/docs/sub", true),
+            ("C:/Drive/docs/sub/b.txt", false),
+            ("C:/Drive/docs2/c.txt", false),
+            ("C:/Drive/dots/d.txt", false),
+        ]
+        .into_iter()
+        .map(|(path, is_folder)| MetadataEntry::new(drive, path, is_folder))
+        .collect();
+        db.batch_insert(&entries).unwrap();
+
+        let mut children: Vec<String> = db
+            .list_children("C:/Drive/docs/")
+            .unwrap()
+            .into_iter()
+            .map(|meta| meta.local_path)
+            .collect();
+        children.sort();
+        assert_eq!(children, vec!["C:/Drive/docs/a.txt", "C:/Drive/docs/sub"]);
+        // `_` matches any character to LIKE
+        assert!(db.list_children("C:/Drive/do_s").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,86 @@
+use super::InventoryDb;
+use anyhow::{Context, Result};
+use chrono::Utc;
+use diesel::prelude::*;
+
+use crate::inventory::schema::initial_sync_reports::{self, dsl as reports_dsl};
+
+impl InventoryDb {
+    /// Store the report of a drive's first full sync, as JSON. Returns false
+    /// if the drive has a report already, which is kept.
+    pub fn save_initial_sync_report(&self, drive_id: &str, report: &str) -> Result<bool> {
+        let mut conn = self.connection()?;
+        let inserted = diesel::insert_or_ignore_into(initial_sync_reports::table)
+            .values(&NewInitialSyncReportRow {
+                drive_id: drive_id.to_string(),
+                report: report.to_string(),
+                created_at: Utc::now().timestamp(),
+            })
+            .execute(&mut conn)
+            .context("Failed to store initial sync report")?;
+        Ok(inserted > 0)
+    }
+
+    /// The report of a drive's first full sync as JSON, if it was written
+    pub fn initial_sync_report(&self, drive_id: &str) -> Result<Option<String>> {
+        let mut conn = self.connection()?;
+        reports_dsl::initial_sync_reports
+            .filter(reports_dsl::drive_id.eq(drive_id))
+            .select(reports_dsl::report)
+            .first::<String>(&mut conn)
+            .optional()
+            .context("Failed to query initial sync report")
+    }
+
+    /// Whether the first full sync of a drive was reported already
+    pub fn has_initial_sync_report(&self, drive_id: &str) -> Result<bool> {
+        let mut conn = self.connection()?;
+        let exists: Option<i64> = reports_dsl::initial_sync_reports
+            .filter(reports_dsl::drive_id.eq(drive_id))
+            .select(reports_dsl::id)
+            .first(&mut conn)
+            .optional()
+            .context("Failed to check initial sync report existence")?;
+        Ok(exists.is_some())
+    }
+}
+
+// =========================================================================
+// Row Types
+// =========================================================================
+
+#[derive(Insertable)]
+#[diesel(table_name = initial_sync_reports)]
+struct NewInitialSyncReportRow {
+    drive_id: String,
+    report: String,
+    created_at: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_report_is_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = InventoryDb::with_path(dir.path().join("meta.db")).unwrap();
+        assert!(!db.has_initial_sync_report("drive").unwrap());
+        assert_eq!(db.initial_sync_report("drive").unwrap(), None);
+
+        assert!(
+            db.save_initial_sync_report("drive", "{\"files\":1}")
+                .unwrap()
+        );
+        assert!(
+            !db.save_initial_sync_report("drive", "{\"files\":2}")
+                .unwrap()
+        );
+
+        assert!(db.has_initial_sync_report("drive").unwrap());
+        assert_eq!(
+            db.initial_sync_report("drive").unwrap().as_deref(),
+            Some("{\"files\":1}")
+        );
+    }
+}
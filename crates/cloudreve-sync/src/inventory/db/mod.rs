@@ -1,13 +1,22 @@
+mod credential_events;
 mod drive_props;
+mod event_outbox;
 mod file_metadata;
+mod initial_sync_reports;
+mod pending_intake;
+mod quarantine;
+mod snapshots;
+mod sync_failures;
 mod tasks;
 mod upload_sessions;
 
+pub use snapshots::InventorySnapshot;
 pub use tasks::RecentTasks;
 
 use anyhow::{Context, Result, anyhow};
-use diesel::Connection;
+use diesel::prelude::*;
 use diesel::r2d2::{ConnectionManager, Pool, PooledConnection};
+use diesel::sql_types::Text;
 use diesel::sqlite::SqliteConnection;
 use diesel_migrations::{EmbeddedMigrations, MigrationHarness, embed_migrations};
 use dirs::home_dir;
@@ -17,9 +26,34 @@ use std::sync::Arc;
 
 const MIGRATIONS: EmbeddedMigrations = embed_migrations!("./migrations/inventory");
 
+/// Tables whose rows belong to a drive
+const DRIVE_TABLES: &[&str] = &[
+    "file_metadata",
+    "task_queue",
+    "upload_sessions",
+    "drive_props",
+    "quarantined_files",
+    "sync_failures",
+    "credential_events",
+    "pending_intake",
+    "initial_sync_reports",
+];
+
+/// Tables of [`DRIVE_TABLES`] with a `local_path` column
+const PATH_TABLES: &[&str] = &[
+    "file_metadata",
+    "task_queue",
+    "upload_sessions",
+    "quarantined_files",
+    "sync_failures",
+    "pending_intake",
+];
+
 /// SQLite-backed inventory database that relies on Diesel for schema management.
 pub struct InventoryDb {
     pool: Arc<Pool<ConnectionManager<SqliteConnection>>>,
+    /// Location of the database file, snapshots are kept next to it
+    path: PathBuf,
 }
 
 impl InventoryDb {
@@ -56,6 +90,7 @@ impl InventoryDb {
 
         Ok(Self {
             pool: Arc::new(pool),
+            path,
         })
     }
 
@@ -64,6 +99,104 @@ impl InventoryDb {
         Ok(home.join(".cloudreve").join("meta.db"))
     }
 
+    /// Move every row of drive `old` to drive `new`, for a drive given a new
+    /// ID. Returns the number of rows moved.
+    pub fn reassign_drive_id(&self, old: &str, new: &str) -> Result<usize> {
+        let mut conn = self.connection()?;
+        conn.transaction::<_, diesel::result::Error, _>(|tx_conn| {
+            let mut moved = 0;
+            for table in DRIVE_TABLES {
+                moved += diesel::sql_query(format!(
+                    "UPDATE {table} SET drive_id = ? WHERE drive_id = ?"
+                ))
+                .bind::<Text, _>(new)
+                .bind::<Text, _>(old)
+                .execute(tx_conn)?;
+            }
+            Ok(moved)
+        })
+        .context("Failed to reassign drive ID")
+    }
+
+    /// Move the rows of drive `drive` from the sync root `old_root` to
+    /// `new_root`, for a drive whose folder moved. Returns the number of rows
+    /// updated.
+    pub fn move_drive_root(&self, drive: &str, old_root: &str, new_root: &str) -> Result<usize> {
+        if old_root == new_root {
+            return Ok(0);
+        }
+        let old_prefix = format!("{}{}", old_root, std::path::MAIN_SEPARATOR);
+        let new_prefix = format!("{}{}", new_root, std::path::MAIN_SEPARATOR);
+        let descendant_like = format!("{}%", old_prefix);
+
+        let mut conn = self.connection()?;
+        conn.transaction::<_, diesel::result::Error, _>(|tx_conn| {
+            let mut moved = 0;
+            for table in PATH_TABLES {
+                moved += diesel::sql_query(format!(
+                    "UPDATE {table} SET local_path = ? WHERE drive_id = ? AND local_path = ?"
+                ))
+                .bind::<Text, _>(new_root)
+                .bind::<Text, _>(drive)
+                .bind::<Text, _>(old_root)
+                .execute(tx_conn)?;
+                moved += diesel::sql_query(format!(
+                    "UPDATE {table} SET local_path = ? || substr(local_path, length(?) + 1) \
+                     WHERE drive_id = ? AND local_path LIKE ?"
+                ))
+                .bind::<Text, _>(&new_prefix)
+                .bind::<Text, _>(&old_prefix)
+                .bind::<Text, _>(drive)
+                .bind::<Text, _>(&descendant_like)
+                .execute(tx_conn)?;
+            }
+            Ok(moved)
+        })
+        .context("Failed to move drive rows to new sync root")
+    }
+
+    /// Delete every row of drive `drive`, for a removed drive. Returns the
+    /// number of rows deleted.
+    pub fn purge_drive(&self, drive: &str) -> Result<usize> {
+        let mut conn = self.connection()?;
+        conn.transaction::<_, diesel::result::Error, _>(|tx_conn| {
+            let mut deleted = 0;
+            for table in DRIVE_TABLES {
+                deleted += diesel::sql_query(format!("DELETE FROM {table} WHERE drive_id = ?"))
+                    .bind::<Text, _>(drive)
+                    .execute(tx_conn)?;
+            }
+            Ok(deleted)
+        })
+        .context("Failed to purge drive rows")
+    }
+
+    /// IDs of the drives that have rows in any table
+    pub fn drive_ids(&self) -> Result<Vec<String>> {
+        let mut conn = self.connection()?;
+        let query = DRIVE_TABLES
+            .iter()
+            .map(|table| format!("SELECT drive_id FROM {table}"))
+            .collect::<Vec<_>>()
+            .join(" UNION ");
+        let rows = diesel::sql_query(query)
+            .load::<DriveIdRow>(&mut conn)
+            .context("Failed to list drive IDs")?;
+        Ok(rows.into_iter().map(|row| row.drive_id).collect())
+    }
+
+    /// Rebuild the database file, giving the space of deleted rows back.
+    /// Returns the number of bytes reclaimed.
+    pub fn vacuum(&self) -> Result<u64> {
+        let before = fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0);
+        let mut conn = self.connection()?;
+        diesel::sql_query("VACUUM")
+            .execute(&mut conn)
+            .context("Failed to vacuum inventory database")?;
+        let after = fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0);
+        Ok(before.saturating_sub(after))
+    }
+
     pub(crate) fn connection(
         &self,
     ) -> Result<PooledConnection<ConnectionManager<SqliteConnection>>> {
@@ -73,6 +206,12 @@ impl InventoryDb {
     }
 }
 
+#[derive(QueryableByName)]
+struct DriveIdRow {
+    #[diesel(sql_type = Text)]
+    drive_id: String,
+}
+
 fn run_migrations(database_url: &str) -> Result<()> {
     let mut conn = SqliteConnection::establish(database_url)
         .with_context(|| format!("Failed to open inventory database at {}", database_url))?;
@@ -80,3 +219,80 @@ fn run_migrations(database_url: &str) -> Result<()> {
         .map_err(|err| anyhow!("Failed to run inventory database migrations: {err}"))?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inventory::MetadataEntry;
+    use uuid::Uuid;
+
+    #[test]
+    fn reassigned_drive_keeps_its_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = InventoryDb::with_path(dir.path().join("meta.db")).unwrap();
+        let drive = Uuid::new_v4();
+        db.insert(&MetadataEntry::new(drive, "C:/Drive/a.txt", false))
+            .unwrap();
+        db.insert(&MetadataEntry::new(Uuid::new_v4(), "C:/Other/b.txt", false))
+            .unwrap();
+
+        let new_id = Uuid::new_v4().to_string();
+        assert_eq!(
+            db.reassign_drive_id(&drive.to_string(), &new_id).unwrap(),
+            1
+        );
+        let meta = db.query_by_path("C:/Drive/a.txt").unwrap().unwrap();
+        assert_eq!(meta.drive_id.to_string(), new_id);
+        assert_eq!(db.list_drive_files(&new_id).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn purged_drive_leaves_no_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = InventoryDb::with_path(dir.path().join("meta.db")).unwrap();
+        let drive = Uuid::new_v4();
+        let other = Uuid::new_v4();
+        db.insert(&MetadataEntry::new(drive, "C:/Drive/a.txt", false))
+            .unwrap();
+        db.insert(&MetadataEntry::new(other, "C:/Other/b.txt", false))
+            .unwrap();
+        db.save_initial_sync_report(&drive.to_string(), "{}")
+            .unwrap();
+
+        assert_eq!(db.drive_ids().unwrap().len(), 2);
+        assert_eq!(db.purge_drive(&drive.to_string()).unwrap(), 2);
+        assert_eq!(db.drive_ids().unwrap(), vec![other.to_string()]);
+        assert!(db.query_by_path("C:/Other/b.txt").unwrap().is_some());
+        db.vacuum().unwrap();
+    }
+
+    #[test]
+    fn moved_drive_root_keeps_its_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = InventoryDb::with_path(dir.path().join("meta.db")).unwrap();
+        let drive = Uuid::new_v4();
+        db.insert(&MetadataEntry::new(drive, "C:/Drive", true))
+            .unwrap();
+        db.insert(&MetadataEntry::new(drive, "C:/Drive/docs/a.txt", false))
+            .unwrap();
+        db.insert(&MetadataEntry::new(
+            Uuid::new_v4(),
+            "C:/Drive2/b.txt",
+            false,
+        ))
+        .unwrap();
+
+        let moved = db
+            .move_drive_root(&drive.to_string(), "C:/Drive", "D:/Sync/Drive")
+            .unwrap();
+        assert_eq!(moved, 2);
+        assert!(db.query_by_path("D:/Sync/Drive").unwrap().is_some());
+        assert!(
+            db.query_by_path("D:/Sync/Drive/docs/a.txt")
+                .unwrap()
+                .is_some()
+        );
+        assert!(db.query_by_path("C:/Drive2/b.txt").unwrap().is_some());
+        assert!(db.query_by_path("C:/Drive/docs/a.txt").unwrap().is_none());
+    }
+}
@@ -0,0 +1,157 @@
+use super::InventoryDb;
+use anyhow::{Context, Result, anyhow};
+use chrono::Utc;
+use diesel::prelude::*;
+
+use crate::inventory::schema::pending_intake::{self, dsl as intake_dsl};
+use crate::inventory::{IntakeReason, PendingIntake};
+
+impl InventoryDb {
+    /// Remember local files found to need an upload until their task is
+    /// queued. Files recorded already keep their first discovery time.
+    pub fn record_pending_intake(
+        &self,
+        drive_id: &str,
+        entries: &[(String, IntakeReason)],
+    ) -> Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let now = Utc::now().timestamp();
+        let rows: Vec<NewPendingIntakeRow> = entries
+            .iter()
+            .map(|(local_path, reason)| NewPendingIntakeRow {
+                drive_id: drive_id.to_string(),
+                local_path: local_path.clone(),
+                reason: reason.as_str().to_string(),
+                discovered_at: now,
+            })
+            .collect();
+        let mut conn = self.connection()?;
+        (&mut *conn)
+            .transaction::<_, diesel::result::Error, _>(|tx_conn| {
+                for row in &rows {
+                    diesel::insert_or_ignore_into(pending_intake::table)
+                        .values(row)
+                        .execute(tx_conn)?;
+                }
+                Ok(())
+            })
+            .context("Failed to record pending intake")
+    }
+
+    /// Forget files whose upload is queued or no longer needed. Returns the
+    /// number of entries removed.
+    pub fn remove_pending_intake(&self, drive_id: &str, local_paths: &[&str]) -> Result<usize> {
+        if local_paths.is_empty() {
+            return Ok(0);
+        }
+        let mut conn = self.connection()?;
+        diesel::delete(
+            intake_dsl::pending_intake
+                .filter(intake_dsl::drive_id.eq(drive_id))
+                .filter(intake_dsl::local_path.eq_any(local_paths)),
+        )
+        .execute(&mut conn)
+        .context("Failed to remove pending intake")
+    }
+
+    /// Files of a drive waiting to be queued for upload, oldest first
+    pub fn list_pending_intake(&self, drive_id: &str) -> Result<Vec<PendingIntake>> {
+        let mut conn = self.connection()?;
+        let rows = intake_dsl::pending_intake
+            .filter(intake_dsl::drive_id.eq(drive_id))
+            .order((intake_dsl::discovered_at.asc(), intake_dsl::id.asc()))
+            .select((
+                intake_dsl::drive_id,
+                intake_dsl::local_path,
+                intake_dsl::reason,
+                intake_dsl::discovered_at,
+            ))
+            .load::<PendingIntakeRow>(&mut conn)
+            .context("Failed to list pending intake")?;
+        rows.into_iter().map(PendingIntake::try_from).collect()
+    }
+}
+
+// =========================================================================
+// Row Types
+// =========================================================================
+
+#[derive(Queryable)]
+#[diesel(table_name = pending_intake)]
+struct PendingIntakeRow {
+    drive_id: String,
+    local_path: String,
+    reason: String,
+    discovered_at: i64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = pending_intake)]
+struct NewPendingIntakeRow {
+    drive_id: String,
+    local_path: String,
+    reason: String,
+    discovered_at: i64,
+}
+
+impl TryFrom<PendingIntakeRow> for PendingIntake {
+    type Error = anyhow::Error;
+
+    fn try_from(row: PendingIntakeRow) -> Result<Self> {
+        let reason = IntakeReason::from_str(&row.reason)
+            .ok_or_else(|| anyhow!("Unknown intake reason: {}", row.reason))?;
+        Ok(Self {
+            drive_id: row.drive_id,
+            local_path: row.local_path,
+            reason,
+            discovered_at: row.discovered_at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intake_is_kept_until_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = InventoryDb::with_path(dir.path().join("meta.db")).unwrap();
+        db.record_pending_intake(
+            "drive",
+            &[
+                ("C:\\Drive\\a.txt".to_string(), IntakeReason::Created),
+                ("C:\\Drive\\b.txt".to_string(), IntakeReason::RemoteMissing),
+            ],
+        )
+        .unwrap();
+        // Recording again keeps the first entry
+        db.record_pending_intake(
+            "drive",
+            &[("C:\\Drive\\a.txt".to_string(), IntakeReason::Modified)],
+        )
+        .unwrap();
+        db.record_pending_intake(
+            "other",
+            &[("D:\\Other\\c.txt".to_string(), IntakeReason::Bulk)],
+        )
+        .unwrap();
+
+        let pending = db.list_pending_intake("drive").unwrap();
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].local_path, "C:\\Drive\\a.txt");
+        assert_eq!(pending[0].reason, IntakeReason::Created);
+
+        assert_eq!(
+            db.remove_pending_intake("drive", &["C:\\Drive\\a.txt"])
+                .unwrap(),
+            1
+        );
+        let pending = db.list_pending_intake("drive").unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].reason, IntakeReason::RemoteMissing);
+        assert_eq!(db.list_pending_intake("other").unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,130 @@
+use super::InventoryDb;
+use anyhow::{Context, Result};
+use chrono::Utc;
+use diesel::prelude::*;
+
+use crate::inventory::QuarantinedFile;
+use crate::inventory::schema::quarantined_files::{self, dsl as quarantined_files_dsl};
+
+impl InventoryDb {
+    /// Record a file as quarantined, replacing any previous record for it
+    pub fn quarantine_file(&self, drive_id: &str, local_path: &str, detail: &str) -> Result<()> {
+        let mut conn = self.connection()?;
+        let row = QuarantinedFileRow {
+            drive_id: drive_id.to_string(),
+            local_path: local_path.to_string(),
+            detail: detail.to_string(),
+            detected_at: Utc::now().timestamp(),
+        };
+        diesel::replace_into(quarantined_files::table)
+            .values(&row)
+            .execute(&mut conn)
+            .context("Failed to record quarantined file")?;
+        Ok(())
+    }
+
+    /// Get the quarantine record of a file, if it is quarantined
+    pub fn get_quarantined(&self, local_path: &str) -> Result<Option<QuarantinedFile>> {
+        let mut conn = self.connection()?;
+        let row = quarantined_files_dsl::quarantined_files
+            .filter(quarantined_files_dsl::local_path.eq(local_path))
+            .first::<QuarantinedFileRow>(&mut conn)
+            .optional()
+            .context("Failed to query quarantined file")?;
+        Ok(row.map(QuarantinedFile::from))
+    }
+
+    /// List quarantined files of a drive, most recent first
+    pub fn list_quarantined(&self, drive_id: &str) -> Result<Vec<QuarantinedFile>> {
+        let mut conn = self.connection()?;
+        let rows = quarantined_files_dsl::quarantined_files
+            .filter(quarantined_files_dsl::drive_id.eq(drive_id))
+            .order(quarantined_files_dsl::detected_at.desc())
+            .load::<QuarantinedFileRow>(&mut conn)
+            .context("Failed to list quarantined files")?;
+        Ok(rows.into_iter().map(QuarantinedFile::from).collect())
+    }
+
+    /// Release a file from quarantine. Returns true if it was quarantined.
+    pub fn release_quarantine(&self, local_path: &str) -> Result<bool> {
+        let mut conn = self.connection()?;
+        let rows_affected = diesel::delete(
+            quarantined_files_dsl::quarantined_files
+                .filter(quarantined_files_dsl::local_path.eq(local_path)),
+        )
+        .execute(&mut conn)
+        .context("Failed to release quarantined file")?;
+        Ok(rows_affected > 0)
+    }
+
+    /// Drop quarantine records of files at, or under, the given paths
+    pub fn batch_release_quarantine_by_path(&self, paths: &[&str]) -> Result<()> {
+        if paths.is_empty() {
+            return Ok(());
+        }
+
+        let mut conn = self.connection()?;
+        for path in paths {
+            let prefix = format!("{}{}%", path, std::path::MAIN_SEPARATOR);
+            diesel::delete(
+                quarantined_files_dsl::quarantined_files.filter(
+                    quarantined_files_dsl::local_path
+                        .eq(path)
+                        .or(quarantined_files_dsl::local_path.like(&prefix)),
+                ),
+            )
+            .execute(&mut conn)
+            .context("Failed to release quarantined files")?;
+        }
+        Ok(())
+    }
+}
+
+// =========================================================================
+// Row Types
+// =========================================================================
+
+#[derive(Queryable, Insertable)]
+#[diesel(table_name = quarantined_files)]
+struct QuarantinedFileRow {
+    drive_id: String,
+    local_path: String,
+    detail: String,
+    detected_at: i64,
+}
+
+impl From<QuarantinedFileRow> for QuarantinedFile {
+    fn from(row: QuarantinedFileRow) -> Self {
+        Self {
+            drive_id: row.drive_id,
+            local_path: row.local_path,
+            detail: row.detail,
+            detected_at: row.detected_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn quarantine_is_released_with_parent_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = InventoryDb::with_path(dir.path().join("meta.db")).unwrap();
+        let folder = Path::new("Drive").join("shared");
+        let file = folder.join("setup.exe");
+        let (folder, file) = (folder.to_str().unwrap(), file.to_str().unwrap());
+        db.quarantine_file("drive", file, "Trojan:Win32/Test")
+            .unwrap();
+
+        let record = db.get_quarantined(file).unwrap().unwrap();
+        assert_eq!(record.detail, "Trojan:Win32/Test");
+        assert_eq!(db.list_quarantined("drive").unwrap().len(), 1);
+
+        db.batch_release_quarantine_by_path(&[folder]).unwrap();
+        assert!(db.get_quarantined(file).unwrap().is_none());
+        assert!(!db.release_quarantine(file).unwrap());
+    }
+}
@@ -0,0 +1,236 @@
+use super::InventoryDb;
+use anyhow::{Context, Result, anyhow};
+use chrono::Utc;
+use diesel::prelude::*;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Number of snapshots kept, older ones are deleted when a new one is taken
+const MAX_SNAPSHOTS: usize = 7;
+const SNAPSHOT_PREFIX: &str = "inventory-";
+const SNAPSHOT_EXTENSION: &str = "db";
+
+/// Columns restored from a snapshot, per table
+const RESTORED_TABLES: &[(&str, &str)] = &[
+    (
+        "file_metadata",
+        "id, drive_id, is_folder, local_path, created_at, updated_at, etag, metadata, props, \
+         permissions, shared, size, conflict_state",
+    ),
+    (
+        "drive_props",
+        "id, drive_id, capacity, capacity_updated_at, storage_policies, \
+         storage_policies_updated_at, user_settings, user_settings_updated_at, created_at, \
+         updated_at",
+    ),
+];
+
+/// A copy of the inventory database taken at a point in time
+#[derive(Debug, Clone, Serialize)]
+pub struct InventorySnapshot {
+    /// Unix timestamp (seconds) the snapshot was taken at, also its identifier
+    pub timestamp: i64,
+    pub path: PathBuf,
+    /// Size of the snapshot file in bytes
+    pub size: u64,
+}
+
+impl InventoryDb {
+    fn snapshot_dir(&self) -> PathBuf {
+        self.path
+            .parent()
+            .map(|parent| parent.join("snapshots"))
+            .unwrap_or_else(|| PathBuf::from("snapshots"))
+    }
+
+    /// Copy the whole database into a new snapshot file and prune old ones
+    pub fn create_snapshot(&self) -> Result<InventorySnapshot> {
+        let snapshot = self.write_snapshot()?;
+        self.prune_snapshots()?;
+        Ok(snapshot)
+    }
+
+    /// Copy the whole database into a new snapshot file
+    fn write_snapshot(&self) -> Result<InventorySnapshot> {
+        let dir = self.snapshot_dir();
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create snapshot dir {}", dir.display()))?;
+
+        // Never overwrite a snapshot taken within the same second
+        let mut timestamp = Utc::now().timestamp();
+        while snapshot_path(&dir, timestamp).exists() {
+            timestamp += 1;
+        }
+        let path = snapshot_path(&dir, timestamp);
+
+        let mut conn = self.connection()?;
+        diesel::sql_query(format!("VACUUM INTO {}", sql_string(&path)?))
+            .execute(&mut conn)
+            .context("Failed to write inventory snapshot")?;
+        drop(conn);
+
+        let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        tracing::info!(target: "inventory", path = %path.display(), size, "Inventory snapshot created");
+        Ok(InventorySnapshot {
+            timestamp,
+            path,
+            size,
+        })
+    }
+
+    /// Delete all but the newest [MAX_SNAPSHOTS] snapshots
+    fn prune_snapshots(&self) -> Result<()> {
+        for stale in self.list_snapshots()?.into_iter().skip(MAX_SNAPSHOTS) {
+            if let Err(e) = fs::remove_file(&stale.path) {
+                tracing::warn!(target: "inventory", path = %stale.path.display(), error = %e, "Failed to delete old inventory snapshot");
+            }
+        }
+        Ok(())
+    }
+
+    /// List available snapshots, newest first
+    pub fn list_snapshots(&self) -> Result<Vec<InventorySnapshot>> {
+        let dir = self.snapshot_dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut snapshots = Vec::new();
+        for entry in fs::read_dir(&dir).context("Failed to read snapshot dir")? {
+            let entry = entry?;
+            let path = entry.path();
+            let Some(timestamp) = parse_snapshot_name(&path) else {
+                continue;
+            };
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            snapshots.push(InventorySnapshot {
+                timestamp,
+                path,
+                size,
+            });
+        }
+        snapshots.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        Ok(snapshots)
+    }
+
+    /// Replace file metadata and drive props with the content of the snapshot
+    /// taken at `timestamp`. The current state is snapshotted first, so the
+    /// restore itself can be undone. Old snapshots are only pruned once the
+    /// restore is done, which may otherwise delete the one being restored.
+    pub fn restore_snapshot(&self, timestamp: i64) -> Result<()> {
+        let path = snapshot_path(&self.snapshot_dir(), timestamp);
+        if !path.exists() {
+            return Err(anyhow!("No inventory snapshot at {}", timestamp));
+        }
+        self.write_snapshot()
+            .context("Failed to back up inventory before restoring")?;
+
+        let mut conn = self.connection()?;
+        diesel::sql_query(format!("ATTACH DATABASE {} AS snapshot", sql_string(&path)?))
+            .execute(&mut conn)
+            .context("Failed to open inventory snapshot")?;
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|tx_conn| {
+            for (table, columns) in RESTORED_TABLES {
+                diesel::sql_query(format!("DELETE FROM main.{table}")).execute(tx_conn)?;
+                diesel::sql_query(format!(
+                    "INSERT INTO main.{table} ({columns}) SELECT {columns} FROM snapshot.{table}"
+                ))
+                .execute(tx_conn)?;
+            }
+            Ok(())
+        });
+
+        let detached = diesel::sql_query("DETACH DATABASE snapshot").execute(&mut conn);
+        result.context("Failed to restore inventory snapshot")?;
+        detached.context("Failed to close inventory snapshot")?;
+
+        tracing::info!(target: "inventory", timestamp, "Inventory restored from snapshot");
+        self.prune_snapshots()
+    }
+}
+
+fn snapshot_path(dir: &Path, timestamp: i64) -> PathBuf {
+    dir.join(format!(
+        "{}{}.{}",
+        SNAPSHOT_PREFIX, timestamp, SNAPSHOT_EXTENSION
+    ))
+}
+
+fn parse_snapshot_name(path: &Path) -> Option<i64> {
+    if path.extension()?.to_str()? != SNAPSHOT_EXTENSION {
+        return None;
+    }
+    path.file_stem()?
+        .to_str()?
+        .strip_prefix(SNAPSHOT_PREFIX)?
+        .parse()
+        .ok()
+}
+
+/// Quote a path as an SQL string literal
+fn sql_string(path: &Path) -> Result<String> {
+    let path = path
+        .to_str()
+        .ok_or_else(|| anyhow!("Invalid snapshot path"))?;
+    Ok(format!("'{}'", path.replace('\'', "''")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inventory::DrivePropsUpdate;
+    use cloudreve_api::models::user::Capacity;
+
+    #[test]
+    fn restores_drive_props_from_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = InventoryDb::with_path(dir.path().join("meta.db")).unwrap();
+        let capacity = Capacity {
+            total: 100,
+            used: 10,
+            storage_pack_total: 0,
+        };
+        db.upsert_drive_props("drive", DrivePropsUpdate::default().with_capacity(capacity))
+            .unwrap();
+
+        let snapshot = db.create_snapshot().unwrap();
+        db.delete_drive_props("drive").unwrap();
+        assert!(!db.has_drive_props("drive").unwrap());
+
+        db.restore_snapshot(snapshot.timestamp).unwrap();
+        assert!(db.has_drive_props("drive").unwrap());
+        // The state before the restore was kept as a snapshot too
+        assert_eq!(db.list_snapshots().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn restores_the_oldest_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = InventoryDb::with_path(dir.path().join("meta.db")).unwrap();
+        for _ in 0..MAX_SNAPSHOTS {
+            db.create_snapshot().unwrap();
+        }
+        let oldest = db.list_snapshots().unwrap().last().unwrap().timestamp;
+
+        db.restore_snapshot(oldest).unwrap();
+        let snapshots = db.list_snapshots().unwrap();
+        assert_eq!(snapshots.len(), MAX_SNAPSHOTS);
+        // The backup taken before the restore is the newest one kept
+        assert!(snapshots.iter().all(|s| s.timestamp != oldest));
+    }
+
+    #[test]
+    fn parses_snapshot_names() {
+        assert_eq!(
+            parse_snapshot_name(Path::new("snapshots/inventory-1700000000.db")),
+            Some(1700000000)
+        );
+        assert_eq!(parse_snapshot_name(Path::new("snapshots/other.db")), None);
+        assert_eq!(
+            parse_snapshot_name(Path::new("snapshots/inventory-1700000000.db-journal")),
+            None
+        );
+    }
+}
@@ -0,0 +1,287 @@
+use super::InventoryDb;
+use anyhow::{Context, Result, anyhow};
+use chrono::Utc;
+use diesel::prelude::*;
+
+use crate::inventory::schema::sync_failures::{self, dsl as sync_failures_dsl};
+use crate::inventory::{SyncFailure, SyncFailureState, SyncOperation};
+
+impl InventoryDb {
+    /// Count a failed operation on a file. Once it failed `threshold` times
+    /// in a row the file is quarantined. Returns the updated record.
+    pub fn record_sync_failure(
+        &self,
+        drive_id: &str,
+        local_path: &str,
+        operation: SyncOperation,
+        error: &str,
+        threshold: i32,
+    ) -> Result<SyncFailure> {
+        let mut conn = self.connection()?;
+        let row = (&mut *conn)
+            .transaction::<SyncFailureRow, diesel::result::Error, _>(|tx_conn| {
+                let query = sync_failures_dsl::sync_failures
+                    .filter(sync_failures_dsl::local_path.eq(local_path))
+                    .filter(sync_failures_dsl::operation.eq(operation.as_str()));
+                let existing = query.clone().first::<SyncFailureRow>(tx_conn).optional()?;
+                let now = Utc::now().timestamp();
+
+                match existing {
+                    Some(existing) => {
+                        let failures = existing.failures + 1;
+                        let state = if existing.state == SyncFailureState::Failing.as_str()
+                            && failures >= threshold
+                        {
+                            SyncFailureState::Quarantined.as_str()
+                        } else {
+                            existing.state.as_str()
+                        };
+                        diesel::update(sync_failures_dsl::sync_failures.find(existing.id))
+                            .set((
+                                sync_failures_dsl::failures.eq(failures),
+                                sync_failures_dsl::last_error.eq(error),
+                                sync_failures_dsl::state.eq(state),
+                                sync_failures_dsl::updated_at.eq(now),
+                            ))
+                            .execute(tx_conn)?;
+                    }
+                    None => {
+                        let state = if threshold <= 1 {
+                            SyncFailureState::Quarantined
+                        } else {
+                            SyncFailureState::Failing
+                        };
+                        diesel::insert_into(sync_failures::table)
+                            .values(&NewSyncFailureRow {
+                                drive_id: drive_id.to_string(),
+                                local_path: local_path.to_string(),
+                                operation: operation.as_str().to_string(),
+                                failures: 1,
+                                last_error: error.to_string(),
+                                state: state.as_str().to_string(),
+                                updated_at: now,
+                            })
+                            .execute(tx_conn)?;
+                    }
+                }
+                query.first::<SyncFailureRow>(tx_conn)
+            })
+            .context("Failed to record sync failure")?;
+        SyncFailure::try_from(row)
+    }
+
+    /// Forget failures of an operation on a file, e.g. after it succeeded.
+    /// Returns true if there were any.
+    pub fn clear_sync_failure(&self, local_path: &str, operation: SyncOperation) -> Result<bool> {
+        let mut conn = self.connection()?;
+        let rows_affected = diesel::delete(
+            sync_failures_dsl::sync_failures
+                .filter(sync_failures_dsl::local_path.eq(local_path))
+                .filter(sync_failures_dsl::operation.eq(operation.as_str())),
+        )
+        .execute(&mut conn)
+        .context("Failed to clear sync failure")?;
+        Ok(rows_affected > 0)
+    }
+
+    /// State of an operation on a file that failed before, if any
+    pub fn sync_failure_state(
+        &self,
+        local_path: &str,
+        operation: SyncOperation,
+    ) -> Result<Option<SyncFailureState>> {
+        let mut conn = self.connection()?;
+        let state = sync_failures_dsl::sync_failures
+            .filter(sync_failures_dsl::local_path.eq(local_path))
+            .filter(sync_failures_dsl::operation.eq(operation.as_str()))
+            .select(sync_failures_dsl::state)
+            .first::<String>(&mut conn)
+            .optional()
+            .context("Failed to query sync failure")?;
+        Ok(state.as_deref().and_then(SyncFailureState::from_str))
+    }
+
+    /// List failures of a drive in the given states, most recent first
+    pub fn list_sync_failures(
+        &self,
+        drive_id: &str,
+        states: &[SyncFailureState],
+    ) -> Result<Vec<SyncFailure>> {
+        let states: Vec<&str> = states.iter().map(|s| s.as_str()).collect();
+        let mut conn = self.connection()?;
+        let rows = sync_failures_dsl::sync_failures
+            .filter(sync_failures_dsl::drive_id.eq(drive_id))
+            .filter(sync_failures_dsl::state.eq_any(states))
+            .order(sync_failures_dsl::updated_at.desc())
+            .load::<SyncFailureRow>(&mut conn)
+            .context("Failed to list sync failures")?;
+        rows.into_iter().map(SyncFailure::try_from).collect()
+    }
+
+    /// Number of quarantined files of a drive
+    pub fn count_quarantined_failures(&self, drive_id: &str) -> Result<i64> {
+        let mut conn = self.connection()?;
+        sync_failures_dsl::sync_failures
+            .filter(sync_failures_dsl::drive_id.eq(drive_id))
+            .filter(sync_failures_dsl::state.eq(SyncFailureState::Quarantined.as_str()))
+            .count()
+            .get_result(&mut conn)
+            .context("Failed to count quarantined files")
+    }
+
+    /// Remove failure records so the files are synced again. Returns the
+    /// removed records.
+    pub fn take_sync_failures(&self, ids: &[i64]) -> Result<Vec<SyncFailure>> {
+        let mut conn = self.connection()?;
+        let rows = (&mut *conn)
+            .transaction::<Vec<SyncFailureRow>, diesel::result::Error, _>(|tx_conn| {
+                let rows = sync_failures_dsl::sync_failures
+                    .filter(sync_failures_dsl::id.eq_any(ids))
+                    .load::<SyncFailureRow>(tx_conn)?;
+                diesel::delete(
+                    sync_failures_dsl::sync_failures.filter(sync_failures_dsl::id.eq_any(ids)),
+                )
+                .execute(tx_conn)?;
+                Ok(rows)
+            })
+            .context("Failed to remove sync failures")?;
+        rows.into_iter().map(SyncFailure::try_from).collect()
+    }
+
+    /// Stop syncing the files of the given failure records. Returns the
+    /// number of records changed.
+    pub fn ignore_sync_failures(&self, ids: &[i64]) -> Result<usize> {
+        let mut conn = self.connection()?;
+        diesel::update(sync_failures_dsl::sync_failures.filter(sync_failures_dsl::id.eq_any(ids)))
+            .set((
+                sync_failures_dsl::state.eq(SyncFailureState::Ignored.as_str()),
+                sync_failures_dsl::updated_at.eq(Utc::now().timestamp()),
+            ))
+            .execute(&mut conn)
+            .context("Failed to ignore sync failures")
+    }
+
+    /// Drop failure records of files at, or under, the given paths
+    pub fn batch_delete_sync_failures_by_path(&self, paths: &[&str]) -> Result<()> {
+        if paths.is_empty() {
+            return Ok(());
+        }
+
+        let mut conn = self.connection()?;
+        for path in paths {
+            let prefix = format!("{}{}%", path, std::path::MAIN_SEPARATOR);
+            diesel::delete(
+                sync_failures_dsl::sync_failures.filter(
+                    sync_failures_dsl::local_path
+                        .eq(path)
+                        .or(sync_failures_dsl::local_path.like(&prefix)),
+                ),
+            )
+            .execute(&mut conn)
+            .context("Failed to delete sync failures")?;
+        }
+        Ok(())
+    }
+}
+
+// =========================================================================
+// Row Types
+// =========================================================================
+
+#[derive(Queryable)]
+#[diesel(table_name = sync_failures)]
+struct SyncFailureRow {
+    id: i64,
+    drive_id: String,
+    local_path: String,
+    operation: String,
+    failures: i32,
+    last_error: String,
+    state: String,
+    updated_at: i64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = sync_failures)]
+struct NewSyncFailureRow {
+    drive_id: String,
+    local_path: String,
+    operation: String,
+    failures: i32,
+    last_error: String,
+    state: String,
+    updated_at: i64,
+}
+
+impl TryFrom<SyncFailureRow> for SyncFailure {
+    type Error = anyhow::Error;
+
+    fn try_from(row: SyncFailureRow) -> Result<Self> {
+        Ok(Self {
+            id: row.id,
+            operation: SyncOperation::from_str(&row.operation)
+                .ok_or_else(|| anyhow!("Unknown sync operation {}", row.operation))?,
+            state: SyncFailureState::from_str(&row.state)
+                .ok_or_else(|| anyhow!("Unknown sync failure state {}", row.state))?,
+            drive_id: row.drive_id,
+            local_path: row.local_path,
+            failures: row.failures,
+            last_error: row.last_error,
+            updated_at: row.updated_at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_failures_are_quarantined() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = InventoryDb::with_path(dir.path().join("meta.db")).unwrap();
+        let path = "C:/Drive/report.docx";
+
+        for _ in 0..2 {
+            let failure = db
+                .record_sync_failure("drive", path, SyncOperation::Upload, "timeout", 3)
+                .unwrap();
+            assert_eq!(failure.state, SyncFailureState::Failing);
+        }
+        let failure = db
+            .record_sync_failure("drive", path, SyncOperation::Upload, "too large", 3)
+            .unwrap();
+        assert_eq!(failure.state, SyncFailureState::Quarantined);
+        assert_eq!(failure.failures, 3);
+        assert_eq!(failure.last_error, "too large");
+        // Other operations on the same file are counted separately
+        assert_eq!(
+            db.sync_failure_state(path, SyncOperation::Download)
+                .unwrap(),
+            None
+        );
+        assert_eq!(db.count_quarantined_failures("drive").unwrap(), 1);
+
+        assert_eq!(db.ignore_sync_failures(&[failure.id]).unwrap(), 1);
+        assert_eq!(
+            db.sync_failure_state(path, SyncOperation::Upload).unwrap(),
+            Some(SyncFailureState::Ignored)
+        );
+        // Ignored files stay ignored when they fail again
+        db.record_sync_failure("drive", path, SyncOperation::Upload, "timeout", 3)
+            .unwrap();
+        assert_eq!(
+            db.list_sync_failures("drive", &[SyncFailureState::Ignored])
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let taken = db.take_sync_failures(&[failure.id]).unwrap();
+        assert_eq!(taken.len(), 1);
+        assert_eq!(
+            db.sync_failure_state(path, SyncOperation::Upload).unwrap(),
+            None
+        );
+    }
+}
@@ -85,6 +85,21 @@ impl InventoryDb {
             .collect::<Result<Vec<_>>>()
     }
 
+    /// List every task record of one file, oldest first
+    pub fn list_tasks_for_path(&self, drive_id: &str, path: &str) -> Result<Vec<TaskRecord>> {
+        let mut conn = self.connection()?;
+        let rows = task_queue_dsl::task_queue
+            .filter(task_queue_dsl::drive_id.eq(drive_id))
+            .filter(task_queue_dsl::local_path.eq(path))
+            .order(task_queue_dsl::created_at.asc())
+            .load::<TaskRow>(&mut conn)
+            .context("Failed to query task records of file")?;
+
+        rows.into_iter()
+            .map(TaskRecord::try_from)
+            .collect::<Result<Vec<_>>>()
+    }
+
     /// Delete a completed/failed task entry
     pub fn delete_task(&self, task_id: &str) -> Result<()> {
         let mut conn = self.connection()?;
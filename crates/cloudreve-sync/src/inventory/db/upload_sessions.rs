@@ -75,6 +75,18 @@ impl InventoryDb {
         Ok(affected > 0)
     }
 
+    /// Delete the upload sessions of drive `drive`, for a drive whose remote
+    /// folder changed. Returns the number of sessions deleted.
+    pub fn delete_upload_sessions_by_drive(&self, drive: &str) -> Result<usize> {
+        let mut conn = self.connection()?;
+        let deleted = diesel::delete(
+            upload_sessions_dsl::upload_sessions.filter(upload_sessions_dsl::drive_id.eq(drive)),
+        )
+        .execute(&mut conn)
+        .context("Failed to delete upload sessions of drive")?;
+        Ok(deleted)
+    }
+
     /// Delete expired upload sessions
     pub fn delete_expired_upload_sessions(&self) -> Result<usize> {
         let mut conn = self.connection()?;
@@ -2,10 +2,12 @@ mod db;
 mod models;
 pub(crate) mod schema;
 
-pub use db::{InventoryDb, RecentTasks};
+pub use db::{InventoryDb, InventorySnapshot, RecentTasks};
 pub use models::{
-    ConflictState, DriveProps, DrivePropsUpdate, FileMetadata, MetadataEntry, NewTaskRecord,
-    TaskRecord, TaskStatus, TaskUpdate,
+    ConflictState, CredentialEvent, CredentialEventKind, CredentialSource, DriveProps,
+    DrivePropsUpdate, FileMetadata, FileOwner, IntakeReason, MetadataEntry, NewTaskRecord,
+    OutboxEvent, PendingIntake, QuarantinedFile, SyncFailure, SyncFailureState, SyncOperation,
+    TaskRecord, TaskStatus, TaskUpdate, mtime_matches, remote_props,
 };
 
 pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
@@ -1,7 +1,8 @@
-use cloudreve_api::models::explorer::StoragePolicy;
+use cloudreve_api::models::explorer::{FileResponse, StoragePolicy};
 use cloudreve_api::models::user::{Capacity, UserSettings};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
 use uuid::Uuid;
 
 /// Represents the conflict state of a file with the remote
@@ -30,7 +31,66 @@ impl ConflictState {
     }
 }
 
+/// Key in `props` holding the [`FileOwner`] of a file the user does not own
+const OWNER_PROP: &str = "owner";
+
+/// Key in `props` holding the ID of the file on the server
+const FILE_ID_PROP: &str = "file_id";
+
+/// Props recording the server ID of `file` and, if it belongs to someone
+/// else, its [`FileOwner`]
+pub fn remote_props(file: &FileResponse) -> serde_json::Value {
+    let mut props = serde_json::json!({ FILE_ID_PROP: file.id });
+    if let Some(owner) = FileOwner::of(file).and_then(|owner| serde_json::to_value(owner).ok()) {
+        props[OWNER_PROP] = owner;
+    }
+    props
+}
+
+/// Owner of a file the user does not own, such as in a group folder
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FileOwner {
+    pub id: String,
+    pub nickname: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+}
+
+impl FileOwner {
+    /// Owner of `file`, if it belongs to someone else
+    pub fn of(file: &FileResponse) -> Option<Self> {
+        if file.owned != Some(false) {
+            return None;
+        }
+        file.owner.as_ref().map(|owner| Self {
+            id: owner.id.clone(),
+            nickname: owner.nickname.clone(),
+            group: owner.group.as_ref().map(|g| g.name.clone()),
+        })
+    }
+
+    fn from_props(props: Option<&serde_json::Value>) -> Option<Self> {
+        serde_json::from_value(props?.get(OWNER_PROP)?.clone()).ok()
+    }
+}
+
+/// Whether a local modification time is within `tolerance_secs` of a Unix
+/// timestamp recorded from the server. An unknown local time never matches.
+pub fn mtime_matches(local: Option<SystemTime>, recorded: i64, tolerance_secs: u64) -> bool {
+    local
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| (d.as_secs() as i64).abs_diff(recorded) <= tolerance_secs)
+        .unwrap_or(false)
+}
+
 /// Represents a file metadata entry in the inventory
+///
+/// `etag` and `updated_at` are recorded from the server and are what remote
+/// changes are detected against: a different etag means different content, a
+/// different `updated_at` with the same etag only changed metadata. Neither
+/// depends on the local clock. A local file's modification time is only a
+/// hint that the file changed, compared with [`mtime_matches`] within a
+/// tolerance since clocks and file system precision differ between machines.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileMetadata {
     pub id: i64,
@@ -38,7 +98,9 @@ pub struct FileMetadata {
     pub is_folder: bool,
     pub local_path: String,
     pub created_at: i64, // Unix timestamp
-    pub updated_at: i64, // Unix timestamp
+    /// Modification time reported by the server, Unix timestamp
+    pub updated_at: i64,
+    /// ID of the file's current content on the server (its primary entity)
     pub etag: String,
     pub metadata: HashMap<String, String>,
     pub props: Option<serde_json::Value>,
@@ -48,7 +110,22 @@ pub struct FileMetadata {
     pub conflict_state: Option<ConflictState>,
 }
 
-/// Entry for inserting or updating file metadata
+impl FileMetadata {
+    /// Owner of the file if the user does not own it
+    pub fn owner(&self) -> Option<FileOwner> {
+        FileOwner::from_props(self.props.as_ref())
+    }
+
+    /// ID of the file on the server, unknown for entries recorded before it
+    /// was kept
+    pub fn file_id(&self) -> Option<&str> {
+        self.props.as_ref()?.get(FILE_ID_PROP)?.as_str()
+    }
+}
+
+/// Entry for inserting or updating file metadata. `updated_at` defaults to the
+/// local time and should be set from the server with
+/// [`MetadataEntry::with_updated_at`]; see [`FileMetadata`].
 #[derive(Debug, Clone)]
 pub struct MetadataEntry {
     pub drive_id: Uuid,
@@ -296,6 +373,234 @@ impl TaskUpdate {
     }
 }
 
+/// A downloaded file that was flagged by the scanner
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuarantinedFile {
+    pub drive_id: String,
+    pub local_path: String,
+    /// Scanner output describing the threat
+    pub detail: String,
+    pub detected_at: i64, // Unix timestamp
+}
+
+/// Operation that failed for a file
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncOperation {
+    Upload,
+    Download,
+    Rename,
+}
+
+impl SyncOperation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SyncOperation::Upload => "upload",
+            SyncOperation::Download => "download",
+            SyncOperation::Rename => "rename",
+        }
+    }
+
+    pub fn from_str(value: &str) -> Option<Self> {
+        match value {
+            "upload" => Some(SyncOperation::Upload),
+            "download" => Some(SyncOperation::Download),
+            "rename" => Some(SyncOperation::Rename),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncFailureState {
+    /// Failed before, still retried
+    Failing,
+    /// Failed too often, not retried until the user asks to
+    Quarantined,
+    /// The user chose to stop syncing it
+    Ignored,
+}
+
+impl SyncFailureState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SyncFailureState::Failing => "failing",
+            SyncFailureState::Quarantined => "quarantined",
+            SyncFailureState::Ignored => "ignored",
+        }
+    }
+
+    pub fn from_str(value: &str) -> Option<Self> {
+        match value {
+            "failing" => Some(SyncFailureState::Failing),
+            "quarantined" => Some(SyncFailureState::Quarantined),
+            "ignored" => Some(SyncFailureState::Ignored),
+            _ => None,
+        }
+    }
+
+    /// Whether the operation is skipped instead of attempted
+    pub fn is_blocked(&self) -> bool {
+        !matches!(self, SyncFailureState::Failing)
+    }
+}
+
+/// A file whose sync failed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncFailure {
+    pub id: i64,
+    pub drive_id: String,
+    pub local_path: String,
+    pub operation: SyncOperation,
+    /// Consecutive failures
+    pub failures: i32,
+    pub last_error: String,
+    pub state: SyncFailureState,
+    pub updated_at: i64, // Unix timestamp
+}
+
+/// What happened to a drive's credentials
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialEventKind {
+    /// Signed in when the drive was added
+    Login,
+    /// Signed in again for an existing drive
+    CredentialsUpdated,
+    /// The access token was renewed with the refresh token
+    Refreshed,
+    /// Renewing the access token failed
+    RefreshFailed,
+    /// The server rejected the credentials, the user has to sign in again
+    Expired,
+}
+
+impl CredentialEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CredentialEventKind::Login => "login",
+            CredentialEventKind::CredentialsUpdated => "credentials_updated",
+            CredentialEventKind::Refreshed => "refreshed",
+            CredentialEventKind::RefreshFailed => "refresh_failed",
+            CredentialEventKind::Expired => "expired",
+        }
+    }
+
+    pub fn from_str(value: &str) -> Option<Self> {
+        match value {
+            "login" => Some(CredentialEventKind::Login),
+            "credentials_updated" => Some(CredentialEventKind::CredentialsUpdated),
+            "refreshed" => Some(CredentialEventKind::Refreshed),
+            "refresh_failed" => Some(CredentialEventKind::RefreshFailed),
+            "expired" => Some(CredentialEventKind::Expired),
+            _ => None,
+        }
+    }
+}
+
+/// Who used a drive's credentials
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialSource {
+    /// The user signing in
+    User,
+    /// The client renewing tokens on its own
+    AutoRefresh,
+    /// A response from the server
+    Server,
+}
+
+impl CredentialSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CredentialSource::User => "user",
+            CredentialSource::AutoRefresh => "auto_refresh",
+            CredentialSource::Server => "server",
+        }
+    }
+
+    pub fn from_str(value: &str) -> Option<Self> {
+        match value {
+            "user" => Some(CredentialSource::User),
+            "auto_refresh" => Some(CredentialSource::AutoRefresh),
+            "server" => Some(CredentialSource::Server),
+            _ => None,
+        }
+    }
+}
+
+/// A recorded use of a drive's credentials. Tokens are never part of it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialEvent {
+    pub id: i64,
+    pub drive_id: String,
+    pub kind: CredentialEventKind,
+    pub source: CredentialSource,
+    /// Error of a failed refresh, empty otherwise
+    pub detail: String,
+    pub occurred_at: i64, // Unix timestamp
+}
+
+/// A critical event kept until the UI acknowledges it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxEvent {
+    pub event_id: String,
+    /// Name of the event
+    pub kind: String,
+    /// The event as JSON
+    pub payload: String,
+    pub created_at: i64, // Unix timestamp
+}
+
+/// Why a local file was found to need an upload
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IntakeReason {
+    /// Created locally, seen by the watcher
+    Created,
+    /// Modified locally, seen by the watcher
+    Modified,
+    /// Found by a sync, missing on the server
+    RemoteMissing,
+    /// Found by a sync, different from the server
+    RemoteMismatch,
+    /// Found while reconciling an event storm, uploaded in one task
+    Bulk,
+}
+
+impl IntakeReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IntakeReason::Created => "created",
+            IntakeReason::Modified => "modified",
+            IntakeReason::RemoteMissing => "remote_missing",
+            IntakeReason::RemoteMismatch => "remote_mismatch",
+            IntakeReason::Bulk => "bulk",
+        }
+    }
+
+    pub fn from_str(value: &str) -> Option<Self> {
+        match value {
+            "created" => Some(IntakeReason::Created),
+            "modified" => Some(IntakeReason::Modified),
+            "remote_missing" => Some(IntakeReason::RemoteMissing),
+            "remote_mismatch" => Some(IntakeReason::RemoteMismatch),
+            "bulk" => Some(IntakeReason::Bulk),
+            _ => None,
+        }
+    }
+}
+
+/// A local file found to need an upload that is not queued as a task yet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingIntake {
+    pub drive_id: String,
+    pub local_path: String,
+    pub reason: IntakeReason,
+    pub discovered_at: i64, // Unix timestamp
+}
+
 /// Cached properties for a drive
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct DriveProps {
@@ -339,3 +644,54 @@ impl DrivePropsUpdate {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cloudreve_api::models::explorer::{GroupBrief, UserBrief};
+    use std::time::Duration;
+
+    #[test]
+    fn mtimes_match_within_tolerance() {
+        let recorded = 1_700_000_000;
+        let at = |secs: u64| Some(UNIX_EPOCH + Duration::from_secs(secs));
+        assert!(mtime_matches(at(1_700_000_000), recorded, 0));
+        assert!(mtime_matches(at(1_700_000_002), recorded, 2));
+        assert!(mtime_matches(at(1_699_999_998), recorded, 2));
+        assert!(!mtime_matches(at(1_700_000_003), recorded, 2));
+        assert!(!mtime_matches(None, recorded, 2));
+    }
+
+    #[test]
+    fn remote_props_keep_the_file_id_and_owner() {
+        let mut file = FileResponse {
+            id: "f1".to_string(),
+            owned: Some(false),
+            owner: Some(UserBrief {
+                id: "u1".to_string(),
+                nickname: "Alice".to_string(),
+                group: Some(GroupBrief {
+                    id: "g1".to_string(),
+                    name: "Design".to_string(),
+                }),
+            }),
+            ..Default::default()
+        };
+        let props = remote_props(&file);
+        assert_eq!(props.get(FILE_ID_PROP), Some(&serde_json::json!("f1")));
+        assert_eq!(
+            FileOwner::from_props(Some(&props)),
+            Some(FileOwner {
+                id: "u1".to_string(),
+                nickname: "Alice".to_string(),
+                group: Some("Design".to_string()),
+            })
+        );
+
+        file.owned = Some(true);
+        let props = remote_props(&file);
+        assert_eq!(props, serde_json::json!({ FILE_ID_PROP: "f1" }));
+        assert_eq!(FileOwner::from_props(Some(&props)), None);
+        assert_eq!(FileOwner::from_props(None), None);
+    }
+}
@@ -68,3 +68,66 @@ diesel::table! {
         updated_at -> BigInt,
     }
 }
+
+diesel::table! {
+    quarantined_files (local_path) {
+        drive_id -> Text,
+        local_path -> Text,
+        detail -> Text,
+        detected_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    sync_failures (id) {
+        id -> BigInt,
+        drive_id -> Text,
+        local_path -> Text,
+        operation -> Text,
+        failures -> Integer,
+        last_error -> Text,
+        state -> Text,
+        updated_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    credential_events (id) {
+        id -> BigInt,
+        drive_id -> Text,
+        kind -> Text,
+        source -> Text,
+        detail -> Text,
+        occurred_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    event_outbox (id) {
+        id -> BigInt,
+        event_id -> Text,
+        kind -> Text,
+        payload -> Text,
+        created_at -> BigInt,
+        acknowledged_at -> Nullable<BigInt>,
+    }
+}
+
+diesel::table! {
+    pending_intake (id) {
+        id -> BigInt,
+        drive_id -> Text,
+        local_path -> Text,
+        reason -> Text,
+        discovered_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    initial_sync_reports (id) {
+        id -> BigInt,
+        drive_id -> Text,
+        report -> Text,
+        created_at -> BigInt,
+    }
+}
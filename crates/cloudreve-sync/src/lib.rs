@@ -2,27 +2,57 @@ pub mod cfapi;
 pub mod config;
 pub mod drive;
 pub mod events;
+pub mod idle;
 pub mod inventory;
 pub mod logging;
+pub mod policy;
+pub mod power;
+pub mod presentation;
+pub mod sdk;
 pub mod shellext;
 pub mod tasks;
 pub mod uploader;
 pub mod utils;
+pub mod vfs;
 
 // Re-export commonly used types
-pub use config::{AppConfig, ConfigManager};
-pub use drive::manager::{DriveInfo, DriveInfoStatus, DriveManager, StatusSummary, TaskWithProgress};
-pub use drive::mounts::{Credentials, DriveConfig, SyncDirection};
+pub use config::{
+    AppConfig, BatterySaver, ConfigChanged, ConfigManager, DownloadScan, FairScheduling,
+    IdleSchedule, PresentationMode, WindowGeometry,
+};
+pub use drive::manager::{
+    AddDriveError, CommandQueueReport, DriveInfo, DriveInfoStatus, DriveManager, DriveTemplate,
+    IssueAction, IssueKind, IssueSeverity, PurgeReport, ShareReport, SharedItem, StatusSummary,
+    SyncIssue, TaskWithProgress, TrashItem,
+};
+pub use drive::duplicate_files::{DuplicateAction, DuplicateReport, DuplicateResolution};
+pub use drive::history::{FileHistoryEntry, FileHistoryKind};
+pub use drive::initial_report::{InitialSyncReport, SkipReason, SkippedFile};
+pub use drive::label::DriveLabel;
+pub use drive::mounts::{Credentials, DriveConfig, NetworkSettings, SyncDirection};
+pub use drive::remote_path::{PathVariables, expand_remote_path};
+pub use drive::repair::RepairReport;
+pub use drive::summary::FolderSummary;
+pub use drive::type_filter::{FileCategory, TypeFilter, TypeRule};
+pub use tasks::{AutoscaleSettings, TransferLimits};
+pub use uploader::CompressionSettings;
 pub use events::{Event, EventBroadcaster};
 pub use logging::{LogConfig, LogGuard};
+pub use policy::Policy;
+pub use cloudreve_api::{PinKind, TlsPin};
 
 /// User agent string for HTTP requests
 pub const USER_AGENT: &str = concat!("cloudreve-desktop/", env!("CARGO_PKG_VERSION"));
 
+/// Name of this computer, reported to the server in the user agent
+pub fn device_name() -> String {
+    std::env::var("COMPUTERNAME").unwrap_or_else(|_| "Windows".to_string())
+}
+
 #[macro_use]
 extern crate rust_i18n;
 
-i18n!("../../locales");
+i18n!("../../locales", fallback = "en-US");
 
 /// Initialize the application root path (Windows Package detection)
 pub fn init_app_root() {
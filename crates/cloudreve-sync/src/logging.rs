@@ -1,10 +1,16 @@
 use anyhow::{Context, Result};
-use std::path::PathBuf;
-use std::sync::OnceLock;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use tokio::sync::broadcast::error::RecvError;
+use tracing_appender::non_blocking::{NonBlocking, WorkerGuard};
 use tracing_subscriber::{
-    EnvFilter,
-    fmt::{self, format::FmtSpan},
-    layer::SubscriberExt,
+    EnvFilter, Registry,
+    fmt::{
+        self,
+        format::{Compact, DefaultFields, FmtSpan, Format},
+    },
+    layer::{Layered, SubscriberExt},
+    reload,
     util::SubscriberInitExt,
 };
 
@@ -62,6 +68,48 @@ impl LogConfig {
 /// Global flag for whether file logging is enabled
 static FILE_LOGGING_ENABLED: OnceLock<std::sync::RwLock<bool>> = OnceLock::new();
 
+/// Handle used to swap the level filter of the running subscriber
+static FILTER_HANDLE: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// Subscriber the file layer is stacked on
+type FilteredRegistry = Layered<reload::Layer<EnvFilter, Registry>, Registry>;
+type FileLayer = fmt::Layer<FilteredRegistry, DefaultFields, Format<Compact>, NonBlocking>;
+
+/// Handle used to swap the file layer of the running subscriber, `None`
+/// while file logging is off
+static FILE_LAYER_HANDLE: OnceLock<reload::Handle<Option<FileLayer>, FilteredRegistry>> =
+    OnceLock::new();
+
+/// Directory and name prefix of the log files
+static FILE_TARGET: OnceLock<(PathBuf, String)> = OnceLock::new();
+
+/// Flushes the writer of the current file layer when dropped
+static FILE_WORKER: Mutex<Option<WorkerGuard>> = Mutex::new(None);
+
+/// Build the layer writing to daily rotated files in `log_dir`, keeping
+/// `max_files` of them
+fn file_layer(log_dir: &Path, prefix: &str, max_files: usize) -> Result<(FileLayer, WorkerGuard)> {
+    let file_appender = tracing_appender::rolling::RollingFileAppender::builder()
+        .rotation(tracing_appender::rolling::Rotation::DAILY)
+        .filename_prefix(prefix)
+        .filename_suffix("log")
+        .max_log_files(max_files)
+        .build(log_dir)
+        .context("Failed to create file appender")?;
+
+    // Create non-blocking writer for file output
+    let (non_blocking_file, worker_guard) = tracing_appender::non_blocking(file_appender);
+
+    let layer = fmt::layer()
+        .compact()
+        .with_writer(non_blocking_file)
+        .with_target(true)
+        .with_thread_ids(true)
+        .with_thread_names(true)
+        .with_span_events(FmtSpan::NEW | FmtSpan::CLOSE);
+    Ok((layer, worker_guard))
+}
+
 /// Initialize the logging system with both file and stdout output
 ///
 /// This sets up:
@@ -102,66 +150,35 @@ pub fn init_logging(config: LogConfig) -> Result<LogGuard> {
     // Configure environment filter with defaults
     let env_filter = EnvFilter::try_from_default_env()
         .unwrap_or_else(|_| EnvFilter::new(&config.log_level));
+    let (env_filter, filter_handle) = reload::Layer::new(env_filter);
+    let _ = FILTER_HANDLE.set(filter_handle);
 
-    // Initialize the subscriber based on whether file logging is enabled
-    // We need separate branches due to tracing-subscriber's type system
-    let worker_guard = if config.log_to_file {
-        // Create file appender with rotation
-        let file_appender = tracing_appender::rolling::RollingFileAppender::builder()
-            .rotation(tracing_appender::rolling::Rotation::DAILY)
-            .filename_prefix(&config.file_prefix)
-            .filename_suffix("log")
-            .max_log_files(config.max_files)
-            .build(&config.log_dir)
-            .context("Failed to create file appender")?;
-
-        // Create non-blocking writer for file output
-        let (non_blocking_file, worker_guard) = tracing_appender::non_blocking(file_appender);
-
-        // Create file layer
-        let file_layer = fmt::layer()
-            .compact()
-            .with_writer(non_blocking_file)
-            .with_target(true)
-            .with_thread_ids(true)
-            .with_thread_names(true)
-            .with_span_events(FmtSpan::NEW | FmtSpan::CLOSE);
-
-        // Create stdout layer (human-readable with colors)
-        let stdout_layer = fmt::layer()
-            .compact()
-            .with_target(true)
-            .with_thread_ids(false)
-            .with_line_number(true)
-            .with_ansi(true);
-
-        tracing_subscriber::registry()
-            .with(env_filter)
-            .with(file_layer)
-            .with(stdout_layer)
-            .init();
-
-        worker_guard
+    // The file layer is always in place, reloaded with `None` while file
+    // logging is off, so it can be turned on and off at runtime
+    let _ = FILE_TARGET.set((config.log_dir.clone(), config.file_prefix.clone()));
+    let (file_layer, worker_guard) = if config.log_to_file {
+        let (layer, guard) = file_layer(&config.log_dir, &config.file_prefix, config.max_files)?;
+        (Some(layer), Some(guard))
     } else {
-        // Create a dummy non-blocking writer that we won't use, just for the guard
-        let (non_blocking_sink, worker_guard) = tracing_appender::non_blocking(std::io::sink());
-        drop(non_blocking_sink);
-
-        // Create stdout layer only (human-readable with colors)
-        let stdout_layer = fmt::layer()
-            .compact()
-            .with_target(true)
-            .with_thread_ids(false)
-            .with_line_number(true)
-            .with_ansi(true);
-
-        tracing_subscriber::registry()
-            .with(env_filter)
-            .with(stdout_layer)
-            .init();
-
-        worker_guard
+        (None, None)
     };
+    *FILE_WORKER.lock().unwrap() = worker_guard;
+    let (file_layer, file_layer_handle) = reload::Layer::new(file_layer);
+    let _ = FILE_LAYER_HANDLE.set(file_layer_handle);
+
+    // Create stdout layer (human-readable with colors)
+    let stdout_layer = fmt::layer()
+        .compact()
+        .with_target(true)
+        .with_thread_ids(false)
+        .with_line_number(true)
+        .with_ansi(true);
+
+    tracing_subscriber::registry()
+        .with(env_filter)
+        .with(file_layer)
+        .with(stdout_layer)
+        .init();
 
     tracing::info!(
         target: "main",
@@ -172,20 +189,102 @@ pub fn init_logging(config: LogConfig) -> Result<LogGuard> {
         "Logging system initialized"
     );
 
-    Ok(LogGuard {
-        _worker_guard: worker_guard,
-    })
+    Ok(LogGuard { _private: () })
 }
 
-/// Update the log level setting (note: requires restart to take effect)
+/// Apply a new log level to the running subscriber.
+///
+/// Ignored when the filter is controlled by the `RUST_LOG` environment variable.
 pub fn set_log_level(level: LogLevel) -> Result<()> {
-    // The log level change is persisted to config but requires restart
-    // because tracing-subscriber doesn't support runtime filter changes easily
-    // when combined with multiple layers
-    tracing::info!(target: "config", level = level.as_str(), "Log level setting updated (restart required)");
+    if std::env::var_os("RUST_LOG").is_some() {
+        tracing::info!(target: "config", level = level.as_str(), "Log level setting updated, RUST_LOG takes precedence");
+        return Ok(());
+    }
+
+    let handle = FILTER_HANDLE
+        .get()
+        .context("Logging system not initialized")?;
+    handle
+        .reload(EnvFilter::new(level.as_str()))
+        .context("Failed to reload log filter")?;
+
+    tracing::info!(target: "config", level = level.as_str(), "Log level applied");
+    Ok(())
+}
+
+/// Turn writing logs to files on or off, keeping `max_files` of them, on the
+/// running subscriber
+pub fn set_file_logging(enabled: bool, max_files: usize) -> Result<()> {
+    let handle = FILE_LAYER_HANDLE
+        .get()
+        .context("Logging system not initialized")?;
+    let (log_dir, prefix) = FILE_TARGET
+        .get()
+        .context("Logging system not initialized")?;
+
+    let (layer, worker_guard) = if enabled {
+        let (layer, guard) = file_layer(log_dir, prefix, max_files)?;
+        (Some(layer), Some(guard))
+    } else {
+        (None, None)
+    };
+    handle
+        .reload(layer)
+        .context("Failed to reload file logging")?;
+    // Dropping the guard of the previous layer flushes what it still holds
+    let previous = std::mem::replace(&mut *FILE_WORKER.lock().unwrap(), worker_guard);
+    drop(previous);
+
+    if let Some(lock) = FILE_LOGGING_ENABLED.get() {
+        if let Ok(mut current) = lock.write() {
+            *current = enabled;
+        }
+    }
+    tracing::info!(target: "config", log_to_file = enabled, max_files, "File logging applied");
     Ok(())
 }
 
+/// Spawn a task that applies logging settings as soon as they change in [ConfigManager].
+///
+/// The log level, file logging, log rotation and HTTP tracing are all applied live.
+pub fn spawn_config_watcher() {
+    let Some(config_manager) = ConfigManager::try_get() else {
+        return;
+    };
+    let mut receiver = config_manager.subscribe();
+    cloudreve_api::trace::set_http_trace(config_manager.http_trace());
+
+    tokio::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(change) => {
+                    if change.changed(|c| &c.log_level) {
+                        if let Err(e) = set_log_level(change.current.log_level) {
+                            tracing::warn!(target: "config", error = %e, "Failed to apply log level");
+                        }
+                    }
+                    if change.changed(|c| &c.log_to_file) || change.changed(|c| &c.log_max_files) {
+                        if let Err(e) = set_file_logging(
+                            change.current.log_to_file,
+                            change.current.log_max_files,
+                        ) {
+                            tracing::warn!(target: "config", error = %e, "Failed to apply file logging");
+                        }
+                    }
+                    if change.changed(|c| &c.http_trace) {
+                        cloudreve_api::trace::set_http_trace(change.current.http_trace);
+                        tracing::info!(target: "config", http_trace = change.current.http_trace, "HTTP trace setting applied");
+                    }
+                }
+                Err(RecvError::Lagged(n)) => {
+                    tracing::warn!(target: "config", skipped = n, "Config watcher lagged, some changes were skipped");
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
 /// Get the current file logging enabled state
 pub fn is_file_logging_enabled() -> bool {
     FILE_LOGGING_ENABLED
@@ -196,16 +295,17 @@ pub fn is_file_logging_enabled() -> bool {
 }
 
 /// Guard that ensures logs are flushed before exit
-/// This wraps the WorkerGuard from tracing_appender which MUST be kept alive
+/// It drops the WorkerGuard of the current file layer, so it MUST be kept alive
 /// for the entire application lifetime to ensure file logging works properly
 pub struct LogGuard {
-    _worker_guard: tracing_appender::non_blocking::WorkerGuard,
+    _private: (),
 }
 
 impl Drop for LogGuard {
     fn drop(&mut self) {
         tracing::info!(target: "main", "Flushing logs before shutdown");
-        // WorkerGuard will be dropped here, flushing remaining logs
+        // Dropping the WorkerGuard flushes remaining logs
+        drop(FILE_WORKER.lock().ok().and_then(|mut guard| guard.take()));
     }
 }
 
@@ -0,0 +1,309 @@
+//! Administrator-provisioned (machine-level) policy.
+//!
+//! Policy is read from `%ProgramData%\Cloudreve\policy.json` and from the
+//! `HKLM\SOFTWARE\Policies\Cloudreve\Sync` registry key, with registry values
+//! taking precedence. Users cannot change it from the app; the settings API only
+//! reports it.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use widestring::U16CString;
+use windows::Win32::Foundation::ERROR_SUCCESS;
+use windows::Win32::System::Registry::{
+    HKEY_LOCAL_MACHINE, RRF_RT_REG_DWORD, RRF_RT_REG_MULTI_SZ, RegGetValueW,
+};
+use windows::core::PCWSTR;
+
+use crate::config::AppConfig;
+use crate::drive::manager::DriveTemplate;
+
+/// Global policy instance, loaded on first access
+static POLICY: OnceLock<Policy> = OnceLock::new();
+
+/// Registry key holding policy values (under HKEY_LOCAL_MACHINE)
+const POLICY_REGISTRY_KEY: &str = r"SOFTWARE\Policies\Cloudreve\Sync";
+
+/// Machine-level policy set by an administrator
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Policy {
+    /// Server URLs offered when adding a drive
+    pub allowed_server_urls: Vec<String>,
+    /// Only allow drives on servers listed in `allowed_server_urls`
+    pub disable_personal_accounts: bool,
+    /// Never upload or apply local changes to the cloud
+    pub force_read_only: bool,
+    /// Upload bandwidth cap in bytes per second, shared by all drives
+    pub max_upload_speed: Option<u64>,
+    /// Download bandwidth cap in bytes per second, shared by all drives
+    pub max_download_speed: Option<u64>,
+    /// Values applied over the user's [AppConfig], keyed by field name
+    pub settings: Map<String, Value>,
+    /// [AppConfig] field names the user cannot change
+    pub locked_settings: Vec<String>,
+    /// Drive templates offered when adding a drive
+    pub drive_templates: Vec<DriveTemplate>,
+    /// What happens to downloaded content when the server unlinks this device
+    pub remote_wipe_content: RemoteWipeContent,
+}
+
+/// Downloaded content left behind when the server unlinks this device
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RemoteWipeContent {
+    /// Leave the sync folder as it is
+    #[default]
+    Keep,
+    /// Free the content of downloaded files, leaving placeholders
+    Dehydrate,
+    /// Delete the synced files, keeping local changes not uploaded yet.
+    /// A sync folder that is a whole known folder is kept.
+    Delete,
+}
+
+impl RemoteWipeContent {
+    fn from_dword(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(Self::Keep),
+            1 => Some(Self::Dehydrate),
+            2 => Some(Self::Delete),
+            _ => None,
+        }
+    }
+}
+
+impl Policy {
+    /// Get the global policy, loading it on first access.
+    pub fn get() -> &'static Policy {
+        POLICY.get_or_init(|| {
+            let policy = Self::load();
+            if policy != Policy::default() {
+                tracing::info!(target: "policy", policy = ?policy, "Administrator policy loaded");
+            }
+            policy
+        })
+    }
+
+    /// Load policy from the policy file and the registry.
+    /// Sources that are missing or invalid are skipped.
+    fn load() -> Policy {
+        let mut policy = match Self::get_policy_path() {
+            Some(path) => Self::load_from_path(&path).unwrap_or_else(|e| {
+                tracing::error!(target: "policy", path = %path.display(), error = %e, "Failed to load policy file");
+                Policy::default()
+            }),
+            None => Policy::default(),
+        };
+        policy.merge_registry();
+        policy
+    }
+
+    /// Get the policy file path (%ProgramData%\Cloudreve\policy.json)
+    fn get_policy_path() -> Option<PathBuf> {
+        std::env::var_os("ProgramData")
+            .map(PathBuf::from)
+            .map(|dir| dir.join("Cloudreve").join("policy.json"))
+    }
+
+    fn load_from_path(path: &PathBuf) -> Result<Policy> {
+        if !path.exists() {
+            return Ok(Policy::default());
+        }
+        let content = fs::read_to_string(path).context("Failed to read policy file")?;
+        serde_json::from_str(&content).context("Failed to parse policy file")
+    }
+
+    /// Override fields with values set under the policy registry key
+    fn merge_registry(&mut self) {
+        if let Some(urls) = read_registry_multi_sz("AllowedServerUrls") {
+            self.allowed_server_urls = urls;
+        }
+        if let Some(value) = read_registry_dword("DisablePersonalAccounts") {
+            self.disable_personal_accounts = value != 0;
+        }
+        if let Some(value) = read_registry_dword("ForceReadOnly") {
+            self.force_read_only = value != 0;
+        }
+        if let Some(value) = read_registry_dword("MaxUploadSpeed") {
+            self.max_upload_speed = Some(value as u64);
+        }
+        if let Some(value) = read_registry_dword("MaxDownloadSpeed") {
+            self.max_download_speed = Some(value as u64);
+        }
+        if let Some(keys) = read_registry_multi_sz("LockedSettings") {
+            self.locked_settings = keys;
+        }
+        if let Some(content) =
+            read_registry_dword("RemoteWipeContent").and_then(RemoteWipeContent::from_dword)
+        {
+            self.remote_wipe_content = content;
+        }
+    }
+
+    /// Whether `instance_url` may be used for a new drive
+    pub fn is_server_allowed(&self, instance_url: &str) -> bool {
+        if !self.disable_personal_accounts {
+            return true;
+        }
+        let normalize = |url: &str| url.trim().trim_end_matches('/').to_lowercase();
+        let instance_url = normalize(instance_url);
+        self.allowed_server_urls
+            .iter()
+            .any(|allowed| normalize(allowed) == instance_url)
+    }
+
+    /// Fail if `instance_url` is not allowed by policy
+    pub fn check_server_allowed(&self, instance_url: &str) -> Result<()> {
+        if !self.is_server_allowed(instance_url) {
+            anyhow::bail!(
+                "Server {} is not allowed by administrator policy",
+                instance_url
+            );
+        }
+        Ok(())
+    }
+
+    /// Whether the given [AppConfig] field is locked
+    pub fn is_locked(&self, setting: &str) -> bool {
+        self.locked_settings.iter().any(|s| s == setting)
+    }
+
+    /// Apply policy-provided setting values over `config`
+    pub fn apply_settings(&self, config: AppConfig) -> AppConfig {
+        if self.settings.is_empty() {
+            return config;
+        }
+        let Ok(Value::Object(mut merged)) = serde_json::to_value(&config) else {
+            return config;
+        };
+        for (key, value) in &self.settings {
+            merged.insert(key.clone(), value.clone());
+        }
+        serde_json::from_value(Value::Object(merged)).unwrap_or_else(|e| {
+            tracing::warn!(target: "policy", error = %e, "Invalid policy settings, ignoring");
+            config
+        })
+    }
+
+    /// Fail if `next` changes a locked setting compared to `previous`
+    pub fn check_locked(&self, previous: &AppConfig, next: &AppConfig) -> Result<()> {
+        if self.locked_settings.is_empty() {
+            return Ok(());
+        }
+        let previous = serde_json::to_value(previous)?;
+        let next = serde_json::to_value(next)?;
+        for key in &self.locked_settings {
+            if previous.get(key) != next.get(key) {
+                anyhow::bail!("Setting {} is locked by administrator policy", key);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_registry_dword(name: &str) -> Option<u32> {
+    let key = U16CString::from_str(POLICY_REGISTRY_KEY).ok()?;
+    let name = U16CString::from_str(name).ok()?;
+    let mut data: u32 = 0;
+    let mut size = std::mem::size_of::<u32>() as u32;
+    let result = unsafe {
+        RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            PCWSTR(key.as_ptr()),
+            PCWSTR(name.as_ptr()),
+            RRF_RT_REG_DWORD,
+            None,
+            Some(&mut data as *mut u32 as *mut _),
+            Some(&mut size),
+        )
+    };
+    (result == ERROR_SUCCESS).then_some(data)
+}
+
+fn read_registry_multi_sz(name: &str) -> Option<Vec<String>> {
+    let key = U16CString::from_str(POLICY_REGISTRY_KEY).ok()?;
+    let name = U16CString::from_str(name).ok()?;
+
+    // Query the size first
+    let mut size: u32 = 0;
+    let result = unsafe {
+        RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            PCWSTR(key.as_ptr()),
+            PCWSTR(name.as_ptr()),
+            RRF_RT_REG_MULTI_SZ,
+            None,
+            None,
+            Some(&mut size),
+        )
+    };
+    if result != ERROR_SUCCESS || size == 0 {
+        return None;
+    }
+
+    let mut buffer = vec![0u16; (size as usize).div_ceil(2)];
+    let result = unsafe {
+        RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            PCWSTR(key.as_ptr()),
+            PCWSTR(name.as_ptr()),
+            RRF_RT_REG_MULTI_SZ,
+            None,
+            Some(buffer.as_mut_ptr() as *mut _),
+            Some(&mut size),
+        )
+    };
+    if result != ERROR_SUCCESS {
+        return None;
+    }
+
+    Some(
+        buffer
+            .split(|&c| c == 0)
+            .filter(|s| !s.is_empty())
+            .map(String::from_utf16_lossy)
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LogLevel;
+
+    #[test]
+    fn test_server_allowed() {
+        let policy = Policy {
+            allowed_server_urls: vec!["https://cloud.example.com/".to_string()],
+            disable_personal_accounts: true,
+            ..Default::default()
+        };
+        assert!(policy.is_server_allowed("https://CLOUD.example.com"));
+        assert!(!policy.is_server_allowed("https://other.example.com"));
+        assert!(Policy::default().is_server_allowed("https://other.example.com"));
+    }
+
+    #[test]
+    fn test_locked_settings() {
+        let mut settings = Map::new();
+        settings.insert("log_level".to_string(), Value::from("error"));
+        let policy = Policy {
+            settings,
+            locked_settings: vec!["log_level".to_string()],
+            ..Default::default()
+        };
+
+        let config = policy.apply_settings(AppConfig::default());
+        assert_eq!(config.log_level, LogLevel::Error);
+
+        let mut next = config.clone();
+        next.auto_start = !next.auto_start;
+        assert!(policy.check_locked(&config, &next).is_ok());
+        next.log_level = LogLevel::Debug;
+        assert!(policy.check_locked(&config, &next).is_err());
+    }
+}
@@ -0,0 +1,104 @@
+//! Power source awareness
+//!
+//! On battery power with the charge at or below the threshold configured in
+//! [`BatterySaver`], sync enters power saving: transfers run one at a time and
+//! background full syncs are deferred until the machine is plugged in or the
+//! charge recovers.
+
+use crate::config::BatterySaver;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static POWER_SAVING: AtomicBool = AtomicBool::new(false);
+
+/// Power source and battery charge of the machine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PowerStatus {
+    pub on_battery: bool,
+    /// Remaining charge, `None` if unknown or there is no battery
+    pub battery_percent: Option<u8>,
+}
+
+impl PowerStatus {
+    /// Query the current status, `None` if it is not available
+    pub fn current() -> Option<Self> {
+        use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+        /// `ACLineStatus` when running on battery
+        const AC_OFFLINE: u8 = 0;
+        /// `BatteryFlag` bit set on machines without a battery
+        const NO_SYSTEM_BATTERY: u8 = 128;
+        /// `BatteryLifePercent` when the charge is unknown
+        const UNKNOWN_PERCENT: u8 = 255;
+
+        let mut status = SYSTEM_POWER_STATUS::default();
+        unsafe { GetSystemPowerStatus(&mut status) }.ok()?;
+
+        let has_battery = status.BatteryFlag & NO_SYSTEM_BATTERY == 0;
+        Some(Self {
+            on_battery: has_battery && status.ACLineStatus == AC_OFFLINE,
+            battery_percent: (has_battery && status.BatteryLifePercent != UNKNOWN_PERCENT)
+                .then_some(status.BatteryLifePercent),
+        })
+    }
+
+    /// Whether sync should be throttled in this state
+    pub fn should_save_power(&self, settings: &BatterySaver) -> bool {
+        settings.enabled
+            && self.on_battery
+            && self
+                .battery_percent
+                .is_some_and(|percent| percent <= settings.threshold_percent)
+    }
+}
+
+/// Whether sync is currently throttled to save power
+pub fn is_power_saving() -> bool {
+    POWER_SAVING.load(Ordering::SeqCst)
+}
+
+/// Update the power saving state, returns whether it changed
+pub(crate) fn set_power_saving(active: bool) -> bool {
+    POWER_SAVING.swap(active, Ordering::SeqCst) != active
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saves_power_on_low_battery_only() {
+        let settings = BatterySaver {
+            enabled: true,
+            threshold_percent: 30,
+        };
+        let low = PowerStatus {
+            on_battery: true,
+            battery_percent: Some(25),
+        };
+        assert!(low.should_save_power(&settings));
+
+        let charging = PowerStatus {
+            on_battery: false,
+            ..low
+        };
+        assert!(!charging.should_save_power(&settings));
+
+        let charged = PowerStatus {
+            battery_percent: Some(80),
+            ..low
+        };
+        assert!(!charged.should_save_power(&settings));
+
+        let unknown = PowerStatus {
+            battery_percent: None,
+            ..low
+        };
+        assert!(!unknown.should_save_power(&settings));
+
+        let disabled = BatterySaver {
+            enabled: false,
+            ..settings
+        };
+        assert!(!low.should_save_power(&disabled));
+    }
+}
@@ -0,0 +1,149 @@
+//! Presentation awareness
+//!
+//! With [`PresentationMode`] enabled, sync keeps quiet while the screen is
+//! presented or shared: notifications are held back and uploads and
+//! background syncs pause until the presentation ends. Presenting is detected
+//! from the state Windows itself uses to hold back notifications (presentation
+//! settings turned on, a full-screen Direct3D app such as a slide show) and
+//! from a duplicated display, as when mirroring the screen to a projector.
+
+use crate::config::{ConfigManager, PresentationMode};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static PRESENTING: AtomicBool = AtomicBool::new(false);
+
+/// What Windows reports about the screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PresentationStatus {
+    /// Presentation settings are on or a full-screen app is running
+    pub full_screen: bool,
+    /// The desktop is duplicated on another display
+    pub mirrored: bool,
+}
+
+impl PresentationStatus {
+    /// Query the current status
+    pub fn current() -> Self {
+        Self {
+            full_screen: query_full_screen(),
+            mirrored: query_mirrored(),
+        }
+    }
+
+    /// Whether sync should keep quiet in this state
+    pub fn is_presenting(&self, settings: &PresentationMode) -> bool {
+        settings.enabled && (self.full_screen || self.mirrored)
+    }
+}
+
+/// Whether Windows currently holds back notifications for a presentation or a
+/// full-screen app
+fn query_full_screen() -> bool {
+    use windows::Win32::UI::Shell::{
+        QUNS_BUSY, QUNS_PRESENTATION_MODE, QUNS_RUNNING_D3D_FULL_SCREEN,
+        SHQueryUserNotificationState,
+    };
+
+    match unsafe { SHQueryUserNotificationState() } {
+        Ok(state) => {
+            state == QUNS_PRESENTATION_MODE
+                || state == QUNS_RUNNING_D3D_FULL_SCREEN
+                || state == QUNS_BUSY
+        }
+        Err(e) => {
+            tracing::debug!(target: "presentation", error = %e, "Failed to query notification state");
+            false
+        }
+    }
+}
+
+/// Whether the active display topology duplicates the desktop
+fn query_mirrored() -> bool {
+    use windows::Win32::Devices::Display::{
+        DISPLAYCONFIG_MODE_INFO, DISPLAYCONFIG_PATH_INFO, DISPLAYCONFIG_TOPOLOGY_CLONE,
+        DISPLAYCONFIG_TOPOLOGY_ID, GetDisplayConfigBufferSizes, QDC_DATABASE_CURRENT,
+        QueryDisplayConfig,
+    };
+
+    let mut path_count = 0u32;
+    let mut mode_count = 0u32;
+    let sizes = unsafe {
+        GetDisplayConfigBufferSizes(QDC_DATABASE_CURRENT, &mut path_count, &mut mode_count)
+    };
+    if sizes.is_err() {
+        return false;
+    }
+
+    let mut paths = vec![DISPLAYCONFIG_PATH_INFO::default(); path_count as usize];
+    let mut modes = vec![DISPLAYCONFIG_MODE_INFO::default(); mode_count as usize];
+    let mut topology = DISPLAYCONFIG_TOPOLOGY_ID::default();
+    let result = unsafe {
+        QueryDisplayConfig(
+            QDC_DATABASE_CURRENT,
+            &mut path_count,
+            paths.as_mut_ptr(),
+            &mut mode_count,
+            modes.as_mut_ptr(),
+            Some(&mut topology),
+        )
+    };
+    result.is_ok() && topology == DISPLAYCONFIG_TOPOLOGY_CLONE
+}
+
+/// Whether a presentation is currently detected
+pub fn is_presenting() -> bool {
+    PRESENTING.load(Ordering::SeqCst)
+}
+
+/// Update the presentation state, returns whether it changed
+pub(crate) fn set_presenting(active: bool) -> bool {
+    PRESENTING.swap(active, Ordering::SeqCst) != active
+}
+
+fn settings() -> PresentationMode {
+    ConfigManager::try_get()
+        .map(|config| config.presentation_mode())
+        .unwrap_or_default()
+}
+
+/// Whether notifications are currently held back for a presentation
+pub fn suppresses_notifications() -> bool {
+    is_presenting() && settings().suppress_notifications
+}
+
+/// Whether uploads and background syncs currently pause for a presentation
+pub fn pauses_transfers() -> bool {
+    is_presenting() && settings().pause_transfers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presents_only_when_enabled() {
+        let settings = PresentationMode {
+            enabled: true,
+            ..Default::default()
+        };
+        let idle = PresentationStatus {
+            full_screen: false,
+            mirrored: false,
+        };
+        assert!(!idle.is_presenting(&settings));
+
+        let full_screen = PresentationStatus {
+            full_screen: true,
+            ..idle
+        };
+        assert!(full_screen.is_presenting(&settings));
+
+        let mirrored = PresentationStatus {
+            mirrored: true,
+            ..idle
+        };
+        assert!(mirrored.is_presenting(&settings));
+
+        assert!(!full_screen.is_presenting(&PresentationMode::default()));
+    }
+}
@@ -0,0 +1,201 @@
+//! Facade for embedding the sync engine in another application.
+//!
+//! Running the engine takes an [`EventBroadcaster`], a [`DriveManager`] with
+//! its command processor and background tasks, and the drives saved by an
+//! earlier run. [`SyncEngine`] wires them together behind one handle, with
+//! nothing tied to Tauri or the shell service.
+//!
+//! # Example
+//! ```no_run
+//! use cloudreve_sync::sdk::{EngineOptions, SyncEngine};
+//!
+//! # async fn run() -> anyhow::Result<()> {
+//! let engine = SyncEngine::new(EngineOptions::default())?;
+//! // Subscribe before starting, so no event raised while drives mount is missed
+//! let (pending, mut events) = engine.subscribe_events()?;
+//! engine.start().await?;
+//!
+//! let status = engine.query_status(None).await?;
+//! println!("{} drives, {} active tasks", status.drives.len(), status.active_tasks.len());
+//! while let Ok(event) = events.recv().await {
+//!     println!("{}", event.name());
+//! }
+//! engine.shutdown().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The manager and broadcaster stay reachable through
+//! [`SyncEngine::drive_manager`] and [`SyncEngine::event_broadcaster`] for
+//! anything the facade does not cover.
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use tokio::sync::broadcast;
+
+use crate::drive::mounts::DriveConfig;
+use crate::events::{Event, EventBroadcaster};
+use crate::utils::correlation::user_action;
+use crate::{DriveManager, StatusSummary};
+
+/// What [`SyncEngine::start`] runs. Fields left out when deserializing keep
+/// their default.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct EngineOptions {
+    /// Events buffered for each subscriber before the slowest ones lag
+    pub event_capacity: usize,
+    /// Mount the drives saved by an earlier run
+    pub load_drives: bool,
+    /// Apply configuration changes (log level, HTTP tracing, ...) while
+    /// running and forward them as [`Event::ConfigChanged`]
+    pub watch_config: bool,
+    /// Run the power, presentation and idle watchers and the periodic
+    /// inventory snapshots
+    pub background_tasks: bool,
+}
+
+impl Default for EngineOptions {
+    fn default() -> Self {
+        Self {
+            event_capacity: 100,
+            load_drives: true,
+            watch_config: true,
+            background_tasks: true,
+        }
+    }
+}
+
+/// The sync engine, see the [module documentation](self)
+pub struct SyncEngine {
+    options: EngineOptions,
+    drive_manager: Arc<DriveManager>,
+    event_broadcaster: Arc<EventBroadcaster>,
+}
+
+impl SyncEngine {
+    /// Create the engine. Nothing runs until [`SyncEngine::start`].
+    pub fn new(options: EngineOptions) -> Result<Self> {
+        crate::init_app_root();
+        let event_broadcaster = Arc::new(EventBroadcaster::new(options.event_capacity));
+        let drive_manager = Arc::new(
+            DriveManager::new(event_broadcaster.clone())
+                .context("Failed to create DriveManager")?,
+        );
+        Ok(Self {
+            options,
+            drive_manager,
+            event_broadcaster,
+        })
+    }
+
+    /// Start the background tasks and mount the saved drives
+    pub async fn start(&self) -> Result<()> {
+        if self.options.watch_config {
+            crate::logging::spawn_config_watcher();
+            self.event_broadcaster.spawn_config_bridge();
+        }
+
+        self.drive_manager.spawn_command_processor().await;
+        tracing::info!(target: "sdk", "DriveManager command processor started");
+
+        if self.options.background_tasks {
+            // Periodically snapshot the inventory for metadata rollback
+            self.drive_manager.spawn_inventory_snapshot_task();
+            // Throttle sync while running on a low battery
+            self.drive_manager.spawn_power_watcher();
+            // Keep quiet while the screen is presented or shared
+            self.drive_manager.spawn_presentation_watcher();
+            // Run full syncs deferred while the user was active
+            self.drive_manager.spawn_idle_scheduler();
+            // Share transfers between busy drives
+            self.drive_manager.spawn_fair_scheduler();
+            // Delete data left behind by removed drives
+            self.drive_manager.spawn_cleanup_task();
+        }
+
+        // Report per-task progress to subscribers
+        self.drive_manager.spawn_progress_reporter();
+
+        if self.options.load_drives {
+            self.drive_manager
+                .load()
+                .await
+                .context("Failed to load drive configurations")?;
+        }
+
+        self.event_broadcaster.connection_status_changed(true);
+        tracing::info!(target: "sdk", "Sync engine started");
+        Ok(())
+    }
+
+    /// Stop syncing every drive and save their configurations
+    pub async fn shutdown(&self) -> Result<()> {
+        self.event_broadcaster.connection_status_changed(false);
+        self.drive_manager.shutdown().await;
+        self.drive_manager
+            .persist()
+            .await
+            .context("Failed to persist drive configurations")
+    }
+
+    pub fn drive_manager(&self) -> &Arc<DriveManager> {
+        &self.drive_manager
+    }
+
+    pub fn event_broadcaster(&self) -> &Arc<EventBroadcaster> {
+        &self.event_broadcaster
+    }
+
+    /// Add a drive, mount it and save it. Returns the id of the drive.
+    pub async fn add_drive(&self, config: DriveConfig) -> Result<String> {
+        let id = user_action(self.drive_manager.add_drive(config)).await?;
+        self.drive_manager.persist().await?;
+        Ok(id)
+    }
+
+    /// Remove a drive and save the change. Returns whether it existed.
+    pub async fn remove_drive(&self, drive_id: &str) -> Result<bool> {
+        let removed = user_action(self.drive_manager.remove_drive(drive_id)).await?;
+        self.drive_manager.persist().await?;
+        Ok(removed.is_some())
+    }
+
+    pub async fn list_drives(&self) -> Vec<DriveConfig> {
+        self.drive_manager.list_drives().await
+    }
+
+    /// Stop syncing a drive, keeping it registered
+    pub async fn pause_drive(&self, drive_id: &str) -> Result<()> {
+        user_action(self.drive_manager.unmount_drive(drive_id)).await
+    }
+
+    /// Sync a drive paused with [`SyncEngine::pause_drive`] again
+    pub async fn resume_drive(&self, drive_id: &str) -> Result<()> {
+        user_action(self.drive_manager.mount_drive(drive_id)).await
+    }
+
+    /// Drives, active and recent tasks, of one drive or of all of them
+    pub async fn query_status(&self, drive_id: Option<&str>) -> Result<StatusSummary> {
+        self.drive_manager.get_status_summary(drive_id).await
+    }
+
+    /// Cancel a pending or running task of any drive
+    pub async fn cancel_task(&self, task_id: &str) -> Result<()> {
+        user_action(self.drive_manager.cancel_task(task_id)).await
+    }
+
+    /// Subscribe to events. Also returns the critical events not
+    /// acknowledged yet, see [`EventBroadcaster::subscribe_with_pending`].
+    pub fn subscribe_events(&self) -> Result<(Vec<Event>, broadcast::Receiver<Event>)> {
+        self.event_broadcaster.subscribe_with_pending()
+    }
+
+    /// Acknowledge a critical event so it is not delivered again. Returns
+    /// whether it was pending.
+    pub fn acknowledge_event(&self, event_id: &str) -> Result<bool> {
+        self.event_broadcaster.acknowledge(event_id)
+    }
+}
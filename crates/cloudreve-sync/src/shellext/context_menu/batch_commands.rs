@@ -0,0 +1,118 @@
+use crate::drive::manager::DriveManager;
+use crate::policy::Policy;
+use crate::tasks::BatchOperation;
+use crate::{drive::commands::ManagerCommand, utils::app::AppRoot};
+use std::path::PathBuf;
+use std::sync::Arc;
+use windows::{
+    Win32::{Foundation::*, System::Com::*, UI::Shell::*},
+    core::*,
+};
+
+/// Context menu command applying a [`BatchOperation`] to every selected item
+/// at once
+#[implement(IExplorerCommand)]
+pub struct BatchCommandHandler {
+    drive_manager: Arc<DriveManager>,
+    app_root: AppRoot,
+    operation: BatchOperation,
+}
+
+impl BatchCommandHandler {
+    pub fn new(
+        drive_manager: Arc<DriveManager>,
+        app_root: AppRoot,
+        operation: BatchOperation,
+    ) -> Self {
+        Self {
+            drive_manager,
+            app_root,
+            operation,
+        }
+    }
+}
+
+impl IExplorerCommand_Impl for BatchCommandHandler_Impl {
+    fn GetTitle(&self, _items: Option<&IShellItemArray>) -> Result<PWSTR> {
+        let hstring = HSTRING::from(self.operation.title());
+        unsafe { SHStrDupW(&hstring) }
+    }
+
+    fn GetIcon(&self, _items: Option<&IShellItemArray>) -> Result<PWSTR> {
+        let icon = match self.operation {
+            BatchOperation::FreeUpSpace => "sync-to1.ico",
+            BatchOperation::MakeAvailableOffline => "sync-from1.ico",
+            BatchOperation::Share => "people.ico",
+        };
+        let icon_path = format!("{}\\{}", self.app_root.image_path(), icon);
+        let hstring = HSTRING::from(icon_path);
+        unsafe { SHStrDupW(&hstring) }
+    }
+
+    fn GetToolTip(&self, _items: Option<&IShellItemArray>) -> Result<PWSTR> {
+        Err(Error::from(E_NOTIMPL))
+    }
+
+    fn GetCanonicalName(&self) -> Result<GUID> {
+        Ok(match self.operation {
+            BatchOperation::FreeUpSpace => GUID::from_u128(0x3d2f7a90_6c1e_4b8d_9a45_e17c0b5f2d63),
+            BatchOperation::MakeAvailableOffline => {
+                GUID::from_u128(0x9e4b1c37_52a8_4f06_b3d9_84f6a2e0c715)
+            }
+            BatchOperation::Share => GUID::from_u128(0xc61a5e08_d3f4_4a7b_8e2c_5b9d07f3a1e4),
+        })
+    }
+
+    fn GetState(&self, _items: Option<&IShellItemArray>, _oktobeslow: BOOL) -> Result<u32> {
+        if self.operation == BatchOperation::Share && Policy::get().force_read_only {
+            Ok(ECS_HIDDEN.0 as u32)
+        } else {
+            Ok(ECS_ENABLED.0 as u32)
+        }
+    }
+
+    fn Invoke(
+        &self,
+        selection: Option<&IShellItemArray>,
+        _bindctx: Option<&IBindCtx>,
+    ) -> Result<()> {
+        tracing::debug!(target: "shellext::context_menu", operation = ?self.operation, "Batch context menu command invoked");
+
+        let Some(items) = selection else {
+            return Ok(());
+        };
+
+        let paths = unsafe {
+            let count = items.GetCount()?;
+            let mut paths = Vec::with_capacity(count as usize);
+            for i in 0..count {
+                let item = items.GetItemAt(i)?;
+                let display_name = item.GetDisplayName(SIGDN_FILESYSPATH)?;
+                paths.push(PathBuf::from(display_name.to_string()?));
+            }
+            paths
+        };
+        if paths.is_empty() {
+            return Ok(());
+        }
+
+        // The whole selection goes out as one command, so it becomes one task
+        let command_tx = self.drive_manager.get_command_sender();
+        if let Err(e) = command_tx.send_user_action(ManagerCommand::BatchOperation {
+            operation: self.operation,
+            paths,
+        }) {
+            tracing::error!(target: "shellext::context_menu", error = %e, "Failed to send BatchOperation command");
+        }
+
+        Ok(())
+    }
+
+    fn GetFlags(&self) -> Result<u32> {
+        Ok(ECF_DEFAULT.0 as u32)
+    }
+
+    fn EnumSubCommands(&self) -> Result<IEnumExplorerCommand> {
+        Err(Error::from(E_NOTIMPL))
+    }
+}
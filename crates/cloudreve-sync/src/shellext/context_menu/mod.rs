@@ -1,15 +1,19 @@
 // Context menu handler for Windows Explorer
 // This implements a COM object that provides a custom context menu item
 
+mod batch_commands;
 mod explorer_command;
 mod factory;
+mod prefetch;
 mod resolve_conflict;
 mod sub_commands;
 mod sync_now;
 mod view_online;
 
+pub use batch_commands::BatchCommandHandler;
 pub use explorer_command::CrExplorerCommandHandler;
 pub use factory::CrExplorerCommandFactory;
+pub use prefetch::PrefetchCommandHandler;
 pub use resolve_conflict::ResolveConflictCommandHandler;
 pub use sub_commands::SubCommands;
 pub use sync_now::SyncNowCommandHandler;
@@ -0,0 +1,108 @@
+use crate::drive::manager::DriveManager;
+use crate::{drive::commands::ManagerCommand, utils::app::AppRoot};
+use rust_i18n::t;
+use std::path::PathBuf;
+use std::sync::Arc;
+use windows::{
+    Win32::{Foundation::*, System::Com::*, UI::Shell::*},
+    core::*,
+};
+
+/// Context menu command prefetching the selected folders
+#[implement(IExplorerCommand)]
+pub struct PrefetchCommandHandler {
+    drive_manager: Arc<DriveManager>,
+    app_root: AppRoot,
+}
+
+impl PrefetchCommandHandler {
+    pub fn new(drive_manager: Arc<DriveManager>, app_root: AppRoot) -> Self {
+        Self {
+            drive_manager,
+            app_root,
+        }
+    }
+}
+
+impl IExplorerCommand_Impl for PrefetchCommandHandler_Impl {
+    fn GetTitle(&self, _items: Option<&IShellItemArray>) -> Result<PWSTR> {
+        let hstring = HSTRING::from(t!("prefetchFolder").as_ref());
+        unsafe { SHStrDupW(&hstring) }
+    }
+
+    fn GetIcon(&self, _items: Option<&IShellItemArray>) -> Result<PWSTR> {
+        let icon_path = format!("{}\\sync-from1.ico", self.app_root.image_path());
+        let hstring = HSTRING::from(icon_path);
+        unsafe { SHStrDupW(&hstring) }
+    }
+
+    fn GetToolTip(&self, _items: Option<&IShellItemArray>) -> Result<PWSTR> {
+        Err(Error::from(E_NOTIMPL))
+    }
+
+    fn GetCanonicalName(&self) -> Result<GUID> {
+        Ok(GUID::from_u128(0x7b3e9d21_4f6a_4c85_a0d2_19e8c5f4b603))
+    }
+
+    fn GetState(&self, items: Option<&IShellItemArray>, _oktobeslow: BOOL) -> Result<u32> {
+        // Only folders can be prefetched
+        let Some(items) = items else {
+            return Ok(ECS_HIDDEN.0 as u32);
+        };
+        let all_folders = unsafe {
+            let count = items.GetCount()?;
+            let mut all_folders = count > 0;
+            for i in 0..count {
+                let attributes = items.GetItemAt(i)?.GetAttributes(SFGAO_FOLDER)?;
+                all_folders &= attributes.contains(SFGAO_FOLDER);
+            }
+            all_folders
+        };
+        if all_folders {
+            Ok(ECS_ENABLED.0 as u32)
+        } else {
+            Ok(ECS_HIDDEN.0 as u32)
+        }
+    }
+
+    fn Invoke(
+        &self,
+        selection: Option<&IShellItemArray>,
+        _bindctx: Option<&IBindCtx>,
+    ) -> Result<()> {
+        tracing::debug!(target: "shellext::context_menu", "Prefetch context menu command invoked");
+
+        let Some(items) = selection else {
+            return Ok(());
+        };
+
+        let paths = unsafe {
+            let count = items.GetCount()?;
+            let mut paths = Vec::with_capacity(count as usize);
+            for i in 0..count {
+                let item = items.GetItemAt(i)?;
+                let display_name = item.GetDisplayName(SIGDN_FILESYSPATH)?;
+                paths.push(PathBuf::from(display_name.to_string()?));
+            }
+            paths
+        };
+        if paths.is_empty() {
+            return Ok(());
+        }
+
+        let command_tx = self.drive_manager.get_command_sender();
+        if let Err(e) = command_tx.send_user_action(ManagerCommand::PrefetchFolder { paths }) {
+            tracing::error!(target: "shellext::context_menu", error = %e, "Failed to send PrefetchFolder command");
+        }
+
+        Ok(())
+    }
+
+    fn GetFlags(&self) -> Result<u32> {
+        Ok(ECF_DEFAULT.0 as u32)
+    }
+
+    fn EnumSubCommands(&self) -> Result<IEnumExplorerCommand> {
+        Err(Error::from(E_NOTIMPL))
+    }
+}
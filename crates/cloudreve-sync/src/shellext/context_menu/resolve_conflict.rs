@@ -131,7 +131,8 @@ impl IExplorerCommand_Impl for ResolveConflictCommandHandler_Impl {
             // Send command through channel to async processor
             let command_tx = self.drive_manager.get_command_sender();
 
-            if let Err(e) = command_tx.send(ManagerCommand::ShowConflictToast { path }) {
+            if let Err(e) = command_tx.send_user_action(ManagerCommand::ShowConflictToast { path })
+            {
                 tracing::error!(
                     target: "shellext::context_menu",
                     error = %e,
@@ -1,5 +1,8 @@
-use super::{ResolveConflictCommandHandler, SyncNowCommandHandler, ViewOnlineCommandHandler};
-use crate::{drive::manager::DriveManager, utils::app::AppRoot};
+use super::{
+    BatchCommandHandler, PrefetchCommandHandler, ResolveConflictCommandHandler,
+    SyncNowCommandHandler, ViewOnlineCommandHandler,
+};
+use crate::{drive::manager::DriveManager, tasks::BatchOperation, utils::app::AppRoot};
 use std::sync::{Arc, Mutex};
 use windows::{
     Win32::{Foundation::*, UI::Shell::*},
@@ -111,6 +114,29 @@ macro_rules! sub_command_factory {
 sub_command_factory!(create_view_online_command, ViewOnlineCommandHandler);
 sub_command_factory!(create_sync_now_command, SyncNowCommandHandler);
 sub_command_factory!(create_resolve_conflict_command, ResolveConflictCommandHandler);
+sub_command_factory!(create_prefetch_command, PrefetchCommandHandler);
+
+macro_rules! batch_command_factory {
+    ($name:ident, $operation:expr) => {
+        fn $name(drive_manager: Arc<DriveManager>, app_root: AppRoot) -> IExplorerCommand {
+            BatchCommandHandler::new(drive_manager, app_root, $operation).into()
+        }
+    };
+}
+
+batch_command_factory!(create_free_up_space_command, BatchOperation::FreeUpSpace);
+batch_command_factory!(
+    create_make_available_offline_command,
+    BatchOperation::MakeAvailableOffline
+);
+batch_command_factory!(create_share_command, BatchOperation::Share);
 
-const SUB_COMMAND_FACTORIES: [SubCommandFactory; 3] =
-    [create_view_online_command, create_sync_now_command, create_resolve_conflict_command];
+const SUB_COMMAND_FACTORIES: [SubCommandFactory; 7] = [
+    create_view_online_command,
+    create_sync_now_command,
+    create_make_available_offline_command,
+    create_prefetch_command,
+    create_free_up_space_command,
+    create_share_command,
+    create_resolve_conflict_command,
+];
@@ -86,7 +86,7 @@ impl IExplorerCommand_Impl for SyncNowCommandHandler_Impl {
 
                 // Send command through channel to async processor
                 let command_tx = self.drive_manager.get_command_sender();
-                if let Err(e) = command_tx.send(ManagerCommand::SyncNow {
+                if let Err(e) = command_tx.send_user_action(ManagerCommand::SyncNow {
                     paths: paths,
                     mode: SyncMode::FullHierarchy,
                 }) {
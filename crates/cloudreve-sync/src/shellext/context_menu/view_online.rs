@@ -90,7 +90,9 @@ impl IExplorerCommand_Impl for ViewOnlineCommandHandler_Impl {
                 // Send command through channel to async processor
                 let command_tx = self.drive_manager.get_command_sender();
 
-                if let Err(e) = command_tx.send(ManagerCommand::ViewOnline { path: path.clone() }) {
+                if let Err(e) =
+                    command_tx.send_user_action(ManagerCommand::ViewOnline { path: path.clone() })
+                {
                     tracing::error!(target: "shellext::context_menu", error = %e, "Failed to send ViewOnline command");
                 }
             }
@@ -1,8 +1,7 @@
 use crate::drive::manager::DriveManager;
 use crate::inventory::InventoryDb;
 use crate::utils::app::{AppRoot, get_app_root};
-use cloudreve_api::Boolset;
-use cloudreve_api::models::explorer::file_permission;
+use cloudreve_api::models::explorer::{FilePermission, FilePermissions};
 use std::sync::Arc;
 use windows::{
     Foundation::Collections::*,
@@ -64,12 +63,24 @@ impl IStorageProviderItemPropertySource_Impl for CustomStateHandler_Impl {
             vec.push(Some(properties));
         }
 
+        if let Some(owner) = file_metadata.owner() {
+            let shared_by = match &owner.group {
+                Some(group) => t!("sharedByInGroup", "name" => owner.nickname, "group" => group),
+                None => t!("sharedBy", "name" => owner.nickname),
+            };
+            let properties = StorageProviderItemProperty::new()?;
+            properties.SetId(3)?;
+            properties.SetIconResource(&HSTRING::from(format!("{}\\people.ico,0", image_path)))?;
+            properties.SetValue(&HSTRING::from(shared_by.as_ref()))?;
+            vec.push(Some(properties));
+        }
+
         if !file_metadata.permissions.is_empty() {
-            let permission = Boolset::from_base64(&file_metadata.permissions).map_err(|e| {
+            let permission = FilePermissions::from_base64(&file_metadata.permissions).map_err(|e| {
                 tracing::error!(target: "shellext::custom_state", "Failed to parse permission for path {}: {:?}", itempath, e);
                 Error::from(E_FAIL)
             })?;
-            if !permission.enabled(file_permission::READ as usize) {
+            if !permission.has(FilePermission::Read) {
                 let properties = StorageProviderItemProperty::new()?;
                 properties.SetId(2)?;
                 properties
@@ -1,15 +1,17 @@
+use crate::drive::command_queue::CommandSender;
 use crate::drive::commands::ManagerCommand;
 use crate::drive::manager::{DriveManager, DriveStatusUI, SyncStatus};
 use crate::shellext::vector::create_vector;
 use crate::utils::app::{AppRoot, get_app_root};
+use crate::utils::format::{format_duration, plural};
 use std::sync::Arc;
+use std::time::Duration;
 use windows::Foundation::{EventRegistrationToken, TypedEventHandler, Uri};
 use windows::{
     Storage::Provider::*,
     Win32::{Foundation::*, System::Com::*},
     core::*,
 };
-use tokio::sync::mpsc;
 
 // UUID for our custom state handler - matches the C++ implementation
 pub const CLSID_STATUS_UI_HANDLER: GUID = GUID::from_u128(0xb1d8ef74_822d_401a_a14a_25f45b1f70b7);
@@ -35,7 +37,7 @@ pub struct SyncStatusUICommand {
     description: HSTRING,
     icon: Uri,
     action: StatusUIAction,
-    command_tx: mpsc::UnboundedSender<ManagerCommand>,
+    command_tx: CommandSender<ManagerCommand>,
 }
 
 impl SyncStatusUICommand {
@@ -45,7 +47,7 @@ impl SyncStatusUICommand {
         description: HSTRING,
         icon: Uri,
         action: StatusUIAction,
-        command_tx: mpsc::UnboundedSender<ManagerCommand>,
+        command_tx: CommandSender<ManagerCommand>,
     ) -> Self {
         Self {
             app_root,
@@ -93,7 +95,7 @@ impl IStorageProviderUICommand_Impl for SyncStatusUICommand_Impl {
             }
         };
 
-        if let Err(e) = self.command_tx.send(command) {
+        if let Err(e) = self.command_tx.send_user_action(command) {
             tracing::error!(target: "shellext::status_ui", error = %e, "Failed to send command");
         }
 
@@ -164,14 +166,18 @@ impl IStorageProviderStatusUISource_Impl for StatusUIHandler_Impl {
                         status.name.clone(),
                         format!("{}\\CloudIconSyncing.svg", image_path),
                         t!("syncing").to_string(),
-                        t!("syncingDescription", "count" => status.active_task_count).to_string(),
+                        syncing_description(status),
                     ),
                     SyncStatus::InSync => (
                         StorageProviderState::InSync,
                         status.name.clone(),
                         format!("{}\\CloudIconSynced.svg", image_path),
                         t!("synced").to_string(),
-                        t!("syncedDescription").to_string(),
+                        if status.conflict_count > 0 {
+                            plural("conflictCount", status.conflict_count as u64)
+                        } else {
+                            t!("syncedDescription").to_string()
+                        },
                     ),
                     SyncStatus::Paused => (
                         StorageProviderState::Paused,
@@ -185,7 +191,11 @@ impl IStorageProviderStatusUISource_Impl for StatusUIHandler_Impl {
                         status.name.clone(),
                         format!("{}\\CloudIconError.svg", image_path),
                         t!("error").to_string(),
-                        t!("errorDescription").to_string(),
+                        if status.quarantined_count > 0 {
+                            plural("quarantinedDescription", status.quarantined_count as u64)
+                        } else {
+                            t!("errorDescription").to_string()
+                        },
                     ),
                 }
             }
@@ -303,6 +313,20 @@ impl IStorageProviderStatusUISource_Impl for StatusUIHandler_Impl {
     }
 }
 
+/// Task count of a syncing drive, with the time left once it is known
+fn syncing_description(status: &DriveStatusUI) -> String {
+    let tasks = plural("syncingDescription", status.active_task_count as u64);
+    match status.eta_seconds {
+        Some(eta) => t!(
+            "syncingEta",
+            "tasks" => tasks,
+            "duration" => format_duration(Duration::from_secs(eta))
+        )
+        .to_string(),
+        None => tasks,
+    }
+}
+
 #[implement(IStorageProviderStatusUISourceFactory)]
 pub struct StatusUIHandlerFactory {
     drive_manager: Arc<DriveManager>,
@@ -0,0 +1,229 @@
+//! Adaptive transfer concurrency
+//!
+//! When enabled, the task queue periodically picks a concurrency level between
+//! a floor and a ceiling instead of using the fixed limits: it grows while
+//! tasks are waiting for a slot and the CPU has headroom, and shrinks when the
+//! CPU is saturated, transfers start failing, or the queue runs mostly idle.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// How often the level is re-evaluated
+pub(crate) const AUTOSCALE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Shrink if more than this share of the tasks finished since the last
+/// evaluation failed
+const MAX_ERROR_RATE: f64 = 0.25;
+/// Shrink above this CPU utilization
+const CPU_BUSY: f64 = 0.85;
+/// Only grow below this CPU utilization
+const CPU_HEADROOM: f64 = 0.6;
+
+fn default_floor() -> usize {
+    1
+}
+
+fn default_ceiling() -> usize {
+    8
+}
+
+/// Bounds of the adaptive concurrency level
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AutoscaleSettings {
+    /// Whether the level replaces the fixed upload and download limits
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_floor")]
+    pub floor: usize,
+    #[serde(default = "default_ceiling")]
+    pub ceiling: usize,
+}
+
+impl Default for AutoscaleSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            floor: default_floor(),
+            ceiling: default_ceiling(),
+        }
+    }
+}
+
+impl AutoscaleSettings {
+    /// Floor and ceiling, with the floor at least 1 and the ceiling at least
+    /// the floor
+    pub fn bounds(&self) -> (usize, usize) {
+        let floor = self.floor.max(1);
+        (floor, self.ceiling.max(floor))
+    }
+}
+
+/// Counters kept by the queue for the autoscaler
+#[derive(Debug, Default)]
+pub(crate) struct QueueStats {
+    /// Tasks waiting for a slot
+    pub waiting: AtomicUsize,
+    /// Upload and custom tasks waiting for or holding a slot
+    pub uploads: AtomicUsize,
+    /// Download tasks waiting for or holding a slot
+    pub downloads: AtomicUsize,
+    pub completed: AtomicU64,
+    pub failed: AtomicU64,
+}
+
+/// Load observed over one evaluation interval
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LoadSample {
+    pub waiting: usize,
+    pub running: usize,
+    /// System CPU utilization (0.0 - 1.0), if it could be measured
+    pub cpu: Option<f64>,
+    /// Share of the tasks finished during the interval that failed
+    pub error_rate: f64,
+}
+
+/// Level to use for the next interval
+pub(crate) fn next_level(
+    current: usize,
+    sample: &LoadSample,
+    settings: &AutoscaleSettings,
+) -> usize {
+    let (floor, ceiling) = settings.bounds();
+    let cpu_busy = sample.cpu.is_some_and(|cpu| cpu > CPU_BUSY);
+    let cpu_headroom = sample.cpu.is_none_or(|cpu| cpu < CPU_HEADROOM);
+
+    let level = if sample.error_rate > MAX_ERROR_RATE || cpu_busy {
+        current.saturating_sub(1)
+    } else if sample.waiting > 0 && cpu_headroom {
+        current + 1
+    } else if sample.waiting == 0 && sample.running < current / 2 {
+        current - 1
+    } else {
+        current
+    };
+    level.clamp(floor, ceiling)
+}
+
+/// Tracks finished task counters between evaluations
+#[derive(Debug, Default)]
+pub(crate) struct ErrorRate {
+    completed: u64,
+    failed: u64,
+}
+
+impl ErrorRate {
+    /// Share of tasks that failed since the previous call
+    pub fn sample(&mut self, stats: &QueueStats) -> f64 {
+        let completed = stats.completed.load(Ordering::Relaxed);
+        let failed = stats.failed.load(Ordering::Relaxed);
+        let new_completed = completed - self.completed;
+        let new_failed = failed - self.failed;
+        self.completed = completed;
+        self.failed = failed;
+
+        let finished = new_completed + new_failed;
+        if finished == 0 {
+            0.0
+        } else {
+            new_failed as f64 / finished as f64
+        }
+    }
+}
+
+/// Measures system CPU utilization between calls
+#[derive(Debug, Default)]
+pub(crate) struct CpuMeter {
+    previous: Option<(u64, u64)>,
+}
+
+impl CpuMeter {
+    /// Utilization since the previous call, `None` on the first call or if
+    /// the system times are unavailable
+    pub fn sample(&mut self) -> Option<f64> {
+        let (idle, total) = system_times()?;
+        let previous = self.previous.replace((idle, total));
+        let (prev_idle, prev_total) = previous?;
+        let total = total.checked_sub(prev_total).filter(|total| *total > 0)?;
+        let idle = idle.saturating_sub(prev_idle).min(total);
+        Some(1.0 - idle as f64 / total as f64)
+    }
+}
+
+/// Idle and total (kernel + user) CPU time of the system, in 100ns units
+fn system_times() -> Option<(u64, u64)> {
+    use windows::Win32::Foundation::FILETIME;
+    use windows::Win32::System::Threading::GetSystemTimes;
+
+    let mut idle = FILETIME::default();
+    let mut kernel = FILETIME::default();
+    let mut user = FILETIME::default();
+    unsafe { GetSystemTimes(Some(&mut idle), Some(&mut kernel), Some(&mut user)) }.ok()?;
+
+    let to_u64 = |time: FILETIME| ((time.dwHighDateTime as u64) << 32) | time.dwLowDateTime as u64;
+    // Kernel time includes idle time
+    Some((to_u64(idle), to_u64(kernel) + to_u64(user)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> AutoscaleSettings {
+        AutoscaleSettings {
+            enabled: true,
+            floor: 2,
+            ceiling: 4,
+        }
+    }
+
+    #[test]
+    fn grows_while_tasks_wait_and_cpu_has_headroom() {
+        let sample = LoadSample {
+            waiting: 3,
+            running: 2,
+            cpu: Some(0.3),
+            error_rate: 0.0,
+        };
+        assert_eq!(next_level(2, &sample, &settings()), 3);
+        assert_eq!(next_level(4, &sample, &settings()), 4);
+
+        let busy = LoadSample {
+            cpu: Some(0.7),
+            ..sample
+        };
+        assert_eq!(next_level(2, &busy, &settings()), 2);
+    }
+
+    #[test]
+    fn shrinks_on_errors_saturation_and_idleness() {
+        let failing = LoadSample {
+            waiting: 3,
+            running: 3,
+            cpu: Some(0.3),
+            error_rate: 0.5,
+        };
+        assert_eq!(next_level(3, &failing, &settings()), 2);
+
+        let saturated = LoadSample {
+            cpu: Some(0.95),
+            error_rate: 0.0,
+            ..failing
+        };
+        assert_eq!(next_level(3, &saturated, &settings()), 2);
+
+        let idle = LoadSample::default();
+        assert_eq!(next_level(4, &idle, &settings()), 3);
+        assert_eq!(next_level(2, &idle, &settings()), 2);
+    }
+
+    #[test]
+    fn error_rate_covers_tasks_since_last_sample() {
+        let stats = QueueStats::default();
+        let mut rate = ErrorRate::default();
+        stats.completed.store(3, Ordering::Relaxed);
+        stats.failed.store(1, Ordering::Relaxed);
+        assert_eq!(rate.sample(&stats), 0.25);
+        assert_eq!(rate.sample(&stats), 0.0);
+    }
+}
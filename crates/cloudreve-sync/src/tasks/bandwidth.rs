@@ -0,0 +1,172 @@
+//! Bandwidth caps set by administrator policy
+//!
+//! Uploads and downloads of every drive share one cap each, see
+//! [`Policy::max_upload_speed`] and [`Policy::max_download_speed`]. Each
+//! transfer reserves time for the bytes it moves and waits until the
+//! reservation is due, so concurrent transfers split the cap between them.
+
+use std::io;
+use std::pin::Pin;
+use std::sync::{Mutex, OnceLock};
+use std::task::{Context, Poll, ready};
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use futures::Stream;
+use tokio::time::Sleep;
+
+use crate::policy::Policy;
+
+/// Bytes that may be sent at once after an idle period, in time at the cap
+const BURST: Duration = Duration::from_millis(250);
+
+static UPLOAD_LIMITER: OnceLock<Option<RateLimiter>> = OnceLock::new();
+static DOWNLOAD_LIMITER: OnceLock<Option<RateLimiter>> = OnceLock::new();
+
+/// Shared limiter of all uploads, `None` without a cap
+pub(crate) fn upload_limiter() -> Option<&'static RateLimiter> {
+    UPLOAD_LIMITER
+        .get_or_init(|| RateLimiter::from_policy(Policy::get().max_upload_speed))
+        .as_ref()
+}
+
+/// Shared limiter of all downloads and hydrations, `None` without a cap
+pub(crate) fn download_limiter() -> Option<&'static RateLimiter> {
+    DOWNLOAD_LIMITER
+        .get_or_init(|| RateLimiter::from_policy(Policy::get().max_download_speed))
+        .as_ref()
+}
+
+/// Keeps the bytes moved through it under a rate
+pub(crate) struct RateLimiter {
+    bytes_per_sec: u64,
+    /// When the bytes reserved so far are all due
+    next: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec: bytes_per_sec.max(1),
+            next: Mutex::new(None),
+        }
+    }
+
+    fn from_policy(bytes_per_sec: Option<u64>) -> Option<Self> {
+        bytes_per_sec.filter(|&rate| rate > 0).map(Self::new)
+    }
+
+    /// Reserve time for `bytes` at `now`. Returns how long to wait before
+    /// moving more.
+    fn reserve(&self, bytes: u64, now: Instant) -> Duration {
+        let mut next = self.next.lock().unwrap();
+        // Time left unused while idle only allows a short burst
+        let earliest = now.checked_sub(BURST).unwrap_or(now);
+        let start = next.map_or(earliest, |next| next.max(earliest));
+        let due = start + Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec as f64);
+        *next = Some(due);
+        due.saturating_duration_since(now)
+    }
+
+    /// Wait until `bytes` more fit under the rate
+    pub(crate) async fn acquire(&self, bytes: u64) {
+        let wait = self.reserve(bytes, Instant::now());
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Stream of bytes held back to the rate of a [`RateLimiter`]. Passes
+/// everything through right away without one.
+pub(crate) struct Throttled<S> {
+    inner: S,
+    limiter: Option<&'static RateLimiter>,
+    delay: Option<Pin<Box<Sleep>>>,
+}
+
+impl<S> Throttled<S> {
+    pub(crate) fn new(inner: S, limiter: Option<&'static RateLimiter>) -> Self {
+        Self {
+            inner,
+            limiter,
+            delay: None,
+        }
+    }
+}
+
+impl<S> Stream for Throttled<S>
+where
+    S: Stream<Item = Result<Bytes, io::Error>> + Unpin,
+{
+    type Item = Result<Bytes, io::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Some(delay) = self.delay.as_mut() {
+            ready!(delay.as_mut().poll(cx));
+            self.delay = None;
+        }
+        let item = ready!(Pin::new(&mut self.inner).poll_next(cx));
+        if let (Some(Ok(bytes)), Some(limiter)) = (&item, self.limiter) {
+            let wait = limiter.reserve(bytes.len() as u64, Instant::now());
+            if !wait.is_zero() {
+                self.delay = Some(Box::pin(tokio::time::sleep(wait)));
+            }
+        }
+        Poll::Ready(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[test]
+    fn reservations_queue_up_at_the_rate() {
+        let limiter = RateLimiter::new(1000);
+        let now = Instant::now() + Duration::from_secs(1);
+
+        // The burst allowance covers the first 250 bytes
+        assert_eq!(limiter.reserve(250, now), Duration::ZERO);
+        assert_eq!(limiter.reserve(500, now), Duration::from_millis(500));
+        assert_eq!(limiter.reserve(500, now), Duration::from_secs(1));
+
+        // Reservations made while the limiter was busy are still due
+        let later = now + Duration::from_millis(400);
+        assert_eq!(limiter.reserve(0, later), Duration::from_millis(600));
+    }
+
+    #[test]
+    fn idle_time_does_not_accumulate() {
+        let limiter = RateLimiter::new(1000);
+        let now = Instant::now() + Duration::from_secs(1);
+        limiter.reserve(1000, now);
+
+        let idle = now + Duration::from_secs(60);
+        assert_eq!(limiter.reserve(250, idle), Duration::ZERO);
+        assert_eq!(limiter.reserve(250, idle), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn no_cap_without_a_positive_rate() {
+        assert!(RateLimiter::from_policy(None).is_none());
+        assert!(RateLimiter::from_policy(Some(0)).is_none());
+        assert!(RateLimiter::from_policy(Some(1)).is_some());
+    }
+
+    #[tokio::test]
+    async fn throttled_streams_wait_between_items() {
+        let limiter: &'static RateLimiter = Box::leak(Box::new(RateLimiter::new(10_000)));
+        let items: Vec<io::Result<Bytes>> =
+            vec![Ok(Bytes::from(vec![0; 3500])), Ok(Bytes::from(vec![0; 10]))];
+        let mut stream = Throttled::new(futures::stream::iter(items), Some(limiter));
+
+        let start = Instant::now();
+        assert_eq!(stream.next().await.unwrap().unwrap().len(), 3500);
+        assert!(start.elapsed() < Duration::from_millis(100));
+        assert_eq!(stream.next().await.unwrap().unwrap().len(), 10);
+        assert!(stream.next().await.is_none());
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,204 @@
+//! Operations on several files selected together in Explorer.
+//!
+//! The selection of one drive becomes a single task, so it shows up as one
+//! entry with progress, and ends with one notification summing up what
+//! succeeded and what failed instead of one per file.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use cloudreve_api::{api::ShareApi, models::share::CreateShareService};
+use futures::future::BoxFuture;
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+
+use crate::cfapi::placeholder::PinState;
+use crate::drive::commands::ManagerCommand;
+use crate::drive::utils::local_path_to_cr_uri;
+use crate::inventory::TaskUpdate;
+use crate::tasks::registry::{TaskContext, TaskHandler, register_task_handler, task_handler};
+use crate::utils::format::plural;
+use crate::utils::toast::send_general_text_toast;
+use crate::vfs::VirtualFs;
+
+/// Task type of batch operations
+pub const BATCH_OPERATION_KIND: &str = "batch_operation";
+
+/// What to do with every selected file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchOperation {
+    /// Drop local content, keeping placeholders
+    FreeUpSpace,
+    /// Download content and keep it on disk
+    MakeAvailableOffline,
+    /// Create a share link on the server
+    Share,
+}
+
+impl BatchOperation {
+    /// Title of the operation, as shown in the context menu
+    pub fn title(&self) -> String {
+        match self {
+            BatchOperation::FreeUpSpace => t!("freeUpSpace"),
+            BatchOperation::MakeAvailableOffline => t!("makeAvailableOffline"),
+            BatchOperation::Share => t!("createShareLinks"),
+        }
+        .to_string()
+    }
+}
+
+/// Share link created for a file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShareLink {
+    pub path: PathBuf,
+    pub url: String,
+}
+
+/// Persisted state of a batch operation task
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchOperationState {
+    pub operation: BatchOperation,
+    pub paths: Vec<PathBuf>,
+    /// Number of `paths` already processed
+    #[serde(default)]
+    pub done: usize,
+    /// Paths the operation failed for
+    #[serde(default)]
+    pub failed: Vec<PathBuf>,
+    /// Links created by [`BatchOperation::Share`]
+    #[serde(default)]
+    pub links: Vec<ShareLink>,
+}
+
+struct BatchOperationHandler {
+    vfs: Arc<dyn VirtualFs>,
+}
+
+impl BatchOperationHandler {
+    async fn run(&self, ctx: TaskContext<'_>) -> Result<()> {
+        let mut state: BatchOperationState = serde_json::from_value(
+            ctx.payload
+                .custom_state
+                .clone()
+                .context("Batch operation task has no state")?,
+        )
+        .context("Failed to parse batch operation state")?;
+        let total = state.paths.len();
+
+        while state.done < total {
+            let path = state.paths[state.done].clone();
+            match self.apply(&ctx, state.operation, &path).await {
+                Ok(Some(url)) => state.links.push(ShareLink { path, url }),
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::warn!(target: "tasks::batch", task_id = %ctx.task_id, path = %path.display(), operation = ?state.operation, error = ?e, "Batch operation failed for item");
+                    state.failed.push(path);
+                }
+            }
+            state.done += 1;
+
+            let progress = state.done as f64 / total as f64;
+            let custom_state = serde_json::to_value(&state)?;
+            ctx.report_progress(
+                progress,
+                Some(state.done as i64),
+                Some(total as i64),
+                Some(custom_state.clone()),
+            );
+            if let Err(e) = ctx.inventory.update_task(
+                ctx.task_id,
+                TaskUpdate {
+                    progress: Some(progress),
+                    processed_bytes: Some(state.done as i64),
+                    total_bytes: Some(total as i64),
+                    custom_state: Some(Some(custom_state)),
+                    ..Default::default()
+                },
+            ) {
+                tracing::warn!(target: "tasks::batch", task_id = %ctx.task_id, error = %e, "Failed to persist batch operation progress");
+            }
+        }
+
+        let failed = state.failed.len();
+        tracing::info!(target: "tasks::batch", drive = %ctx.drive_id, task_id = %ctx.task_id, operation = ?state.operation, count = total, failed, "Batch operation finished");
+
+        let mut message = plural("batchSucceeded", (total - failed) as u64);
+        if failed > 0 {
+            message.push('\n');
+            message.push_str(&plural("batchFailed", failed as u64));
+        }
+        send_general_text_toast(&state.operation.title(), &message);
+
+        if !state.links.is_empty() {
+            if let Err(e) = ctx
+                .manager_command_tx
+                .send(ManagerCommand::ShareLinksCreated {
+                    drive_id: ctx.drive_id.to_string(),
+                    links: state.links,
+                })
+            {
+                tracing::warn!(target: "tasks::batch", error = %e, "Failed to send ShareLinksCreated command");
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply `operation` to one path. Returns the share link, if one was
+    /// created.
+    async fn apply(
+        &self,
+        ctx: &TaskContext<'_>,
+        operation: BatchOperation,
+        path: &Path,
+    ) -> Result<Option<String>> {
+        match operation {
+            // Explorer reports the pin change, and the placeholder is then
+            // hydrated or dehydrated like after "Always keep on this device"
+            BatchOperation::FreeUpSpace => {
+                self.vfs.pin_tree(path, PinState::Unpinned)?;
+                Ok(None)
+            }
+            BatchOperation::MakeAvailableOffline => {
+                self.vfs.pin_tree(path, PinState::Pinned)?;
+                Ok(None)
+            }
+            BatchOperation::Share => {
+                let uri = local_path_to_cr_uri(
+                    path.to_path_buf(),
+                    ctx.sync_path.to_path_buf(),
+                    ctx.remote_base.to_string(),
+                )
+                .context("failed to convert local path to cloudreve uri")?;
+                let url = ctx
+                    .cr_client
+                    .create_share(&CreateShareService {
+                        uri: uri.to_string(),
+                        ..Default::default()
+                    })
+                    .await
+                    .with_context(|| format!("Failed to share {}", path.display()))?;
+                Ok(Some(url))
+            }
+        }
+    }
+}
+
+impl TaskHandler for BatchOperationHandler {
+    fn kind(&self) -> &'static str {
+        BATCH_OPERATION_KIND
+    }
+
+    fn execute<'a>(&'a self, ctx: TaskContext<'a>) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.run(ctx))
+    }
+}
+
+/// Register the batch operation task type. Does nothing if it already is.
+pub(crate) fn register(vfs: Arc<dyn VirtualFs>) -> Result<()> {
+    if task_handler(BATCH_OPERATION_KIND).is_some() {
+        return Ok(());
+    }
+    register_task_handler(Arc::new(BatchOperationHandler { vfs }))
+}
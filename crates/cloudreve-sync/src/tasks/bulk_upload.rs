@@ -0,0 +1,161 @@
+//! Upload of the changes a mass operation made in one subtree.
+//!
+//! After an event storm (see [`crate::drive::storm`]) the subtree is
+//! reconciled once, and the files it found to upload are handed to a single
+//! task instead of one task each. The files are uploaded one after the
+//! other, so the operation shows up as one entry whose progress counts files.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use dashmap::DashMap;
+use futures::future::BoxFuture;
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+
+use crate::inventory::TaskUpdate;
+use crate::tasks::queue::QueuedTask;
+use crate::tasks::registry::{TaskContext, TaskHandler, register_task_handler, task_handler};
+use crate::tasks::types::TaskPayload;
+use crate::tasks::upload::UploadTask;
+use crate::utils::format::plural;
+use crate::utils::toast::send_general_text_toast;
+
+/// Task type of bulk uploads
+pub const BULK_UPLOAD_KIND: &str = "bulk_upload";
+
+/// How often the state of a running upload is saved
+const PERSIST_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Persisted state of a bulk upload task
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BulkUploadState {
+    pub paths: Vec<PathBuf>,
+    /// Number of `paths` already processed
+    #[serde(default)]
+    pub done: usize,
+    /// Paths that failed to upload
+    #[serde(default)]
+    pub failed: Vec<PathBuf>,
+}
+
+impl BulkUploadState {
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        Self {
+            paths,
+            done: 0,
+            failed: Vec::new(),
+        }
+    }
+}
+
+struct BulkUploadHandler;
+
+impl BulkUploadHandler {
+    async fn run(&self, ctx: TaskContext<'_>) -> Result<()> {
+        let mut state: BulkUploadState = serde_json::from_value(
+            ctx.payload
+                .custom_state
+                .clone()
+                .context("Bulk upload task has no state")?,
+        )
+        .context("Failed to parse bulk upload state")?;
+        let total = state.paths.len();
+        // Progress of single files is not shown, only the file count
+        let file_progress = Arc::new(DashMap::new());
+
+        let mut last_persist = Instant::now();
+        while state.done < total {
+            let path = state.paths[state.done].clone();
+            let task = QueuedTask {
+                task_id: format!("{}:{}", ctx.task_id, state.done),
+                payload: TaskPayload::upload(path.clone()),
+            };
+            let result = UploadTask::new(
+                ctx.inventory.clone(),
+                ctx.cr_client.clone(),
+                ctx.drive_id,
+                &task,
+                ctx.sync_path.to_path_buf(),
+                ctx.remote_base.to_string(),
+                file_progress.clone(),
+            )
+            .execute()
+            .await;
+            if let Err(e) = result {
+                tracing::warn!(target: "tasks::bulk_upload", task_id = %ctx.task_id, path = %path.display(), error = ?e, "Failed to upload file");
+                state.failed.push(path);
+            }
+            state.done += 1;
+
+            ctx.report_progress(
+                state.done as f64 / total as f64,
+                Some(state.done as i64),
+                Some(total as i64),
+                None,
+            );
+            if last_persist.elapsed() >= PERSIST_INTERVAL {
+                persist_state(&ctx, &state);
+                last_persist = Instant::now();
+            }
+        }
+        persist_state(&ctx, &state);
+
+        let failed = state.failed.len();
+        tracing::info!(target: "tasks::bulk_upload", drive = %ctx.drive_id, task_id = %ctx.task_id, path = %ctx.payload.local_path_display(), count = total, failed, "Bulk upload finished");
+
+        let name = ctx
+            .payload
+            .local_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| ctx.payload.local_path_display());
+        let mut message = plural("batchSucceeded", (total - failed) as u64);
+        if failed > 0 {
+            message.push('\n');
+            message.push_str(&plural("batchFailed", failed as u64));
+        }
+        send_general_text_toast(&t!("bulkUploadFinished", "name" => name), &message);
+        Ok(())
+    }
+}
+
+impl TaskHandler for BulkUploadHandler {
+    fn kind(&self) -> &'static str {
+        BULK_UPLOAD_KIND
+    }
+
+    fn execute<'a>(&'a self, ctx: TaskContext<'a>) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.run(ctx))
+    }
+}
+
+/// Save the state, so a restarted task skips the files already uploaded
+fn persist_state(ctx: &TaskContext<'_>, state: &BulkUploadState) {
+    let custom_state = match serde_json::to_value(state) {
+        Ok(value) => value,
+        Err(e) => {
+            tracing::warn!(target: "tasks::bulk_upload", task_id = %ctx.task_id, error = %e, "Failed to serialize bulk upload state");
+            return;
+        }
+    };
+    if let Err(e) = ctx.inventory.update_task(
+        ctx.task_id,
+        TaskUpdate {
+            custom_state: Some(Some(custom_state)),
+            ..Default::default()
+        },
+    ) {
+        tracing::warn!(target: "tasks::bulk_upload", task_id = %ctx.task_id, error = %e, "Failed to persist bulk upload progress");
+    }
+}
+
+/// Register the bulk upload task type. Does nothing if it already is.
+pub(crate) fn register() -> Result<()> {
+    if task_handler(BULK_UPLOAD_KIND).is_some() {
+        return Ok(());
+    }
+    register_task_handler(Arc::new(BulkUploadHandler))
+}
@@ -20,7 +20,6 @@ use std::{
 use anyhow::{Context, Result};
 use cloudreve_api::{Client, api::ExplorerApi, models::explorer::FileURLService};
 use dashmap::DashMap;
-use futures::StreamExt;
 use tokio::io::AsyncWriteExt;
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, info, warn};
@@ -28,9 +27,16 @@ use uuid::Uuid;
 
 use crate::{
     cfapi::placeholder::LocalFileInfo,
-    drive::{placeholder::CrPlaceholder, utils::local_path_to_cr_uri},
+    drive::{
+        command_queue::CommandSender,
+        commands::ManagerCommand,
+        origin::{ChangeOrigins, EngineWrite},
+        placeholder::CrPlaceholder,
+        scan::scan_downloaded_file,
+        utils::local_path_to_cr_uri,
+    },
     inventory::{FileMetadata, InventoryDb},
-    tasks::queue::QueuedTask,
+    tasks::{ResumableDownload, queue::QueuedTask},
 };
 
 use super::types::TaskProgress;
@@ -173,6 +179,10 @@ pub struct DownloadTask<'a> {
     remote_file_info: Option<cloudreve_api::models::explorer::FileResponse>,
     cancel_token: CancellationToken,
     progress_map: Arc<DashMap<String, TaskProgress>>,
+    /// Set to scan the file once downloaded
+    manager_command_tx: Option<CommandSender<ManagerCommand>>,
+    /// Set to record the content replacement as the engine's own write
+    change_origins: Option<ChangeOrigins>,
 }
 
 impl<'a> DownloadTask<'a> {
@@ -197,9 +207,24 @@ impl<'a> DownloadTask<'a> {
             remote_base,
             cancel_token: CancellationToken::new(),
             progress_map,
+            manager_command_tx: None,
+            change_origins: None,
         }
     }
 
+    /// Scan the file once downloaded, reporting threats through `manager_command_tx`
+    pub fn with_manager_command_tx(mut self, tx: CommandSender<ManagerCommand>) -> Self {
+        self.manager_command_tx = Some(tx);
+        self
+    }
+
+    /// Record the content replacement in `origins`, so the watcher does not
+    /// upload it again
+    pub fn with_change_origins(mut self, origins: ChangeOrigins) -> Self {
+        self.change_origins = Some(origins);
+        self
+    }
+
     /// Set the cancellation token
     #[allow(dead_code)]
     pub fn with_cancel_token(mut self, token: CancellationToken) -> Self {
@@ -323,23 +348,13 @@ impl<'a> DownloadTask<'a> {
             request.entity = self.remote_file_info.as_ref().unwrap().primary_entity.clone();
         }
 
-        let entity_url_res = self
-            .cr_client
-            .get_file_url(&request)
-            .await
-            .context("failed to get file url")?;
-
-        let download_url = entity_url_res
-            .urls
-            .first()
-            .context("no download URL in response")?
-            .url
-            .clone();
+        let download =
+            ResumableDownload::new(self.cr_client.clone(), request, Some(0..file_size)).await?;
 
         debug!(
             target: "tasks::download",
             task_id = %self.task.task_id,
-            download_url = %download_url,
+            download_url = %download.url(),
             file_size = file_size,
             "Got download URL"
         );
@@ -363,7 +378,7 @@ impl<'a> DownloadTask<'a> {
 
         // Download to temp file
         let download_result = self
-            .download_to_temp(&download_url, &temp_path, tracker.clone(), &reporter)
+            .download_to_temp(download, &temp_path, tracker.clone(), &reporter)
             .await;
 
         match download_result {
@@ -388,6 +403,8 @@ impl<'a> DownloadTask<'a> {
                     "Download completed successfully"
                 );
 
+                self.scan_downloaded().await;
+
                 Ok(())
             }
             Err(e) => {
@@ -400,42 +417,46 @@ impl<'a> DownloadTask<'a> {
         }
     }
 
+    /// Hand the downloaded file to the configured scanner
+    async fn scan_downloaded(&self) {
+        let Some(tx) = &self.manager_command_tx else {
+            return;
+        };
+        let local_path = &self.task.payload.local_path;
+        if let Err(e) = scan_downloaded_file(self.drive_id, local_path, &self.inventory, tx).await {
+            warn!(
+                target: "tasks::download",
+                task_id = %self.task.task_id,
+                error = %e,
+                "Failed to scan downloaded file"
+            );
+        }
+    }
+
     /// Download file content to a temporary file
     async fn download_to_temp(
         &self,
-        url: &str,
+        mut download: ResumableDownload,
         temp_path: &PathBuf,
         tracker: Arc<DownloadProgressTracker>,
         reporter: &InMemoryDownloadProgressReporter,
     ) -> Result<()> {
-        let client = reqwest::Client::new();
-        let response = client
-            .get(url)
-            .send()
-            .await
-            .context("failed to send download request")?;
-
-        if !response.status().is_success() {
-            anyhow::bail!("Download request failed with status: {}", response.status());
-        }
-
         // Create temp file
         let mut file = tokio::fs::File::create(&temp_path)
             .await
             .context("failed to create temp file")?;
 
-        // Stream download with progress tracking
-        let mut stream = response.bytes_stream();
+        // Stream download with progress tracking; expired URLs are refreshed
+        // and the transfer resumes at the current offset
         let mut last_report = Instant::now();
         const REPORT_INTERVAL: Duration = Duration::from_millis(100);
 
-        while let Some(chunk_result) = stream.next().await {
+        while let Some(chunk) = download.next_chunk().await? {
             // Check for cancellation
             if self.cancel_token.is_cancelled() {
                 anyhow::bail!("Download cancelled");
             }
 
-            let chunk = chunk_result.context("failed to read chunk from stream")?;
             file.write_all(&chunk)
                 .await
                 .context("failed to write chunk to temp file")?;
@@ -470,6 +491,10 @@ impl<'a> DownloadTask<'a> {
             local_path = %local_path.display(),
             "Replacing placeholder content"
         );
+        let _origin = self
+            .change_origins
+            .as_ref()
+            .map(|origins| origins.begin(local_path.clone(), EngineWrite::Download));
 
         // Use Windows ReplaceFileW for atomic replacement
         // This preserves file attributes and provides atomicity
@@ -0,0 +1,162 @@
+//! Sharing transfer slots between drives
+//!
+//! Each drive's task queue has its own limits, so without coordination one
+//! drive's large initial sync can take every connection while the others
+//! wait. With fair scheduling enabled, a global budget of uploads and
+//! downloads is split between the drives that have work queued, by their
+//! priority, and each queue's limits are capped at its share. A drive can be
+//! boosted for a while to get most of the budget.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How often the shares are recomputed from the queued work
+pub(crate) const FAIR_SHARE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Weight multiplier of a boosted drive
+const BOOST_FACTOR: u64 = 10;
+
+/// Slots a drive's queue may use at most, on top of its own limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FairShare {
+    pub uploads: usize,
+    pub downloads: usize,
+}
+
+/// Queued work and weight of one drive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Demand {
+    /// Tasks waiting for or holding a slot
+    pub tasks: usize,
+    pub weight: u64,
+}
+
+/// Split `budget` slots between drives. Every drive with queued tasks gets at
+/// least one slot, the rest go one at a time to the drive with the fewest
+/// slots relative to its weight that can still use one. Idle drives get the
+/// share they would have if every drive were busy, so they can start work
+/// before the next split.
+pub(crate) fn allocate(budget: usize, demands: &[Demand]) -> Vec<usize> {
+    let weight = |demand: &Demand| demand.weight.max(1);
+    let mut shares: Vec<usize> = demands
+        .iter()
+        .map(|demand| usize::from(demand.tasks > 0))
+        .collect();
+
+    let mut remaining = budget.saturating_sub(shares.iter().sum());
+    while remaining > 0 {
+        // Smallest shares[i] / weight(i), compared without division
+        let next = (0..demands.len())
+            .filter(|&i| shares[i] < demands[i].tasks)
+            .min_by(|&a, &b| {
+                let lhs = shares[a] as u64 * weight(&demands[b]);
+                let rhs = shares[b] as u64 * weight(&demands[a]);
+                lhs.cmp(&rhs)
+            });
+        let Some(next) = next else {
+            break;
+        };
+        shares[next] += 1;
+        remaining -= 1;
+    }
+
+    let total_weight: u64 = demands.iter().map(weight).sum();
+    for (share, demand) in shares.iter_mut().zip(demands) {
+        if demand.tasks == 0 {
+            let fair = budget as u64 * weight(demand) / total_weight.max(1);
+            *share = (fair as usize).max(1);
+        }
+    }
+    shares
+}
+
+/// Drives temporarily given a larger share, until the recorded deadline
+#[derive(Debug, Default)]
+pub(crate) struct Boosts {
+    until: Mutex<HashMap<String, Instant>>,
+}
+
+impl Boosts {
+    /// Boost `drive_id` for `duration`, or end its boost if zero
+    pub fn set(&self, drive_id: &str, duration: Duration) {
+        let mut until = self.until.lock().unwrap();
+        if duration.is_zero() {
+            until.remove(drive_id);
+        } else {
+            until.insert(drive_id.to_string(), Instant::now() + duration);
+        }
+    }
+
+    /// Weight of `drive_id` for `priority`, raised while it is boosted.
+    /// Expired boosts are dropped.
+    pub fn weight(&self, drive_id: &str, priority: u32) -> u64 {
+        let mut until = self.until.lock().unwrap();
+        let boosted = match until.get(drive_id) {
+            Some(deadline) if *deadline > Instant::now() => true,
+            Some(_) => {
+                until.remove(drive_id);
+                false
+            }
+            None => false,
+        };
+        let weight = u64::from(priority.max(1));
+        if boosted {
+            weight * BOOST_FACTOR
+        } else {
+            weight
+        }
+    }
+
+    /// Time left on the boost of `drive_id`, if any
+    pub fn remaining(&self, drive_id: &str) -> Option<Duration> {
+        let until = self.until.lock().unwrap();
+        until
+            .get(drive_id)
+            .and_then(|deadline| deadline.checked_duration_since(Instant::now()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn busy(tasks: usize, weight: u64) -> Demand {
+        Demand { tasks, weight }
+    }
+
+    #[test]
+    fn busy_drives_split_by_weight() {
+        let shares = allocate(8, &[busy(100, 1), busy(100, 1)]);
+        assert_eq!(shares, vec![4, 4]);
+
+        let shares = allocate(8, &[busy(100, 3), busy(100, 1)]);
+        assert_eq!(shares, vec![6, 2]);
+    }
+
+    #[test]
+    fn unused_share_goes_to_busier_drives() {
+        let shares = allocate(8, &[busy(100, 1), busy(2, 1), busy(0, 1)]);
+        assert_eq!(shares, vec![6, 2, 2]);
+    }
+
+    #[test]
+    fn every_busy_drive_gets_a_slot() {
+        let shares = allocate(2, &[busy(100, 10), busy(5, 1), busy(5, 1)]);
+        assert_eq!(shares, vec![1, 1, 1]);
+    }
+
+    #[test]
+    fn boost_raises_weight_until_cleared() {
+        let boosts = Boosts::default();
+        assert_eq!(boosts.weight("a", 2), 2);
+
+        boosts.set("a", Duration::from_secs(60));
+        assert_eq!(boosts.weight("a", 2), 2 * BOOST_FACTOR);
+        assert!(boosts.remaining("a").is_some());
+
+        boosts.set("a", Duration::ZERO);
+        assert_eq!(boosts.weight("a", 2), 2);
+        assert_eq!(boosts.remaining("a"), None);
+    }
+}
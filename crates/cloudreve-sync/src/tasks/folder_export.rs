@@ -0,0 +1,399 @@
+//! Export a hydrated copy of a remote folder to a local folder outside the
+//! sync root, e.g. for a backup or to hand the files to someone.
+//!
+//! The remote tree is listed once when the task first runs, and every file is
+//! downloaded at the version seen then, so files changed in the cloud during
+//! the export don't mix old and new content. Several files download at once.
+//! Content is written to `.part` files that a resumed task continues from.
+//! Once every file is in place, a `MANIFEST.sha256` in the format of
+//! `sha256sum` lists the hash of each file.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use cloudreve_api::{
+    api::explorer::ExplorerApiExt,
+    models::{
+        common::ListAllRes,
+        explorer::{FileURLService, ListResponse, file_type},
+        uri::CrUri,
+    },
+};
+use futures::{StreamExt, future::BoxFuture};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+
+use crate::drive::utils::remote_path_to_local_relative_path;
+use crate::inventory::TaskUpdate;
+use crate::tasks::ResumableDownload;
+use crate::tasks::registry::{TaskContext, TaskHandler, register_task_handler, task_handler};
+use crate::utils::file::write_atomic;
+
+/// Task type of folder exports
+pub const FOLDER_EXPORT_KIND: &str = "folder_export";
+/// Name of the hash list written into the export folder
+pub const EXPORT_MANIFEST: &str = "MANIFEST.sha256";
+
+/// Files downloaded at the same time
+const EXPORT_PARALLELISM: usize = 4;
+/// Page size used when listing the remote folder
+const LIST_PAGE_SIZE: i32 = 1000;
+/// Minimum time between two saves of the task state
+const PERSIST_INTERVAL: Duration = Duration::from_secs(5);
+
+/// File of a folder export
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedFile {
+    /// Path relative to the export folder
+    pub path: PathBuf,
+    pub uri: String,
+    pub size: i64,
+    /// Version of the file when the folder was listed
+    pub entity: Option<String>,
+    /// Hex SHA-256 of the content, set once the file is exported
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+/// Persisted state of a folder export task
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FolderExportState {
+    /// Remote folder to export
+    pub uri: String,
+    /// Local folder to export it into
+    pub destination: PathBuf,
+    /// Set once the remote folder is listed
+    #[serde(default)]
+    pub listed: bool,
+    /// Folders relative to `destination`, created even if empty
+    #[serde(default)]
+    pub folders: Vec<PathBuf>,
+    #[serde(default)]
+    pub files: Vec<ExportedFile>,
+}
+
+impl FolderExportState {
+    pub fn new(uri: String, destination: PathBuf) -> Self {
+        Self {
+            uri,
+            destination,
+            listed: false,
+            folders: Vec::new(),
+            files: Vec::new(),
+        }
+    }
+
+    fn total_bytes(&self) -> i64 {
+        self.files.iter().map(|file| file.size).sum()
+    }
+
+    fn exported_bytes(&self) -> i64 {
+        self.files
+            .iter()
+            .filter(|file| file.sha256.is_some())
+            .map(|file| file.size)
+            .sum()
+    }
+
+    /// Lines of the manifest, sorted by path and with `/` as separator
+    fn manifest(&self) -> String {
+        let mut entries: Vec<(String, &str)> = self
+            .files
+            .iter()
+            .filter_map(|file| {
+                let path = file.path.to_string_lossy().replace('\\', "/");
+                Some((path, file.sha256.as_deref()?))
+            })
+            .collect();
+        entries.sort();
+        entries
+            .into_iter()
+            .map(|(path, hash)| format!("{}  {}\n", hash, path))
+            .collect()
+    }
+}
+
+struct FolderExportHandler;
+
+impl FolderExportHandler {
+    async fn run(&self, ctx: TaskContext<'_>) -> Result<()> {
+        let mut state: FolderExportState = serde_json::from_value(
+            ctx.payload
+                .custom_state
+                .clone()
+                .context("Folder export task has no state")?,
+        )
+        .context("Failed to parse folder export state")?;
+
+        if !state.listed {
+            list_remote_folder(&ctx, &mut state).await?;
+            persist_state(&ctx, &state, 0);
+        }
+
+        tokio::fs::create_dir_all(&state.destination)
+            .await
+            .context("Failed to create export folder")?;
+        for folder in &state.folders {
+            tokio::fs::create_dir_all(state.destination.join(folder))
+                .await
+                .with_context(|| format!("Failed to create folder {}", folder.display()))?;
+        }
+
+        let total = state.total_bytes();
+        // Bytes of finished files plus those received for files in progress
+        let received = AtomicU64::new(state.exported_bytes() as u64);
+        let jobs: Vec<(usize, ExportedFile)> = state
+            .files
+            .iter()
+            .enumerate()
+            .filter(|(_, file)| file.sha256.is_none())
+            .map(|(i, file)| (i, file.clone()))
+            .collect();
+        tracing::info!(target: "tasks::folder_export", task_id = %ctx.task_id, uri = %state.uri, destination = %state.destination.display(), files = state.files.len(), pending = jobs.len(), "Exporting folder");
+
+        let destination = state.destination.clone();
+        let mut exports = futures::stream::iter(jobs)
+            .map(|(i, file)| {
+                let ctx = &ctx;
+                let destination = &destination;
+                let received = &received;
+                async move {
+                    let result = export_file(ctx, destination, &file, received, total).await;
+                    (i, file, result)
+                }
+            })
+            .buffer_unordered(EXPORT_PARALLELISM);
+
+        let mut last_persist = Instant::now();
+        while let Some((i, file, result)) = exports.next().await {
+            match result {
+                Ok(hash) => state.files[i].sha256 = Some(hash),
+                Err(e) => {
+                    // Keep what is done, so a retry continues from here
+                    drop(exports);
+                    persist_state(&ctx, &state, total);
+                    return Err(e).with_context(|| format!("Failed to export {}", file.uri));
+                }
+            }
+            if last_persist.elapsed() >= PERSIST_INTERVAL {
+                persist_state(&ctx, &state, total);
+                last_persist = Instant::now();
+            }
+        }
+        persist_state(&ctx, &state, total);
+
+        let manifest = state.manifest();
+        let destination = state.destination.clone();
+        tokio::task::spawn_blocking(move || {
+            write_atomic(&destination.join(EXPORT_MANIFEST), manifest)
+        })
+        .await
+        .context("Manifest writing panicked")?
+        .context("Failed to write export manifest")?;
+
+        tracing::info!(target: "tasks::folder_export", drive = %ctx.drive_id, task_id = %ctx.task_id, uri = %state.uri, destination = %state.destination.display(), files = state.files.len(), bytes = total, "Folder export finished");
+        Ok(())
+    }
+}
+
+impl TaskHandler for FolderExportHandler {
+    fn kind(&self) -> &'static str {
+        FOLDER_EXPORT_KIND
+    }
+
+    fn execute<'a>(&'a self, ctx: TaskContext<'a>) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.run(ctx))
+    }
+}
+
+/// Register the folder export task type. Does nothing if it already is.
+pub(crate) fn register() -> Result<()> {
+    if task_handler(FOLDER_EXPORT_KIND).is_some() {
+        return Ok(());
+    }
+    register_task_handler(Arc::new(FolderExportHandler))
+}
+
+/// Record every folder and file under the exported folder in `state`
+async fn list_remote_folder(ctx: &TaskContext<'_>, state: &mut FolderExportState) -> Result<()> {
+    let base = CrUri::new(&state.uri)?;
+    let mut pending = vec![state.uri.clone()];
+    while let Some(uri) = pending.pop() {
+        let mut previous: Option<ListAllRes<ListResponse>> = None;
+        loop {
+            let response = ctx
+                .cr_client
+                .list_files_all(previous.as_ref(), &uri, LIST_PAGE_SIZE)
+                .await
+                .with_context(|| format!("failed to list remote folder {}", uri))?;
+
+            for file in &response.res.files {
+                let path = remote_path_to_local_relative_path(&CrUri::new(&file.path)?, &base)?;
+                if file.file_type == file_type::FOLDER {
+                    state.folders.push(path);
+                    pending.push(file.path.clone());
+                } else {
+                    state.files.push(ExportedFile {
+                        path,
+                        uri: file.path.clone(),
+                        size: file.size.max(0),
+                        entity: file.primary_entity.clone(),
+                        sha256: None,
+                    });
+                }
+            }
+
+            if !response.more {
+                break;
+            }
+            previous = Some(response);
+        }
+    }
+
+    state.listed = true;
+    tracing::debug!(target: "tasks::folder_export", task_id = %ctx.task_id, uri = %state.uri, files = state.files.len(), folders = state.folders.len(), "Listed folder to export");
+    Ok(())
+}
+
+/// Download `file` into the export folder and return the hash of its content
+async fn export_file(
+    ctx: &TaskContext<'_>,
+    destination: &Path,
+    file: &ExportedFile,
+    received: &AtomicU64,
+    total: i64,
+) -> Result<String> {
+    let target = destination.join(&file.path);
+    let mut part = target.clone().into_os_string();
+    part.push(".part");
+    let part = PathBuf::from(part);
+    let size = file.size as u64;
+
+    // Continue a partial download of an earlier run
+    let (mut hasher, offset) = {
+        let part = part.clone();
+        tokio::task::spawn_blocking(move || hash_partial(&part, size))
+            .await
+            .context("Hashing panicked")??
+    };
+    received.fetch_add(offset, Ordering::SeqCst);
+
+    let mut out = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&part)
+        .await
+        .with_context(|| format!("Failed to open {}", part.display()))?;
+    if offset < size {
+        let request = FileURLService {
+            uris: vec![file.uri.clone()],
+            entity: file.entity.clone(),
+            ..Default::default()
+        };
+        let mut download =
+            ResumableDownload::new(ctx.cr_client.clone(), request, Some(offset..size)).await?;
+        while let Some(chunk) = download.next_chunk().await? {
+            out.write_all(&chunk)
+                .await
+                .context("Failed to write exported file")?;
+            hasher.update(&chunk);
+            let done =
+                received.fetch_add(chunk.len() as u64, Ordering::SeqCst) + chunk.len() as u64;
+            let progress = if total > 0 {
+                (done as f64 / total as f64).min(1.0)
+            } else {
+                1.0
+            };
+            ctx.report_progress(progress, Some(done as i64), Some(total), None);
+        }
+    }
+    out.sync_all()
+        .await
+        .context("Failed to flush exported file")?;
+    drop(out);
+
+    tokio::fs::rename(&part, &target)
+        .await
+        .with_context(|| format!("Failed to move {} into place", target.display()))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Hash what an earlier run downloaded to `part`. Returns the hasher and the
+/// offset to continue from; a file larger than expected is started over.
+fn hash_partial(part: &Path, size: u64) -> Result<(Sha256, u64)> {
+    let mut hasher = Sha256::new();
+    let mut file = match std::fs::File::open(part) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok((hasher, 0)),
+        Err(e) => return Err(e).with_context(|| format!("Failed to open {}", part.display())),
+    };
+    if file.metadata()?.len() > size {
+        drop(file);
+        std::fs::remove_file(part)?;
+        return Ok((hasher, 0));
+    }
+    let offset = io::copy(&mut file, &mut hasher)?;
+    Ok((hasher, offset))
+}
+
+/// Save the state, so a restarted task skips the files already exported
+fn persist_state(ctx: &TaskContext<'_>, state: &FolderExportState, total: i64) {
+    let exported = state.exported_bytes();
+    let progress = if total > 0 {
+        exported as f64 / total as f64
+    } else {
+        0.0
+    };
+    let custom_state = match serde_json::to_value(state) {
+        Ok(value) => value,
+        Err(e) => {
+            tracing::warn!(target: "tasks::folder_export", task_id = %ctx.task_id, error = %e, "Failed to serialize folder export state");
+            return;
+        }
+    };
+    if let Err(e) = ctx.inventory.update_task(
+        ctx.task_id,
+        TaskUpdate {
+            progress: Some(progress),
+            processed_bytes: Some(exported),
+            total_bytes: Some(total),
+            custom_state: Some(Some(custom_state)),
+            ..Default::default()
+        },
+    ) {
+        tracing::warn!(target: "tasks::folder_export", task_id = %ctx.task_id, error = %e, "Failed to persist folder export progress");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_lists_exported_files_by_path() {
+        let file = |path: &str, sha256: Option<&str>| ExportedFile {
+            path: PathBuf::from(path),
+            uri: format!("cloudreve://my/Export/{}", path),
+            size: 1,
+            entity: None,
+            sha256: sha256.map(|hash| hash.repeat(64)),
+        };
+        let mut state = FolderExportState::new("cloudreve://my/Export".into(), "D:\\Backup".into());
+        state.files = vec![
+            file("docs\\b.txt", Some("a")),
+            file("a.txt", Some("b")),
+            file("pending.txt", None),
+        ];
+
+        let a = "a".repeat(64);
+        let b = "b".repeat(64);
+        assert_eq!(state.manifest(), format!("{b}  a.txt\n{a}  docs/b.txt\n"));
+        assert_eq!(state.exported_bytes(), 2);
+        assert_eq!(state.total_bytes(), 3);
+    }
+}
@@ -0,0 +1,560 @@
+//! Import the local folder of another sync provider, such as OneDrive,
+//! Dropbox or Nextcloud, into a remote folder.
+//!
+//! The folder is scanned once when the task first runs, leaving out the
+//! bookkeeping files of the provider. Files are uploaded one after the other,
+//! optionally moved elsewhere in the destination by [ImportMapping]s. Files
+//! already in the destination are kept as they are, and with
+//! `skip_duplicates` a file with the same content as one imported before is
+//! not uploaded again. The folder can then become the sync folder of a new
+//! drive, which adopts the imported files instead of downloading them.
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use cloudreve_api::{
+    ApiError,
+    api::ExplorerApi,
+    error::ErrorCode,
+    models::{explorer::CreateFileService, uri::CrUri},
+};
+use dashmap::DashMap;
+use futures::future::BoxFuture;
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+
+use crate::drive::commands::ManagerCommand;
+use crate::drive::duplicate_files::hash_file;
+use crate::drive::ignore::IgnoreMatcher;
+use crate::inventory::TaskUpdate;
+use crate::tasks::TaskProgress;
+use crate::tasks::registry::{TaskContext, TaskHandler, register_task_handler, task_handler};
+use crate::uploader::{ProgressCallback, ProgressUpdate, UploadParams, Uploader, UploaderConfig};
+use crate::utils::format::plural;
+use crate::utils::toast::send_general_text_toast;
+
+/// Task type of folder imports
+pub const FOLDER_IMPORT_KIND: &str = "folder_import";
+
+/// Minimum time between two saves of the task state
+const PERSIST_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Sync provider whose folder is imported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportSource {
+    OneDrive,
+    Dropbox,
+    /// Nextcloud or ownCloud
+    Nextcloud,
+    Other,
+}
+
+impl ImportSource {
+    /// Recognize the provider from the files it keeps in `folder`, or for
+    /// OneDrive from the folder it was set up with
+    pub fn detect(folder: &Path) -> Self {
+        if folder.join(".dropbox").exists() || folder.join(".dropbox.cache").exists() {
+            return ImportSource::Dropbox;
+        }
+        let nextcloud = std::fs::read_dir(folder).is_ok_and(|entries| {
+            entries.flatten().any(|entry| {
+                let name = entry.file_name().to_string_lossy().to_lowercase();
+                name.starts_with(".sync_")
+                    || name.starts_with("._sync_")
+                    || name.starts_with(".owncloudsync.log")
+            })
+        });
+        if nextcloud {
+            return ImportSource::Nextcloud;
+        }
+        let onedrive = ["OneDrive", "OneDriveConsumer", "OneDriveCommercial"]
+            .iter()
+            .filter_map(std::env::var_os)
+            .any(|root| folder.starts_with(&root));
+        if onedrive {
+            return ImportSource::OneDrive;
+        }
+        ImportSource::Other
+    }
+
+    /// Ignore patterns for the files the provider keeps for itself
+    pub fn ignore_patterns(self) -> Vec<String> {
+        let patterns: &[&str] = match self {
+            ImportSource::OneDrive => &[".849C9593-D756-4E56-8D6E-42412F2A707B"],
+            ImportSource::Dropbox => &[".dropbox", ".dropbox.attr", ".dropbox.cache/"],
+            ImportSource::Nextcloud => &[
+                ".sync_*.db*",
+                "._sync_*.db*",
+                ".owncloudsync.log*",
+                ".nextcloudsync.log*",
+                ".sync-exclude.lst",
+            ],
+            ImportSource::Other => &[],
+        };
+        patterns
+            .iter()
+            .chain(&["desktop.ini", "Thumbs.db", ".DS_Store"])
+            .map(|pattern| pattern.to_string())
+            .collect()
+    }
+}
+
+/// Put the files under `source` into `target` instead of the same place in
+/// the destination
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportMapping {
+    /// Folder relative to the imported folder
+    pub source: PathBuf,
+    /// Folder relative to the destination, `/`-separated; empty for the
+    /// destination itself, unset to leave the files out
+    pub target: Option<String>,
+}
+
+/// What happened to an imported file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ImportOutcome {
+    Uploaded,
+    /// A file was already at the target path and was kept
+    Existing,
+    /// Same content as a file imported before
+    Duplicate {
+        of: PathBuf,
+    },
+}
+
+/// File of a folder import
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportedFile {
+    /// Path relative to the imported folder
+    pub path: PathBuf,
+    /// Path relative to the destination, `/`-separated
+    pub target: String,
+    pub size: u64,
+    /// Hex SHA-256 of the content, if `skip_duplicates` is set
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// Set once the file is handled
+    #[serde(default)]
+    pub outcome: Option<ImportOutcome>,
+}
+
+/// Persisted state of a folder import task
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FolderImportState {
+    /// Local folder to import
+    pub source: PathBuf,
+    pub provider: ImportSource,
+    /// Remote folder to import into
+    pub uri: String,
+    #[serde(default)]
+    pub mappings: Vec<ImportMapping>,
+    #[serde(default)]
+    pub skip_duplicates: bool,
+    /// Make `source` the sync folder of a new drive once imported
+    #[serde(default)]
+    pub setup_sync_root: bool,
+    /// Set once the folder is scanned
+    #[serde(default)]
+    pub scanned: bool,
+    /// Folders relative to the destination, parents first. They are created
+    /// before uploading, so empty folders are imported too.
+    #[serde(default)]
+    pub folders: Vec<String>,
+    #[serde(default)]
+    pub files: Vec<ImportedFile>,
+}
+
+impl FolderImportState {
+    /// Target of the file or folder at `relative`, or `None` if it is left out
+    fn target(&self, relative: &Path) -> Option<String> {
+        let mapping = self
+            .mappings
+            .iter()
+            .filter(|mapping| relative.starts_with(&mapping.source))
+            .max_by_key(|mapping| mapping.source.components().count());
+        let (rest, base) = match mapping {
+            Some(mapping) => (
+                relative.strip_prefix(&mapping.source).ok()?,
+                mapping.target.as_deref()?.trim_matches('/'),
+            ),
+            None => (relative, ""),
+        };
+        let rest = rest.to_string_lossy().replace('\\', "/");
+        Some(match (base.is_empty(), rest.is_empty()) {
+            (true, _) => rest,
+            (false, true) => base.to_string(),
+            (false, false) => format!("{}/{}", base, rest),
+        })
+    }
+
+    fn total_bytes(&self) -> u64 {
+        self.files.iter().map(|file| file.size).sum()
+    }
+
+    fn count(&self, matches: impl Fn(&ImportOutcome) -> bool) -> usize {
+        self.files
+            .iter()
+            .filter(|file| file.outcome.as_ref().is_some_and(&matches))
+            .count()
+    }
+}
+
+/// Reports the progress of one upload as part of the whole import
+struct ImportProgress {
+    task_id: String,
+    progress_map: Arc<DashMap<String, TaskProgress>>,
+    /// Bytes of the files handled before this one
+    done: u64,
+    total: u64,
+}
+
+impl ProgressCallback for ImportProgress {
+    fn on_progress(&self, update: ProgressUpdate) {
+        if let Some(mut entry) = self.progress_map.get_mut(&self.task_id) {
+            let processed = self.done + update.uploaded;
+            let progress = if self.total > 0 {
+                (processed as f64 / self.total as f64).min(1.0)
+            } else {
+                1.0
+            };
+            entry.update_with_speed(
+                progress,
+                processed as i64,
+                self.total as i64,
+                update.speed_bytes_per_sec,
+                update.eta_seconds,
+            );
+        }
+    }
+}
+
+struct FolderImportHandler;
+
+impl FolderImportHandler {
+    async fn run(&self, ctx: TaskContext<'_>) -> Result<()> {
+        let mut state: FolderImportState = serde_json::from_value(
+            ctx.payload
+                .custom_state
+                .clone()
+                .context("Folder import task has no state")?,
+        )
+        .context("Failed to parse folder import state")?;
+
+        if !state.scanned {
+            state = tokio::task::spawn_blocking(move || scan_folder(state))
+                .await
+                .context("Folder scan panicked")??;
+            persist_state(&ctx, &state);
+        }
+        tracing::info!(target: "tasks::folder_import", task_id = %ctx.task_id, source = %state.source.display(), uri = %state.uri, provider = ?state.provider, files = state.files.len(), "Importing folder");
+
+        for folder in &state.folders {
+            ctx.cr_client
+                .create_file(&CreateFileService {
+                    uri: remote_uri(&state.uri, folder)?,
+                    file_type: "folder".to_string(),
+                    err_on_conflict: Some(false),
+                    metadata: None,
+                })
+                .await
+                .with_context(|| format!("Failed to create folder {}", folder))?;
+        }
+
+        let total = state.total_bytes();
+        let mut hashes: HashMap<String, PathBuf> = state
+            .files
+            .iter()
+            .filter(|file| matches!(file.outcome, Some(ImportOutcome::Uploaded)))
+            .filter_map(|file| Some((file.sha256.clone()?, file.path.clone())))
+            .collect();
+        let mut done: u64 = state
+            .files
+            .iter()
+            .filter(|file| file.outcome.is_some())
+            .map(|file| file.size)
+            .sum();
+
+        let pending: Vec<usize> = state
+            .files
+            .iter()
+            .enumerate()
+            .filter(|(_, file)| file.outcome.is_none())
+            .map(|(i, _)| i)
+            .collect();
+        let mut last_persist = Instant::now();
+        for i in pending {
+            let path = state.source.join(&state.files[i].path);
+
+            if state.skip_duplicates {
+                let hash = {
+                    let path = path.clone();
+                    tokio::task::spawn_blocking(move || hash_file(&path))
+                        .await
+                        .context("Hashing panicked")??
+                };
+                state.files[i].sha256 = Some(hash.clone());
+                if let Some(original) = hashes.get(&hash) {
+                    state.files[i].outcome = Some(ImportOutcome::Duplicate {
+                        of: original.clone(),
+                    });
+                }
+            }
+
+            if state.files[i].outcome.is_none() {
+                let file = &state.files[i];
+                let progress = ImportProgress {
+                    task_id: ctx.task_id.to_string(),
+                    progress_map: ctx.progress.clone(),
+                    done,
+                    total,
+                };
+                let outcome = match upload_file(&ctx, &path, &state.uri, file, progress).await {
+                    Ok(outcome) => outcome,
+                    Err(e) => {
+                        // Keep what is done, so a retry continues from here
+                        persist_state(&ctx, &state);
+                        return Err(e)
+                            .with_context(|| format!("Failed to import {}", path.display()));
+                    }
+                };
+                if let (ImportOutcome::Uploaded, Some(hash)) = (&outcome, &file.sha256) {
+                    hashes.insert(hash.clone(), file.path.clone());
+                }
+                state.files[i].outcome = Some(outcome);
+            }
+
+            done += state.files[i].size;
+            let progress = if total > 0 {
+                done as f64 / total as f64
+            } else {
+                1.0
+            };
+            ctx.report_progress(progress, Some(done as i64), Some(total as i64), None);
+            if last_persist.elapsed() >= PERSIST_INTERVAL {
+                persist_state(&ctx, &state);
+                last_persist = Instant::now();
+            }
+        }
+        persist_state(&ctx, &state);
+
+        let uploaded = state.count(|outcome| *outcome == ImportOutcome::Uploaded);
+        let skipped = state.files.len() - uploaded;
+        tracing::info!(target: "tasks::folder_import", drive = %ctx.drive_id, task_id = %ctx.task_id, source = %state.source.display(), uploaded, existing = state.count(|outcome| *outcome == ImportOutcome::Existing), duplicates = state.count(|outcome| matches!(outcome, ImportOutcome::Duplicate { .. })), "Folder import finished");
+
+        let mut message = plural("batchSucceeded", uploaded as u64);
+        if skipped > 0 {
+            message.push('\n');
+            message.push_str(&plural("folderImportSkipped", skipped as u64));
+        }
+        send_general_text_toast(&t!("folderImportFinished"), &message);
+
+        if state.setup_sync_root {
+            if let Err(e) = ctx.manager_command_tx.send(ManagerCommand::FolderImported {
+                drive_id: ctx.drive_id.to_string(),
+                source: state.source.clone(),
+                uri: state.uri.clone(),
+                ignore_patterns: state.provider.ignore_patterns(),
+            }) {
+                tracing::warn!(target: "tasks::folder_import", error = %e, "Failed to send FolderImported command");
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TaskHandler for FolderImportHandler {
+    fn kind(&self) -> &'static str {
+        FOLDER_IMPORT_KIND
+    }
+
+    fn execute<'a>(&'a self, ctx: TaskContext<'a>) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.run(ctx))
+    }
+}
+
+/// Register the folder import task type. Does nothing if it already is.
+pub(crate) fn register() -> Result<()> {
+    if task_handler(FOLDER_IMPORT_KIND).is_some() {
+        return Ok(());
+    }
+    register_task_handler(Arc::new(FolderImportHandler))
+}
+
+/// Record the folders and files to import in `state`
+fn scan_folder(mut state: FolderImportState) -> Result<FolderImportState> {
+    let matcher = IgnoreMatcher::new(&state.provider.ignore_patterns(), state.source.clone())?;
+    let mut folders = BTreeSet::new();
+    let mut pending = vec![state.source.clone()];
+    while let Some(folder) = pending.pop() {
+        let entries = std::fs::read_dir(&folder)
+            .with_context(|| format!("Failed to read folder {}", folder.display()))?;
+        for entry in entries {
+            let entry = entry?;
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+            if file_type.is_symlink() || matcher.is_match(&path) {
+                continue;
+            }
+            let relative = path.strip_prefix(&state.source)?.to_path_buf();
+            let Some(target) = state.target(&relative) else {
+                continue;
+            };
+            if file_type.is_dir() {
+                folders.insert(target);
+                pending.push(path);
+            } else {
+                state.files.push(ImportedFile {
+                    path: relative,
+                    target,
+                    size: entry.metadata()?.len(),
+                    sha256: None,
+                    outcome: None,
+                });
+            }
+        }
+    }
+    // Mappings can target folders that don't exist in the source
+    let targets: Vec<String> = state
+        .files
+        .iter()
+        .map(|file| file.target.clone())
+        .chain(folders.iter().cloned())
+        .collect();
+    for target in &targets {
+        let mut parent = target.as_str();
+        while let Some((ancestor, _)) = parent.rsplit_once('/') {
+            folders.insert(ancestor.to_string());
+            parent = ancestor;
+        }
+    }
+    folders.remove("");
+
+    // Sorted by path, parents come before their children
+    state.folders = folders.into_iter().collect();
+    state.files.sort_by(|a, b| a.path.cmp(&b.path));
+    state.scanned = true;
+    Ok(state)
+}
+
+/// Upload `file` unless a file is already at its target
+async fn upload_file(
+    ctx: &TaskContext<'_>,
+    path: &Path,
+    base: &str,
+    file: &ImportedFile,
+    progress: ImportProgress,
+) -> Result<ImportOutcome> {
+    let last_modified = std::fs::metadata(path)?
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|time| time.as_millis() as i64);
+    let params = UploadParams {
+        local_path: path.to_path_buf(),
+        remote_uri: remote_uri(base, &file.target)?,
+        file_size: file.size,
+        mime_type: None,
+        last_modified,
+        overwrite: false,
+        previous_version: String::new(),
+        task_id: ctx.task_id.to_string(),
+        drive_id: ctx.drive_id.to_string(),
+    };
+
+    let uploader = Uploader::new(
+        ctx.cr_client.clone(),
+        ctx.inventory.clone(),
+        UploaderConfig::default(),
+    );
+    match uploader.upload(params, progress).await {
+        Ok(()) => Ok(ImportOutcome::Uploaded),
+        Err(e) if is_object_existed(&e) => Ok(ImportOutcome::Existing),
+        Err(e) => Err(e),
+    }
+}
+
+fn is_object_existed(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<ApiError>(),
+            Some(ApiError::ApiError { code, .. }) if *code == ErrorCode::ObjectExisted as i32
+        )
+    })
+}
+
+/// URI of `target` under the remote folder `base`
+fn remote_uri(base: &str, target: &str) -> Result<String> {
+    let mut uri = CrUri::new(base)?;
+    if !target.is_empty() {
+        uri.join(&target.split('/').collect::<Vec<_>>());
+    }
+    Ok(uri.to_string())
+}
+
+/// Save the state, so a restarted task skips the files already handled
+fn persist_state(ctx: &TaskContext<'_>, state: &FolderImportState) {
+    let custom_state = match serde_json::to_value(state) {
+        Ok(value) => value,
+        Err(e) => {
+            tracing::warn!(target: "tasks::folder_import", task_id = %ctx.task_id, error = %e, "Failed to serialize folder import state");
+            return;
+        }
+    };
+    if let Err(e) = ctx.inventory.update_task(
+        ctx.task_id,
+        TaskUpdate {
+            custom_state: Some(Some(custom_state)),
+            ..Default::default()
+        },
+    ) {
+        tracing::warn!(target: "tasks::folder_import", task_id = %ctx.task_id, error = %e, "Failed to persist folder import progress");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mappings_move_and_leave_out_files() {
+        let mapping = |source: &str, target: Option<&str>| ImportMapping {
+            source: PathBuf::from(source),
+            target: target.map(str::to_string),
+        };
+        let state = FolderImportState {
+            source: PathBuf::from("C:\\Users\\me\\Dropbox"),
+            provider: ImportSource::Dropbox,
+            uri: "cloudreve://my/Imported".to_string(),
+            mappings: vec![
+                mapping("Camera Uploads", Some("Photos/Phone")),
+                mapping("Camera Uploads\\Private", None),
+                mapping("Inbox", Some("")),
+            ],
+            skip_duplicates: false,
+            setup_sync_root: false,
+            scanned: false,
+            folders: Vec::new(),
+            files: Vec::new(),
+        };
+
+        let target = |path: &str| state.target(Path::new(path));
+        assert_eq!(target("Docs\\a.txt").as_deref(), Some("Docs/a.txt"));
+        assert_eq!(
+            target("Camera Uploads\\b.jpg").as_deref(),
+            Some("Photos/Phone/b.jpg")
+        );
+        assert_eq!(target("Camera Uploads").as_deref(), Some("Photos/Phone"));
+        assert_eq!(target("Camera Uploads\\Private\\c.jpg"), None);
+        assert_eq!(target("Inbox\\d.pdf").as_deref(), Some("d.pdf"));
+        // Only whole folder names match
+        assert_eq!(
+            target("Camera Uploads Old\\e.jpg").as_deref(),
+            Some("Camera Uploads Old/e.jpg")
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//! Per-drive limits on concurrent transfers
+//!
+//! The limits can be changed while tasks are running: raising one lets queued
+//! tasks start right away, lowering one takes effect as running tasks finish.
+
+use crate::tasks::autoscale::AutoscaleSettings;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{AcquireError, OwnedSemaphorePermit, Semaphore};
+
+fn default_max_uploads() -> usize {
+    2
+}
+
+fn default_max_downloads() -> usize {
+    4
+}
+
+fn default_priority() -> u32 {
+    1
+}
+
+/// Per-drive transfer concurrency settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferLimits {
+    /// Upload (and custom) tasks running at the same time
+    #[serde(default = "default_max_uploads")]
+    pub max_uploads: usize,
+    /// Download tasks and placeholder hydrations running at the same time
+    #[serde(default = "default_max_downloads")]
+    pub max_downloads: usize,
+    /// Upper bound on chunks of one file uploaded in parallel. The storage
+    /// policy's own setting applies if unset or lower.
+    #[serde(default)]
+    pub max_chunk_concurrency: Option<usize>,
+    /// Adjust the upload and download limits to the system load instead
+    #[serde(default)]
+    pub autoscale: AutoscaleSettings,
+    /// Weight of the drive when busy drives share the transfer budget, see
+    /// [`crate::config::FairScheduling`]
+    #[serde(default = "default_priority")]
+    pub priority: u32,
+}
+
+impl Default for TransferLimits {
+    fn default() -> Self {
+        Self {
+            max_uploads: default_max_uploads(),
+            max_downloads: default_max_downloads(),
+            max_chunk_concurrency: None,
+            autoscale: AutoscaleSettings::default(),
+            priority: default_priority(),
+        }
+    }
+}
+
+impl TransferLimits {
+    /// Copy with every limit raised to at least 1
+    pub fn sanitized(&self) -> Self {
+        Self {
+            max_uploads: self.max_uploads.max(1),
+            max_downloads: self.max_downloads.max(1),
+            max_chunk_concurrency: self.max_chunk_concurrency.map(|limit| limit.max(1)),
+            autoscale: self.autoscale.clone(),
+            priority: self.priority.max(1),
+        }
+    }
+}
+
+/// Semaphore whose number of permits can be changed while permits are held
+pub struct AdjustableLimit {
+    semaphore: Arc<Semaphore>,
+    limit: Mutex<usize>,
+}
+
+impl AdjustableLimit {
+    pub fn new(limit: usize) -> Self {
+        let limit = limit.max(1);
+        Self {
+            semaphore: Arc::new(Semaphore::new(limit)),
+            limit: Mutex::new(limit),
+        }
+    }
+
+    /// Current number of permits
+    pub fn limit(&self) -> usize {
+        *self.limit.lock().unwrap()
+    }
+
+    /// Wait for a free slot, held until the permit is dropped
+    pub async fn acquire(&self) -> Result<OwnedSemaphorePermit, AcquireError> {
+        self.semaphore.clone().acquire_owned().await
+    }
+
+    /// Change the number of permits
+    pub fn set_limit(&self, limit: usize) {
+        let limit = limit.max(1);
+        let mut current = self.limit.lock().unwrap();
+        if limit > *current {
+            self.semaphore.add_permits(limit - *current);
+        } else if limit < *current {
+            // Retire permits as they are released by running tasks
+            let excess = (*current - limit) as u32;
+            let semaphore = self.semaphore.clone();
+            tokio::spawn(async move {
+                if let Ok(permits) = semaphore.acquire_many_owned(excess).await {
+                    permits.forget();
+                }
+            });
+        }
+        *current = limit;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn limit_can_shrink_and_grow_while_held() {
+        let limit = AdjustableLimit::new(2);
+        let first = limit.acquire().await.unwrap();
+        let second = limit.acquire().await.unwrap();
+
+        limit.set_limit(1);
+        drop(first);
+        drop(second);
+        tokio::task::yield_now().await;
+        assert_eq!(limit.semaphore.available_permits(), 1);
+
+        limit.set_limit(3);
+        assert_eq!(limit.semaphore.available_permits(), 3);
+        assert_eq!(limit.limit(), 3);
+    }
+
+    #[test]
+    fn sanitized_limits_allow_one_transfer() {
+        let limits = TransferLimits {
+            max_uploads: 0,
+            max_downloads: 0,
+            max_chunk_concurrency: Some(0),
+            priority: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            limits.sanitized(),
+            TransferLimits {
+                max_uploads: 1,
+                max_downloads: 1,
+                max_chunk_concurrency: Some(1),
+                ..Default::default()
+            }
+        );
+    }
+}
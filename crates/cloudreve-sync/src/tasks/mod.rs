@@ -1,7 +1,40 @@
+mod autoscale;
+pub(crate) mod bandwidth;
+pub(crate) mod batch;
+pub(crate) mod bulk_upload;
 mod download;
+pub(crate) mod fair_share;
+pub(crate) mod folder_export;
+pub(crate) mod folder_import;
+mod limits;
+mod naming;
+pub(crate) mod prefetch;
 mod queue;
+mod registry;
+pub(crate) mod remote_transfer;
+mod resumable;
+pub(crate) mod simulated_transfer;
+pub(crate) mod trash_restore;
 mod types;
 mod upload;
+pub(crate) mod url_pool;
 
+pub use autoscale::AutoscaleSettings;
+pub use batch::{BATCH_OPERATION_KIND, BatchOperation, BatchOperationState, ShareLink};
+pub use bulk_upload::{BULK_UPLOAD_KIND, BulkUploadState};
+pub use fair_share::FairShare;
+pub use folder_export::{EXPORT_MANIFEST, ExportedFile, FOLDER_EXPORT_KIND, FolderExportState};
+pub use folder_import::{
+    FOLDER_IMPORT_KIND, FolderImportState, ImportMapping, ImportOutcome, ImportSource, ImportedFile,
+};
+pub use limits::{AdjustableLimit, TransferLimits};
+pub use naming::{DEFAULT_MAX_NAME_LENGTH, NamePolicy, NameViolation};
+pub use prefetch::{DEFAULT_PREFETCH_MAX_FILE_SIZE, PREFETCH_KIND, PrefetchFile, PrefetchState};
 pub use queue::{TaskQueue, TaskQueueConfig};
+pub use registry::{TaskContext, TaskHandler, register_task_handler};
+pub use remote_transfer::{REMOTE_TRANSFER_KIND, RemoteTransferState};
+pub use resumable::ResumableDownload;
+pub use simulated_transfer::{SIMULATED_TRANSFER_KIND, SimulatedTransferState};
+pub use trash_restore::{TRASH_RESTORE_KIND, TrashRestoreState};
 pub use types::{TaskKind, TaskPayload, TaskProgress};
+pub use url_pool::UrlPool;
@@ -0,0 +1,146 @@
+//! Checks of new remote names against the server's naming policy.
+//!
+//! The server only validates a name when the upload is completed, so a name
+//! it rejects would fail after the whole content was transferred. Uploads of
+//! new files check their name first and fail right away instead.
+
+use cloudreve_api::models::site::SiteConfig;
+use thiserror::Error;
+
+/// Longest file name accepted by the server, in bytes, unless it says otherwise
+pub const DEFAULT_MAX_NAME_LENGTH: usize = 255;
+
+/// What the server accepts as a file name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamePolicy {
+    /// Longest name, in bytes
+    pub max_length: usize,
+    /// Characters not allowed anywhere in a name
+    pub forbidden_chars: Vec<char>,
+}
+
+impl Default for NamePolicy {
+    fn default() -> Self {
+        Self {
+            max_length: DEFAULT_MAX_NAME_LENGTH,
+            forbidden_chars: vec!['/', '\\'],
+        }
+    }
+}
+
+/// Why a name can't be uploaded
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum NameViolation {
+    #[error("Name {name:?} is reserved")]
+    Reserved { name: String },
+    #[error("Name {name:?} is {length} bytes long, the server accepts at most {max}")]
+    TooLong {
+        name: String,
+        length: usize,
+        max: usize,
+    },
+    #[error("Name {name:?} contains {forbidden:?}, which the server does not accept")]
+    ForbiddenChar { name: String, forbidden: char },
+    /// Another file in the folder has the same name in a different case,
+    /// which can't be synced back to a case-insensitive file system
+    #[error("Name {name:?} only differs in case from {existing:?} in the same folder")]
+    CaseCollision { name: String, existing: String },
+}
+
+impl NamePolicy {
+    /// Policy announced in the site config, with defaults for what is missing
+    pub fn from_site_config(config: &SiteConfig) -> Self {
+        let default = Self::default();
+        let mut forbidden_chars = default.forbidden_chars;
+        if let Some(chars) = &config.forbidden_name_chars {
+            for c in chars.chars() {
+                if !forbidden_chars.contains(&c) {
+                    forbidden_chars.push(c);
+                }
+            }
+        }
+        Self {
+            max_length: config
+                .max_name_length
+                .filter(|max| *max > 0)
+                .map(|max| max as usize)
+                .unwrap_or(default.max_length),
+            forbidden_chars,
+        }
+    }
+
+    /// Check a new `name` for a folder that already has `siblings`
+    pub fn check<'a>(
+        &self,
+        name: &str,
+        siblings: impl IntoIterator<Item = &'a str>,
+    ) -> Result<(), NameViolation> {
+        if name.is_empty() || name == "." || name == ".." {
+            return Err(NameViolation::Reserved {
+                name: name.to_string(),
+            });
+        }
+        if name.len() > self.max_length {
+            return Err(NameViolation::TooLong {
+                name: name.to_string(),
+                length: name.len(),
+                max: self.max_length,
+            });
+        }
+        if let Some(forbidden) = name.chars().find(|c| self.forbidden_chars.contains(c)) {
+            return Err(NameViolation::ForbiddenChar {
+                name: name.to_string(),
+                forbidden,
+            });
+        }
+
+        let lowercase = name.to_lowercase();
+        let existing = siblings
+            .into_iter()
+            .find(|sibling| *sibling != name && sibling.to_lowercase() == lowercase);
+        if let Some(existing) = existing {
+            return Err(NameViolation::CaseCollision {
+                name: name.to_string(),
+                existing: existing.to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_checked_against_policy() {
+        let policy = NamePolicy::from_site_config(&SiteConfig {
+            max_name_length: Some(10),
+            forbidden_name_chars: Some("#%".to_string()),
+            ..Default::default()
+        });
+
+        assert_eq!(policy.check("notes.txt", []), Ok(()));
+        assert!(matches!(
+            policy.check("..", []),
+            Err(NameViolation::Reserved { .. })
+        ));
+        assert!(matches!(
+            policy.check("meeting-notes.txt", []),
+            Err(NameViolation::TooLong { length: 17, .. })
+        ));
+        assert!(matches!(
+            policy.check("a#b.txt", []),
+            Err(NameViolation::ForbiddenChar { forbidden: '#', .. })
+        ));
+        assert_eq!(
+            policy.check("notes.txt", ["Notes.txt", "other.txt"]),
+            Err(NameViolation::CaseCollision {
+                name: "notes.txt".to_string(),
+                existing: "Notes.txt".to_string(),
+            })
+        );
+        // The file itself is not a collision
+        assert_eq!(policy.check("notes.txt", ["notes.txt"]), Ok(()));
+    }
+}
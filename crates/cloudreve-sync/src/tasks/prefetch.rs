@@ -0,0 +1,304 @@
+//! Warming up the local copy of a folder, e.g. before travelling.
+//!
+//! The folder is walked once, listing every placeholder folder that was
+//! never opened so the whole tree is browsable offline. Files up to a size
+//! limit then get their content downloaded one after the other; larger files
+//! stay placeholders. Unlike "Always keep on this device" nothing is pinned,
+//! so the content can be freed again like any other downloaded file.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use futures::future::BoxFuture;
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+
+use crate::inventory::TaskUpdate;
+use crate::tasks::registry::{TaskContext, TaskHandler, register_task_handler, task_handler};
+use crate::utils::format::plural;
+use crate::utils::toast::send_general_text_toast;
+use crate::vfs::VirtualFs;
+
+/// Task type of folder prefetches
+pub const PREFETCH_KIND: &str = "prefetch";
+
+/// Files up to this size are downloaded when prefetching from the context
+/// menu
+pub const DEFAULT_PREFETCH_MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// How often the state of a running prefetch is saved
+const PERSIST_INTERVAL: Duration = Duration::from_secs(5);
+
+/// File whose content is prefetched
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrefetchFile {
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+/// Persisted state of a prefetch task
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrefetchState {
+    /// Files up to this size get their content downloaded. `None` only lists
+    /// folders.
+    pub max_file_size: Option<u64>,
+    /// The folder tree was walked and `files` is complete
+    #[serde(default)]
+    pub listed: bool,
+    #[serde(default)]
+    pub folders: usize,
+    #[serde(default)]
+    pub files: Vec<PrefetchFile>,
+    /// Number of `files` already processed
+    #[serde(default)]
+    pub done: usize,
+    /// Files that failed to download
+    #[serde(default)]
+    pub failed: Vec<PathBuf>,
+}
+
+impl PrefetchState {
+    pub fn new(max_file_size: Option<u64>) -> Self {
+        Self {
+            max_file_size,
+            listed: false,
+            folders: 0,
+            files: Vec::new(),
+            done: 0,
+            failed: Vec::new(),
+        }
+    }
+}
+
+/// Progress shown while the task runs
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+enum PrefetchProgress {
+    Listing { folders: usize },
+    Downloading { files: usize, total: usize },
+}
+
+struct PrefetchHandler {
+    vfs: Arc<dyn VirtualFs>,
+}
+
+impl PrefetchHandler {
+    async fn run(&self, ctx: TaskContext<'_>) -> Result<()> {
+        let mut state: PrefetchState = serde_json::from_value(
+            ctx.payload
+                .custom_state
+                .clone()
+                .context("Prefetch task has no state")?,
+        )
+        .context("Failed to parse prefetch state")?;
+
+        if !state.listed {
+            self.list(&ctx, &mut state).await?;
+            state.listed = true;
+            persist_state(&ctx, &state);
+        }
+
+        let total = state.files.len();
+        let total_bytes: u64 = state.files.iter().map(|file| file.size).sum();
+        let mut done_bytes: u64 = state.files[..state.done].iter().map(|file| file.size).sum();
+        let mut last_persist = Instant::now();
+        while state.done < total {
+            let file = state.files[state.done].clone();
+            if let Err(e) = self.hydrate(file.path.clone()).await {
+                tracing::warn!(target: "tasks::prefetch", task_id = %ctx.task_id, path = %file.path.display(), error = ?e, "Failed to prefetch file");
+                state.failed.push(file.path);
+            }
+            state.done += 1;
+            done_bytes += file.size;
+
+            let progress = if total_bytes > 0 {
+                done_bytes as f64 / total_bytes as f64
+            } else {
+                state.done as f64 / total as f64
+            };
+            ctx.report_progress(
+                progress,
+                Some(done_bytes as i64),
+                Some(total_bytes as i64),
+                serde_json::to_value(PrefetchProgress::Downloading {
+                    files: state.done,
+                    total,
+                })
+                .ok(),
+            );
+            if last_persist.elapsed() >= PERSIST_INTERVAL {
+                persist_state(&ctx, &state);
+                last_persist = Instant::now();
+            }
+        }
+        persist_state(&ctx, &state);
+
+        let failed = state.failed.len();
+        tracing::info!(target: "tasks::prefetch", drive = %ctx.drive_id, task_id = %ctx.task_id, path = %ctx.payload.local_path_display(), folders = state.folders, files = total, failed, "Prefetch finished");
+
+        let name = ctx
+            .payload
+            .local_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| ctx.payload.local_path_display());
+        let mut message = plural("batchSucceeded", (total - failed) as u64);
+        if failed > 0 {
+            message.push('\n');
+            message.push_str(&plural("batchFailed", failed as u64));
+        }
+        send_general_text_toast(&t!("prefetchFinished", "name" => name), &message);
+        Ok(())
+    }
+
+    /// Walk the folder tree, listing folders never opened and collecting the
+    /// files to download
+    async fn list(&self, ctx: &TaskContext<'_>, state: &mut PrefetchState) -> Result<()> {
+        state.folders = 0;
+        state.files.clear();
+        let mut pending = vec![ctx.payload.local_path.clone()];
+        while let Some(folder) = pending.pop() {
+            let vfs = self.vfs.clone();
+            let max_file_size = state.max_file_size;
+            let listing = tokio::task::spawn_blocking(move || {
+                list_folder(vfs.as_ref(), &folder, max_file_size)
+            })
+            .await
+            .context("Folder listing panicked")?;
+            match listing {
+                Ok((folders, files)) => {
+                    pending.extend(folders);
+                    state.files.extend(files);
+                }
+                Err(e) => {
+                    tracing::warn!(target: "tasks::prefetch", task_id = %ctx.task_id, error = ?e, "Failed to list folder");
+                }
+            }
+            state.folders += 1;
+            ctx.report_progress(
+                0.0,
+                None,
+                None,
+                serde_json::to_value(PrefetchProgress::Listing {
+                    folders: state.folders,
+                })
+                .ok(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Download the content of a file, unless it is on disk already
+    async fn hydrate(&self, path: PathBuf) -> Result<()> {
+        let vfs = self.vfs.clone();
+        tokio::task::spawn_blocking(move || {
+            let status = vfs.status(&path)?;
+            if !status.exists || !status.is_placeholder || !status.partial_on_disk {
+                return Ok(());
+            }
+            vfs.hydrate(&path)
+        })
+        .await
+        .context("Hydration panicked")?
+    }
+}
+
+/// List the children of `folder`, populating it first if it was never
+/// opened. Returns its subfolders and the files to download.
+fn list_folder(
+    vfs: &dyn VirtualFs,
+    folder: &Path,
+    max_file_size: Option<u64>,
+) -> Result<(Vec<PathBuf>, Vec<PrefetchFile>)> {
+    let status = vfs.status(folder)?;
+    if status.is_placeholder && status.partial_on_disk {
+        vfs.populate(folder)
+            .with_context(|| format!("Failed to populate {}", folder.display()))?;
+    }
+
+    let mut folders = Vec::new();
+    let mut files = Vec::new();
+    for entry in
+        std::fs::read_dir(folder).with_context(|| format!("Failed to read {}", folder.display()))?
+    {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            folders.push(entry.path());
+        } else if max_file_size.is_some_and(|max| metadata.len() <= max) {
+            files.push(PrefetchFile {
+                path: entry.path(),
+                size: metadata.len(),
+            });
+        }
+    }
+    Ok((folders, files))
+}
+
+impl TaskHandler for PrefetchHandler {
+    fn kind(&self) -> &'static str {
+        PREFETCH_KIND
+    }
+
+    fn execute<'a>(&'a self, ctx: TaskContext<'a>) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.run(ctx))
+    }
+}
+
+/// Save the state, so a restarted task skips the files already downloaded
+fn persist_state(ctx: &TaskContext<'_>, state: &PrefetchState) {
+    let custom_state = match serde_json::to_value(state) {
+        Ok(value) => value,
+        Err(e) => {
+            tracing::warn!(target: "tasks::prefetch", task_id = %ctx.task_id, error = %e, "Failed to serialize prefetch state");
+            return;
+        }
+    };
+    if let Err(e) = ctx.inventory.update_task(
+        ctx.task_id,
+        TaskUpdate {
+            custom_state: Some(Some(custom_state)),
+            ..Default::default()
+        },
+    ) {
+        tracing::warn!(target: "tasks::prefetch", task_id = %ctx.task_id, error = %e, "Failed to persist prefetch progress");
+    }
+}
+
+/// Register the prefetch task type. Does nothing if it already is.
+pub(crate) fn register(vfs: Arc<dyn VirtualFs>) -> Result<()> {
+    if task_handler(PREFETCH_KIND).is_some() {
+        return Ok(());
+    }
+    register_task_handler(Arc::new(PrefetchHandler { vfs }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::FakeVirtualFs;
+
+    #[test]
+    fn listing_leaves_out_large_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("small.txt"), b"hello").unwrap();
+        std::fs::write(dir.path().join("large.bin"), vec![0u8; 64]).unwrap();
+
+        let vfs = FakeVirtualFs::new();
+        let (folders, files) = list_folder(&vfs, dir.path(), Some(16)).unwrap();
+        assert_eq!(folders, vec![dir.path().join("sub")]);
+        assert_eq!(
+            files,
+            vec![PrefetchFile {
+                path: dir.path().join("small.txt"),
+                size: 5,
+            }]
+        );
+
+        let (_, files) = list_folder(&vfs, dir.path(), None).unwrap();
+        assert!(files.is_empty());
+    }
+}
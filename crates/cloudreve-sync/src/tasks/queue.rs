@@ -1,17 +1,34 @@
+use crate::drive::command_queue::CommandSender;
+use crate::drive::commands::ManagerCommand;
+use crate::drive::origin::ChangeOrigins;
+use crate::drive::failures;
 use crate::inventory::{InventoryDb, NewTaskRecord, TaskRecord, TaskStatus, TaskUpdate};
+use crate::policy::Policy;
+use crate::power;
+use crate::presentation;
+use crate::tasks::autoscale::{
+    AUTOSCALE_INTERVAL, CpuMeter, ErrorRate, LoadSample, QueueStats, next_level,
+};
 use crate::tasks::download::DownloadTask;
+use crate::tasks::fair_share::FairShare;
+use crate::tasks::limits::{AdjustableLimit, TransferLimits};
+use crate::tasks::naming::NamePolicy;
+use crate::tasks::registry::{TaskContext, task_handler};
 use crate::tasks::types::{TaskKind, TaskPayload, TaskProgress};
 use crate::tasks::upload::UploadTask;
+use crate::uploader::CompressionSettings;
+use crate::utils::correlation;
 use anyhow::{Context, Result, anyhow};
 use cloudreve_api::Client;
 use dashmap::DashMap;
 use serde_json::Value;
 use std::path::PathBuf;
-use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
 use tokio::sync::{
-    Mutex, Notify, Semaphore,
+    Mutex, Notify, OwnedSemaphorePermit,
     mpsc::{self, UnboundedReceiver, UnboundedSender},
+    watch,
 };
 use tokio::task::JoinHandle;
 use tracing::{debug, error, info, warn};
@@ -19,12 +36,19 @@ use uuid::Uuid;
 
 #[derive(Debug, Clone)]
 pub struct TaskQueueConfig {
-    pub max_concurrent: usize,
+    pub limits: TransferLimits,
+    pub compression: CompressionSettings,
+    /// Start with uploads held, see [`TaskQueue::hold_uploads`]
+    pub hold_uploads: bool,
 }
 
 impl Default for TaskQueueConfig {
     fn default() -> Self {
-        Self { max_concurrent: 2 }
+        Self {
+            limits: TransferLimits::default(),
+            compression: CompressionSettings::default(),
+            hold_uploads: false,
+        }
     }
 }
 
@@ -35,7 +59,25 @@ pub struct TaskQueue {
     pub sync_path: PathBuf,
     pub remote_base: String,
     config: TaskQueueConfig,
-    semaphore: Arc<Semaphore>,
+    /// Current transfer limits, changeable at runtime
+    limits: std::sync::RwLock<TransferLimits>,
+    upload_limit: AdjustableLimit,
+    /// Shared by download tasks and placeholder hydrations
+    download_limit: AdjustableLimit,
+    /// Level picked by autoscaling for both limits, 0 while it is disabled
+    worker_level: AtomicUsize,
+    /// Cap on the limits from sharing transfers with other drives
+    fair_share: std::sync::RwLock<Option<FairShare>>,
+    /// Whether uploads wait before taking a slot
+    uploads_held: watch::Sender<bool>,
+    /// Names the server accepts, checked before uploading new files
+    name_policy: std::sync::RwLock<NamePolicy>,
+    stats: QueueStats,
+    autoscale_handle: Mutex<Option<JoinHandle<()>>>,
+    /// Local writes made by downloads, so their events are not uploaded
+    change_origins: ChangeOrigins,
+    /// Used to report threats found in downloaded files
+    manager_command_tx: CommandSender<ManagerCommand>,
     command_tx: UnboundedSender<QueueCommand>,
     dispatcher_handle: Mutex<Option<JoinHandle<()>>>,
     inflight: AtomicUsize,
@@ -56,10 +98,15 @@ impl TaskQueue {
         config: TaskQueueConfig,
         sync_path: PathBuf,
         remote_base: String,
+        manager_command_tx: CommandSender<ManagerCommand>,
     ) -> Arc<Self> {
         let drive_id = drive_id.into();
-        let max_concurrent = config.max_concurrent.max(1);
-        let sanitized_config = TaskQueueConfig { max_concurrent };
+        let limits = config.limits.sanitized();
+        let sanitized_config = TaskQueueConfig {
+            limits: limits.clone(),
+            compression: config.compression,
+            hold_uploads: config.hold_uploads,
+        };
 
         let (command_tx, command_rx) = mpsc::unbounded_channel();
         let queue = Arc::new(Self {
@@ -69,7 +116,17 @@ impl TaskQueue {
             sync_path,
             remote_base,
             config: sanitized_config,
-            semaphore: Arc::new(Semaphore::new(max_concurrent)),
+            upload_limit: AdjustableLimit::new(limits.max_uploads),
+            download_limit: AdjustableLimit::new(limits.max_downloads),
+            limits: std::sync::RwLock::new(limits.clone()),
+            worker_level: AtomicUsize::new(0),
+            fair_share: std::sync::RwLock::new(None),
+            uploads_held: watch::Sender::new(config.hold_uploads),
+            name_policy: std::sync::RwLock::new(NamePolicy::default()),
+            stats: QueueStats::default(),
+            autoscale_handle: Mutex::new(None),
+            change_origins: ChangeOrigins::new(),
+            manager_command_tx,
             command_tx,
             dispatcher_handle: Mutex::new(None),
             inflight: AtomicUsize::new(0),
@@ -81,7 +138,9 @@ impl TaskQueue {
             task_paths: DashMap::new(),
         });
 
+        queue.apply_limits(&limits);
         queue.spawn_dispatcher(command_rx).await;
+        queue.spawn_autoscaler().await;
         if let Err(err) = queue.resume_incomplete_tasks().await {
             warn!(
                 target: "tasks::queue",
@@ -93,8 +152,150 @@ impl TaskQueue {
         queue
     }
 
-    pub fn max_concurrent(&self) -> usize {
-        self.config.max_concurrent
+    /// Transfer limits currently in effect
+    pub fn limits(&self) -> TransferLimits {
+        self.limits.read().unwrap().clone()
+    }
+
+    /// Apply new transfer limits to queued and future transfers
+    pub fn set_limits(&self, limits: TransferLimits) {
+        let limits = limits.sanitized();
+        self.apply_limits(&limits);
+        info!(
+            target: "tasks::queue",
+            drive = %self.drive_id,
+            max_uploads = limits.max_uploads,
+            max_downloads = limits.max_downloads,
+            max_chunk_concurrency = ?limits.max_chunk_concurrency,
+            autoscale = limits.autoscale.enabled,
+            "Transfer limits updated"
+        );
+        *self.limits.write().unwrap() = limits;
+    }
+
+    /// Concurrency level picked by autoscaling, if enabled
+    pub fn worker_level(&self) -> Option<usize> {
+        match self.worker_level.load(Ordering::SeqCst) {
+            0 => None,
+            level => Some(level),
+        }
+    }
+
+    /// Share of the transfer budget given to this drive, `None` if it is not
+    /// shared with other drives
+    pub fn fair_share(&self) -> Option<FairShare> {
+        *self.fair_share.read().unwrap()
+    }
+
+    /// Cap the limits at `share`, or lift the cap
+    pub fn set_fair_share(&self, share: Option<FairShare>) {
+        let changed = {
+            let mut current = self.fair_share.write().unwrap();
+            let changed = *current != share;
+            *current = share;
+            changed
+        };
+        if changed {
+            debug!(
+                target: "tasks::queue",
+                drive = %self.drive_id,
+                share = ?share,
+                "Fair share updated"
+            );
+            self.refresh_limits();
+        }
+    }
+
+    /// Upload and download tasks waiting for or holding a slot
+    pub fn demand(&self) -> (usize, usize) {
+        (
+            self.stats.uploads.load(Ordering::SeqCst),
+            self.stats.downloads.load(Ordering::SeqCst),
+        )
+    }
+
+    /// Hold uploads that have not started yet, or let them start. Downloads
+    /// are not affected.
+    pub fn hold_uploads(&self, held: bool) {
+        self.uploads_held.send_if_modified(|current| {
+            let changed = *current != held;
+            *current = held;
+            changed
+        });
+    }
+
+    /// Check names of new files against `policy` before uploading them
+    pub fn set_name_policy(&self, policy: NamePolicy) {
+        let mut current = self.name_policy.write().unwrap();
+        if *current != policy {
+            debug!(
+                target: "tasks::queue",
+                drive = %self.drive_id,
+                policy = ?policy,
+                "Name policy updated"
+            );
+            *current = policy;
+        }
+    }
+
+    /// Re-apply the current limits, e.g. after power saving or a presentation
+    /// started or ended
+    pub fn refresh_limits(&self) {
+        self.apply_limits(&self.limits());
+    }
+
+    fn apply_limits(&self, limits: &TransferLimits) {
+        if power::is_power_saving() || presentation::pauses_transfers() {
+            // One transfer of each kind at a time, the configured limits and
+            // autoscaling resume once power saving or the presentation ends
+            self.upload_limit.set_limit(1);
+            self.download_limit.set_limit(1);
+        } else if limits.autoscale.enabled {
+            // Keep the current level within the new bounds, or start from
+            // the upload limit
+            let (floor, ceiling) = limits.autoscale.bounds();
+            let level = self.worker_level().unwrap_or(limits.max_uploads);
+            self.apply_worker_level(level.clamp(floor, ceiling));
+        } else {
+            self.worker_level.store(0, Ordering::SeqCst);
+            self.set_shared_limits(limits.max_uploads, limits.max_downloads);
+            self.report_worker_level(None);
+        }
+    }
+
+    fn apply_worker_level(&self, level: usize) {
+        self.worker_level.store(level, Ordering::SeqCst);
+        self.set_shared_limits(level, level);
+        self.report_worker_level(Some(level));
+    }
+
+    /// Set the limits, capped at the fair share if there is one
+    fn set_shared_limits(&self, uploads: usize, downloads: usize) {
+        let (uploads, downloads) = match self.fair_share() {
+            Some(share) => (uploads.min(share.uploads), downloads.min(share.downloads)),
+            None => (uploads, downloads),
+        };
+        self.upload_limit.set_limit(uploads);
+        self.download_limit.set_limit(downloads);
+    }
+
+    fn report_worker_level(&self, level: Option<usize>) {
+        for mut entry in self.progress.iter_mut() {
+            entry.worker_level = level;
+        }
+    }
+
+    /// Wait for a download slot, e.g. before hydrating a placeholder
+    pub async fn acquire_download_slot(&self) -> Result<OwnedSemaphorePermit> {
+        self.download_limit
+            .acquire()
+            .await
+            .context("download limit closed")
+    }
+
+    /// Ledger of local writes made by the drive's engine
+    pub fn change_origins(&self) -> &ChangeOrigins {
+        &self.change_origins
     }
 
     pub fn drive_id(&self) -> &str {
@@ -105,6 +306,27 @@ impl TaskQueue {
         if self.shutting_down.load(Ordering::SeqCst) {
             return Err(anyhow!("task queue is shutting down"));
         }
+        if payload.kind == TaskKind::Upload && Policy::get().force_read_only {
+            return Err(anyhow!("uploads are disabled by administrator policy"));
+        }
+        if payload.kind == TaskKind::Upload && self.cr_client.share_session().is_some() {
+            return Err(anyhow!("uploads are disabled for guest shares"));
+        }
+        if matches!(payload.kind, TaskKind::Custom(kind) if task_handler(kind).is_none()) {
+            return Err(anyhow!(
+                "no handler registered for task type {}",
+                payload.kind.as_str()
+            ));
+        }
+        if let Some(operation) = failures::task_operation(&payload.kind) {
+            if failures::is_blocked(&self.inventory, &payload.local_path, operation) {
+                return Err(anyhow!(
+                    "{} is quarantined after repeated {} failures",
+                    payload.local_path_display(),
+                    operation.as_str()
+                ));
+            }
+        }
 
         let task_id = payload
             .task_id
@@ -141,6 +363,16 @@ impl TaskQueue {
             .insert_task_if_not_exist(&record)
             .with_context(|| format!("Failed to persist task {}", task_id))?;
 
+        // Either way the upload is queued now, see `drive::intake`
+        if payload.kind == TaskKind::Upload {
+            if let Err(e) = self
+                .inventory
+                .remove_pending_intake(&self.drive_id, &[&payload.local_path_display()])
+            {
+                warn!(target: "tasks::queue", drive = %self.drive_id, error = ?e, "Failed to remove pending intake");
+            }
+        }
+
         if !inserted {
             return Err(anyhow!(
                 "Task already exists for {} with type {}",
@@ -169,7 +401,8 @@ impl TaskQueue {
     }
 
     fn dispatch_task(&self, task_id: String, payload: TaskPayload) -> Result<()> {
-        let command = QueueCommand::Enqueue(QueuedTask { task_id, payload });
+        let command =
+            QueueCommand::Enqueue(QueuedTask { task_id, payload }, correlation::current());
         self.command_tx
             .send(command)
             .context("Task dispatcher closed")?;
@@ -207,8 +440,15 @@ impl TaskQueue {
         if let Some(handle) = self.dispatcher_handle.lock().await.take() {
             handle.abort();
         }
+        if let Some(handle) = self.autoscale_handle.lock().await.take() {
+            handle.abort();
+        }
 
         self.cancel_running_tasks().await;
+        // Tasks still waiting for a slot
+        for handle in self.task_handles.iter() {
+            handle.abort();
+        }
         self.task_handles.clear();
         self.task_paths.clear();
         self.progress.clear();
@@ -278,6 +518,39 @@ impl TaskQueue {
         Ok(cancelled_count)
     }
 
+    /// Cancel one pending or running task. Returns false if it is not
+    /// active on this queue.
+    pub async fn cancel(&self, task_id: &str) -> Result<bool> {
+        let active = self
+            .inventory
+            .list_tasks(
+                Some(&self.drive_id),
+                Some(&[TaskStatus::Pending, TaskStatus::Running]),
+            )
+            .context("Failed to list active tasks")?;
+        if !active.iter().any(|task| task.id == task_id) {
+            return Ok(false);
+        }
+
+        self.inventory
+            .update_task(
+                task_id,
+                TaskUpdate {
+                    status: Some(TaskStatus::Cancelled),
+                    ..Default::default()
+                },
+            )
+            .context("Failed to cancel task in inventory")?;
+        if let Some((_, handle)) = self.task_handles.remove(task_id) {
+            handle.abort();
+        }
+        self.task_paths.remove(task_id);
+        self.progress.remove(task_id);
+
+        info!(target: "tasks::queue", drive = %self.drive_id, task_id = %task_id, "Cancelled task");
+        Ok(true)
+    }
+
     async fn spawn_dispatcher(self: &Arc<Self>, command_rx: UnboundedReceiver<QueueCommand>) {
         let queue = Arc::clone(self);
         let handle = tokio::spawn(async move {
@@ -286,18 +559,71 @@ impl TaskQueue {
         *self.dispatcher_handle.lock().await = Some(handle);
     }
 
+    async fn spawn_autoscaler(self: &Arc<Self>) {
+        let queue = Arc::downgrade(self);
+        let handle = tokio::spawn(Self::run_autoscale_loop(queue));
+        *self.autoscale_handle.lock().await = Some(handle);
+    }
+
+    /// Periodically re-evaluate the worker level while autoscaling is enabled.
+    /// Holds the queue weakly so it does not outlive its drive.
+    async fn run_autoscale_loop(queue: Weak<Self>) {
+        let mut cpu = CpuMeter::default();
+        let mut errors = ErrorRate::default();
+        loop {
+            tokio::time::sleep(AUTOSCALE_INTERVAL).await;
+            let Some(queue) = queue.upgrade() else {
+                break;
+            };
+
+            // Sample every interval, so the first evaluation after enabling
+            // only covers recent load
+            let waiting = queue.stats.waiting.load(Ordering::SeqCst);
+            let sample = LoadSample {
+                waiting,
+                running: queue
+                    .inflight
+                    .load(Ordering::SeqCst)
+                    .saturating_sub(waiting),
+                cpu: cpu.sample(),
+                error_rate: errors.sample(&queue.stats),
+            };
+
+            if power::is_power_saving() || presentation::pauses_transfers() {
+                continue;
+            }
+            let settings = queue.limits().autoscale;
+            let Some(current) = queue.worker_level().filter(|_| settings.enabled) else {
+                continue;
+            };
+            let level = next_level(current, &sample, &settings);
+            if level != current {
+                debug!(
+                    target: "tasks::queue",
+                    drive = %queue.drive_id,
+                    from = current,
+                    to = level,
+                    sample = ?sample,
+                    "Autoscaled worker level"
+                );
+                queue.apply_worker_level(level);
+            }
+        }
+    }
+
     async fn run_dispatch_loop(self: Arc<Self>, mut command_rx: UnboundedReceiver<QueueCommand>) {
         info!(
             target: "tasks::queue",
             drive = %self.drive_id,
-            concurrency = self.config.max_concurrent,
+            max_uploads = self.config.limits.max_uploads,
+            max_downloads = self.config.limits.max_downloads,
             "Task queue dispatcher started"
         );
 
         while let Some(command) = command_rx.recv().await {
             match command {
-                QueueCommand::Enqueue(task) => {
-                    self.launch_task(task).await;
+                QueueCommand::Enqueue(task, correlation_id) => {
+                    self.launch_task(task, correlation_id).await;
                 }
                 QueueCommand::Shutdown => {
                     debug!(
@@ -317,9 +643,57 @@ impl TaskQueue {
         );
     }
 
-    async fn launch_task(self: &Arc<Self>, task: QueuedTask) {
-        let permit = match self.semaphore.clone().acquire_owned().await {
-            Ok(permit) => permit,
+    /// Run a task with the correlation id of the action that dispatched it
+    async fn launch_task(self: &Arc<Self>, task: QueuedTask, correlation_id: Option<String>) {
+        self.inflight.fetch_add(1, Ordering::SeqCst);
+        let queue_for_execute = Arc::clone(self);
+        let queue_for_notify = Arc::clone(self);
+        let task_id = task.task_id.clone();
+        let handle_task_id = task_id.clone();
+        let kind = task.payload.kind;
+        self.demand_counter(kind).fetch_add(1, Ordering::SeqCst);
+
+        // Wait for a slot inside the task, so that e.g. a full upload queue
+        // does not hold back downloads
+        let handle = tokio::spawn(correlation::scope(correlation_id, async move {
+            if let Some(permit) = queue_for_execute.acquire_slot(&task).await {
+                queue_for_execute.execute_task(task).await;
+                drop(permit);
+            }
+            queue_for_notify
+                .demand_counter(kind)
+                .fetch_sub(1, Ordering::SeqCst);
+            queue_for_notify.inflight.fetch_sub(1, Ordering::SeqCst);
+            queue_for_notify.idle_notify.notify_waiters();
+            queue_for_notify.task_handles.remove(&handle_task_id);
+        }));
+
+        self.task_handles.insert(task_id, handle);
+    }
+
+    /// Counter of queued tasks of the limit `kind` counts against
+    fn demand_counter(&self, kind: TaskKind) -> &AtomicUsize {
+        match kind {
+            TaskKind::Download => &self.stats.downloads,
+            TaskKind::Upload | TaskKind::Custom(_) => &self.stats.uploads,
+        }
+    }
+
+    /// Wait for a free slot of the limit the task counts against
+    async fn acquire_slot(&self, task: &QueuedTask) -> Option<OwnedSemaphorePermit> {
+        let limit = match task.payload.kind {
+            TaskKind::Download => &self.download_limit,
+            TaskKind::Upload | TaskKind::Custom(_) => &self.upload_limit,
+        };
+        self.stats.waiting.fetch_add(1, Ordering::SeqCst);
+        if task.payload.kind == TaskKind::Upload {
+            // The sender lives as long as the queue, so this only fails on shutdown
+            let _ = self.uploads_held.subscribe().wait_for(|held| !held).await;
+        }
+        let permit = limit.acquire().await;
+        self.stats.waiting.fetch_sub(1, Ordering::SeqCst);
+        match permit {
+            Ok(permit) => Some(permit),
             Err(err) => {
                 error!(
                     target: "tasks::queue",
@@ -342,25 +716,9 @@ impl TaskQueue {
                         "Failed to persist scheduling failure"
                     );
                 }
-                return;
+                None
             }
-        };
-
-        self.inflight.fetch_add(1, Ordering::SeqCst);
-        let queue_for_execute = Arc::clone(self);
-        let queue_for_notify = Arc::clone(self);
-        let task_id = task.task_id.clone();
-        let handle_task_id = task_id.clone();
-
-        let handle = tokio::spawn(async move {
-            queue_for_execute.execute_task(task).await;
-            drop(permit);
-            queue_for_notify.inflight.fetch_sub(1, Ordering::SeqCst);
-            queue_for_notify.idle_notify.notify_waiters();
-            queue_for_notify.task_handles.remove(&handle_task_id);
-        });
-
-        self.task_handles.insert(task_id, handle);
+        }
     }
 
     async fn execute_task(self: Arc<Self>, task: QueuedTask) {
@@ -419,9 +777,17 @@ impl TaskQueue {
             .insert(task.task_id.clone(), task.payload.local_path_display());
 
         self.register_progress_entry(&task).await;
+        debug!(
+            target: "tasks::queue",
+            drive = %self.drive_id,
+            task_id = %task.task_id,
+            correlation_id = %correlation::current().unwrap_or_default(),
+            "Task started"
+        );
 
         match self.run_placeholder_task(&task).await {
             Ok(TaskRunState::Completed) => {
+                self.stats.completed.fetch_add(1, Ordering::Relaxed);
                 if let Err(err) = self.inventory.update_task(
                     &task.task_id,
                     TaskUpdate {
@@ -437,6 +803,9 @@ impl TaskQueue {
                         "Failed to mark task as completed"
                     );
                 }
+                if let Some(operation) = failures::task_operation(&task.payload.kind) {
+                    failures::clear_failure(&self.inventory, &task.payload.local_path, operation);
+                }
             }
             Ok(TaskRunState::Cancelled) => {
                 if let Err(err) = self.inventory.update_task(
@@ -458,6 +827,7 @@ impl TaskQueue {
                 return;
             }
             Err(err) => {
+                self.stats.failed.fetch_add(1, Ordering::Relaxed);
                 error!(
                     target: "tasks::queue",
                     drive = %self.drive_id,
@@ -481,6 +851,16 @@ impl TaskQueue {
                         "Failed to persist task failure state"
                     );
                 }
+                if let Some(operation) = failures::task_operation(&task.payload.kind) {
+                    failures::record_failure(
+                        &self.inventory,
+                        &self.manager_command_tx,
+                        &self.drive_id,
+                        &task.payload.local_path,
+                        operation,
+                        &err,
+                    );
+                }
                 self.cleanup_task_entry(&task.task_id).await;
                 return;
             }
@@ -509,7 +889,11 @@ impl TaskQueue {
                     self.sync_path.clone(),
                     self.remote_base.clone(),
                     Arc::clone(&self.progress),
-                );
+                )
+                .with_compression(self.config.compression.clone())
+                .with_chunk_concurrency(self.limits().max_chunk_concurrency)
+                .with_name_policy(self.name_policy.read().unwrap().clone())
+                .with_manager_command_tx(self.manager_command_tx.clone());
 
                 task_executor.execute().await?;
             }
@@ -522,10 +906,29 @@ impl TaskQueue {
                     self.sync_path.clone(),
                     self.remote_base.clone(),
                     Arc::clone(&self.progress),
-                );
+                )
+                .with_change_origins(self.change_origins.clone())
+                .with_manager_command_tx(self.manager_command_tx.clone());
 
                 task_executor.execute().await?;
             }
+            TaskKind::Custom(kind) => {
+                let handler = task_handler(kind)
+                    .ok_or_else(|| anyhow!("no handler registered for task type {}", kind))?;
+                handler
+                    .execute(TaskContext {
+                        drive_id: self.drive_id.as_str(),
+                        task_id: &task.task_id,
+                        payload: &task.payload,
+                        cr_client: self.cr_client.clone(),
+                        inventory: self.inventory.clone(),
+                        sync_path: &self.sync_path,
+                        remote_base: &self.remote_base,
+                        progress: Arc::clone(&self.progress),
+                        manager_command_tx: self.manager_command_tx.clone(),
+                    })
+                    .await?;
+            }
         }
 
         // for step in 0..PLACEHOLDER_STEPS {
@@ -575,10 +978,9 @@ impl TaskQueue {
     }
 
     async fn register_progress_entry(&self, task: &QueuedTask) {
-        self.progress.insert(
-            task.task_id.clone(),
-            TaskProgress::from_payload(&task.task_id, &task.payload),
-        );
+        let mut progress = TaskProgress::from_payload(&task.task_id, &task.payload);
+        progress.worker_level = self.worker_level();
+        self.progress.insert(task.task_id.clone(), progress);
     }
 
     #[allow(dead_code)]
@@ -708,7 +1110,8 @@ pub enum TaskRunState {
 }
 
 enum QueueCommand {
-    Enqueue(QueuedTask),
+    /// A task and the correlation id of the action that dispatched it
+    Enqueue(QueuedTask, Option<String>),
     Shutdown,
 }
 
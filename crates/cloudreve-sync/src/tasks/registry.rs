@@ -0,0 +1,124 @@
+//! Task types provided outside the built-in upload and download tasks.
+//!
+//! A [`TaskHandler`] registered at startup (before drives are loaded, so that
+//! persisted tasks of its type can be restored) runs in the same per-drive
+//! queue as transfers: it shares the upload concurrency limit, is persisted
+//! and cancellable, and its progress shows up next to the other tasks.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use anyhow::{Result, anyhow};
+use cloudreve_api::Client;
+use dashmap::DashMap;
+use futures::future::BoxFuture;
+use serde_json::Value;
+
+use crate::drive::command_queue::CommandSender;
+use crate::drive::commands::ManagerCommand;
+use crate::inventory::InventoryDb;
+use crate::tasks::types::{TaskKind, TaskPayload, TaskProgress};
+
+type HandlerMap = HashMap<&'static str, Arc<dyn TaskHandler>>;
+
+static HANDLERS: OnceLock<RwLock<HandlerMap>> = OnceLock::new();
+
+fn handlers() -> &'static RwLock<HandlerMap> {
+    HANDLERS.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Executes tasks of one custom type
+pub trait TaskHandler: Send + Sync {
+    /// Unique name of the task type, persisted with queued tasks
+    fn kind(&self) -> &'static str;
+
+    /// Run one task. Returning an error marks the task as failed.
+    fn execute<'a>(&'a self, ctx: TaskContext<'a>) -> BoxFuture<'a, Result<()>>;
+}
+
+/// What a [`TaskHandler`] gets to work with for a single task
+pub struct TaskContext<'a> {
+    pub drive_id: &'a str,
+    pub task_id: &'a str,
+    pub payload: &'a TaskPayload,
+    pub cr_client: Arc<Client>,
+    pub inventory: Arc<InventoryDb>,
+    pub sync_path: &'a Path,
+    pub remote_base: &'a str,
+    pub(crate) progress: Arc<DashMap<String, TaskProgress>>,
+    /// Commands for the manager running the task. Handlers are shared by all
+    /// managers, so they must not hold on to a sender of their own.
+    pub(crate) manager_command_tx: CommandSender<ManagerCommand>,
+}
+
+impl TaskContext<'_> {
+    /// Update the progress shown for the task. `custom_state` carries any
+    /// task-specific data for the UI.
+    pub fn report_progress(
+        &self,
+        progress: f64,
+        processed: Option<i64>,
+        total: Option<i64>,
+        custom_state: Option<Value>,
+    ) {
+        if let Some(mut entry) = self.progress.get_mut(self.task_id) {
+            entry.update(progress, processed, total, custom_state);
+        }
+    }
+}
+
+/// Register a handler for a custom task type. Fails if the name is taken by a
+/// built-in or already registered task type.
+pub fn register_task_handler(handler: Arc<dyn TaskHandler>) -> Result<()> {
+    let kind = handler.kind();
+    if matches!(kind, "upload" | "download") {
+        return Err(anyhow!("task type {} is built in", kind));
+    }
+
+    let mut handlers = handlers().write().unwrap();
+    if handlers.contains_key(kind) {
+        return Err(anyhow!("task type {} is already registered", kind));
+    }
+    handlers.insert(kind, handler);
+    tracing::info!(target: "tasks::registry", kind, "Registered task type");
+    Ok(())
+}
+
+/// Handler registered for a custom task type
+pub(crate) fn task_handler(kind: &str) -> Option<Arc<dyn TaskHandler>> {
+    handlers().read().unwrap().get(kind).cloned()
+}
+
+/// Resolve a persisted task type name to a registered custom kind
+pub(crate) fn custom_kind(kind: &str) -> Option<TaskKind> {
+    task_handler(kind).map(|handler| TaskKind::Custom(handler.kind()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Checksum;
+
+    impl TaskHandler for Checksum {
+        fn kind(&self) -> &'static str {
+            "test_checksum"
+        }
+
+        fn execute<'a>(&'a self, _ctx: TaskContext<'a>) -> BoxFuture<'a, Result<()>> {
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    #[test]
+    fn registered_kinds_round_trip() {
+        register_task_handler(Arc::new(Checksum)).unwrap();
+        assert!(register_task_handler(Arc::new(Checksum)).is_err());
+
+        let kind = TaskKind::from_str("test_checksum").unwrap();
+        assert_eq!(kind, TaskKind::Custom("test_checksum"));
+        assert_eq!(kind.as_str(), "test_checksum");
+        assert_eq!(TaskKind::from_str("unregistered"), None);
+    }
+}
@@ -0,0 +1,123 @@
+//! Copy and move files between remote folders on the server.
+//!
+//! The server does the work, so no content is downloaded or uploaded, even
+//! between two drives on the same instance. Files are sent one request at a
+//! time so the task can report progress and, after a restart, continue with
+//! the files that were not transferred yet.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use cloudreve_api::{api::ExplorerApi, models::explorer::MoveFileService};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::drive::commands::ManagerCommand;
+use crate::drive::sync::SyncMode;
+use crate::inventory::TaskUpdate;
+use crate::tasks::registry::{TaskContext, TaskHandler, register_task_handler, task_handler};
+
+/// Task type of remote transfers
+pub const REMOTE_TRANSFER_KIND: &str = "remote_transfer";
+
+/// Persisted state of a remote transfer task
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteTransferState {
+    /// Files and folders to transfer
+    pub uris: Vec<String>,
+    /// Folder to transfer them into
+    pub dst: String,
+    /// Copy instead of move
+    pub copy: bool,
+    /// Local folders to sync once the transfer is done
+    #[serde(default)]
+    pub refresh: Vec<PathBuf>,
+    /// Number of `uris` already transferred
+    #[serde(default)]
+    pub done: usize,
+}
+
+struct RemoteTransferHandler;
+
+impl RemoteTransferHandler {
+    async fn run(&self, ctx: TaskContext<'_>) -> Result<()> {
+        let mut state: RemoteTransferState = serde_json::from_value(
+            ctx.payload
+                .custom_state
+                .clone()
+                .context("Remote transfer task has no state")?,
+        )
+        .context("Failed to parse remote transfer state")?;
+        let total = state.uris.len();
+
+        while state.done < total {
+            let uri = state.uris[state.done].clone();
+            ctx.cr_client
+                .move_files(&MoveFileService {
+                    uris: vec![uri.clone()],
+                    dst: state.dst.clone(),
+                    copy: Some(state.copy),
+                })
+                .await
+                .with_context(|| {
+                    let verb = if state.copy { "copy" } else { "move" };
+                    format!("Failed to {} {} to {}", verb, uri, state.dst)
+                })?;
+            state.done += 1;
+
+            let progress = state.done as f64 / total as f64;
+            let custom_state = serde_json::to_value(&state)?;
+            ctx.report_progress(
+                progress,
+                Some(state.done as i64),
+                Some(total as i64),
+                Some(custom_state.clone()),
+            );
+            // Persist after every file, so a resumed move does not try to
+            // move files that are already gone
+            if let Err(e) = ctx.inventory.update_task(
+                ctx.task_id,
+                TaskUpdate {
+                    progress: Some(progress),
+                    processed_bytes: Some(state.done as i64),
+                    total_bytes: Some(total as i64),
+                    custom_state: Some(Some(custom_state)),
+                    ..Default::default()
+                },
+            ) {
+                tracing::warn!(target: "tasks::remote_transfer", task_id = %ctx.task_id, error = %e, "Failed to persist remote transfer progress");
+            }
+        }
+
+        tracing::info!(target: "tasks::remote_transfer", drive = %ctx.drive_id, task_id = %ctx.task_id, count = total, copy = state.copy, dst = %state.dst, "Remote transfer finished");
+
+        for path in state.refresh {
+            if let Err(e) = ctx.manager_command_tx.send(ManagerCommand::SyncNow {
+                paths: vec![path],
+                mode: SyncMode::PathAndFirstLayer,
+            }) {
+                tracing::warn!(target: "tasks::remote_transfer", error = %e, "Failed to send SyncNow command");
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TaskHandler for RemoteTransferHandler {
+    fn kind(&self) -> &'static str {
+        REMOTE_TRANSFER_KIND
+    }
+
+    fn execute<'a>(&'a self, ctx: TaskContext<'a>) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.run(ctx))
+    }
+}
+
+/// Register the remote transfer task type. Does nothing if it already is.
+pub(crate) fn register() -> Result<()> {
+    if task_handler(REMOTE_TRANSFER_KIND).is_some() {
+        return Ok(());
+    }
+    register_task_handler(Arc::new(RemoteTransferHandler))
+}
@@ -0,0 +1,251 @@
+//! Download streams that survive expiry of signed URLs.
+//!
+//! Storage policies hand out download URLs that are only valid for a limited
+//! time. A large hydration can outlive that window, in which case the storage
+//! answers 403 when the connection is reopened. [`ResumableDownload`] requests
+//! a fresh URL from Cloudreve and continues from the last received byte instead
+//! of failing the whole transfer.
+
+use std::{ops::Range, sync::Arc};
+
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use cloudreve_api::{Client, api::ExplorerApi, models::explorer::FileURLService};
+use futures::{StreamExt, stream::BoxStream};
+use reqwest::StatusCode;
+use tracing::{debug, warn};
+
+use crate::tasks::bandwidth;
+
+/// Maximum number of fresh URLs requested during a single transfer
+const MAX_URL_REFRESHES: u32 = 3;
+/// Maximum number of reconnects after the stream broke off mid-transfer
+const MAX_RESUMES: u32 = 5;
+
+/// Whether a response status means the signed URL is no longer valid
+fn is_url_expired(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN | StatusCode::GONE
+    )
+}
+
+/// Range header for the bytes from `offset` up to the exclusive `end`
+fn range_header(offset: u64, end: Option<u64>) -> Option<String> {
+    match end {
+        Some(end) => Some(format!("bytes={}-{}", offset, end - 1)),
+        None if offset > 0 => Some(format!("bytes={}-", offset)),
+        None => None,
+    }
+}
+
+/// Streams a file (or a byte range of it), refreshing the download URL and
+/// resuming at the current offset when the URL expires mid-transfer.
+pub struct ResumableDownload {
+    http: reqwest::Client,
+    cr_client: Arc<Client>,
+    request: FileURLService,
+    url: String,
+    /// URL as issued by the server, used when the LAN route fails
+    public_url: String,
+    /// Offset of the next byte to be returned
+    offset: u64,
+    /// Exclusive end of the requested range, `None` to read until EOF
+    end: Option<u64>,
+    stream: Option<BoxStream<'static, reqwest::Result<Bytes>>>,
+    refreshes: u32,
+    resumes: u32,
+}
+
+impl ResumableDownload {
+    /// Request a download URL for `request` and prepare to stream `range`,
+    /// or the whole file when `range` is `None`.
+    pub async fn new(
+        cr_client: Arc<Client>,
+        request: FileURLService,
+        range: Option<Range<u64>>,
+    ) -> Result<Self> {
+        let public_url = Self::request_url(&cr_client, &request).await?;
+        Self::with_url(cr_client, request, public_url, range).await
+    }
+
+    /// Prepare to stream `range` from a URL requested earlier for `request`,
+    /// e.g. taken from a [`UrlPool`](super::url_pool::UrlPool). `request` is
+    /// used to refresh it if it expires.
+    pub async fn with_url(
+        cr_client: Arc<Client>,
+        request: FileURLService,
+        public_url: String,
+        range: Option<Range<u64>>,
+    ) -> Result<Self> {
+        let url = cr_client.prefer_lan(&public_url).await;
+        Ok(Self {
+            http: cr_client.config().http_client_builder().build()?,
+            cr_client,
+            request,
+            url,
+            public_url,
+            offset: range.as_ref().map(|r| r.start).unwrap_or(0),
+            end: range.map(|r| r.end),
+            stream: None,
+            refreshes: 0,
+            resumes: 0,
+        })
+    }
+
+    /// Current download URL
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Return the next chunk of data, or `None` once the range is complete.
+    pub async fn next_chunk(&mut self) -> Result<Option<Bytes>> {
+        loop {
+            if self.end.is_some_and(|end| self.offset >= end) {
+                return Ok(None);
+            }
+
+            if self.stream.is_none() {
+                self.open().await?;
+            }
+            let stream = self.stream.as_mut().expect("stream opened above");
+
+            match stream.next().await {
+                Some(Ok(chunk)) => {
+                    self.offset += chunk.len() as u64;
+                    if let Some(limiter) = bandwidth::download_limiter() {
+                        limiter.acquire(chunk.len() as u64).await;
+                    }
+                    return Ok(Some(chunk));
+                }
+                None => {
+                    self.stream = None;
+                    match self.end {
+                        // Connection closed before the range was complete
+                        Some(end) if self.offset < end => self.count_resume(None)?,
+                        _ => return Ok(None),
+                    }
+                }
+                Some(Err(e)) => {
+                    self.stream = None;
+                    self.count_resume(Some(e))?;
+                }
+            }
+        }
+    }
+
+    /// Open a connection starting at the current offset, refreshing the URL
+    /// if the storage reports it expired.
+    async fn open(&mut self) -> Result<()> {
+        loop {
+            let mut request = self.http.get(&self.url);
+            if let Some(range) = range_header(self.offset, self.end) {
+                request = request.header(reqwest::header::RANGE, range);
+            }
+            let response = match request.send().await {
+                Ok(response) => response,
+                Err(e) if self.url != self.public_url => {
+                    warn!(
+                        target: "tasks::resumable",
+                        error = %e,
+                        "LAN route failed, falling back to the public URL"
+                    );
+                    self.cr_client.clear_lan_base_url().await;
+                    self.url = self.public_url.clone();
+                    continue;
+                }
+                Err(e) => return Err(e).context("failed to send download request"),
+            };
+            let status = response.status();
+
+            if is_url_expired(status) {
+                self.refresh_url(status).await?;
+                continue;
+            }
+            if status == StatusCode::OK && self.offset > 0 {
+                anyhow::bail!("storage ignored range request while resuming download");
+            }
+            if !status.is_success() {
+                anyhow::bail!("Download request failed with status: {}", status);
+            }
+
+            self.stream = Some(response.bytes_stream().boxed());
+            return Ok(());
+        }
+    }
+
+    async fn refresh_url(&mut self, status: StatusCode) -> Result<()> {
+        if self.refreshes >= MAX_URL_REFRESHES {
+            anyhow::bail!(
+                "download URL rejected with status {} after {} refreshes",
+                status,
+                self.refreshes
+            );
+        }
+        self.refreshes += 1;
+        warn!(
+            target: "tasks::resumable",
+            status = %status,
+            offset = self.offset,
+            attempt = self.refreshes,
+            "Download URL expired, requesting a fresh one"
+        );
+
+        // Bypass the server-side URL cache so we don't get the expired one back
+        self.request.no_cache = Some(true);
+        self.public_url = Self::request_url(&self.cr_client, &self.request).await?;
+        self.url = self.cr_client.prefer_lan(&self.public_url).await;
+        Ok(())
+    }
+
+    fn count_resume(&mut self, error: Option<reqwest::Error>) -> Result<()> {
+        if self.resumes >= MAX_RESUMES {
+            return match error {
+                Some(e) => Err(e).context("failed to read chunk from stream"),
+                None => anyhow::bail!("download stream ended at offset {}", self.offset),
+            };
+        }
+        self.resumes += 1;
+        debug!(
+            target: "tasks::resumable",
+            offset = self.offset,
+            attempt = self.resumes,
+            error = ?error,
+            "Download stream interrupted, resuming"
+        );
+        Ok(())
+    }
+
+    async fn request_url(cr_client: &Client, request: &FileURLService) -> Result<String> {
+        let response = cr_client
+            .get_file_url(request)
+            .await
+            .context("failed to get file url")?;
+        Ok(response
+            .urls
+            .first()
+            .context("no download URL in response")?
+            .url
+            .clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn range_header_for_bounded_and_open_ranges() {
+        assert_eq!(range_header(0, Some(4096)).as_deref(), Some("bytes=0-4095"));
+        assert_eq!(range_header(100, None).as_deref(), Some("bytes=100-"));
+        assert_eq!(range_header(0, None), None);
+    }
+
+    #[test]
+    fn expired_statuses() {
+        assert!(is_url_expired(StatusCode::FORBIDDEN));
+        assert!(is_url_expired(StatusCode::UNAUTHORIZED));
+        assert!(!is_url_expired(StatusCode::NOT_FOUND));
+        assert!(!is_url_expired(StatusCode::PARTIAL_CONTENT));
+    }
+}
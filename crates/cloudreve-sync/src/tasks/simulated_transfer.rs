@@ -0,0 +1,78 @@
+//! Transfers of simulated drives.
+//!
+//! Nothing is sent or received: the task only reports progress in steps over
+//! about ten seconds, and fails halfway if it was told to. See
+//! [`crate::drive::simulated`].
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::tasks::registry::{TaskContext, TaskHandler, register_task_handler, task_handler};
+
+/// Task type of simulated transfers
+pub const SIMULATED_TRANSFER_KIND: &str = "simulated_transfer";
+
+/// Steps a simulated transfer reports progress in
+const TRANSFER_STEPS: i64 = 40;
+/// Delay between two progress steps
+const TRANSFER_STEP_DELAY: Duration = Duration::from_millis(250);
+
+/// Persisted state of a simulated transfer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SimulatedTransferState {
+    /// `upload` or `download`, only shown
+    pub direction: String,
+    /// Fail halfway, as a dropped connection would
+    pub fail: bool,
+}
+
+struct SimulatedTransferHandler;
+
+impl SimulatedTransferHandler {
+    async fn run(&self, ctx: TaskContext<'_>) -> Result<()> {
+        let state: SimulatedTransferState = serde_json::from_value(
+            ctx.payload
+                .custom_state
+                .clone()
+                .context("Simulated transfer task has no state")?,
+        )
+        .context("Failed to parse simulated transfer state")?;
+        let size = ctx.payload.total_bytes.unwrap_or(0);
+
+        for step in 1..=TRANSFER_STEPS {
+            tokio::time::sleep(TRANSFER_STEP_DELAY).await;
+            if state.fail && step > TRANSFER_STEPS / 2 {
+                bail!("Simulated {} failed: connection reset", state.direction);
+            }
+            ctx.report_progress(
+                step as f64 / TRANSFER_STEPS as f64,
+                Some(size * step / TRANSFER_STEPS),
+                Some(size),
+                None,
+            );
+        }
+        Ok(())
+    }
+}
+
+impl TaskHandler for SimulatedTransferHandler {
+    fn kind(&self) -> &'static str {
+        SIMULATED_TRANSFER_KIND
+    }
+
+    fn execute<'a>(&'a self, ctx: TaskContext<'a>) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.run(ctx))
+    }
+}
+
+/// Register the simulated transfer task type. Does nothing if it already is.
+pub(crate) fn register() -> Result<()> {
+    if task_handler(SIMULATED_TRANSFER_KIND).is_some() {
+        return Ok(());
+    }
+    register_task_handler(Arc::new(SimulatedTransferHandler))
+}
@@ -0,0 +1,115 @@
+//! Restore files from the server trash back into a drive.
+//!
+//! Entries are restored a batch per request. Once restored they are back in
+//! their original remote folders, and syncing the nearest local folders that
+//! still exist creates their placeholders.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use cloudreve_api::api::TrashApi;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::drive::commands::ManagerCommand;
+use crate::drive::sync::SyncMode;
+use crate::inventory::TaskUpdate;
+use crate::tasks::registry::{TaskContext, TaskHandler, register_task_handler, task_handler};
+
+/// Task type of trash restores
+pub const TRASH_RESTORE_KIND: &str = "trash_restore";
+
+/// Trash entries restored in one request
+const RESTORE_BATCH_SIZE: usize = 50;
+
+/// Persisted state of a trash restore task
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrashRestoreState {
+    /// URIs of the entries in the trash
+    pub uris: Vec<String>,
+    /// Local folders to sync once the entries are restored
+    #[serde(default)]
+    pub refresh: Vec<PathBuf>,
+    /// Number of `uris` already restored
+    #[serde(default)]
+    pub done: usize,
+}
+
+struct TrashRestoreHandler;
+
+impl TrashRestoreHandler {
+    async fn run(&self, ctx: TaskContext<'_>) -> Result<()> {
+        let mut state: TrashRestoreState = serde_json::from_value(
+            ctx.payload
+                .custom_state
+                .clone()
+                .context("Trash restore task has no state")?,
+        )
+        .context("Failed to parse trash restore state")?;
+        let total = state.uris.len();
+
+        while state.done < total {
+            let end = (state.done + RESTORE_BATCH_SIZE).min(total);
+            let batch = &state.uris[state.done..end];
+            ctx.cr_client
+                .restore_from_trash(batch)
+                .await
+                .with_context(|| format!("Failed to restore {} files from trash", batch.len()))?;
+            state.done = end;
+
+            let progress = state.done as f64 / total as f64;
+            let custom_state = serde_json::to_value(&state)?;
+            ctx.report_progress(
+                progress,
+                Some(state.done as i64),
+                Some(total as i64),
+                Some(custom_state.clone()),
+            );
+            // Persist after every batch, so a resumed restore does not ask for
+            // entries that already left the trash
+            if let Err(e) = ctx.inventory.update_task(
+                ctx.task_id,
+                TaskUpdate {
+                    progress: Some(progress),
+                    processed_bytes: Some(state.done as i64),
+                    total_bytes: Some(total as i64),
+                    custom_state: Some(Some(custom_state)),
+                    ..Default::default()
+                },
+            ) {
+                tracing::warn!(target: "tasks::trash_restore", task_id = %ctx.task_id, error = %e, "Failed to persist trash restore progress");
+            }
+        }
+
+        tracing::info!(target: "tasks::trash_restore", drive = %ctx.drive_id, task_id = %ctx.task_id, count = total, "Trash restore finished");
+
+        if !state.refresh.is_empty() {
+            if let Err(e) = ctx.manager_command_tx.send(ManagerCommand::SyncNow {
+                paths: state.refresh,
+                mode: SyncMode::PathAndFirstLayer,
+            }) {
+                tracing::warn!(target: "tasks::trash_restore", error = %e, "Failed to send SyncNow command");
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TaskHandler for TrashRestoreHandler {
+    fn kind(&self) -> &'static str {
+        TRASH_RESTORE_KIND
+    }
+
+    fn execute<'a>(&'a self, ctx: TaskContext<'a>) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.run(ctx))
+    }
+}
+
+/// Register the trash restore task type. Does nothing if it already is.
+pub(crate) fn register() -> Result<()> {
+    if task_handler(TRASH_RESTORE_KIND).is_some() {
+        return Ok(());
+    }
+    register_task_handler(Arc::new(TrashRestoreHandler))
+}
@@ -1,11 +1,13 @@
-use serde::{Deserialize, Serialize};
+use serde::{Serialize, Serializer};
 use serde_json::Value;
 use std::path::PathBuf;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TaskKind {
     Upload,
     Download,
+    /// Task type provided by a registered [`TaskHandler`](super::TaskHandler)
+    Custom(&'static str),
 }
 
 impl TaskKind {
@@ -13,6 +15,7 @@ impl TaskKind {
         match self {
             TaskKind::Upload => "upload",
             TaskKind::Download => "download",
+            TaskKind::Custom(kind) => kind,
         }
     }
 
@@ -20,7 +23,17 @@ impl TaskKind {
         match value {
             "upload" => Some(TaskKind::Upload),
             "download" => Some(TaskKind::Download),
-            _ => None,
+            _ => super::registry::custom_kind(value),
+        }
+    }
+}
+
+impl Serialize for TaskKind {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            TaskKind::Upload => serializer.serialize_str("Upload"),
+            TaskKind::Download => serializer.serialize_str("Download"),
+            TaskKind::Custom(kind) => serializer.serialize_str(kind),
         }
     }
 }
@@ -113,6 +126,8 @@ pub struct TaskProgress {
     pub eta_seconds: Option<u64>,
     /// Custom state for task-specific data
     pub custom_state: Option<Value>,
+    /// Concurrency level picked by autoscaling for the task's kind, if enabled
+    pub worker_level: Option<usize>,
 }
 
 impl TaskProgress {
@@ -127,6 +142,7 @@ impl TaskProgress {
             speed_bytes_per_sec: 0,
             eta_seconds: None,
             custom_state: payload.custom_state.clone(),
+            worker_level: None,
         }
     }
 
@@ -1,19 +1,36 @@
-use std::{path::PathBuf, str::FromStr, sync::Arc, time::SystemTime};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
 
 use crate::utils::toast::send_conflict_toast;
 use crate::{
-    drive::{placeholder::CrPlaceholder, utils::local_path_to_cr_uri},
+    drive::{
+        command_queue::CommandSender, commands::ManagerCommand, placeholder::CrPlaceholder,
+        utils::local_path_to_cr_uri,
+    },
     inventory::{ConflictState, FileMetadata, InventoryDb},
-    tasks::queue::QueuedTask,
-    uploader::{ProgressCallback, ProgressUpdate, UploadParams, Uploader, UploaderConfig},
+    tasks::{naming::NamePolicy, queue::QueuedTask},
+    uploader::{
+        CompressionSettings, CompressionStats, IntegrityStats, MAX_COMPRESSION_RATIO,
+        ProgressCallback, ProgressUpdate, UploadParams, Uploader, UploaderConfig, compress_to_zip,
+    },
 };
 use anyhow::{Context, Result};
 use bytes::Bytes;
 use cloudreve_api::{
     ApiError, Client,
-    api::ExplorerApi,
+    api::{ExplorerApi, WorkflowApi},
     error::ErrorCode,
-    models::explorer::{CreateFileService, FileResponse, FileUpdateService, file_type},
+    models::{
+        explorer::{
+            CreateFileService, DeleteFileService, FileResponse, FileUpdateService, file_type,
+        },
+        uri::CrUri,
+        workflow::ArchiveWorkflowService,
+    },
 };
 use dashmap::DashMap;
 use tokio_util::sync::CancellationToken;
@@ -22,6 +39,24 @@ use uuid::Uuid;
 
 use super::types::TaskProgress;
 
+/// Folder at the root of the drive's file system holding archives being
+/// extracted. The server has no hidden or temporary folders, a dot-named one
+/// outside the synced folder is the closest, and every archive is deleted
+/// again whether its upload and extraction succeed or not.
+const STAGING_FOLDER: [&str; 2] = [".cloudreve", "staging"];
+/// How long to wait for the server to extract a compressed upload
+const EXTRACT_TIMEOUT: Duration = Duration::from_secs(600);
+/// How long to wait for an extraction to stop once it is cancelled
+const EXTRACT_CANCEL_TIMEOUT: Duration = Duration::from_secs(60);
+/// Interval between polls of the extraction task
+const EXTRACT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// An extraction was cancelled but did not stop. The server may still write
+/// the file, so it must not be uploaded as is in the meantime.
+#[derive(Debug, thiserror::Error)]
+#[error("archive extraction did not stop after it was cancelled")]
+struct ExtractionUnsettled;
+
 /// Progress reporter that updates task progress in-memory via a DashMap reference.
 /// Does NOT persist to inventory - only keeps in-memory for real-time queries.
 pub struct InMemoryProgressReporter {
@@ -58,6 +93,11 @@ pub struct UploadTask<'a> {
     cancel_token: CancellationToken,
     /// Reference to the in-memory progress map for real-time progress updates
     progress_map: Arc<DashMap<String, TaskProgress>>,
+    compression: CompressionSettings,
+    max_chunk_concurrency: Option<usize>,
+    name_policy: NamePolicy,
+    /// Set to report conflicts to the UI
+    manager_command_tx: Option<CommandSender<ManagerCommand>>,
 }
 
 impl<'a> UploadTask<'a> {
@@ -81,9 +121,37 @@ impl<'a> UploadTask<'a> {
             remote_base,
             cancel_token: CancellationToken::new(),
             progress_map,
+            compression: CompressionSettings::default(),
+            max_chunk_concurrency: None,
+            name_policy: NamePolicy::default(),
+            manager_command_tx: None,
         }
     }
 
+    /// Set the compressed upload settings of the drive
+    pub fn with_compression(mut self, compression: CompressionSettings) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Cap the number of chunks uploaded in parallel
+    pub fn with_chunk_concurrency(mut self, limit: Option<usize>) -> Self {
+        self.max_chunk_concurrency = limit;
+        self
+    }
+
+    /// Check names of new files against the server's naming policy
+    pub fn with_name_policy(mut self, policy: NamePolicy) -> Self {
+        self.name_policy = policy;
+        self
+    }
+
+    /// Report conflicts through `manager_command_tx`
+    pub fn with_manager_command_tx(mut self, tx: CommandSender<ManagerCommand>) -> Self {
+        self.manager_command_tx = Some(tx);
+        self
+    }
+
     /// Set the cancellation token
     #[allow(dead_code)]
     pub fn with_cancel_token(mut self, token: CancellationToken) -> Self {
@@ -137,6 +205,13 @@ impl<'a> UploadTask<'a> {
             .query_by_path(path_str)
             .context("failed to get inventory meta")?;
 
+        // Files already on the server keep their accepted name
+        if self.inventory_meta.is_none() {
+            if let Err(e) = self.check_remote_name() {
+                return self.handle_error(Err(e)).await;
+            }
+        }
+
         // clear file error state
         // Mark file as error state
         if let Err(e) = self
@@ -163,6 +238,39 @@ impl<'a> UploadTask<'a> {
         self.handle_error(upload_res).await
     }
 
+    /// Fail before transferring anything if the server would reject the
+    /// name of a new file
+    fn check_remote_name(&self) -> Result<()> {
+        let local_path = &self.task.payload.local_path;
+        let (Some(name), Some(parent)) = (
+            local_path.file_name().and_then(|name| name.to_str()),
+            local_path.parent().and_then(|parent| parent.to_str()),
+        ) else {
+            return Ok(());
+        };
+
+        let siblings = self
+            .inventory
+            .list_children(parent)
+            .context("failed to list siblings")?;
+        let sibling_names = siblings.iter().filter_map(|sibling| {
+            Path::new(&sibling.local_path)
+                .file_name()
+                .and_then(|name| name.to_str())
+        });
+        if let Err(violation) = self.name_policy.check(name, sibling_names) {
+            warn!(
+                target: "tasks::upload",
+                task_id = %self.task.task_id,
+                local_path = %self.task.payload.local_path_display(),
+                violation = %violation,
+                "Name rejected by server naming policy"
+            );
+            return Err(violation.into());
+        }
+        Ok(())
+    }
+
     async fn handle_error(&mut self, r: Result<()>) -> Result<()> {
         match r {
             Ok(()) => Ok(()),
@@ -213,7 +321,16 @@ impl<'a> UploadTask<'a> {
                             .as_ref()
                             .map(|meta| meta.id)
                             .unwrap_or(0),
-                    )
+                    );
+                    if let Some(tx) = &self.manager_command_tx {
+                        let command = ManagerCommand::ConflictDetected {
+                            drive_id: self.drive_id.to_string(),
+                            path: self.task.payload.local_path.clone(),
+                        };
+                        if let Err(e) = tx.send(command) {
+                            warn!(target: "tasks::upload", error = %e, "Failed to send ConflictDetected command");
+                        }
+                    }
                 }
 
                 // Mark file as error state
@@ -263,6 +380,14 @@ impl<'a> UploadTask<'a> {
         }
     }
 
+    /// Uploader configuration with the drive's chunk concurrency cap
+    fn uploader_config(&self) -> UploaderConfig {
+        UploaderConfig {
+            max_chunk_concurrency: self.max_chunk_concurrency,
+            ..Default::default()
+        }
+    }
+
     /// Upload a file using the new uploader module
     async fn upload_file_with_uploader(&mut self) -> Result<()> {
         let local_file = self.local_file.as_ref().unwrap();
@@ -286,6 +411,29 @@ impl<'a> UploadTask<'a> {
         .context("failed to convert local path to cloudreve uri")?
         .to_string();
 
+        // New text-like files may be sent compressed and extracted by the server
+        if is_new_file
+            && !self.task.payload.force_override
+            && self
+                .compression
+                .applies_to(&self.task.payload.local_path, file_size)
+        {
+            match self.upload_compressed(&uri, file_size).await {
+                Ok(true) => return self.finalize_upload(file_size).await,
+                Ok(false) => {}
+                Err(e) if e.downcast_ref::<ExtractionUnsettled>().is_some() => return Err(e),
+                Err(e) => {
+                    warn!(
+                        target: "tasks::upload",
+                        task_id = %self.task.task_id,
+                        local_path = %self.task.payload.local_path_display(),
+                        error = %e,
+                        "Compressed upload failed, uploading the file as is"
+                    );
+                }
+            }
+        }
+
         // If conflict state is set to Override, omit previous_version to force upload without version check
         let previous_version = if let Some(meta) = &self.inventory_meta {
             if matches!(meta.conflict_state, Some(ConflictState::Override)) {
@@ -314,7 +462,7 @@ impl<'a> UploadTask<'a> {
         };
 
         // Create uploader configuration
-        let config = UploaderConfig::default();
+        let config = self.uploader_config();
 
         // Create uploader
         let uploader = Uploader::new(self.cr_client.clone(), self.inventory.clone(), config)
@@ -333,13 +481,225 @@ impl<'a> UploadTask<'a> {
             .context("failed to upload file")?;
 
         // Update local file placeholder status after successful upload
-        self.finalize_upload().await?;
+        self.finalize_upload(file_size).await?;
 
         Ok(())
     }
 
-    /// Finalize upload by updating local file placeholder
-    async fn finalize_upload(&mut self) -> Result<()> {
+    /// Upload the file as a zip archive to the staging folder and have the
+    /// server extract it in place. Returns `Ok(false)` if compression does not
+    /// pay off or is not possible for this drive.
+    async fn upload_compressed(&mut self, uri: &str, file_size: u64) -> Result<bool> {
+        // The staging folder must be outside the synced folder, otherwise the
+        // archive would be synced back down
+        let remote_base = CrUri::new(&self.remote_base)?;
+        if remote_base.is_root() {
+            debug!(
+                target: "tasks::upload",
+                task_id = %self.task.task_id,
+                "Drive syncs a filesystem root, no room for a staging folder"
+            );
+            return Ok(false);
+        }
+
+        let file_name = self
+            .task
+            .payload
+            .local_path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .context("file name is not valid UTF-8")?
+            .to_string();
+        let archive_name = format!("{}.zip", self.task.task_id);
+        let archive_path = std::env::temp_dir().join(format!("cloudreve-{}", archive_name));
+
+        let result: Result<bool> = async {
+            let compressed_size =
+                compress_to_zip(&self.task.payload.local_path, &file_name, &archive_path).await?;
+            if compressed_size as f64 > file_size as f64 * MAX_COMPRESSION_RATIO {
+                debug!(
+                    target: "tasks::upload",
+                    task_id = %self.task.task_id,
+                    file_size,
+                    compressed_size,
+                    "File does not compress well, uploading it as is"
+                );
+                return Ok(false);
+            }
+
+            let mut staging_uri = CrUri::new(&remote_base.base(true))?;
+            staging_uri
+                .join(&STAGING_FOLDER)
+                .join(&[archive_name.as_str()]);
+            let staging_uri = staging_uri.to_string();
+            let destination = CrUri::new(uri)?.parent()?.to_string();
+
+            // A partly uploaded archive is not left behind in the staging
+            // folder either
+            if let Err(e) = self
+                .upload_archive(&archive_path, &staging_uri, compressed_size)
+                .await
+            {
+                self.delete_staging_archive(&staging_uri).await;
+                return Err(e);
+            }
+            let extracted = self.extract_archive(&staging_uri, &destination).await;
+            // The server may still be reading an archive whose extraction
+            // did not stop
+            if !extracted
+                .as_ref()
+                .is_err_and(|e| e.downcast_ref::<ExtractionUnsettled>().is_some())
+            {
+                self.delete_staging_archive(&staging_uri).await;
+            }
+            extracted?;
+
+            CompressionStats::record_compressed(file_size, compressed_size);
+            info!(
+                target: "tasks::upload",
+                task_id = %self.task.task_id,
+                local_path = %self.task.payload.local_path_display(),
+                file_size,
+                compressed_size,
+                "Uploaded file compressed"
+            );
+            Ok(true)
+        }
+        .await;
+
+        let _ = tokio::fs::remove_file(&archive_path).await;
+        result
+    }
+
+    async fn upload_archive(
+        &self,
+        archive_path: &Path,
+        staging_uri: &str,
+        compressed_size: u64,
+    ) -> Result<()> {
+        let params = UploadParams {
+            local_path: archive_path.to_path_buf(),
+            remote_uri: staging_uri.to_string(),
+            file_size: compressed_size,
+            mime_type: Some("application/zip".to_string()),
+            last_modified: None,
+            overwrite: true,
+            previous_version: String::new(),
+            task_id: self.task.task_id.clone(),
+            drive_id: self.drive_id.to_string(),
+        };
+
+        let uploader = Uploader::new(
+            self.cr_client.clone(),
+            self.inventory.clone(),
+            self.uploader_config(),
+        )
+        .with_cancel_token(self.cancel_token.clone());
+        let progress = InMemoryProgressReporter::new(
+            self.task.task_id.clone(),
+            Arc::clone(&self.progress_map),
+        );
+        uploader
+            .upload(params, progress)
+            .await
+            .context("failed to upload archive")
+    }
+
+    /// Run the server's extract workflow and wait for it to finish. Fails
+    /// with [`ExtractionUnsettled`] if it timed out and could not be stopped.
+    async fn extract_archive(&self, staging_uri: &str, destination: &str) -> Result<()> {
+        let task = self
+            .cr_client
+            .extract_archive(&ArchiveWorkflowService {
+                src: vec![staging_uri.to_string()],
+                dst: destination.to_string(),
+                preferred_node_id: None,
+                encoding: None,
+                password: None,
+                file_mask: None,
+            })
+            .await
+            .context("failed to start archive extraction")?;
+
+        let deadline = Instant::now() + EXTRACT_TIMEOUT;
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(EXTRACT_POLL_INTERVAL) => {}
+                _ = self.cancel_token.cancelled() => {
+                    let _ = self.cr_client.cancel_task(&task.id).await;
+                    anyhow::bail!("upload cancelled during archive extraction");
+                }
+            }
+
+            let status = self
+                .cr_client
+                .get_task(&task.id)
+                .await
+                .context("failed to get archive extraction status")?;
+            match status.status.as_str() {
+                "completed" => return Ok(()),
+                "error" | "canceled" => anyhow::bail!(
+                    "archive extraction {}: {}",
+                    status.status,
+                    status.error.unwrap_or_default()
+                ),
+                _ if Instant::now() >= deadline => {
+                    return self.cancel_extraction(&task.id).await;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Cancel a timed out extraction and wait until the server confirms it
+    /// stopped. `Ok` if it completed in the meantime.
+    async fn cancel_extraction(&self, task_id: &str) -> Result<()> {
+        if let Err(e) = self.cr_client.cancel_task(task_id).await {
+            warn!(
+                target: "tasks::upload",
+                task_id = %self.task.task_id,
+                error = %e,
+                "Failed to cancel archive extraction"
+            );
+        }
+
+        let deadline = Instant::now() + EXTRACT_CANCEL_TIMEOUT;
+        while Instant::now() < deadline {
+            tokio::time::sleep(EXTRACT_POLL_INTERVAL).await;
+            let Ok(status) = self.cr_client.get_task(task_id).await else {
+                continue;
+            };
+            match status.status.as_str() {
+                "completed" => return Ok(()),
+                "error" | "canceled" => anyhow::bail!("archive extraction timed out"),
+                _ => {}
+            }
+        }
+        Err(ExtractionUnsettled.into())
+    }
+
+    async fn delete_staging_archive(&self, staging_uri: &str) {
+        if let Err(e) = self
+            .cr_client
+            .delete_files(&DeleteFileService {
+                uris: vec![staging_uri.to_string()],
+                unlink: None,
+                skip_soft_delete: Some(true),
+            })
+            .await
+        {
+            warn!(
+                target: "tasks::upload",
+                task_id = %self.task.task_id,
+                uri = %staging_uri,
+                error = %e,
+                "Failed to delete staging archive"
+            );
+        }
+    }
+
+    /// Finalize upload by verifying the remote size and updating local file placeholder
+    async fn finalize_upload(&mut self, file_size: u64) -> Result<()> {
         // Get file info from server to confirm upload
         let uri = local_path_to_cr_uri(
             self.task.payload.local_path.clone(),
@@ -360,6 +720,16 @@ impl<'a> UploadTask<'a> {
             .await
             .context("failed to get file info after upload")?;
 
+        // End-to-end check: the stored file must be as large as what we sent
+        if file_info.size as u64 != file_size {
+            IntegrityStats::record_size_mismatch();
+            anyhow::bail!(
+                "uploaded file size mismatch: remote {} bytes, local {} bytes",
+                file_info.size,
+                file_size
+            );
+        }
+
         self.file_uploaded(&file_info)
             .context("failed to commit uploaded file")?;
         Ok(())
@@ -0,0 +1,174 @@
+//! Download URLs requested ahead of the hydrations that use them.
+//!
+//! Every hydration needs a signed download URL, and asking for them one file
+//! at a time adds a round trip per file when a large folder is made
+//! available offline. `FileURLService` accepts several URIs, so the URLs of
+//! the siblings waiting to be hydrated are requested in one batch and pooled
+//! by file id. Entries are handed out until shortly before they expire; a
+//! later miss requests them again together with the next batch.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+
+/// URIs requested at most in one batch
+pub(crate) const URL_BATCH_SIZE: usize = 50;
+
+/// URLs expiring sooner than this are requested again instead of handed out
+const REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// Upper bound on pooled URLs, so pinning a huge tree can't grow it unbounded
+const MAX_POOLED_URLS: usize = 5000;
+
+#[derive(Debug)]
+struct PooledUrl {
+    url: String,
+    /// Content the URL was requested for, matched against the hydration
+    entity: Option<String>,
+    expires: Instant,
+}
+
+/// Short-lived download URLs by remote file id
+#[derive(Debug, Default)]
+pub struct UrlPool {
+    urls: Mutex<HashMap<String, PooledUrl>>,
+}
+
+impl UrlPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pooled URL of `file_id` for the content `entity`, unless it expires
+    /// within [`REFRESH_MARGIN`]
+    pub fn get(&self, file_id: &str, entity: Option<&str>) -> Option<String> {
+        let urls = self.urls.lock().unwrap();
+        urls.get(file_id)
+            .filter(|pooled| is_fresh(pooled, Instant::now()))
+            .filter(|pooled| entity.is_none() || pooled.entity.as_deref() == entity)
+            .map(|pooled| pooled.url.clone())
+    }
+
+    /// Whether `file_id` has a URL that needs no refresh yet
+    pub fn is_fresh(&self, file_id: &str) -> bool {
+        let urls = self.urls.lock().unwrap();
+        urls.get(file_id)
+            .is_some_and(|pooled| is_fresh(pooled, Instant::now()))
+    }
+
+    /// Pool `url` for `file_id` until `expires`
+    pub fn insert(&self, file_id: String, entity: Option<String>, url: String, expires: Instant) {
+        let now = Instant::now();
+        let mut urls = self.urls.lock().unwrap();
+        if urls.len() >= MAX_POOLED_URLS {
+            urls.retain(|_, pooled| is_fresh(pooled, now));
+            if urls.len() >= MAX_POOLED_URLS {
+                return;
+            }
+        }
+        urls.insert(
+            file_id,
+            PooledUrl {
+                url,
+                entity,
+                expires,
+            },
+        );
+    }
+}
+
+fn is_fresh(pooled: &PooledUrl, now: Instant) -> bool {
+    pooled
+        .expires
+        .checked_duration_since(now)
+        .is_some_and(|remaining| remaining > REFRESH_MARGIN)
+}
+
+/// When URLs with the server's `expires` timestamp (RFC 3339) stop working,
+/// or `None` if it can't be parsed or has passed. The timestamp is compared
+/// with `server_now`, the server's clock corrected for skew, since it is
+/// issued by the server; the pool stops handing URLs out [`REFRESH_MARGIN`]
+/// before this.
+pub(crate) fn expiry_instant(
+    expires: &str,
+    server_now: DateTime<Utc>,
+    now: Instant,
+) -> Option<Instant> {
+    let expires = DateTime::parse_from_rfc3339(expires).ok()?;
+    let remaining = (expires.with_timezone(&Utc) - server_now).to_std().ok()?;
+    Some(now + remaining)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls_are_not_handed_out_close_to_expiry() {
+        let pool = UrlPool::new();
+        let now = Instant::now();
+        pool.insert(
+            "a".into(),
+            None,
+            "https://a".into(),
+            now + Duration::from_secs(600),
+        );
+        pool.insert(
+            "b".into(),
+            None,
+            "https://b".into(),
+            now + REFRESH_MARGIN / 2,
+        );
+
+        assert_eq!(pool.get("a", None).as_deref(), Some("https://a"));
+        assert!(pool.is_fresh("a"));
+        assert_eq!(pool.get("b", None), None);
+        assert!(!pool.is_fresh("b"));
+        assert_eq!(pool.get("c", None), None);
+    }
+
+    #[test]
+    fn urls_match_the_requested_entity() {
+        let pool = UrlPool::new();
+        let expires = Instant::now() + Duration::from_secs(600);
+        pool.insert("a".into(), Some("e1".into()), "https://a".into(), expires);
+
+        assert!(pool.get("a", Some("e1")).is_some());
+        assert!(pool.get("a", None).is_some());
+        assert_eq!(pool.get("a", Some("e2")), None);
+    }
+
+    #[test]
+    fn expiry_from_server_timestamp() {
+        let now = Instant::now();
+        let server_now = Utc::now();
+        let in_ten_minutes = (server_now + chrono::Duration::minutes(10)).to_rfc3339();
+        let expires = expiry_instant(&in_ten_minutes, server_now, now).unwrap();
+        assert!(expires > now + Duration::from_secs(9 * 60));
+
+        let past = (server_now - chrono::Duration::minutes(1)).to_rfc3339();
+        assert_eq!(expiry_instant(&past, server_now, now), None);
+        assert_eq!(expiry_instant("not a date", server_now, now), None);
+    }
+
+    #[test]
+    fn expiry_follows_the_server_clock() {
+        let now = Instant::now();
+        let local_now = Utc::now();
+        // The server runs an hour ahead, so its URLs look long-lived locally
+        let server_now = local_now + chrono::Duration::hours(1);
+        let expires = (server_now + chrono::Duration::minutes(5)).to_rfc3339();
+        let expires = expiry_instant(&expires, server_now, now).unwrap();
+        assert_eq!(expires, now + Duration::from_secs(5 * 60));
+
+        let pool = UrlPool::new();
+        pool.insert("a".into(), None, "https://a".into(), expires);
+        assert!(pool.is_fresh("a"));
+        assert!(!is_fresh(
+            &pool.urls.lock().unwrap()["a"],
+            expires - REFRESH_MARGIN
+        ));
+    }
+}
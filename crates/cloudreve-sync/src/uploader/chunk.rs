@@ -1,8 +1,10 @@
 //! Chunk-based upload logic with streaming support and progress tracking
 
+use crate::tasks::bandwidth::{self, Throttled};
 use crate::uploader::UploaderConfig;
 use crate::uploader::encrypt::EncryptionConfig;
 use crate::uploader::error::UploadError;
+use crate::uploader::integrity::{ChunkDigest, ChunkReceipt, DigestStream, IntegrityStats};
 use crate::uploader::progress::{ProgressCallback, ProgressTracker};
 use crate::uploader::providers::{self, PolicyType};
 use crate::uploader::session::UploadSession;
@@ -293,7 +295,10 @@ impl ChunkUploader {
         progress_callback: Arc<P>,
         cancel_token: &CancellationToken,
     ) -> Result<()> {
-        let concurrency = session.chunk_concurrency();
+        let concurrency = match self.config.max_chunk_concurrency {
+            Some(limit) => session.chunk_concurrency().min(limit.max(1)),
+            None => session.chunk_concurrency(),
+        };
 
         info!(
             target: "uploader::chunk",
@@ -386,6 +391,8 @@ impl ChunkUploader {
     /// - Starts up to `concurrency` uploads simultaneously
     /// - When a chunk completes, immediately starts the next pending chunk
     /// - Stops all uploads on first error
+    /// - Stops starting new chunks once the session is about to expire, returning
+    ///   [`UploadError::SessionExpired`] so the caller can renew it
     async fn upload_chunks_with_pool(
         &self,
         local_path: &Path,
@@ -421,6 +428,10 @@ impl ChunkUploader {
         // Local path needs to be owned for spawned tasks
         let local_path = local_path.to_path_buf();
 
+        // Workers cancel this on failure; it must not cancel the whole upload
+        // so that it can go on with a renewed session
+        let pool_cancel = cancel_token.child_token();
+
         // Spawn initial workers up to concurrency limit
         let mut handles = Vec::with_capacity(concurrency);
 
@@ -432,7 +443,7 @@ impl ChunkUploader {
                     chunk,
                     encryption.clone(),
                     Arc::clone(tracker),
-                    cancel_token.clone(),
+                    pool_cancel.clone(),
                     Arc::clone(&pool_state),
                     Arc::clone(&progress_state),
                     Arc::clone(&shared_session),
@@ -458,6 +469,14 @@ impl ChunkUploader {
 
         // Check if there was an error
         if let Some(error_msg) = pool_state.get_error() {
+            if shared_session.is_expired() && !cancel_token.is_cancelled() {
+                warn!(
+                    target: "uploader::chunk",
+                    error = %error_msg,
+                    "Chunk upload failed after the session expired"
+                );
+                return Err(UploadError::SessionExpired.into());
+            }
             error!(
                 target: "uploader::chunk",
                 error = %error_msg,
@@ -466,6 +485,16 @@ impl ChunkUploader {
             return Err(anyhow::anyhow!("Upload failed: {}", error_msg));
         }
 
+        if pool_state.session_expiring() {
+            info!(
+                target: "uploader::chunk",
+                expires_at = session.expires_at,
+                remaining = session.pending_chunks().len(),
+                "Upload session about to expire, pausing for renewal"
+            );
+            return Err(UploadError::SessionExpired.into());
+        }
+
         info!(
             target: "uploader::chunk",
             "All chunks uploaded successfully"
@@ -502,6 +531,13 @@ impl ChunkUploader {
                     return;
                 }
 
+                // Leave the chunk for the renewed session
+                if session.expires_soon() {
+                    pool_state.set_session_expiring();
+                    pool_state.worker_done();
+                    return;
+                }
+
                 let chunk_index = chunk.index;
                 tracker.start_chunk();
 
@@ -592,6 +628,8 @@ struct UploadPoolState {
     error: Mutex<Option<String>>,
     /// Flag indicating an error occurred
     has_error: AtomicBool,
+    /// Flag indicating workers stopped because the session is about to expire
+    session_expiring: AtomicBool,
     /// Number of active workers
     active_workers: AtomicUsize,
     /// Notify when all workers are done
@@ -604,6 +642,7 @@ impl UploadPoolState {
             pending_chunks: Mutex::new(chunks),
             error: Mutex::new(None),
             has_error: AtomicBool::new(false),
+            session_expiring: AtomicBool::new(false),
             active_workers: AtomicUsize::new(0),
             all_done: Notify::new(),
         }
@@ -641,6 +680,16 @@ impl UploadPoolState {
         }
     }
 
+    /// Mark that the session needs renewal before more chunks can be uploaded
+    fn set_session_expiring(&self) {
+        self.session_expiring.store(true, Ordering::SeqCst);
+    }
+
+    /// Check if workers stopped because the session is about to expire
+    fn session_expiring(&self) -> bool {
+        self.session_expiring.load(Ordering::SeqCst)
+    }
+
     /// Get the error message if any
     fn get_error(&self) -> Option<String> {
         self.error.try_lock().ok().and_then(|e| e.clone())
@@ -722,8 +771,13 @@ async fn upload_chunk_with_retry(
             .await
             .map_err(|e| UploadError::FileReadError(format!("Failed to create stream: {}", e)))?;
 
-        // Wrap with progress tracking
-        let progress_stream = ProgressStream::new(inner_stream, Arc::clone(tracker));
+        // Hash what is actually sent so it can be checked against the provider digest
+        let digest_stream = DigestStream::new(inner_stream);
+        let digest = digest_stream.digest_handle();
+
+        // Hold back to the policy bandwidth cap, then track progress
+        let throttled_stream = Throttled::new(digest_stream, bandwidth::upload_limiter());
+        let progress_stream = ProgressStream::new(throttled_stream, Arc::clone(tracker));
         // Capture bytes counter before stream is consumed
         let bytes_sent_counter = progress_stream.bytes_sent_counter();
 
@@ -736,15 +790,17 @@ async fn upload_chunk_with_retry(
             session.as_ref(),
         )
         .await
+        .and_then(|receipt| verify_chunk_digest(chunk, receipt, &digest))
         {
-            Ok(etag) => {
+            Ok((receipt, verified)) => {
                 debug!(
                     target: "uploader::chunk",
                     chunk = chunk.index,
-                    etag = ?etag,
+                    etag = ?receipt.etag,
+                    verified,
                     "Chunk uploaded successfully"
                 );
-                return Ok(etag);
+                return Ok(receipt.etag);
             }
             Err(e) => {
                 // Use the captured counter to get bytes sent after stream was consumed
@@ -773,3 +829,43 @@ async fn upload_chunk_with_retry(
 
     Err(anyhow::anyhow!("Chunk upload failed, max retries exceeded"))
 }
+
+/// Check the digest reported by the provider against what was sent, and
+/// return whether the chunk was verified. A mismatch fails the attempt so the
+/// chunk is uploaded again. An ETag that only looks like a digest verifies the
+/// chunk if it matches, and leaves it unverified otherwise.
+fn verify_chunk_digest(
+    chunk: &ChunkInfo,
+    receipt: ChunkReceipt,
+    digest: &ChunkDigest,
+) -> Result<(ChunkReceipt, bool)> {
+    let Some(remote_md5) = receipt.md5.as_deref() else {
+        let verified = receipt.etag_md5.as_deref() == Some(digest.hex().as_str());
+        if verified {
+            IntegrityStats::record_chunk_verified();
+        } else if receipt.etag_md5.is_some() {
+            debug!(
+                target: "uploader::chunk",
+                chunk = chunk.index,
+                "ETag is not the MD5 of the chunk, e.g. on an encrypted bucket, left unverified"
+            );
+        }
+        return Ok((receipt, verified));
+    };
+
+    let local_md5 = digest.hex();
+    if remote_md5 != local_md5 {
+        IntegrityStats::record_chunk_mismatch();
+        return Err(UploadError::chunk_failed(
+            chunk.index,
+            format!(
+                "checksum mismatch: provider reported {}, sent {}",
+                remote_md5, local_md5
+            ),
+        )
+        .into());
+    }
+
+    IntegrityStats::record_chunk_verified();
+    Ok((receipt, true))
+}
@@ -0,0 +1,200 @@
+//! Compressed uploads for text-like files
+//!
+//! Cloudreve stores uploaded bytes as they are, so a file cannot simply be sent
+//! compressed. Instead the file is packed into a single-entry zip archive, which
+//! is uploaded to a staging folder and extracted into place by the server's
+//! archive workflow.
+
+use anyhow::{Context, Result};
+use chrono::{Datelike, Local, Timelike};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+static FILES_COMPRESSED: AtomicU64 = AtomicU64::new(0);
+static BYTES_SAVED: AtomicU64 = AtomicU64::new(0);
+
+/// Only use the archive if it is at most this share of the original size,
+/// as the extraction round trip costs more than it saves otherwise
+pub const MAX_COMPRESSION_RATIO: f64 = 0.8;
+
+/// Compressed upload counters since the app started
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct CompressionStats {
+    /// Files uploaded as a compressed archive
+    pub files_compressed: u64,
+    /// Bytes not sent thanks to compression
+    pub bytes_saved: u64,
+}
+
+impl CompressionStats {
+    /// Current counter values
+    pub fn snapshot() -> Self {
+        Self {
+            files_compressed: FILES_COMPRESSED.load(Ordering::Relaxed),
+            bytes_saved: BYTES_SAVED.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn record_compressed(original_size: u64, compressed_size: u64) {
+        FILES_COMPRESSED.fetch_add(1, Ordering::Relaxed);
+        BYTES_SAVED.fetch_add(original_size.saturating_sub(compressed_size), Ordering::Relaxed);
+    }
+}
+
+fn default_extensions() -> Vec<String> {
+    [
+        "txt", "csv", "tsv", "log", "json", "xml", "html", "htm", "css", "js", "md", "svg",
+        "sql", "yaml", "yml", "ini",
+    ]
+    .iter()
+    .map(|ext| ext.to_string())
+    .collect()
+}
+
+fn default_min_size() -> u64 {
+    1024 * 1024
+}
+
+/// Per-drive settings for compressed uploads
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompressionSettings {
+    /// Whether new files matching the allowlist are uploaded compressed
+    #[serde(default)]
+    pub enabled: bool,
+    /// Extensions (without the dot) of files worth compressing
+    #[serde(default = "default_extensions")]
+    pub extensions: Vec<String>,
+    /// Files smaller than this many bytes are uploaded as they are
+    #[serde(default = "default_min_size")]
+    pub min_size: u64,
+}
+
+impl Default for CompressionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            extensions: default_extensions(),
+            min_size: default_min_size(),
+        }
+    }
+}
+
+impl CompressionSettings {
+    /// Whether a file of `size` bytes at `path` should be uploaded compressed
+    pub fn applies_to(&self, path: &Path, size: u64) -> bool {
+        if !self.enabled || size < self.min_size {
+            return false;
+        }
+        let Some(ext) = path.extension().and_then(|ext| ext.to_str()) else {
+            return false;
+        };
+        self.extensions
+            .iter()
+            .any(|allowed| allowed.trim_start_matches('.').eq_ignore_ascii_case(ext))
+    }
+}
+
+/// Modification time of a zip entry, in local time like the archive format
+/// expects. `None` outside of the years a zip entry can hold.
+fn zip_time(modified: SystemTime) -> Option<zip::DateTime> {
+    let local = chrono::DateTime::<Local>::from(modified);
+    zip::DateTime::from_date_and_time(
+        u16::try_from(local.year()).ok()?,
+        local.month() as u8,
+        local.day() as u8,
+        local.hour() as u8,
+        local.minute() as u8,
+        local.second() as u8,
+    )
+    .ok()
+}
+
+/// Pack `src` into a zip archive at `dst` holding a single entry named
+/// `entry_name`, with the modification time of `src`. Returns the size of
+/// the archive.
+pub async fn compress_to_zip(src: &Path, entry_name: &str, dst: &Path) -> Result<u64> {
+    let src = src.to_path_buf();
+    let dst = dst.to_path_buf();
+    let entry_name = entry_name.to_string();
+
+    tokio::task::spawn_blocking(move || -> Result<u64> {
+        let mut input = File::open(&src)
+            .with_context(|| format!("failed to open {} for compression", src.display()))?;
+        let metadata = input.metadata()?;
+        let size = metadata.len();
+
+        let output = File::create(&dst)
+            .with_context(|| format!("failed to create archive {}", dst.display()))?;
+        let mut zip = zip::ZipWriter::new(output);
+        let mut options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated)
+            .large_file(size >= u32::MAX as u64);
+        // The server gives the extracted file the time of its entry
+        if let Some(modified) = metadata.modified().ok().and_then(zip_time) {
+            options = options.last_modified_time(modified);
+        }
+        zip.start_file(entry_name, options)
+            .context("failed to start archive entry")?;
+        io::copy(&mut input, &mut zip).context("failed to compress file")?;
+        zip.finish().context("failed to finish archive")?;
+
+        Ok(std::fs::metadata(&dst)?.len())
+    })
+    .await
+    .context("compression task panicked")?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_to_allowlisted_extensions_above_threshold() {
+        let settings = CompressionSettings {
+            enabled: true,
+            ..Default::default()
+        };
+        let min = settings.min_size;
+        assert!(settings.applies_to(Path::new("C:/data/report.CSV"), min));
+        assert!(!settings.applies_to(Path::new("C:/data/report.csv"), min - 1));
+        assert!(!settings.applies_to(Path::new("C:/data/photo.jpg"), min));
+        assert!(!settings.applies_to(Path::new("C:/data/Makefile"), min));
+    }
+
+    #[tokio::test]
+    async fn archive_entry_keeps_the_modification_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("notes.txt");
+        std::fs::write(&src, "notes ".repeat(1000)).unwrap();
+        let modified = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        File::options()
+            .write(true)
+            .open(&src)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+
+        let dst = dir.path().join("notes.zip");
+        compress_to_zip(&src, "notes.txt", &dst).await.unwrap();
+        let mut archive = zip::ZipArchive::new(File::open(&dst).unwrap()).unwrap();
+        let entry = archive.by_index(0).unwrap();
+        assert_eq!(entry.name(), "notes.txt");
+        assert_eq!(entry.last_modified(), zip_time(modified));
+    }
+
+    #[test]
+    fn zip_time_is_limited_to_the_zip_years() {
+        assert!(zip_time(SystemTime::UNIX_EPOCH).is_none());
+        assert!(zip_time(SystemTime::now()).is_some());
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let settings = CompressionSettings::default();
+        assert!(!settings.applies_to(Path::new("notes.txt"), u64::MAX));
+    }
+}
@@ -4,7 +4,7 @@ use crate::uploader::error::{UploadError, UploadResult};
 use aes::Aes256;
 use aes::cipher::{KeyIvInit, StreamCipher};
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
-use cloudreve_api::models::explorer::EncryptMetadata;
+use cloudreve_api::models::explorer::{EncryptMetadata, EncryptionCipher};
 use ctr::Ctr128BE;
 
 type Aes256Ctr = Ctr128BE<Aes256>;
@@ -21,6 +21,13 @@ pub struct EncryptionConfig {
 impl EncryptionConfig {
     /// Create encryption config from Cloudreve's encrypt metadata
     pub fn from_metadata(metadata: &EncryptMetadata) -> UploadResult<Self> {
+        if !matches!(metadata.algorithm, EncryptionCipher::Aes256Ctr) {
+            return Err(UploadError::EncryptionError(format!(
+                "Unsupported cipher: {:?}",
+                metadata.algorithm
+            )));
+        }
+
         let key_bytes = BASE64
             .decode(&metadata.key_plain_text)
             .map_err(|e| UploadError::EncryptionError(format!("Invalid key: {}", e)))?;
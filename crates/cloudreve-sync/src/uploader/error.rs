@@ -103,6 +103,11 @@ impl UploadError {
         )
     }
 
+    /// Check if the upload session expired or is about to
+    pub fn is_session_expired(&self) -> bool {
+        matches!(self, UploadError::SessionExpired)
+    }
+
     /// Check if this error is due to cancellation
     pub fn is_cancelled(&self) -> bool {
         matches!(self, UploadError::Cancelled)
@@ -0,0 +1,162 @@
+//! Transfer integrity checks for uploaded chunks and files
+
+use crate::uploader::providers::PolicyType;
+use bytes::Bytes;
+use futures::Stream;
+use md5::{Digest, Md5};
+use serde::Serialize;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context as TaskContext, Poll};
+
+static CHUNKS_VERIFIED: AtomicU64 = AtomicU64::new(0);
+static CHUNK_MISMATCHES: AtomicU64 = AtomicU64::new(0);
+static SIZE_MISMATCHES: AtomicU64 = AtomicU64::new(0);
+
+/// Integrity check counters since the app started
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct IntegrityStats {
+    /// Chunks whose provider digest matched the local one
+    pub chunks_verified: u64,
+    /// Chunks re-uploaded because the provider digest did not match
+    pub chunk_mismatches: u64,
+    /// Completed uploads whose remote size did not match the local file
+    pub size_mismatches: u64,
+}
+
+impl IntegrityStats {
+    /// Current counter values
+    pub fn snapshot() -> Self {
+        Self {
+            chunks_verified: CHUNKS_VERIFIED.load(Ordering::Relaxed),
+            chunk_mismatches: CHUNK_MISMATCHES.load(Ordering::Relaxed),
+            size_mismatches: SIZE_MISMATCHES.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn record_chunk_verified() {
+        CHUNKS_VERIFIED.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_chunk_mismatch() {
+        CHUNK_MISMATCHES.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_size_mismatch() {
+        SIZE_MISMATCHES.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// What the storage provider reported after accepting a chunk
+#[derive(Debug, Clone, Default)]
+pub struct ChunkReceipt {
+    /// ETag to keep for completing the upload
+    pub etag: Option<String>,
+    /// Lowercase hex MD5 of the stored bytes, if the provider reports one
+    pub md5: Option<String>,
+    /// The ETag, if it may be the MD5 of the stored bytes. Only a match
+    /// verifies the chunk, anything else leaves it unverified.
+    pub etag_md5: Option<String>,
+}
+
+impl ChunkReceipt {
+    /// Build a receipt from a part ETag. S3-like providers use the MD5 of the
+    /// part as its ETag on plain buckets, but buckets encrypting with SSE-KMS
+    /// or SSE-C use 32 hex digits that are not one, so the ETag can only
+    /// confirm a chunk, never reject it.
+    pub fn from_etag(policy_type: PolicyType, etag: Option<String>) -> Self {
+        let etag_md5 = etag
+            .as_deref()
+            .filter(|_| policy_type.is_s3_like())
+            .and_then(normalize_md5);
+        Self {
+            etag,
+            md5: None,
+            etag_md5,
+        }
+    }
+}
+
+/// Normalize a hex MD5 digest, returning `None` if it is not one
+pub fn normalize_md5(value: &str) -> Option<String> {
+    let value = value.trim().trim_matches('"');
+    (value.len() == 32 && value.chars().all(|c| c.is_ascii_hexdigit()))
+        .then(|| value.to_ascii_lowercase())
+}
+
+/// Stream wrapper computing the MD5 of the bytes actually sent
+pub struct DigestStream<S> {
+    inner: S,
+    hasher: Arc<Mutex<Md5>>,
+}
+
+impl<S> DigestStream<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            hasher: Arc::new(Mutex::new(Md5::new())),
+        }
+    }
+
+    /// Handle to the digest that remains valid after the stream is consumed
+    pub fn digest_handle(&self) -> ChunkDigest {
+        ChunkDigest(Arc::clone(&self.hasher))
+    }
+}
+
+impl<S> Stream for DigestStream<S>
+where
+    S: Stream<Item = Result<Bytes, io::Error>> + Unpin,
+{
+    type Item = Result<Bytes, io::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Option<Self::Item>> {
+        let poll = Pin::new(&mut self.inner).poll_next(cx);
+        if let Poll::Ready(Some(Ok(bytes))) = &poll {
+            self.hasher.lock().unwrap().update(bytes);
+        }
+        poll
+    }
+}
+
+/// MD5 of a chunk collected by a [`DigestStream`]
+pub struct ChunkDigest(Arc<Mutex<Md5>>);
+
+impl ChunkDigest {
+    /// Lowercase hex digest of everything streamed so far
+    pub fn hex(&self) -> String {
+        let digest = self.0.lock().unwrap().clone().finalize();
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_quoted_etags() {
+        assert_eq!(
+            normalize_md5("\"9E107D9D372BB6826BD81D3542A419D6\"").as_deref(),
+            Some("9e107d9d372bb6826bd81d3542a419d6")
+        );
+        // Multipart and provider specific ETags are not digests
+        assert_eq!(normalize_md5("9e107d9d372bb6826bd81d3542a419d6-2"), None);
+        assert_eq!(normalize_md5("FmDZwqadA4-ib_15hYfQpb7UXUYR"), None);
+    }
+
+    #[test]
+    fn only_s3_like_etags_may_be_digests() {
+        let etag = Some("9e107d9d372bb6826bd81d3542a419d6".to_string());
+        let receipt = ChunkReceipt::from_etag(PolicyType::S3, etag.clone());
+        assert!(receipt.etag_md5.is_some());
+        assert!(receipt.md5.is_none());
+        assert!(
+            ChunkReceipt::from_etag(PolicyType::OneDrive, etag)
+                .etag_md5
+                .is_none()
+        );
+    }
+}
@@ -4,20 +4,30 @@
 //! multiple storage providers, encryption, resumable uploads, and progress tracking.
 
 mod chunk;
+mod compression;
 mod encrypt;
 mod error;
+mod integrity;
 mod progress;
 mod providers;
 mod session;
 
 use anyhow::{Context, Result};
 pub use chunk::{ChunkProgress, ChunkUploader};
+pub use compression::{
+    CompressionSettings, CompressionStats, MAX_COMPRESSION_RATIO, compress_to_zip,
+};
 pub use error::{UploadError, UploadResult};
+pub use integrity::IntegrityStats;
 pub use progress::{ProgressCallback, ProgressUpdate};
 pub use session::UploadSession;
 
 use crate::inventory::InventoryDb;
-use cloudreve_api::{Client as CrClient, api::ExplorerApi};
+use cloudreve_api::{
+    Client as CrClient,
+    api::ExplorerApi,
+    models::explorer::{UploadCredential, UploadSessionRequest},
+};
 use reqwest::Client as HttpClient;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -25,6 +35,9 @@ use std::time::Duration;
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, warn};
 
+/// Maximum number of times an expiring session is renewed during one upload
+const MAX_SESSION_RENEWALS: u32 = 5;
+
 /// Configuration for the uploader
 #[derive(Debug, Clone)]
 pub struct UploaderConfig {
@@ -36,6 +49,8 @@ pub struct UploaderConfig {
     pub retry_max_delay: Duration,
     /// Request timeout for chunk uploads
     pub request_timeout: Duration,
+    /// Cap on the storage policy's chunk concurrency
+    pub max_chunk_concurrency: Option<usize>,
 }
 
 impl Default for UploaderConfig {
@@ -45,6 +60,7 @@ impl Default for UploaderConfig {
             retry_base_delay: Duration::from_secs(1),
             retry_max_delay: Duration::from_secs(30),
             request_timeout: Duration::from_secs(60),
+            max_chunk_concurrency: None,
         }
     }
 }
@@ -93,7 +109,11 @@ impl Uploader {
         inventory: Arc<InventoryDb>,
         config: UploaderConfig,
     ) -> Self {
-        let http_client = HttpClient::builder()
+        // Storage providers get the connection settings of the drive too, and
+        // the server its certificate pin when uploads go through it
+        let http_client = cr_client
+            .config()
+            .http_client_builder()
             .connect_timeout(config.request_timeout)
             .build()
             .expect("Failed to create HTTP client");
@@ -121,6 +141,7 @@ impl Uploader {
     /// - Uploading chunks with retries
     /// - Reporting progress
     /// - Persisting state for resumability
+    /// - Renewing the session before it expires
     /// - Completing the upload
     pub async fn upload<P: ProgressCallback + 'static>(
         &self,
@@ -163,19 +184,34 @@ impl Uploader {
             }
         };
 
-        // Create chunk uploader based on policy type
-        let chunk_uploader = self.create_chunk_uploader(&session)?;
-
-        // Upload all chunks
+        // Upload all chunks, renewing the session whenever it is about to expire
         let progress = Arc::new(progress);
-        let result = chunk_uploader
-            .upload_all(
-                &params.local_path,
-                &mut session,
-                progress,
-                &self.cancel_token,
-            )
-            .await;
+        let mut renewals = 0;
+        let result = loop {
+            // Create chunk uploader based on policy type
+            let chunk_uploader = self.create_chunk_uploader(&session)?;
+            let result = chunk_uploader
+                .upload_all(
+                    &params.local_path,
+                    &mut session,
+                    Arc::clone(&progress),
+                    &self.cancel_token,
+                )
+                .await;
+
+            let expired = result.as_ref().err().is_some_and(|e| {
+                e.downcast_ref::<UploadError>()
+                    .is_some_and(UploadError::is_session_expired)
+            });
+            if !expired || renewals >= MAX_SESSION_RENEWALS {
+                break result;
+            }
+
+            renewals += 1;
+            if let Err(e) = self.renew_session(&params, &mut session).await {
+                break Err(e);
+            }
+        };
 
         match result {
             Ok(()) => {
@@ -262,11 +298,9 @@ impl Uploader {
         }
     }
 
-    /// Create a new upload session via Cloudreve API
-    async fn create_session(&self, params: &UploadParams) -> Result<UploadSession> {
-        use cloudreve_api::models::explorer::UploadSessionRequest;
-
-        let request = UploadSessionRequest {
+    /// Build the session request for an upload
+    fn session_request(params: &UploadParams) -> UploadSessionRequest {
+        UploadSessionRequest {
             uri: params.remote_uri.clone(),
             size: params.file_size as i64,
             policy_id: "".to_string(),
@@ -286,13 +320,17 @@ impl Uploader {
             encryption_supported: Some(vec![
                 cloudreve_api::models::explorer::EncryptionCipher::Aes256Ctr,
             ]),
-        };
+        }
+    }
 
+    /// Create a new upload session via Cloudreve API
+    async fn create_session(&self, params: &UploadParams) -> Result<UploadSession> {
         let credential = self
             .cr_client
-            .create_upload_session(&request)
+            .create_upload_session(&Self::session_request(params))
             .await
             .context("failed to create upload session")?;
+        let credential = self.with_local_expiry(credential).await;
 
         debug!(
             target: "uploader",
@@ -319,6 +357,88 @@ impl Uploader {
         Ok(session)
     }
 
+    /// Sessions are checked for expiry against the local clock, so shift the
+    /// server-issued expiry by the server's clock skew
+    async fn with_local_expiry(&self, mut credential: UploadCredential) -> UploadCredential {
+        credential.expires = self
+            .cr_client
+            .server_to_local_timestamp(credential.expires)
+            .await;
+        credential
+    }
+
+    /// Renew a session that is about to expire.
+    ///
+    /// Cloudreve has no dedicated extension endpoint, so the session is
+    /// requested again for the same file. If the server extends the same
+    /// session, the uploaded chunks are kept. Otherwise the old session is
+    /// discarded and the upload continues from the first chunk in the new one.
+    async fn renew_session(
+        &self,
+        params: &UploadParams,
+        session: &mut UploadSession,
+    ) -> Result<()> {
+        let old = session.clone();
+        let mut old_deleted = false;
+
+        let credential = match self
+            .cr_client
+            .create_upload_session(&Self::session_request(params))
+            .await
+        {
+            Ok(credential) => credential,
+            Err(e) => {
+                // The server may refuse a second session while the old one
+                // still holds the file
+                debug!(
+                    target: "uploader",
+                    session_id = %old.session_id(),
+                    error = %e,
+                    "Session renewal refused, replacing the old session"
+                );
+                if let Err(e) = self.delete_remote_session(&old).await {
+                    warn!(
+                        target: "uploader",
+                        session_id = %old.session_id(),
+                        error = %e,
+                        "Failed to delete expiring upload session"
+                    );
+                }
+                old_deleted = true;
+                self.cr_client
+                    .create_upload_session(&Self::session_request(params))
+                    .await
+                    .context("failed to renew upload session")?
+            }
+        };
+
+        let kept = session.renew(self.with_local_expiry(credential).await);
+        if !kept && !old_deleted {
+            if let Err(e) = self.delete_remote_session(&old).await {
+                warn!(
+                    target: "uploader",
+                    session_id = %old.session_id(),
+                    error = %e,
+                    "Failed to delete replaced upload session"
+                );
+            }
+        }
+
+        self.cleanup_session(&old).await?;
+        self.inventory
+            .insert_upload_session(session)
+            .map_err(|e| UploadError::DatabaseError(e.to_string()))?;
+
+        info!(
+            target: "uploader",
+            session_id = %session.session_id(),
+            expires_at = session.expires_at,
+            kept_chunks = kept,
+            "Upload session renewed"
+        );
+        Ok(())
+    }
+
     /// Create appropriate chunk uploader based on policy type
     fn create_chunk_uploader(&self, session: &UploadSession) -> UploadResult<ChunkUploader> {
         let policy_type = if session.is_relay() {
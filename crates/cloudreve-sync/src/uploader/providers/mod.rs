@@ -7,6 +7,7 @@ mod s3;
 mod upyun;
 
 use crate::uploader::chunk::ChunkInfo;
+use crate::uploader::integrity::ChunkReceipt;
 use crate::uploader::session::UploadSession;
 use anyhow::Result;
 use bytes::Bytes;
@@ -57,7 +58,7 @@ impl PolicyType {
             ApiPolicyType::S3 => PolicyType::S3,
             ApiPolicyType::Ks3 => PolicyType::Ks3,
             ApiPolicyType::Obs => PolicyType::Obs,
-            ApiPolicyType::LoadBalance => PolicyType::Local, // Fallback
+            ApiPolicyType::LoadBalance | ApiPolicyType::Unknown => PolicyType::Local, // Fallback
         }
     }
 
@@ -124,11 +125,11 @@ pub async fn upload_chunk_with_progress<S>(
     chunk: &ChunkInfo,
     stream: S,
     session: &UploadSession,
-) -> Result<Option<String>>
+) -> Result<ChunkReceipt>
 where
     S: Stream<Item = Result<Bytes, io::Error>> + Send + Sync + Unpin + 'static,
 {
-    match policy_type {
+    let etag = match policy_type {
         PolicyType::Local | PolicyType::Remote => {
             local::upload_chunk_generic(http_client, cr_client, chunk, stream, session).await
         }
@@ -141,9 +142,13 @@ where
         PolicyType::OneDrive => {
             onedrive::upload_chunk_generic(http_client, chunk, stream, session).await
         }
-        PolicyType::Qiniu => qiniu::upload_chunk_generic(http_client, chunk, stream, session).await,
+        // Qiniu reports the MD5 separately from its own ETag format
+        PolicyType::Qiniu => {
+            return qiniu::upload_chunk_generic(http_client, chunk, stream, session).await;
+        }
         PolicyType::Upyun => upyun::upload_chunk_generic(http_client, chunk, stream, session).await,
-    }
+    }?;
+    Ok(ChunkReceipt::from_etag(policy_type, etag))
 }
 
 /// Complete the upload for the appropriate provider
@@ -1,6 +1,7 @@
 //! Qiniu Cloud Storage upload implementation
 
 use crate::uploader::chunk::ChunkInfo;
+use crate::uploader::integrity::{ChunkReceipt, normalize_md5};
 use crate::uploader::session::UploadSession;
 use anyhow::{Context, Result, bail};
 use bytes::Bytes;
@@ -15,7 +16,6 @@ use tracing::debug;
 struct QiniuChunkResponse {
     etag: String,
     #[serde(default)]
-    #[allow(dead_code)]
     md5: String,
 }
 
@@ -48,7 +48,7 @@ pub async fn upload_chunk_generic<S>(
     chunk: &ChunkInfo,
     stream: S,
     session: &UploadSession,
-) -> Result<Option<String>>
+) -> Result<ChunkReceipt>
 where
     S: Stream<Item = Result<Bytes, io::Error>> + Send + Sync + Unpin + 'static,
 {
@@ -101,7 +101,11 @@ where
         .await
         .context("failed to parse Qiniu response")?;
 
-    Ok(Some(chunk_response.etag))
+    Ok(ChunkReceipt {
+        md5: normalize_md5(&chunk_response.md5),
+        etag: Some(chunk_response.etag),
+        etag_md5: None,
+    })
 }
 
 /// Complete Qiniu multipart upload
@@ -5,6 +5,15 @@ use crate::uploader::providers::PolicyType;
 use chrono::Utc;
 use cloudreve_api::models::explorer::{EncryptMetadata, UploadCredential};
 use serde::{Deserialize, Serialize};
+
+/// Renew a session at most this many seconds before it expires, so that a
+/// chunk started right before the deadline can still finish
+const MAX_RENEWAL_MARGIN_SECS: i64 = 600;
+
+/// Part of its lifetime before expiry a session is renewed at, so that short
+/// lived sessions are used for most of it
+const RENEWAL_MARGIN_DIVISOR: i64 = 10;
+
 /// Persisted upload session state
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UploadSession {
@@ -115,6 +124,48 @@ impl UploadSession {
         Utc::now().timestamp() >= self.expires_at
     }
 
+    /// Seconds before expiry the session is renewed at: a tenth of its
+    /// lifetime, up to [MAX_RENEWAL_MARGIN_SECS]
+    pub fn renewal_margin_secs(&self) -> i64 {
+        ((self.expires_at - self.created_at) / RENEWAL_MARGIN_DIVISOR)
+            .clamp(0, MAX_RENEWAL_MARGIN_SECS)
+    }
+
+    /// Check if the session is close enough to expiry to be renewed before
+    /// sending another chunk
+    pub fn expires_soon(&self) -> bool {
+        Utc::now().timestamp() + self.renewal_margin_secs() >= self.expires_at
+    }
+
+    /// Replace the credential with a renewed one. Returns whether the
+    /// uploaded chunks were kept: if the server handed back the same session
+    /// and upload at the storage provider, only its expiry moved and the
+    /// upload continues where it was. A new session starts over from the
+    /// first chunk.
+    pub fn renew(&mut self, credential: UploadCredential) -> bool {
+        let same_session = credential.session_id == self.credential.session_id
+            && credential.upload_id == self.credential.upload_id
+            && credential.chunk_size as u64 == self.chunk_size;
+        if same_session {
+            let now = Utc::now().timestamp();
+            self.expires_at = credential.expires;
+            self.created_at = now;
+            self.updated_at = now;
+            self.credential = credential;
+            return true;
+        }
+
+        *self = Self::new(
+            self.task_id.clone(),
+            self.drive_id.clone(),
+            self.local_path.clone(),
+            self.remote_uri.clone(),
+            self.file_size,
+            credential,
+        );
+        false
+    }
+
     /// Get total number of chunks
     pub fn num_chunks(&self) -> usize {
         self.chunk_progress.len()
@@ -280,3 +331,55 @@ mod policy_type_serde {
         Ok(PolicyType::from_str(&s))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn credential(session_id: &str, upload_id: &str, expires: i64) -> UploadCredential {
+        UploadCredential {
+            session_id: session_id.to_string(),
+            upload_id: upload_id.to_string(),
+            chunk_size: 10,
+            expires,
+            ..Default::default()
+        }
+    }
+
+    fn session() -> UploadSession {
+        let expires = Utc::now().timestamp() + 60;
+        let mut session = UploadSession::new(
+            "task".to_string(),
+            "drive".to_string(),
+            "C:\\Drive\\file.bin".to_string(),
+            "cloudreve://my/file.bin".to_string(),
+            25,
+            credential("session", "upload", expires),
+        );
+        session.complete_chunk(0, Some("etag".to_string()));
+        session
+    }
+
+    #[test]
+    fn renewing_the_same_session_keeps_uploaded_chunks() {
+        let mut session = session();
+        let expires = Utc::now().timestamp() + 3600;
+        assert!(session.renew(credential("session", "upload", expires)));
+        assert_eq!(session.expires_at, expires);
+        assert_eq!(session.pending_chunks(), vec![1, 2]);
+        assert_eq!(session.chunk_progress[0].etag.as_deref(), Some("etag"));
+    }
+
+    #[test]
+    fn a_new_session_starts_over() {
+        let expires = Utc::now().timestamp() + 3600;
+        for renewed in [
+            credential("other", "upload", expires),
+            credential("session", "other", expires),
+        ] {
+            let mut session = session();
+            assert!(!session.renew(renewed));
+            assert_eq!(session.pending_chunks(), vec![0, 1, 2]);
+        }
+    }
+}
@@ -0,0 +1,33 @@
+//! Correlation ids tying the work done for a user action together.
+//!
+//! Each action the user starts (a context menu click, a command from the
+//! app) gets a fresh id. The id follows the action through the command
+//! queues, the tasks it dispatches and the tasks spawned for it, is sent to
+//! the server with every API request and is logged with the events it
+//! raises, so the action can be traced end to end.
+
+use std::future::Future;
+
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+pub use cloudreve_api::correlation::{current, scope, set_current};
+
+/// A fresh correlation id
+pub fn new_id() -> String {
+    Uuid::new_v4().to_string()
+}
+
+/// Run `future` as a new user action with a fresh correlation id
+pub async fn user_action<F: Future>(future: F) -> F::Output {
+    scope(Some(new_id()), future).await
+}
+
+/// Spawn `future` as a task carrying on the current correlation id
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    tokio::spawn(scope(current(), future))
+}
@@ -0,0 +1,178 @@
+//! Locale-aware formatting helpers for user-facing strings (toasts, status UI).
+
+use std::time::Duration;
+
+/// CLDR plural category used to pick a translation variant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluralCategory {
+    One,
+    Few,
+    Many,
+    Other,
+}
+
+impl PluralCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PluralCategory::One => "one",
+            PluralCategory::Few => "few",
+            PluralCategory::Many => "many",
+            PluralCategory::Other => "other",
+        }
+    }
+}
+
+/// Language part of a locale tag (e.g. "zh" for "zh-TW")
+fn language(locale: &str) -> &str {
+    locale.split(['-', '_']).next().unwrap_or(locale)
+}
+
+/// Plural category of `count` for the given locale (integer rules only).
+pub fn plural_category(locale: &str, count: u64) -> PluralCategory {
+    match language(locale) {
+        // No grammatical plural
+        "zh" | "ja" | "ko" => PluralCategory::Other,
+        // 0 and 1 are singular
+        "fr" => {
+            if count <= 1 {
+                PluralCategory::One
+            } else {
+                PluralCategory::Other
+            }
+        }
+        "ru" => {
+            let (m10, m100) = (count % 10, count % 100);
+            if m10 == 1 && m100 != 11 {
+                PluralCategory::One
+            } else if (2..=4).contains(&m10) && !(12..=14).contains(&m100) {
+                PluralCategory::Few
+            } else {
+                PluralCategory::Many
+            }
+        }
+        "pl" => {
+            let (m10, m100) = (count % 10, count % 100);
+            if count == 1 {
+                PluralCategory::One
+            } else if (2..=4).contains(&m10) && !(12..=14).contains(&m100) {
+                PluralCategory::Few
+            } else {
+                PluralCategory::Many
+            }
+        }
+        _ => {
+            if count == 1 {
+                PluralCategory::One
+            } else {
+                PluralCategory::Other
+            }
+        }
+    }
+}
+
+/// Translation key for the plural variant of `base` in the active locale,
+/// e.g. `tasksInProgress.few`.
+///
+/// Each locale must define the categories its language uses.
+pub fn plural_key(base: &str, count: u64) -> String {
+    let category = plural_category(&rust_i18n::locale(), count);
+    format!("{}.{}", base, category.as_str())
+}
+
+/// Translate a pluralized `%{count}` message, e.g. `plural("conflictCount", 3)`.
+pub fn plural(base: &str, count: u64) -> String {
+    t!(plural_key(base, count).as_str(), "count" => count).to_string()
+}
+
+/// Decimal separator for the given locale
+fn decimal_separator(locale: &str) -> char {
+    match language(locale) {
+        "de" | "fr" | "es" | "it" | "ru" | "pl" => ',',
+        _ => '.',
+    }
+}
+
+/// Format a float with one decimal place using the locale's decimal separator
+fn format_decimal(value: f64, locale: &str) -> String {
+    let formatted = format!("{:.1}", value);
+    match decimal_separator(locale) {
+        '.' => formatted,
+        sep => formatted.replace('.', &sep.to_string()),
+    }
+}
+
+/// Format bytes into a human-readable string (e.g., "1.5 GB" / "1,5 GB") for the given locale
+pub fn format_bytes_in(bytes: i64, locale: &str) -> String {
+    const KB: f64 = 1024.0;
+    const MB: f64 = KB * 1024.0;
+    const GB: f64 = MB * 1024.0;
+    const TB: f64 = GB * 1024.0;
+
+    let bytes_f = bytes as f64;
+
+    let (value, unit) = if bytes_f >= TB {
+        (bytes_f / TB, "TB")
+    } else if bytes_f >= GB {
+        (bytes_f / GB, "GB")
+    } else if bytes_f >= MB {
+        (bytes_f / MB, "MB")
+    } else if bytes_f >= KB {
+        (bytes_f / KB, "KB")
+    } else {
+        return format!("{} B", bytes);
+    };
+
+    format!("{} {}", format_decimal(value, locale), unit)
+}
+
+/// Format bytes into a human-readable string using the active locale
+pub fn format_bytes(bytes: i64) -> String {
+    format_bytes_in(bytes, &rust_i18n::locale())
+}
+
+/// Format a percentage with one decimal place using the active locale
+pub fn format_percentage(value: f64) -> String {
+    format!("{}%", format_decimal(value, &rust_i18n::locale()))
+}
+
+/// Format a duration into a short localized string (e.g., "2 h 5 min")
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let hours = secs / 3600;
+    let minutes = (secs % 3600) / 60;
+    let seconds = secs % 60;
+
+    if hours > 0 {
+        t!("durationHoursMinutes", "hours" => hours, "minutes" => minutes).to_string()
+    } else if minutes > 0 {
+        t!("durationMinutesSeconds", "minutes" => minutes, "seconds" => seconds).to_string()
+    } else {
+        t!("durationSeconds", "seconds" => seconds).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plural_category() {
+        assert_eq!(plural_category("en-US", 1), PluralCategory::One);
+        assert_eq!(plural_category("en-US", 0), PluralCategory::Other);
+        assert_eq!(plural_category("fr", 0), PluralCategory::One);
+        assert_eq!(plural_category("zh-CN", 1), PluralCategory::Other);
+        assert_eq!(plural_category("ru", 21), PluralCategory::One);
+        assert_eq!(plural_category("ru", 23), PluralCategory::Few);
+        assert_eq!(plural_category("ru", 12), PluralCategory::Many);
+        assert_eq!(plural_category("pl", 22), PluralCategory::Few);
+        assert_eq!(plural_category("pl", 25), PluralCategory::Many);
+    }
+
+    #[test]
+    fn test_format_bytes_in() {
+        assert_eq!(format_bytes_in(512, "en-US"), "512 B");
+        assert_eq!(format_bytes_in(1536, "en-US"), "1.5 KB");
+        assert_eq!(format_bytes_in(1536, "de"), "1,5 KB");
+        assert_eq!(format_bytes_in(3 * 1024 * 1024 * 1024, "zh-CN"), "3.0 GB");
+    }
+}
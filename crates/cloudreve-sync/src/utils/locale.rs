@@ -0,0 +1,136 @@
+/// Locale used when neither the requested locale nor its language is available
+pub const DEFAULT_LOCALE: &str = "en-US";
+
+/// List the locales embedded from the `locales/` translation files.
+pub fn available_locales() -> Vec<String> {
+    let mut locales: Vec<String> = rust_i18n::available_locales!()
+        .into_iter()
+        .map(|l| l.to_string())
+        .collect();
+    locales.sort();
+    locales
+}
+
+/// Regional locale a request of a language falls back to when several of its
+/// regions are available, by lowercase tag prefix. More specific prefixes come
+/// first.
+const REGION_FALLBACKS: &[(&str, &str)] = &[
+    ("zh-hant", "zh-TW"),
+    ("zh-hk", "zh-TW"),
+    ("zh-mo", "zh-TW"),
+    ("zh", "zh-CN"),
+];
+
+/// Resolve a requested locale against the available ones.
+///
+/// Fallback chain: exact match (case-insensitive, `_` treated as `-`), then the
+/// region listed in [`REGION_FALLBACKS`] (e.g. `zh` -> `zh-CN`, `zh-HK` ->
+/// `zh-TW`), then the bare language, then the first other locale of the
+/// language in sort order, then `en-US`. The result does not depend on the
+/// order of `available`.
+pub fn resolve_locale<S: AsRef<str>>(requested: &str, available: &[S]) -> String {
+    let requested = requested.trim().replace('_', "-");
+
+    if let Some(exact) = available
+        .iter()
+        .find(|l| l.as_ref().eq_ignore_ascii_case(&requested))
+    {
+        return exact.as_ref().to_string();
+    }
+
+    let lowercase = requested.to_ascii_lowercase();
+    let regional = REGION_FALLBACKS
+        .iter()
+        .find(|(prefix, _)| lowercase == *prefix || lowercase.starts_with(&format!("{prefix}-")))
+        .and_then(|(_, locale)| {
+            available
+                .iter()
+                .find(|l| l.as_ref().eq_ignore_ascii_case(locale))
+        });
+    if let Some(locale) = regional {
+        return locale.as_ref().to_string();
+    }
+
+    let language = requested.split('-').next().unwrap_or_default();
+    if !language.is_empty() {
+        let same_language = available
+            .iter()
+            .map(AsRef::as_ref)
+            .filter(|l| {
+                l.split('-')
+                    .next()
+                    .map(|lang| lang.eq_ignore_ascii_case(language))
+                    .unwrap_or(false)
+            })
+            // The bare language sorts before its regions
+            .min_by_key(|l| l.to_ascii_lowercase());
+        if let Some(locale) = same_language {
+            return locale.to_string();
+        }
+    }
+
+    DEFAULT_LOCALE.to_string()
+}
+
+/// Resolve and apply a locale for all translated strings.
+///
+/// Shell extension strings are looked up on each menu build, so they pick up the
+/// change without a restart. Returns the locale that was applied.
+pub fn apply_locale(requested: &str) -> String {
+    let locale = resolve_locale(requested, &available_locales());
+    rust_i18n::set_locale(&locale);
+    tracing::info!(target: "i18n", requested = %requested, locale = %locale, "Locale applied");
+    locale
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AVAILABLE: &[&str] = &["de", "en-US", "zh-CN", "zh-TW"];
+
+    #[test]
+    fn test_exact_match() {
+        assert_eq!(resolve_locale("zh-TW", AVAILABLE), "zh-TW");
+        assert_eq!(resolve_locale("zh_tw", AVAILABLE), "zh-TW");
+    }
+
+    #[test]
+    fn test_language_fallback() {
+        assert_eq!(resolve_locale("de-AT", AVAILABLE), "de");
+        assert_eq!(resolve_locale("zh-HK", AVAILABLE), "zh-TW");
+        assert_eq!(resolve_locale("zh-Hant-HK", AVAILABLE), "zh-TW");
+        assert_eq!(resolve_locale("zh-SG", AVAILABLE), "zh-CN");
+        assert_eq!(resolve_locale("en-GB", AVAILABLE), "en-US");
+    }
+
+    #[test]
+    fn test_base_language() {
+        assert_eq!(resolve_locale("zh", AVAILABLE), "zh-CN");
+        assert_eq!(resolve_locale("ZH", AVAILABLE), "zh-CN");
+        assert_eq!(resolve_locale("de", AVAILABLE), "de");
+        assert_eq!(resolve_locale("en", AVAILABLE), "en-US");
+    }
+
+    #[test]
+    fn test_fallback_ignores_available_order() {
+        let reversed: Vec<&str> = AVAILABLE.iter().rev().copied().collect();
+        for requested in ["zh", "zh-HK", "zh-SG", "de-CH", "en"] {
+            assert_eq!(
+                resolve_locale(requested, &reversed),
+                resolve_locale(requested, AVAILABLE)
+            );
+        }
+        // Without a listed region, the first regional locale in sort order wins
+        assert_eq!(resolve_locale("fr", &["fr-FR", "fr-CA"]), "fr-CA");
+        assert_eq!(resolve_locale("zh", &["zh-TW"]), "zh-TW");
+    }
+
+    #[test]
+    fn test_default_fallback() {
+        assert_eq!(resolve_locale("pt-BR", AVAILABLE), "en-US");
+        assert_eq!(resolve_locale("pt", AVAILABLE), "en-US");
+        assert_eq!(resolve_locale("xx-YY", AVAILABLE), "en-US");
+        assert_eq!(resolve_locale("", AVAILABLE), "en-US");
+    }
+}
@@ -1,2 +1,7 @@
 pub mod app;
+pub mod correlation;
+pub mod file;
+pub mod format;
+pub mod locale;
+pub mod network;
 pub mod toast;
@@ -0,0 +1,143 @@
+//! Binding a drive's connections to a network adapter.
+//!
+//! A drive can be bound by adapter name (e.g. `Ethernet`) or by source IP
+//! address. An adapter name is resolved to one of its addresses when the
+//! drive starts, as connections can only be bound to an address on Windows.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use anyhow::{Result, anyhow};
+use cloudreve_api::IpStrategy;
+use widestring::U16CStr;
+use windows::Win32::Foundation::{ERROR_BUFFER_OVERFLOW, ERROR_SUCCESS};
+use windows::Win32::NetworkManagement::IpHelper::{
+    GAA_FLAG_SKIP_ANYCAST, GAA_FLAG_SKIP_DNS_SERVER, GAA_FLAG_SKIP_MULTICAST, GetAdaptersAddresses,
+    IP_ADAPTER_ADDRESSES_LH,
+};
+use windows::Win32::NetworkManagement::Ndis::IfOperStatusUp;
+use windows::Win32::Networking::WinSock::{
+    AF_INET, AF_INET6, AF_UNSPEC, SOCKADDR_IN, SOCKADDR_IN6,
+};
+
+/// Source address for connections bound to `binding`, an IP address or the
+/// name of a network adapter that is up
+pub fn resolve_bind_address(binding: &str, strategy: IpStrategy) -> Result<IpAddr> {
+    let binding = binding.trim();
+    if let Ok(addr) = binding.parse::<IpAddr>() {
+        return Ok(addr);
+    }
+    let addresses = adapter_addresses(binding)?;
+    pick_address(&addresses, strategy)
+        .ok_or_else(|| anyhow!("Network adapter {} has no usable address", binding))
+}
+
+/// Address to bind to among those of an adapter. Link-local addresses can't
+/// reach the server, and the family the drive prefers comes first.
+fn pick_address(addresses: &[IpAddr], strategy: IpStrategy) -> Option<IpAddr> {
+    let usable = |addr: &&IpAddr| match addr {
+        IpAddr::V4(v4) => !v4.is_link_local() && !v4.is_unspecified(),
+        IpAddr::V6(v6) => (v6.segments()[0] & 0xffc0) != 0xfe80 && !v6.is_unspecified(),
+    };
+    let prefer_v6 = strategy == IpStrategy::PreferIpv6;
+    addresses
+        .iter()
+        .filter(usable)
+        .find(|addr| addr.is_ipv6() == prefer_v6)
+        .or_else(|| addresses.iter().find(|addr| usable(addr)))
+        .copied()
+}
+
+/// Unicast addresses of the adapter named `name`, compared without case
+fn adapter_addresses(name: &str) -> Result<Vec<IpAddr>> {
+    let flags = GAA_FLAG_SKIP_ANYCAST | GAA_FLAG_SKIP_MULTICAST | GAA_FLAG_SKIP_DNS_SERVER;
+    // The size needed can change between calls when adapters come and go
+    let mut size: u32 = 16 * 1024;
+    let mut buffer: Vec<u64>;
+    loop {
+        buffer = vec![0u64; (size as usize).div_ceil(8)];
+        let result = unsafe {
+            GetAdaptersAddresses(
+                AF_UNSPEC.0 as u32,
+                flags,
+                None,
+                Some(buffer.as_mut_ptr() as *mut IP_ADAPTER_ADDRESSES_LH),
+                &mut size,
+            )
+        };
+        if result == ERROR_SUCCESS.0 {
+            break;
+        }
+        if result != ERROR_BUFFER_OVERFLOW.0 {
+            return Err(anyhow!("Failed to list network adapters: error {}", result));
+        }
+    }
+
+    let mut adapter = buffer.as_ptr() as *const IP_ADAPTER_ADDRESSES_LH;
+    while let Some(current) = unsafe { adapter.as_ref() } {
+        adapter = current.Next;
+        let friendly_name = unsafe { U16CStr::from_ptr_str(current.FriendlyName.0) };
+        if !friendly_name.to_string_lossy().eq_ignore_ascii_case(name) {
+            continue;
+        }
+        if current.OperStatus != IfOperStatusUp {
+            return Err(anyhow!("Network adapter {} is not connected", name));
+        }
+
+        let mut addresses = Vec::new();
+        let mut unicast = current.FirstUnicastAddress;
+        while let Some(address) = unsafe { unicast.as_ref() } {
+            unicast = address.Next;
+            let sockaddr = address.Address.lpSockaddr;
+            if sockaddr.is_null() {
+                continue;
+            }
+            let family = unsafe { (*sockaddr).sa_family };
+            if family == AF_INET {
+                let sockaddr = unsafe { &*(sockaddr as *const SOCKADDR_IN) };
+                let octets = unsafe { sockaddr.sin_addr.S_un.S_un_b };
+                addresses.push(IpAddr::V4(Ipv4Addr::new(
+                    octets.s_b1,
+                    octets.s_b2,
+                    octets.s_b3,
+                    octets.s_b4,
+                )));
+            } else if family == AF_INET6 {
+                let sockaddr = unsafe { &*(sockaddr as *const SOCKADDR_IN6) };
+                let octets = unsafe { sockaddr.sin6_addr.u.Byte };
+                addresses.push(IpAddr::V6(Ipv6Addr::from(octets)));
+            }
+        }
+        return Ok(addresses);
+    }
+    Err(anyhow!("Network adapter {} not found", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_routable_address_of_preferred_family() {
+        let addresses: Vec<IpAddr> = ["fe80::1", "169.254.3.4", "192.168.1.5", "2001:db8::5"]
+            .iter()
+            .map(|addr| addr.parse().unwrap())
+            .collect();
+        assert_eq!(
+            pick_address(&addresses, IpStrategy::Auto),
+            Some("192.168.1.5".parse().unwrap())
+        );
+        assert_eq!(
+            pick_address(&addresses, IpStrategy::PreferIpv6),
+            Some("2001:db8::5".parse().unwrap())
+        );
+        assert_eq!(
+            pick_address(&addresses[..3], IpStrategy::PreferIpv6),
+            Some("192.168.1.5".parse().unwrap())
+        );
+        assert_eq!(pick_address(&addresses[..2], IpStrategy::Auto), None);
+        assert_eq!(
+            resolve_bind_address(" 10.0.0.2 ", IpStrategy::Auto).unwrap(),
+            IpAddr::from([10, 0, 0, 2])
+        );
+    }
+}
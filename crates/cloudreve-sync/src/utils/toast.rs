@@ -10,10 +10,24 @@ use win32_notif::{
 };
 
 use crate::config::ConfigManager;
+use crate::drive::actors::recent_remote_actor;
+use crate::presentation;
 
 const APP_NAME: &str = "Cloudreve.Sync";
 
+/// Whether toasts are held back because the screen is being presented
+fn suppressed_by_presentation() -> bool {
+    if presentation::suppresses_notifications() {
+        tracing::debug!(target: "toast", "Notification suppressed during presentation");
+        return true;
+    }
+    false
+}
+
 pub fn send_general_text_toast(title: &str, message: &str) {
+    if suppressed_by_presentation() {
+        return;
+    }
     let notifier = ToastsNotifier::new(APP_NAME).unwrap();
 
     let notif = NotificationBuilder::new()
@@ -39,6 +53,10 @@ pub fn send_general_text_toast(title: &str, message: &str) {
 /// Uses drive_id as the tag to prevent duplicate notifications for the same drive.
 /// Respects the notify_credential_expired config setting.
 pub fn send_token_expiry_toast(drive_id: &str, title: &str, message: &str) {
+    if suppressed_by_presentation() {
+        return;
+    }
+
     // Check if credential expired notifications are enabled
     if let Some(config) = ConfigManager::try_get() {
         if !config.notify_credential_expired() {
@@ -74,8 +92,13 @@ pub fn send_token_expiry_toast(drive_id: &str, title: &str, message: &str) {
 }
 
 /// Send a toast notification for file conflicts.
+/// Names the other user when someone else recently changed the file in the cloud.
 /// Respects the notify_file_conflict config setting.
 pub fn send_conflict_toast(drive_id: &str, path: &PathBuf, inventory_id: i64) {
+    if suppressed_by_presentation() {
+        return;
+    }
+
     // Check if file conflict notifications are enabled
     if let Some(config) = ConfigManager::try_get() {
         if !config.notify_file_conflict() {
@@ -84,6 +107,12 @@ pub fn send_conflict_toast(drive_id: &str, path: &PathBuf, inventory_id: i64) {
         }
     }
 
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let message = match recent_remote_actor(path) {
+        Some(user) => t!("conflictToastMessageByUser", "name" => name, "user" => user),
+        None => t!("conflictToastMessage", "name" => name),
+    };
+
     let notifier = ToastsNotifier::new(APP_NAME).unwrap();
 
     let notif = NotificationBuilder::new()
@@ -94,7 +123,7 @@ pub fn send_conflict_toast(drive_id: &str, path: &PathBuf, inventory_id: i64) {
                 .with_style(HintStyle::Title),
         )
         .visual(
-            Text::create(2, path.file_name().unwrap_or_default().to_str().unwrap_or_default())
+            Text::create(2, message.as_ref())
                 .with_align_center(true)
                 .with_wrap(true)
                 .with_style(HintStyle::Body),
@@ -126,3 +155,40 @@ pub fn send_conflict_toast(drive_id: &str, path: &PathBuf, inventory_id: i64) {
 
     notif.show().unwrap();
 }
+
+/// Send a toast notification for a downloaded file that was quarantined
+pub fn send_threat_toast(path: &PathBuf) {
+    if suppressed_by_presentation() {
+        return;
+    }
+    let notifier = ToastsNotifier::new(APP_NAME).unwrap();
+
+    let notif = NotificationBuilder::new()
+        .visual(
+            Text::create(1, t!("threatToastTitle").as_ref())
+                .with_align_center(true)
+                .with_wrap(true)
+                .with_style(HintStyle::Title),
+        )
+        .visual(
+            Text::create(
+                2,
+                t!(
+                    "threatToastMessage",
+                    "name" => path.file_name().unwrap_or_default().to_string_lossy()
+                )
+                .as_ref(),
+            )
+                .with_align_center(true)
+                .with_wrap(true)
+                .with_style(HintStyle::Body),
+        )
+        .visual(
+            Image::create(3, "ms-appx:///Images/warning.svg")
+                .with_placement(Placement::AppLogoOverride)
+        )
+        .build(0, &notifier, &format!("threat_{}", path.display()), "threat")
+        .unwrap();
+
+    notif.show().unwrap();
+}
@@ -0,0 +1,91 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use futures::future::BoxFuture;
+
+use super::{PlaceholderStatus, VirtualFs};
+use crate::cfapi::placeholder::{LocalFileInfo, OpenOptions, PinOptions, PinState};
+
+impl From<&LocalFileInfo> for PlaceholderStatus {
+    fn from(info: &LocalFileInfo) -> Self {
+        Self {
+            exists: info.exists,
+            is_directory: info.is_directory(),
+            is_placeholder: info.is_placeholder(),
+            in_sync: info.in_sync(),
+            partial_on_disk: info.partial_on_disk(),
+            pin_state: info.pinned(),
+        }
+    }
+}
+
+/// [`VirtualFs`] backed by the Cloud Files API
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CloudFilesVfs;
+
+impl VirtualFs for CloudFilesVfs {
+    fn status(&self, path: &Path) -> Result<PlaceholderStatus> {
+        let info = LocalFileInfo::from_path(path)?;
+        Ok(PlaceholderStatus::from(&info))
+    }
+
+    fn hydrate(&self, path: &Path) -> Result<()> {
+        let mut placeholder = OpenOptions::new()
+            .open_win32(path)
+            .context("failed to open placeholder")?;
+        placeholder
+            .hydrate(0..)
+            .context("failed to hydrate placeholder")
+    }
+
+    fn dehydrate<'a>(&'a self, path: &'a Path) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let mut placeholder = OpenOptions::new()
+                .open_win32_with_retry(path)
+                .await
+                .context("failed to open placeholder")?;
+            placeholder
+                .dehydrate(0..)
+                .context("failed to dehydrate placeholder")
+        })
+    }
+
+    fn set_pin_state(&self, path: &Path, state: PinState) -> Result<()> {
+        let mut placeholder = OpenOptions::new()
+            .open_win32(path)
+            .context("failed to open placeholder")?;
+        placeholder
+            .mark_pin(state, PinOptions::default())
+            .context("failed to set pin state")?;
+        Ok(())
+    }
+
+    fn pin_tree(&self, path: &Path, state: PinState) -> Result<()> {
+        let mut placeholder = OpenOptions::new()
+            .open_win32(path)
+            .context("failed to open placeholder")?;
+        placeholder
+            .mark_pin(state, *PinOptions::default().recurse())
+            .context("failed to set pin state")?;
+        Ok(())
+    }
+
+    fn mark_in_sync(&self, path: &Path, in_sync: bool) -> Result<()> {
+        let mut placeholder = OpenOptions::new()
+            .open_win32(path)
+            .context("failed to open placeholder")?;
+        placeholder
+            .mark_in_sync(in_sync, None)
+            .context("failed to set in-sync state")?;
+        Ok(())
+    }
+
+    fn populate(&self, path: &Path) -> Result<()> {
+        // Listing an unpopulated folder makes the filter fetch its placeholders
+        let children = std::fs::read_dir(path)
+            .context("failed to list folder")?
+            .count();
+        tracing::debug!(target: "vfs", path = %path.display(), children, "Populated folder");
+        Ok(())
+    }
+}
@@ -0,0 +1,12 @@
+//! Placeholder operations the sync engine performs on the local file system.
+//!
+//! The operations, the decisions made on them and [`FakeVirtualFs`] live in
+//! [`cloudreve_sync_core::vfs`], which builds on any OS. [`CloudFilesVfs`]
+//! backs them with the Windows Cloud Files API.
+
+#[cfg(windows)]
+mod cloud_files;
+
+#[cfg(windows)]
+pub use cloud_files::CloudFilesVfs;
+pub use cloudreve_sync_core::vfs::*;
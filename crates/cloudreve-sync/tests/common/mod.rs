@@ -0,0 +1,100 @@
+//! Shared fixtures for the integration tests. The mock Cloudreve server comes
+//! from [`cloudreve_sync_core::testing`]; this adds what needs the sync crate
+//! itself: an inventory and a [`DriveManager`] in a temporary directory.
+
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use cloudreve_sync::drive::manager::DriveManager;
+use cloudreve_sync::drive::mounts::{Credentials, DriveConfig, DriveKind};
+use cloudreve_sync::events::EventBroadcaster;
+use cloudreve_sync::inventory::InventoryDb;
+use tempfile::TempDir;
+
+pub use cloudreve_sync_core::testing::*;
+
+/// Inventory database in a temporary directory, removed on drop
+pub struct TestInventory {
+    pub db: Arc<InventoryDb>,
+    pub dir: TempDir,
+}
+
+impl TestInventory {
+    pub fn new() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let db = InventoryDb::with_path(dir.path().join("meta.db")).unwrap();
+        Self {
+            db: Arc::new(db),
+            dir,
+        }
+    }
+
+    /// Write a file into the temporary directory and return its path
+    pub fn write_file(&self, name: &str, content: &[u8]) -> PathBuf {
+        let path = self.dir.path().join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+}
+
+/// Drive manager keeping `drives.json` and its inventory in a temporary
+/// directory, removed on drop. Sync folders are created under it too.
+pub struct TestDriveManager {
+    pub manager: Arc<DriveManager>,
+    pub events: Arc<EventBroadcaster>,
+    pub dir: TempDir,
+}
+
+impl TestDriveManager {
+    pub fn new() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        Self::in_dir(dir)
+    }
+
+    /// A manager over the config directory of `dir`, e.g. to load what
+    /// another manager saved there
+    pub fn in_dir(dir: TempDir) -> Self {
+        let events = Arc::new(EventBroadcaster::new(16));
+        let manager =
+            DriveManager::with_config_dir(events.clone(), dir.path().join("config")).unwrap();
+        Self {
+            manager: Arc::new(manager),
+            events,
+            dir,
+        }
+    }
+
+    /// Save the drives and reopen the same directory in a new manager
+    pub async fn reopen(self) -> Self {
+        self.manager.persist().await.unwrap();
+        let Self { manager, dir, .. } = self;
+        drop(manager);
+        Self::in_dir(dir)
+    }
+
+    /// Config of a drive syncing `name` in the temporary directory with the
+    /// mock server. Its icon is set, so no favicon is fetched.
+    pub fn drive_config(&self, server: &MockServer, name: &str) -> DriveConfig {
+        let sync_path = self.dir.path().join(name);
+        std::fs::create_dir_all(&sync_path).unwrap();
+        let icon_path = self.dir.path().join(format!("{}.ico", name));
+        std::fs::write(&icon_path, b"").unwrap();
+        DriveConfig {
+            name: name.to_string(),
+            instance_url: server.base_url().to_string(),
+            remote_path: "cloudreve://my".to_string(),
+            credentials: Credentials {
+                access_token: None,
+                refresh_token: "refresh".to_string(),
+                refresh_expires: String::new(),
+                access_expires: None,
+            },
+            sync_path,
+            icon_path: Some(icon_path.to_string_lossy().into_owned()),
+            kind: DriveKind::Cloudreve,
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,52 @@
+mod common;
+
+use cloudreve_sync::drive::manager::{AddDriveError, DriveInfoStatus};
+use common::{MockServer, TestDriveManager};
+
+#[tokio::test]
+async fn registered_drives_are_kept_across_restarts() {
+    let server = MockServer::start().await;
+    let fixture = TestDriveManager::new();
+    let config = fixture.drive_config(&server, "Cloudreve");
+    let sync_path = config.sync_path.clone();
+    let id = fixture.manager.register_drive(config).await.unwrap();
+
+    let fixture = fixture.reopen().await;
+    fixture.manager.load().await.unwrap();
+
+    let drives = fixture.manager.get_drives_info().await.unwrap();
+    assert_eq!(drives.len(), 1);
+    assert_eq!(drives[0].id, id);
+    assert_eq!(drives[0].instance_url, server.base_url());
+    assert_eq!(drives[0].sync_path, sync_path.to_string_lossy());
+    assert!(!drives[0].enabled);
+    assert!(matches!(drives[0].status, DriveInfoStatus::Unmounted));
+}
+
+#[tokio::test]
+async fn a_sync_folder_is_registered_once() {
+    let server = MockServer::start().await;
+    let fixture = TestDriveManager::new();
+    let id = fixture
+        .manager
+        .register_drive(fixture.drive_config(&server, "Cloudreve"))
+        .await
+        .unwrap();
+
+    let error = fixture
+        .manager
+        .register_drive(fixture.drive_config(&server, "Cloudreve"))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<AddDriveError>(),
+        Some(&AddDriveError::AlreadyExists { existing_id: id })
+    );
+}
+
+#[tokio::test]
+async fn a_fresh_config_dir_loads_no_drive() {
+    let fixture = TestDriveManager::new();
+    fixture.manager.load().await.unwrap();
+    assert!(fixture.manager.get_drives_info().await.unwrap().is_empty());
+}
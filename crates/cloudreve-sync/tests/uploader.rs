@@ -0,0 +1,97 @@
+mod common;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use cloudreve_sync::uploader::{
+    ProgressCallback, ProgressUpdate, UploadParams, Uploader, UploaderConfig,
+};
+use common::{CHUNK_SIZE, MockServer, TestInventory};
+
+#[derive(Clone, Default)]
+struct MaxProgress(Arc<AtomicU64>);
+
+impl ProgressCallback for MaxProgress {
+    fn on_progress(&self, update: ProgressUpdate) {
+        self.0.fetch_max(update.uploaded, Ordering::Relaxed);
+    }
+}
+
+fn params(inventory: &TestInventory, name: &str, content: &[u8]) -> UploadParams {
+    UploadParams {
+        local_path: inventory.write_file(name, content),
+        remote_uri: format!("cloudreve://my/{}", name),
+        file_size: content.len() as u64,
+        mime_type: None,
+        last_modified: None,
+        overwrite: false,
+        previous_version: String::new(),
+        task_id: format!("task-{}", name),
+        drive_id: "drive".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn uploads_file_in_chunks() {
+    let server = MockServer::start().await;
+    let inventory = TestInventory::new();
+    let uploader = Uploader::new(
+        server.client().await,
+        inventory.db.clone(),
+        UploaderConfig::default(),
+    );
+
+    // Spans three chunks, the last one partial
+    let content = b"hello, world";
+    assert!(content.len() as i64 > 2 * CHUNK_SIZE);
+    let progress = MaxProgress::default();
+    uploader
+        .upload(params(&inventory, "hello.txt", content), progress.clone())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        server.uploaded("cloudreve://my/hello.txt").as_deref(),
+        Some(&content[..])
+    );
+    assert_eq!(progress.0.load(Ordering::Relaxed), content.len() as u64);
+    assert!(server.open_uploads().is_empty());
+
+    // The finished session is no longer persisted for resuming
+    let local_path = inventory.dir.path().join("hello.txt");
+    let session = inventory
+        .db
+        .get_upload_session_by_path(&local_path.to_string_lossy())
+        .unwrap();
+    assert!(session.is_none());
+}
+
+#[tokio::test]
+async fn uploaded_file_is_listed() {
+    use cloudreve_api::api::explorer::ExplorerApiExt;
+
+    let server = MockServer::start().await;
+    let inventory = TestInventory::new();
+    let client = server.client().await;
+    let uploader = Uploader::new(
+        client.clone(),
+        inventory.db.clone(),
+        UploaderConfig::default(),
+    );
+
+    uploader
+        .upload(
+            params(&inventory, "notes.md", b"# notes"),
+            MaxProgress::default(),
+        )
+        .await
+        .unwrap();
+
+    let listing = client
+        .list_files_all(None, "cloudreve://my", 100)
+        .await
+        .unwrap();
+    assert_eq!(listing.res.files.len(), 1);
+    assert_eq!(listing.res.files[0].name, "notes.md");
+    assert_eq!(listing.res.files[0].size, 7);
+}
@@ -1,15 +1,32 @@
+use crate::ipc::{
+    AddDriveArgs, AddDriveFailure, AddShareDriveArgs, BatterySaver, CommandQueueReport,
+    CompressionSettings, CreateDriveFromTemplateArgs, CredentialEvent, DownloadScan, DriveConfig,
+    DriveInfo, DriveLabel, DriveTemplate, DuplicateAction, DuplicateReport, DuplicateResolution,
+    FairScheduling, FileHistoryEntry, FileIconResponse, FolderSummary, GeneralSettings,
+    IdleSchedule, ImportFolderArgs, ImportSource, InitialSyncReport, InventorySnapshot,
+    IssueAction, NetworkSettings, PinKind, Policy, PresentationMode, PurgeReport, QuarantinedFile,
+    RepairReport, ShareReport, StatusSummary, SyncDirection, SyncFailure, SyncIssue, TlsPin,
+    TransferLimits, TrashItem, TypeFilter,
+};
+use crate::window_manager::{self, AppWindow};
 use crate::AppStateHandle;
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use chrono::{Duration, Utc};
 use cloudreve_sync::{
-    config::LogLevel, ConfigManager, Credentials, DriveConfig, DriveInfo, StatusSummary, SyncDirection,
+    config::LogLevel,
+    drive::commands::{ConflictAction, ManagerCommand},
+    events::Event,
+    expand_remote_path,
+    utils::correlation::user_action,
+    AddDriveError, ConfigManager, Credentials, PathVariables,
 };
+use std::path::{Path, PathBuf};
 #[cfg(target_os = "macos")]
 use tauri::TitleBarStyle;
 use tauri::{
     utils::{config::WindowEffectsConfig, WindowEffect},
     webview::WebviewWindowBuilder,
-    AppHandle, Manager, State, WebviewUrl,
+    AppHandle, Manager, State, WebviewUrl, WebviewWindow,
 };
 use tauri_plugin_frame::WebviewWindowExt;
 use tauri_plugin_positioner::{Position, WindowExt};
@@ -35,8 +52,15 @@ fn is_root_drive(path: &str) -> bool {
     rest.is_empty()
 }
 
+/// Check if a folder exists and contains any entries
+fn folder_has_entries(path: &str) -> bool {
+    std::fs::read_dir(path.trim())
+        .map(|mut entries| entries.next().is_some())
+        .unwrap_or(false)
+}
+
 /// Get the URL with language query parameter appended
-fn get_url_with_lang(base_path: &str) -> String {
+pub(crate) fn get_url_with_lang(base_path: &str) -> String {
     let locale = crate::get_effective_locale();
     if base_path.contains('?') {
         format!("{}&lng={}", base_path, locale)
@@ -54,61 +78,87 @@ pub async fn list_drives(state: State<'_, AppStateHandle>) -> CommandResult<Vec<
     Ok(app_state.drive_manager.list_drives().await)
 }
 
-#[derive(serde::Deserialize)]
-pub struct AddDriveArgs {
-    pub site_url: String,
-    pub access_token: String,
-    pub refresh_token: String,
-    pub access_token_expires: u64,
-    pub refresh_token_expires: u64,
-    pub drive_name: String,
-    pub remote_path: String,
-    pub local_path: String,
-    pub user_id: String,
-    pub drive_id: Option<String>,
+/// Build credentials from a token response, converting relative expiry times
+/// (seconds) to absolute RFC3339 timestamps
+fn credentials_from_tokens(
+    access_token: String,
+    refresh_token: String,
+    access_token_expires: u64,
+    refresh_token_expires: u64,
+) -> Credentials {
+    let now = Utc::now();
+    let access_expires = (now + Duration::seconds(access_token_expires as i64)).to_rfc3339();
+    let refresh_expires = (now + Duration::seconds(refresh_token_expires as i64)).to_rfc3339();
+
+    Credentials {
+        access_token: Some(access_token),
+        refresh_token,
+        access_expires: Some(access_expires),
+        refresh_expires,
+    }
 }
 
 /// Add a new drive configuration
 #[tauri::command]
 pub async fn add_drive(
     state: State<'_, AppStateHandle>,
-    config: AddDriveArgs,
-) -> CommandResult<String> {
+    mut config: AddDriveArgs,
+) -> Result<String, AddDriveFailure> {
     let app_state = state
         .get()
         .ok_or_else(|| "App not yet initialized".to_string())?;
 
     // Validate local_path for new drives (not for reauthorization)
     if config.drive_id.is_none() && is_root_drive(&config.local_path) {
-        return Err(t!("localPathCannotBeRootDrive").to_string());
+        return Err(t!("localPathCannotBeRootDrive").to_string().into());
+    }
+    // Variables like {hostname} are replaced once, when the drive is added
+    if config.drive_id.is_none() {
+        config.remote_path = expand_remote_path(
+            &config.remote_path,
+            &PathVariables::current(&config.user_id),
+        )
+        .map_err(|e| format!("{:#}", e))?;
+    }
+    // An existing drive's folder is not empty, so check for duplicates first
+    if config.drive_id.is_none() {
+        if let Err(error) = app_state
+            .drive_manager
+            .check_new_drive(
+                &config.site_url,
+                &config.remote_path,
+                Path::new(&config.local_path),
+            )
+            .await
+        {
+            return Err(
+                AddDriveFailure::from_add_drive_error(&app_state.drive_manager, error).await,
+            );
+        }
+    }
+    let import_existing = config.drive_id.is_none() && folder_has_entries(&config.local_path);
+    if import_existing && !config.merge_existing {
+        return Err(t!("localPathNotEmpty").to_string().into());
     }
 
-    // Convert relative expiry times (seconds) to absolute RFC3339 timestamps
-    let now = Utc::now();
-    let access_expires = (now + Duration::seconds(config.access_token_expires as i64)).to_rfc3339();
-    let refresh_expires =
-        (now + Duration::seconds(config.refresh_token_expires as i64)).to_rfc3339();
-
-    let credentials = Credentials {
-        access_token: Some(config.access_token),
-        refresh_token: config.refresh_token,
-        access_expires: Some(access_expires),
-        refresh_expires,
-    };
+    let credentials = credentials_from_tokens(
+        config.access_token,
+        config.refresh_token,
+        config.access_token_expires,
+        config.refresh_token_expires,
+    );
 
     // If drive_id is provided, update existing drive instead of creating a new one
     if let Some(drive_id) = config.drive_id {
-        app_state
-            .drive_manager
-            .update_drive_credentials(
-                &drive_id,
-                config.drive_name,
-                config.site_url,
-                credentials,
-                &config.user_id,
-            )
-            .await
-            .map_err(|e| e.to_string())?;
+        user_action(app_state.drive_manager.update_drive_credentials(
+            &drive_id,
+            config.drive_name,
+            config.site_url,
+            credentials,
+            &config.user_id,
+        ))
+        .await
+        .map_err(|e| e.to_string())?;
 
         // Persist drive configurations
         app_state
@@ -120,6 +170,17 @@ pub async fn add_drive(
         return Ok(drive_id);
     }
 
+    // Trusting the server on first use pins the certificate it presents now
+    let mut tls_pins = config.tls_pins;
+    if let (true, Some(kind)) = (tls_pins.is_empty(), config.pin_server) {
+        let pin = app_state
+            .drive_manager
+            .fetch_server_pin(&config.site_url, kind)
+            .await
+            .map_err(|e| format!("{:#}", e))?;
+        tls_pins.push(pin);
+    }
+
     // Generate a new UUID for a new drive
     let drive_id = Uuid::new_v4().to_string();
 
@@ -130,24 +191,155 @@ pub async fn add_drive(
         remote_path: config.remote_path,
         credentials,
         sync_path: config.local_path.into(),
+        portable_sync_path: None,
         icon_path: None,
         raw_icon_path: None,
         enabled: true,
         user_id: config.user_id,
         sync_root_id: None,
         ignore_patterns: Vec::new(),
+        type_filter: Default::default(),
         sync_direction: SyncDirection::default(),
+        site_title: None,
+        label: None,
+        label_icon_path: None,
+        import_existing,
+        usn_checkpoint: None,
+        resume: Default::default(),
+        network: NetworkSettings {
+            tls_pins,
+            ..Default::default()
+        },
+        compression: Default::default(),
+        transfer_limits: Default::default(),
+        guest_share: None,
+        kind: Default::default(),
+        last_state: None,
+        suspicious_changes: None,
         extra: Default::default(),
     };
 
     // Add drive to manager
-    let id = app_state
+    let id = match user_action(app_state.drive_manager.add_drive(drive_config)).await {
+        Ok(id) => id,
+        Err(e) => {
+            return Err(match e.downcast::<AddDriveError>() {
+                Ok(error) => {
+                    AddDriveFailure::from_add_drive_error(&app_state.drive_manager, error).await
+                }
+                Err(e) => e.to_string().into(),
+            });
+        }
+    };
+
+    // Persist drive configurations
+    app_state
         .drive_manager
-        .add_drive(drive_config)
+        .persist()
         .await
         .map_err(|e| e.to_string())?;
 
-    // Persist drive configurations
+    Ok(id)
+}
+
+/// Preview the remote folder a new drive would sync, with variables like
+/// `{hostname}` replaced. Fails if the path is not valid.
+#[tauri::command]
+pub fn preview_remote_path(remote_path: String, user_id: String) -> CommandResult<String> {
+    expand_remote_path(&remote_path, &PathVariables::current(&user_id))
+        .map_err(|e| format!("{:#}", e))
+}
+
+/// Fingerprint of the certificate a server presents now, shown by the
+/// add-drive wizard before the user decides to trust it
+#[tauri::command]
+pub async fn fetch_server_pin(
+    state: State<'_, AppStateHandle>,
+    site_url: String,
+    kind: PinKind,
+) -> CommandResult<TlsPin> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .drive_manager
+        .fetch_server_pin(&site_url, kind)
+        .await
+        .map_err(|e| format!("{:#}", e))
+}
+
+/// Add a read-only drive from a public share link, without signing in
+#[tauri::command]
+pub async fn add_share_drive(
+    state: State<'_, AppStateHandle>,
+    args: AddShareDriveArgs,
+) -> Result<String, AddDriveFailure> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    if is_root_drive(&args.local_path) {
+        return Err(t!("localPathCannotBeRootDrive").to_string().into());
+    }
+    let import_existing = folder_has_entries(&args.local_path);
+    if import_existing && !args.merge_existing {
+        return Err(t!("localPathNotEmpty").to_string().into());
+    }
+
+    let id = match user_action(app_state.drive_manager.add_share_drive(
+        &args.share_url,
+        args.password,
+        args.local_path.into(),
+        args.drive_name,
+        import_existing,
+    ))
+    .await
+    {
+        Ok(id) => id,
+        Err(e) => {
+            return Err(match e.downcast::<AddDriveError>() {
+                Ok(error) => {
+                    AddDriveFailure::from_add_drive_error(&app_state.drive_manager, error).await
+                }
+                Err(e) => format!("{:#}", e).into(),
+            });
+        }
+    };
+
+    app_state
+        .drive_manager
+        .persist()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(id)
+}
+
+/// Add a simulated drive, which fakes a server for UI development and demos.
+/// Only allowed when `simulated_drives` is set in config.json.
+#[tauri::command]
+pub async fn add_simulated_drive(
+    state: State<'_, AppStateHandle>,
+    drive_name: String,
+    local_path: String,
+) -> CommandResult<String> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    if is_root_drive(&local_path) {
+        return Err(t!("localPathCannotBeRootDrive").to_string());
+    }
+
+    let id = user_action(
+        app_state
+            .drive_manager
+            .add_simulated_drive(drive_name, local_path.into()),
+    )
+    .await
+    .map_err(|e| format!("{:#}", e))?;
+
     app_state
         .drive_manager
         .persist()
@@ -157,6 +349,12 @@ pub async fn add_drive(
     Ok(id)
 }
 
+/// Check whether a local folder already contains files, so the UI can offer merge mode
+#[tauri::command]
+pub async fn local_folder_has_files(path: String) -> CommandResult<bool> {
+    Ok(folder_has_entries(&path))
+}
+
 /// Remove a drive by ID
 #[tauri::command]
 pub async fn remove_drive(
@@ -167,9 +365,7 @@ pub async fn remove_drive(
         .get()
         .ok_or_else(|| "App not yet initialized".to_string())?;
 
-    let result = app_state
-        .drive_manager
-        .remove_drive(&drive_id)
+    let result = user_action(app_state.drive_manager.remove_drive(&drive_id))
         .await
         .map_err(|e| e.to_string())?;
 
@@ -183,6 +379,147 @@ pub async fn remove_drive(
     Ok(result)
 }
 
+/// Connect a registered drive and start syncing it
+#[tauri::command]
+pub async fn mount_drive(state: State<'_, AppStateHandle>, drive_id: String) -> CommandResult<()> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    user_action(app_state.drive_manager.mount_drive(&drive_id))
+        .await
+        .map_err(|e| format!("{:#}", e))
+}
+
+/// Stop syncing a drive, keeping it configured
+#[tauri::command]
+pub async fn unmount_drive(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+) -> CommandResult<()> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .drive_manager
+        .unmount_drive(&drive_id)
+        .await
+        .map_err(|e| format!("{:#}", e))
+}
+
+/// Change the name, remote folder or local folder of a drive. The remote
+/// folder can only be changed while the local folder is empty.
+#[tauri::command]
+pub async fn update_drive(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+    config: DriveConfig,
+) -> CommandResult<()> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    user_action(app_state.drive_manager.update_drive(&drive_id, config))
+        .await
+        .map_err(|e| format!("{:#}", e))?;
+
+    app_state
+        .drive_manager
+        .persist()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Sync a drive whose folder went missing with another folder
+#[tauri::command]
+pub async fn relink_sync_root(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+    path: String,
+) -> CommandResult<()> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    user_action(
+        app_state
+            .drive_manager
+            .relink_sync_root(&drive_id, PathBuf::from(path)),
+    )
+    .await
+    .map_err(|e| format!("{:#}", e))
+}
+
+/// Sync a drive whose folder went missing with the same folder on the disk
+/// letter it has now, returning that folder
+#[tauri::command]
+pub async fn relocate_sync_root(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+) -> CommandResult<String> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    user_action(app_state.drive_manager.relocate_sync_root(&drive_id))
+        .await
+        .map(|path| path.to_string_lossy().to_string())
+        .map_err(|e| format!("{:#}", e))
+}
+
+/// Create the missing folder of a drive again and download its content
+#[tauri::command]
+pub async fn recreate_sync_root(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+) -> CommandResult<()> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    user_action(app_state.drive_manager.recreate_sync_root(&drive_id))
+        .await
+        .map_err(|e| format!("{:#}", e))
+}
+
+/// Restore the files a drive stopped syncing for from the cloud, returning
+/// how many were restored
+#[tauri::command]
+pub async fn restore_suspicious_changes(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+) -> CommandResult<usize> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    user_action(
+        app_state
+            .drive_manager
+            .restore_suspicious_changes(&drive_id),
+    )
+    .await
+    .map_err(|e| format!("{:#}", e))
+}
+
+/// Resume syncing a drive stopped for suspicious changes, keeping them
+#[tauri::command]
+pub async fn dismiss_suspicious_changes(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+) -> CommandResult<()> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .drive_manager
+        .dismiss_suspicious_changes(&drive_id)
+        .await
+        .map_err(|e| format!("{:#}", e))
+}
+
 /// Get sync status for a drive
 #[tauri::command]
 pub async fn get_sync_status(
@@ -228,17 +565,6 @@ pub async fn get_drives_info(state: State<'_, AppStateHandle>) -> CommandResult<
         .map_err(|e| e.to_string())
 }
 
-/// File icon response containing base64 encoded RGBA pixel data
-#[derive(serde::Serialize)]
-pub struct FileIconResponse {
-    /// Base64 encoded RGBA pixel data
-    pub data: String,
-    /// Icon width in pixels
-    pub width: u32,
-    /// Icon height in pixels
-    pub height: u32,
-}
-
 /// Get file icon for a given path
 /// Returns base64 encoded RGBA pixel data with dimensions
 #[tauri::command]
@@ -264,11 +590,6 @@ pub fn show_main_window(app: &AppHandle) {
     show_main_window_at_position(app, Position::TrayCenter);
 }
 
-/// Show or create the main window (positioned at bottom right)
-pub fn show_main_window_center(app: &AppHandle) {
-    show_main_window_at_position(app, Position::Center);
-}
-
 /// Internal function to show or create the main window at a specific position
 fn show_main_window_at_position(app: &AppHandle, position: Position) {
     // Check if window already exists
@@ -424,54 +745,71 @@ pub async fn show_settings_window(app: AppHandle) -> CommandResult<()> {
 
 /// Show or create the settings window
 pub fn show_settings_window_impl(app: &AppHandle) {
-    // Check if window already exists
-    if let Some(window) = app.get_webview_window("settings") {
-        let _ = window.show();
-        let _ = window.unminimize();
-        let _ = window.set_focus();
-        return;
-    }
-
-    let builder = WebviewWindowBuilder::new(
-        app,
-        "settings",
-        WebviewUrl::App(get_url_with_lang("index.html/#/settings").into()),
-    )
-    .title("Settings")
-    .inner_size(700.0, 500.0)
-    .min_inner_size(600.0, 400.0)
-    .visible(false)
-    .resizable(true)
-    .decorations(false)
-    .minimizable(true);
-
-    // Platform-specific: title_bar_style and hidden_title are macOS-only
-    #[cfg(target_os = "macos")]
-    let builder = builder
-        .title_bar_style(TitleBarStyle::Overlay)
-        .hidden_title(true);
+    window_manager::open(app, AppWindow::Settings);
+}
 
-    match builder.build() {
-        Ok(window) => {
-            let _ = window.move_window(Position::Center);
-            let _ = window.create_overlay_titlebar();
-            let _ = window.show();
-            let _ = window.set_focus();
-        }
-        Err(e) => {
-            tracing::error!(target: "main", error = %e, "Failed to create settings window");
-        }
-    }
+/// Command to show the sync status in its own window
+#[tauri::command]
+pub async fn show_status_window(app: AppHandle) -> CommandResult<()> {
+    window_manager::open(&app, AppWindow::Status);
+    Ok(())
 }
 
-/// The TaskId defined in AppxManifest.xml for the startup task
-const STARTUP_TASK_ID: &str = "cloudreve";
+/// Command to show the window resolving the conflict of a file
+#[tauri::command]
+pub async fn show_conflict_window(
+    app: AppHandle,
+    drive_id: String,
+    file_id: i64,
+    path: String,
+) -> CommandResult<()> {
+    window_manager::open(
+        &app,
+        AppWindow::Conflict {
+            drive_id,
+            file_id,
+            path,
+        },
+    );
+    Ok(())
+}
 
-/// Get whether auto-start is enabled using Windows StartupTask API
+/// Resolve the conflict of a file with `action`: `keep_remote`,
+/// `overwrite_remote` or `save_as_new`
 #[tauri::command]
-pub async fn get_auto_start_enabled() -> CommandResult<bool> {
-    tokio::task::spawn_blocking(|| {
-        let task_id: windows::core::HSTRING = STARTUP_TASK_ID.into();
+pub async fn resolve_conflict(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+    file_id: i64,
+    path: String,
+    action: String,
+) -> CommandResult<()> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+    let action = ConflictAction::from_str(&action)
+        .ok_or_else(|| format!("Unknown conflict action: {}", action))?;
+
+    app_state
+        .drive_manager
+        .get_command_sender()
+        .send_user_action(ManagerCommand::ResolveConflict {
+            drive_id,
+            file_id,
+            path,
+            action,
+        })
+        .map_err(|e| e.to_string())
+}
+
+/// The TaskId defined in AppxManifest.xml for the startup task
+const STARTUP_TASK_ID: &str = "cloudreve";
+
+/// Get whether auto-start is enabled using Windows StartupTask API
+#[tauri::command]
+pub async fn get_auto_start_enabled() -> CommandResult<bool> {
+    tokio::task::spawn_blocking(|| {
+        let task_id: windows::core::HSTRING = STARTUP_TASK_ID.into();
         let task = StartupTask::GetAsync(&task_id)
             .map_err(|e| format!("Failed to get startup task: {}", e))?
             .get()
@@ -546,6 +884,57 @@ pub async fn set_fast_popup_launch(enabled: bool) -> CommandResult<()> {
         .map_err(|e| e.to_string())
 }
 
+/// Set the scanner run on downloaded files
+#[tauri::command]
+pub async fn set_download_scan(scan: DownloadScan) -> CommandResult<()> {
+    ConfigManager::get()
+        .set_download_scan(scan)
+        .map_err(|e| e.to_string())
+}
+
+/// Set when sync is throttled on battery power
+#[tauri::command]
+pub async fn set_battery_saver(battery_saver: BatterySaver) -> CommandResult<()> {
+    ConfigManager::get()
+        .set_battery_saver(battery_saver)
+        .map_err(|e| e.to_string())
+}
+
+/// Set whether notifications and transfers are held back while presenting
+#[tauri::command]
+pub async fn set_presentation_mode(presentation_mode: PresentationMode) -> CommandResult<()> {
+    ConfigManager::get()
+        .set_presentation_mode(presentation_mode)
+        .map_err(|e| e.to_string())
+}
+
+/// Set how transfer slots are shared between busy drives
+#[tauri::command]
+pub async fn set_fair_scheduling(fair_scheduling: FairScheduling) -> CommandResult<()> {
+    ConfigManager::get()
+        .set_fair_scheduling(fair_scheduling)
+        .map_err(|e| e.to_string())
+}
+
+/// Set whether full syncs and maintenance wait for the user to be idle
+#[tauri::command]
+pub async fn set_idle_schedule(idle_schedule: IdleSchedule) -> CommandResult<()> {
+    ConfigManager::get()
+        .set_idle_schedule(idle_schedule)
+        .map_err(|e| e.to_string())
+}
+
+/// Run full syncs deferred for power saving or user activity right away
+#[tauri::command]
+pub async fn run_deferred_work(state: State<'_, AppStateHandle>) -> CommandResult<()> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state.drive_manager.run_deferred_work(true).await;
+    Ok(())
+}
+
 /// Get all general settings
 #[tauri::command]
 pub async fn get_general_settings() -> CommandResult<GeneralSettings> {
@@ -557,21 +946,22 @@ pub async fn get_general_settings() -> CommandResult<GeneralSettings> {
         log_to_file: config.log_to_file,
         log_level: config.log_level.as_str().to_string(),
         log_max_files: config.log_max_files,
+        http_trace: config.http_trace,
         log_dir: ConfigManager::get_log_dir().display().to_string(),
         language: config.language,
+        download_scan: config.download_scan,
+        battery_saver: config.battery_saver,
+        idle_schedule: config.idle_schedule,
+        presentation_mode: config.presentation_mode,
+        fair_scheduling: config.fair_scheduling,
+        locked_settings: Policy::get().locked_settings.clone(),
     })
 }
 
-#[derive(serde::Serialize)]
-pub struct GeneralSettings {
-    pub notify_credential_expired: bool,
-    pub notify_file_conflict: bool,
-    pub fast_popup_launch: bool,
-    pub log_to_file: bool,
-    pub log_level: String,
-    pub log_max_files: usize,
-    pub log_dir: String,
-    pub language: Option<String>,
+/// Get the administrator policy (read-only)
+#[tauri::command]
+pub async fn get_policy() -> CommandResult<Policy> {
+    Ok(Policy::get().clone())
 }
 
 /// Set log to file setting
@@ -587,7 +977,7 @@ pub async fn set_log_to_file(enabled: bool) -> CommandResult<()> {
 pub async fn set_log_level(level: String) -> CommandResult<()> {
     let log_level = LogLevel::from_str(&level);
 
-    // Update config (requires restart to take effect)
+    // Applied to the running subscriber by the logging config watcher
     ConfigManager::get()
         .set_log_level(log_level)
         .map_err(|e| e.to_string())
@@ -601,19 +991,31 @@ pub async fn set_log_max_files(max_files: usize) -> CommandResult<()> {
         .map_err(|e| e.to_string())
 }
 
+/// Set whether requests to the servers are logged
+#[tauri::command]
+pub async fn set_http_trace(enabled: bool) -> CommandResult<()> {
+    // Applied to the API clients by the logging config watcher
+    ConfigManager::get()
+        .set_http_trace(enabled)
+        .map_err(|e| e.to_string())
+}
+
 /// Set language setting and update rust_i18n locale
+///
+/// The locale is resolved through the fallback chain (e.g. zh-HK -> zh -> en-US)
+/// and the effective locale is returned.
 #[tauri::command]
-pub async fn set_language(app: AppHandle, language: Option<String>) -> CommandResult<()> {
+pub async fn set_language(app: AppHandle, language: Option<String>) -> CommandResult<String> {
     // Update the config
     ConfigManager::get()
         .set_language(language.clone())
         .map_err(|e| e.to_string())?;
 
-    // Update rust_i18n locale
-    let locale = language.unwrap_or_else(|| {
+    // Update rust_i18n locale, shell extension strings pick it up on next menu build
+    let requested = language.unwrap_or_else(|| {
         sys_locale::get_locale().unwrap_or_else(|| String::from("en-US"))
     });
-    rust_i18n::set_locale(&locale);
+    let locale = cloudreve_sync::utils::locale::apply_locale(&requested);
 
     // Close main window to force reload with new language
      // Check if window already exists
@@ -622,7 +1024,13 @@ pub async fn set_language(app: AppHandle, language: Option<String>) -> CommandRe
         let _ = window.destroy();
     }
 
-    Ok(())
+    Ok(locale)
+}
+
+/// List the locales available from the embedded translation files
+#[tauri::command]
+pub async fn available_locales() -> CommandResult<Vec<String>> {
+    Ok(cloudreve_sync::utils::locale::available_locales())
 }
 
 /// Open the log folder in file explorer
@@ -693,3 +1101,939 @@ pub async fn set_sync_direction(
 
     Ok(())
 }
+
+/// Get the connection settings for a specific drive
+#[tauri::command]
+pub async fn get_network_settings(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+) -> CommandResult<NetworkSettings> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .drive_manager
+        .get_network_settings(&drive_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Set the connection settings for a specific drive, applied on next start
+#[tauri::command]
+pub async fn set_network_settings(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+    settings: NetworkSettings,
+) -> CommandResult<()> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .drive_manager
+        .set_network_settings(&drive_id, settings)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Persist drive configurations after update
+    app_state
+        .drive_manager
+        .persist()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Get the compressed upload settings for a specific drive
+#[tauri::command]
+pub async fn get_compression_settings(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+) -> CommandResult<CompressionSettings> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .drive_manager
+        .get_compression_settings(&drive_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Set the compressed upload settings for a specific drive, applied on next start
+#[tauri::command]
+pub async fn set_compression_settings(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+    settings: CompressionSettings,
+) -> CommandResult<()> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .drive_manager
+        .set_compression_settings(&drive_id, settings)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Persist drive configurations after update
+    app_state
+        .drive_manager
+        .persist()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Get the transfer concurrency limits for a specific drive
+#[tauri::command]
+pub async fn get_transfer_limits(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+) -> CommandResult<TransferLimits> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .drive_manager
+        .get_transfer_limits(&drive_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Set the transfer concurrency limits for a specific drive, applied immediately
+#[tauri::command]
+pub async fn set_transfer_limits(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+    limits: TransferLimits,
+) -> CommandResult<()> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .drive_manager
+        .set_transfer_limits(&drive_id, limits)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Persist drive configurations after update
+    app_state
+        .drive_manager
+        .persist()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Get the file type filter of a specific drive
+#[tauri::command]
+pub async fn get_type_filter(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+) -> CommandResult<TypeFilter> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .drive_manager
+        .get_type_filter(&drive_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Set the file type filter of a specific drive and resync it
+#[tauri::command]
+pub async fn set_type_filter(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+    filter: TypeFilter,
+) -> CommandResult<()> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    user_action(app_state.drive_manager.set_type_filter(&drive_id, filter))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Persist drive configurations after update
+    app_state
+        .drive_manager
+        .persist()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Give a drive most of the transfer slots for `duration_secs` seconds, or
+/// end its boost with 0
+#[tauri::command]
+pub async fn boost_drive(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+    duration_secs: u64,
+) -> CommandResult<()> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .drive_manager
+        .boost_drive(&drive_id, std::time::Duration::from_secs(duration_secs))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Set the color/emoji label for a specific drive
+#[tauri::command]
+pub async fn set_drive_label(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+    color: Option<String>,
+    emoji: Option<String>,
+) -> CommandResult<()> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    let label = DriveLabel { color, emoji };
+    app_state
+        .drive_manager
+        .set_drive_label(&drive_id, Some(label))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Persist drive configurations after update
+    app_state
+        .drive_manager
+        .persist()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Scan a drive for broken placeholders and repair them
+#[tauri::command]
+pub async fn repair_placeholders(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+) -> CommandResult<RepairReport> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    user_action(app_state.drive_manager.repair_placeholders(&drive_id))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Report of a drive's first full sync, `None` until that sync finished
+#[tauri::command]
+pub async fn get_initial_sync_report(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+) -> CommandResult<Option<InitialSyncReport>> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .drive_manager
+        .get_initial_sync_report(&drive_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Find files with identical content in a drive
+#[tauri::command]
+pub async fn find_duplicate_files(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+) -> CommandResult<DuplicateReport> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .drive_manager
+        .find_duplicate_files(&drive_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Keep one of a group of duplicate files and delete or replace the others
+#[tauri::command]
+pub async fn resolve_duplicate_files(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+    keep: String,
+    extras: Vec<String>,
+    action: DuplicateAction,
+) -> CommandResult<DuplicateResolution> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    user_action(app_state.drive_manager.resolve_duplicates(
+        &drive_id,
+        keep.into(),
+        extras.into_iter().map(Into::into).collect(),
+        action,
+    ))
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// List inventory snapshots available for rollback, newest first
+#[tauri::command]
+pub async fn list_inventory_snapshots(
+    state: State<'_, AppStateHandle>,
+) -> CommandResult<Vec<InventorySnapshot>> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .drive_manager
+        .list_inventory_snapshots()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Roll local file metadata back to an inventory snapshot. Intended as a
+/// maintenance command after metadata corruption.
+#[tauri::command]
+pub async fn restore_inventory_snapshot(
+    state: State<'_, AppStateHandle>,
+    timestamp: i64,
+) -> CommandResult<()> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    user_action(
+        app_state
+            .drive_manager
+            .restore_inventory_snapshot(timestamp),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Delete inventory rows and icons left behind by removed drives, reporting
+/// the space reclaimed
+#[tauri::command]
+pub async fn purge_orphaned_data(state: State<'_, AppStateHandle>) -> CommandResult<PurgeReport> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    user_action(app_state.drive_manager.purge_orphaned_data())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// List downloaded files of a drive quarantined by the scanner
+#[tauri::command]
+pub async fn list_quarantined_files(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+) -> CommandResult<Vec<QuarantinedFile>> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .drive_manager
+        .list_quarantined_files(&drive_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Allow a quarantined file to be downloaded again
+#[tauri::command]
+pub async fn release_quarantined_file(
+    state: State<'_, AppStateHandle>,
+    path: String,
+) -> CommandResult<bool> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .drive_manager
+        .release_quarantined_file(&path)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// List files of a drive quarantined after their sync failed repeatedly
+#[tauri::command]
+pub async fn list_quarantined(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+) -> CommandResult<Vec<SyncFailure>> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .drive_manager
+        .list_sync_failures(&drive_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// List the sign-ins, token refreshes and credential updates of a drive,
+/// most recent first
+#[tauri::command]
+pub async fn list_credential_events(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+) -> CommandResult<Vec<CredentialEvent>> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .drive_manager
+        .list_credential_events(&drive_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Subscribe the calling window to `events`, so it is only sent those. Returns
+/// the critical events among them not acknowledged yet, oldest first, for
+/// windows opened after they were raised.
+#[tauri::command]
+pub async fn subscribe_events(
+    window: WebviewWindow,
+    state: State<'_, AppStateHandle>,
+    events: Vec<String>,
+) -> CommandResult<Vec<Event>> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state.event_bridge.subscribe(&window, &events);
+
+    let pending = app_state
+        .event_broadcaster
+        .pending_critical_events()
+        .map_err(|e| e.to_string())?;
+    Ok(pending
+        .into_iter()
+        .filter(|event| events.iter().any(|name| name == event.name()))
+        .collect())
+}
+
+/// Undo a subscription of the calling window to `events`
+#[tauri::command]
+pub async fn unsubscribe_events(
+    window: WebviewWindow,
+    state: State<'_, AppStateHandle>,
+    events: Vec<String>,
+) -> CommandResult<()> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state.event_bridge.unsubscribe(window.label(), &events);
+    Ok(())
+}
+
+/// The calling window handled `count` more of the events it subscribed to
+#[tauri::command]
+pub async fn ack_events(
+    window: WebviewWindow,
+    state: State<'_, AppStateHandle>,
+    count: usize,
+) -> CommandResult<()> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state.event_bridge.acknowledge(window.label(), count);
+    Ok(())
+}
+
+/// Acknowledge a critical event so it is not delivered again
+#[tauri::command]
+pub async fn acknowledge_event(
+    state: State<'_, AppStateHandle>,
+    event_id: String,
+) -> CommandResult<bool> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .event_broadcaster
+        .acknowledge(&event_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Take files out of the sync quarantine and sync them again
+#[tauri::command]
+pub async fn retry_quarantined(
+    state: State<'_, AppStateHandle>,
+    ids: Vec<i64>,
+) -> CommandResult<usize> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    user_action(app_state.drive_manager.retry_sync_failures(ids))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Keep files in the sync quarantine out of sync
+#[tauri::command]
+pub async fn ignore_quarantined(
+    state: State<'_, AppStateHandle>,
+    ids: Vec<i64>,
+) -> CommandResult<usize> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .drive_manager
+        .ignore_sync_failures(ids)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// List current problems of every drive for the issues panel, most severe first
+#[tauri::command]
+pub async fn get_issues(state: State<'_, AppStateHandle>) -> CommandResult<Vec<SyncIssue>> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .drive_manager
+        .get_issues()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Act on an issue from the issues panel: retry what can be retried, and open
+/// the window or folder the rest of the action needs
+#[tauri::command]
+pub async fn resolve_issue(
+    app: AppHandle,
+    state: State<'_, AppStateHandle>,
+    issue_id: String,
+) -> CommandResult<()> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    let issue = user_action(app_state.drive_manager.resolve_issue(&issue_id))
+        .await
+        .map_err(|e| e.to_string())?;
+    match issue.action {
+        IssueAction::ResolveConflict => {
+            if let (Some(file_id), Some(path)) = (issue.file_id, issue.path) {
+                window_manager::open(
+                    &app,
+                    AppWindow::Conflict {
+                        drive_id: issue.drive_id,
+                        file_id,
+                        path,
+                    },
+                );
+            }
+        }
+        IssueAction::Reauthorize => {
+            let mount = app_state
+                .drive_manager
+                .get_drive(&issue.drive_id)
+                .await
+                .ok_or_else(|| "Drive not found".to_string())?;
+            let config = mount.get_config().await;
+            show_reauthorize_window_impl(&app, &config.id, &config.instance_url, &config.name);
+        }
+        IssueAction::ReviewThreat | IssueAction::FreeDiskSpace => {
+            if let Some(path) = issue.path {
+                showfile::show_path_in_file_manager(&path);
+            }
+        }
+        IssueAction::RemoveDrive | IssueAction::FreeCloudSpace => {
+            show_settings_window_impl(&app);
+        }
+        IssueAction::Retry | IssueAction::Wait => {}
+    }
+    Ok(())
+}
+
+/// Get the total size and file count of a remote folder, e.g. before making it
+/// available offline
+#[tauri::command]
+pub async fn get_folder_summary(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+    path: String,
+) -> CommandResult<FolderSummary> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .drive_manager
+        .get_folder_summary(&drive_id, path.into())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Timeline of a file for the file details panel, oldest first
+#[tauri::command]
+pub async fn get_file_history(
+    state: State<'_, AppStateHandle>,
+    path: String,
+) -> CommandResult<Vec<FileHistoryEntry>> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .drive_manager
+        .get_file_history(path.into())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Get the depth of the command queues, for diagnostics
+#[tauri::command]
+pub async fn get_command_queue_stats(
+    state: State<'_, AppStateHandle>,
+) -> CommandResult<CommandQueueReport> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    Ok(app_state.drive_manager.command_queue_stats().await)
+}
+
+/// List drive templates, the ones provided by policy first
+#[tauri::command]
+pub async fn list_drive_templates(
+    state: State<'_, AppStateHandle>,
+) -> CommandResult<Vec<DriveTemplate>> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .drive_manager
+        .list_drive_templates()
+        .map_err(|e| e.to_string())
+}
+
+/// Create or update a drive template
+#[tauri::command]
+pub async fn save_drive_template(
+    state: State<'_, AppStateHandle>,
+    template: DriveTemplate,
+) -> CommandResult<DriveTemplate> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .drive_manager
+        .save_drive_template(template)
+        .map_err(|e| e.to_string())
+}
+
+/// Save the settings of an existing drive as a new template
+#[tauri::command]
+pub async fn save_drive_as_template(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+    name: String,
+) -> CommandResult<DriveTemplate> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .drive_manager
+        .save_drive_as_template(&drive_id, name)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Copy files into a remote folder on the server, possibly of another drive
+/// on the same account. Returns the ID of the task tracking the copy.
+#[tauri::command]
+pub async fn copy_remote_files(
+    state: State<'_, AppStateHandle>,
+    source_drive_id: String,
+    sources: Vec<String>,
+    destination_drive_id: String,
+    destination: String,
+) -> CommandResult<String> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    user_action(app_state.drive_manager.transfer_remote_files(
+        &source_drive_id,
+        sources,
+        &destination_drive_id,
+        destination,
+        true,
+    ))
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Move files into a remote folder on the server, possibly of another drive
+/// on the same account. Returns the ID of the task tracking the move.
+#[tauri::command]
+pub async fn move_remote_files(
+    state: State<'_, AppStateHandle>,
+    source_drive_id: String,
+    sources: Vec<String>,
+    destination_drive_id: String,
+    destination: String,
+) -> CommandResult<String> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    user_action(app_state.drive_manager.transfer_remote_files(
+        &source_drive_id,
+        sources,
+        &destination_drive_id,
+        destination,
+        false,
+    ))
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Search the server trash for files deleted from a drive
+#[tauri::command]
+pub async fn search_trash(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+    keyword: Option<String>,
+) -> CommandResult<Vec<TrashItem>> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    user_action(app_state.drive_manager.search_trash(&drive_id, keyword))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Restore files found with `search_trash` into their drive. Returns the ID
+/// of the task tracking the restore.
+#[tauri::command]
+pub async fn restore_from_trash(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+    items: Vec<TrashItem>,
+) -> CommandResult<String> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    user_action(app_state.drive_manager.restore_from_trash(&drive_id, items))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// List the share links exposing a folder of a drive or anything in it
+#[tauri::command]
+pub async fn inspect_folder_shares(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+    folder: String,
+) -> CommandResult<ShareReport> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    user_action(
+        app_state
+            .drive_manager
+            .inspect_folder_shares(&drive_id, PathBuf::from(folder)),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Export a downloaded copy of a remote folder into an empty local folder
+/// outside the sync roots, with a `MANIFEST.sha256` of its files. Returns the
+/// ID of the task tracking the export.
+#[tauri::command]
+pub async fn export_folder(
+    state: State<'_, AppStateHandle>,
+    drive_id: String,
+    source: String,
+    destination: String,
+) -> CommandResult<String> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    user_action(app_state.drive_manager.export_folder(
+        &drive_id,
+        source,
+        PathBuf::from(destination),
+    ))
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Recognize which sync provider a folder to import belongs to
+#[tauri::command]
+pub async fn detect_import_source(path: String) -> CommandResult<ImportSource> {
+    Ok(ImportSource::detect(Path::new(&path)))
+}
+
+/// Upload the folder of another sync provider into a drive. Returns the ID of
+/// the task tracking the import.
+#[tauri::command]
+pub async fn import_folder(
+    state: State<'_, AppStateHandle>,
+    args: ImportFolderArgs,
+) -> CommandResult<String> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    user_action(app_state.drive_manager.import_folder(
+        &args.drive_id,
+        PathBuf::from(args.source),
+        args.destination,
+        args.mappings,
+        args.skip_duplicates,
+        args.setup_sync_root,
+    ))
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Prefetch a synced folder in the background, downloading the content of
+/// files up to `max_file_size` bytes. Returns the ID of the task tracking it.
+#[tauri::command]
+pub async fn prefetch_folder(
+    state: State<'_, AppStateHandle>,
+    path: String,
+    max_file_size: Option<u64>,
+) -> CommandResult<String> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    user_action(
+        app_state
+            .drive_manager
+            .prefetch_folder(PathBuf::from(path), max_file_size),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Cancel a pending or running task
+#[tauri::command]
+pub async fn cancel_task(state: State<'_, AppStateHandle>, task_id: String) -> CommandResult<()> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .drive_manager
+        .cancel_task(&task_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Delete a drive template
+#[tauri::command]
+pub async fn delete_drive_template(
+    state: State<'_, AppStateHandle>,
+    template_id: String,
+) -> CommandResult<bool> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    app_state
+        .drive_manager
+        .delete_drive_template(&template_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Add a drive from a template
+#[tauri::command]
+pub async fn create_drive_from_template(
+    state: State<'_, AppStateHandle>,
+    args: CreateDriveFromTemplateArgs,
+) -> Result<String, AddDriveFailure> {
+    let app_state = state
+        .get()
+        .ok_or_else(|| "App not yet initialized".to_string())?;
+
+    if is_root_drive(&args.local_path) {
+        return Err(t!("localPathCannotBeRootDrive").to_string().into());
+    }
+    let import_existing = folder_has_entries(&args.local_path);
+    if import_existing && !args.merge_existing {
+        return Err(t!("localPathNotEmpty").to_string().into());
+    }
+
+    let credentials = credentials_from_tokens(
+        args.access_token,
+        args.refresh_token,
+        args.access_token_expires,
+        args.refresh_token_expires,
+    );
+    let id = match user_action(app_state.drive_manager.create_drive_from_template(
+        &args.template_id,
+        credentials,
+        &args.user_id,
+        args.local_path.into(),
+        args.drive_name,
+        import_existing,
+    ))
+    .await
+    {
+        Ok(id) => id,
+        Err(e) => {
+            return Err(match e.downcast::<AddDriveError>() {
+                Ok(error) => {
+                    AddDriveFailure::from_add_drive_error(&app_state.drive_manager, error).await
+                }
+                Err(e) => e.to_string().into(),
+            });
+        }
+    };
+
+    app_state
+        .drive_manager
+        .persist()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(id)
+}
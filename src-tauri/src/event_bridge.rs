@@ -0,0 +1,208 @@
+//! Bridge from the [`EventBroadcaster`] to the webviews.
+//!
+//! A window subscribes to the events it shows and gets them through a bounded
+//! channel of its own, so the settings window is not sent task progress. The
+//! window acknowledges the events it handled, and no more than
+//! [`MAX_IN_FLIGHT`] are sent to it unacknowledged: a webview that stops
+//! keeping up only fills its own channel, after which its events are dropped
+//! and counted instead of holding back the bridge. Critical events dropped
+//! this way are not lost, they stay in the outbox until acknowledged and are
+//! handed out again on the next subscription.
+//!
+//! Listeners not scoped to a subscribed window get every event, as before.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use cloudreve_sync::events::Event;
+use cloudreve_sync::EventBroadcaster;
+use tauri::async_runtime::spawn;
+use tauri::{AppHandle, Emitter, EventTarget, Manager, WebviewWindow, WindowEvent};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{broadcast, mpsc, Semaphore};
+
+use crate::event_handler;
+
+/// Events waiting for a window before newer ones are dropped
+const WINDOW_CHANNEL_CAPACITY: usize = 256;
+/// Events sent to a window that it has not acknowledged yet
+const MAX_IN_FLIGHT: usize = 32;
+
+/// Subscription of one window
+struct WindowChannel {
+    /// Names of the events the window wants, with the number of
+    /// subscriptions to each
+    events: HashMap<String, usize>,
+    sender: mpsc::Sender<Event>,
+    /// Events the window may still be sent before acknowledging
+    credits: Arc<Semaphore>,
+    /// Events dropped since the channel filled up
+    dropped: u64,
+}
+
+impl Drop for WindowChannel {
+    fn drop(&mut self) {
+        // Stop the forwarder even if it waits for an acknowledgment
+        self.credits.close();
+    }
+}
+
+/// Windows subscribed to events, by label
+#[derive(Default)]
+pub struct EventBridge {
+    windows: Mutex<HashMap<String, WindowChannel>>,
+}
+
+impl EventBridge {
+    /// Forward the events of `event_broadcaster` to the windows
+    pub fn spawn(self: &Arc<Self>, app_handle: AppHandle, event_broadcaster: &EventBroadcaster) {
+        let mut receiver = event_broadcaster.subscribe();
+        let bridge = self.clone();
+
+        spawn(async move {
+            tracing::info!(target: "events", "Event bridge started");
+
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => {
+                        event_handler::handle_event(&app_handle, &event);
+                        bridge.deliver(&app_handle, &event);
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        tracing::warn!(target: "events", skipped = n, "Event receiver lagged, some events were skipped");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        tracing::info!(target: "events", "Event broadcaster closed, stopping bridge");
+                        break;
+                    }
+                }
+            }
+        });
+    }
+
+    /// Subscribe `window` to `events`, in addition to those it already gets
+    pub fn subscribe(self: &Arc<Self>, window: &WebviewWindow, events: &[String]) {
+        let label = window.label();
+        let mut windows = self.windows.lock().unwrap();
+        let channel = windows.entry(label.to_string()).or_insert_with(|| {
+            let (sender, receiver) = mpsc::channel(WINDOW_CHANNEL_CAPACITY);
+            let credits = Arc::new(Semaphore::new(MAX_IN_FLIGHT));
+            spawn_forwarder(
+                window.app_handle().clone(),
+                label.to_string(),
+                receiver,
+                credits.clone(),
+            );
+
+            // Forget the window once closed, a window opened again with the
+            // same label starts afresh
+            let bridge = self.clone();
+            let closed_label = label.to_string();
+            window.on_window_event(move |event| {
+                if let WindowEvent::Destroyed = event {
+                    bridge.windows.lock().unwrap().remove(&closed_label);
+                }
+            });
+
+            tracing::debug!(target: "events", window = label, "Window subscribed to events");
+            WindowChannel {
+                events: HashMap::new(),
+                sender,
+                credits,
+                dropped: 0,
+            }
+        });
+        for event in events {
+            *channel.events.entry(event.clone()).or_default() += 1;
+        }
+    }
+
+    /// Undo a subscription of the window `label` to `events`. A window left
+    /// without subscriptions gets every event again.
+    pub fn unsubscribe(&self, label: &str, events: &[String]) {
+        let mut windows = self.windows.lock().unwrap();
+        let Some(window) = windows.get_mut(label) else {
+            return;
+        };
+        for event in events {
+            if let Some(count) = window.events.get_mut(event) {
+                *count -= 1;
+                if *count == 0 {
+                    window.events.remove(event);
+                }
+            }
+        }
+        if window.events.is_empty() {
+            windows.remove(label);
+            tracing::debug!(target: "events", window = label, "Window unsubscribed from events");
+        }
+    }
+
+    /// The window `label` handled `count` more events
+    pub fn acknowledge(&self, label: &str, count: usize) {
+        let windows = self.windows.lock().unwrap();
+        if let Some(window) = windows.get(label) {
+            let missing = MAX_IN_FLIGHT.saturating_sub(window.credits.available_permits());
+            window.credits.add_permits(count.min(missing));
+        }
+    }
+
+    /// Send `event` to the windows that want it
+    fn deliver(&self, app_handle: &AppHandle, event: &Event) {
+        let mut windows = self.windows.lock().unwrap();
+        for (label, window) in windows.iter_mut() {
+            if !window.events.contains_key(event.name()) {
+                continue;
+            }
+            match window.sender.try_send(event.clone()) {
+                Ok(()) if window.dropped > 0 => {
+                    tracing::info!(target: "events", window = %label, dropped = window.dropped, "Window caught up with events");
+                    window.dropped = 0;
+                }
+                Ok(()) | Err(TrySendError::Closed(_)) => {}
+                Err(TrySendError::Full(_)) => {
+                    if window.dropped == 0 {
+                        tracing::warn!(target: "events", window = %label, "Window is not keeping up, dropping its events");
+                    }
+                    window.dropped += 1;
+                }
+            }
+        }
+
+        // Windows that did not subscribe get every event
+        let subscribed: Vec<String> = windows.keys().cloned().collect();
+        drop(windows);
+        let result = app_handle.emit_filter(event.name(), event, |target| match target {
+            EventTarget::Window { label }
+            | EventTarget::Webview { label }
+            | EventTarget::WebviewWindow { label } => !subscribed.contains(label),
+            _ => true,
+        });
+        if let Err(e) = result {
+            tracing::error!(target: "events", error = %e, "Failed to emit event to frontend");
+        } else {
+            tracing::trace!(target: "events", event = ?event, "Event emitted to frontend");
+        }
+    }
+}
+
+/// Emit the events of a window's channel as it acknowledges earlier ones
+fn spawn_forwarder(
+    app_handle: AppHandle,
+    label: String,
+    mut receiver: mpsc::Receiver<Event>,
+    credits: Arc<Semaphore>,
+) {
+    spawn(async move {
+        while let Some(event) = receiver.recv().await {
+            match credits.acquire().await {
+                Ok(permit) => permit.forget(),
+                Err(_) => break,
+            }
+            let target = EventTarget::webview_window(label.as_str());
+            if let Err(e) = app_handle.emit_to(target, event.name(), &event) {
+                tracing::error!(target: "events", window = %label, error = %e, "Failed to emit event to window");
+            }
+        }
+    });
+}
@@ -1,7 +1,9 @@
 use cloudreve_sync::events::Event;
-use tauri::{AppHandle, Emitter};
+use tauri::AppHandle;
 
-use crate::commands::{show_add_drive_window_impl, show_main_window_center, show_settings_window_impl};
+use crate::commands::{show_add_drive_window_impl, show_settings_window_impl};
+use crate::window_manager::{self, AppWindow};
+use crate::TRAY_ID;
 
 /// Handle incoming events from the event broadcaster.
 /// Returns true if the event was handled, false otherwise.
@@ -13,6 +15,42 @@ pub fn handle_event(app_handle: &AppHandle, event: &Event) {
         }
         Event::OpenSyncStatusWindow => handle_open_sync_status_window(app_handle),
         Event::OpenSettingsWindow => handle_open_settings_window(app_handle),
+        Event::DriveLabelChanged { .. } => {
+            // Currently just forwarded to frontend via emit
+        }
+        Event::ConfigChanged { .. } => {
+            // Currently just forwarded to frontend via emit
+        }
+        Event::SecurityThreatDetected { .. } => {
+            // Currently just forwarded to frontend via emit
+        }
+        Event::PowerSavingChanged { .. } | Event::PresentationModeChanged { .. } => {
+            update_tray_tooltip(app_handle)
+        }
+        Event::MountStateChanged { .. } => {
+            // Currently just forwarded to frontend via emit
+        }
+        Event::SyncItemQuarantined { .. } => {
+            // Currently just forwarded to frontend via emit
+        }
+        Event::TaskProgress { .. } => {
+            // Currently just forwarded to frontend via emit
+        }
+        Event::ShareLinksCreated { .. } => {
+            // Currently just forwarded to frontend via emit
+        }
+        Event::ServerMaintenanceChanged { .. } => {
+            // Currently just forwarded to frontend via emit
+        }
+        Event::ReauthRequired { .. }
+        | Event::ConflictDetected { .. }
+        | Event::MassDeletionDetected { .. }
+        | Event::PinMismatch { .. } => {
+            // Forwarded to frontend via emit, kept until acknowledged
+        }
+        Event::EventAcknowledged { .. } => {
+            // Currently just forwarded to frontend via emit
+        }
     }
 }
 
@@ -21,19 +59,27 @@ fn handle_no_drive(app_handle: &AppHandle) {
 }
 
 fn handle_open_sync_status_window(app_handle: &AppHandle) {
-    // Open the main popup window which shows sync status
-    show_main_window_center(app_handle);
+    // Not anchored to the tray, so it stays open until closed
+    window_manager::open(app_handle, AppWindow::Status);
 }
 
 fn handle_open_settings_window(app_handle: &AppHandle) {
     show_settings_window_impl(app_handle);
 }
 
-/// Emit an event to the frontend
-pub fn emit_event(app_handle: &AppHandle, event: &Event) {
-    if let Err(e) = app_handle.emit(event.name(), event) {
-        tracing::error!(target: "events", error = %e, "Failed to emit event to frontend");
+/// Show in the tray tooltip why sync is slowed down or paused
+fn update_tray_tooltip(app_handle: &AppHandle) {
+    let Some(tray) = app_handle.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    let tooltip = if cloudreve_sync::presentation::is_presenting() {
+        t!("presentationModeTooltip").to_string()
+    } else if cloudreve_sync::power::is_power_saving() {
+        t!("powerSavingTooltip").to_string()
     } else {
-        tracing::trace!(target: "events", event = ?event, "Event emitted to frontend");
+        app_handle.package_info().name.clone()
+    };
+    if let Err(e) = tray.set_tooltip(Some(tooltip)) {
+        tracing::warn!(target: "events", error = %e, "Failed to update tray tooltip");
     }
 }
@@ -0,0 +1,237 @@
+//! Request and response types of the Tauri commands.
+//!
+//! Every shape that crosses the IPC boundary is defined or re-exported here,
+//! so the command surface can be read in one place. The frontend mirrors these
+//! in `ui/src/ipc.ts`; field names are sent as is (snake_case) unless a type
+//! says otherwise.
+
+use cloudreve_sync::{AddDriveError, DriveManager};
+use serde::{Deserialize, Serialize};
+
+// Drives
+pub use cloudreve_sync::{
+    DriveConfig, DriveInfo, DriveLabel, DriveTemplate, DuplicateAction, DuplicateReport,
+    DuplicateResolution, FileCategory, FolderSummary, InitialSyncReport, NetworkSettings, PinKind,
+    PurgeReport, RepairReport, SyncDirection, TlsPin, TypeFilter, TypeRule,
+};
+// Files
+pub use cloudreve_sync::tasks::{ImportMapping, ImportSource};
+pub use cloudreve_sync::{FileHistoryEntry, ShareReport, SharedItem, TrashItem};
+// Tasks
+pub use cloudreve_sync::{CommandQueueReport, CompressionSettings, StatusSummary, TransferLimits};
+// Conflicts and files set aside
+pub use cloudreve_sync::inventory::{
+    CredentialEvent, InventorySnapshot, QuarantinedFile, SyncFailure,
+};
+pub use cloudreve_sync::{IssueAction, SyncIssue};
+// Settings
+pub use cloudreve_sync::{
+    BatterySaver, DownloadScan, FairScheduling, IdleSchedule, Policy, PresentationMode,
+};
+
+/// Drive to add, or existing drive to reauthorize, as completed by the
+/// add-drive wizard
+#[derive(Debug, Deserialize)]
+pub struct AddDriveArgs {
+    pub site_url: String,
+    pub access_token: String,
+    pub refresh_token: String,
+    pub access_token_expires: u64,
+    pub refresh_token_expires: u64,
+    pub drive_name: String,
+    /// May contain variables like `{hostname}`, replaced when the drive is
+    /// added
+    pub remote_path: String,
+    pub local_path: String,
+    pub user_id: String,
+    /// Reauthorize this drive instead of adding a new one
+    pub drive_id: Option<String>,
+    /// Import files already present in a non-empty local folder
+    #[serde(default)]
+    pub merge_existing: bool,
+    /// Hashes the server certificate must match from now on
+    #[serde(default)]
+    pub tls_pins: Vec<TlsPin>,
+    /// Pin the certificate the server presents while the drive is added,
+    /// trusting it on first use. Ignored if `tls_pins` is given.
+    #[serde(default)]
+    pub pin_server: Option<PinKind>,
+}
+
+/// Drive to add from a template. Everything but the credentials and the local
+/// folder comes from the template.
+#[derive(Debug, Deserialize)]
+pub struct CreateDriveFromTemplateArgs {
+    pub template_id: String,
+    pub access_token: String,
+    pub refresh_token: String,
+    pub access_token_expires: u64,
+    pub refresh_token_expires: u64,
+    /// Defaults to the template name
+    pub drive_name: Option<String>,
+    pub local_path: String,
+    pub user_id: String,
+    /// Import files already present in a non-empty local folder
+    #[serde(default)]
+    pub merge_existing: bool,
+}
+
+/// Drive to add from a public share link, browsed without an account
+#[derive(Debug, Deserialize)]
+pub struct AddShareDriveArgs {
+    /// Link to the share, such as `https://cloud.example.com/s/AbC1`
+    pub share_url: String,
+    /// Password of a password protected share, if not part of the link
+    pub password: Option<String>,
+    /// Defaults to the name of the share
+    pub drive_name: Option<String>,
+    pub local_path: String,
+    /// Import files already present in a non-empty local folder
+    #[serde(default)]
+    pub merge_existing: bool,
+}
+
+/// Folder of another sync provider to upload, as set up in the migration
+/// assistant
+#[derive(Debug, Deserialize)]
+pub struct ImportFolderArgs {
+    pub drive_id: String,
+    /// Local folder to import
+    pub source: String,
+    /// Local path in the drive or `cloudreve://` URI to import into
+    pub destination: String,
+    #[serde(default)]
+    pub mappings: Vec<ImportMapping>,
+    /// Don't upload files with the same content as one already imported
+    #[serde(default)]
+    pub skip_duplicates: bool,
+    /// Sync `source` as a new drive once imported
+    #[serde(default)]
+    pub setup_sync_root: bool,
+}
+
+/// Error from adding a drive, shaped like the wizard's validation errors
+/// (`type` and `params`) so it can pick the message to show
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "params", rename_all = "camelCase")]
+pub enum AddDriveFailure {
+    /// The same drive is already added. The wizard offers to adopt it, which
+    /// reauthorizes the existing drive with the new credentials.
+    DriveAlreadyAdded {
+        id: String,
+        name: String,
+    },
+    /// The local folder is synced by another drive
+    SyncPathInUse {
+        name: String,
+    },
+    AddDriveFailed {
+        message: String,
+    },
+}
+
+impl From<String> for AddDriveFailure {
+    fn from(message: String) -> Self {
+        AddDriveFailure::AddDriveFailed { message }
+    }
+}
+
+impl AddDriveFailure {
+    pub(crate) async fn from_add_drive_error(
+        drive_manager: &DriveManager,
+        error: AddDriveError,
+    ) -> Self {
+        let existing_id = match &error {
+            AddDriveError::AlreadyExists { existing_id }
+            | AddDriveError::SyncPathInUse { existing_id } => existing_id.clone(),
+        };
+        let name = match drive_manager.get_drive(&existing_id).await {
+            Some(mount) => mount.get_config().await.name,
+            None => existing_id.clone(),
+        };
+        match error {
+            AddDriveError::AlreadyExists { .. } => AddDriveFailure::DriveAlreadyAdded {
+                id: existing_id,
+                name,
+            },
+            AddDriveError::SyncPathInUse { .. } => AddDriveFailure::SyncPathInUse { name },
+        }
+    }
+}
+
+/// File icon response containing base64 encoded RGBA pixel data
+#[derive(Debug, Serialize)]
+pub struct FileIconResponse {
+    /// Base64 encoded RGBA pixel data
+    pub data: String,
+    /// Icon width in pixels
+    pub width: u32,
+    /// Icon height in pixels
+    pub height: u32,
+}
+
+/// Application-wide settings shown on the general settings page
+#[derive(Debug, Serialize)]
+pub struct GeneralSettings {
+    pub notify_credential_expired: bool,
+    pub notify_file_conflict: bool,
+    pub fast_popup_launch: bool,
+    pub log_to_file: bool,
+    /// One of `trace`, `debug`, `info`, `warn` or `error`
+    pub log_level: String,
+    pub log_max_files: usize,
+    /// Requests to the servers are logged, with credentials redacted
+    pub http_trace: bool,
+    pub log_dir: String,
+    pub language: Option<String>,
+    pub download_scan: DownloadScan,
+    pub battery_saver: BatterySaver,
+    pub idle_schedule: IdleSchedule,
+    pub presentation_mode: PresentationMode,
+    pub fair_scheduling: FairScheduling,
+    /// Settings locked by administrator policy
+    pub locked_settings: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wire_shapes_match_frontend() {
+        let args: AddDriveArgs = serde_json::from_value(serde_json::json!({
+            "site_url": "https://cloud.example.com",
+            "access_token": "a",
+            "refresh_token": "r",
+            "access_token_expires": 3600,
+            "refresh_token_expires": 86400,
+            "drive_name": "Work",
+            "remote_path": "cloudreve://my",
+            "local_path": "C:\\Users\\me\\Work",
+            "user_id": "u1",
+        }))
+        .unwrap();
+        assert_eq!(args.drive_id, None);
+        assert!(!args.merge_existing);
+
+        let share: AddShareDriveArgs = serde_json::from_value(serde_json::json!({
+            "share_url": "https://cloud.example.com/s/AbC1",
+            "local_path": "C:\\Users\\me\\Shared",
+        }))
+        .unwrap();
+        assert_eq!(share.password, None);
+        assert_eq!(share.drive_name, None);
+
+        let failure = AddDriveFailure::DriveAlreadyAdded {
+            id: "d1".to_string(),
+            name: "Work".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(&failure).unwrap(),
+            serde_json::json!({
+                "type": "driveAlreadyAdded",
+                "params": { "id": "d1", "name": "Work" },
+            })
+        );
+    }
+}
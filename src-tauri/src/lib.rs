@@ -1,4 +1,5 @@
 use anyhow::Context;
+use cloudreve_sync::sdk::{EngineOptions, SyncEngine};
 use cloudreve_sync::{ConfigManager, DriveManager, EventBroadcaster, LogConfig, LogGuard, shellext::shell_service::ServiceHandle};
 use std::sync::{Arc, Mutex};
 use tauri::{
@@ -11,28 +12,26 @@ use tauri_plugin_deep_link::DeepLinkExt;
 use tokio::sync::OnceCell;
 
 use crate::commands::{show_add_drive_window_impl, show_main_window, show_settings_window_impl};
+use crate::event_bridge::EventBridge;
+use crate::window_manager::AppWindow;
 mod commands;
+mod event_bridge;
 mod event_handler;
+mod ipc;
+mod window_manager;
 
 #[macro_use]
 extern crate rust_i18n;
 
-i18n!("../locales");
+i18n!("../locales", fallback = "en-US");
 
 /// Initialize i18n based on config setting or system locale
 fn init_i18n() {
-    use rust_i18n::set_locale;
-    use sys_locale::get_locale;
-
-    // Try to get language from config, fallback to system locale
-    let locale = ConfigManager::try_get()
-        .and_then(|cm| cm.language())
-        .unwrap_or_else(|| get_locale().unwrap_or_else(|| String::from("en-US")));
-    set_locale(locale.as_str());
+    cloudreve_sync::utils::locale::apply_locale(&get_requested_locale());
 }
 
-/// Get the current effective locale (from config or system)
-pub fn get_effective_locale() -> String {
+/// Get the locale requested by the user (from config or system), before fallback
+fn get_requested_locale() -> String {
     use sys_locale::get_locale;
 
     ConfigManager::try_get()
@@ -40,10 +39,21 @@ pub fn get_effective_locale() -> String {
         .unwrap_or_else(|| get_locale().unwrap_or_else(|| String::from("en-US")))
 }
 
+/// Get the current effective locale (from config or system, resolved against
+/// the available translations)
+pub fn get_effective_locale() -> String {
+    use cloudreve_sync::utils::locale::{available_locales, resolve_locale};
+
+    resolve_locale(&get_requested_locale(), &available_locales())
+}
+
 /// Application state containing the drive manager and event broadcaster
 pub struct AppState {
     pub drive_manager: Arc<DriveManager>,
     pub event_broadcaster: Arc<EventBroadcaster>,
+    pub event_bridge: Arc<EventBridge>,
+    /// Owns the drive manager and event broadcaster above
+    engine: SyncEngine,
     // Keep the log guard alive for the entire application lifetime
     #[allow(dead_code)]
     log_guard: LogGuard,
@@ -66,28 +76,22 @@ async fn init_sync_service(app: AppHandle) -> anyhow::Result<()> {
 
     tracing::info!(target: "main", "Starting Cloudreve Sync Service (Tauri)...");
 
-    // Initialize EventBroadcaster
-    let event_broadcaster = Arc::new(EventBroadcaster::new(100));
-    tracing::info!(target: "main", "Event broadcasting system initialized");
+    // Initialize the sync engine (EventBroadcaster and DriveManager)
+    tracing::info!(target: "main", "Initializing sync engine...");
+    let engine =
+        SyncEngine::new(EngineOptions::default()).context("Failed to create sync engine")?;
+    let drive_manager = engine.drive_manager().clone();
+    let event_broadcaster = engine.event_broadcaster().clone();
 
     // Spawn event bridge to forward events to tarui
-    spawn_event_bridge(app.clone(), &event_broadcaster);
+    let event_bridge = Arc::new(EventBridge::default());
+    event_bridge.spawn(app.clone(), &event_broadcaster);
 
-    // Initialize DriveManager
-    tracing::info!(target: "main", "Initializing DriveManager...");
-    let drive_manager = Arc::new(
-        DriveManager::new(event_broadcaster.clone()).context("Failed to create DriveManager")?,
-    );
-
-    // Spawn command processor for DriveManager
-    drive_manager.spawn_command_processor().await;
-    tracing::info!(target: "main", "DriveManager command processor started");
-
-    // Load drive configurations from disk
-    drive_manager
-        .load()
+    // Start background tasks and load drive configurations from disk
+    engine
+        .start()
         .await
-        .context("Failed to load drive configurations")?;
+        .context("Failed to start sync engine")?;
 
     // Initialize and start the shell services (context menu handler) in a separate thread
     let mut shell_service =
@@ -101,13 +105,12 @@ async fn init_sync_service(app: AppHandle) -> anyhow::Result<()> {
         tracing::info!(target: "main", "Shell services initialized successfully!");
     }
 
-    // Broadcast initial connection status
-    event_broadcaster.connection_status_changed(true);
-
     // Store the state in the global cell
     let state = AppState {
         drive_manager,
         event_broadcaster: event_broadcaster.clone(),
+        event_bridge,
+        engine,
         log_guard,
         shell_service: Mutex::new(shell_service),
     };
@@ -133,46 +136,14 @@ impl AppStateHandle {
     }
 }
 
-/// Spawn a task that bridges EventBroadcaster to Tauri events
-fn spawn_event_bridge(app_handle: AppHandle, event_broadcaster: &EventBroadcaster) {
-    let mut receiver = event_broadcaster.subscribe();
-
-    spawn(async move {
-        tracing::info!(target: "events", "Event bridge started");
-
-        loop {
-            match receiver.recv().await {
-                Ok(event) => {
-                    event_handler::handle_event(&app_handle, &event);
-                    event_handler::emit_event(&app_handle, &event);
-                }
-                Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
-                    tracing::warn!(target: "events", skipped = n, "Event receiver lagged, some events were skipped");
-                }
-                Err(tokio::sync::broadcast::error::RecvError::Closed) => {
-                    tracing::info!(target: "events", "Event broadcaster closed, stopping bridge");
-                    break;
-                }
-            }
-        }
-    });
-}
-
 /// Perform graceful shutdown
 async fn shutdown() {
     tracing::info!(target: "main", "Initiating shutdown...");
 
     if let Some(state) = APP_STATE.get() {
-        // Broadcast disconnection event
-        state.event_broadcaster.connection_status_changed(false);
-
-        // Shutdown drive manager
-        tracing::info!(target: "main", "Shutting down drive manager...");
-        state.drive_manager.shutdown().await;
-
-        // Persist drive state
-        tracing::info!(target: "main", "Persisting drive configurations...");
-        if let Err(e) = state.drive_manager.persist().await {
+        // Shutdown sync engine and persist drive state
+        tracing::info!(target: "main", "Shutting down sync engine...");
+        if let Err(e) = state.engine.shutdown().await {
             tracing::error!(target: "main", error = %e, "Failed to persist drive configurations");
         } else {
             tracing::info!(target: "main", "Drive configurations saved successfully");
@@ -182,10 +153,20 @@ async fn shutdown() {
     tracing::info!(target: "main", "Shutdown complete");
 }
 
+/// Id of the system tray icon
+pub(crate) const TRAY_ID: &str = "main";
+
 /// Setup the system tray icon
 fn setup_tray(app: &tauri::App) -> anyhow::Result<()> {
     // Create menu items
     let show_i = MenuItem::with_id(app, "show", t!("show").as_ref(), true, None::<&str>)?;
+    let status_i = MenuItem::with_id(
+        app,
+        "status",
+        t!("syncStatus").as_ref(),
+        true,
+        None::<&str>,
+    )?;
     let add_drive_i = MenuItem::with_id(
         app,
         "add_drive",
@@ -201,10 +182,13 @@ fn setup_tray(app: &tauri::App) -> anyhow::Result<()> {
         None::<&str>,
     )?;
     let quit_i = MenuItem::with_id(app, "quit", t!("quit").as_ref(), true, None::<&str>)?;
-    let menu = Menu::with_items(app, &[&show_i, &add_drive_i, &settings_i, &quit_i])?;
+    let menu = Menu::with_items(
+        app,
+        &[&show_i, &status_i, &add_drive_i, &settings_i, &quit_i],
+    )?;
 
     // Build tray icon
-    TrayIconBuilder::new()
+    TrayIconBuilder::with_id(TRAY_ID)
         .icon(app.default_window_icon().unwrap().clone())
         .menu(&menu)
         .show_menu_on_left_click(false)
@@ -212,6 +196,9 @@ fn setup_tray(app: &tauri::App) -> anyhow::Result<()> {
             "show" => {
                 show_main_window(app);
             }
+            "status" => {
+                window_manager::open(app, AppWindow::Status);
+            }
             "add_drive" => {
                 show_add_drive_window_impl(app);
             }
@@ -253,7 +240,7 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_single_instance::init(|app, argv, _cwd| {
             tracing::info!("a new app instance was opened with {argv:?} and the deep link event was already triggered");
-            if argv.len() > 1 {
+            if argv.len() > 1 && !window_manager::open_deep_link(app, &argv[1]) {
                 let _ = app.emit("deeplink", argv[1].clone());
                 show_add_drive_window_impl(app);
             }
@@ -295,7 +282,18 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             commands::list_drives,
             commands::add_drive,
+            commands::preview_remote_path,
+            commands::fetch_server_pin,
+            commands::local_folder_has_files,
             commands::remove_drive,
+            commands::mount_drive,
+            commands::unmount_drive,
+            commands::update_drive,
+            commands::relink_sync_root,
+            commands::relocate_sync_root,
+            commands::recreate_sync_root,
+            commands::restore_suspicious_changes,
+            commands::dismiss_suspicious_changes,
             commands::get_sync_status,
             commands::get_status_summary,
             commands::get_drives_info,
@@ -304,19 +302,80 @@ pub fn run() {
             commands::show_add_drive_window,
             commands::show_reauthorize_window,
             commands::show_settings_window,
+            commands::show_status_window,
+            commands::show_conflict_window,
+            commands::resolve_conflict,
             commands::get_auto_start_enabled,
             commands::set_auto_start,
             commands::set_notify_credential_expired,
             commands::set_notify_file_conflict,
+            commands::set_download_scan,
+            commands::set_battery_saver,
+            commands::set_idle_schedule,
+            commands::set_presentation_mode,
+            commands::set_fair_scheduling,
+            commands::run_deferred_work,
             commands::set_fast_popup_launch,
             commands::get_general_settings,
+            commands::get_policy,
             commands::set_log_to_file,
             commands::set_log_level,
             commands::set_log_max_files,
+            commands::set_http_trace,
             commands::set_language,
+            commands::available_locales,
             commands::open_log_folder,
             commands::get_sync_direction,
             commands::set_sync_direction,
+            commands::get_network_settings,
+            commands::set_network_settings,
+            commands::get_compression_settings,
+            commands::set_compression_settings,
+            commands::get_transfer_limits,
+            commands::set_transfer_limits,
+            commands::get_type_filter,
+            commands::set_type_filter,
+            commands::boost_drive,
+            commands::set_drive_label,
+            commands::repair_placeholders,
+            commands::get_initial_sync_report,
+            commands::find_duplicate_files,
+            commands::resolve_duplicate_files,
+            commands::list_inventory_snapshots,
+            commands::restore_inventory_snapshot,
+            commands::purge_orphaned_data,
+            commands::list_quarantined_files,
+            commands::release_quarantined_file,
+            commands::list_quarantined,
+            commands::retry_quarantined,
+            commands::ignore_quarantined,
+            commands::list_credential_events,
+            commands::subscribe_events,
+            commands::unsubscribe_events,
+            commands::ack_events,
+            commands::acknowledge_event,
+            commands::get_issues,
+            commands::resolve_issue,
+            commands::get_folder_summary,
+            commands::get_file_history,
+            commands::get_command_queue_stats,
+            commands::list_drive_templates,
+            commands::save_drive_template,
+            commands::save_drive_as_template,
+            commands::delete_drive_template,
+            commands::create_drive_from_template,
+            commands::add_share_drive,
+            commands::add_simulated_drive,
+            commands::copy_remote_files,
+            commands::move_remote_files,
+            commands::search_trash,
+            commands::restore_from_trash,
+            commands::inspect_folder_shares,
+            commands::export_folder,
+            commands::detect_import_source,
+            commands::import_folder,
+            commands::prefetch_folder,
+            commands::cancel_task,
         ])
         .build(tauri::generate_context!())
         .expect("error while building tauri application")
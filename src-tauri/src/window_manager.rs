@@ -0,0 +1,240 @@
+//! Windows opened on demand from the tray, the shell and deep links.
+//!
+//! Each window has a fixed label, so opening it again focuses the existing
+//! one. Where a window was and how large it was is remembered when it is
+//! closed, and restored when it opens again.
+
+use crate::commands::get_url_with_lang;
+use cloudreve_sync::{ConfigManager, WindowGeometry};
+#[cfg(target_os = "macos")]
+use tauri::TitleBarStyle;
+use tauri::{
+    webview::WebviewWindowBuilder, AppHandle, Manager, PhysicalPosition, PhysicalSize, WebviewUrl,
+    WebviewWindow, WindowEvent,
+};
+use tauri_plugin_frame::WebviewWindowExt;
+use tauri_plugin_positioner::{Position, WindowExt};
+
+/// A window the app opens on demand
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppWindow {
+    /// Sync status, detached from the tray
+    Status,
+    Settings,
+    /// Resolution of the conflict of one file
+    Conflict {
+        drive_id: String,
+        file_id: i64,
+        path: String,
+    },
+}
+
+impl AppWindow {
+    /// Label of the window. Conflicts get one window per file.
+    fn label(&self) -> String {
+        match self {
+            AppWindow::Status => "status".to_string(),
+            AppWindow::Settings => "settings".to_string(),
+            AppWindow::Conflict { file_id, path, .. } => {
+                // Files only on disk have no ID yet
+                let key = if *file_id > 0 {
+                    file_id.to_string()
+                } else {
+                    format!("{:x}", fnv1a(path))
+                };
+                format!("conflict-{}", key)
+            }
+        }
+    }
+
+    /// Key the geometry is stored under, shared by windows of the same kind
+    fn geometry_key(&self) -> &'static str {
+        match self {
+            AppWindow::Status => "status",
+            AppWindow::Settings => "settings",
+            AppWindow::Conflict { .. } => "conflict",
+        }
+    }
+
+    fn route(&self) -> String {
+        match self {
+            AppWindow::Status => "index.html/#/status".to_string(),
+            AppWindow::Settings => "index.html/#/settings".to_string(),
+            AppWindow::Conflict {
+                drive_id,
+                file_id,
+                path,
+            } => format!(
+                "index.html/#/conflict/{}/{}/{}",
+                drive_id,
+                file_id,
+                urlencoding::encode(path)
+            ),
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            AppWindow::Status => "Sync Status",
+            AppWindow::Settings => "Settings",
+            AppWindow::Conflict { .. } => "Resolve Conflict",
+        }
+    }
+
+    /// Default and minimum inner size, in logical pixels
+    fn sizes(&self) -> ((f64, f64), (f64, f64)) {
+        match self {
+            AppWindow::Status => ((370.0, 530.0), (320.0, 400.0)),
+            AppWindow::Settings => ((700.0, 500.0), (600.0, 400.0)),
+            AppWindow::Conflict { .. } => ((460.0, 320.0), (400.0, 280.0)),
+        }
+    }
+}
+
+/// Show the window, creating it if it is not open yet
+pub fn open(app: &AppHandle, window: AppWindow) {
+    let label = window.label();
+    if let Some(existing) = app.get_webview_window(&label) {
+        let _ = existing.show();
+        let _ = existing.unminimize();
+        let _ = existing.set_focus();
+        return;
+    }
+
+    let ((width, height), (min_width, min_height)) = window.sizes();
+    let builder = WebviewWindowBuilder::new(
+        app,
+        &label,
+        WebviewUrl::App(get_url_with_lang(&window.route()).into()),
+    )
+    .title(window.title())
+    .inner_size(width, height)
+    .min_inner_size(min_width, min_height)
+    .visible(false)
+    .resizable(true)
+    .decorations(false)
+    .minimizable(true);
+
+    // Platform-specific: title_bar_style and hidden_title are macOS-only
+    #[cfg(target_os = "macos")]
+    let builder = builder
+        .title_bar_style(TitleBarStyle::Overlay)
+        .hidden_title(true);
+
+    let created = match builder.build() {
+        Ok(created) => created,
+        Err(e) => {
+            tracing::error!(target: "main", window = %label, error = %e, "Failed to create window");
+            return;
+        }
+    };
+
+    let geometry_key = window.geometry_key();
+    match ConfigManager::get().window_geometry(geometry_key) {
+        Some(geometry) if is_on_screen(&created, &geometry) => {
+            let _ = created.set_size(PhysicalSize::new(geometry.width, geometry.height));
+            let _ = created.set_position(PhysicalPosition::new(geometry.x, geometry.y));
+        }
+        _ => {
+            let _ = created.move_window(Position::Center);
+        }
+    }
+
+    let window_clone = created.clone();
+    created.on_window_event(move |event| {
+        if let WindowEvent::CloseRequested { .. } = event {
+            save_geometry(&window_clone, geometry_key);
+        }
+    });
+
+    let _ = created.create_overlay_titlebar();
+    let _ = created.show();
+    let _ = created.set_focus();
+}
+
+/// Open the window a `cloudreve://` deep link points to. Returns false if the
+/// link is not about a window.
+pub fn open_deep_link(app: &AppHandle, link: &str) -> bool {
+    match parse_deep_link(link) {
+        Some(window) => {
+            open(app, window);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Window of `cloudreve://status`, `cloudreve://settings` and
+/// `cloudreve://conflict?drive=<id>&file=<id>&path=<path>`
+fn parse_deep_link(link: &str) -> Option<AppWindow> {
+    let rest = link.strip_prefix("cloudreve://")?;
+    let (target, query) = rest.split_once('?').unwrap_or((rest, ""));
+    match target.trim_end_matches('/') {
+        "status" => Some(AppWindow::Status),
+        "settings" => Some(AppWindow::Settings),
+        "conflict" => {
+            let mut drive_id = None;
+            let mut file_id = 0;
+            let mut path = None;
+            for pair in query.split('&') {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                let value = urlencoding::decode(value).ok()?.into_owned();
+                match key {
+                    "drive" => drive_id = Some(value),
+                    "file" => file_id = value.parse().ok()?,
+                    "path" => path = Some(value),
+                    _ => {}
+                }
+            }
+            Some(AppWindow::Conflict {
+                drive_id: drive_id?,
+                file_id,
+                path: path?,
+            })
+        }
+        _ => None,
+    }
+}
+
+fn save_geometry(window: &WebviewWindow, key: &str) {
+    // A minimized window reports a position far off screen
+    if window.is_minimized().unwrap_or(false) {
+        return;
+    }
+    let (Ok(position), Ok(size)) = (window.outer_position(), window.inner_size()) else {
+        return;
+    };
+    let geometry = WindowGeometry {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+    };
+    if let Err(e) = ConfigManager::get().set_window_geometry(key, geometry) {
+        tracing::warn!(target: "main", window = %key, error = %e, "Failed to save window geometry");
+    }
+}
+
+/// Whether the top left corner of `geometry` is on one of the monitors, so a
+/// window is not restored where a since disconnected monitor was
+fn is_on_screen(window: &WebviewWindow, geometry: &WindowGeometry) -> bool {
+    let Ok(monitors) = window.available_monitors() else {
+        return false;
+    };
+    monitors.iter().any(|monitor| {
+        let origin = monitor.position();
+        let size = monitor.size();
+        geometry.x >= origin.x
+            && geometry.y >= origin.y
+            && geometry.x < origin.x + size.width as i32
+            && geometry.y < origin.y + size.height as i32
+    })
+}
+
+/// Stable hash of a path, for window labels, which only allow a few
+/// characters
+fn fnv1a(value: &str) -> u64 {
+    value.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
@@ -15,6 +15,7 @@ import { createAppTheme } from "./theme";
 import AddDrive from "./pages/AddDrive";
 import Popup from "./pages/popup";
 import Settings from "./pages/settings";
+import Conflict from "./pages/conflict";
 
 function LoadingFallback() {
   return (
@@ -47,6 +48,8 @@ function App() {
             <Route path="/reauthorize/:driveId/:siteUrl/:driveName" element={<AddDrive mode="reauthorize" />} />
             <Route path="/popup" element={<Popup />} />
             <Route path="/settings" element={<Settings />} />
+            <Route path="/status" element={<Popup detached />} />
+            <Route path="/conflict/:driveId/:fileId/:path" element={<Conflict />} />
           </Routes>
         </HashRouter>
       </ThemeProvider>
@@ -0,0 +1,191 @@
+// Request and response types of the Tauri commands, mirroring
+// src-tauri/src/ipc.rs. Keep both sides in sync when a shape changes.
+
+export type { FileIconResponse } from "./pages/popup/types";
+
+/** Drive to add, or existing drive to reauthorize (`add_drive`) */
+export interface AddDriveArgs {
+  site_url: string;
+  access_token: string;
+  refresh_token: string;
+  access_token_expires: number;
+  refresh_token_expires: number;
+  drive_name: string;
+  /** May contain `{hostname}`, `{username}`, `{user_id}` and `{date}`, see `preview_remote_path` */
+  remote_path: string;
+  local_path: string;
+  user_id: string;
+  /** Reauthorize this drive instead of adding a new one */
+  drive_id?: string;
+  /** Import files already present in a non-empty local folder */
+  merge_existing?: boolean;
+  /** Hashes the server certificate must match from now on */
+  tls_pins?: TlsPin[];
+  /** Pin the certificate presented while the drive is added; ignored if `tls_pins` is given */
+  pin_server?: PinKind;
+}
+
+/** What part of the server certificate a pin hashes */
+export type PinKind = "certificate" | "public_key";
+
+/** SHA-256 hash of a server certificate or its public key (`fetch_server_pin`) */
+export interface TlsPin {
+  kind: PinKind;
+  /** Base64 encoded */
+  sha256: string;
+}
+
+/** Drive to add from a template (`create_drive_from_template`) */
+export interface CreateDriveFromTemplateArgs {
+  template_id: string;
+  access_token: string;
+  refresh_token: string;
+  access_token_expires: number;
+  refresh_token_expires: number;
+  /** Defaults to the template name */
+  drive_name?: string;
+  local_path: string;
+  user_id: string;
+  merge_existing?: boolean;
+}
+
+/** Drive to add from a public share link, without signing in (`add_share_drive`) */
+export interface AddShareDriveArgs {
+  share_url: string;
+  /** Password of a password protected share, if not part of the link */
+  password?: string;
+  /** Defaults to the name of the share */
+  drive_name?: string;
+  local_path: string;
+  merge_existing?: boolean;
+}
+
+/**
+ * New name and folders of a drive (`update_drive`). The remote folder can
+ * only be changed while the local folder is empty: its files are not
+ * remapped, add another drive to sync a different remote folder instead.
+ */
+export interface UpdateDriveArgs {
+  drive_id: string;
+  /** Saved config of the drive with a new `name`, `remote_path` or `sync_path`; other fields are ignored */
+  config: Record<string, unknown> & {
+    name: string;
+    remote_path: string;
+    sync_path: string;
+  };
+}
+
+/** Sync provider of a folder to import (`detect_import_source`) */
+export type ImportSource = "one_drive" | "dropbox" | "nextcloud" | "other";
+
+/** Moves the files under `source` to `target` in the destination */
+export interface ImportMapping {
+  /** Folder relative to the imported folder */
+  source: string;
+  /** `/`-separated folder relative to the destination, `""` for the destination itself, `null` to leave the files out */
+  target: string | null;
+}
+
+/** Folder of another sync provider to upload (`import_folder`) */
+export interface ImportFolderArgs {
+  drive_id: string;
+  source: string;
+  /** Local path in the drive or `cloudreve://` URI */
+  destination: string;
+  mappings?: ImportMapping[];
+  skip_duplicates?: boolean;
+  /** Sync `source` as a new drive once imported; not possible with mappings */
+  setup_sync_root?: boolean;
+}
+
+/** Error of `add_drive`, `create_drive_from_template` and `add_share_drive` */
+export type AddDriveFailure =
+  | { type: "driveAlreadyAdded"; params: { id: string; name: string } }
+  | { type: "syncPathInUse"; params: { name: string } }
+  | { type: "addDriveFailed"; params: { message: string } };
+
+export interface IdleSchedule {
+  enabled: boolean;
+  idle_threshold_secs: number;
+}
+
+export interface BatterySaver {
+  enabled: boolean;
+  threshold_percent: number;
+}
+
+export interface PresentationMode {
+  enabled: boolean;
+  suppress_notifications: boolean;
+  pause_transfers: boolean;
+}
+
+export type DownloadScan =
+  | { mode: "off" }
+  | { mode: "defender" }
+  | { mode: "command"; program: string; args: string[] };
+
+/** Result of `get_general_settings` */
+export interface GeneralSettings {
+  notify_credential_expired: boolean;
+  notify_file_conflict: boolean;
+  fast_popup_launch: boolean;
+  log_to_file: boolean;
+  log_level: "trace" | "debug" | "info" | "warn" | "error";
+  log_max_files: number;
+  /** Requests to the servers are logged, with credentials redacted */
+  http_trace: boolean;
+  log_dir: string;
+  language: string | null;
+  download_scan: DownloadScan;
+  battery_saver: BatterySaver;
+  idle_schedule: IdleSchedule;
+  presentation_mode: PresentationMode;
+  /** Settings locked by administrator policy */
+  locked_settings: string[];
+}
+
+/** Entry of `get_file_history`, oldest first */
+export interface FileHistoryEntry {
+  kind: "version" | "restored" | "uploaded" | "downloaded" | "failed" | "conflicted";
+  /** Unix timestamp in seconds */
+  at: number;
+  size: number | null;
+  /** Who stored a version, or why a transfer failed */
+  detail: string | null;
+}
+
+/** Entry of `get_issues`; pass `id` to `resolve_issue` */
+export interface SyncIssue {
+  id: string;
+  kind:
+    | "conflict"
+    | "sync_failure"
+    | "threat"
+    | "credential_expired"
+    | "share_unavailable"
+    | "storage_full"
+    | "low_disk_space"
+    | "event_push_lost"
+    | "server_maintenance"
+    | "sync_root_missing"
+    | "suspicious_changes";
+  severity: "info" | "warning" | "error";
+  drive_id: string;
+  drive_name: string;
+  path: string | null;
+  file_id: number | null;
+  /** Error message, scanner output or storage usage */
+  detail: string | null;
+  action:
+    | "resolve_conflict"
+    | "retry"
+    | "review_threat"
+    | "reauthorize"
+    | "remove_drive"
+    | "free_cloud_space"
+    | "free_disk_space"
+    | "wait"
+    | "recover_sync_root"
+    | "review_suspicious_changes";
+}
@@ -1,4 +1,4 @@
-import { Box, Button, CircularProgress, Container, InputAdornment, Snackbar, Typography } from "@mui/material";
+import { Alert, Box, Button, Checkbox, CircularProgress, Container, FormControlLabel, InputAdornment, Snackbar, Typography } from "@mui/material";
 import { openUrl, openPath } from "@tauri-apps/plugin-opener";
 import { open as openDialog } from "@tauri-apps/plugin-dialog";
 import { invoke } from '@tauri-apps/api/core';
@@ -20,8 +20,9 @@ import {
 import { listen } from '@tauri-apps/api/event';
 import { getCurrentWindow } from '@tauri-apps/api/window';
 import { CALLBACK_PATH, CLIENT_ID, SCOPES } from "../utils/constants";
+import type { AddDriveArgs, AddShareDriveArgs } from "../ipc";
 
-type PageState = "url_input" | "waiting" | "final_setup" | "setting_up" | "success";
+type PageState = "url_input" | "share_input" | "waiting" | "final_setup" | "setting_up" | "success";
 
 interface OAuthCallbackData {
   code: string;
@@ -97,10 +98,19 @@ export default function AddDrive({ mode = "add" }: AddDriveProps) {
   const [authorizeUrl, setAuthorizeUrl] = useState<string | null>(null);
   const [pageState, setPageState] = useState<PageState>(isReauthorize ? "url_input" : "url_input");
   const [localPath, setLocalPath] = useState("");
+  const [localPathHasFiles, setLocalPathHasFiles] = useState(false);
+  const [mergeExisting, setMergeExisting] = useState(false);
   const [driveName, setDriveName] = useState(driveNameQuery ? decodeURIComponent(driveNameQuery) : "");
+  // Drive that already syncs the chosen folder, offered for adoption
+  const [existingDrive, setExistingDrive] = useState<{ id: string; name: string } | null>(null);
+  // Public share mounted without signing in
+  const [shareUrl, setShareUrl] = useState("");
+  const [sharePassword, setSharePassword] = useState("");
   const lastFetchedUrl = useRef<string>("");
   const currentIconUrl = useRef<string | undefined>(undefined);
   const pkceSessionRef = useRef<PKCESession | null>(null);
+  // The authorization code can only be exchanged once, keep the tokens for retries
+  const tokensRef = useRef<TokenResponse | null>(null);
   const hasInitialized = useRef(false);
 
   // Listen for deeplink events from OAuth callback
@@ -250,6 +260,29 @@ export default function AddDrive({ mode = "add" }: AddDriveProps) {
     }
   }, [isReauthorize, decodedSiteUrl, startAuthorization]);
 
+  // Offer merge mode when the chosen local folder already contains files
+  useEffect(() => {
+    if (isReauthorize || !localPath) {
+      setLocalPathHasFiles(false);
+      return;
+    }
+    let cancelled = false;
+    invoke<boolean>('local_folder_has_files', { path: localPath })
+      .then((hasFiles) => {
+        if (!cancelled) {
+          setLocalPathHasFiles(hasFiles);
+        }
+      })
+      .catch(() => {
+        if (!cancelled) {
+          setLocalPathHasFiles(false);
+        }
+      });
+    return () => {
+      cancelled = true;
+    };
+  }, [isReauthorize, localPath]);
+
   // Trigger confetti effect when entering success state
   useEffect(() => {
     if (pageState === "success") {
@@ -276,7 +309,10 @@ export default function AddDrive({ mode = "add" }: AddDriveProps) {
     setAuthorizeUrl(null);
     setPageState("url_input");
     setLocalPath("");
+    setExistingDrive(null);
+    setSharePassword("");
     pkceSessionRef.current = null;
+    tokensRef.current = null;
   };
 
   const handleCloseSnackbar = () => {
@@ -297,15 +333,16 @@ export default function AddDrive({ mode = "add" }: AddDriveProps) {
   const handleFinish = async (e: React.FormEvent) => {
     e.preventDefault();
     setPageState("setting_up");
-    // TODO: Call backend to complete drive setup
+    setExistingDrive(null);
 
     let tokens: TokenResponse;
     try {
-      tokens = await exchangeTokens(
+      tokens = tokensRef.current ?? await exchangeTokens(
         pkceSessionRef.current!.siteUrl,
         pkceSessionRef.current!.callbackData!.code,
         pkceSessionRef.current!.codeVerifier
       );
+      tokensRef.current = tokens;
     } catch (error) {
       if (isValidationError(error)) {
         setError(t(`addDrive.errors.${error.type}`, error.params));
@@ -318,27 +355,51 @@ export default function AddDrive({ mode = "add" }: AddDriveProps) {
       return;
     }
 
+    await submitDrive(tokens, isReauthorize ? driveId : undefined);
+  }
+
+  // Adopt the drive that already syncs this folder by reauthorizing it
+  const handleAdoptExisting = async () => {
+    if (!existingDrive || !tokensRef.current) {
+      return;
+    }
+    setPageState("setting_up");
+    await submitDrive(tokensRef.current, existingDrive.id);
+  }
+
+  const submitDrive = async (tokens: TokenResponse, targetDriveId?: string) => {
     // Clean the site URL to only include origin (no path or trailing slash)
     const cleanSiteUrl = new URL(pkceSessionRef.current!.siteUrl).origin;
 
     try {
-      await invoke('add_drive', {
-        config: {
-          site_url: cleanSiteUrl,
-          access_token: tokens.access_token,
-          refresh_token: tokens.refresh_token,
-          access_token_expires: tokens.expires_in,
-          refresh_token_expires: tokens.refresh_token_expires_in,
-          drive_name: driveName,
-          local_path: localPath,
-          remote_path: pkceSessionRef.current!.callbackData!.path,
-          user_id: pkceSessionRef.current!.callbackData!.user_id || "",
-          drive_id: isReauthorize ? driveId : undefined,
-        }
-      });
+      const config: AddDriveArgs = {
+        site_url: cleanSiteUrl,
+        access_token: tokens.access_token,
+        refresh_token: tokens.refresh_token,
+        access_token_expires: tokens.expires_in,
+        refresh_token_expires: tokens.refresh_token_expires_in,
+        drive_name: driveName,
+        local_path: localPath,
+        remote_path: pkceSessionRef.current!.callbackData!.path,
+        user_id: pkceSessionRef.current!.callbackData!.user_id || "",
+        drive_id: targetDriveId,
+        merge_existing: localPathHasFiles && mergeExisting,
+      };
+      await invoke('add_drive', { config });
       // Success - switch to success state
       setPageState("success");
     } catch (error) {
+      if (isValidationError(error)) {
+        if (error.type === "driveAlreadyAdded") {
+          setExistingDrive({ id: error.params.id, name: error.params.name });
+        } else {
+          setError(t(`addDrive.errors.${error.type}`, error.params));
+          setSnackbarOpen(true);
+        }
+        // Let the user pick another folder or adopt the existing drive
+        setPageState("final_setup");
+        return;
+      }
       const message = error instanceof Error ? error.message : String(error);
       setError(t("addDrive.errors.addDriveFailed", { message }));
       setPageState("url_input");
@@ -346,6 +407,31 @@ export default function AddDrive({ mode = "add" }: AddDriveProps) {
     }
   }
 
+  const handleAddShare = async (e: React.FormEvent) => {
+    e.preventDefault();
+    setPageState("setting_up");
+    try {
+      const args: AddShareDriveArgs = {
+        share_url: shareUrl.trim(),
+        password: sharePassword || undefined,
+        drive_name: driveName || undefined,
+        local_path: localPath,
+        merge_existing: localPathHasFiles && mergeExisting,
+      };
+      await invoke('add_share_drive', { args });
+      setPageState("success");
+    } catch (error) {
+      if (isValidationError(error)) {
+        setError(t(`addDrive.errors.${error.type}`, error.params));
+      } else {
+        const message = error instanceof Error ? error.message : String(error);
+        setError(t("addDrive.errors.addDriveFailed", { message }));
+      }
+      setPageState("share_input");
+      setSnackbarOpen(true);
+    }
+  }
+
   const handleOpenDriveAndClose = async () => {
     const pathToOpen = localPath.endsWith('/') || localPath.endsWith('\\') ? localPath : localPath + '/';
     await openPath(pathToOpen);
@@ -431,7 +517,7 @@ export default function AddDrive({ mode = "add" }: AddDriveProps) {
                     fullWidth
                     onClick={handleOpenDriveAndClose}
                   >
-                    {t("addDrive.openDrive", { name: driveName })}
+                    {t("addDrive.openDrive", { name: driveName || localPath })}
                   </Button>
                 )}
               </Box>
@@ -519,6 +605,35 @@ export default function AddDrive({ mode = "add" }: AddDriveProps) {
                   />
                 )}
 
+                {!isReauthorize && localPathHasFiles && (
+                  <FormControlLabel
+                    control={
+                      <Checkbox
+                        checked={mergeExisting}
+                        onChange={(e) => setMergeExisting(e.target.checked)}
+                      />
+                    }
+                    label={
+                      <Typography variant="body2" color="text.secondary">
+                        {t("addDrive.mergeExisting")}
+                      </Typography>
+                    }
+                  />
+                )}
+
+                {existingDrive && (
+                  <Alert
+                    severity="info"
+                    action={
+                      <Button color="inherit" size="small" onClick={handleAdoptExisting}>
+                        {t("addDrive.adoptExisting")}
+                      </Button>
+                    }
+                  >
+                    {t("addDrive.driveAlreadyAdded", { name: existingDrive.name })}
+                  </Alert>
+                )}
+
                 <Button
                   type="submit"
                   variant="contained"
@@ -528,6 +643,129 @@ export default function AddDrive({ mode = "add" }: AddDriveProps) {
                   {isReauthorize ? t("addDrive.reauthorizeConfirm") : t("addDrive.finish")}
                 </Button>
 
+                <Button
+                  variant="text"
+                  size="large"
+                  fullWidth
+                  onClick={handleBack}
+                >
+                  {t("addDrive.back")}
+                </Button>
+              </Box>
+            </>
+          ) : pageState === "share_input" ? (
+            // Public share link, mounted read-only without signing in
+            <>
+              <Typography
+                sx={{ mt: 2 }}
+                variant="h5"
+                component="h1"
+                fontWeight={500}
+              >
+                {t("addDrive.shareTitle")}
+              </Typography>
+
+              <Typography
+                variant="body2"
+                color="text.secondary"
+                textAlign="center"
+              >
+                {t("addDrive.shareDescription")}
+              </Typography>
+
+              <Box
+                component="form"
+                onSubmit={handleAddShare}
+                sx={{
+                  width: "100%",
+                  display: "flex",
+                  flexDirection: "column",
+                  gap: 2,
+                  mt: 2,
+                }}
+              >
+                <FilledTextField
+                  fullWidth
+                  autoComplete="off"
+                  label={t("addDrive.shareUrl")}
+                  placeholder={t("addDrive.shareUrlPlaceholder")}
+                  value={shareUrl}
+                  onChange={(e) => setShareUrl(e.target.value)}
+                  variant="filled"
+                  type="url"
+                  required
+                />
+
+                <FilledTextField
+                  fullWidth
+                  autoComplete="off"
+                  label={t("addDrive.sharePassword")}
+                  value={sharePassword}
+                  onChange={(e) => setSharePassword(e.target.value)}
+                  variant="filled"
+                  type="password"
+                />
+
+                <FilledTextField
+                  fullWidth
+                  autoComplete="off"
+                  label={t("addDrive.localDriveName")}
+                  placeholder={t("addDrive.shareDriveNamePlaceholder")}
+                  value={driveName}
+                  onChange={(e) => setDriveName(e.target.value)}
+                  variant="filled"
+                />
+
+                <FilledTextField
+                  fullWidth
+                  autoComplete="off"
+                  label={t("addDrive.localPath")}
+                  placeholder={t("addDrive.localPathPlaceholder")}
+                  value={localPath}
+                  onChange={(e) => setLocalPath(e.target.value)}
+                  variant="filled"
+                  required
+                  slotProps={{
+                    input: {
+                      endAdornment: (
+                        <InputAdornment position="end">
+                          <Button
+                            onClick={handleBrowseFolder}
+                            size="small"
+                          >
+                            {t("addDrive.browse")}
+                          </Button>
+                        </InputAdornment>
+                      ),
+                    },
+                  }}
+                />
+
+                {localPathHasFiles && (
+                  <FormControlLabel
+                    control={
+                      <Checkbox
+                        checked={mergeExisting}
+                        onChange={(e) => setMergeExisting(e.target.checked)}
+                      />
+                    }
+                    label={
+                      <Typography variant="body2" color="text.secondary">
+                        {t("addDrive.mergeExisting")}
+                      </Typography>
+                    }
+                  />
+                )}
+
+                <Button
+                  type="submit"
+                  variant="contained"
+                  size="large"
+                  fullWidth
+                >
+                  {t("addDrive.finish")}
+                </Button>
+
                 <Button
                   variant="text"
                   size="large"
@@ -644,6 +882,18 @@ export default function AddDrive({ mode = "add" }: AddDriveProps) {
                 >
                   {t("addDrive.connect")}
                 </Button>
+
+                {!isReauthorize && (
+                  <Button
+                    variant="text"
+                    size="large"
+                    fullWidth
+                    disabled={loading}
+                    onClick={() => setPageState("share_input")}
+                  >
+                    {t("addDrive.addShareInstead")}
+                  </Button>
+                )}
               </Box>
             </>
           )}
@@ -0,0 +1,85 @@
+import { Box, Button, Typography } from "@mui/material";
+import { useState } from "react";
+import { useParams } from "react-router-dom";
+import { useTranslation } from "react-i18next";
+import { invoke } from "@tauri-apps/api/core";
+import { getCurrentWindow } from "@tauri-apps/api/window";
+import { SecondaryButton } from "../../common/StyledComponent";
+import FileIcon from "../popup/FileIcon";
+
+type ConflictAction = "keep_remote" | "overwrite_remote" | "save_as_new";
+
+export default function Conflict() {
+  const { t } = useTranslation();
+  const { driveId, fileId, path: encodedPath } = useParams();
+  const path = decodeURIComponent(encodedPath ?? "");
+  const fileName = path.split(/[\\/]/).pop() ?? path;
+  const [error, setError] = useState<string | null>(null);
+  const [resolving, setResolving] = useState(false);
+
+  const resolve = async (action: ConflictAction) => {
+    setResolving(true);
+    setError(null);
+    try {
+      await invoke("resolve_conflict", {
+        driveId,
+        fileId: Number(fileId),
+        path,
+        action,
+      });
+      await getCurrentWindow().close();
+    } catch (e) {
+      setError(t("conflict.failed", { message: String(e) }));
+      setResolving(false);
+    }
+  };
+
+  return (
+    <Box
+      sx={{
+        height: "100vh",
+        display: "flex",
+        flexDirection: "column",
+        bgcolor: "background.paper",
+        p: 3,
+        gap: 2,
+      }}
+    >
+      <Typography data-tauri-drag-region variant="h6">
+        {t("conflict.title")}
+      </Typography>
+      <Box sx={{ display: "flex", alignItems: "center", gap: 1.5 }}>
+        <FileIcon path={path} size={32} />
+        <Box sx={{ minWidth: 0 }}>
+          <Typography variant="body1" noWrap title={path}>
+            {fileName}
+          </Typography>
+          <Typography variant="body2" color="text.secondary">
+            {t("conflict.description")}
+          </Typography>
+        </Box>
+      </Box>
+      {error && (
+        <Typography variant="body2" color="error">
+          {error}
+        </Typography>
+      )}
+      <Box sx={{ flexGrow: 1 }} />
+      <Box sx={{ display: "flex", justifyContent: "flex-end", gap: 1, flexWrap: "wrap" }}>
+        <SecondaryButton disabled={resolving} onClick={() => resolve("save_as_new")}>
+          {t("conflict.saveAsNew")}
+        </SecondaryButton>
+        <SecondaryButton disabled={resolving} onClick={() => resolve("keep_remote")}>
+          {t("conflict.keepRemote")}
+        </SecondaryButton>
+        <Button
+          variant="contained"
+          disabled={resolving}
+          onClick={() => resolve("overwrite_remote")}
+        >
+          {t("conflict.overwriteRemote")}
+        </Button>
+      </Box>
+    </Box>
+  );
+}
@@ -0,0 +1,112 @@
+import { Alert, Box } from "@mui/material";
+import { useCallback, useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { useTranslation } from "react-i18next";
+import { subscribeEvents } from "../../utils/events";
+import type { CriticalEvent, EventAcknowledgedEvent } from "./types";
+import { getFileName } from "./utils";
+
+const CRITICAL_EVENTS: (CriticalEvent | EventAcknowledgedEvent)["type"][] = [
+  "ReauthRequired",
+  "ConflictDetected",
+  "MassDeletionDetected",
+  "PinMismatch",
+  "EventAcknowledged",
+];
+
+// Events the user must not miss, shown until dismissed even if they were
+// raised while no window was open
+export default function CriticalEvents() {
+  const { t } = useTranslation();
+  const [events, setEvents] = useState<CriticalEvent[]>([]);
+
+  const addEvents = useCallback((added: CriticalEvent[]) => {
+    setEvents((prev) => {
+      const known = new Set(prev.map((event) => event.data.event_id));
+      return [...prev, ...added.filter((event) => !known.has(event.data.event_id))];
+    });
+  }, []);
+
+  const removeEvent = useCallback((eventId: string) => {
+    setEvents((prev) => prev.filter((event) => event.data.event_id !== eventId));
+  }, []);
+
+  useEffect(() => {
+    let unsubscribe: (() => void) | undefined;
+    let disposed = false;
+
+    subscribeEvents<CriticalEvent | EventAcknowledgedEvent>(CRITICAL_EVENTS, (event) => {
+      if (event.type === "EventAcknowledged") {
+        removeEvent(event.data.event_id);
+      } else {
+        addEvents([event]);
+      }
+    })
+      .then((subscription) => {
+        if (disposed) {
+          subscription.unsubscribe();
+          return;
+        }
+        unsubscribe = subscription.unsubscribe;
+        addEvents(subscription.pending as CriticalEvent[]);
+      })
+      .catch((error) => console.error("Failed to subscribe to critical events:", error));
+
+    return () => {
+      disposed = true;
+      unsubscribe?.();
+    };
+  }, [addEvents, removeEvent]);
+
+  const handleDismiss = async (eventId: string) => {
+    removeEvent(eventId);
+    try {
+      await invoke("acknowledge_event", { eventId });
+    } catch (error) {
+      console.error("Failed to acknowledge event:", error);
+    }
+  };
+
+  const message = (event: CriticalEvent) => {
+    switch (event.type) {
+      case "ReauthRequired":
+        return t("popup.reauthRequired", "Sign in again to keep syncing {{name}}", {
+          name: event.data.drive_name,
+        });
+      case "ConflictDetected":
+        return t("popup.conflictDetected", "{{name}} was changed both here and in the cloud", {
+          name: getFileName(event.data.path),
+        });
+      case "MassDeletionDetected":
+        return t(
+          "popup.massDeletionDetected",
+          "{{count}} files were deleted from the cloud at once, they can be restored from the trash",
+          { count: event.data.count }
+        );
+      case "PinMismatch":
+        return t(
+          "popup.pinMismatch",
+          "The certificate of {{url}} changed, {{name}} stopped syncing in case someone is impersonating the server",
+          { url: event.data.instance_url, name: event.data.drive_name }
+        );
+    }
+  };
+
+  if (events.length === 0) {
+    return null;
+  }
+
+  return (
+    <Box sx={{ px: 2, pt: 1, display: "flex", flexDirection: "column", gap: 1 }}>
+      {events.map((event) => (
+        <Alert
+          key={event.data.event_id}
+          severity={event.type === "ConflictDetected" ? "warning" : "error"}
+          onClose={() => handleDismiss(event.data.event_id)}
+        >
+          {message(event)}
+        </Alert>
+      ))}
+    </Box>
+  );
+}
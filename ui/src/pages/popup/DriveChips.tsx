@@ -1,4 +1,4 @@
-import { Box, Chip, styled } from "@mui/material";
+import { Box, Chip, Tooltip, styled } from "@mui/material";
 import { Add as AddIcon } from "@mui/icons-material";
 import { useCallback, useEffect, useRef, useState } from "react";
 import { useTranslation } from "react-i18next";
@@ -17,6 +17,7 @@ const StyledChip = styled(Chip, {
 
 interface DriveChipsProps {
   drives: DriveConfig[];
+  quarantined: Record<string, number>;
   selectedDrive: string | null;
   onDriveSelect: (driveId: string | null) => void;
   onAddDrive: () => void;
@@ -24,6 +25,7 @@ interface DriveChipsProps {
 
 export default function DriveChips({
   drives,
+  quarantined,
   selectedDrive,
   onDriveSelect,
   onAddDrive,
@@ -148,24 +150,61 @@ export default function DriveChips({
           selected={selectedDrive === null}
           onClick={() => onDriveSelect(null)}
         />
-        {drives.map((drive) => (
-          <StyledChip
-            key={drive.id}
-            icon={
-              drive.icon_path ? (
-                <img
-                  src={convertFileSrc(drive.icon_path)}
-                  alt=""
-                  style={{ width: 18,borderRadius:"6px", height: 18 }}
-                />
-              ) : undefined
-            }
-            label={drive.name}
-            size="small"
-            selected={selectedDrive === drive.id}
-            onClick={() => onDriveSelect(drive.id)}
-          />
-        ))}
+        {drives.map((drive) => {
+          const quarantinedCount = quarantined[drive.id] ?? 0;
+          const chip = (
+            <StyledChip
+              key={drive.id}
+              icon={
+                drive.icon_path ? (
+                  <img
+                    src={convertFileSrc(drive.icon_path)}
+                    alt=""
+                    style={{ width: 18,borderRadius:"6px", height: 18 }}
+                  />
+                ) : undefined
+              }
+              label={
+                quarantinedCount > 0 ? (
+                  <>
+                    {drive.name}
+                    <Box
+                      component="span"
+                      sx={{
+                        ml: 0.75,
+                        px: 0.75,
+                        borderRadius: 1,
+                        fontSize: "0.7rem",
+                        bgcolor: "error.main",
+                        color: "error.contrastText",
+                      }}
+                    >
+                      {quarantinedCount}
+                    </Box>
+                  </>
+                ) : (
+                  drive.name
+                )
+              }
+              size="small"
+              selected={selectedDrive === drive.id}
+              onClick={() => onDriveSelect(drive.id)}
+            />
+          );
+          return quarantinedCount > 0 ? (
+            <Tooltip
+              key={drive.id}
+              title={t("popup.quarantined", {
+                count: quarantinedCount,
+                defaultValue: "{{count}} file(s) keep failing to sync",
+              })}
+            >
+              {chip}
+            </Tooltip>
+          ) : (
+            chip
+          );
+        })}
         <Chip
           icon={<AddIcon />}
           label={t("popup.newDrive", "New Drive")}
@@ -16,11 +16,18 @@ import { getCurrentWindow } from "@tauri-apps/api/window";
 import { useTranslation } from "react-i18next";
 import Settings from "../../common/icons/Settings";
 import CloudreveLogo from "../../common/CloudreveLogo";
-import type { StatusSummary } from "./types";
+import { subscribeEvents } from "../../utils/events";
+import type { StatusSummary, TaskProgressEvent } from "./types";
 import DriveChips from "./DriveChips";
+import CriticalEvents from "./CriticalEvents";
 import TaskItem from "./TaskItem";
 
-export default function Popup() {
+interface PopupProps {
+  // Opened in its own window rather than from the tray
+  detached?: boolean;
+}
+
+export default function Popup({ detached = false }: PopupProps) {
   const { t } = useTranslation();
   const [summary, setSummary] = useState<StatusSummary | null>(null);
   const [selectedDrive, setSelectedDrive] = useState<string | null>(null);
@@ -29,6 +36,7 @@ export default function Popup() {
 
   // Close window on blur (when it loses focus)
   useEffect(() => {
+    if (detached) return;
     let unlisten: () => void;
     const currentWindow = getCurrentWindow();
 
@@ -47,7 +55,7 @@ export default function Popup() {
         unlisten();
       }
     };
-  }, []);
+  }, [detached]);
 
   // Fetch status summary
   const fetchSummary = useCallback(async () => {
@@ -80,6 +88,48 @@ export default function Popup() {
     };
   }, [fetchSummary]);
 
+  // Apply per-task progress pushed between polls
+  useEffect(() => {
+    let unsubscribe: (() => void) | undefined;
+    let disposed = false;
+
+    subscribeEvents<{ data: TaskProgressEvent }>(["TaskProgress"], ({ data }) => {
+      setSummary((prev) =>
+        prev && {
+          ...prev,
+          active_tasks: prev.active_tasks.map((task) => {
+            const live = task.live_progress;
+            if (task.id !== data.task_id || !live) return task;
+            const total = data.total ?? live.total_bytes;
+            return {
+              ...task,
+              live_progress: {
+                ...live,
+                progress: total ? data.processed / total : live.progress,
+                processed_bytes: data.processed,
+                total_bytes: total,
+                speed_bytes_per_sec: data.speed,
+              },
+            };
+          }),
+        }
+      );
+    })
+      .then((subscription) => {
+        if (disposed) {
+          subscription.unsubscribe();
+          return;
+        }
+        unsubscribe = subscription.unsubscribe;
+      })
+      .catch((error) => console.error("Failed to subscribe to task progress:", error));
+
+    return () => {
+      disposed = true;
+      unsubscribe?.();
+    };
+  }, []);
+
   const handleDriveSelect = (driveId: string | null) => {
     setSelectedDrive(driveId);
   };
@@ -147,12 +197,15 @@ export default function Popup() {
         {/* Drive filter chips */}
         <DriveChips
           drives={summary?.drives ?? []}
+          quarantined={summary?.quarantined ?? {}}
           selectedDrive={selectedDrive}
           onDriveSelect={handleDriveSelect}
           onAddDrive={handleAddDrive}
         />
       </Box>
 
+      <CriticalEvents />
+
       {/* Task List */}
       <Box sx={{ flex: 1, overflow: "auto" }}>
         {loading ? (
@@ -8,13 +8,59 @@ export interface DriveConfig {
 
 export interface TaskProgress {
   task_id: string;
-  kind: "Upload" | "Download";
+  // Custom task types use their registered name
+  kind: "Upload" | "Download" | (string & {});
   local_path: string;
   progress: number;
   processed_bytes?: number;
   total_bytes?: number;
   speed_bytes_per_sec: number;
   eta_seconds?: number;
+  // Concurrency level picked by autoscaling, if enabled for the drive
+  worker_level?: number;
+}
+
+// Payload of the TaskProgress event
+export interface TaskProgressEvent {
+  task_id: string;
+  drive_id: string;
+  path: string;
+  processed: number;
+  total?: number;
+  // Bytes per second
+  speed: number;
+}
+
+// Events kept until acknowledged, see get_pending_events
+export type CriticalEvent =
+  | {
+      type: "ReauthRequired";
+      data: { event_id: string; drive_id: string; drive_name: string };
+    }
+  | {
+      type: "ConflictDetected";
+      data: { event_id: string; drive_id: string; path: string };
+    }
+  | {
+      type: "MassDeletionDetected";
+      data: { event_id: string; drive_id: string; count: number };
+    }
+  | {
+      type: "PinMismatch";
+      data: {
+        event_id: string;
+        drive_id: string;
+        drive_name: string;
+        instance_url: string;
+        // Pins of the certificate presented, like `sha256/...`
+        presented: string[];
+      };
+    };
+
+/** A critical event was acknowledged, possibly from another window */
+export interface EventAcknowledgedEvent {
+  type: "EventAcknowledged";
+  data: { event_id: string };
 }
 
 export interface TaskRecord {
@@ -35,10 +81,25 @@ export interface TaskWithProgress extends TaskRecord {
   live_progress?: TaskProgress;
 }
 
+export interface IntegrityStats {
+  chunks_verified: number;
+  chunk_mismatches: number;
+  size_mismatches: number;
+}
+
+export interface CompressionStats {
+  files_compressed: number;
+  bytes_saved: number;
+}
+
 export interface StatusSummary {
   drives: DriveConfig[];
   active_tasks: TaskWithProgress[];
   finished_tasks: TaskRecord[];
+  integrity: IntegrityStats;
+  compression: CompressionStats;
+  /** Files quarantined after their sync failed repeatedly, by drive ID */
+  quarantined: Record<string, number>;
 }
 
 export interface FileIconResponse {
@@ -10,6 +10,9 @@ import {
   Divider,
   FormControlLabel,
   Switch,
+  Select,
+  MenuItem,
+  InputBase,
 } from "@mui/material";
 import {
   FolderOpen as FolderOpenIcon,
@@ -18,15 +21,32 @@ import {
   Add as AddIcon,
   DeleteOutlineRounded,
   RefreshRounded,
+  WarningAmberRounded,
+  LanRounded,
+  RouterRounded,
+  SettingsEthernetRounded,
+  SpeedRounded,
+  PlayArrowRounded,
+  PauseRounded,
+  RestoreRounded,
+  CheckRounded,
 } from "@mui/icons-material";
 import { useCallback, useEffect, useRef, useState } from "react";
 import { useTranslation } from "react-i18next";
 import { invoke } from "@tauri-apps/api/core";
 import { convertFileSrc } from "@tauri-apps/api/core";
 import { openUrl } from "@tauri-apps/plugin-opener";
-import type { DriveInfo } from "./types";
+import type {
+  AddressFamily,
+  CompressionSettings,
+  DriveInfo,
+  IpStrategy,
+  NetworkSettings,
+  ServerInfo,
+  TransferLimits,
+} from "./types";
 import {  SecondaryButton, SecondaryErrorButton } from "../../common/StyledComponent";
-import { ask } from '@tauri-apps/plugin-dialog';
+import { ask, open as openDialog } from '@tauri-apps/plugin-dialog';
 
 interface DriveInfoResponse {
   id: string;
@@ -44,14 +64,27 @@ interface DriveInfoResponse {
     used: number;
     label: string;
   };
+  server?: ServerInfo;
+  address_family?: AddressFamily;
+  clock_skew_secs?: number;
 }
 
+const TRANSFER_LIMIT_OPTIONS = [1, 2, 3, 4, 6, 8];
+const CHUNK_CONCURRENCY_OPTIONS = [1, 2, 4, 8];
+/** Clock differences below this are within the precision of the estimate */
+const CLOCK_SKEW_NOTICE_SECS = 5;
+
 export default function DrivesSection() {
   const { t } = useTranslation();
   const [drives, setDrives] = useState<DriveInfo[]>([]);
   const [loading, setLoading] = useState(true);
   const isFetchingRef = useRef(false);
   const [syncDirections, setSyncDirections] = useState<Record<string, string>>({});
+  const [networkSettings, setNetworkSettings] = useState<Record<string, NetworkSettings>>({});
+  const [compressionSettings, setCompressionSettings] = useState<
+    Record<string, CompressionSettings>
+  >({});
+  const [transferLimits, setTransferLimits] = useState<Record<string, TransferLimits>>({});
 
   const fetchDrives = useCallback(async () => {
     if (isFetchingRef.current) return;
@@ -83,6 +116,63 @@ export default function DrivesSection() {
         directions[id] = direction;
       }
       setSyncDirections(directions);
+
+      const networkResults = await Promise.all(
+        result.map(async (drive) => {
+          try {
+            const settings = await invoke<NetworkSettings>("get_network_settings", {
+              driveId: drive.id,
+            });
+            return { id: drive.id, settings };
+          } catch (error) {
+            console.error(`Failed to fetch network settings for drive ${drive.id}:`, error);
+            return null;
+          }
+        })
+      );
+      const network: Record<string, NetworkSettings> = {};
+      for (const entry of networkResults) {
+        if (entry) network[entry.id] = entry.settings;
+      }
+      setNetworkSettings(network);
+
+      const compressionResults = await Promise.all(
+        result.map(async (drive) => {
+          try {
+            const settings = await invoke<CompressionSettings>("get_compression_settings", {
+              driveId: drive.id,
+            });
+            return { id: drive.id, settings };
+          } catch (error) {
+            console.error(`Failed to fetch compression settings for drive ${drive.id}:`, error);
+            return null;
+          }
+        })
+      );
+      const compression: Record<string, CompressionSettings> = {};
+      for (const entry of compressionResults) {
+        if (entry) compression[entry.id] = entry.settings;
+      }
+      setCompressionSettings(compression);
+
+      const limitResults = await Promise.all(
+        result.map(async (drive) => {
+          try {
+            const limits = await invoke<TransferLimits>("get_transfer_limits", {
+              driveId: drive.id,
+            });
+            return { id: drive.id, limits };
+          } catch (error) {
+            console.error(`Failed to fetch transfer limits for drive ${drive.id}:`, error);
+            return null;
+          }
+        })
+      );
+      const limits: Record<string, TransferLimits> = {};
+      for (const entry of limitResults) {
+        if (entry) limits[entry.id] = entry.limits;
+      }
+      setTransferLimits(limits);
     } catch (error) {
       console.error("Failed to fetch drives:", error);
     } finally {
@@ -123,6 +213,76 @@ export default function DrivesSection() {
     }
   };
 
+  const handleRelinkSyncRoot = async (drive: DriveInfo) => {
+    const selected = await openDialog({
+      directory: true,
+      multiple: false,
+      title: t("settings.relinkFolder"),
+    });
+    if (!selected) return;
+    try {
+      await invoke("relink_sync_root", { driveId: drive.id, path: selected });
+      await fetchDrives();
+    } catch (error) {
+      console.error("Failed to relink sync folder:", error);
+    }
+  };
+
+  const handleRelocateSyncRoot = async (drive: DriveInfo) => {
+    try {
+      await invoke("relocate_sync_root", { driveId: drive.id });
+      await fetchDrives();
+    } catch (error) {
+      console.error("Failed to relocate sync folder:", error);
+    }
+  };
+
+  const handleRecreateSyncRoot = async (drive: DriveInfo) => {
+    try {
+      await invoke("recreate_sync_root", { driveId: drive.id });
+      await fetchDrives();
+    } catch (error) {
+      console.error("Failed to recreate sync folder:", error);
+    }
+  };
+
+  const handleRestoreSuspiciousChanges = async (drive: DriveInfo) => {
+    const confirmed = await ask(t("settings.restoreFilesConfirm", { name: drive.name }), {
+      title: t("settings.restoreFiles"),
+      kind: "warning",
+    });
+    if (!confirmed) return;
+    try {
+      await invoke("restore_suspicious_changes", { driveId: drive.id });
+      await fetchDrives();
+    } catch (error) {
+      console.error("Failed to restore files:", error);
+    }
+  };
+
+  const handleDismissSuspiciousChanges = async (drive: DriveInfo) => {
+    const confirmed = await ask(t("settings.keepChangesConfirm"), {
+      title: t("settings.keepChanges"),
+      kind: "warning",
+    });
+    if (!confirmed) return;
+    try {
+      await invoke("dismiss_suspicious_changes", { driveId: drive.id });
+      await fetchDrives();
+    } catch (error) {
+      console.error("Failed to keep suspicious changes:", error);
+    }
+  };
+
+  const handleToggleMounted = async (drive: DriveInfo) => {
+    try {
+      await invoke(drive.enabled ? "unmount_drive" : "mount_drive", { driveId: drive.id });
+      await fetchDrives();
+    } catch (error) {
+      console.error("Failed to change whether the drive is mounted:", error);
+    }
+  };
+
   const handleOpenFolder = async (path: string) => {
     try {
       await invoke("show_file_in_explorer", { path });
@@ -163,13 +323,100 @@ export default function DrivesSection() {
     }
   };
 
+  const handleCompressionChange = async (driveId: string, enabled: boolean) => {
+    const previous = compressionSettings[driveId];
+    if (!previous) return;
+    const updated = { ...previous, enabled };
+
+    setCompressionSettings(prev => ({ ...prev, [driveId]: updated }));
+
+    try {
+      await invoke("set_compression_settings", { driveId, settings: updated });
+    } catch (error) {
+      console.error("Failed to change compression settings:", error);
+      setCompressionSettings(prev => ({ ...prev, [driveId]: previous }));
+    }
+  };
+
+  const handleTransferLimitsChange = async (
+    driveId: string,
+    change: Partial<TransferLimits>
+  ) => {
+    const previous = transferLimits[driveId];
+    if (!previous) return;
+    const updated = { ...previous, ...change };
+
+    setTransferLimits(prev => ({ ...prev, [driveId]: updated }));
+
+    try {
+      await invoke("set_transfer_limits", { driveId, limits: updated });
+    } catch (error) {
+      console.error("Failed to change transfer limits:", error);
+      setTransferLimits(prev => ({ ...prev, [driveId]: previous }));
+    }
+  };
+
+  const handleIpStrategyChange = async (driveId: string, ipStrategy: IpStrategy) => {
+    const previous = networkSettings[driveId];
+    if (!previous) return;
+    const updated = { ...previous, ip_strategy: ipStrategy };
+
+    setNetworkSettings(prev => ({ ...prev, [driveId]: updated }));
+
+    try {
+      await invoke("set_network_settings", { driveId, settings: updated });
+    } catch (error) {
+      console.error("Failed to change connection strategy:", error);
+      setNetworkSettings(prev => ({ ...prev, [driveId]: previous }));
+    }
+  };
+
+  const handleLanUrlCommit = async (driveId: string, lanUrl: string) => {
+    const previous = networkSettings[driveId];
+    if (!previous) return;
+    const trimmed = lanUrl.trim();
+    if ((previous.lan_url ?? "") === trimmed) return;
+    const updated = { ...previous, lan_url: trimmed || null };
+
+    setNetworkSettings(prev => ({ ...prev, [driveId]: updated }));
+
+    try {
+      await invoke("set_network_settings", { driveId, settings: updated });
+      await fetchDrives();
+    } catch (error) {
+      console.error("Failed to change LAN address:", error);
+      setNetworkSettings(prev => ({ ...prev, [driveId]: previous }));
+    }
+  };
+
+  const handleBindInterfaceCommit = async (driveId: string, bindInterface: string) => {
+    const previous = networkSettings[driveId];
+    if (!previous) return;
+    const trimmed = bindInterface.trim();
+    if ((previous.bind_interface ?? "") === trimmed) return;
+    const updated = { ...previous, bind_interface: trimmed || null };
+
+    setNetworkSettings(prev => ({ ...prev, [driveId]: updated }));
+
+    try {
+      await invoke("set_network_settings", { driveId, settings: updated });
+    } catch (error) {
+      console.error("Failed to change network adapter:", error);
+      setNetworkSettings(prev => ({ ...prev, [driveId]: previous }));
+    }
+  };
+
   const getStatusColor = (status: DriveInfo["status"]) => {
     switch (status) {
       case "active":
         return "#4caf50"; // green
       case "event_push_lost":
+      case "server_maintenance":
         return "#ff9800"; // orange
       case "credential_expired":
+      case "share_unavailable":
+      case "sync_root_missing":
+      case "suspicious_changes":
         return "#f44336"; // red
       default:
         return "#9e9e9e"; // grey
@@ -189,6 +436,16 @@ export default function DrivesSection() {
         return t("settings.driveStatus.eventPushLost");
       case "credential_expired":
         return t("settings.driveStatus.credentialExpired");
+      case "share_unavailable":
+        return t("settings.driveStatus.shareUnavailable");
+      case "server_maintenance":
+        return t("settings.driveStatus.serverMaintenance");
+      case "unmounted":
+        return t("settings.driveStatus.unmounted");
+      case "sync_root_missing":
+        return t("settings.driveStatus.syncRootMissing");
+      case "suspicious_changes":
+        return t("settings.driveStatus.suspiciousChanges");
       default:
         return status;
     }
@@ -311,6 +568,15 @@ export default function DrivesSection() {
                         >
                           {drive.instance_url}
                         </Link>
+                        {drive.guest_share && (
+                          <Typography
+                            variant="caption"
+                            color="text.secondary"
+                            sx={{ whiteSpace: "nowrap" }}
+                          >
+                            · {t("settings.guestShare")}
+                          </Typography>
+                        )}
                       </Box>
                     </Tooltip>
 
@@ -345,6 +611,29 @@ export default function DrivesSection() {
                       </Box>
                     </Tooltip>
 
+                    {/* Server Compatibility */}
+                    {drive.server?.outdated && (
+                      <Box
+                        sx={{
+                          display: "flex",
+                          alignItems: "center",
+                          gap: 0.75,
+                          mb: 1.5,
+                          mt: -0.5,
+                        }}
+                      >
+                        <WarningAmberRounded
+                          sx={{ fontSize: 16, color: "warning.main" }}
+                        />
+                        <Typography variant="caption" color="warning.main">
+                          {t("settings.serverOutdated", {
+                            version: drive.server.version,
+                            tested: drive.server.min_tested_version,
+                          })}
+                        </Typography>
+                      </Box>
+                    )}
+
                     {/* Storage Usage */}
                     {drive.capacity && (
                       <Box sx={{ mb: 1 }}>
@@ -394,6 +683,273 @@ export default function DrivesSection() {
                       />
                     </Tooltip>
 
+                    {/* Compressed Uploads Toggle */}
+                    {compressionSettings[drive.id] && (
+                      <Tooltip
+                        title={t("settings.compressUploadsDescription", {
+                          extensions: compressionSettings[drive.id].extensions.join(", "),
+                        })}
+                        placement="bottom-start"
+                      >
+                        <FormControlLabel
+                          control={
+                            <Switch
+                              size="small"
+                              checked={compressionSettings[drive.id].enabled}
+                              onChange={(e) => handleCompressionChange(drive.id, e.target.checked)}
+                              disabled={loading}
+                            />
+                          }
+                          label={
+                            <Typography variant="caption" color="text.secondary">
+                              {t("settings.compressUploads")}
+                            </Typography>
+                          }
+                          sx={{ ml: 0 }}
+                        />
+                      </Tooltip>
+                    )}
+
+                    {/* Connection Strategy */}
+                    {networkSettings[drive.id] && (
+                      <Tooltip
+                        title={t("settings.ipStrategyDescription")}
+                        placement="bottom-start"
+                      >
+                        <Box
+                          sx={{
+                            display: "flex",
+                            alignItems: "center",
+                            gap: 0.75,
+                            mt: 1,
+                          }}
+                        >
+                          <LanRounded sx={{ fontSize: 16, color: "text.secondary" }} />
+                          <Select
+                            size="small"
+                            variant="standard"
+                            disableUnderline
+                            value={networkSettings[drive.id].ip_strategy}
+                            onChange={(e) =>
+                              handleIpStrategyChange(drive.id, e.target.value as IpStrategy)
+                            }
+                            sx={{ typography: "caption", color: "text.secondary" }}
+                          >
+                            <MenuItem value="auto">{t("settings.ipStrategy.auto")}</MenuItem>
+                            <MenuItem value="prefer_ipv4">
+                              {t("settings.ipStrategy.preferIpv4")}
+                            </MenuItem>
+                            <MenuItem value="prefer_ipv6">
+                              {t("settings.ipStrategy.preferIpv6")}
+                            </MenuItem>
+                          </Select>
+                          {drive.address_family && (
+                            <Typography variant="caption" color="text.secondary">
+                              {t("settings.connectedVia", {
+                                family: drive.address_family === "ipv6" ? "IPv6" : "IPv4",
+                              })}
+                            </Typography>
+                          )}
+                          {drive.clock_skew_secs != null &&
+                            Math.abs(drive.clock_skew_secs) >= CLOCK_SKEW_NOTICE_SECS && (
+                              <Typography variant="caption" color="warning.main">
+                                {t(
+                                  drive.clock_skew_secs > 0
+                                    ? "settings.clockSkewAhead"
+                                    : "settings.clockSkewBehind",
+                                  { seconds: Math.abs(drive.clock_skew_secs) },
+                                )}
+                              </Typography>
+                            )}
+                        </Box>
+                      </Tooltip>
+                    )}
+
+                    {/* LAN Direct Transfer */}
+                    {networkSettings[drive.id] && (
+                      <Tooltip
+                        title={t("settings.lanUrlDescription")}
+                        placement="bottom-start"
+                      >
+                        <Box
+                          sx={{
+                            display: "flex",
+                            alignItems: "center",
+                            gap: 0.75,
+                            mt: 0.5,
+                          }}
+                        >
+                          <RouterRounded sx={{ fontSize: 16, color: "text.secondary" }} />
+                          <InputBase
+                            key={networkSettings[drive.id].lan_url ?? ""}
+                            defaultValue={networkSettings[drive.id].lan_url ?? ""}
+                            placeholder={t("settings.lanUrlPlaceholder")}
+                            onBlur={(e) => handleLanUrlCommit(drive.id, e.target.value)}
+                            onKeyDown={(e) => {
+                              if (e.key === "Enter") {
+                                (e.target as HTMLInputElement).blur();
+                              }
+                            }}
+                            sx={{ typography: "caption", color: "text.secondary", flex: 1 }}
+                          />
+                          {networkSettings[drive.id].lan_url && (
+                            <Typography
+                              variant="caption"
+                              color={drive.lan_active ? "success.main" : "text.secondary"}
+                            >
+                              {drive.lan_active
+                                ? t("settings.lanActive")
+                                : t("settings.lanUnreachable")}
+                            </Typography>
+                          )}
+                        </Box>
+                      </Tooltip>
+                    )}
+
+                    {/* Network Adapter Binding */}
+                    {networkSettings[drive.id] && (
+                      <Tooltip
+                        title={t("settings.bindInterfaceDescription")}
+                        placement="bottom-start"
+                      >
+                        <Box
+                          sx={{
+                            display: "flex",
+                            alignItems: "center",
+                            gap: 0.75,
+                            mt: 0.5,
+                          }}
+                        >
+                          <SettingsEthernetRounded
+                            sx={{ fontSize: 16, color: "text.secondary" }}
+                          />
+                          <InputBase
+                            key={networkSettings[drive.id].bind_interface ?? ""}
+                            defaultValue={networkSettings[drive.id].bind_interface ?? ""}
+                            placeholder={t("settings.bindInterfacePlaceholder")}
+                            onBlur={(e) => handleBindInterfaceCommit(drive.id, e.target.value)}
+                            onKeyDown={(e) => {
+                              if (e.key === "Enter") {
+                                (e.target as HTMLInputElement).blur();
+                              }
+                            }}
+                            sx={{ typography: "caption", color: "text.secondary", flex: 1 }}
+                          />
+                          {networkSettings[drive.id].bind_interface && (
+                            <Typography
+                              variant="caption"
+                              color={drive.bind_address ? "success.main" : "warning.main"}
+                            >
+                              {drive.bind_address
+                                ? t("settings.bindInterfaceActive", {
+                                    address: drive.bind_address,
+                                  })
+                                : t("settings.bindInterfaceUnavailable")}
+                            </Typography>
+                          )}
+                        </Box>
+                      </Tooltip>
+                    )}
+
+                    {/* Transfer Limits */}
+                    {transferLimits[drive.id] && (
+                      <Tooltip
+                        title={t("settings.transferLimitsDescription")}
+                        placement="bottom-start"
+                      >
+                        <Box
+                          sx={{
+                            display: "flex",
+                            alignItems: "center",
+                            gap: 1.5,
+                            mt: 0.5,
+                          }}
+                        >
+                          <SpeedRounded sx={{ fontSize: 16, color: "text.secondary" }} />
+                          <Select
+                            size="small"
+                            variant="standard"
+                            disableUnderline
+                            value={transferLimits[drive.id].max_uploads}
+                            disabled={transferLimits[drive.id].autoscale.enabled}
+                            onChange={(e) =>
+                              handleTransferLimitsChange(drive.id, {
+                                max_uploads: Number(e.target.value),
+                              })
+                            }
+                            sx={{ typography: "caption", color: "text.secondary" }}
+                          >
+                            {TRANSFER_LIMIT_OPTIONS.map((count) => (
+                              <MenuItem key={count} value={count}>
+                                {t("settings.maxUploads", { count })}
+                              </MenuItem>
+                            ))}
+                          </Select>
+                          <Select
+                            size="small"
+                            variant="standard"
+                            disableUnderline
+                            value={transferLimits[drive.id].max_downloads}
+                            disabled={transferLimits[drive.id].autoscale.enabled}
+                            onChange={(e) =>
+                              handleTransferLimitsChange(drive.id, {
+                                max_downloads: Number(e.target.value),
+                              })
+                            }
+                            sx={{ typography: "caption", color: "text.secondary" }}
+                          >
+                            {TRANSFER_LIMIT_OPTIONS.map((count) => (
+                              <MenuItem key={count} value={count}>
+                                {t("settings.maxDownloads", { count })}
+                              </MenuItem>
+                            ))}
+                          </Select>
+                          <Select
+                            size="small"
+                            variant="standard"
+                            disableUnderline
+                            value={transferLimits[drive.id].max_chunk_concurrency ?? 0}
+                            onChange={(e) => {
+                              const value = Number(e.target.value);
+                              handleTransferLimitsChange(drive.id, {
+                                max_chunk_concurrency: value > 0 ? value : null,
+                              });
+                            }}
+                            sx={{ typography: "caption", color: "text.secondary" }}
+                          >
+                            <MenuItem value={0}>{t("settings.chunkConcurrencyAuto")}</MenuItem>
+                            {CHUNK_CONCURRENCY_OPTIONS.map((count) => (
+                              <MenuItem key={count} value={count}>
+                                {t("settings.chunkConcurrency", { count })}
+                              </MenuItem>
+                            ))}
+                          </Select>
+                          <FormControlLabel
+                            control={
+                              <Switch
+                                size="small"
+                                checked={transferLimits[drive.id].autoscale.enabled}
+                                onChange={(e) =>
+                                  handleTransferLimitsChange(drive.id, {
+                                    autoscale: {
+                                      ...transferLimits[drive.id].autoscale,
+                                      enabled: e.target.checked,
+                                    },
+                                  })
+                                }
+                              />
+                            }
+                            label={
+                              <Typography variant="caption" color="text.secondary">
+                                {t("settings.autoscaleTransfers")}
+                              </Typography>
+                            }
+                            sx={{ ml: 0 }}
+                          />
+                        </Box>
+                      </Tooltip>
+                    )}
+
                   </Box>
                 </Box>
 
@@ -416,6 +972,61 @@ export default function DrivesSection() {
                     </SecondaryButton>
                   )}
 
+                  {drive.status === "sync_root_missing" && (
+                    <>
+                      {drive.relocation && (
+                        <SecondaryButton
+                          size="small"
+                          startIcon={<FolderOpenRounded />}
+                          onClick={() => handleRelocateSyncRoot(drive)}
+                        >
+                          {t("settings.relocateFolder", { path: drive.relocation })}
+                        </SecondaryButton>
+                      )}
+                      <SecondaryButton
+                        size="small"
+                        startIcon={<FolderOpenRounded />}
+                        onClick={() => handleRelinkSyncRoot(drive)}
+                      >
+                        {t("settings.relinkFolder")}
+                      </SecondaryButton>
+                      <SecondaryButton
+                        size="small"
+                        startIcon={<RefreshRounded />}
+                        onClick={() => handleRecreateSyncRoot(drive)}
+                      >
+                        {t("settings.recreateFolder")}
+                      </SecondaryButton>
+                    </>
+                  )}
+
+                  {drive.status === "suspicious_changes" && (
+                    <>
+                      <SecondaryButton
+                        size="small"
+                        startIcon={<RestoreRounded />}
+                        onClick={() => handleRestoreSuspiciousChanges(drive)}
+                      >
+                        {t("settings.restoreFiles")}
+                      </SecondaryButton>
+                      <SecondaryButton
+                        size="small"
+                        startIcon={<CheckRounded />}
+                        onClick={() => handleDismissSuspiciousChanges(drive)}
+                      >
+                        {t("settings.keepChanges")}
+                      </SecondaryButton>
+                    </>
+                  )}
+
+                  <SecondaryButton
+                    size="small"
+                    startIcon={drive.enabled ? <PauseRounded /> : <PlayArrowRounded />}
+                    onClick={() => handleToggleMounted(drive)}
+                  >
+                    {drive.enabled ? t("settings.unmountDrive") : t("settings.mountDrive")}
+                  </SecondaryButton>
+
                   <Box sx={{ flex: 1 }} />
 
                   <SecondaryErrorButton
@@ -12,6 +12,13 @@ import { useEffect, useState } from "react";
 import { useTranslation } from "react-i18next";
 import { invoke } from "@tauri-apps/api/core";
 import { languages } from "../../i18n";
+import type {
+  BatterySaver,
+  DownloadScan,
+  GeneralSettings,
+  IdleSchedule,
+  PresentationMode,
+} from "../../ipc";
 
 interface SettingItemProps {
   title: string;
@@ -202,16 +209,11 @@ function SettingsGroup({ title, children }: SettingsGroupProps) {
   );
 }
 
-interface GeneralSettings {
-  notify_credential_expired: boolean;
-  notify_file_conflict: boolean;
-  fast_popup_launch: boolean;
-  log_to_file: boolean;
-  log_level: string;
-  log_max_files: number;
-  log_dir: string;
-  language: string | null;
-}
+// Battery charge (%) at or below which sync is throttled, 100 means always on battery
+const BATTERY_THRESHOLD_OPTIONS = [20, 30, 50, 100];
+
+// Minutes without input after which full syncs and maintenance may run
+const IDLE_THRESHOLD_OPTIONS = [2, 5, 15, 30];
 
 const LOG_LEVELS = [
   { value: "trace", label: "Trace" },
@@ -237,8 +239,23 @@ export default function GeneralSection() {
   const [logToFile, setLogToFile] = useState(true);
   const [logLevel, setLogLevel] = useState("info");
   const [logMaxFiles, setLogMaxFiles] = useState(5);
+  const [httpTrace, setHttpTrace] = useState(false);
   const [logDir, setLogDir] = useState("");
   const [language, setLanguage] = useState<string | null>(null);
+  const [downloadScan, setDownloadScan] = useState<DownloadScan>({ mode: "off" });
+  const [batterySaver, setBatterySaver] = useState<BatterySaver>({
+    enabled: true,
+    threshold_percent: 30,
+  });
+  const [idleSchedule, setIdleSchedule] = useState<IdleSchedule>({
+    enabled: false,
+    idle_threshold_secs: 300,
+  });
+  const [presentationMode, setPresentationMode] = useState<PresentationMode>({
+    enabled: false,
+    suppress_notifications: true,
+    pause_transfers: true,
+  });
   const [loading, setLoading] = useState(true);
 
   useEffect(() => {
@@ -255,8 +272,13 @@ export default function GeneralSection() {
         setLogToFile(settings.log_to_file);
         setLogLevel(settings.log_level);
         setLogMaxFiles(settings.log_max_files);
+        setHttpTrace(settings.http_trace);
         setLogDir(settings.log_dir);
         setLanguage(settings.language);
+        setDownloadScan(settings.download_scan);
+        setBatterySaver(settings.battery_saver);
+        setIdleSchedule(settings.idle_schedule);
+        setPresentationMode(settings.presentation_mode);
       } catch (error) {
         console.error("Failed to load settings:", error);
       } finally {
@@ -347,6 +369,83 @@ export default function GeneralSection() {
     }
   };
 
+  const handleHttpTraceChange = async (checked: boolean) => {
+    const previousValue = httpTrace;
+    setHttpTrace(checked);
+    try {
+      await invoke("set_http_trace", { enabled: checked });
+    } catch (error) {
+      console.error("Failed to change HTTP trace setting:", error);
+      setHttpTrace(previousValue);
+    }
+  };
+
+  const handleDownloadScanChange = async (value: string) => {
+    // A custom command can only be configured in config.json, keep it as is
+    if (value === downloadScan.mode) {
+      return;
+    }
+    const scan = { mode: value } as DownloadScan;
+    const previousValue = downloadScan;
+    setDownloadScan(scan);
+    try {
+      await invoke("set_download_scan", { scan });
+    } catch (error) {
+      console.error("Failed to change download scan setting:", error);
+      setDownloadScan(previousValue);
+    }
+  };
+
+  const handleBatterySaverChange = async (value: string) => {
+    const updated =
+      value === "off"
+        ? { ...batterySaver, enabled: false }
+        : { enabled: true, threshold_percent: parseInt(value, 10) };
+    const previousValue = batterySaver;
+    setBatterySaver(updated);
+    try {
+      await invoke("set_battery_saver", { batterySaver: updated });
+    } catch (error) {
+      console.error("Failed to change battery saver setting:", error);
+      setBatterySaver(previousValue);
+    }
+  };
+
+  const handleIdleScheduleChange = async (value: string) => {
+    const updated =
+      value === "off"
+        ? { ...idleSchedule, enabled: false }
+        : { enabled: true, idle_threshold_secs: parseInt(value, 10) * 60 };
+    const previousValue = idleSchedule;
+    setIdleSchedule(updated);
+    try {
+      await invoke("set_idle_schedule", { idleSchedule: updated });
+    } catch (error) {
+      console.error("Failed to change idle schedule setting:", error);
+      setIdleSchedule(previousValue);
+    }
+  };
+
+  const handlePresentationModeChange = async (checked: boolean) => {
+    const updated = { ...presentationMode, enabled: checked };
+    const previousValue = presentationMode;
+    setPresentationMode(updated);
+    try {
+      await invoke("set_presentation_mode", { presentationMode: updated });
+    } catch (error) {
+      console.error("Failed to change presentation mode setting:", error);
+      setPresentationMode(previousValue);
+    }
+  };
+
+  const handleRunDeferredWork = async () => {
+    try {
+      await invoke("run_deferred_work");
+    } catch (error) {
+      console.error("Failed to run deferred work:", error);
+    }
+  };
+
   const handleOpenLogFolder = async () => {
     try {
       await invoke("open_log_folder");
@@ -433,6 +532,99 @@ export default function GeneralSection() {
         />
       </SettingsGroup>
 
+      <SettingsGroup title={t("settings.securitySettings")}>
+        <SettingSelectItem
+          title={t("settings.downloadScan")}
+          description={t("settings.downloadScanDescription")}
+          value={downloadScan.mode}
+          options={[
+            { value: "off", label: t("settings.downloadScanOff") },
+            { value: "defender", label: t("settings.downloadScanDefender") },
+            ...(downloadScan.mode === "command"
+              ? [
+                  {
+                    value: "command",
+                    label: t("settings.downloadScanCommand", {
+                      program: downloadScan.program,
+                    }),
+                  },
+                ]
+              : []),
+          ]}
+          onChange={handleDownloadScanChange}
+          disabled={loading}
+          isLast={true}
+        />
+      </SettingsGroup>
+
+      <SettingsGroup title={t("settings.powerSettings")}>
+        <SettingSelectItem
+          title={t("settings.batterySaver")}
+          description={t("settings.batterySaverDescription")}
+          value={batterySaver.enabled ? String(batterySaver.threshold_percent) : "off"}
+          options={[
+            { value: "off", label: t("settings.batterySaverOff") },
+            ...(batterySaver.enabled &&
+            !BATTERY_THRESHOLD_OPTIONS.includes(batterySaver.threshold_percent)
+              ? [batterySaver.threshold_percent]
+              : []
+            )
+              .concat(BATTERY_THRESHOLD_OPTIONS)
+              .map((percent) => ({
+                value: String(percent),
+                label:
+                  percent >= 100
+                    ? t("settings.batterySaverAlways")
+                    : t("settings.batterySaverBelow", { percent }),
+              })),
+          ]}
+          onChange={handleBatterySaverChange}
+          disabled={loading}
+          isLast={false}
+        />
+        <SettingSelectItem
+          title={t("settings.idleSchedule")}
+          description={t("settings.idleScheduleDescription")}
+          value={
+            idleSchedule.enabled
+              ? String(Math.round(idleSchedule.idle_threshold_secs / 60))
+              : "off"
+          }
+          options={[
+            { value: "off", label: t("settings.idleScheduleOff") },
+            ...(idleSchedule.enabled &&
+            !IDLE_THRESHOLD_OPTIONS.includes(Math.round(idleSchedule.idle_threshold_secs / 60))
+              ? [Math.round(idleSchedule.idle_threshold_secs / 60)]
+              : []
+            )
+              .concat(IDLE_THRESHOLD_OPTIONS)
+              .map((minutes) => ({
+                value: String(minutes),
+                label: t("settings.idleScheduleAfter", { minutes }),
+              })),
+          ]}
+          onChange={handleIdleScheduleChange}
+          disabled={loading}
+          isLast={false}
+        />
+        <SettingItem
+          title={t("settings.presentationMode")}
+          description={t("settings.presentationModeDescription")}
+          checked={presentationMode.enabled}
+          onChange={handlePresentationModeChange}
+          disabled={loading}
+          isLast={false}
+        />
+        <SettingActionItem
+          title={t("settings.runDeferredWork")}
+          description={t("settings.runDeferredWorkDescription")}
+          actionLabel={t("settings.runNow")}
+          onAction={handleRunDeferredWork}
+          disabled={loading}
+          isLast={true}
+        />
+      </SettingsGroup>
+
       <SettingsGroup title={t("settings.logSettings")}>
         <SettingActionItem
           title={t("settings.logFolder")}
@@ -466,6 +658,14 @@ export default function GeneralSection() {
           options={MAX_FILES_OPTIONS}
           onChange={handleLogMaxFilesChange}
           disabled={loading}
+          isLast={false}
+        />
+        <SettingItem
+          title={t("settings.httpTrace")}
+          description={t("settings.httpTraceDescription")}
+          checked={httpTrace}
+          onChange={handleHttpTraceChange}
+          disabled={loading}
           isLast={true}
         />
       </SettingsGroup>
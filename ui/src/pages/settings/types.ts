@@ -1,18 +1,94 @@
+import type { TlsPin } from "../../ipc";
+
 export interface DriveInfo {
   id: string;
   name: string;
   instance_url: string;
   sync_path: string;
+  /** The missing sync folder found on another disk letter */
+  relocation?: string | null;
   icon_path?: string;
   raw_icon_path?: string;
   enabled: boolean;
   user_id: string;
   remote_path: string
+  label?: DriveLabel;
+  /** Public share mounted without an account, read-only */
+  guest_share: boolean;
   status: DriveStatus;
+  state: MountState;
   capacity?: CapacitySummary;
+  server?: ServerInfo;
+  address_family?: AddressFamily;
+  clock_skew_secs?: number;
+  lan_active: boolean;
+  /** Source address connections are bound to */
+  bind_address?: string | null;
+}
+
+export type AddressFamily = "ipv4" | "ipv6";
+
+export type MountState =
+  | "initializing"
+  | "syncing_initial"
+  | "idle"
+  | "syncing"
+  | "paused"
+  | "degraded"
+  | "shutting_down";
+
+export type IpStrategy = "auto" | "prefer_ipv4" | "prefer_ipv6";
+
+export interface NetworkSettings {
+  ip_strategy: IpStrategy;
+  dns_overrides: Record<string, string[]>;
+  lan_url?: string | null;
+  bind_interface?: string | null;
+  poll_interval_secs?: number | null;
+  /** Applied when the drive restarts */
+  tls_pins?: TlsPin[];
+}
+
+export interface CompressionSettings {
+  enabled: boolean;
+  extensions: string[];
+  min_size: number;
+}
+
+export interface TransferLimits {
+  max_uploads: number;
+  max_downloads: number;
+  /** Cap on chunks uploaded in parallel, null follows the storage policy */
+  max_chunk_concurrency: number | null;
+  /** Adjust upload and download limits to the system load within the bounds */
+  autoscale: {
+    enabled: boolean;
+    floor: number;
+    ceiling: number;
+  };
+}
+
+export interface ServerInfo {
+  version: string;
+  min_tested_version: string;
+  outdated: boolean;
+  unavailable_features: string[];
+}
+
+export interface DriveLabel {
+  color?: string;
+  emoji?: string;
 }
 
-export type DriveStatus = "active" | "event_push_lost" | "credential_expired";
+export type DriveStatus =
+  | "active"
+  | "event_push_lost"
+  | "credential_expired"
+  | "share_unavailable"
+  | "server_maintenance"
+  | "unmounted"
+  | "sync_root_missing"
+  | "suspicious_changes";
 
 export interface CapacitySummary {
   total: number;
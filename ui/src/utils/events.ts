@@ -0,0 +1,71 @@
+import { invoke } from "@tauri-apps/api/core";
+import { getCurrentWebviewWindow } from "@tauri-apps/api/webviewWindow";
+
+// Handled events are acknowledged together after this delay
+const ACK_DELAY_MS = 100;
+
+let handledEvents = 0;
+let ackTimer: ReturnType<typeof setTimeout> | null = null;
+
+function acknowledgeEvent() {
+  handledEvents += 1;
+  if (ackTimer) return;
+  ackTimer = setTimeout(() => {
+    const count = handledEvents;
+    handledEvents = 0;
+    ackTimer = null;
+    invoke("ack_events", { count }).catch((error) =>
+      console.error("Failed to acknowledge events:", error)
+    );
+  }, ACK_DELAY_MS);
+}
+
+export interface EventSubscription<T> {
+  /** Critical events among those subscribed to, not acknowledged yet */
+  pending: T[];
+  unsubscribe: () => void;
+}
+
+/**
+ * Subscribe this window to the backend events `names`.
+ * Once subscribed, a window is only sent the events it subscribed to, and
+ * must acknowledge them to be sent more, which is done after `handler` ran.
+ */
+export async function subscribeEvents<T>(
+  names: string[],
+  handler: (payload: T) => void
+): Promise<EventSubscription<T>> {
+  const currentWindow = getCurrentWebviewWindow();
+
+  // Listen before subscribing so nothing sent in between is missed
+  const unlisteners = await Promise.all(
+    names.map((name) =>
+      currentWindow.listen<T>(name, ({ payload }) => {
+        try {
+          handler(payload);
+        } finally {
+          acknowledgeEvent();
+        }
+      })
+    )
+  );
+  const unlisten = () => unlisteners.forEach((fn) => fn());
+
+  let pending: T[];
+  try {
+    pending = await invoke<T[]>("subscribe_events", { events: names });
+  } catch (error) {
+    unlisten();
+    throw error;
+  }
+
+  return {
+    pending,
+    unsubscribe: () => {
+      unlisten();
+      invoke("unsubscribe_events", { events: names }).catch((error) =>
+        console.error("Failed to unsubscribe from events:", error)
+      );
+    },
+  };
+}
@@ -13,7 +13,11 @@ export type ValidationErrorType =
   | "httpError"
   | "apiError"
   | "versionTooLow"
-  | "connectionFailed";
+  | "connectionFailed"
+  // Returned by the add_drive command
+  | "driveAlreadyAdded"
+  | "syncPathInUse"
+  | "addDriveFailed";
 
 export interface ValidationError {
   type: ValidationErrorType;